@@ -16,7 +16,7 @@ set iskeyword=a-z,A-Z,-,*,_,!,@
 syntax keyword penguinTodos TODO FIXME NOTE
 
 " Language keywords
-syntax keyword penguinKeywords let func import
+syntax keyword penguinKeywords let const mut func import pub if else match while for in loop break continue struct enum impl trait as true false
 
 " Comments
 syntax region penguinCommentLine start="//" end="$"          contains=penguinTodos
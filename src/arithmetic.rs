@@ -0,0 +1,115 @@
+//! Integer arithmetic, as every phase does it.
+//!
+//! Constant folding and the bytecode VM compute on `i128`s, which every
+//! integer type fits in, and then decide what a result that doesn't fit its
+//! type is. Native code can't, so `checks` tells a backend what it has to
+//! check around an instruction to get the same result.
+//!
+//! With overflow checks, which are on by default, a result that doesn't fit
+//! is an error. Without them (`--overflow-checks off`) it wraps around, e.g.
+//! `255 + 1` is `0` as a `u8`, and the smallest `i8` divided by `-1` is
+//! itself. Dividing by zero is an error either way, and nothing is ever left
+//! over when dividing by `-1`.
+use crate::parser::ast::BinaryOp;
+use crate::typeck::IntTy;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// What happens to the result of arithmetic that doesn't fit its type.
+pub enum Overflow {
+    /// It is an error, which stops the program
+    #[default]
+    Checked,
+    /// It wraps around, keeping its low bits
+    Wrapping,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithmeticError {
+    DivisionByZero,
+    /// The result doesn't fit its type, with overflow checks
+    Overflow,
+}
+
+/// The result of `+`, `-`, `*`, `/` or `%` on integers of a type.
+pub fn binary(
+    op: BinaryOp,
+    ty: IntTy,
+    left: i128,
+    right: i128,
+    overflow: Overflow,
+) -> Result<i128, ArithmeticError> {
+    match exact(op, left, right) {
+        Ok(result) => fit(result, ty, overflow),
+        // Only the product of two 64-bit values can be too large for an
+        // `i128`, and its low bits are right when it wraps around
+        Err(ArithmeticError::Overflow) if overflow == Overflow::Wrapping => {
+            Ok(ty.truncate(left.wrapping_mul(right)))
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// The result of negating an integer of a type.
+pub fn negate(ty: IntTy, value: i128, overflow: Overflow) -> Result<i128, ArithmeticError> {
+    fit(-value, ty, overflow)
+}
+
+/// The result of arithmetic on integers whose type isn't known, which only
+/// has to fit in an `i128`.
+pub fn exact(op: BinaryOp, left: i128, right: i128) -> Result<i128, ArithmeticError> {
+    let result = match op {
+        BinaryOp::Divide | BinaryOp::Modulo if right == 0 => {
+            return Err(ArithmeticError::DivisionByZero)
+        }
+        BinaryOp::Add => left.checked_add(right),
+        BinaryOp::Subtract => left.checked_sub(right),
+        BinaryOp::Multiply => left.checked_mul(right),
+        BinaryOp::Divide => left.checked_div(right),
+        _ => left.checked_rem(right),
+    };
+
+    result.ok_or(ArithmeticError::Overflow)
+}
+
+/// A result in the range of its type.
+fn fit(result: i128, ty: IntTy, overflow: Overflow) -> Result<i128, ArithmeticError> {
+    match overflow {
+        _ if (ty.min()..=ty.max()).contains(&result) => Ok(result),
+        Overflow::Checked => Err(ArithmeticError::Overflow),
+        Overflow::Wrapping => Ok(ty.truncate(result)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What native code checks around an instruction doing arithmetic on
+/// integers of a type, whose result wraps around in the type's bits.
+pub struct Checks {
+    /// Whether the divisor is zero, which is an error
+    pub zero: bool,
+    /// Whether the result wrapped around, which is an error. The only
+    /// division that does is of the smallest value by -1.
+    pub overflow: bool,
+    /// Whether the divisor is -1, which processors fault on when dividing
+    /// the smallest value by it. The quotient is the negated dividend then,
+    /// wrapping around, and nothing is left over.
+    pub minus_one: bool,
+}
+
+/// What native code checks around `+`, `-`, `*`, `/` or `%` on integers of a
+/// type, to get the result constant folding and the VM get.
+pub fn checks(op: BinaryOp, ty: IntTy, overflow: Overflow) -> Checks {
+    let division = matches!(op, BinaryOp::Divide | BinaryOp::Modulo);
+    let checked = overflow == Overflow::Checked;
+
+    let overflow = match op {
+        BinaryOp::Divide => checked && ty.is_signed(),
+        BinaryOp::Modulo => false,
+        _ => checked,
+    };
+
+    Checks {
+        zero: division,
+        overflow,
+        minus_one: division && ty.is_signed() && !overflow,
+    }
+}
@@ -0,0 +1,268 @@
+//! A backend compiling the HIR of a program straight to assembly, which the
+//! system's assembler turns into native object files.
+//!
+//! There is no code generator in between, so it needs nothing but an
+//! assembler, and every instruction of a program can be traced back to the
+//! code it was compiled from. It only compiles the core of the language:
+//! integers, booleans, functions and control flow. Values are never more
+//! than a register, and nothing is optimized.
+//!
+//! - `codegen` walks a lowered program, compiling it with the instructions
+//!   of an `Isa`.
+//! - `x86_64` and `aarch64` are the instruction sets that can be compiled
+//!   to, each with its calling convention.
+use crate::backend::{Arch, Backend, CodegenError, Os, Target};
+use crate::hir;
+use crate::parser::ast::BinaryOp;
+use crate::source_map::SourceMap;
+use crate::typeck::IntTy;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+pub mod aarch64;
+pub mod codegen;
+pub mod x86_64;
+
+pub use codegen::compile;
+
+/// The assembler of the machine the compiler runs on, which has to be on
+/// the path.
+pub const ASSEMBLER: &str = "as";
+
+/// The assembly backend, which compiles programs to assembly and the
+/// assembly to object files with an assembler.
+pub struct Asm;
+
+impl Backend for Asm {
+    fn name(&self) -> &'static str {
+        "the assembler"
+    }
+
+    fn write_object(
+        &self,
+        program: &hir::Program,
+        sources: Option<&SourceMap>,
+        target: Target,
+        path: &Path,
+    ) -> Result<(), Vec<CodegenError>> {
+        let assembly = compile(program, sources, target)?;
+
+        assemble(&assembly, target, path).map_err(|error| vec![error])
+    }
+}
+
+/// The instructions of a machine and its calling convention, which
+/// `codegen` compiles the HIR of a program with.
+///
+/// Every value is a 64-bit integer, and instructions work on the one in the
+/// result register, which is also where functions return theirs. Binary
+/// operations take their right side from the operand register. Locals and
+/// temporaries are kept in the slots of a function's frame, by position.
+pub trait Isa {
+    /// Start a function with a frame of some slots, moving each argument to
+    /// the slot of its parameter.
+    fn prologue(&self, code: &mut Code, slots: usize, params: &[usize]);
+
+    /// Return from a function with the result.
+    fn epilogue(&self, code: &mut Code);
+
+    /// Put a constant in the result.
+    fn constant(&self, code: &mut Code, value: i64);
+
+    /// Put the value in a slot in the result.
+    fn load(&self, code: &mut Code, slot: usize);
+
+    /// Put the result in a slot.
+    fn store(&self, code: &mut Code, slot: usize);
+
+    /// Put the value of a global, which is at a symbol, in the result.
+    fn load_global(&self, code: &mut Code, symbol: &str);
+
+    fn store_global(&self, code: &mut Code, symbol: &str);
+
+    /// Put the address of a function in the result.
+    fn address(&self, code: &mut Code, symbol: &str);
+
+    /// Copy the result to the operand.
+    fn operand(&self, code: &mut Code);
+
+    /// Integer arithmetic on the result and the operand, jumping to
+    /// `overflow` if the result doesn't fit its type and to `zero` if it
+    /// divides by zero. Only the checks that are made are given. Without
+    /// `overflow`, the result wraps around in 64 bits, and dividing a signed
+    /// value by -1 negates it.
+    fn arithmetic(
+        &self,
+        code: &mut Code,
+        op: BinaryOp,
+        ty: IntTy,
+        overflow: Option<&str>,
+        zero: Option<&str>,
+    );
+
+    /// Compare the result with the operand, making the result 1 if they are
+    /// ordered like `op` says and 0 otherwise.
+    fn compare(&self, code: &mut Code, op: BinaryOp, signed: bool);
+
+    /// Turn a boolean result from 0 to 1 or back.
+    fn not(&self, code: &mut Code);
+
+    /// Keep the bits of the result that a value of a type has, sign or zero
+    /// extended.
+    fn extend(&self, code: &mut Code, ty: IntTy);
+
+    /// Jump to a label if the result is zero, or if it isn't.
+    fn branch(&self, code: &mut Code, zero: bool, label: &str);
+
+    fn jump(&self, code: &mut Code, label: &str);
+
+    /// Stop the program, for code that is never run.
+    fn trap(&self, code: &mut Code);
+
+    /// Call a function with the values in some slots, leaving what it
+    /// returns in the result.
+    fn call(&self, code: &mut Code, callee: Callee, args: &[usize]);
+
+    /// Call a function from the C library. A function taking any number of
+    /// arguments, like `printf`, takes the ones after its first `fixed`
+    /// differently on some targets.
+    fn call_c(&self, code: &mut Code, name: &str, args: &[Argument], fixed: usize);
+}
+
+/// The ISA of a target.
+pub fn isa(target: Target) -> Box<dyn Isa> {
+    match target.arch {
+        Arch::X86_64 => Box::new(x86_64::X86_64 { os: target.os }),
+        Arch::Aarch64 => Box::new(aarch64::Aarch64 { os: target.os }),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// The function a call calls.
+pub enum Callee<'a> {
+    Symbol(&'a str),
+    /// The function whose address is in a slot
+    Slot(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+/// An argument of a C function.
+pub enum Argument<'a> {
+    /// The address of a label or symbol
+    Address(&'a str),
+    Int(i64),
+    Slot(usize),
+}
+
+/// The instructions of a function being written.
+pub struct Code {
+    text: String,
+    os: Os,
+    /// How many labels have been made, in every function so far
+    labels: usize,
+}
+
+impl Code {
+    fn new(os: Os, labels: usize) -> Self {
+        Self {
+            text: String::new(),
+            os,
+            labels,
+        }
+    }
+
+    pub fn emit(&mut self, instruction: impl AsRef<str>) {
+        let _ = writeln!(self.text, "    {}", instruction.as_ref());
+    }
+
+    /// Place a label at the next instruction.
+    pub fn place(&mut self, label: &str) {
+        let _ = writeln!(self.text, "{}:", label);
+    }
+
+    /// A label nothing else uses. Labels starting with `L` on macOS and
+    /// `.L` elsewhere aren't kept in the object file.
+    pub fn label(&mut self) -> String {
+        self.labels += 1;
+
+        match self.os {
+            Os::MacOs => format!("L{}", self.labels - 1),
+            Os::Linux => format!(".L{}", self.labels - 1),
+        }
+    }
+}
+
+/// A symbol as it is written in assembly. Names are quoted, since the names
+/// of methods and generic functions have characters symbols can't, and
+/// start with an underscore on macOS, like every C symbol there.
+pub fn symbol(os: Os, name: &str) -> String {
+    format!("\"{}\"", c_symbol(os, name))
+}
+
+/// The symbol of a function from the C library.
+pub fn c_symbol(os: Os, name: &str) -> String {
+    match os {
+        Os::MacOs => format!("_{}", name),
+        Os::Linux => name.to_string(),
+    }
+}
+
+/// The assembler for a target. The system's assembler is used for the
+/// machine the compiler runs on, and for either architecture on macOS, and
+/// GNU's assembler for the target's architecture otherwise, e.g.
+/// `aarch64-linux-gnu-as`.
+pub fn assembler(target: Target) -> Command {
+    let arch = match target.arch {
+        Arch::X86_64 => "x86_64",
+        Arch::Aarch64 => "aarch64",
+    };
+
+    if target.os == Os::MacOs && Target::host().os == Os::MacOs {
+        let mut command = Command::new(ASSEMBLER);
+
+        command.args(["-arch", if arch == "x86_64" { arch } else { "arm64" }]);
+
+        return command;
+    }
+
+    match target == Target::host() {
+        true => Command::new(ASSEMBLER),
+        false => Command::new(format!("{}-linux-gnu-as", arch)),
+    }
+}
+
+/// Assemble the assembly of an object file for a target.
+pub fn assemble(assembly: &str, target: Target, path: &Path) -> Result<(), CodegenError> {
+    let fail = |message: String| CodegenError::object(message);
+    let mut command = assembler(target);
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    // With no files to assemble, the assembler reads the assembly from stdin
+    let mut child = command
+        .arg("-o")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| fail(format!("couldn't run '{}': {}", program, error)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(assembly.as_bytes())
+            .map_err(|error| fail(error.to_string()))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| fail(error.to_string()))?;
+
+    if !output.status.success() {
+        return Err(fail(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,379 @@
+//! AArch64 assembly, for Linux and macOS.
+//!
+//! - `abi` says where arguments and results are passed, which an emitter of
+//!   object files needs as much as this one does.
+//!
+//! The result is `x0` and the operand is `x1`, and `x9` and `x10` hold what
+//! an instruction needs in between. Every slot is in the stack frame below
+//! `x29`, so the stack pointer only moves to pass arguments. Integers are
+//! kept sign or zero extended to 64 bits, like on x86-64.
+use crate::asm::aarch64::abi::Location;
+use crate::asm::{self, Argument, Callee, Code, Isa};
+use crate::backend::Os;
+use crate::parser::ast::BinaryOp;
+use crate::typeck::IntTy;
+
+pub mod abi;
+
+/// The AArch64 instruction set, with the calling convention of an
+/// operating system.
+pub struct Aarch64 {
+    pub os: Os,
+}
+
+impl Aarch64 {
+    /// Load or store a register in a slot. Slots further than a load's
+    /// offset can reach are addressed through a scratch register.
+    fn access(code: &mut Code, store: bool, register: &str, slot: usize) {
+        let offset = abi::SLOT * (slot as i64 + 1);
+        let scratch = abi::SCRATCH[0].name();
+
+        if offset <= 256 {
+            let instruction = if store { "stur" } else { "ldur" };
+
+            return code.emit(format!("{} {}, [x29, #-{}]", instruction, register, offset));
+        }
+
+        Self::immediate(code, offset, &scratch);
+        code.emit(format!("sub {}, x29, {}", scratch, scratch));
+        code.emit(format!(
+            "{} {}, [{}]",
+            if store { "str" } else { "ldr" },
+            register,
+            scratch
+        ));
+    }
+
+    /// Put an integer in a register, as its 64 bits, 16 bits at a time if
+    /// it doesn't fit in one move.
+    fn immediate(code: &mut Code, value: i64, register: &str) {
+        if (-65536..65536).contains(&value) {
+            return code.emit(format!("mov {}, #{}", register, value));
+        }
+
+        code.emit(format!("movz {}, #{}", register, value & 0xffff));
+
+        for shift in [16, 32, 48] {
+            let part = (value >> shift) & 0xffff;
+
+            if part != 0 {
+                code.emit(format!("movk {}, #{}, lsl #{}", register, part, shift));
+            }
+        }
+    }
+
+    /// Move the stack pointer down by some bytes, or back up.
+    fn reserve(code: &mut Code, bytes: i64) {
+        let instruction = if bytes > 0 { "sub" } else { "add" };
+
+        match bytes.abs() {
+            0 => {}
+            bytes if bytes < 4096 => code.emit(format!("{} sp, sp, #{}", instruction, bytes)),
+            bytes => {
+                let scratch = abi::SCRATCH[0].name();
+
+                Self::immediate(code, bytes, &scratch);
+                code.emit(format!("{} sp, sp, {}", instruction, scratch));
+            }
+        }
+    }
+
+    /// Put the address of a symbol or label in a register, from the page it
+    /// is in and its offset in the page.
+    fn address_into(&self, code: &mut Code, symbol: &str, register: &str) {
+        code.emit(format!("adrp {}, {}", register, self.page(symbol)));
+        code.emit(format!(
+            "add {}, {}, {}",
+            register,
+            register,
+            self.page_offset(symbol)
+        ));
+    }
+
+    fn page(&self, symbol: &str) -> String {
+        match self.os {
+            Os::MacOs => format!("{}@PAGE", symbol),
+            Os::Linux => symbol.to_string(),
+        }
+    }
+
+    fn page_offset(&self, symbol: &str) -> String {
+        match self.os {
+            Os::MacOs => format!("{}@PAGEOFF", symbol),
+            Os::Linux => format!(":lo12:{}", symbol),
+        }
+    }
+
+    /// Sign or zero extend the low bits of `x0` that a value of a type has
+    /// into a register.
+    fn extend_into(code: &mut Code, ty: IntTy, register: &str) {
+        let low = register.replacen('x', "w", 1);
+
+        match ty {
+            IntTy::I8 => code.emit(format!("sxtb {}, w0", register)),
+            IntTy::U8 => code.emit(format!("and {}, x0, #0xff", register)),
+            IntTy::I16 => code.emit(format!("sxth {}, w0", register)),
+            IntTy::U16 => code.emit(format!("and {}, x0, #0xffff", register)),
+            IntTy::I32 => code.emit(format!("sxtw {}, w0", register)),
+            // Writing the low half of a register clears the high half
+            IntTy::U32 => code.emit(format!("mov {}, w0", low)),
+            IntTy::I64 | IntTy::U64 if register == "x0" => {}
+            IntTy::I64 | IntTy::U64 => code.emit(format!("mov {}, x0", register)),
+        }
+    }
+
+    /// Move the arguments of a call where the calling convention passes
+    /// them, making room on the stack for the ones passed there. Returns the
+    /// bytes of stack taken.
+    fn arguments(&self, code: &mut Code, args: &[Argument], fixed: usize) -> i64 {
+        let stack = abi::stack_arguments(args.len(), fixed, self.os);
+        let locations = abi::arguments(args.len(), fixed, self.os);
+
+        Self::reserve(code, stack);
+
+        for (location, arg) in locations.into_iter().zip(args) {
+            let register = match location {
+                Location::Register(register) => register.name(),
+                Location::Stack(_) => "x9".to_string(),
+            };
+
+            match *arg {
+                Argument::Address(label) => self.address_into(code, label, &register),
+                Argument::Int(value) => Self::immediate(code, value, &register),
+                Argument::Slot(slot) => Self::access(code, false, &register, slot),
+            }
+
+            if let Location::Stack(offset) = location {
+                code.emit(format!("str x9, [sp, #{}]", offset));
+            }
+        }
+
+        stack
+    }
+}
+
+impl Isa for Aarch64 {
+    fn prologue(&self, code: &mut Code, slots: usize, params: &[usize]) {
+        code.emit("stp x29, x30, [sp, #-16]!");
+        code.emit("mov x29, sp");
+        Self::reserve(code, abi::align(slots as i64 * abi::SLOT));
+
+        let locations = abi::arguments(params.len(), params.len(), self.os);
+
+        for (&param, location) in params.iter().zip(locations) {
+            match location {
+                Location::Register(register) => {
+                    Self::access(code, true, &register.name(), param);
+                }
+                Location::Stack(offset) => {
+                    let offset = abi::parameter_offset(offset);
+
+                    code.emit(format!("ldr x9, [x29, #{}]", offset));
+                    Self::access(code, true, "x9", param);
+                }
+            }
+        }
+    }
+
+    fn epilogue(&self, code: &mut Code) {
+        code.emit("mov sp, x29");
+        code.emit("ldp x29, x30, [sp], #16");
+        code.emit("ret");
+    }
+
+    fn constant(&self, code: &mut Code, value: i64) {
+        Self::immediate(code, value, "x0");
+    }
+
+    fn load(&self, code: &mut Code, slot: usize) {
+        Self::access(code, false, "x0", slot);
+    }
+
+    fn store(&self, code: &mut Code, slot: usize) {
+        Self::access(code, true, "x0", slot);
+    }
+
+    fn load_global(&self, code: &mut Code, symbol: &str) {
+        let scratch = abi::SCRATCH[0].name();
+
+        code.emit(format!("adrp {}, {}", scratch, self.page(symbol)));
+        code.emit(format!(
+            "ldr x0, [{}, {}]",
+            scratch,
+            self.page_offset(symbol)
+        ));
+    }
+
+    fn store_global(&self, code: &mut Code, symbol: &str) {
+        let scratch = abi::SCRATCH[0].name();
+
+        code.emit(format!("adrp {}, {}", scratch, self.page(symbol)));
+        code.emit(format!(
+            "str x0, [{}, {}]",
+            scratch,
+            self.page_offset(symbol)
+        ));
+    }
+
+    fn address(&self, code: &mut Code, symbol: &str) {
+        self.address_into(code, symbol, "x0");
+    }
+
+    fn operand(&self, code: &mut Code) {
+        code.emit("mov x1, x0");
+    }
+
+    fn arithmetic(
+        &self,
+        code: &mut Code,
+        op: BinaryOp,
+        ty: IntTy,
+        overflow: Option<&str>,
+        zero: Option<&str>,
+    ) {
+        let signed = ty.is_signed();
+
+        if matches!(op, BinaryOp::Divide | BinaryOp::Modulo) {
+            if let Some(zero) = zero {
+                code.emit(format!("cbz x1, {}", zero));
+            }
+
+            // The only division that overflows is of the smallest value by
+            // -1, which is only compared with when dividing by -1. It is the
+            // smallest value again otherwise, and nothing is left over.
+            if let Some(overflow) = overflow {
+                Self::immediate(code, ty.min() as i64, "x9");
+                code.emit("cmn x1, #1");
+                code.emit("ccmp x0, x9, #0, eq");
+                code.emit(format!("b.eq {}", overflow));
+            }
+
+            code.emit(if signed {
+                "sdiv x9, x0, x1"
+            } else {
+                "udiv x9, x0, x1"
+            });
+
+            // There is no remainder instruction, so it is what is left once
+            // the quotient times the divisor is taken away
+            match op {
+                BinaryOp::Modulo => code.emit("msub x0, x9, x1, x0"),
+                _ => code.emit("mov x0, x9"),
+            }
+
+            return;
+        }
+
+        let wide = ty.bits() == 64;
+
+        match (op, wide) {
+            // The flags of 64-bit additions and subtractions say if they
+            // overflowed
+            (BinaryOp::Add, true) => code.emit("adds x0, x0, x1"),
+            (BinaryOp::Subtract, true) => code.emit("subs x0, x0, x1"),
+            (BinaryOp::Add, false) => code.emit("add x0, x0, x1"),
+            (BinaryOp::Subtract, false) => code.emit("sub x0, x0, x1"),
+            // The high half of a 64-bit product is computed on its own, and
+            // has to be all sign bits or zero for the product to fit
+            (_, true) if signed => {
+                code.emit("mul x9, x0, x1");
+                code.emit("smulh x10, x0, x1");
+                code.emit("mov x0, x9");
+                code.emit("cmp x10, x9, asr #63");
+            }
+            (_, true) => {
+                code.emit("umulh x10, x0, x1");
+                code.emit("mul x0, x0, x1");
+                code.emit("cmp x10, #0");
+            }
+            (_, false) => code.emit("mul x0, x0, x1"),
+        }
+
+        let Some(overflow) = overflow else {
+            return;
+        };
+
+        let condition = match (op, wide, signed) {
+            (BinaryOp::Add | BinaryOp::Subtract, true, true) => "vs",
+            // The carry is set when an unsigned addition overflows, and
+            // clear when a subtraction borrows
+            (BinaryOp::Add, true, false) => "hs",
+            (BinaryOp::Subtract, true, false) => "lo",
+            (_, true, _) => "ne",
+            // The operands of narrower types can't overflow 64 bits, so the
+            // result fits if extending its low bits doesn't change it
+            (_, false, _) => {
+                Self::extend_into(code, ty, "x9");
+                code.emit("cmp x9, x0");
+
+                "ne"
+            }
+        };
+
+        code.emit(format!("b.{} {}", condition, overflow));
+    }
+
+    fn compare(&self, code: &mut Code, op: BinaryOp, signed: bool) {
+        let condition = match (op, signed) {
+            (BinaryOp::Equal, _) => "eq",
+            (BinaryOp::NotEqual, _) => "ne",
+            (BinaryOp::Less, true) => "lt",
+            (BinaryOp::Less, false) => "lo",
+            (BinaryOp::LessEqual, true) => "le",
+            (BinaryOp::LessEqual, false) => "ls",
+            (BinaryOp::Greater, true) => "gt",
+            (BinaryOp::Greater, false) => "hi",
+            (_, true) => "ge",
+            (_, false) => "hs",
+        };
+
+        code.emit("cmp x0, x1");
+        code.emit(format!("cset x0, {}", condition));
+    }
+
+    fn not(&self, code: &mut Code) {
+        code.emit("eor x0, x0, #1");
+    }
+
+    fn extend(&self, code: &mut Code, ty: IntTy) {
+        Self::extend_into(code, ty, "x0");
+    }
+
+    fn branch(&self, code: &mut Code, zero: bool, label: &str) {
+        code.emit(format!(
+            "{} x0, {}",
+            if zero { "cbz" } else { "cbnz" },
+            label
+        ));
+    }
+
+    fn jump(&self, code: &mut Code, label: &str) {
+        code.emit(format!("b {}", label));
+    }
+
+    fn trap(&self, code: &mut Code) {
+        code.emit("brk #1");
+    }
+
+    fn call(&self, code: &mut Code, callee: Callee, args: &[usize]) {
+        let args: Vec<_> = args.iter().map(|&slot| Argument::Slot(slot)).collect();
+        let stack = self.arguments(code, &args, args.len());
+
+        match callee {
+            Callee::Symbol(symbol) => code.emit(format!("bl {}", symbol)),
+            Callee::Slot(slot) => {
+                Self::access(code, false, "x9", slot);
+                code.emit("blr x9");
+            }
+        }
+
+        Self::reserve(code, -stack);
+    }
+
+    fn call_c(&self, code: &mut Code, name: &str, args: &[Argument], fixed: usize) {
+        let stack = self.arguments(code, args, fixed);
+
+        code.emit(format!("bl {}", asm::c_symbol(self.os, name)));
+        Self::reserve(code, -stack);
+    }
+}
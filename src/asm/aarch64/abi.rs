@@ -0,0 +1,122 @@
+//! The AAPCS64 calling convention, which AArch64 code follows on Linux, with
+//! the changes Apple made to it on macOS: where a function finds its
+//! arguments, where it leaves what it returns, and what it has to keep as it
+//! was.
+//!
+//! None of this is about how instructions are written, so the assembly
+//! emitter and an emitter of object files can both follow it.
+use crate::backend::Os;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A 64-bit general purpose register, `x0` to `x30`, by its number.
+pub struct Register(pub u8);
+
+impl Register {
+    /// The register's number in the encoding of instructions.
+    pub fn number(self) -> u8 {
+        self.0
+    }
+
+    /// The register's name, e.g. `x0`.
+    pub fn name(self) -> String {
+        format!("x{}", self.0)
+    }
+}
+
+/// The registers the first integer arguments of a call are passed in, in
+/// order. The rest are passed on the stack.
+pub const ARGS: [Register; 8] = [
+    Register(0),
+    Register(1),
+    Register(2),
+    Register(3),
+    Register(4),
+    Register(5),
+    Register(6),
+    Register(7),
+];
+
+/// The register an integer is returned in.
+pub const RETURN: Register = Register(0);
+
+/// The register that points at the frame of the function being run.
+pub const FRAME: Register = Register(29);
+
+/// The register a call leaves the address it returns to in.
+pub const LINK: Register = Register(30);
+
+/// The registers a function has to restore before it returns, if it uses
+/// them. Every other register can be changed by any call.
+pub const CALLEE_SAVED: [Register; 10] = [
+    Register(19),
+    Register(20),
+    Register(21),
+    Register(22),
+    Register(23),
+    Register(24),
+    Register(25),
+    Register(26),
+    Register(27),
+    Register(28),
+];
+
+/// The registers the linker may change between a call and the function it
+/// calls, which nothing else keeps anything in, so they are free to use for
+/// a few instructions.
+pub const SCRATCH: [Register; 2] = [Register(16), Register(17)];
+
+/// The bytes every argument takes on the stack.
+pub const SLOT: i64 = 8;
+
+/// What the stack pointer is a multiple of, always.
+pub const STACK_ALIGNMENT: i64 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Where an argument is passed.
+pub enum Location {
+    Register(Register),
+    /// On the stack, this many bytes above the stack pointer at the call
+    Stack(i64),
+}
+
+/// Where each of a number of integer arguments is passed, in order, when the
+/// function takes the first `fixed` and then any number more, like `printf`.
+/// On macOS, the arguments after the fixed ones are all passed on the stack.
+pub fn arguments(count: usize, fixed: usize, os: Os) -> Vec<Location> {
+    let mut stack = 0;
+
+    (0..count)
+        .map(|i| match ARGS.get(i) {
+            Some(&register) if i < fixed || os != Os::MacOs => Location::Register(register),
+            _ => {
+                stack += SLOT;
+
+                Location::Stack(stack - SLOT)
+            }
+        })
+        .collect()
+}
+
+/// The bytes the arguments of a call that are passed on the stack take,
+/// with the padding needed after them so the stack stays aligned.
+pub fn stack_arguments(count: usize, fixed: usize, os: Os) -> i64 {
+    let bytes = arguments(count, fixed, os)
+        .iter()
+        .filter(|location| matches!(location, Location::Stack(_)))
+        .count() as i64
+        * SLOT;
+
+    align(bytes)
+}
+
+/// Where a function finds an argument that was passed on the stack, as an
+/// offset from its frame pointer. The frame pointer and the link register
+/// of its caller are saved in between.
+pub fn parameter_offset(offset: i64) -> i64 {
+    2 * SLOT + offset
+}
+
+/// Round a size up so the stack stays aligned when it is reserved.
+pub fn align(bytes: i64) -> i64 {
+    (bytes + STACK_ALIGNMENT - 1) / STACK_ALIGNMENT * STACK_ALIGNMENT
+}
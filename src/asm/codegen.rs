@@ -0,0 +1,835 @@
+use crate::arithmetic;
+use crate::asm::{self, Argument, Callee, Code, Isa};
+use crate::backend::{
+    self, int, CheckKind, CodegenError, CodegenErrorKind, Failure, Layout, Os, Target,
+};
+use crate::hir::{self, Arm, Block, Expr, ExprKind, Pattern, Stmt};
+use crate::lexer::Span;
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use crate::source_map::SourceMap;
+use crate::typeck::Ty;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Compile a lowered program to the assembly of a whole object file for a
+/// target. The spans of runtime errors are located in `sources`, if there
+/// are any, so they can say where the code that failed is.
+pub fn compile(
+    program: &hir::Program,
+    sources: Option<&SourceMap>,
+    target: Target,
+) -> Result<String, Vec<CodegenError>> {
+    let isa = asm::isa(target);
+
+    let mut codegen = Codegen {
+        hir: program,
+        layout: Layout::new(program),
+        sources,
+        isa: isa.as_ref(),
+        os: target.os,
+        text: String::new(),
+        data: String::new(),
+        bytes: HashMap::new(),
+        labels: 0,
+        errors: Vec::new(),
+    };
+
+    for function in &program.functions {
+        codegen.function(function);
+    }
+
+    for global in &program.globals {
+        codegen.global(global);
+    }
+
+    codegen.entry_point();
+
+    if !codegen.errors.is_empty() {
+        return Err(codegen.errors);
+    }
+
+    Ok(codegen.finish())
+}
+
+struct Codegen<'a> {
+    hir: &'a hir::Program,
+    layout: Layout<'a>,
+    sources: Option<&'a SourceMap>,
+    isa: &'a dyn Isa,
+    os: Os,
+    /// The functions compiled so far
+    text: String,
+    /// The constant bytes of strings and messages
+    data: String,
+    /// The label of each constant, by its bytes
+    bytes: HashMap<Vec<u8>, String>,
+    /// How many labels have been made
+    labels: usize,
+    errors: Vec<CodegenError>,
+}
+
+/// A function being compiled.
+struct Compiler<'c, 'a> {
+    codegen: &'c mut Codegen<'a>,
+    isa: &'a dyn Isa,
+    /// How many locals the function has
+    locals: usize,
+    /// The instructions of the function's body
+    code: Code,
+    /// How many temporary slots are in use
+    temporaries: usize,
+    /// The most temporary slots that have been in use at once
+    deepest: usize,
+    /// The label of each check that can fail, and the message it fails with
+    failures: Vec<(String, String)>,
+    /// The deferred blocks of each block being compiled, innermost last
+    scopes: Vec<Vec<Block>>,
+    /// The loops being compiled, innermost last
+    loops: Vec<Loop>,
+}
+
+struct Loop {
+    /// The label `continue` jumps to
+    header: String,
+    /// The label `break` jumps to
+    exit: String,
+    /// How many blocks were being compiled when the loop started
+    scopes: usize,
+}
+
+impl<'a> Codegen<'a> {
+    fn function(&mut self, function: &hir::Function) {
+        for &param in &function.params {
+            self.check_type(&function.locals[param.index()].ty, function.span);
+        }
+
+        self.check_type(&function.ret, function.span);
+
+        let name = self.symbol(&format!("pg.{}", function.full_name()));
+        let params: Vec<_> = function.params.iter().map(|param| param.index()).collect();
+
+        self.build(&name, true, function.locals.len(), &params, |compiler| {
+            compiler.expr(&function.body)
+        });
+    }
+
+    /// Compile the function computing the value of a global, which is called
+    /// before `main`.
+    fn global(&mut self, global: &hir::Global) {
+        self.check_type(&global.ty, global.span);
+
+        let name = self.symbol(&format!("pg.init.{}", global.name));
+
+        self.build(&name, false, global.locals.len(), &[], |compiler| {
+            compiler.expr(&global.value)
+        });
+    }
+
+    /// Compile the `main` the program starts at, which sets the globals in
+    /// order and calls the program's `main`, exiting with what it returns.
+    fn entry_point(&mut self) {
+        let Some(position) = backend::entry_point(self.hir) else {
+            return;
+        };
+
+        let name = asm::c_symbol(self.os, "main");
+
+        self.build(&name, true, 0, &[], |compiler| {
+            let hir = compiler.codegen.hir;
+
+            for global in &hir.globals {
+                let init = compiler.codegen.symbol(&format!("pg.init.{}", global.name));
+                let memory = compiler.codegen.global_symbol(&global.name);
+
+                compiler
+                    .isa
+                    .call(&mut compiler.code, Callee::Symbol(&init), &[]);
+                compiler.isa.store_global(&mut compiler.code, &memory);
+            }
+
+            let main = &hir.functions[position];
+            let symbol = compiler.codegen.symbol(&format!("pg.{}", main.full_name()));
+
+            compiler
+                .isa
+                .call(&mut compiler.code, Callee::Symbol(&symbol), &[]);
+
+            // C's `main` returns an `int`, which is the low half of the result
+            if !matches!(main.ret, Ty::Int(_)) {
+                compiler.isa.constant(&mut compiler.code, 0);
+            }
+        });
+    }
+
+    /// Compile a function with some locals, whose body `body` compiles,
+    /// returning from it with the result. Its arguments are moved to the
+    /// slots in `params`, and it can be called from other object files if
+    /// it is `exported`. Functions with errors aren't written.
+    fn build(
+        &mut self,
+        name: &str,
+        exported: bool,
+        locals: usize,
+        params: &[usize],
+        body: impl FnOnce(&mut Compiler<'_, 'a>),
+    ) {
+        let errors = self.errors.len();
+        let (isa, os, labels) = (self.isa, self.os, self.labels);
+
+        let mut compiler = Compiler {
+            codegen: self,
+            isa,
+            locals,
+            code: Code::new(os, labels),
+            temporaries: 0,
+            deepest: 0,
+            failures: Vec::new(),
+            scopes: Vec::new(),
+            loops: Vec::new(),
+        };
+
+        body(&mut compiler);
+        isa.epilogue(&mut compiler.code);
+
+        for (label, message) in std::mem::take(&mut compiler.failures) {
+            compiler.failure(&label, &message);
+        }
+
+        let (code, slots) = (compiler.code, locals + compiler.deepest);
+        let mut prologue = Code::new(os, code.labels);
+
+        isa.prologue(&mut prologue, slots, params);
+        self.labels = prologue.labels;
+
+        if self.errors.len() > errors {
+            return;
+        }
+
+        if exported {
+            let _ = writeln!(self.text, "    .globl {}", name);
+        }
+
+        let _ = writeln!(self.text, "    .p2align 4\n{}:", name);
+
+        self.text.push_str(&prologue.text);
+        self.text.push_str(&code.text);
+        self.text.push('\n');
+    }
+
+    /// The assembly of the whole object file.
+    fn finish(self) -> String {
+        let mut assembly = format!("    .text\n{}", self.text);
+
+        if !self.data.is_empty() {
+            let section = match self.os {
+                Os::MacOs => "__TEXT,__const",
+                Os::Linux => ".rodata",
+            };
+
+            let _ = write!(assembly, "    .section {}\n{}", section, self.data);
+        }
+
+        if !self.hir.globals.is_empty() {
+            assembly.push_str(match self.os {
+                Os::MacOs => "    .data\n",
+                Os::Linux => "    .bss\n",
+            });
+            assembly.push_str("    .p2align 3\n");
+
+            for global in &self.hir.globals {
+                let _ = writeln!(
+                    assembly,
+                    "{}:\n    .zero 8",
+                    self.global_symbol(&global.name)
+                );
+            }
+        }
+
+        match self.os {
+            // Without this, linkers assume the program needs an executable
+            // stack
+            Os::Linux => assembly.push_str("    .section .note.GNU-stack,\"\",@progbits\n"),
+            // Symbols are what the linker can take apart, so it can leave out
+            // functions that aren't used
+            Os::MacOs => assembly.push_str("    .subsections_via_symbols\n"),
+        }
+
+        assembly
+    }
+
+    fn symbol(&self, name: &str) -> String {
+        asm::symbol(self.os, name)
+    }
+
+    /// The symbol of the memory of a global.
+    fn global_symbol(&self, name: &str) -> String {
+        self.symbol(&format!("pg.global.{}", name))
+    }
+
+    /// Report a value of a type that doesn't fit in a register.
+    fn check_type(&mut self, ty: &Ty, span: Span) {
+        match ty {
+            Ty::Param(_) | Ty::Unknown => self.unsupported("values of unknown types", span),
+            ty if !fits(ty) => self.unsupported(&format!("values of type '{}'", ty), span),
+            _ => {}
+        }
+    }
+
+    /// Report something that isn't supported.
+    fn unsupported(&mut self, what: &str, span: Span) {
+        let error = CodegenError::new(
+            CodegenErrorKind::Unsupported {
+                backend: "assembly",
+                what: what.to_string(),
+            },
+            span,
+        );
+
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    /// The label of a constant with some bytes, adding it if there isn't
+    /// one.
+    fn constant(&mut self, bytes: &[u8]) -> String {
+        if let Some(label) = self.bytes.get(bytes) {
+            return label.clone();
+        }
+
+        let prefix = match self.os {
+            Os::MacOs => "L",
+            Os::Linux => ".L",
+        };
+        let label = format!("{}str.{}", prefix, self.bytes.len());
+
+        let _ = writeln!(self.data, "{}:\n    .ascii \"{}\"", label, escape(bytes));
+        self.bytes.insert(bytes.to_vec(), label.clone());
+
+        label
+    }
+
+    /// The label of text ending in a zero byte, for C functions.
+    fn cstring(&mut self, text: &str) -> String {
+        let mut bytes = text.as_bytes().to_vec();
+
+        bytes.push(0);
+
+        self.constant(&bytes)
+    }
+}
+
+impl<'c, 'a> Compiler<'c, 'a> {
+    /// A temporary slot to keep a value in, until it is released.
+    fn temporary(&mut self) -> usize {
+        self.temporaries += 1;
+        self.deepest = self.deepest.max(self.temporaries);
+
+        self.locals + self.temporaries - 1
+    }
+
+    /// Release the temporary slots taken last.
+    fn release(&mut self, count: usize) {
+        self.temporaries -= count;
+    }
+
+    /// Keep the result in a temporary slot.
+    fn keep(&mut self) -> usize {
+        let slot = self.temporary();
+
+        self.isa.store(&mut self.code, slot);
+
+        slot
+    }
+
+    /// Stop the program with a runtime error when the label this returns is
+    /// jumped to.
+    fn fail(&mut self, failure: &Failure, span: Span) -> String {
+        let label = self.code.label();
+        let message = backend::runtime_error(self.codegen.sources, span, failure);
+
+        self.failures.push((label.clone(), message));
+
+        label
+    }
+
+    /// Write the code a check jumps to when it fails, which prints a runtime
+    /// error and exits.
+    fn failure(&mut self, label: &str, message: &str) {
+        let format = self.codegen.cstring("[RUNTIME ERROR]: %.*s.\n");
+        let text = self.codegen.constant(message.as_bytes());
+
+        self.code.place(label);
+        self.isa.call_c(
+            &mut self.code,
+            "printf",
+            &[
+                Argument::Address(&format),
+                Argument::Int(message.len() as i64),
+                Argument::Address(&text),
+            ],
+            1,
+        );
+        self.isa
+            .call_c(&mut self.code, "exit", &[Argument::Int(1)], 1);
+    }
+
+    /// Compile an expression, leaving its value in the result.
+    fn expr(&mut self, expr: &Expr) {
+        // Calls of imports have no type
+        let imported = matches!(
+            &expr.kind,
+            ExprKind::Call { callee, .. } if matches!(callee.kind, ExprKind::Import(_))
+        );
+
+        if !imported {
+            self.codegen.check_type(&expr.ty, expr.span);
+        }
+
+        self.value(expr);
+    }
+
+    fn value(&mut self, expr: &Expr) {
+        let isa = self.isa;
+
+        match &expr.kind {
+            ExprKind::Int(value) => isa.constant(&mut self.code, *value as i64),
+            ExprKind::Bool(value) => isa.constant(&mut self.code, *value as i64),
+            ExprKind::Local(local) => isa.load(&mut self.code, local.index()),
+            ExprKind::Function(position) => {
+                let name = format!("pg.{}", self.codegen.hir.functions[*position].full_name());
+                let symbol = self.codegen.symbol(&name);
+
+                isa.address(&mut self.code, &symbol);
+            }
+            ExprKind::Global(position) => {
+                let symbol = self
+                    .codegen
+                    .global_symbol(&self.codegen.hir.globals[*position].name);
+
+                isa.load_global(&mut self.code, &symbol);
+            }
+            ExprKind::Import(name) => {
+                self.codegen
+                    .unsupported(&format!("'{}' from another file", name), expr.span);
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Unary { op, operand } => {
+                self.expr(operand);
+
+                match op {
+                    UnaryOp::Negate => {
+                        isa.operand(&mut self.code);
+                        isa.constant(&mut self.code, 0);
+                        self.arithmetic(BinaryOp::Subtract, &expr.ty, expr.span);
+                    }
+                    UnaryOp::Not => isa.not(&mut self.code),
+                }
+            }
+            ExprKind::Binary { op, left, right } => self.binary(*op, left, right, expr),
+            ExprKind::Cast(value) => {
+                self.expr(value);
+
+                // Booleans are already 0 or 1
+                if let (Ty::Int(_), Ty::Int(to)) = (&value.ty, &expr.ty) {
+                    isa.extend(&mut self.code, *to);
+                }
+            }
+            ExprKind::Call { callee, args } => self.call(callee, args),
+            // What these are taken from doesn't fit in a register, which is
+            // reported when it is compiled
+            ExprKind::MethodCall {
+                receiver: object, ..
+            }
+            | ExprKind::Index { object, .. }
+            | ExprKind::Field { object, .. } => self.expr(object),
+            ExprKind::Assign { target, value } => self.assignment(target, value),
+            ExprKind::Closure { .. } => self.codegen.unsupported("closures", expr.span),
+            ExprKind::Match { value, arms } => self.matching(value, arms),
+            ExprKind::Loop(body) => self.looping(body),
+            ExprKind::Break | ExprKind::Continue => {
+                let Some(innermost) = self.loops.last() else {
+                    self.codegen
+                        .unsupported("leaving a loop outside of one", expr.span);
+
+                    return;
+                };
+
+                let scopes = innermost.scopes;
+                let target = match expr.kind {
+                    ExprKind::Break => innermost.exit.clone(),
+                    _ => innermost.header.clone(),
+                };
+
+                self.run_defers(scopes);
+                isa.jump(&mut self.code, &target);
+            }
+            ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+
+                self.keeping(|compiler| compiler.run_defers(0));
+                isa.epilogue(&mut self.code);
+            }
+            // Their types don't fit in a register, which is reported above
+            ExprKind::Str(_)
+            | ExprKind::Tuple(_)
+            | ExprKind::Array(_)
+            | ExprKind::Struct { .. }
+            | ExprKind::Variant { .. } => {}
+        }
+    }
+
+    /// Compile something that leaves the result as it was.
+    fn keeping(&mut self, compile: impl FnOnce(&mut Self)) {
+        let (length, deepest) = (self.code.text.len(), self.deepest);
+        let slot = self.keep();
+        let kept = self.code.text.len();
+
+        compile(self);
+
+        // Nothing needs keeping if nothing was compiled
+        if self.code.text.len() == kept {
+            self.code.text.truncate(length);
+            self.deepest = deepest;
+        } else {
+            self.isa.load(&mut self.code, slot);
+        }
+
+        self.release(1);
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: &Expr, right: &Expr, expr: &Expr) {
+        let isa = self.isa;
+
+        // The result already has the value when the right side isn't needed
+        if let Some(decided) = backend::short_circuit(op) {
+            let end = self.code.label();
+
+            self.expr(left);
+            isa.branch(&mut self.code, !decided, &end);
+            self.expr(right);
+            self.code.place(&end);
+
+            return;
+        }
+
+        self.expr(left);
+
+        let slot = self.keep();
+
+        self.expr(right);
+        isa.operand(&mut self.code);
+        isa.load(&mut self.code, slot);
+        self.release(1);
+
+        match op {
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulo => self.arithmetic(op, &expr.ty, expr.span),
+            _ => isa.compare(&mut self.code, op, int(&left.ty).is_signed()),
+        }
+    }
+
+    /// Integer arithmetic on the result and the operand, checked like
+    /// `arithmetic::checks` says.
+    fn arithmetic(&mut self, op: BinaryOp, ty: &Ty, span: Span) {
+        let ty = int(ty);
+        let checks = arithmetic::checks(op, ty, self.codegen.hir.overflow);
+
+        let overflow = match checks.overflow {
+            true => Some(self.fail(&Failure::Overflow(ty), span)),
+            false => None,
+        };
+        let zero = match checks.zero {
+            true => Some(self.fail(&Failure::DivisionByZero, span)),
+            false => None,
+        };
+
+        self.isa
+            .arithmetic(&mut self.code, op, ty, overflow.as_deref(), zero.as_deref());
+
+        // What wrapped around is only wrong in the bits above the type's
+        if !checks.overflow && ty.bits() < 64 {
+            self.isa.extend(&mut self.code, ty);
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr]) {
+        if let ExprKind::Import(name) = &callee.kind {
+            if name == "print" {
+                return self.print(args);
+            }
+
+            return self
+                .codegen
+                .unsupported(&format!("'{}' from another file", name), callee.span);
+        }
+
+        // Functions are called by name, and anything else by its address
+        let target = match &callee.kind {
+            ExprKind::Function(position) => {
+                let name = format!("pg.{}", self.codegen.hir.functions[*position].full_name());
+
+                Err(self.codegen.symbol(&name))
+            }
+            _ => {
+                self.expr(callee);
+
+                Ok(self.keep())
+            }
+        };
+
+        let slots: Vec<_> = args
+            .iter()
+            .map(|arg| {
+                self.expr(arg);
+                self.keep()
+            })
+            .collect();
+
+        let callee = match &target {
+            Ok(slot) => Callee::Slot(*slot),
+            Err(symbol) => Callee::Symbol(symbol),
+        };
+
+        self.isa.call(&mut self.code, callee, &slots);
+        self.release(slots.len() + usize::from(target.is_ok()));
+    }
+
+    /// Print values on a line, separated by spaces.
+    fn print(&mut self, args: &[Expr]) {
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                self.print_text(" ");
+            }
+
+            match (&arg.kind, &arg.ty) {
+                (ExprKind::Str(text), _) => self.print_text(text),
+                (_, Ty::Int(int)) => {
+                    let format =
+                        self.codegen
+                            .cstring(if int.is_signed() { "%lld" } else { "%llu" });
+
+                    self.expr(arg);
+
+                    let slot = self.keep();
+
+                    self.isa.call_c(
+                        &mut self.code,
+                        "printf",
+                        &[Argument::Address(&format), Argument::Slot(slot)],
+                        1,
+                    );
+                    self.release(1);
+                }
+                (_, Ty::Bool) => {
+                    let (no, end) = (self.code.label(), self.code.label());
+
+                    self.expr(arg);
+                    self.isa.branch(&mut self.code, true, &no);
+                    self.print_text("true");
+                    self.isa.jump(&mut self.code, &end);
+                    self.code.place(&no);
+                    self.print_text("false");
+                    self.code.place(&end);
+                }
+                (_, Ty::Function { .. }) => {
+                    self.expr(arg);
+                    self.print_text("<func>");
+                }
+                (_, Ty::Tuple(types)) if types.is_empty() => {
+                    self.expr(arg);
+                    self.print_text("()");
+                }
+                _ => self.expr(arg),
+            }
+        }
+
+        self.print_text("\n");
+    }
+
+    fn print_text(&mut self, text: &str) {
+        let format = self.codegen.cstring("%.*s");
+        let bytes = self.codegen.constant(text.as_bytes());
+
+        self.isa.call_c(
+            &mut self.code,
+            "printf",
+            &[
+                Argument::Address(&format),
+                Argument::Int(text.len() as i64),
+                Argument::Address(&bytes),
+            ],
+            1,
+        );
+    }
+
+    /// Compile an assignment, storing the value where the target is.
+    fn assignment(&mut self, target: &Expr, value: &Expr) {
+        match &target.kind {
+            ExprKind::Local(local) => {
+                self.expr(value);
+                self.isa.store(&mut self.code, local.index());
+            }
+            ExprKind::Global(position) => {
+                let symbol = self
+                    .codegen
+                    .global_symbol(&self.codegen.hir.globals[*position].name);
+
+                self.expr(value);
+                self.isa.store_global(&mut self.code, &symbol);
+            }
+            // Anything else is in a value that doesn't fit in a register,
+            // which is reported when it is compiled
+            _ => self.expr(target),
+        }
+    }
+
+    /// Compile a block, running what it deferred once it has its value.
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(Vec::new());
+
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+
+        if let Some(value) = &block.value {
+            self.expr(value);
+        }
+
+        let deferred = self.scopes.pop().unwrap_or_default();
+
+        self.keeping(|compiler| {
+            for body in backend::unwinding(&[deferred]) {
+                compiler.block(&body);
+            }
+        });
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { local, value } => {
+                self.expr(value);
+                self.isa.store(&mut self.code, local.index());
+            }
+            Stmt::Assert { condition, message } => {
+                self.expr(condition);
+
+                let failure = Failure::AssertionFailed(message.clone());
+                let fail = self.fail(&failure, condition.span);
+
+                self.isa.branch(&mut self.code, true, &fail);
+            }
+            Stmt::Defer(body) => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push(body.clone());
+                }
+            }
+            Stmt::Expr(expr) => self.expr(expr),
+        }
+    }
+
+    fn run_defers(&mut self, scope: usize) {
+        for body in backend::unwinding(&self.scopes[scope..]) {
+            self.block(&body);
+        }
+    }
+
+    fn looping(&mut self, body: &Block) {
+        let (header, exit) = (self.code.label(), self.code.label());
+
+        self.code.place(&header);
+        self.loops.push(Loop {
+            header: header.clone(),
+            exit: exit.clone(),
+            scopes: self.scopes.len(),
+        });
+
+        self.block(body);
+        self.isa.jump(&mut self.code, &header);
+
+        self.loops.pop();
+        self.code.place(&exit);
+    }
+
+    fn matching(&mut self, value: &Expr, arms: &[Arm]) {
+        self.expr(value);
+
+        let scrutinee = self.keep();
+        let end = self.code.label();
+
+        for arm in arms {
+            let next = self.code.label();
+
+            self.pattern(&arm.pattern, &value.ty, scrutinee, &next);
+            self.expr(&arm.value);
+            self.isa.jump(&mut self.code, &end);
+            self.code.place(&next);
+        }
+
+        // The arms cover every value
+        self.isa.trap(&mut self.code);
+        self.code.place(&end);
+        self.release(1);
+    }
+
+    /// Compile a pattern matched against the value of a type in a slot,
+    /// binding what it binds, and jumping to `fail` if it doesn't match.
+    fn pattern(&mut self, pattern: &Pattern, ty: &Ty, scrutinee: usize, fail: &str) {
+        let isa = self.isa;
+
+        // What they take from the value doesn't fit in a register, which is
+        // reported when it is compiled
+        let Ok(checks) = self.codegen.layout.checks(pattern, ty) else {
+            return;
+        };
+
+        for check in checks {
+            let constant = match check.kind {
+                _ if !check.path.is_empty() => return,
+                CheckKind::Bind(local) => {
+                    isa.load(&mut self.code, scrutinee);
+                    isa.store(&mut self.code, local.index());
+
+                    continue;
+                }
+                CheckKind::Bool(value) => value as i64,
+                CheckKind::Int(value) => value as i64,
+                _ => return,
+            };
+
+            isa.constant(&mut self.code, constant);
+            isa.operand(&mut self.code);
+            isa.load(&mut self.code, scrutinee);
+            isa.compare(&mut self.code, BinaryOp::Equal, false);
+            isa.branch(&mut self.code, true, fail);
+        }
+    }
+}
+
+/// Whether values of a type fit in a register.
+fn fits(ty: &Ty) -> bool {
+    match ty {
+        Ty::Int(_) | Ty::Bool | Ty::Never => true,
+        Ty::Tuple(types) => types.is_empty(),
+        Ty::Function { params, ret } => params.iter().all(fits) && fits(ret),
+        _ => false,
+    }
+}
+
+/// Bytes as the contents of a string in assembly, with anything that isn't
+/// printable written in octal.
+fn escape(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            b'"' | b'\\' => format!("\\{}", byte as char),
+            b' '..=b'~' => (byte as char).to_string(),
+            _ => format!("\\{:03o}", byte),
+        })
+        .collect()
+}
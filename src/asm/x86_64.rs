@@ -0,0 +1,312 @@
+//! x86-64 assembly in AT&T syntax, for Linux and macOS.
+//!
+//! - `abi` says where arguments and results are passed, which an emitter of
+//!   object files needs as much as this one does.
+//!
+//! The result is `%rax` and the operand is `%rcx`. Every slot is in the
+//! stack frame below `%rbp`, so the stack pointer only moves to pass
+//! arguments. Integers are kept sign or zero extended to 64 bits, so any two
+//! values can be compared whole, and arithmetic on narrower types is done in
+//! 64 bits and checked to still fit.
+use crate::asm::x86_64::abi::Location;
+use crate::asm::{self, Argument, Callee, Code, Isa};
+use crate::backend::Os;
+use crate::parser::ast::BinaryOp;
+use crate::typeck::IntTy;
+
+pub mod abi;
+
+/// The x86-64 instruction set, with the calling convention of an operating
+/// system.
+pub struct X86_64 {
+    pub os: Os,
+}
+
+impl X86_64 {
+    /// The memory of a slot, as an operand.
+    fn slot(slot: usize) -> String {
+        format!("{}(%rbp)", -abi::SLOT * (slot as i64 + 1))
+    }
+
+    /// Put an integer in a register, as its 64 bits.
+    fn immediate(code: &mut Code, value: i64, register: &str) {
+        match i32::try_from(value) {
+            Ok(value) => code.emit(format!("movq ${}, {}", value, register)),
+            Err(_) => code.emit(format!("movabsq ${}, {}", value, register)),
+        }
+    }
+
+    /// Sign or zero extend the low bits of `%rax` that a value of a type
+    /// has into a register.
+    fn extend_into(code: &mut Code, ty: IntTy, register: &str) {
+        let instruction = match ty {
+            IntTy::I8 => "movsbq %al",
+            IntTy::U8 => "movzbq %al",
+            IntTy::I16 => "movswq %ax",
+            IntTy::U16 => "movzwq %ax",
+            IntTy::I32 => "movslq %eax",
+            // Writing the low half of a register clears the high half
+            IntTy::U32 => {
+                return code.emit(format!("movl %eax, {}", register.replace("%r", "%e")));
+            }
+            IntTy::I64 | IntTy::U64 if register == "%rax" => return,
+            IntTy::I64 | IntTy::U64 => "movq %rax",
+        };
+
+        code.emit(format!("{}, {}", instruction, register));
+    }
+
+    /// Move the arguments of a call where the calling convention passes
+    /// them, making room on the stack for the ones passed there. Returns the
+    /// bytes of stack taken.
+    fn arguments(&self, code: &mut Code, args: &[Argument]) -> i64 {
+        let stack = abi::stack_arguments(args.len());
+
+        if stack > 0 {
+            code.emit(format!("subq ${}, %rsp", stack));
+        }
+
+        for (location, arg) in abi::arguments(args.len()).into_iter().zip(args) {
+            let register = match location {
+                Location::Register(register) => register.name(),
+                Location::Stack(_) => "%rax",
+            };
+
+            match *arg {
+                Argument::Address(label) => {
+                    code.emit(format!("leaq {}(%rip), {}", label, register));
+                }
+                Argument::Int(value) => Self::immediate(code, value, register),
+                Argument::Slot(slot) => {
+                    code.emit(format!("movq {}, {}", Self::slot(slot), register));
+                }
+            }
+
+            if let Location::Stack(offset) = location {
+                code.emit(format!("movq %rax, {}(%rsp)", offset));
+            }
+        }
+
+        stack
+    }
+}
+
+impl Isa for X86_64 {
+    fn prologue(&self, code: &mut Code, slots: usize, params: &[usize]) {
+        let frame = abi::align(slots as i64 * abi::SLOT);
+
+        code.emit("pushq %rbp");
+        code.emit("movq %rsp, %rbp");
+
+        if frame > 0 {
+            code.emit(format!("subq ${}, %rsp", frame));
+        }
+
+        for (&param, location) in params.iter().zip(abi::arguments(params.len())) {
+            match location {
+                Location::Register(register) => {
+                    code.emit(format!("movq {}, {}", register.name(), Self::slot(param)));
+                }
+                Location::Stack(offset) => {
+                    let offset = abi::parameter_offset(offset);
+
+                    code.emit(format!("movq {}(%rbp), %rax", offset));
+                    code.emit(format!("movq %rax, {}", Self::slot(param)));
+                }
+            }
+        }
+    }
+
+    fn epilogue(&self, code: &mut Code) {
+        code.emit("leave");
+        code.emit("ret");
+    }
+
+    fn constant(&self, code: &mut Code, value: i64) {
+        Self::immediate(code, value, "%rax");
+    }
+
+    fn load(&self, code: &mut Code, slot: usize) {
+        code.emit(format!("movq {}, %rax", Self::slot(slot)));
+    }
+
+    fn store(&self, code: &mut Code, slot: usize) {
+        code.emit(format!("movq %rax, {}", Self::slot(slot)));
+    }
+
+    fn load_global(&self, code: &mut Code, symbol: &str) {
+        code.emit(format!("movq {}(%rip), %rax", symbol));
+    }
+
+    fn store_global(&self, code: &mut Code, symbol: &str) {
+        code.emit(format!("movq %rax, {}(%rip)", symbol));
+    }
+
+    fn address(&self, code: &mut Code, symbol: &str) {
+        code.emit(format!("leaq {}(%rip), %rax", symbol));
+    }
+
+    fn operand(&self, code: &mut Code) {
+        code.emit("movq %rax, %rcx");
+    }
+
+    fn arithmetic(
+        &self,
+        code: &mut Code,
+        op: BinaryOp,
+        ty: IntTy,
+        overflow: Option<&str>,
+        zero: Option<&str>,
+    ) {
+        let instruction = match (op, ty) {
+            (BinaryOp::Add, _) => "addq %rcx, %rax",
+            (BinaryOp::Subtract, _) => "subq %rcx, %rax",
+            // Only the unsigned multiplication tells if the result doesn't
+            // fit in 64 unsigned bits
+            (BinaryOp::Multiply, IntTy::U64) => "mulq %rcx",
+            (BinaryOp::Multiply, _) => "imulq %rcx, %rax",
+            _ => {
+                if let Some(zero) = zero {
+                    code.emit("testq %rcx, %rcx");
+                    code.emit(format!("je {}", zero));
+                }
+
+                let end = code.label();
+
+                // The processor faults on dividing the smallest value by -1,
+                // so the result is worked out without dividing
+                if ty.is_signed() && overflow.is_none() {
+                    let divide = code.label();
+
+                    code.emit("cmpq $-1, %rcx");
+                    code.emit(format!("jne {}", divide));
+                    code.emit(match op {
+                        BinaryOp::Divide => "negq %rax",
+                        _ => "xorl %eax, %eax",
+                    });
+                    code.emit(format!("jmp {}", end));
+                    code.place(&divide);
+                }
+
+                // Which is checked for before it faults, when it overflows
+                if let Some(overflow) = overflow {
+                    let ok = code.label();
+
+                    code.emit("cmpq $-1, %rcx");
+                    code.emit(format!("jne {}", ok));
+                    Self::immediate(code, ty.min() as i64, "%rdx");
+                    code.emit("cmpq %rdx, %rax");
+                    code.emit(format!("je {}", overflow));
+                    code.place(&ok);
+                }
+
+                if ty.is_signed() {
+                    code.emit("cqto");
+                    code.emit("idivq %rcx");
+                } else {
+                    code.emit("xorl %edx, %edx");
+                    code.emit("divq %rcx");
+                }
+
+                // The remainder is left in `%rdx`
+                if op == BinaryOp::Modulo {
+                    code.emit("movq %rdx, %rax");
+                }
+
+                code.place(&end);
+
+                return;
+            }
+        };
+
+        code.emit(instruction);
+
+        let Some(overflow) = overflow else {
+            return;
+        };
+
+        // The operands of narrower types can't overflow 64 bits, so the
+        // result fits if extending its low bits doesn't change it
+        if ty.bits() == 64 {
+            let jump = if ty.is_signed() { "jo" } else { "jc" };
+
+            code.emit(format!("{} {}", jump, overflow));
+        } else {
+            Self::extend_into(code, ty, "%rcx");
+            code.emit("cmpq %rax, %rcx");
+            code.emit(format!("jne {}", overflow));
+        }
+    }
+
+    fn compare(&self, code: &mut Code, op: BinaryOp, signed: bool) {
+        let condition = match (op, signed) {
+            (BinaryOp::Equal, _) => "e",
+            (BinaryOp::NotEqual, _) => "ne",
+            (BinaryOp::Less, true) => "l",
+            (BinaryOp::Less, false) => "b",
+            (BinaryOp::LessEqual, true) => "le",
+            (BinaryOp::LessEqual, false) => "be",
+            (BinaryOp::Greater, true) => "g",
+            (BinaryOp::Greater, false) => "a",
+            (_, true) => "ge",
+            (_, false) => "ae",
+        };
+
+        code.emit("cmpq %rcx, %rax");
+        code.emit(format!("set{} %al", condition));
+        code.emit("movzbq %al, %rax");
+    }
+
+    fn not(&self, code: &mut Code) {
+        code.emit("xorq $1, %rax");
+    }
+
+    fn extend(&self, code: &mut Code, ty: IntTy) {
+        Self::extend_into(code, ty, "%rax");
+    }
+
+    fn branch(&self, code: &mut Code, zero: bool, label: &str) {
+        code.emit("testq %rax, %rax");
+        code.emit(format!("{} {}", if zero { "je" } else { "jne" }, label));
+    }
+
+    fn jump(&self, code: &mut Code, label: &str) {
+        code.emit(format!("jmp {}", label));
+    }
+
+    fn trap(&self, code: &mut Code) {
+        code.emit("ud2");
+    }
+
+    fn call(&self, code: &mut Code, callee: Callee, args: &[usize]) {
+        let args: Vec<_> = args.iter().map(|&slot| Argument::Slot(slot)).collect();
+        let stack = self.arguments(code, &args);
+
+        match callee {
+            Callee::Symbol(symbol) => code.emit(format!("call {}", symbol)),
+            Callee::Slot(slot) => code.emit(format!("call *{}", Self::slot(slot))),
+        }
+
+        if stack > 0 {
+            code.emit(format!("addq ${}, %rsp", stack));
+        }
+    }
+
+    fn call_c(&self, code: &mut Code, name: &str, args: &[Argument], fixed: usize) {
+        let stack = self.arguments(code, args);
+
+        // No arguments are passed in vector registers
+        if args.len() > fixed {
+            code.emit("xorl %eax, %eax");
+        }
+
+        match self.os {
+            Os::MacOs => code.emit(format!("call {}", asm::c_symbol(self.os, name))),
+            Os::Linux => code.emit(format!("call {}@PLT", name)),
+        }
+
+        if stack > 0 {
+            code.emit(format!("addq ${}, %rsp", stack));
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! The System V calling convention, which x86-64 code follows on Linux and
+//! macOS: where a function finds its arguments, where it leaves what it
+//! returns, and what it has to keep as it was.
+//!
+//! None of this is about how instructions are written, so the assembly
+//! emitter and an emitter of object files can both follow it.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A 64-bit general purpose register, in the order they are numbered in.
+pub enum Register {
+    Rax,
+    Rcx,
+    Rdx,
+    Rbx,
+    Rsp,
+    Rbp,
+    Rsi,
+    Rdi,
+    R8,
+    R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15,
+}
+
+impl Register {
+    /// The register's number in the encoding of instructions. Registers from
+    /// `r8` on need the extra bit of a REX prefix.
+    pub fn number(self) -> u8 {
+        self as u8
+    }
+
+    /// The register's name in AT&T syntax, e.g. `%rax`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Register::Rax => "%rax",
+            Register::Rcx => "%rcx",
+            Register::Rdx => "%rdx",
+            Register::Rbx => "%rbx",
+            Register::Rsp => "%rsp",
+            Register::Rbp => "%rbp",
+            Register::Rsi => "%rsi",
+            Register::Rdi => "%rdi",
+            Register::R8 => "%r8",
+            Register::R9 => "%r9",
+            Register::R10 => "%r10",
+            Register::R11 => "%r11",
+            Register::R12 => "%r12",
+            Register::R13 => "%r13",
+            Register::R14 => "%r14",
+            Register::R15 => "%r15",
+        }
+    }
+}
+
+/// The registers the first integer arguments of a call are passed in, in
+/// order. The rest are passed on the stack.
+pub const ARGS: [Register; 6] = [
+    Register::Rdi,
+    Register::Rsi,
+    Register::Rdx,
+    Register::Rcx,
+    Register::R8,
+    Register::R9,
+];
+
+/// The register an integer is returned in.
+pub const RETURN: Register = Register::Rax;
+
+/// The registers a function has to restore before it returns, if it uses
+/// them. Every other register can be changed by any call.
+pub const CALLEE_SAVED: [Register; 6] = [
+    Register::Rbx,
+    Register::Rbp,
+    Register::R12,
+    Register::R13,
+    Register::R14,
+    Register::R15,
+];
+
+/// The register a call to a function taking any number of arguments, like
+/// `printf`, says how many vector registers it passes arguments in with.
+pub const VECTOR_ARGS: Register = Register::Rax;
+
+/// The bytes every argument takes on the stack.
+pub const SLOT: i64 = 8;
+
+/// What the stack pointer is a multiple of at every call.
+pub const STACK_ALIGNMENT: i64 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Where an argument is passed.
+pub enum Location {
+    Register(Register),
+    /// On the stack, this many bytes above the stack pointer at the call
+    Stack(i64),
+}
+
+/// Where each of a number of integer arguments is passed, in order.
+pub fn arguments(count: usize) -> Vec<Location> {
+    (0..count)
+        .map(|i| match ARGS.get(i) {
+            Some(&register) => Location::Register(register),
+            None => Location::Stack((i - ARGS.len()) as i64 * SLOT),
+        })
+        .collect()
+}
+
+/// The bytes the arguments of a call that are passed on the stack take,
+/// with the padding needed before them so the stack stays aligned.
+pub fn stack_arguments(count: usize) -> i64 {
+    let bytes = count.saturating_sub(ARGS.len()) as i64 * SLOT;
+
+    align(bytes)
+}
+
+/// Where a function finds an argument that was passed on the stack, as an
+/// offset from its frame pointer once the frame pointer of its caller has
+/// been pushed. The return address and the saved frame pointer are in
+/// between.
+pub fn parameter_offset(offset: i64) -> i64 {
+    2 * SLOT + offset
+}
+
+/// Round a size up so the stack stays aligned when it is reserved.
+pub fn align(bytes: i64) -> i64 {
+    (bytes + STACK_ALIGNMENT - 1) / STACK_ALIGNMENT * STACK_ALIGNMENT
+}
@@ -0,0 +1,573 @@
+//! What the native backends have in common, so each of them lowers the HIR
+//! of a program the same way and the CLI can compile with any of them.
+//!
+//! - `Backend` is what the CLI compiles a program to an object file with.
+//! - `Target` is the machine and operating system code is compiled for.
+//! - `Layout` tells what the values of a type are made of, so every
+//!   backend lays out aggregates the same way.
+//! - `CodegenError` is what goes wrong compiling a program natively.
+//! - `Failure` is why a compiled program stops at runtime.
+//! - `Layout::checks`, `short_circuit` and `unwinding` decide how matches,
+//!   `&&` and `||`, and deferred blocks run, which the bytecode compiler
+//!   and the lowering to the IR share with the native backends.
+//!
+//! A backend compiles every function of a program, a function computing
+//! each global, and a C `main` that sets the globals in order and calls the
+//! program's `main`, exiting with what it returns. Arithmetic is checked
+//! like it is by the bytecode VM, and a program stops with a runtime error
+//! when a check fails.
+use crate::bytecode::{RuntimeError, RuntimeErrorKind};
+use crate::hir::{self, LocalId, Pattern};
+use crate::lexer::Span;
+use crate::parser::ast::BinaryOp;
+use crate::source_map::SourceMap;
+use crate::typeck::{IntTy, Ty};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// A backend compiling programs to native object files.
+pub trait Backend {
+    /// The name of the backend in messages, e.g. `LLVM`.
+    fn name(&self) -> &'static str;
+
+    /// Compile a lowered program to an object file for a target. The spans
+    /// of runtime errors are located in `sources`, if there are any, so they
+    /// can say where the code that failed is.
+    fn write_object(
+        &self,
+        program: &hir::Program,
+        sources: Option<&SourceMap>,
+        target: Target,
+        path: &Path,
+    ) -> Result<(), Vec<CodegenError>>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An instruction set architecture.
+pub enum Arch {
+    X86_64,
+    /// 64-bit ARM, which Apple Silicon and many servers are
+    Aarch64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An operating system, which decides the format of object files and how
+/// their symbols are named.
+pub enum Os {
+    Linux,
+    MacOs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What native code is compiled for.
+pub struct Target {
+    pub arch: Arch,
+    pub os: Os,
+}
+
+impl Target {
+    /// Every target that can be compiled for.
+    pub const ALL: [Target; 4] = [
+        Target::new(Arch::X86_64, Os::Linux),
+        Target::new(Arch::Aarch64, Os::Linux),
+        Target::new(Arch::X86_64, Os::MacOs),
+        Target::new(Arch::Aarch64, Os::MacOs),
+    ];
+
+    pub const fn new(arch: Arch, os: Os) -> Self {
+        Self { arch, os }
+    }
+
+    /// The machine the compiler runs on. Machines that can't be compiled
+    /// for are treated as x86-64 Linux.
+    pub fn host() -> Self {
+        let arch = match cfg!(target_arch = "aarch64") {
+            true => Arch::Aarch64,
+            false => Arch::X86_64,
+        };
+        let os = match cfg!(target_os = "macos") {
+            true => Os::MacOs,
+            false => Os::Linux,
+        };
+
+        Self::new(arch, os)
+    }
+
+    /// The target a triple like `aarch64-apple-darwin` names. Only the
+    /// architecture and operating system matter, so `arm64-apple-macos` and
+    /// `x86_64-linux-musl` are understood too.
+    pub fn parse(triple: &str) -> Option<Self> {
+        let (arch, rest) = triple.split_once('-')?;
+
+        let arch = match arch {
+            "x86_64" | "amd64" => Arch::X86_64,
+            "aarch64" | "arm64" => Arch::Aarch64,
+            _ => return None,
+        };
+
+        let os = if rest.split('-').any(|part| part == "linux") {
+            Os::Linux
+        } else if rest.starts_with("apple") || rest.contains("darwin") || rest.contains("macos") {
+            Os::MacOs
+        } else {
+            return None;
+        };
+
+        Some(Self::new(arch, os))
+    }
+
+    /// The target's triple, as LLVM and Cranelift name it.
+    pub fn triple(self) -> &'static str {
+        match (self.arch, self.os) {
+            (Arch::X86_64, Os::Linux) => "x86_64-unknown-linux-gnu",
+            (Arch::Aarch64, Os::Linux) => "aarch64-unknown-linux-gnu",
+            (Arch::X86_64, Os::MacOs) => "x86_64-apple-darwin",
+            (Arch::Aarch64, Os::MacOs) => "aarch64-apple-darwin",
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.triple())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodegenErrorKind {
+    /// Something in the program a backend can't compile yet, e.g. closures.
+    Unsupported { backend: &'static str, what: String },
+    /// The object file couldn't be written, e.g. because `llc` couldn't be
+    /// run.
+    Object { message: String },
+    /// The program couldn't be compiled into memory to be run, e.g. because
+    /// it has no `main` to start at.
+    Jit { message: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An error compiling a program to native code.
+pub struct CodegenError {
+    pub kind: CodegenErrorKind,
+    pub span: Span,
+}
+
+impl CodegenError {
+    pub fn new(kind: CodegenErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// An error writing an object file, which isn't about any code.
+    pub fn object(message: impl Into<String>) -> Self {
+        Self::new(
+            CodegenErrorKind::Object {
+                message: message.into(),
+            },
+            Span::default(),
+        )
+    }
+
+    /// An error compiling a program into memory, which isn't about any code.
+    pub fn jit(message: impl Into<String>) -> Self {
+        Self::new(
+            CodegenErrorKind::Jit {
+                message: message.into(),
+            },
+            Span::default(),
+        )
+    }
+
+    /// A stable code identifying the kind of error, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            CodegenErrorKind::Unsupported { .. } => "E0801",
+            CodegenErrorKind::Object { .. } => "E0802",
+            CodegenErrorKind::Jit { .. } => "E0803",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Codegen errors are about the code being compiled, not a declaration.
+    pub fn declared(&self) -> Option<Span> {
+        None
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            CodegenErrorKind::Unsupported { backend, what } => {
+                format!("The {} backend doesn't support {} yet", backend, what)
+            }
+            CodegenErrorKind::Object { message } => {
+                format!("Couldn't write the object file: {}", message)
+            }
+            CodegenErrorKind::Jit { message } => {
+                format!("Couldn't compile the program to run it: {}", message)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.", self.message())
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Why a compiled program stops, where the bytecode VM would stop it with a
+/// runtime error.
+pub enum Failure {
+    /// The result of arithmetic doesn't fit its type
+    Overflow(IntTy),
+    DivisionByZero,
+    /// An index isn't less than the length of what it indexes
+    OutOfBounds,
+    /// An `assert` failed, with its message if it has one
+    AssertionFailed(Option<String>),
+}
+
+impl Failure {
+    /// The message the program stops with, which is the VM's, besides an
+    /// index out of bounds, whose index and length aren't kept to print.
+    pub fn message(&self) -> String {
+        let kind = match self {
+            Failure::Overflow(ty) => RuntimeErrorKind::Overflow { ty: *ty },
+            Failure::DivisionByZero => RuntimeErrorKind::DivisionByZero,
+            Failure::OutOfBounds => return "An index is out of bounds".to_string(),
+            Failure::AssertionFailed(message) => RuntimeErrorKind::AssertionFailed {
+                message: message.clone(),
+            },
+        };
+
+        RuntimeError::new(kind, Span::default()).message()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A step from a value to a part of it that a pattern checks.
+pub enum Step {
+    /// An element of a tuple, or of the payload of a variant
+    Element(usize),
+    /// The value of an optional
+    Unwrap,
+    /// The payload of the variant of an enum at a position
+    Payload(usize),
+}
+
+impl Step {
+    /// The position of the part in what `Layout::elements` returns for the
+    /// type of the value it is taken from.
+    pub fn position(self) -> usize {
+        match self {
+            Step::Element(position) => position,
+            Step::Unwrap => 1,
+            Step::Payload(position) => position + 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckKind<'a> {
+    /// Bind the part to a local, which always matches
+    Bind(LocalId),
+    Bool(bool),
+    Int(i128),
+    Str(&'a str),
+    /// The optional has a value
+    Some,
+    /// The optional has no value
+    None,
+    /// The enum is the variant at a position
+    Variant(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Something a pattern checks about a part of the value it is matched
+/// against.
+pub struct Check<'a> {
+    pub kind: CheckKind<'a>,
+    /// The steps from the value to the part
+    pub path: Vec<Step>,
+    /// The type of the part
+    pub ty: Ty,
+}
+
+/// The structs and enums of a program by name, to tell what the values of
+/// a type are made of.
+pub struct Layout<'a> {
+    program: &'a hir::Program,
+    /// The position of each struct, by name
+    structs: HashMap<&'a str, usize>,
+    /// The position of each enum, by name
+    enums: HashMap<&'a str, usize>,
+}
+
+impl<'a> Layout<'a> {
+    pub fn new(program: &'a hir::Program) -> Self {
+        Self {
+            program,
+            structs: program
+                .structs
+                .iter()
+                .enumerate()
+                .map(|(i, structure)| (structure.name.as_str(), i))
+                .collect(),
+            enums: program
+                .enums
+                .iter()
+                .enumerate()
+                .map(|(i, enumeration)| (enumeration.name.as_str(), i))
+                .collect(),
+        }
+    }
+
+    /// The types of the values in an aggregate, in order. An enum is its tag
+    /// followed by the payload of each variant as a tuple, and an optional is
+    /// whether it has a value followed by the value.
+    pub fn elements(&self, ty: &Ty) -> Vec<Ty> {
+        match ty {
+            Ty::Tuple(types) => types.clone(),
+            Ty::Array {
+                element,
+                length: Some(length),
+            } => vec![(**element).clone(); *length],
+            Ty::Struct(name, args) => {
+                let Some(structure) = self.structure(name) else {
+                    return Vec::new();
+                };
+
+                let bindings: HashMap<_, _> = structure
+                    .generics
+                    .iter()
+                    .cloned()
+                    .zip(args.iter().cloned())
+                    .collect();
+
+                structure
+                    .fields
+                    .iter()
+                    .map(|(_, ty)| ty.substitute(&bindings))
+                    .collect()
+            }
+            Ty::Enum(name) => {
+                let Some(variants) = self.variants(name) else {
+                    return Vec::new();
+                };
+
+                let payloads = variants
+                    .iter()
+                    .map(|(_, payload)| Ty::Tuple(payload.clone()));
+
+                std::iter::once(Ty::Int(IntTy::U32))
+                    .chain(payloads)
+                    .collect()
+            }
+            Ty::Optional(value) => vec![Ty::Bool, (**value).clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn structure(&self, name: &str) -> Option<&'a hir::Struct> {
+        let &position = self.structs.get(name)?;
+
+        Some(&self.program.structs[position])
+    }
+
+    /// The variants of an enum, and the types of their payloads.
+    pub fn variants(&self, name: &str) -> Option<&'a [(String, Vec<Ty>)]> {
+        let &position = self.enums.get(name)?;
+
+        Some(&self.program.enums[position].variants)
+    }
+
+    /// The position of a variant of an enum, and the types of its payload.
+    pub fn variant(&self, enum_name: &str, variant: &str) -> Option<(usize, Vec<Ty>)> {
+        self.variants(enum_name)?
+            .iter()
+            .enumerate()
+            .find(|(_, (name, _))| name == variant)
+            .map(|(position, (_, payload))| (position, payload.clone()))
+    }
+
+    /// The position of a field of a struct, or an element of a tuple.
+    pub fn field(&self, ty: &Ty, field: &str) -> Option<u32> {
+        match ty {
+            Ty::Struct(name, _) => self
+                .structure(name)?
+                .fields
+                .iter()
+                .position(|(name, _)| name == field)
+                .map(|position| position as u32),
+            Ty::Tuple(_) => field.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// What matching a pattern against a value of a type checks, in the
+    /// order it is checked. Whether an optional has a value, or an enum is
+    /// a variant, is checked before anything in it is taken, and a pattern
+    /// matches if every check does. The arms of a match are tried in order,
+    /// each going on to the next when one of its checks fails.
+    ///
+    /// Returns the enum and the variant if a pattern names a variant that
+    /// isn't in the program.
+    pub fn checks<'p>(
+        &self,
+        pattern: &'p Pattern,
+        ty: &Ty,
+    ) -> Result<Vec<Check<'p>>, (&'p str, &'p str)> {
+        let mut checks = Vec::new();
+
+        self.check(pattern, ty, &mut Vec::new(), &mut checks)?;
+
+        Ok(checks)
+    }
+
+    fn check<'p>(
+        &self,
+        pattern: &'p Pattern,
+        ty: &Ty,
+        path: &mut Vec<Step>,
+        checks: &mut Vec<Check<'p>>,
+    ) -> Result<(), (&'p str, &'p str)> {
+        let kind = match pattern {
+            Pattern::Wildcard => return Ok(()),
+            Pattern::Binding(local) => CheckKind::Bind(*local),
+            Pattern::Bool(value) => CheckKind::Bool(*value),
+            Pattern::Int(value) => CheckKind::Int(*value),
+            Pattern::Str(string) => CheckKind::Str(string),
+            Pattern::Tuple(patterns) => {
+                return self.check_all(patterns, &self.elements(ty), path, checks);
+            }
+            Pattern::Some(inner) => {
+                let inner_ty = match ty {
+                    Ty::Optional(inner) => (**inner).clone(),
+                    _ => Ty::Unknown,
+                };
+
+                checks.push(Check {
+                    kind: CheckKind::Some,
+                    path: path.clone(),
+                    ty: ty.clone(),
+                });
+
+                path.push(Step::Unwrap);
+                self.check(inner, &inner_ty, path, checks)?;
+                path.pop();
+
+                return Ok(());
+            }
+            Pattern::None => CheckKind::None,
+            Pattern::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                let Some((position, types)) = self.variant(enum_name, variant) else {
+                    return Err((enum_name, variant));
+                };
+
+                checks.push(Check {
+                    kind: CheckKind::Variant(position),
+                    path: path.clone(),
+                    ty: ty.clone(),
+                });
+
+                path.push(Step::Payload(position));
+                self.check_all(payload, &types, path, checks)?;
+                path.pop();
+
+                return Ok(());
+            }
+        };
+
+        checks.push(Check {
+            kind,
+            path: path.clone(),
+            ty: ty.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// The checks of the patterns matched against the elements of a value.
+    fn check_all<'p>(
+        &self,
+        patterns: &'p [Pattern],
+        types: &[Ty],
+        path: &mut Vec<Step>,
+        checks: &mut Vec<Check<'p>>,
+    ) -> Result<(), (&'p str, &'p str)> {
+        for (position, pattern) in patterns.iter().enumerate() {
+            let ty = types.get(position).cloned().unwrap_or(Ty::Unknown);
+
+            path.push(Step::Element(position));
+            self.check(pattern, &ty, path, checks)?;
+            path.pop();
+        }
+
+        Ok(())
+    }
+}
+
+/// The value of the left side of `&&` or `||` which is the result without
+/// evaluating the right side, which is only evaluated when it is needed.
+/// Both sides of every other operator are evaluated.
+pub fn short_circuit(op: BinaryOp) -> Option<bool> {
+    match op {
+        BinaryOp::And => Some(false),
+        BinaryOp::Or => Some(true),
+        _ => None,
+    }
+}
+
+/// The deferred blocks to run when leaving the blocks that deferred them,
+/// innermost first, and the last deferred first in each. A block runs its
+/// own at its end, and `return`, `break` and `continue` run those of every
+/// block they leave.
+pub fn unwinding<T: Clone>(scopes: &[Vec<T>]) -> Vec<T> {
+    scopes
+        .iter()
+        .rev()
+        .flat_map(|scope| scope.iter().rev().cloned())
+        .collect()
+}
+
+/// The position of the function a program starts at, if it has one.
+pub fn entry_point(program: &hir::Program) -> Option<usize> {
+    program
+        .functions
+        .iter()
+        .position(|function| function.name == "main" && function.args.is_empty())
+}
+
+/// The message of a runtime error, starting with where the code that
+/// failed is when it can be located in `sources`.
+pub fn runtime_error(sources: Option<&SourceMap>, span: Span, failure: &Failure) -> String {
+    let message = failure.message();
+
+    match sources.and_then(|sources| sources.locate(span)) {
+        Some((source, span)) => {
+            let (line, column) = source.line_column(span.start);
+
+            format!("[{}:{}:{}] {}", source.name(), line, column, message)
+        }
+        None => message,
+    }
+}
+
+/// The integer type arithmetic is done in. Values of unknown types are
+/// treated as `i64`s.
+pub fn int(ty: &Ty) -> IntTy {
+    match ty {
+        Ty::Int(ty) => *ty,
+        _ => IntTy::I64,
+    }
+}
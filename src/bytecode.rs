@@ -0,0 +1,395 @@
+//! Bytecode, which the HIR of a program is compiled to so it can be run by a
+//! stack-based virtual machine.
+//!
+//! Each function is a list of instructions working on a stack of values and
+//! the slots of the function's frame. Locals are slots, by the position of
+//! their `LocalId`, and the values of functions and closures are values too,
+//! so calling one is pushing it, pushing its arguments and calling.
+//!
+//! Running bytecode is a loop over instructions that are a few bytes each,
+//! instead of a walk over a tree that follows a pointer for every node, so it
+//! is a lot faster. Every instruction knows where in the source it came
+//! from, so errors while running point at the code that caused them.
+//!
+//! - `compile` turns a lowered program into bytecode.
+//! - `vm` runs it.
+//! - `disassemble` writes it as text, one instruction per line, for
+//!   `--emit bytecode`.
+use crate::arithmetic::Overflow;
+use crate::lexer::Span;
+use crate::typeck::IntTy;
+use std::fmt;
+use std::rc::Rc;
+
+pub mod compile;
+pub mod disassemble;
+pub mod vm;
+
+pub use compile::compile;
+pub use disassemble::disassemble;
+pub use vm::run;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An instruction. Instructions pop what they use from the stack, and push
+/// what they make. Jumps are to positions in the function's code.
+pub enum Instr {
+    /// Push a constant of the function
+    Const(u32),
+    /// Push `()`
+    Unit,
+    /// Pop a value, throwing it away
+    Pop,
+    /// Pop `n` values, throwing them away
+    Drop(u32),
+    /// Push the value of a slot
+    Load(u32),
+    /// Pop a value into a slot
+    Store(u32),
+    /// Push the value of a slot, leaving `()` in it, so the value can be
+    /// changed without being copied before it is stored back
+    Take(u32),
+    /// Push the value on top of the stack again
+    Dup,
+    LoadGlobal(u32),
+    StoreGlobal(u32),
+    /// Pop `n` values into a tuple, the first popped being the last element,
+    /// or push `()` if `n` is 0
+    Tuple(u32),
+    /// Pop `n` values into an array
+    Array(u32),
+    /// Pop the fields of a struct, in the order they are declared
+    Struct(u32),
+    /// Pop the payload of a variant of an enum, by their positions
+    Variant {
+        enumeration: u32,
+        variant: u32,
+    },
+    /// Push the element of a tuple, the field of a struct or the value in
+    /// the payload of a variant at a position
+    Element(u32),
+    /// Pop a value and a tuple or struct, pushing the tuple or struct with
+    /// the value as the element at a position
+    SetElement(u32),
+    /// Pop an index and an array or string, pushing the element or byte at
+    /// the index
+    Index,
+    /// Pop an index, a value and an array, pushing the array with the value
+    /// at the index
+    SetIndex,
+    /// Push the length in bytes of a string
+    Len,
+    Neg(IntTy),
+    Not,
+    /// Integer arithmetic, which fails if the result doesn't fit the type
+    Add(IntTy),
+    Sub(IntTy),
+    Mul(IntTy),
+    Div(IntTy),
+    Rem(IntTy),
+    /// Join two strings
+    Concat,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Convert an integer or `bool` to an integer type
+    Cast(IntTy),
+    Jump(u32),
+    /// Pop a `bool`, jumping if it is `false`
+    JumpIfFalse(u32),
+    /// Pop a value, pushing whether it is the variant at a position
+    IsVariant(u32),
+    /// Pop an optional, pushing whether it has a value
+    IsSome,
+    /// Pop an optional with a value, pushing the value
+    Unwrap,
+    /// Pop the values a function captures, pushing a closure of it
+    Closure {
+        function: u32,
+        captures: u32,
+    },
+    /// Pop `n` arguments and a function or closure, calling it
+    Call(u32),
+    /// Pop `n` arguments, calling the function at a position
+    CallDirect {
+        function: u32,
+        args: u32,
+    },
+    /// Pop a value, returning it from the function
+    Return,
+    /// Pop a `bool`, failing with the message in a constant if it is
+    /// `false`, or without one if the constant is `()`
+    Assert(u32),
+    /// Fail, since the name in a constant isn't available when running,
+    /// e.g. a function imported from another file
+    Unavailable(u32),
+    /// Fail, since no arm of a match matched
+    Unreachable,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A value on the stack or in a slot. Tuples, arrays, structs and variants
+/// share their elements until one of them is changed, when it is copied, so
+/// passing them around is cheap but they still behave as values.
+pub enum Value {
+    /// `()`, which is kept apart from other tuples so it doesn't have to be
+    /// allocated
+    Unit,
+    Int(i128),
+    Bool(bool),
+    Str(Rc<str>),
+    Tuple(Rc<Vec<Value>>),
+    Array(Rc<Vec<Value>>),
+    /// A struct, by its position in the program, and its fields
+    Struct(u32, Rc<Vec<Value>>),
+    Variant {
+        enumeration: u32,
+        variant: u32,
+        payload: Rc<Vec<Value>>,
+    },
+    Optional(Option<Rc<Value>>),
+    /// A function, by its position in the program
+    Function(u32),
+    /// A function, and the values of the locals it captures
+    Closure(Rc<(u32, Vec<Value>)>),
+    Native(Native),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A function the virtual machine provides, since it can't be written in
+/// Penguin itself.
+pub enum Native {
+    /// `print` from `import "std.print";`, which writes a value on a line
+    Print,
+}
+
+impl Native {
+    /// The native function an imported name refers to, if there is one.
+    pub fn from_name(name: &str) -> Option<Native> {
+        match name {
+            "print" => Some(Native::Print),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Native::Print => "print",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A compiled program.
+pub struct Program {
+    /// The functions of the lowered program at the same positions, followed
+    /// by the functions computing the values of globals, and then closures
+    pub functions: Vec<Function>,
+    /// The globals, which are set in order before `main` is called
+    pub globals: Vec<Global>,
+    /// The name and fields of each struct, for showing values
+    pub structs: Vec<(String, Vec<String>)>,
+    /// The name of each enum, and the name and payload length of each of
+    /// its variants
+    pub enums: Vec<(String, Vec<(String, usize)>)>,
+    /// The position of `main`, if there is one
+    pub main: Option<u32>,
+    /// What arithmetic that doesn't fit its type does
+    pub overflow: Overflow,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// The slots the arguments are stored in, in order. A closure's
+    /// captures come before its parameters.
+    pub params: Vec<u32>,
+    /// The name of each slot, for the disassembler. Slots the compiler uses
+    /// for values it needs later start with `$`.
+    pub slots: Vec<String>,
+    pub code: Vec<Instr>,
+    /// Where in the source each instruction came from
+    pub spans: Vec<Span>,
+    pub constants: Vec<Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Global {
+    pub name: String,
+    /// The position of the function computing its value
+    pub init: u32,
+}
+
+impl Program {
+    /// A value as it is written in the source, e.g. `Point { x: 1, y: 2 }`.
+    pub fn show(&self, value: &Value) -> String {
+        let list = |values: &[Value]| {
+            values
+                .iter()
+                .map(|value| self.show(value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match value {
+            Value::Unit => "()".to_string(),
+            Value::Int(int) => int.to_string(),
+            Value::Bool(value) => value.to_string(),
+            Value::Str(string) => format!("{:?}", string),
+            Value::Tuple(values) if values.len() == 1 => format!("({},)", list(values)),
+            Value::Tuple(values) => format!("({})", list(values)),
+            Value::Array(values) => format!("[{}]", list(values)),
+            Value::Struct(id, values) => {
+                let (name, fields) = &self.structs[*id as usize];
+
+                let fields: Vec<_> = fields
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(field, value)| format!("{}: {}", field, self.show(value)))
+                    .collect();
+
+                format!("{} {{ {} }}", name, fields.join(", "))
+            }
+            Value::Variant {
+                enumeration,
+                variant,
+                payload,
+            } => {
+                let (name, variants) = &self.enums[*enumeration as usize];
+                let (variant, _) = &variants[*variant as usize];
+
+                if payload.is_empty() {
+                    format!("{}::{}", name, variant)
+                } else {
+                    format!("{}::{}({})", name, variant, list(payload))
+                }
+            }
+            Value::Optional(Some(value)) => format!("Some({})", self.show(value)),
+            Value::Optional(None) => "None".to_string(),
+            Value::Function(function) => {
+                format!("<func {}>", self.functions[*function as usize].name)
+            }
+            Value::Closure(closure) => {
+                format!("<closure {}>", self.functions[closure.0 as usize].name)
+            }
+            Value::Native(native) => format!("<func {}>", native.name()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    DivisionByZero,
+    /// The result of arithmetic doesn't fit its type.
+    Overflow {
+        ty: IntTy,
+    },
+    IndexOutOfBounds {
+        index: i128,
+        length: usize,
+    },
+    /// An `assert` failed, with its message if it has one.
+    AssertionFailed {
+        message: Option<String>,
+    },
+    /// A name isn't available when running, e.g. a function imported from a
+    /// file that isn't compiled with the program.
+    Unavailable {
+        name: String,
+    },
+    /// Functions called each other too deeply.
+    StackOverflow {
+        depth: usize,
+    },
+    /// No arm of a match matched, which the type checker should have
+    /// prevented.
+    NoMatch,
+    /// There is no `main` function to run.
+    NoMain,
+    /// A value wasn't what an instruction works on, which can only happen
+    /// with values whose type wasn't known, like what an import returns.
+    Mismatch {
+        expected: String,
+        found: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An error that stopped a program while it was running.
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    /// The code that was running
+    pub span: Span,
+}
+
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// A stable code identifying the kind of error, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            RuntimeErrorKind::DivisionByZero => "E0701",
+            RuntimeErrorKind::Overflow { .. } => "E0702",
+            RuntimeErrorKind::IndexOutOfBounds { .. } => "E0703",
+            RuntimeErrorKind::AssertionFailed { .. } => "E0704",
+            RuntimeErrorKind::Unavailable { .. } => "E0705",
+            RuntimeErrorKind::StackOverflow { .. } => "E0706",
+            RuntimeErrorKind::NoMatch => "E0707",
+            RuntimeErrorKind::NoMain => "E0708",
+            RuntimeErrorKind::Mismatch { .. } => "E0709",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Runtime errors are about the code that was running, not a
+    /// declaration.
+    pub fn declared(&self) -> Option<Span> {
+        None
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            RuntimeErrorKind::DivisionByZero => "Division by zero".to_string(),
+            RuntimeErrorKind::Overflow { ty } => {
+                format!("The result doesn't fit in {}", ty.name())
+            }
+            RuntimeErrorKind::IndexOutOfBounds { index, length } => format!(
+                "Index {} is out of bounds for a length of {}",
+                index, length
+            ),
+            RuntimeErrorKind::AssertionFailed {
+                message: Some(message),
+            } => {
+                format!("Assertion failed: {}", message)
+            }
+            RuntimeErrorKind::AssertionFailed { message: None } => "Assertion failed".to_string(),
+            RuntimeErrorKind::Unavailable { name } => {
+                format!("'{}' isn't available when running the program", name)
+            }
+            RuntimeErrorKind::StackOverflow { depth } => {
+                format!("Functions were called more than {} deep", depth)
+            }
+            RuntimeErrorKind::NoMatch => "No arm of the match matched".to_string(),
+            RuntimeErrorKind::NoMain => "There is no 'main' function to run".to_string(),
+            RuntimeErrorKind::Mismatch { expected, found } => {
+                format!("Expected {}, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.", self.message())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
@@ -0,0 +1,950 @@
+use super::{Function, Global, Instr, Native, Program, Value};
+use crate::backend::{self, CheckKind, Layout};
+use crate::hir::{self, Arm, Block, Expr, ExprKind, Local, LocalId, Pattern, Stmt};
+use crate::lexer::Span;
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use crate::typeck::{IntTy, Ty};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Compile a lowered program to bytecode.
+pub fn compile(program: &hir::Program) -> Program {
+    let mut compiler = Compiler {
+        structs: program
+            .structs
+            .iter()
+            .enumerate()
+            .map(|(i, structure)| (structure.name.as_str(), i as u32))
+            .collect(),
+        enums: program
+            .enums
+            .iter()
+            .enumerate()
+            .map(|(i, enumeration)| (enumeration.name.as_str(), i as u32))
+            .collect(),
+        hir: program,
+        layout: Layout::new(program),
+        functions: vec![Function::default(); program.functions.len() + program.globals.len()],
+        builder: Builder::default(),
+    };
+
+    for (i, function) in program.functions.iter().enumerate() {
+        compiler.functions[i] = compiler.function(
+            function.full_name(),
+            &function.locals,
+            &function.params,
+            &function.body,
+        );
+    }
+
+    let mut globals = Vec::new();
+
+    for (i, global) in program.globals.iter().enumerate() {
+        let init = program.functions.len() + i;
+
+        compiler.functions[init] =
+            compiler.function(global.name.clone(), &global.locals, &[], &global.value);
+
+        globals.push(Global {
+            name: global.name.clone(),
+            init: init as u32,
+        });
+    }
+
+    let main = program
+        .functions
+        .iter()
+        .position(|function| function.name == "main" && function.args.is_empty())
+        .map(|position| position as u32);
+
+    Program {
+        functions: compiler.functions,
+        globals,
+        structs: program
+            .structs
+            .iter()
+            .map(|structure| {
+                let fields = structure.fields.iter().map(|(name, _)| name.clone());
+
+                (structure.name.clone(), fields.collect())
+            })
+            .collect(),
+        enums: program
+            .enums
+            .iter()
+            .map(|enumeration| {
+                let variants = enumeration
+                    .variants
+                    .iter()
+                    .map(|(name, payload)| (name.clone(), payload.len()));
+
+                (enumeration.name.clone(), variants.collect())
+            })
+            .collect(),
+        main,
+        overflow: program.overflow,
+    }
+}
+
+struct Compiler<'a> {
+    hir: &'a hir::Program,
+    layout: Layout<'a>,
+    /// The position of each struct, by name
+    structs: HashMap<&'a str, u32>,
+    /// The position of each enum, by name
+    enums: HashMap<&'a str, u32>,
+    functions: Vec<Function>,
+    /// The function being compiled
+    builder: Builder,
+}
+
+#[derive(Default)]
+/// A function being compiled.
+struct Builder {
+    function: Function,
+    /// How many locals the lowered function has, which are the first slots
+    locals: usize,
+    /// How many values are on the stack at the instruction being compiled,
+    /// as far as the function is concerned
+    depth: usize,
+    /// The node being compiled, which instructions come from
+    span: Span,
+    /// The deferred blocks of each block being compiled, innermost last
+    scopes: Vec<Vec<Block>>,
+    /// The loops being compiled, innermost last
+    loops: Vec<Loop>,
+}
+
+struct Loop {
+    /// Where the body starts, which `continue` jumps to
+    start: usize,
+    /// The jumps of each `break`, to the end of the loop
+    breaks: Vec<usize>,
+    /// The depth of the stack when the loop started
+    depth: usize,
+    /// How many blocks were being compiled when the loop started
+    scopes: usize,
+}
+
+/// A step from a value to a value in it, when assigning to it or matching a
+/// pattern against it.
+#[derive(Clone, Copy)]
+enum Step {
+    Element(u32),
+    /// An element of an array, at the index in a slot
+    Index(u32),
+    /// The value in an optional
+    Unwrap,
+}
+
+/// What an assignment is to, before the steps into it.
+enum Root {
+    Local(u32),
+    Global(u32),
+    /// Something that isn't stored anywhere, so the assignment is lost
+    Temporary,
+}
+
+impl<'a> Compiler<'a> {
+    /// Compile a function or the value of a global, with its locals and the
+    /// ones it is called with.
+    fn function(
+        &mut self,
+        name: String,
+        locals: &[Local],
+        params: &[LocalId],
+        body: &Expr,
+    ) -> Function {
+        let slots = locals.iter().map(|local| local.name.clone()).collect();
+        let params = params.iter().map(|param| param.index() as u32).collect();
+
+        self.body(name, slots, params, body)
+    }
+
+    /// Compile a function to a new builder, with the names of its slots and
+    /// the slots of its arguments.
+    fn body(
+        &mut self,
+        name: String,
+        slots: Vec<String>,
+        params: Vec<u32>,
+        body: &Expr,
+    ) -> Function {
+        let builder = Builder {
+            locals: slots.len(),
+            function: Function {
+                name,
+                params,
+                slots,
+                ..Function::default()
+            },
+            span: body.span,
+            ..Builder::default()
+        };
+
+        let outer = std::mem::replace(&mut self.builder, builder);
+
+        self.expr(body);
+        self.emit(Instr::Return);
+
+        std::mem::replace(&mut self.builder, outer).function
+    }
+
+    /// Add an instruction, keeping track of the depth of the stack, and
+    /// returning where it is.
+    fn emit(&mut self, instr: Instr) -> usize {
+        let (pops, pushes) = match instr {
+            Instr::Const(_)
+            | Instr::Unit
+            | Instr::Load(_)
+            | Instr::Take(_)
+            | Instr::Dup
+            | Instr::LoadGlobal(_)
+            | Instr::Unavailable(_) => (0, 1),
+            Instr::Pop
+            | Instr::Store(_)
+            | Instr::StoreGlobal(_)
+            | Instr::JumpIfFalse(_)
+            | Instr::Assert(_)
+            | Instr::Return => (1, 0),
+            Instr::Drop(n) => (n as usize, 0),
+            Instr::Tuple(n) | Instr::Array(n) => (n as usize, 1),
+            Instr::Struct(id) => (self.hir.structs[id as usize].fields.len(), 1),
+            Instr::Variant {
+                enumeration,
+                variant,
+            } => (
+                self.hir.enums[enumeration as usize].variants[variant as usize]
+                    .1
+                    .len(),
+                1,
+            ),
+            Instr::Element(_)
+            | Instr::Len
+            | Instr::Neg(_)
+            | Instr::Not
+            | Instr::Cast(_)
+            | Instr::IsVariant(_)
+            | Instr::IsSome
+            | Instr::Unwrap => (1, 1),
+            Instr::SetElement(_)
+            | Instr::Index
+            | Instr::Add(_)
+            | Instr::Sub(_)
+            | Instr::Mul(_)
+            | Instr::Div(_)
+            | Instr::Rem(_)
+            | Instr::Concat
+            | Instr::Eq
+            | Instr::Ne
+            | Instr::Lt
+            | Instr::Le
+            | Instr::Gt
+            | Instr::Ge => (2, 1),
+            Instr::SetIndex => (3, 1),
+            Instr::Closure { captures, .. } => (captures as usize, 1),
+            Instr::Call(args) => (args as usize + 1, 1),
+            Instr::CallDirect { args, .. } => (args as usize, 1),
+            Instr::Jump(_) | Instr::Unreachable => (0, 0),
+        };
+
+        let builder = &mut self.builder;
+
+        builder.depth = builder.depth.saturating_sub(pops) + pushes;
+        builder.function.code.push(instr);
+        builder.function.spans.push(builder.span);
+
+        builder.function.code.len() - 1
+    }
+
+    /// Point the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let here = self.builder.function.code.len() as u32;
+
+        match &mut self.builder.function.code[at] {
+            Instr::Jump(target) | Instr::JumpIfFalse(target) => *target = here,
+            instr => unreachable!("Only jumps can be patched, not {:?}", instr),
+        }
+    }
+
+    /// The position of a constant, adding it if the function doesn't have it.
+    fn constant(&mut self, value: Value) -> u32 {
+        let constants = &mut self.builder.function.constants;
+
+        let position = constants
+            .iter()
+            .position(|constant| *constant == value)
+            .unwrap_or_else(|| {
+                constants.push(value);
+
+                constants.len() - 1
+            });
+
+        position as u32
+    }
+
+    /// A slot the lowered function doesn't have, for a value the compiled
+    /// code needs later.
+    fn scratch(&mut self) -> u32 {
+        let slots = &mut self.builder.function.slots;
+
+        slots.push(format!("${}", slots.len()));
+
+        (slots.len() - 1) as u32
+    }
+
+    /// Compile an expression, which leaves its value on the stack. Code that
+    /// never finishes, like a `break`, leaves a value that is never there
+    /// too, so the code after it is compiled as if it did.
+    fn expr(&mut self, expr: &Expr) {
+        let outer = std::mem::replace(&mut self.builder.span, expr.span);
+
+        self.expr_kind(expr);
+
+        self.builder.span = outer;
+    }
+
+    fn expr_kind(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Int(int) => self.push(Value::Int(*int)),
+            ExprKind::Bool(value) => self.push(Value::Bool(*value)),
+            ExprKind::Str(string) => self.push(Value::Str(Rc::from(string.as_str()))),
+            ExprKind::Local(local) => {
+                self.emit(Instr::Load(local.index() as u32));
+            }
+            ExprKind::Function(position) => self.push(Value::Function(*position as u32)),
+            ExprKind::Global(position) => {
+                self.emit(Instr::LoadGlobal(*position as u32));
+            }
+            ExprKind::Import(name) => match Native::from_name(name) {
+                Some(native) => self.push(Value::Native(native)),
+                None => self.unavailable(name),
+            },
+            ExprKind::Tuple(elements) => {
+                self.exprs(elements);
+                self.emit(Instr::Tuple(elements.len() as u32));
+            }
+            ExprKind::Array(elements) => {
+                self.exprs(elements);
+                self.emit(Instr::Array(elements.len() as u32));
+            }
+            ExprKind::Struct { name, fields } => self.structure(name, fields),
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                let Some((enumeration, position)) = self.variant(enum_name, variant) else {
+                    return self.unavailable(&format!("{}::{}", enum_name, variant));
+                };
+
+                self.exprs(payload);
+                self.emit(Instr::Variant {
+                    enumeration,
+                    variant: position,
+                });
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Unary { op, operand } => {
+                self.expr(operand);
+
+                match op {
+                    UnaryOp::Negate => self.emit(Instr::Neg(int(&expr.ty))),
+                    UnaryOp::Not => self.emit(Instr::Not),
+                };
+            }
+            ExprKind::Binary { op, left, right } => self.binary(*op, left, right, &expr.ty),
+            ExprKind::Cast(value) => {
+                self.expr(value);
+
+                if let Ty::Int(ty) = expr.ty {
+                    if value.ty != expr.ty {
+                        self.emit(Instr::Cast(ty));
+                    }
+                }
+            }
+            ExprKind::Call { callee, args } => {
+                if let ExprKind::Function(function) = callee.kind {
+                    self.exprs(args);
+                    self.emit(Instr::CallDirect {
+                        function: function as u32,
+                        args: args.len() as u32,
+                    });
+
+                    return;
+                }
+
+                self.expr(callee);
+                self.exprs(args);
+                self.emit(Instr::Call(args.len() as u32));
+            }
+            // Methods from other files aren't compiled with the program
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                if receiver.ty == Ty::Str && method == "len" && args.is_empty() {
+                    self.expr(receiver);
+                    self.emit(Instr::Len);
+                } else {
+                    self.unavailable(method);
+                }
+            }
+            ExprKind::Index { object, index } => {
+                self.expr(object);
+                self.expr(index);
+                self.emit(Instr::Index);
+            }
+            ExprKind::Field { object, field } => {
+                self.expr(object);
+
+                match self.field(&object.ty, field) {
+                    Some(position) => {
+                        self.emit(Instr::Element(position));
+                    }
+                    None => {
+                        self.emit(Instr::Pop);
+                        self.unavailable(field);
+                    }
+                }
+            }
+            ExprKind::Assign { target, value } => {
+                self.assign(target, value);
+                self.emit(Instr::Unit);
+            }
+            ExprKind::Closure {
+                params,
+                captures,
+                body,
+            } => self.closure(params, captures, body),
+            ExprKind::Match { value, arms } => self.matching(value, arms),
+            ExprKind::Loop(body) => self.looping(body),
+            ExprKind::Break | ExprKind::Continue => self.leave_loop(&expr.kind),
+            ExprKind::Return(value) => {
+                let depth = self.builder.depth;
+
+                match value {
+                    Some(value) => self.expr(value),
+                    None => {
+                        self.emit(Instr::Unit);
+                    }
+                }
+
+                self.run_defers(0);
+                self.emit(Instr::Return);
+
+                self.builder.depth = depth + 1;
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    /// Push a constant.
+    fn push(&mut self, value: Value) {
+        let constant = self.constant(value);
+
+        self.emit(Instr::Const(constant));
+    }
+
+    /// Fail with a name that isn't available when running.
+    fn unavailable(&mut self, name: &str) {
+        let constant = self.constant(Value::Str(Rc::from(name)));
+
+        self.emit(Instr::Unavailable(constant));
+    }
+
+    /// The positions of an enum and one of its variants.
+    fn variant(&self, enum_name: &str, variant: &str) -> Option<(u32, u32)> {
+        let &enumeration = self.enums.get(enum_name)?;
+
+        let position = self.hir.enums[enumeration as usize]
+            .variants
+            .iter()
+            .position(|(name, _)| name == variant)?;
+
+        Some((enumeration, position as u32))
+    }
+
+    /// The position of a field in a struct of a type.
+    fn field(&self, ty: &Ty, field: &str) -> Option<u32> {
+        let Ty::Struct(name, _) = ty else {
+            return None;
+        };
+
+        let &structure = self.structs.get(name.as_str())?;
+
+        self.hir.structs[structure as usize]
+            .fields
+            .iter()
+            .position(|(name, _)| name == field)
+            .map(|position| position as u32)
+    }
+
+    /// Compile a struct literal, whose fields are evaluated in the order they
+    /// are written but stored in the order they are declared.
+    fn structure(&mut self, name: &str, fields: &[(String, Expr)]) {
+        let Some(&id) = self.structs.get(name) else {
+            return self.unavailable(name);
+        };
+
+        let declared = &self.hir.structs[id as usize].fields;
+
+        let in_order = fields.len() == declared.len()
+            && fields
+                .iter()
+                .zip(declared)
+                .all(|((field, _), (name, _))| field == name);
+
+        if in_order {
+            for (_, value) in fields {
+                self.expr(value);
+            }
+        } else {
+            let mut slots = HashMap::new();
+
+            for (field, value) in fields {
+                self.expr(value);
+
+                let slot = self.scratch();
+
+                self.emit(Instr::Store(slot));
+                slots.insert(field.as_str(), slot);
+            }
+
+            for (name, _) in declared {
+                match slots.get(name.as_str()) {
+                    Some(&slot) => self.emit(Instr::Load(slot)),
+                    None => self.emit(Instr::Unit),
+                };
+            }
+        }
+
+        self.emit(Instr::Struct(id));
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: &Expr, right: &Expr, ty: &Ty) {
+        if let Some(decided) = backend::short_circuit(op) {
+            self.expr(left);
+
+            let otherwise = self.emit(Instr::JumpIfFalse(0));
+            let depth = self.builder.depth;
+
+            match decided {
+                true => self.push(Value::Bool(true)),
+                false => self.expr(right),
+            }
+
+            let end = self.emit(Instr::Jump(0));
+
+            self.patch(otherwise);
+            self.builder.depth = depth;
+
+            match decided {
+                true => self.expr(right),
+                false => self.push(Value::Bool(false)),
+            }
+
+            return self.patch(end);
+        }
+
+        self.expr(left);
+        self.expr(right);
+
+        let instr = match op {
+            BinaryOp::Add if left.ty == Ty::Str => Instr::Concat,
+            BinaryOp::Add => Instr::Add(int(ty)),
+            BinaryOp::Subtract => Instr::Sub(int(ty)),
+            BinaryOp::Multiply => Instr::Mul(int(ty)),
+            BinaryOp::Divide => Instr::Div(int(ty)),
+            BinaryOp::Modulo => Instr::Rem(int(ty)),
+            BinaryOp::Equal => Instr::Eq,
+            BinaryOp::NotEqual => Instr::Ne,
+            BinaryOp::Less => Instr::Lt,
+            BinaryOp::LessEqual => Instr::Le,
+            BinaryOp::Greater => Instr::Gt,
+            BinaryOp::GreaterEqual => Instr::Ge,
+            BinaryOp::And | BinaryOp::Or => unreachable!("Short-circuited above"),
+        };
+
+        self.emit(instr);
+    }
+
+    /// Compile a block, running what it deferred once it has its value.
+    fn block(&mut self, block: &Block) {
+        self.builder.scopes.push(Vec::new());
+
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+
+        match &block.value {
+            Some(value) => self.expr(value),
+            None => {
+                self.emit(Instr::Unit);
+            }
+        }
+
+        let deferred = self.builder.scopes.pop().unwrap_or_default();
+
+        for body in backend::unwinding(&[deferred]) {
+            self.block(&body);
+            self.emit(Instr::Pop);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { local, value } => {
+                self.expr(value);
+                self.emit(Instr::Store(local.index() as u32));
+            }
+            Stmt::Assert { condition, message } => {
+                self.expr(condition);
+
+                let message = match message {
+                    Some(message) => Value::Str(Rc::from(message.as_str())),
+                    None => Value::Unit,
+                };
+                let constant = self.constant(message);
+
+                // A failed assertion points at its condition
+                let outer = std::mem::replace(&mut self.builder.span, condition.span);
+
+                self.emit(Instr::Assert(constant));
+
+                self.builder.span = outer;
+            }
+            Stmt::Defer(body) => {
+                if let Some(scope) = self.builder.scopes.last_mut() {
+                    scope.push(body.clone());
+                }
+            }
+            Stmt::Expr(expr) => {
+                self.expr(expr);
+                self.emit(Instr::Pop);
+            }
+        }
+    }
+
+    /// Run the deferred blocks of the blocks from `scope` in, dropping the
+    /// value of each.
+    fn run_defers(&mut self, scope: usize) {
+        for body in backend::unwinding(&self.builder.scopes[scope..]) {
+            self.block(&body);
+            self.emit(Instr::Pop);
+        }
+    }
+
+    fn looping(&mut self, body: &Block) {
+        let start = self.builder.function.code.len();
+        let depth = self.builder.depth;
+
+        self.builder.loops.push(Loop {
+            start,
+            breaks: Vec::new(),
+            depth,
+            scopes: self.builder.scopes.len(),
+        });
+
+        self.block(body);
+        self.emit(Instr::Pop);
+        self.emit(Instr::Jump(start as u32));
+
+        let finished = self.builder.loops.pop().expect("the loop being compiled");
+
+        for jump in finished.breaks {
+            self.patch(jump);
+        }
+
+        self.builder.depth = depth;
+        self.emit(Instr::Unit);
+    }
+
+    /// Compile a `break` or `continue`, which leaves the blocks in the loop
+    /// and the values on the stack since it started.
+    fn leave_loop(&mut self, kind: &ExprKind) {
+        let depth = self.builder.depth;
+
+        let Some(innermost) = self.builder.loops.last() else {
+            self.emit(Instr::Unreachable);
+            self.builder.depth = depth + 1;
+
+            return;
+        };
+
+        let (start, scopes, loop_depth) = (innermost.start, innermost.scopes, innermost.depth);
+
+        self.run_defers(scopes);
+
+        if depth > loop_depth {
+            self.emit(Instr::Drop((depth - loop_depth) as u32));
+        }
+
+        if let ExprKind::Break = kind {
+            let jump = self.emit(Instr::Jump(0));
+
+            if let Some(innermost) = self.builder.loops.last_mut() {
+                innermost.breaks.push(jump);
+            }
+        } else {
+            self.emit(Instr::Jump(start as u32));
+        }
+
+        self.builder.depth = depth + 1;
+    }
+
+    /// Compile a closure to a function of its own, whose arguments are the
+    /// values it captures followed by its parameters. It has the same slots
+    /// as the function it is in, so its locals are where they are there.
+    fn closure(&mut self, params: &[LocalId], captures: &[LocalId], body: &Expr) {
+        let name = format!("{}::{{closure}}", self.builder.function.name);
+        let slots = self.builder.function.slots[..self.builder.locals].to_vec();
+        let arguments = captures
+            .iter()
+            .chain(params)
+            .map(|local| local.index() as u32)
+            .collect();
+
+        let function = self.body(name, slots, arguments, body);
+
+        self.functions.push(function);
+
+        for capture in captures {
+            self.emit(Instr::Load(capture.index() as u32));
+        }
+
+        self.emit(Instr::Closure {
+            function: (self.functions.len() - 1) as u32,
+            captures: captures.len() as u32,
+        });
+    }
+
+    fn matching(&mut self, value: &Expr, arms: &[Arm]) {
+        let slot = match value.kind {
+            ExprKind::Local(local) => local.index() as u32,
+            _ => {
+                self.expr(value);
+
+                let slot = self.scratch();
+
+                self.emit(Instr::Store(slot));
+
+                slot
+            }
+        };
+
+        let depth = self.builder.depth;
+        let mut ends = Vec::new();
+
+        for arm in arms {
+            let mut fails = Vec::new();
+
+            self.builder.depth = depth;
+            self.pattern(&arm.pattern, &value.ty, slot, &mut fails);
+            self.expr(&arm.value);
+
+            ends.push(self.emit(Instr::Jump(0)));
+
+            for fail in fails {
+                self.patch(fail);
+            }
+        }
+
+        self.builder.depth = depth;
+        self.emit(Instr::Unreachable);
+
+        for end in ends {
+            self.patch(end);
+        }
+
+        self.builder.depth = depth + 1;
+    }
+
+    /// Compile a pattern matched against the value of a type in a slot,
+    /// binding what it binds. Each check that can fail jumps away, and is
+    /// added to `fails`.
+    fn pattern(&mut self, pattern: &Pattern, ty: &Ty, slot: u32, fails: &mut Vec<usize>) {
+        let Ok(checks) = self.layout.checks(pattern, ty) else {
+            self.emit(Instr::Unreachable);
+
+            return;
+        };
+
+        for check in checks {
+            let steps: Vec<_> = check
+                .path
+                .iter()
+                .filter_map(|step| match *step {
+                    backend::Step::Element(position) => Some(Step::Element(position as u32)),
+                    backend::Step::Unwrap => Some(Step::Unwrap),
+                    // The elements of a variant are the ones of its payload
+                    backend::Step::Payload(_) => None,
+                })
+                .collect();
+
+            self.load(slot, &steps);
+
+            match check.kind {
+                CheckKind::Bind(local) => {
+                    self.emit(Instr::Store(local.index() as u32));
+
+                    continue;
+                }
+                CheckKind::Bool(true) => {}
+                CheckKind::Bool(false) => {
+                    self.emit(Instr::Not);
+                }
+                CheckKind::Int(int) => {
+                    self.push(Value::Int(int));
+                    self.emit(Instr::Eq);
+                }
+                CheckKind::Str(string) => {
+                    self.push(Value::Str(Rc::from(string)));
+                    self.emit(Instr::Eq);
+                }
+                CheckKind::Some => {
+                    self.emit(Instr::IsSome);
+                }
+                CheckKind::None => {
+                    self.emit(Instr::IsSome);
+                    self.emit(Instr::Not);
+                }
+                CheckKind::Variant(position) => {
+                    self.emit(Instr::IsVariant(position as u32));
+                }
+            }
+
+            fails.push(self.emit(Instr::JumpIfFalse(0)));
+        }
+    }
+
+    /// Push the value in a slot, after taking steps into it.
+    fn load(&mut self, slot: u32, steps: &[Step]) {
+        self.emit(Instr::Load(slot));
+
+        for &step in steps {
+            self.step(step);
+        }
+    }
+
+    fn step(&mut self, step: Step) {
+        match step {
+            Step::Element(position) => self.emit(Instr::Element(position)),
+            Step::Index(slot) => {
+                self.emit(Instr::Load(slot));
+                self.emit(Instr::Index)
+            }
+            Step::Unwrap => self.emit(Instr::Unwrap),
+        };
+    }
+
+    /// Compile an assignment. The indexes in the target are evaluated first,
+    /// then the value, which is stored by changing each value on the way to
+    /// the target from the inside out, e.g. `a.b[i] = v` is `a = a with b =
+    /// (a.b with [i] = v)`.
+    fn assign(&mut self, target: &Expr, value: &Expr) {
+        let mut steps = Vec::new();
+        let root = self.place(target, &mut steps);
+
+        self.expr(value);
+
+        let Some((last, path)) = steps.split_last() else {
+            return self.store(&root);
+        };
+
+        let slot = self.scratch();
+
+        self.emit(Instr::Store(slot));
+
+        // The value is taken out of its local, so changing it doesn't copy
+        // it when nothing else has it
+        match root {
+            Root::Local(local) => self.emit(Instr::Take(local)),
+            Root::Global(global) => self.emit(Instr::LoadGlobal(global)),
+            Root::Temporary => self.emit(Instr::Unit),
+        };
+
+        for &step in path {
+            self.emit(Instr::Dup);
+            self.step(step);
+        }
+
+        self.emit(Instr::Load(slot));
+
+        for &step in std::iter::once(last).chain(path.iter().rev()) {
+            match step {
+                Step::Element(position) => self.emit(Instr::SetElement(position)),
+                Step::Index(index) => {
+                    self.emit(Instr::Load(index));
+                    self.emit(Instr::SetIndex)
+                }
+                Step::Unwrap => unreachable!("Optionals can't be assigned into"),
+            };
+        }
+
+        self.store(&root);
+    }
+
+    /// Find what an assignment target is in, and the steps into it,
+    /// evaluating its indexes into slots.
+    fn place(&mut self, target: &Expr, steps: &mut Vec<Step>) -> Root {
+        match &target.kind {
+            ExprKind::Local(local) => Root::Local(local.index() as u32),
+            ExprKind::Global(global) => Root::Global(*global as u32),
+            ExprKind::Field { object, field } => {
+                let root = self.place(object, steps);
+
+                match self.field(&object.ty, field) {
+                    Some(position) => steps.push(Step::Element(position)),
+                    None => {
+                        self.unavailable(field);
+                        self.emit(Instr::Pop);
+                    }
+                }
+
+                root
+            }
+            ExprKind::Index { object, index } => {
+                let root = self.place(object, steps);
+
+                self.expr(index);
+
+                let slot = self.scratch();
+
+                self.emit(Instr::Store(slot));
+                steps.push(Step::Index(slot));
+
+                root
+            }
+            _ => {
+                self.expr(target);
+                self.emit(Instr::Pop);
+
+                Root::Temporary
+            }
+        }
+    }
+
+    fn store(&mut self, root: &Root) {
+        match root {
+            Root::Local(local) => self.emit(Instr::Store(*local)),
+            Root::Global(global) => self.emit(Instr::StoreGlobal(*global)),
+            Root::Temporary => self.emit(Instr::Pop),
+        };
+    }
+}
+
+/// The integer type arithmetic is done in. Values of unknown types, like
+/// what an import returns, are treated as `i64`s.
+fn int(ty: &Ty) -> IntTy {
+    match ty {
+        Ty::Int(ty) => *ty,
+        _ => IntTy::I64,
+    }
+}
@@ -0,0 +1,141 @@
+use super::{Function, Instr, Program};
+use std::fmt::Write;
+
+/// Write a program as text, one function after another, e.g.
+///
+/// ```text
+/// func double(a) [a]
+///   0000  load 0          ; a
+///   0001  const 0         ; 2
+///   0002  mul u32
+///   0003  return
+/// ```
+pub fn disassemble(program: &Program) -> String {
+    let mut text = String::new();
+
+    for (i, global) in program.globals.iter().enumerate() {
+        let _ = writeln!(text, "global {} {}", i, global.name);
+    }
+
+    if !program.globals.is_empty() {
+        text.push('\n');
+    }
+
+    let functions: Vec<_> = program
+        .functions
+        .iter()
+        .map(|function| disassemble_function(program, function))
+        .collect();
+
+    text.push_str(&functions.join("\n"));
+
+    text
+}
+
+/// Write a function as text, with a header naming its parameters and
+/// slots, followed by its instructions.
+pub fn disassemble_function(program: &Program, function: &Function) -> String {
+    let slot = |slot: u32| function.slots[slot as usize].as_str();
+
+    let params: Vec<_> = function.params.iter().map(|&param| slot(param)).collect();
+
+    let mut text = format!(
+        "func {}({}) [{}]\n",
+        function.name,
+        params.join(", "),
+        function.slots.join(", ")
+    );
+
+    for (at, instr) in function.code.iter().enumerate() {
+        let (name, operand) = operands(instr);
+
+        let comment = match *instr {
+            Instr::Const(constant) | Instr::Assert(constant) | Instr::Unavailable(constant) => {
+                Some(program.show(&function.constants[constant as usize]))
+            }
+            Instr::Load(index) | Instr::Store(index) | Instr::Take(index) => {
+                Some(slot(index).to_string())
+            }
+            Instr::LoadGlobal(global) | Instr::StoreGlobal(global) => {
+                Some(program.globals[global as usize].name.clone())
+            }
+            Instr::Struct(id) => Some(program.structs[id as usize].0.clone()),
+            Instr::Variant {
+                enumeration,
+                variant,
+            } => {
+                let (name, variants) = &program.enums[enumeration as usize];
+
+                Some(format!("{}::{}", name, variants[variant as usize].0))
+            }
+            Instr::Closure { function, .. } | Instr::CallDirect { function, .. } => {
+                Some(program.functions[function as usize].name.clone())
+            }
+            _ => None,
+        };
+
+        let line = format!("{:04}  {} {}", at, name, operand);
+
+        let _ = match comment {
+            Some(comment) => writeln!(text, "  {:<22}; {}", line.trim_end(), comment),
+            None => writeln!(text, "  {}", line.trim_end()),
+        };
+    }
+
+    text
+}
+
+/// The name of an instruction, and its operands as text.
+fn operands(instr: &Instr) -> (&'static str, String) {
+    match *instr {
+        Instr::Const(constant) => ("const", constant.to_string()),
+        Instr::Unit => ("unit", String::new()),
+        Instr::Pop => ("pop", String::new()),
+        Instr::Drop(n) => ("drop", n.to_string()),
+        Instr::Load(slot) => ("load", slot.to_string()),
+        Instr::Store(slot) => ("store", slot.to_string()),
+        Instr::Take(slot) => ("take", slot.to_string()),
+        Instr::Dup => ("dup", String::new()),
+        Instr::LoadGlobal(global) => ("load_global", global.to_string()),
+        Instr::StoreGlobal(global) => ("store_global", global.to_string()),
+        Instr::Tuple(n) => ("tuple", n.to_string()),
+        Instr::Array(n) => ("array", n.to_string()),
+        Instr::Struct(id) => ("struct", id.to_string()),
+        Instr::Variant {
+            enumeration,
+            variant,
+        } => ("variant", format!("{} {}", enumeration, variant)),
+        Instr::Element(position) => ("element", position.to_string()),
+        Instr::SetElement(position) => ("set_element", position.to_string()),
+        Instr::Index => ("index", String::new()),
+        Instr::SetIndex => ("set_index", String::new()),
+        Instr::Len => ("len", String::new()),
+        Instr::Neg(ty) => ("neg", ty.name().to_string()),
+        Instr::Not => ("not", String::new()),
+        Instr::Add(ty) => ("add", ty.name().to_string()),
+        Instr::Sub(ty) => ("sub", ty.name().to_string()),
+        Instr::Mul(ty) => ("mul", ty.name().to_string()),
+        Instr::Div(ty) => ("div", ty.name().to_string()),
+        Instr::Rem(ty) => ("rem", ty.name().to_string()),
+        Instr::Concat => ("concat", String::new()),
+        Instr::Eq => ("eq", String::new()),
+        Instr::Ne => ("ne", String::new()),
+        Instr::Lt => ("lt", String::new()),
+        Instr::Le => ("le", String::new()),
+        Instr::Gt => ("gt", String::new()),
+        Instr::Ge => ("ge", String::new()),
+        Instr::Cast(ty) => ("cast", ty.name().to_string()),
+        Instr::Jump(target) => ("jump", format!("{:04}", target)),
+        Instr::JumpIfFalse(target) => ("jump_if_false", format!("{:04}", target)),
+        Instr::IsVariant(position) => ("is_variant", position.to_string()),
+        Instr::IsSome => ("is_some", String::new()),
+        Instr::Unwrap => ("unwrap", String::new()),
+        Instr::Closure { function, captures } => ("closure", format!("{} {}", function, captures)),
+        Instr::Call(args) => ("call", args.to_string()),
+        Instr::CallDirect { function, args } => ("call_direct", format!("{} {}", function, args)),
+        Instr::Return => ("return", String::new()),
+        Instr::Assert(message) => ("assert", message.to_string()),
+        Instr::Unavailable(name) => ("unavailable", name.to_string()),
+        Instr::Unreachable => ("unreachable", String::new()),
+    }
+}
@@ -0,0 +1,587 @@
+use super::{Function, Instr, Native, Program, RuntimeError, RuntimeErrorKind, Value};
+use crate::arithmetic::{self, ArithmeticError};
+use crate::lexer::Span;
+use crate::parser::ast::BinaryOp;
+use crate::runtime;
+use crate::typeck::IntTy;
+use std::io::Write;
+use std::rc::Rc;
+
+/// How deeply functions can call each other before the program is stopped,
+/// so a runaway recursion is an error instead of using all the memory.
+pub const MAX_DEPTH: usize = 10_000;
+
+/// Run a program, setting its globals and then calling `main`, and return
+/// what `main` returns. What the program prints is written to `output`.
+pub fn run(program: &Program, output: &mut dyn Write) -> Result<Value, RuntimeError> {
+    let mut vm = Vm::new(program, output);
+
+    for (i, global) in program.globals.iter().enumerate() {
+        vm.globals[i] = vm.call(global.init, Vec::new())?;
+    }
+
+    let main = program
+        .main
+        .ok_or_else(|| RuntimeError::new(RuntimeErrorKind::NoMain, Span::default()))?;
+
+    vm.call(main, Vec::new())
+}
+
+/// A virtual machine running a program.
+pub struct Vm<'a> {
+    program: &'a Program,
+    output: &'a mut dyn Write,
+    /// The values being worked on, by every function being run
+    stack: Vec<Value>,
+    /// The slots of every function being run, each after the one calling it
+    slots: Vec<Value>,
+    frames: Vec<Frame>,
+    globals: Vec<Value>,
+}
+
+/// A function being run.
+struct Frame {
+    function: u32,
+    /// The instruction to run when the function it called returns
+    pc: usize,
+    /// Where the function's slots start
+    slots: usize,
+    /// How many values were on the stack when it was called
+    stack: usize,
+}
+
+/// Fail with an error at the instruction being run.
+macro_rules! fail {
+    ($function:expr, $pc:expr, $kind:expr) => {
+        return Err(RuntimeError::new($kind, $function.spans[$pc - 1]))
+    };
+}
+
+/// Unwrap the result of an instruction, failing at it if it is an error.
+macro_rules! attempt {
+    ($function:expr, $pc:expr, $result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(kind) => fail!($function, $pc, kind),
+        }
+    };
+}
+
+impl<'a> Vm<'a> {
+    /// A machine for a program whose globals aren't set yet.
+    pub fn new(program: &'a Program, output: &'a mut dyn Write) -> Self {
+        Self {
+            program,
+            output,
+            stack: Vec::new(),
+            slots: Vec::new(),
+            frames: Vec::new(),
+            globals: vec![Value::Unit; program.globals.len()],
+        }
+    }
+
+    /// Call a function with its arguments, and return what it returns.
+    pub fn call(&mut self, function: u32, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let depth = self.frames.len();
+        let span = self.program.functions[function as usize]
+            .spans
+            .first()
+            .copied()
+            .unwrap_or_default();
+
+        let start = self.stack.len();
+
+        self.stack.extend(args);
+
+        if let Err(kind) = self.enter(function, start, &[]) {
+            self.stack.truncate(start);
+
+            return Err(RuntimeError::new(kind, span));
+        }
+
+        let result = self.execute(depth);
+
+        // Leave the machine as it was, so it can be called again
+        if result.is_err() {
+            let frame = &self.frames[depth];
+
+            self.slots.truncate(frame.slots);
+            self.stack.truncate(frame.stack);
+            self.frames.truncate(depth);
+        }
+
+        result
+    }
+
+    /// Start running a function, with its captures and the arguments on the
+    /// stack from `args` on.
+    fn enter(
+        &mut self,
+        function: u32,
+        args: usize,
+        captures: &[Value],
+    ) -> Result<(), RuntimeErrorKind> {
+        if self.frames.len() >= MAX_DEPTH {
+            return Err(RuntimeErrorKind::StackOverflow { depth: MAX_DEPTH });
+        }
+
+        let called = &self.program.functions[function as usize];
+        let base = self.slots.len();
+
+        self.slots.resize(base + called.slots.len(), Value::Unit);
+
+        let values = captures.iter().cloned().chain(self.stack.drain(args..));
+
+        for (&slot, value) in called.params.iter().zip(values) {
+            self.slots[base + slot as usize] = value;
+        }
+
+        self.frames.push(Frame {
+            function,
+            pc: 0,
+            slots: base,
+            stack: self.stack.len(),
+        });
+
+        Ok(())
+    }
+
+    /// Run instructions until the function at `depth` returns.
+    fn execute(&mut self, depth: usize) -> Result<Value, RuntimeError> {
+        let program = self.program;
+
+        // The function being run is kept apart from its frame, which is only
+        // updated when it calls another one
+        let frame = self.frames.last().expect("a function to run");
+        let mut function: &Function = &program.functions[frame.function as usize];
+        let mut base = frame.slots;
+        let mut pc = frame.pc;
+
+        loop {
+            let instr = function.code[pc];
+
+            pc += 1;
+
+            match instr {
+                Instr::Const(constant) => self
+                    .stack
+                    .push(function.constants[constant as usize].clone()),
+                Instr::Unit => self.stack.push(Value::Unit),
+                Instr::Pop => {
+                    self.pop();
+                }
+                Instr::Drop(n) => {
+                    let len = self.stack.len() - n as usize;
+
+                    self.stack.truncate(len);
+                }
+                Instr::Load(slot) => self.stack.push(self.slots[base + slot as usize].clone()),
+                Instr::Store(slot) => self.slots[base + slot as usize] = self.pop(),
+                Instr::Take(slot) => {
+                    let value =
+                        std::mem::replace(&mut self.slots[base + slot as usize], Value::Unit);
+
+                    self.stack.push(value);
+                }
+                Instr::Dup => {
+                    let value = self.stack.last().expect("a value on the stack").clone();
+
+                    self.stack.push(value);
+                }
+                Instr::LoadGlobal(global) => self.stack.push(self.globals[global as usize].clone()),
+                Instr::StoreGlobal(global) => self.globals[global as usize] = self.pop(),
+                Instr::Tuple(0) => self.stack.push(Value::Unit),
+                Instr::Tuple(n) => {
+                    let values = self.pop_many(n as usize);
+
+                    self.stack.push(Value::Tuple(Rc::new(values)));
+                }
+                Instr::Array(n) => {
+                    let values = self.pop_many(n as usize);
+
+                    self.stack.push(Value::Array(Rc::new(values)));
+                }
+                Instr::Struct(id) => {
+                    let fields = program.structs[id as usize].1.len();
+                    let values = self.pop_many(fields);
+
+                    self.stack.push(Value::Struct(id, Rc::new(values)));
+                }
+                Instr::Variant {
+                    enumeration,
+                    variant,
+                } => {
+                    let arity = program.enums[enumeration as usize].1[variant as usize].1;
+                    let payload = self.pop_many(arity);
+
+                    self.stack.push(Value::Variant {
+                        enumeration,
+                        variant,
+                        payload: Rc::new(payload),
+                    });
+                }
+                Instr::Element(position) => {
+                    let value = self.pop();
+                    let element = attempt!(function, pc, elements(&value))
+                        .get(position as usize)
+                        .cloned()
+                        .unwrap_or(Value::Unit);
+
+                    self.stack.push(element);
+                }
+                Instr::SetElement(position) => {
+                    let value = self.pop();
+                    let mut aggregate = self.pop();
+                    let elements = attempt!(function, pc, elements_mut(&mut aggregate));
+
+                    if let Some(element) = elements.get_mut(position as usize) {
+                        *element = value;
+                    }
+
+                    self.stack.push(aggregate);
+                }
+                Instr::Index => {
+                    let index = attempt!(function, pc, self.pop_int());
+                    let element = match self.pop() {
+                        Value::Array(values) => {
+                            let at = attempt!(function, pc, bounded(index, values.len()));
+
+                            values[at].clone()
+                        }
+                        Value::Str(string) => {
+                            let at = attempt!(function, pc, bounded(index, string.len()));
+
+                            Value::Int(string.as_bytes()[at] as i128)
+                        }
+                        found => fail!(function, pc, mismatch("an array", &found)),
+                    };
+
+                    self.stack.push(element);
+                }
+                Instr::SetIndex => {
+                    let index = attempt!(function, pc, self.pop_int());
+                    let value = self.pop();
+                    let mut array = self.pop();
+
+                    let Value::Array(values) = &mut array else {
+                        fail!(function, pc, mismatch("an array", &array));
+                    };
+                    let at = attempt!(function, pc, bounded(index, values.len()));
+
+                    Rc::make_mut(values)[at] = value;
+
+                    self.stack.push(array);
+                }
+                Instr::Len => {
+                    let length = match self.pop() {
+                        Value::Str(string) => string.len(),
+                        Value::Array(values) => values.len(),
+                        found => fail!(function, pc, mismatch("a str", &found)),
+                    };
+
+                    self.stack.push(Value::Int(length as i128));
+                }
+                Instr::Neg(ty) => {
+                    let value = attempt!(function, pc, self.pop_int());
+                    let result = arithmetic::negate(ty, value, self.program.overflow);
+                    let result = attempt!(function, pc, failed(result, ty));
+
+                    self.stack.push(Value::Int(result));
+                }
+                Instr::Not => {
+                    let value = attempt!(function, pc, self.pop_bool());
+
+                    self.stack.push(Value::Bool(!value));
+                }
+                Instr::Add(ty)
+                | Instr::Sub(ty)
+                | Instr::Mul(ty)
+                | Instr::Div(ty)
+                | Instr::Rem(ty) => {
+                    let right = attempt!(function, pc, self.pop_int());
+                    let left = attempt!(function, pc, self.pop_int());
+                    let op = match instr {
+                        Instr::Add(_) => BinaryOp::Add,
+                        Instr::Sub(_) => BinaryOp::Subtract,
+                        Instr::Mul(_) => BinaryOp::Multiply,
+                        Instr::Div(_) => BinaryOp::Divide,
+                        _ => BinaryOp::Modulo,
+                    };
+                    let result = arithmetic::binary(op, ty, left, right, self.program.overflow);
+                    let result = attempt!(function, pc, failed(result, ty));
+
+                    self.stack.push(Value::Int(result));
+                }
+                Instr::Concat => {
+                    let right = self.pop();
+                    let left = self.pop();
+
+                    let (Value::Str(left), Value::Str(right)) = (&left, &right) else {
+                        fail!(function, pc, mismatch("two strs", &left));
+                    };
+
+                    self.stack
+                        .push(Value::Str(Rc::from(format!("{}{}", left, right))));
+                }
+                Instr::Eq | Instr::Ne => {
+                    let right = self.pop();
+                    let left = self.pop();
+
+                    self.stack
+                        .push(Value::Bool((left == right) == (instr == Instr::Eq)));
+                }
+                Instr::Lt | Instr::Le | Instr::Gt | Instr::Ge => {
+                    let right = attempt!(function, pc, self.pop_int());
+                    let left = attempt!(function, pc, self.pop_int());
+
+                    let result = match instr {
+                        Instr::Lt => left < right,
+                        Instr::Le => left <= right,
+                        Instr::Gt => left > right,
+                        _ => left >= right,
+                    };
+
+                    self.stack.push(Value::Bool(result));
+                }
+                Instr::Cast(ty) => {
+                    let value = match self.pop() {
+                        Value::Int(int) => ty.truncate(int),
+                        Value::Bool(value) => value as i128,
+                        found => fail!(function, pc, mismatch("an integer", &found)),
+                    };
+
+                    self.stack.push(Value::Int(value));
+                }
+                Instr::Jump(target) => pc = target as usize,
+                Instr::JumpIfFalse(target) => {
+                    if !attempt!(function, pc, self.pop_bool()) {
+                        pc = target as usize;
+                    }
+                }
+                Instr::IsVariant(position) => {
+                    let is =
+                        matches!(self.pop(), Value::Variant { variant, .. } if variant == position);
+
+                    self.stack.push(Value::Bool(is));
+                }
+                Instr::IsSome => {
+                    let is = matches!(self.pop(), Value::Optional(Some(_)));
+
+                    self.stack.push(Value::Bool(is));
+                }
+                Instr::Unwrap => {
+                    let value = match self.pop() {
+                        Value::Optional(Some(value)) => (*value).clone(),
+                        found => fail!(function, pc, mismatch("an optional with a value", &found)),
+                    };
+
+                    self.stack.push(value);
+                }
+                Instr::Closure {
+                    function: closure,
+                    captures,
+                } => {
+                    let values = self.pop_many(captures as usize);
+
+                    self.stack.push(Value::Closure(Rc::new((closure, values))));
+                }
+                Instr::Call(args) => {
+                    let start = self.stack.len() - args as usize;
+
+                    let called = match self.stack[start - 1].clone() {
+                        Value::Function(called) => {
+                            self.stack.remove(start - 1);
+
+                            attempt!(function, pc, self.enter(called, start - 1, &[]));
+
+                            called
+                        }
+                        Value::Closure(closure) => {
+                            self.stack.remove(start - 1);
+
+                            attempt!(function, pc, self.enter(closure.0, start - 1, &closure.1));
+
+                            closure.0
+                        }
+                        Value::Native(native) => {
+                            let args = self.pop_many(args as usize);
+
+                            self.pop();
+                            self.native(native, args);
+
+                            continue;
+                        }
+                        found => fail!(function, pc, mismatch("a function", &found)),
+                    };
+
+                    let len = self.frames.len();
+
+                    self.frames[len - 2].pc = pc;
+                    function = &program.functions[called as usize];
+                    base = self.frames[len - 1].slots;
+                    pc = 0;
+                }
+                Instr::CallDirect {
+                    function: called,
+                    args,
+                } => {
+                    let start = self.stack.len() - args as usize;
+
+                    attempt!(function, pc, self.enter(called, start, &[]));
+
+                    let len = self.frames.len();
+
+                    self.frames[len - 2].pc = pc;
+                    function = &program.functions[called as usize];
+                    base = self.frames[len - 1].slots;
+                    pc = 0;
+                }
+                Instr::Return => {
+                    let value = self.pop();
+                    let finished = self.frames.pop().expect("the function returning");
+
+                    self.slots.truncate(finished.slots);
+                    self.stack.truncate(finished.stack);
+
+                    if self.frames.len() == depth {
+                        return Ok(value);
+                    }
+
+                    self.stack.push(value);
+
+                    let frame = self.frames.last().expect("the function that called it");
+
+                    function = &program.functions[frame.function as usize];
+                    base = frame.slots;
+                    pc = frame.pc;
+                }
+                Instr::Assert(message) => {
+                    if !attempt!(function, pc, self.pop_bool()) {
+                        let message = match &function.constants[message as usize] {
+                            Value::Str(message) => Some(message.to_string()),
+                            _ => None,
+                        };
+
+                        fail!(function, pc, RuntimeErrorKind::AssertionFailed { message });
+                    }
+                }
+                Instr::Unavailable(name) => {
+                    let name = match &function.constants[name as usize] {
+                        Value::Str(name) => name.to_string(),
+                        value => program.show(value),
+                    };
+
+                    fail!(function, pc, RuntimeErrorKind::Unavailable { name });
+                }
+                Instr::Unreachable => fail!(function, pc, RuntimeErrorKind::NoMatch),
+            }
+        }
+    }
+
+    /// Call a function the machine provides, pushing what it returns.
+    fn native(&mut self, native: Native, args: Vec<Value>) {
+        match native {
+            Native::Print => {
+                let line: Vec<_> = args
+                    .iter()
+                    .map(|value| match value {
+                        Value::Str(string) => string.to_string(),
+                        value => self.program.show(value),
+                    })
+                    .collect();
+
+                runtime::print(self.output, &line);
+
+                self.stack.push(Value::Unit);
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("a value on the stack")
+    }
+
+    /// Pop `n` values, in the order they were pushed.
+    fn pop_many(&mut self, n: usize) -> Vec<Value> {
+        let start = self.stack.len() - n;
+
+        self.stack.split_off(start)
+    }
+
+    fn pop_int(&mut self) -> Result<i128, RuntimeErrorKind> {
+        match self.pop() {
+            Value::Int(int) => Ok(int),
+            found => Err(mismatch("an integer", &found)),
+        }
+    }
+
+    fn pop_bool(&mut self) -> Result<bool, RuntimeErrorKind> {
+        match self.pop() {
+            Value::Bool(value) => Ok(value),
+            found => Err(mismatch("a bool", &found)),
+        }
+    }
+}
+
+/// The elements of a tuple, the fields of a struct or the payload of a
+/// variant.
+fn elements(value: &Value) -> Result<&[Value], RuntimeErrorKind> {
+    match value {
+        Value::Tuple(values)
+        | Value::Struct(_, values)
+        | Value::Variant {
+            payload: values, ..
+        } => Ok(values),
+        found => Err(mismatch("a tuple or struct", found)),
+    }
+}
+
+/// The elements of a value to change, copying them first if another value
+/// has them too.
+fn elements_mut(value: &mut Value) -> Result<&mut Vec<Value>, RuntimeErrorKind> {
+    match value {
+        Value::Tuple(values)
+        | Value::Struct(_, values)
+        | Value::Variant {
+            payload: values, ..
+        } => Ok(Rc::make_mut(values)),
+        found => Err(mismatch("a tuple or struct", found)),
+    }
+}
+
+/// An index, if it is in bounds for a length.
+fn bounded(index: i128, length: usize) -> Result<usize, RuntimeErrorKind> {
+    if (0..length as i128).contains(&index) {
+        Ok(index as usize)
+    } else {
+        Err(RuntimeErrorKind::IndexOutOfBounds { index, length })
+    }
+}
+
+/// The runtime error arithmetic on a type failed with.
+fn failed(result: Result<i128, ArithmeticError>, ty: IntTy) -> Result<i128, RuntimeErrorKind> {
+    result.map_err(|error| match error {
+        ArithmeticError::DivisionByZero => RuntimeErrorKind::DivisionByZero,
+        ArithmeticError::Overflow => RuntimeErrorKind::Overflow { ty },
+    })
+}
+
+fn mismatch(expected: &str, found: &Value) -> RuntimeErrorKind {
+    let found = match found {
+        Value::Unit => "()",
+        Value::Int(_) => "an integer",
+        Value::Bool(_) => "a bool",
+        Value::Str(_) => "a str",
+        Value::Tuple(_) => "a tuple",
+        Value::Array(_) => "an array",
+        Value::Struct(..) => "a struct",
+        Value::Variant { .. } => "a variant",
+        Value::Optional(_) => "an optional",
+        Value::Function(_) | Value::Closure(_) | Value::Native(_) => "a function",
+    };
+
+    RuntimeErrorKind::Mismatch {
+        expected: expected.to_string(),
+        found: found.to_string(),
+    }
+}
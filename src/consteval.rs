@@ -0,0 +1,873 @@
+//! Constant evaluation, which works out the values of expressions that can be
+//! known when the program is compiled.
+//!
+//! The value of every `const` item is evaluated, and so is the condition of
+//! every `static_assert`, which has to hold. These are constant contexts, so
+//! anything in them that can only be known when the program runs, like a
+//! call or a variable, is an error. Elsewhere, expressions that happen to be
+//! constant, like `60 * 60` in a function, are folded into their values.
+//!
+//! Integers are evaluated exactly, without the limits of their types, but
+//! once the type of a program is known, every integer that is evaluated has
+//! to fit in its type. `let a : u8 = 300;` or `250 + 10` where a `u8` is
+//! expected are errors wherever they are, constant context or not, unless
+//! arithmetic wraps around without overflow checks, like it does when the
+//! program runs.
+use crate::arithmetic::{self, ArithmeticError, Overflow};
+use crate::lexer::Span;
+use crate::parser::ast::{
+    Arena, Assert, BinaryOp, ExprId, ExprKind, ItemKind, Stmt, StmtKind, Type, UnaryOp,
+};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::ParseResult;
+use crate::resolve::{Resolution, SymbolId, SymbolKind};
+use crate::typeck::{IntTy, Ty, Types};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A value known when the program is compiled.
+pub enum Value {
+    Int(i128),
+    Bool(bool),
+    Str(String),
+    Tuple(Vec<Value>),
+    Array(Vec<Value>),
+}
+
+impl fmt::Display for Value {
+    /// Write the value the way it is written in the source.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(int) => write!(f, "{}", int),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Str(string) => write!(f, "{:?}", string),
+            Value::Tuple(values) if values.len() == 1 => write!(f, "({},)", values[0]),
+            Value::Tuple(values) => write!(f, "({})", List(values)),
+            Value::Array(values) => write!(f, "[{}]", List(values)),
+        }
+    }
+}
+
+/// Writes a list of values separated by commas.
+struct List<'a>(&'a [Value]);
+
+impl fmt::Display for List<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, value) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The values found by evaluating a program's constants.
+pub struct Consts {
+    /// The value of every `const` item, by its symbol
+    pub items: HashMap<SymbolId, Value>,
+    /// The value of every expression that is constant, anywhere in the
+    /// program
+    pub exprs: HashMap<ExprId, Value>,
+    /// What arithmetic that doesn't fit its type was folded into, which it
+    /// has to be when the program runs too
+    pub overflow: Overflow,
+}
+
+impl Consts {
+    pub fn expr(&self, id: ExprId) -> Option<&Value> {
+        self.exprs.get(&id)
+    }
+
+    pub fn item(&self, id: SymbolId) -> Option<&Value> {
+        self.items.get(&id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstErrorKind {
+    /// Something that can only be known when the program runs was used in a
+    /// constant context.
+    NotConstant {
+        /// What was used, e.g. "a call" or "the variable 'a'"
+        what: String,
+    },
+    DivisionByZero,
+    /// An integer got too large to evaluate.
+    Overflow,
+    /// An integer doesn't fit in its type, e.g. `300` as a `u8`.
+    OutOfRange {
+        value: i128,
+        ty: IntTy,
+    },
+    /// An array was indexed past its end.
+    IndexOutOfBounds {
+        index: i128,
+        length: usize,
+    },
+    /// A constant's value depends on itself.
+    Cycle {
+        name: String,
+    },
+    /// A `static_assert`'s condition is false.
+    AssertionFailed {
+        message: Option<String>,
+    },
+    /// Evaluating a constant took more steps than the limit.
+    TooManySteps {
+        limit: usize,
+    },
+    /// Evaluating a constant went through more expressions inside of each
+    /// other, or constants using each other, than the limit.
+    TooDeep {
+        limit: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An error that occurred while evaluating constants.
+pub struct ConstError {
+    pub kind: ConstErrorKind,
+    span: Span,
+    declared: Option<Span>,
+}
+
+impl ConstError {
+    pub fn new(kind: ConstErrorKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            declared: None,
+        }
+    }
+
+    /// Point at the constant the error is in too.
+    pub fn declared_at(mut self, span: Span) -> Self {
+        self.declared = Some(span);
+        self
+    }
+
+    /// A stable code identifying the kind of error, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            ConstErrorKind::NotConstant { .. } => "E0401",
+            ConstErrorKind::DivisionByZero => "E0402",
+            ConstErrorKind::Overflow => "E0403",
+            ConstErrorKind::IndexOutOfBounds { .. } => "E0404",
+            ConstErrorKind::Cycle { .. } => "E0405",
+            ConstErrorKind::AssertionFailed { .. } => "E0406",
+            ConstErrorKind::OutOfRange { .. } => "E0407",
+            ConstErrorKind::TooManySteps { .. } => "E0408",
+            ConstErrorKind::TooDeep { .. } => "E0409",
+        }
+    }
+
+    /// The code that caused the error.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The constant the error is in, or the array indexed past its end, if
+    /// there is one.
+    pub fn declared(&self) -> Option<Span> {
+        self.declared
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ConstErrorKind::NotConstant { what } => {
+                let mut chars = what.chars();
+                let what: String = chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default();
+
+                format!("{} can't be evaluated when compiling", what)
+            }
+            ConstErrorKind::DivisionByZero => "Division by zero".to_string(),
+            ConstErrorKind::Overflow => "The value is too large to evaluate".to_string(),
+            ConstErrorKind::OutOfRange { value, ty } => format!(
+                "The value {} doesn't fit in {}, which holds {} to {}",
+                value,
+                ty.name(),
+                ty.min(),
+                ty.max()
+            ),
+            ConstErrorKind::IndexOutOfBounds { index, length } => format!(
+                "Index {} is out of bounds for an array of length {}",
+                index, length
+            ),
+            ConstErrorKind::Cycle { name } => format!("'{}' depends on its own value", name),
+            ConstErrorKind::AssertionFailed { message: None } => {
+                "Static assertion failed".to_string()
+            }
+            ConstErrorKind::AssertionFailed {
+                message: Some(message),
+            } => format!("Static assertion failed: {}", message),
+            ConstErrorKind::TooManySteps { limit } => format!(
+                "Evaluating this took more than {} steps. The limit can be raised with \
+                 '--const-eval-limit'",
+                limit
+            ),
+            ConstErrorKind::TooDeep { limit } => format!(
+                "Evaluating this went more than {} expressions deep. The limit can be raised \
+                 with '--const-eval-depth'",
+                limit
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ConstError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.", self.message())
+    }
+}
+
+impl std::error::Error for ConstError {}
+
+/// Why an expression has no value.
+#[derive(Clone, Debug)]
+enum Stop {
+    Error(ConstError),
+    /// A constant it uses has no value, or it doesn't type check, which was
+    /// reported already
+    Failed,
+}
+
+type Eval = Result<Value, Stop>;
+
+/// How far a `const` item has been evaluated, so each is only evaluated once.
+#[derive(Clone)]
+enum State {
+    Unevaluated,
+    /// Being evaluated, so using it again is a cycle
+    Evaluating,
+    Evaluated(Option<Value>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How much work evaluating each constant can take, so one that would take
+/// too long, or too deep a stack, is an error instead of hanging or crashing
+/// the compiler.
+pub struct Limits {
+    /// The most expressions evaluated for one constant. Joining strings takes
+    /// a step for each byte too, so a string can't double in size over and
+    /// over.
+    pub steps: usize,
+    /// The most expressions being evaluated inside of each other at once,
+    /// counting those of the constants used
+    pub depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            steps: 1_000_000,
+            depth: 256,
+        }
+    }
+}
+
+/// Evaluates the constants of a resolved program.
+pub struct Evaluator<'a> {
+    parsed: &'a ParseResult,
+    resolution: &'a Resolution,
+    /// The position of each item, by its span
+    items: HashMap<Span, usize>,
+    states: Vec<State>,
+    /// What each expression evaluated to, so shared constants are only
+    /// evaluated once
+    values: HashMap<ExprId, Eval>,
+    /// The integer type of each expression whose type is known, which its
+    /// value has to fit in
+    ints: HashMap<ExprId, IntTy>,
+    limits: Limits,
+    overflow: Overflow,
+    /// The steps taken evaluating the current constant
+    steps: usize,
+    /// How many expressions are being evaluated inside of each other
+    depth: usize,
+    errors: Vec<ConstError>,
+}
+
+/// Evaluate the constants of a program that type checked, and fold every
+/// other expression that is constant. If a constant can't be evaluated or a
+/// static assertion doesn't hold, every error that was found is returned.
+pub fn evaluate(
+    parsed: &ParseResult,
+    resolution: &Resolution,
+    types: &Types,
+) -> Result<Consts, Vec<ConstError>> {
+    evaluate_with(
+        parsed,
+        resolution,
+        types,
+        Limits::default(),
+        Overflow::Checked,
+    )
+}
+
+/// Evaluate the constants of a program like `evaluate` does, with limits on
+/// how much work each can take, and arithmetic that doesn't fit its type
+/// overflowing like `overflow` says.
+pub fn evaluate_with(
+    parsed: &ParseResult,
+    resolution: &Resolution,
+    types: &Types,
+    limits: Limits,
+    overflow: Overflow,
+) -> Result<Consts, Vec<ConstError>> {
+    let mut evaluator = Evaluator::new(parsed, resolution).with_limits(limits);
+    let mut consts = Consts {
+        overflow,
+        ..Consts::default()
+    };
+
+    evaluator.overflow = overflow;
+
+    // The bodies of generic functions have types in each of their instances
+    let bodies: Vec<_> = std::iter::once(&types.exprs)
+        .chain(types.instances.iter().map(|instance| &instance.exprs))
+        .collect();
+
+    evaluator.ints = ints(parsed, &bodies);
+
+    for (i, item) in parsed.program.items.iter().enumerate() {
+        match &item.kind {
+            ItemKind::Const(decl) => {
+                let symbol = resolution.scopes.first().and_then(|module| {
+                    module.symbols.iter().copied().find(|&id| {
+                        let symbol = resolution.symbol(id);
+
+                        symbol.span == item.span && symbol.name == decl.name
+                    })
+                });
+
+                if let (Some(value), Some(symbol)) = (evaluator.item(i), symbol) {
+                    consts.items.insert(symbol, value);
+                }
+            }
+            ItemKind::StaticAssert(assert) => evaluator.assertion(assert),
+            _ => {}
+        }
+    }
+
+    let mut asserts = StaticAsserts(Vec::new());
+
+    asserts.visit_program(parsed);
+
+    for assert in &asserts.0 {
+        evaluator.assertion(assert);
+    }
+
+    for (id, _) in parsed.arena.iter() {
+        let typed = bodies
+            .iter()
+            .filter_map(|exprs| exprs.get(&id))
+            .any(|ty| !matches!(ty, Ty::Unknown | Ty::Never));
+
+        if !typed {
+            continue;
+        }
+
+        match evaluator.expr(id) {
+            Ok(value) => {
+                consts.exprs.insert(id, value);
+            }
+            // Other errors only matter in constant contexts, where they were
+            // reported already, but integers have to fit anywhere. Each is
+            // reported once, though the expressions around it fail too.
+            Err(Stop::Error(error)) if matches!(error.kind, ConstErrorKind::OutOfRange { .. }) => {
+                evaluator.report(error);
+            }
+            Err(_) => {}
+        }
+    }
+
+    // Arrays whose length is known can't be indexed past their end with a
+    // constant, even when the array isn't constant
+    for (_, expr) in parsed.arena.iter() {
+        let ExprKind::Index { object, index } = expr.kind else {
+            continue;
+        };
+
+        let length = bodies.iter().find_map(|exprs| match exprs.get(&object) {
+            Some(Ty::Array {
+                length: Some(length),
+                ..
+            }) => Some(*length),
+            _ => None,
+        });
+
+        if let (Some(length), Some(&Value::Int(index))) = (length, consts.expr(index)) {
+            if usize::try_from(index).map_or(true, |index| index >= length) {
+                let mut error = ConstError::new(
+                    ConstErrorKind::IndexOutOfBounds { index, length },
+                    expr.span,
+                );
+
+                // Point at the array too, when it has a name
+                if let Some(declared) = evaluator.declaration(object) {
+                    error = error.declared_at(declared);
+                }
+
+                evaluator.report(error);
+            }
+        }
+    }
+
+    if evaluator.errors.is_empty() {
+        Ok(consts)
+    } else {
+        Err(evaluator.errors)
+    }
+}
+
+/// The integer type of every expression that has the same one in each body
+/// it is in. Integer literals that are negated are left out, since it is the
+/// negated value that has to fit, e.g. `-128` as an `i8`.
+fn ints(parsed: &ParseResult, bodies: &[&HashMap<ExprId, Ty>]) -> HashMap<ExprId, IntTy> {
+    let mut ints: HashMap<ExprId, Option<IntTy>> = HashMap::new();
+
+    for (&id, ty) in bodies.iter().flat_map(|exprs| exprs.iter()) {
+        let int = match ty {
+            Ty::Int(int) => Some(*int),
+            _ => None,
+        };
+
+        ints.entry(id)
+            .and_modify(|known| {
+                if *known != int {
+                    *known = None;
+                }
+            })
+            .or_insert(int);
+    }
+
+    for (_, expr) in parsed.arena.iter() {
+        if let ExprKind::Unary {
+            op: UnaryOp::Negate,
+            operand,
+        } = expr.kind
+        {
+            if let ExprKind::Number(_) = parsed.arena[operand].kind {
+                ints.remove(&operand);
+            }
+        }
+    }
+
+    ints.into_iter()
+        .filter_map(|(id, int)| Some((id, int?)))
+        .collect()
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(parsed: &'a ParseResult, resolution: &'a Resolution) -> Self {
+        Self {
+            parsed,
+            resolution,
+            items: parsed
+                .program
+                .items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (item.span, i))
+                .collect(),
+            states: vec![State::Unevaluated; parsed.program.items.len()],
+            values: HashMap::new(),
+            ints: HashMap::new(),
+            limits: Limits::default(),
+            overflow: Overflow::Checked,
+            steps: 0,
+            depth: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Limit how much work evaluating each constant can take.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The value of the `const` item at a position, if it can be evaluated.
+    /// Errors are kept, and returned by `evaluate`.
+    pub fn item(&mut self, index: usize) -> Option<Value> {
+        match &self.states[index] {
+            State::Evaluated(value) => return value.clone(),
+            State::Evaluating => return None,
+            State::Unevaluated => {}
+        }
+
+        let item = &self.parsed.program.items[index];
+
+        let ItemKind::Const(decl) = &item.kind else {
+            return None;
+        };
+
+        self.states[index] = State::Evaluating;
+
+        let value = match self.expr(decl.value) {
+            Ok(value) => Some(value),
+            Err(Stop::Error(error)) => {
+                let error = match error.declared {
+                    Some(_) => error,
+                    None => error.declared_at(item.span),
+                };
+
+                self.errors.push(error);
+
+                None
+            }
+            Err(Stop::Failed) => None,
+        };
+
+        self.states[index] = State::Evaluated(value.clone());
+
+        value
+    }
+
+    /// Check that a static assertion holds.
+    fn assertion(&mut self, assert: &Assert) {
+        let span = self.parsed.arena[assert.condition].span;
+
+        match self.expr(assert.condition) {
+            Ok(Value::Bool(false)) => self.errors.push(ConstError::new(
+                ConstErrorKind::AssertionFailed {
+                    message: assert.message.clone(),
+                },
+                span,
+            )),
+            Err(Stop::Error(error)) => self.errors.push(error),
+            _ => {}
+        }
+    }
+
+    /// The value of an expression.
+    fn expr(&mut self, id: ExprId) -> Eval {
+        if let Some(value) = self.values.get(&id) {
+            return value.clone();
+        }
+
+        // Each constant, assertion or expression evaluated from outside of
+        // any other has steps of its own
+        if self.depth == 0 {
+            self.steps = 0;
+        }
+
+        let span = self.parsed.arena[id].span;
+
+        if self.depth >= self.limits.depth {
+            let limit = self.limits.depth;
+
+            return Err(Stop::Error(ConstError::new(
+                ConstErrorKind::TooDeep { limit },
+                span,
+            )));
+        }
+
+        self.step(1, span)?;
+
+        self.depth += 1;
+
+        let value = self.expr_kind(id).and_then(|value| self.fits(id, value));
+
+        self.depth -= 1;
+        self.values.insert(id, value.clone());
+
+        value
+    }
+
+    fn expr_kind(&mut self, id: ExprId) -> Eval {
+        let arena = &self.parsed.arena;
+        let span = arena[id].span;
+
+        let not_constant = |what: &str| {
+            Err(Stop::Error(ConstError::new(
+                ConstErrorKind::NotConstant {
+                    what: what.to_string(),
+                },
+                span,
+            )))
+        };
+
+        match &arena[id].kind {
+            ExprKind::Bool(value) => Ok(Value::Bool(*value)),
+            ExprKind::Number(number) => Ok(Value::Int(*number as i128)),
+            ExprKind::String(string) => Ok(Value::Str(string.clone())),
+            ExprKind::Identifier(name) => self.identifier(id, name, span),
+            ExprKind::Tuple(elements) => Ok(Value::Tuple(self.exprs(elements)?)),
+            ExprKind::Array(elements) => Ok(Value::Array(self.exprs(elements)?)),
+            ExprKind::Block(block) if block.stmts.is_empty() => match block.value {
+                Some(value) => self.expr(value),
+                None => Ok(Value::Tuple(Vec::new())),
+            },
+            ExprKind::Block(_) => not_constant("a block with statements"),
+            ExprKind::Unary { op, operand } => match (op, self.expr(*operand)?) {
+                // A negated literal is one, which has to fit in its type
+                (UnaryOp::Negate, Value::Int(int))
+                    if matches!(arena[*operand].kind, ExprKind::Number(_)) =>
+                {
+                    Ok(Value::Int(-int))
+                }
+                (UnaryOp::Negate, Value::Int(int)) => match self.wrapping(id) {
+                    Some(ty) => Ok(Value::Int(ty.truncate(-int))),
+                    None => Ok(Value::Int(-int)),
+                },
+                (UnaryOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+                _ => Err(Stop::Failed),
+            },
+            ExprKind::Binary { op, left, right } => self.binary(id, *op, *left, *right, span),
+            ExprKind::Index { object, index } => {
+                let (object, Value::Int(index)) = (self.expr(*object)?, self.expr(*index)?) else {
+                    return Err(Stop::Failed);
+                };
+
+                // Indexing a string gives one of its bytes
+                let values = match object {
+                    Value::Array(values) => values,
+                    Value::Str(string) => {
+                        string.bytes().map(|byte| Value::Int(byte.into())).collect()
+                    }
+                    _ => return Err(Stop::Failed),
+                };
+
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|i| values.get(i).cloned())
+                    .ok_or_else(|| {
+                        Stop::Error(ConstError::new(
+                            ConstErrorKind::IndexOutOfBounds {
+                                index,
+                                length: values.len(),
+                            },
+                            span,
+                        ))
+                    })
+            }
+            ExprKind::Cast { value, ty } => {
+                let value = self.expr(*value)?;
+
+                let int = match ty {
+                    Type::Named(name) => IntTy::from_name(name),
+                    _ => None,
+                };
+
+                match (value, int) {
+                    (Value::Int(value), Some(int)) => Ok(Value::Int(int.truncate(value))),
+                    (Value::Bool(value), Some(_)) => Ok(Value::Int(value as i128)),
+                    // Anything else can only be cast to its own type
+                    (value, _) => Ok(value),
+                }
+            }
+            ExprKind::StructLiteral { .. } => not_constant("a struct"),
+            ExprKind::Variant { .. } => not_constant("an enum variant"),
+            ExprKind::Closure(_) => not_constant("a closure"),
+            ExprKind::Call { .. } => not_constant("a call"),
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } if method == "len" && args.is_empty() => match self.expr(*receiver)? {
+                Value::Str(string) => Ok(Value::Int(string.len() as i128)),
+                _ => not_constant("a method call"),
+            },
+            ExprKind::MethodCall { .. } => not_constant("a method call"),
+            ExprKind::Field { .. } => not_constant("a field"),
+            ExprKind::Assign { .. } => not_constant("an assignment"),
+            ExprKind::IfLet { .. } => not_constant("an 'if let'"),
+            ExprKind::Match { .. } => not_constant("a match"),
+        }
+    }
+
+    /// Take steps evaluating the expression at `span`, which is an error once
+    /// there have been more than the limit.
+    fn step(&mut self, steps: usize, span: Span) -> Result<(), Stop> {
+        self.steps = self.steps.saturating_add(steps);
+
+        if self.steps > self.limits.steps {
+            let limit = self.limits.steps;
+
+            return Err(Stop::Error(ConstError::new(
+                ConstErrorKind::TooManySteps { limit },
+                span,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Keep an error, unless the same one was found already, e.g. in a
+    /// constant and again when folding the expressions using it.
+    fn report(&mut self, error: ConstError) {
+        let reported = self
+            .errors
+            .iter()
+            .any(|other| other.kind == error.kind && other.span == error.span);
+
+        if !reported {
+            self.errors.push(error);
+        }
+    }
+
+    /// Where the name an expression uses is declared, if it is one.
+    fn declaration(&self, id: ExprId) -> Option<Span> {
+        let &symbol = self.resolution.uses.get(&id)?;
+
+        Some(
+            self.resolution
+                .names
+                .get(&symbol)
+                .copied()
+                .unwrap_or(self.resolution.symbol(symbol).span),
+        )
+    }
+
+    /// Check that the value of an expression fits in its type, if it is an
+    /// integer whose type is known.
+    fn fits(&self, id: ExprId, value: Value) -> Eval {
+        match (&value, self.ints.get(&id)) {
+            (&Value::Int(int), Some(&ty)) if !(ty.min()..=ty.max()).contains(&int) => {
+                Err(Stop::Error(ConstError::new(
+                    ConstErrorKind::OutOfRange { value: int, ty },
+                    self.parsed.arena[id].span,
+                )))
+            }
+            _ => Ok(value),
+        }
+    }
+
+    fn exprs(&mut self, ids: &[ExprId]) -> Result<Vec<Value>, Stop> {
+        ids.iter().map(|&id| self.expr(id)).collect()
+    }
+
+    /// The value of a name, which has to be a constant.
+    fn identifier(&mut self, id: ExprId, name: &str, span: Span) -> Eval {
+        let Some(symbol) = self.resolution.resolved(id) else {
+            return Err(Stop::Failed);
+        };
+
+        let what = match symbol.kind {
+            SymbolKind::Const => {
+                let Some(&index) = self.items.get(&symbol.span) else {
+                    return Err(Stop::Failed);
+                };
+
+                if let State::Evaluating = self.states[index] {
+                    let error = ConstError::new(
+                        ConstErrorKind::Cycle {
+                            name: name.to_string(),
+                        },
+                        span,
+                    )
+                    .declared_at(symbol.span);
+
+                    return Err(Stop::Error(error));
+                }
+
+                return self.item(index).ok_or(Stop::Failed);
+            }
+            SymbolKind::Global => "the global",
+            SymbolKind::Function => "the function",
+            SymbolKind::Import => "the import",
+            SymbolKind::Param | SymbolKind::ClosureParam => "the parameter",
+            SymbolKind::Local | SymbolKind::Binding => "the variable",
+            SymbolKind::Struct | SymbolKind::Enum => "the type",
+            SymbolKind::Trait => "the trait",
+        };
+
+        Err(Stop::Error(ConstError::new(
+            ConstErrorKind::NotConstant {
+                what: format!("{} '{}'", what, name),
+            },
+            span,
+        )))
+    }
+
+    /// The type an integer expression wraps around in, when arithmetic
+    /// isn't checked and its type is known. Otherwise what doesn't fit is
+    /// reported with its value.
+    fn wrapping(&self, id: ExprId) -> Option<IntTy> {
+        match self.overflow {
+            Overflow::Wrapping => self.ints.get(&id).copied(),
+            Overflow::Checked => None,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        id: ExprId,
+        op: BinaryOp,
+        left: ExprId,
+        right: ExprId,
+        span: Span,
+    ) -> Eval {
+        let error = |kind| Err(Stop::Error(ConstError::new(kind, span)));
+
+        let left = self.expr(left)?;
+
+        // `&&` and `||` only evaluate their right side when they need to
+        match (op, &left) {
+            (BinaryOp::And, Value::Bool(false)) => return Ok(Value::Bool(false)),
+            (BinaryOp::Or, Value::Bool(true)) => return Ok(Value::Bool(true)),
+            (BinaryOp::And | BinaryOp::Or, _) => return self.expr(right),
+            _ => {}
+        }
+
+        let right = self.expr(right)?;
+
+        let int = match (op, &left, &right) {
+            (BinaryOp::Equal, _, _) => return Ok(Value::Bool(left == right)),
+            (BinaryOp::NotEqual, _, _) => return Ok(Value::Bool(left != right)),
+            (BinaryOp::Add, Value::Str(a), Value::Str(b)) => {
+                self.step(a.len() + b.len(), span)?;
+
+                return Ok(Value::Str(format!("{}{}", a, b)));
+            }
+            (_, &Value::Int(a), &Value::Int(b)) => match op {
+                BinaryOp::Add
+                | BinaryOp::Subtract
+                | BinaryOp::Multiply
+                | BinaryOp::Divide
+                | BinaryOp::Modulo => match self.wrapping(id) {
+                    Some(ty) => arithmetic::binary(op, ty, a, b, Overflow::Wrapping),
+                    None => arithmetic::exact(op, a, b),
+                },
+                BinaryOp::Less => return Ok(Value::Bool(a < b)),
+                BinaryOp::LessEqual => return Ok(Value::Bool(a <= b)),
+                BinaryOp::Greater => return Ok(Value::Bool(a > b)),
+                BinaryOp::GreaterEqual => return Ok(Value::Bool(a >= b)),
+                BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::And | BinaryOp::Or => {
+                    return Err(Stop::Failed)
+                }
+            },
+            _ => return Err(Stop::Failed),
+        };
+
+        match int {
+            Ok(int) => Ok(Value::Int(int)),
+            Err(ArithmeticError::DivisionByZero) => error(ConstErrorKind::DivisionByZero),
+            Err(ArithmeticError::Overflow) => error(ConstErrorKind::Overflow),
+        }
+    }
+}
+
+/// Finds the static assertions in function bodies.
+struct StaticAsserts(Vec<Assert>);
+
+impl Visitor for StaticAsserts {
+    fn visit_stmt(&mut self, arena: &Arena, stmt: &Stmt) {
+        if let StmtKind::StaticAssert(assert) = &stmt.kind {
+            self.0.push(assert.clone());
+        }
+
+        visit::walk_stmt(self, arena, stmt);
+    }
+}
@@ -0,0 +1,133 @@
+//! A backend compiling the HIR of a program to native object files with
+//! Cranelift, a code generator written in Rust.
+//!
+//! It needs nothing installed besides the compiler, unlike the LLVM
+//! backend, and compiles programs much faster than LLVM does, though the
+//! code it makes isn't as fast.
+//!
+//! - `codegen` turns a lowered program into the functions of a Cranelift
+//!   module, which can be an object file or any other kind of module.
+//! - `jit` compiles a program into memory and runs it.
+//!
+//! Cranelift's values are only ever scalars, so every value is flattened
+//! into the scalars it is made of: a tuple is the scalars of its elements
+//! one after another, a string is a pointer to its bytes and its length,
+//! and so on. Functions take and return every scalar of their values, and
+//! locals are a variable for each scalar, so nothing is kept in memory
+//! besides globals and strings.
+use crate::backend::{Backend, CodegenError, Target};
+use crate::hir;
+use crate::source_map::SourceMap;
+use cranelift_codegen::isa::{self, OwnedTargetIsa};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_module::default_libcall_names;
+use cranelift_object::{ObjectBuilder, ObjectModule};
+use std::path::Path;
+
+pub mod codegen;
+pub mod jit;
+
+pub use codegen::define;
+
+/// The Cranelift backend.
+pub struct Cranelift;
+
+impl Backend for Cranelift {
+    fn name(&self) -> &'static str {
+        "Cranelift"
+    }
+
+    fn write_object(
+        &self,
+        program: &hir::Program,
+        sources: Option<&SourceMap>,
+        target: Target,
+        path: &Path,
+    ) -> Result<(), Vec<CodegenError>> {
+        let object = compile(program, sources, target)?;
+
+        std::fs::write(path, object).map_err(|error| vec![CodegenError::object(error.to_string())])
+    }
+}
+
+/// The settings code is compiled with. Values with more scalars than there
+/// are registers to return them in are returned through memory.
+const SETTINGS: [(&str, &str); 3] = [
+    ("opt_level", "speed"),
+    ("is_pic", "true"),
+    ("enable_multi_ret_implicit_sret", "true"),
+];
+
+/// A target, as Cranelift compiles for it. Code is position independent, so
+/// object files can be linked into executables that are. The machine the
+/// compiler runs on is detected, so code can use every feature it has.
+pub fn isa(target: Target) -> Result<OwnedTargetIsa, CodegenError> {
+    let mut flags = settings::builder();
+
+    for (flag, value) in SETTINGS {
+        flags
+            .set(flag, value)
+            .map_err(|error| CodegenError::object(error.to_string()))?;
+    }
+
+    let builder = match target == Target::host() {
+        true => cranelift_native::builder().map_err(CodegenError::object)?,
+        false => isa::lookup_by_name(target.triple())
+            .map_err(|error| CodegenError::object(error.to_string()))?,
+    };
+
+    builder
+        .finish(settings::Flags::new(flags))
+        .map_err(|error| CodegenError::object(error.to_string()))
+}
+
+/// Compile a lowered program to the bytes of an object file for a target.
+/// The spans of runtime errors are located in
+/// `sources`, if there are any, so they can say where the code that failed
+/// is.
+pub fn compile(
+    program: &hir::Program,
+    sources: Option<&SourceMap>,
+    target: Target,
+) -> Result<Vec<u8>, Vec<CodegenError>> {
+    let mut module = object_module(target)?;
+
+    define(&mut module, program, sources, None, None)?;
+
+    module
+        .finish()
+        .emit()
+        .map_err(|error| vec![CodegenError::object(error.to_string())])
+}
+
+/// The Cranelift IR of each function of a lowered program compiled for a
+/// target, as text.
+pub fn clif(
+    program: &hir::Program,
+    sources: Option<&SourceMap>,
+    target: Target,
+) -> Result<String, Vec<CodegenError>> {
+    let mut text = String::new();
+
+    define(
+        &mut object_module(target)?,
+        program,
+        sources,
+        Some(&mut text),
+        None,
+    )?;
+
+    Ok(text)
+}
+
+/// A module to define the functions of an object file for a target in.
+fn object_module(target: Target) -> Result<ObjectModule, Vec<CodegenError>> {
+    let builder = ObjectBuilder::new(
+        isa(target).map_err(|error| vec![error])?,
+        "penguin",
+        default_libcall_names(),
+    )
+    .map_err(|error| vec![CodegenError::object(error.to_string())])?;
+
+    Ok(ObjectModule::new(builder))
+}
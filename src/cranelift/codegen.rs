@@ -0,0 +1,1795 @@
+use crate::arithmetic;
+use crate::backend::{self, int, CheckKind, CodegenError, CodegenErrorKind, Failure, Layout};
+use crate::hir::{self, Arm, Block, Expr, ExprKind, LocalId, Pattern, Stmt};
+use crate::lexer::Span;
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use crate::source_map::SourceMap;
+use crate::typeck::{IntTy, Ty};
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{self, types, AbiParam, InstBuilder, MemFlags, Signature, TrapCode};
+use cranelift_codegen::ir::{Type, Value};
+use cranelift_codegen::isa::unwind::UnwindInfo;
+use cranelift_codegen::isa::CallConv;
+use cranelift_frontend::{FuncInstBuilder, FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_module::{DataDescription, DataId, FuncId, Linkage, Module, ModuleResult};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// The bytes each scalar of a global takes in memory, so every scalar is
+/// aligned however big it is.
+const SLOT: usize = 8;
+
+/// The arguments passed between the format of `printf` and the rest, so the
+/// rest are passed on the stack, where it looks for them on macOS on
+/// AArch64. The format and these fill every register arguments are passed
+/// in.
+const PADDING: usize = 7;
+
+/// Define the functions of a lowered program in a module, returning the C
+/// `main` the program starts at, if it has one. The spans of runtime errors
+/// are located in `sources`, if there are any, so they can say where the
+/// code that failed is. The IR of each function is written to `text` if it
+/// is given, and how to unwind each function is added to `unwind` if it is
+/// given.
+pub fn define<M: Module>(
+    module: &mut M,
+    program: &hir::Program,
+    sources: Option<&SourceMap>,
+    text: Option<&mut String>,
+    unwind: Option<&mut Vec<(FuncId, UnwindInfo)>>,
+) -> Result<Option<FuncId>, Vec<CodegenError>> {
+    let pointer = module.target_config().pointer_type();
+
+    let mut codegen = Codegen {
+        module,
+        hir: program,
+        sources,
+        layout: Layout::new(program),
+        pointer,
+        functions: Vec::new(),
+        globals: Vec::new(),
+        imports: HashMap::new(),
+        bytes: HashMap::new(),
+        errors: Vec::new(),
+        text,
+        unwind,
+    };
+
+    codegen.declare();
+
+    if !codegen.errors.is_empty() {
+        return Err(codegen.errors);
+    }
+
+    for (i, function) in program.functions.iter().enumerate() {
+        codegen.function(i, function);
+    }
+
+    for (i, global) in program.globals.iter().enumerate() {
+        codegen.global(i, global);
+    }
+
+    let main = codegen.entry_point();
+
+    if !codegen.errors.is_empty() {
+        return Err(codegen.errors);
+    }
+
+    Ok(main)
+}
+
+struct Codegen<'a, M: Module> {
+    module: &'a mut M,
+    hir: &'a hir::Program,
+    sources: Option<&'a SourceMap>,
+    layout: Layout<'a>,
+    /// The type of pointers on the target
+    pointer: Type,
+    /// The declaration of each function of the program
+    functions: Vec<FuncId>,
+    /// The function computing each global, and its memory
+    globals: Vec<(FuncId, DataId)>,
+    /// The functions from the C library that are used, by name
+    imports: HashMap<&'static str, FuncId>,
+    /// The constant with some bytes, for strings and messages
+    bytes: HashMap<Vec<u8>, DataId>,
+    errors: Vec<CodegenError>,
+    text: Option<&'a mut String>,
+    unwind: Option<&'a mut Vec<(FuncId, UnwindInfo)>>,
+}
+
+/// The variables holding the scalars of a value, with their types.
+type Variables = Vec<(Variable, Type)>;
+
+/// A function being compiled.
+struct Compiler<'c, 'a, M: Module> {
+    codegen: &'c mut Codegen<'a, M>,
+    builder: FunctionBuilder<'c>,
+    /// The type of each local, and its variables once it has been used
+    locals: Vec<(Ty, Option<Variables>)>,
+    /// How many variables have been declared
+    variables: u32,
+    /// Whether the block being compiled has ended, so the code after it is
+    /// never run and needs a block of its own
+    terminated: bool,
+    /// The scalars the function returns
+    ret: Vec<Type>,
+    /// The deferred blocks of each block being compiled, innermost last
+    scopes: Vec<Vec<Block>>,
+    /// The loops being compiled, innermost last
+    loops: Vec<Loop>,
+}
+
+struct Loop {
+    /// The block `continue` jumps to
+    header: ir::Block,
+    /// The block `break` jumps to
+    exit: ir::Block,
+    /// How many blocks were being compiled when the loop started
+    scopes: usize,
+}
+
+/// Where an assignment stores its value.
+enum Root {
+    Local(LocalId),
+    /// A global, by its position in the program
+    Global(usize),
+}
+
+#[derive(Clone, Copy)]
+/// A step from a value to a value in it, when assigning to it.
+enum Step {
+    /// A field of a struct or element of a tuple, by position
+    Field(u32),
+    /// An element of an array, at an index that has been evaluated, with the
+    /// array's length
+    Index(Value, usize),
+}
+
+impl<'a, M: Module> Codegen<'a, M> {
+    /// Declare every function and global, so they can be used before they
+    /// are defined.
+    fn declare(&mut self) {
+        for function in &self.hir.functions {
+            let params: Vec<_> = function
+                .params
+                .iter()
+                .map(|param| function.locals[param.index()].ty.clone())
+                .collect();
+            let signature = self.signature(&params, &function.ret, function.span);
+            let name = format!("pg.{}", function.full_name());
+
+            let declared = self
+                .module
+                .declare_function(&name, Linkage::Export, &signature);
+
+            let Some(id) = self.ok(declared) else {
+                return;
+            };
+
+            self.functions.push(id);
+        }
+
+        for global in &self.hir.globals {
+            let signature = self.signature(&[], &global.ty, global.span);
+            let scalars = self.scalars(&global.ty, global.span).len();
+
+            let init = self.module.declare_function(
+                &format!("pg.init.{}", global.name),
+                Linkage::Local,
+                &signature,
+            );
+            let memory = self.module.declare_data(
+                &format!("pg.global.{}", global.name),
+                Linkage::Local,
+                true,
+                false,
+            );
+
+            let (Some(init), Some(memory)) = (self.ok(init), self.ok(memory)) else {
+                return;
+            };
+
+            let mut description = DataDescription::new();
+
+            description.define_zeroinit(SLOT * scalars.max(1));
+            description.set_align(SLOT as u64);
+
+            let defined = self.module.define_data(memory, &description);
+
+            self.ok(defined);
+            self.globals.push((init, memory));
+        }
+    }
+
+    /// What something done to the module made, reporting it if it failed.
+    fn ok<T>(&mut self, result: ModuleResult<T>) -> Option<T> {
+        result
+            .map_err(|error| self.errors.push(CodegenError::object(error.to_string())))
+            .ok()
+    }
+
+    fn function(&mut self, position: usize, function: &hir::Function) {
+        let id = self.functions[position];
+
+        self.build(id, &function.locals, |compiler, args| {
+            let mut args = args.iter().copied();
+
+            for &param in &function.params {
+                let variables = compiler.variables(param, function.span);
+                let values: Vec<_> = args.by_ref().take(variables.len()).collect();
+
+                compiler.set(&variables, values);
+            }
+
+            compiler.expr(&function.body)
+        });
+    }
+
+    /// Compile the function computing the value of a global, which is called
+    /// before `main`.
+    fn global(&mut self, position: usize, global: &hir::Global) {
+        let (init, _) = self.globals[position];
+
+        self.build(init, &global.locals, |compiler, _| {
+            compiler.expr(&global.value)
+        });
+    }
+
+    /// Compile the `main` the program starts at, which sets the globals in
+    /// order and calls the program's `main`, exiting with what it returns.
+    fn entry_point(&mut self) -> Option<FuncId> {
+        let position = backend::entry_point(self.hir)?;
+
+        let mut signature = self.module.make_signature();
+
+        signature.returns.push(AbiParam::new(types::I32));
+
+        let declared = self
+            .module
+            .declare_function("main", Linkage::Export, &signature);
+        let id = self.ok(declared)?;
+
+        self.build(id, &[], |compiler, _| {
+            for (i, &(init, _)) in compiler.codegen.globals.clone().iter().enumerate() {
+                let value = compiler.call_function(init, &[]);
+
+                compiler.store(&Root::Global(i), value);
+            }
+
+            let main = compiler.codegen.functions[position];
+            let value = compiler.call_function(main, &[]);
+
+            let code = match compiler.codegen.hir.functions[position].ret {
+                Ty::Int(ty) => compiler.convert(value[0], ty, IntTy::I32),
+                _ => compiler.ins().iconst(types::I32, 0),
+            };
+
+            vec![code]
+        });
+
+        Some(id)
+    }
+
+    /// Compile the body of a declared function, which `body` compiles given
+    /// the values of its parameters. Functions with errors aren't defined.
+    fn build(
+        &mut self,
+        id: FuncId,
+        locals: &[hir::Local],
+        body: impl FnOnce(&mut Compiler<'_, 'a, M>, &[Value]) -> Vec<Value>,
+    ) {
+        let declaration = self.module.declarations().get_function_decl(id);
+        let (name, signature) = (declaration.linkage_name(id), declaration.signature.clone());
+        let name = name.into_owned();
+
+        let mut context = self.module.make_context();
+        let mut function_context = FunctionBuilderContext::new();
+        let errors = self.errors.len();
+
+        context.func.signature = signature.clone();
+        context.func.name = ir::UserFuncName::user(0, id.as_u32());
+
+        {
+            let mut builder = FunctionBuilder::new(&mut context.func, &mut function_context);
+            let entry = builder.create_block();
+
+            builder.append_block_params_for_function_params(entry);
+            builder.switch_to_block(entry);
+
+            let args = builder.block_params(entry).to_vec();
+
+            let mut compiler = Compiler {
+                codegen: self,
+                builder,
+                locals: locals
+                    .iter()
+                    .map(|local| (local.ty.clone(), None))
+                    .collect(),
+                variables: 0,
+                terminated: false,
+                ret: signature.returns.iter().map(|ret| ret.value_type).collect(),
+                scopes: Vec::new(),
+                loops: Vec::new(),
+            };
+
+            let value = body(&mut compiler, &args);
+            let ret = compiler.ret.clone();
+            let value = compiler.fit(value, &ret);
+
+            compiler.ins().return_(&value);
+            compiler.builder.seal_all_blocks();
+            compiler.builder.finalize();
+        }
+
+        if self.errors.len() > errors {
+            return;
+        }
+
+        if let Some(text) = &mut self.text {
+            text.push_str(&format!("; {}\n{}\n", name, context.func.display()));
+        }
+
+        if let Err(error) = self.module.define_function(id, &mut context) {
+            self.errors.push(CodegenError::object(error.to_string()));
+
+            return;
+        }
+
+        if let Some(unwind) = &mut self.unwind {
+            let code = context.compiled_code().expect("the function was compiled");
+
+            match code.create_unwind_info(self.module.isa()) {
+                Ok(Some(info)) => unwind.push((id, info)),
+                Ok(None) => {}
+                Err(error) => self.errors.push(CodegenError::object(error.to_string())),
+            }
+        }
+    }
+
+    /// The signature of a function taking and returning values of some
+    /// types, as their scalars.
+    fn signature(&mut self, params: &[Ty], ret: &Ty, span: Span) -> Signature {
+        let mut signature = self.module.make_signature();
+
+        for param in params {
+            for ty in self.scalars(param, span) {
+                signature.params.push(AbiParam::new(ty));
+            }
+        }
+
+        for ty in self.scalars(ret, span) {
+            signature.returns.push(AbiParam::new(ty));
+        }
+
+        signature
+    }
+
+    /// The scalars a value of a type is made of, reporting it if the type
+    /// isn't supported.
+    fn scalars(&mut self, ty: &Ty, span: Span) -> Vec<Type> {
+        match self.scalars_in(ty, 0) {
+            Ok(scalars) => scalars,
+            Err(what) => {
+                self.unsupported(&what, span);
+
+                Vec::new()
+            }
+        }
+    }
+
+    fn scalars_in(&self, ty: &Ty, depth: usize) -> Result<Vec<Type>, String> {
+        // Values of a type that contains itself would never end
+        if depth > 32 {
+            return Err("types that contain themselves".to_string());
+        }
+
+        let scalars = match ty {
+            Ty::Int(ty) => vec![int_type(*ty)],
+            Ty::Bool => vec![types::I8],
+            Ty::Str => vec![self.pointer, types::I64],
+            Ty::Never => Vec::new(),
+            Ty::Function { .. } => vec![self.pointer],
+            Ty::Array { length: None, .. } => {
+                return Err("arrays without a length".to_string());
+            }
+            Ty::Param(_) | Ty::Unknown => return Err("values of unknown types".to_string()),
+            Ty::Tuple(_) | Ty::Array { .. } | Ty::Struct(..) | Ty::Enum(_) | Ty::Optional(_) => {
+                let mut scalars = Vec::new();
+
+                for element in self.layout.elements(ty) {
+                    scalars.extend(self.scalars_in(&element, depth + 1)?);
+                }
+
+                scalars
+            }
+        };
+
+        Ok(scalars)
+    }
+
+    /// The types of the values in an aggregate, with where their scalars
+    /// are among the aggregate's.
+    fn slices(&mut self, ty: &Ty, span: Span) -> Vec<(Ty, Range<usize>)> {
+        let mut start = 0;
+
+        self.layout
+            .elements(ty)
+            .into_iter()
+            .map(|element| {
+                let end = start + self.scalars(&element, span).len();
+                let range = start..end;
+
+                start = end;
+
+                (element, range)
+            })
+            .collect()
+    }
+
+    /// Report something that isn't supported.
+    fn unsupported(&mut self, what: &str, span: Span) {
+        let error = CodegenError::new(
+            CodegenErrorKind::Unsupported {
+                backend: "Cranelift",
+                what: what.to_string(),
+            },
+            span,
+        );
+
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    /// Whether the arguments a function taking any number of them gets
+    /// after its fixed ones are passed on the stack, like on macOS on
+    /// AArch64, where they aren't passed like any other argument.
+    /// Cranelift can't call such functions, so every register argument is
+    /// passed first, as padding.
+    fn variadic_on_stack(&self) -> bool {
+        self.module.isa().default_call_conv() == CallConv::AppleAarch64
+    }
+
+    /// A function from the C library, declaring it if it hasn't been.
+    /// `printf` is always called with a number and a pointer after its
+    /// format, which it ignores if the format doesn't use them, since a
+    /// module can only declare a function with one signature.
+    fn import(&mut self, name: &'static str) -> Option<FuncId> {
+        if let Some(&id) = self.imports.get(name) {
+            return Some(id);
+        }
+
+        let pointer = self.pointer;
+        let (params, returns): (&[Type], &[Type]) = match name {
+            "printf" => (&[pointer, types::I64, pointer], &[types::I32]),
+            "exit" => (&[types::I32], &[]),
+            "malloc" => (&[types::I64], &[pointer]),
+            "memcpy" => (&[pointer, pointer, types::I64], &[pointer]),
+            "memcmp" => (&[pointer, pointer, types::I64], &[types::I32]),
+            _ => return None,
+        };
+
+        let mut signature = self.module.make_signature();
+        let padding = match name == "printf" && self.variadic_on_stack() {
+            true => PADDING,
+            false => 0,
+        };
+
+        signature.params.push(AbiParam::new(params[0]));
+        signature
+            .params
+            .extend((0..padding).map(|_| AbiParam::new(types::I64)));
+        signature
+            .params
+            .extend(params[1..].iter().map(|&ty| AbiParam::new(ty)));
+        signature
+            .returns
+            .extend(returns.iter().map(|&ty| AbiParam::new(ty)));
+
+        let declared = self
+            .module
+            .declare_function(name, Linkage::Import, &signature);
+        let id = self.ok(declared)?;
+
+        self.imports.insert(name, id);
+
+        Some(id)
+    }
+
+    /// The constant with some bytes, adding it if there isn't one.
+    fn data(&mut self, bytes: &[u8]) -> Option<DataId> {
+        if let Some(&id) = self.bytes.get(bytes) {
+            return Some(id);
+        }
+
+        let declared = self.module.declare_anonymous_data(false, false);
+        let id = self.ok(declared)?;
+
+        let mut description = DataDescription::new();
+
+        description.define(bytes.into());
+
+        let defined = self.module.define_data(id, &description);
+
+        self.ok(defined)?;
+        self.bytes.insert(bytes.to_vec(), id);
+
+        Some(id)
+    }
+}
+
+impl<'c, 'a, M: Module> Compiler<'c, 'a, M> {
+    /// Build an instruction in the block being compiled, or a new one if it
+    /// has ended.
+    fn ins(&mut self) -> FuncInstBuilder<'_, 'c> {
+        if self.terminated {
+            let dead = self.builder.create_block();
+
+            self.builder.switch_to_block(dead);
+            self.terminated = false;
+        }
+
+        self.builder.ins()
+    }
+
+    fn jump(&mut self, block: ir::Block) {
+        self.ins().jump(block, &[]);
+        self.terminated = true;
+    }
+
+    fn branch(&mut self, condition: Value, then: ir::Block, otherwise: ir::Block) {
+        self.ins().brif(condition, then, &[], otherwise, &[]);
+        self.terminated = true;
+    }
+
+    /// End the block with a trap, for code that is never run.
+    fn trap(&mut self) {
+        self.ins().trap(TrapCode::unwrap_user(1));
+        self.terminated = true;
+    }
+
+    /// Start compiling a block, which the block before it falls through to
+    /// if it hasn't ended.
+    fn start(&mut self, block: ir::Block) {
+        if !self.terminated {
+            self.builder.ins().jump(block, &[]);
+        }
+
+        self.builder.switch_to_block(block);
+        self.terminated = false;
+    }
+
+    /// Values of some types, made of the given values if there are the
+    /// right number of them. There aren't after code that never finishes,
+    /// or something that isn't supported, so zeros stand in for them.
+    fn fit(&mut self, values: Vec<Value>, types: &[Type]) -> Vec<Value> {
+        if values.len() == types.len() {
+            return values;
+        }
+
+        types.iter().map(|&ty| self.ins().iconst(ty, 0)).collect()
+    }
+
+    /// An integer constant.
+    fn int(&mut self, ty: IntTy, value: i128) -> Value {
+        // Immediates of narrow types are their bits, without a sign
+        let bits = ty.bits();
+        let value = match bits {
+            64 => value as i64,
+            _ => (value as i64) & ((1 << bits) - 1),
+        };
+
+        self.ins().iconst(int_type(ty), value)
+    }
+
+    /// Whether a value is equal to a constant.
+    fn is(&mut self, value: Value, ty: IntTy, constant: i128) -> Value {
+        let constant = self.int(ty, constant);
+
+        self.ins().icmp(IntCC::Equal, value, constant)
+    }
+
+    fn not(&mut self, value: Value) -> Value {
+        self.ins().bxor_imm(value, 1)
+    }
+
+    /// Declare variables for the scalars of a value.
+    fn declare(&mut self, types: &[Type]) -> Variables {
+        types
+            .iter()
+            .map(|&ty| {
+                let variable = Variable::from_u32(self.variables);
+
+                self.variables += 1;
+                self.builder.declare_var(variable, ty);
+
+                (variable, ty)
+            })
+            .collect()
+    }
+
+    /// The variables of a local, declaring them if it hasn't been used.
+    fn variables(&mut self, local: LocalId, span: Span) -> Variables {
+        if let Some(variables) = &self.locals[local.index()].1 {
+            return variables.clone();
+        }
+
+        let ty = self.locals[local.index()].0.clone();
+        let types = self.codegen.scalars(&ty, span);
+        let variables = self.declare(&types);
+
+        self.locals[local.index()].1 = Some(variables.clone());
+
+        variables
+    }
+
+    fn get(&mut self, variables: &[(Variable, Type)]) -> Vec<Value> {
+        // Using a variable is an instruction when it has to be zero
+        self.ins();
+
+        variables
+            .iter()
+            .map(|&(variable, _)| self.builder.use_var(variable))
+            .collect()
+    }
+
+    fn set(&mut self, variables: &[(Variable, Type)], values: Vec<Value>) {
+        let types: Vec<_> = variables.iter().map(|&(_, ty)| ty).collect();
+        let values = self.fit(values, &types);
+
+        for (&(variable, _), value) in variables.iter().zip(values) {
+            self.builder.def_var(variable, value);
+        }
+    }
+
+    /// A pointer to a constant with some bytes.
+    fn constant(&mut self, bytes: &[u8]) -> Value {
+        let pointer = self.codegen.pointer;
+
+        let Some(id) = self.codegen.data(bytes) else {
+            return self.ins().iconst(pointer, 0);
+        };
+
+        let data = self
+            .codegen
+            .module
+            .declare_data_in_func(id, self.builder.func);
+
+        self.ins().symbol_value(pointer, data)
+    }
+
+    /// A string value.
+    fn string(&mut self, string: &str) -> Vec<Value> {
+        let bytes = self.constant(string.as_bytes());
+        let length = self.ins().iconst(types::I64, string.len() as i64);
+
+        vec![bytes, length]
+    }
+
+    /// A pointer to text ending in a zero byte, for C functions.
+    fn cstring(&mut self, text: &str) -> Value {
+        let mut bytes = text.as_bytes().to_vec();
+
+        bytes.push(0);
+
+        self.constant(&bytes)
+    }
+
+    fn call_function(&mut self, id: FuncId, args: &[Value]) -> Vec<Value> {
+        let function = self
+            .codegen
+            .module
+            .declare_func_in_func(id, self.builder.func);
+        let call = self.ins().call(function, args);
+
+        self.builder.inst_results(call).to_vec()
+    }
+
+    /// Call a function from the C library.
+    fn call_import(&mut self, name: &'static str, args: &[Value]) -> Vec<Value> {
+        match self.codegen.import(name) {
+            Some(id) => self.call_function(id, args),
+            None => Vec::new(),
+        }
+    }
+
+    /// Call `printf` with a format and the number and pointer it uses, if
+    /// it uses them.
+    fn printf(&mut self, format: &str, number: Option<Value>, pointer: Option<Value>) {
+        let format = self.cstring(format);
+        let number = number.unwrap_or_else(|| self.ins().iconst(types::I64, 0));
+        let pointer = pointer.unwrap_or_else(|| {
+            let ty = self.codegen.pointer;
+
+            self.ins().iconst(ty, 0)
+        });
+
+        let mut args = vec![format];
+
+        if self.codegen.variadic_on_stack() {
+            let zero = self.ins().iconst(types::I64, 0);
+
+            args.extend([zero; PADDING]);
+        }
+
+        args.extend([number, pointer]);
+        self.call_import("printf", &args);
+    }
+
+    /// Stop the program with a runtime error if `failed` is true.
+    fn check(&mut self, failed: Value, failure: &Failure, span: Span) {
+        let (fail, ok) = (self.builder.create_block(), self.builder.create_block());
+
+        self.builder.set_cold_block(fail);
+        self.branch(failed, fail, ok);
+        self.start(fail);
+
+        let message = backend::runtime_error(self.codegen.sources, span, failure);
+        let text = self.constant(message.as_bytes());
+        let length = self.ins().iconst(types::I64, message.len() as i64);
+
+        self.printf("[RUNTIME ERROR]: %.*s.\n", Some(length), Some(text));
+
+        let code = self.ins().iconst(types::I32, 1);
+
+        self.call_import("exit", &[code]);
+        self.trap();
+        self.start(ok);
+    }
+
+    /// Compile an expression, returning the scalars of its value.
+    fn expr(&mut self, expr: &Expr) -> Vec<Value> {
+        let values = self.value(expr);
+
+        // Calls of imports have no type, and what else isn't supported is
+        // reported where it is
+        match self.codegen.scalars_in(&expr.ty, 0) {
+            Ok(types) => self.fit(values, &types),
+            Err(_) => values,
+        }
+    }
+
+    fn value(&mut self, expr: &Expr) -> Vec<Value> {
+        match &expr.kind {
+            ExprKind::Int(value) => vec![self.int(int(&expr.ty), *value)],
+            ExprKind::Bool(value) => vec![self.ins().iconst(types::I8, *value as i64)],
+            ExprKind::Str(string) => self.string(string),
+            ExprKind::Local(local) => {
+                let variables = self.variables(*local, expr.span);
+
+                self.get(&variables)
+            }
+            ExprKind::Function(position) => {
+                let id = self.codegen.functions[*position];
+                let function = self
+                    .codegen
+                    .module
+                    .declare_func_in_func(id, self.builder.func);
+                let pointer = self.codegen.pointer;
+
+                vec![self.ins().func_addr(pointer, function)]
+            }
+            ExprKind::Global(position) => self.load(&Root::Global(*position), expr.span),
+            ExprKind::Import(name) => {
+                self.codegen
+                    .unsupported(&format!("'{}' from another file", name), expr.span);
+
+                Vec::new()
+            }
+            ExprKind::Tuple(elements) | ExprKind::Array(elements) => elements
+                .iter()
+                .flat_map(|element| self.expr(element))
+                .collect(),
+            ExprKind::Struct { name, fields } => {
+                let values: Vec<_> = fields
+                    .iter()
+                    .map(|(field, value)| (field.as_str(), self.expr(value)))
+                    .collect();
+
+                let Some(structure) = self.codegen.layout.structure(name) else {
+                    self.codegen
+                        .unsupported(&format!("the struct '{}'", name), expr.span);
+
+                    return Vec::new();
+                };
+
+                // The fields are evaluated in the order they are written, but
+                // stored in the order they are declared
+                let mut ordered = Vec::new();
+
+                for ((field, _), (ty, _)) in structure
+                    .fields
+                    .iter()
+                    .zip(self.codegen.slices(&expr.ty, expr.span))
+                {
+                    let value = values
+                        .iter()
+                        .find(|(name, _)| name == field)
+                        .map(|(_, value)| value.clone())
+                        .unwrap_or_default();
+                    let types = self.codegen.scalars(&ty, expr.span);
+
+                    ordered.extend(self.fit(value, &types));
+                }
+
+                ordered
+            }
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                let values: Vec<_> = payload.iter().flat_map(|value| self.expr(value)).collect();
+
+                self.variant(&expr.ty, enum_name, variant, values, expr.span)
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Unary { op, operand } => {
+                let Some(value) = self.scalar(operand) else {
+                    return Vec::new();
+                };
+
+                match op {
+                    UnaryOp::Negate => {
+                        let ty = int(&expr.ty);
+                        let zero = self.int(ty, 0);
+
+                        vec![self.arithmetic(BinaryOp::Subtract, ty, zero, value, expr.span)]
+                    }
+                    UnaryOp::Not => vec![self.not(value)],
+                }
+            }
+            ExprKind::Binary { op, left, right } => self.binary(*op, left, right, expr),
+            ExprKind::Cast(value) => {
+                let compiled = self.expr(value);
+
+                if compiled.is_empty() {
+                    return self.unavailable(value);
+                }
+
+                match (&value.ty, &expr.ty) {
+                    (Ty::Int(from), Ty::Int(to)) => vec![self.convert(compiled[0], *from, *to)],
+                    (Ty::Bool, Ty::Int(to)) => vec![self.convert(compiled[0], IntTy::U8, *to)],
+                    _ => compiled,
+                }
+            }
+            ExprKind::Call { callee, args } => self.call(callee, args, expr),
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                if receiver.ty == Ty::Str && method == "len" && args.is_empty() {
+                    let string = self.expr(receiver);
+
+                    return vec![self.convert(string[1], IntTy::U64, int(&expr.ty))];
+                }
+
+                self.codegen.unsupported(
+                    &format!("the method '{}' from another file", method),
+                    expr.span,
+                );
+
+                Vec::new()
+            }
+            ExprKind::Index { object, index } => self.index(object, index, expr),
+            ExprKind::Field { object, field } => {
+                let values = self.expr(object);
+
+                let Some(position) = self.codegen.layout.field(&object.ty, field) else {
+                    self.codegen
+                        .unsupported(&format!("the field '{}'", field), expr.span);
+
+                    return Vec::new();
+                };
+
+                let slices = self.codegen.slices(&object.ty, object.span);
+
+                match slices.get(position as usize) {
+                    Some((_, range)) => values[range.clone()].to_vec(),
+                    None => Vec::new(),
+                }
+            }
+            ExprKind::Assign { target, value } => {
+                self.assignment(target, value);
+
+                Vec::new()
+            }
+            ExprKind::Closure { .. } => {
+                self.codegen.unsupported("closures", expr.span);
+
+                Vec::new()
+            }
+            ExprKind::Match { value, arms } => self.matching(value, arms, expr),
+            ExprKind::Loop(body) => self.looping(body),
+            ExprKind::Break | ExprKind::Continue => {
+                let Some(innermost) = self.loops.last() else {
+                    self.codegen
+                        .unsupported("leaving a loop outside of one", expr.span);
+
+                    return Vec::new();
+                };
+
+                let (scopes, target) = match expr.kind {
+                    ExprKind::Break => (innermost.scopes, innermost.exit),
+                    _ => (innermost.scopes, innermost.header),
+                };
+
+                self.run_defers(scopes);
+                self.jump(target);
+
+                Vec::new()
+            }
+            ExprKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value),
+                    None => Vec::new(),
+                };
+
+                self.run_defers(0);
+
+                let ret = self.ret.clone();
+                let value = self.fit(value, &ret);
+
+                self.ins().return_(&value);
+                self.terminated = true;
+
+                Vec::new()
+            }
+        }
+    }
+
+    /// A variant of an enum with its payload. Every other variant's payload
+    /// is zero, so variants can be compared by all of their scalars.
+    fn variant(
+        &mut self,
+        ty: &Ty,
+        enum_name: &str,
+        variant: &str,
+        payload: Vec<Value>,
+        span: Span,
+    ) -> Vec<Value> {
+        let Some((position, _)) = self.codegen.layout.variant(enum_name, variant) else {
+            self.codegen
+                .unsupported(&format!("the variant '{}::{}'", enum_name, variant), span);
+
+            return Vec::new();
+        };
+
+        let mut values = vec![self.int(IntTy::U32, position as i128)];
+        let mut payload = Some(payload);
+
+        for (i, (payload_ty, _)) in self.codegen.slices(ty, span).iter().enumerate().skip(1) {
+            let types = self.codegen.scalars(payload_ty, span);
+
+            let value = match i == position + 1 {
+                true => payload.take().unwrap_or_default(),
+                false => Vec::new(),
+            };
+
+            values.extend(self.fit(value, &types));
+        }
+
+        values
+    }
+
+    /// Convert an integer from one type to another, keeping its low bits if
+    /// the new type is narrower.
+    fn convert(&mut self, value: Value, from: IntTy, to: IntTy) -> Value {
+        let (bits, to_bits, ty) = (from.bits(), to.bits(), int_type(to));
+
+        if to_bits < bits {
+            self.ins().ireduce(ty, value)
+        } else if to_bits > bits && from.is_signed() {
+            self.ins().sextend(ty, value)
+        } else if to_bits > bits {
+            self.ins().uextend(ty, value)
+        } else {
+            value
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: &Expr, right: &Expr, expr: &Expr) -> Vec<Value> {
+        if let Some(decided) = backend::short_circuit(op) {
+            let Some(value) = self.scalar(left) else {
+                return Vec::new();
+            };
+            let (rest, end) = (self.builder.create_block(), self.builder.create_block());
+            let result = self.builder.append_block_param(end, types::I8);
+
+            if decided {
+                self.ins().brif(value, end, &[value], rest, &[]);
+            } else {
+                self.ins().brif(value, rest, &[], end, &[value]);
+            }
+
+            self.terminated = true;
+            self.start(rest);
+
+            let right = match self.scalar(right) {
+                Some(right) => right,
+                None => self.ins().iconst(types::I8, 0),
+            };
+
+            self.ins().jump(end, &[right]);
+            self.terminated = true;
+            self.start(end);
+
+            return vec![result];
+        }
+
+        let l = self.expr(left);
+        let r = self.expr(right);
+
+        if l.is_empty() || r.is_empty() {
+            self.unavailable(left);
+
+            return self.unavailable(right);
+        }
+
+        let value = match op {
+            BinaryOp::Add if left.ty == Ty::Str => return self.concat(&l, &r),
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulo => self.arithmetic(op, int(&expr.ty), l[0], r[0], expr.span),
+            BinaryOp::Equal => self.equal(&left.ty, &l, &r, left.span),
+            BinaryOp::NotEqual => {
+                let equal = self.equal(&left.ty, &l, &r, left.span);
+
+                self.not(equal)
+            }
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
+                let condition = match (op, int(&left.ty).is_signed()) {
+                    (BinaryOp::Less, true) => IntCC::SignedLessThan,
+                    (BinaryOp::Less, false) => IntCC::UnsignedLessThan,
+                    (BinaryOp::LessEqual, true) => IntCC::SignedLessThanOrEqual,
+                    (BinaryOp::LessEqual, false) => IntCC::UnsignedLessThanOrEqual,
+                    (BinaryOp::Greater, true) => IntCC::SignedGreaterThan,
+                    (BinaryOp::Greater, false) => IntCC::UnsignedGreaterThan,
+                    (_, true) => IntCC::SignedGreaterThanOrEqual,
+                    (_, false) => IntCC::UnsignedGreaterThanOrEqual,
+                };
+
+                self.ins().icmp(condition, l[0], r[0])
+            }
+            BinaryOp::And | BinaryOp::Or => unreachable!("Short-circuited above"),
+        };
+
+        vec![value]
+    }
+
+    /// Compile an operand that is one scalar, like an integer or a boolean,
+    /// or `None` if it has no value.
+    fn scalar(&mut self, expr: &Expr) -> Option<Value> {
+        let values = self.expr(expr);
+
+        if values.is_empty() {
+            self.unavailable(expr);
+        }
+
+        values.first().copied()
+    }
+
+    /// Report an operand without any values because its type isn't
+    /// supported, e.g. a name from another file, which operators can't be
+    /// compiled without. Operands that never finish have no values either,
+    /// but aren't an error.
+    fn unavailable(&mut self, expr: &Expr) -> Vec<Value> {
+        self.codegen.scalars(&expr.ty, expr.span);
+
+        Vec::new()
+    }
+
+    /// Integer arithmetic, which wraps around without overflow checks.
+    /// Dividing by zero stops the program either way.
+    fn arithmetic(
+        &mut self,
+        op: BinaryOp,
+        ty: IntTy,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Value {
+        let overflow = Failure::Overflow(ty);
+        let checks = arithmetic::checks(op, ty, self.codegen.hir.overflow);
+        let signed = ty.is_signed();
+
+        if !checks.overflow && !checks.zero {
+            return match op {
+                BinaryOp::Add => self.ins().iadd(left, right),
+                BinaryOp::Subtract => self.ins().isub(left, right),
+                _ => self.ins().imul(left, right),
+            };
+        }
+
+        let (value, overflowed) = match (op, signed) {
+            (BinaryOp::Add, true) => self.ins().sadd_overflow(left, right),
+            (BinaryOp::Add, false) => self.ins().uadd_overflow(left, right),
+            (BinaryOp::Subtract, true) => self.ins().ssub_overflow(left, right),
+            (BinaryOp::Subtract, false) => self.ins().usub_overflow(left, right),
+            (BinaryOp::Multiply, true) => self.ins().smul_overflow(left, right),
+            (BinaryOp::Multiply, false) => self.ins().umul_overflow(left, right),
+            _ => {
+                let zero = self.is(right, ty, 0);
+
+                self.check(zero, &Failure::DivisionByZero, span);
+
+                if !signed {
+                    return match op {
+                        BinaryOp::Divide => self.ins().udiv(left, right),
+                        _ => self.ins().urem(left, right),
+                    };
+                }
+
+                let minus_one = self.is(right, ty, -1);
+
+                if checks.overflow {
+                    let smallest = self.is(left, ty, ty.min());
+                    let both = self.ins().band(smallest, minus_one);
+
+                    self.check(both, &overflow, span);
+
+                    return self.ins().sdiv(left, right);
+                }
+
+                // Dividing the smallest value by -1 traps, so it divides by
+                // 1 instead, and negates the dividend for -1
+                let one = self.int(ty, 1);
+                let divisor = self.ins().select(minus_one, one, right);
+
+                if op == BinaryOp::Modulo {
+                    return self.ins().srem(left, divisor);
+                }
+
+                let quotient = self.ins().sdiv(left, divisor);
+                let negated = self.ins().ineg(left);
+
+                return self.ins().select(minus_one, negated, quotient);
+            }
+        };
+
+        self.check(overflowed, &overflow, span);
+
+        value
+    }
+
+    /// Join two strings into a new one.
+    fn concat(&mut self, left: &[Value], right: &[Value]) -> Vec<Value> {
+        let length = self.ins().iadd(left[1], right[1]);
+
+        let Some(&bytes) = self.call_import("malloc", &[length]).first() else {
+            return Vec::new();
+        };
+
+        let rest = self.ins().iadd(bytes, left[1]);
+
+        self.call_import("memcpy", &[bytes, left[0], left[1]]);
+        self.call_import("memcpy", &[rest, right[0], right[1]]);
+
+        vec![bytes, length]
+    }
+
+    /// Whether two values of a type are equal, comparing aggregates by each
+    /// of their values.
+    fn equal(&mut self, ty: &Ty, left: &[Value], right: &[Value], span: Span) -> Value {
+        match ty {
+            Ty::Int(_) | Ty::Bool | Ty::Function { .. } => {
+                self.ins().icmp(IntCC::Equal, left[0], right[0])
+            }
+            Ty::Str => {
+                // Only the bytes of strings of the same length are compared,
+                // so neither is read past its end
+                let same = self.ins().icmp(IntCC::Equal, left[1], right[1]);
+                let zero = self.ins().iconst(types::I64, 0);
+                let length = self.ins().select(same, left[1], zero);
+
+                let Some(&compared) = self
+                    .call_import("memcmp", &[left[0], right[0], length])
+                    .first()
+                else {
+                    return same;
+                };
+
+                let bytes = self.ins().icmp_imm(IntCC::Equal, compared, 0);
+
+                self.ins().band(same, bytes)
+            }
+            _ => {
+                let mut equal = self.ins().iconst(types::I8, 1);
+
+                for (element, range) in self.codegen.slices(ty, span) {
+                    let same = self.equal(&element, &left[range.clone()], &right[range], span);
+
+                    equal = self.ins().band(equal, same);
+                }
+
+                equal
+            }
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr], expr: &Expr) -> Vec<Value> {
+        if let ExprKind::Import(name) = &callee.kind {
+            if name == "print" {
+                return self.print(args);
+            }
+        }
+
+        let values: Vec<_> = args.iter().flat_map(|arg| self.expr(arg)).collect();
+
+        match (&callee.kind, &callee.ty) {
+            (ExprKind::Function(position), _) => {
+                let id = self.codegen.functions[*position];
+
+                self.call_function(id, &values)
+            }
+            (_, Ty::Function { params, ret }) => {
+                let pointer = self.expr(callee)[0];
+                let signature = self.codegen.signature(params, ret, expr.span);
+                let signature = self.builder.import_signature(signature);
+                let call = self.ins().call_indirect(signature, pointer, &values);
+
+                self.builder.inst_results(call).to_vec()
+            }
+            _ => {
+                self.codegen.unsupported("calling this", callee.span);
+
+                Vec::new()
+            }
+        }
+    }
+
+    /// Print values on a line, separated by spaces.
+    fn print(&mut self, args: &[Expr]) -> Vec<Value> {
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                self.print_text(" ");
+            }
+
+            let value = self.expr(arg);
+
+            self.print_value(&arg.ty, &value, true, arg.span);
+        }
+
+        self.print_text("\n");
+
+        Vec::new()
+    }
+
+    fn print_text(&mut self, text: &str) {
+        self.printf(&text.replace('%', "%%"), None, None);
+    }
+
+    /// Print a value as it is written in the source. Strings are printed as
+    /// they are when `raw`, and quoted otherwise.
+    fn print_value(&mut self, ty: &Ty, value: &[Value], raw: bool, span: Span) {
+        match ty {
+            Ty::Int(int) => {
+                let (wide, format) = if int.is_signed() {
+                    (IntTy::I64, "%lld")
+                } else {
+                    (IntTy::U64, "%llu")
+                };
+                let value = self.convert(value[0], *int, wide);
+
+                self.printf(format, Some(value), None);
+            }
+            Ty::Bool => {
+                let (yes, no) = (self.string("true"), self.string("false"));
+                let bytes = self.ins().select(value[0], yes[0], no[0]);
+                let length = self.ins().select(value[0], yes[1], no[1]);
+
+                self.print_str(&[bytes, length]);
+            }
+            Ty::Str if raw => self.print_str(value),
+            Ty::Str => {
+                self.print_text("\"");
+                self.print_str(value);
+                self.print_text("\"");
+            }
+            Ty::Tuple(types) if types.is_empty() => self.print_text("()"),
+            Ty::Tuple(types) => {
+                self.print_text("(");
+                self.print_elements(ty, value, None, span);
+                self.print_text(if types.len() == 1 { ",)" } else { ")" });
+            }
+            Ty::Array { .. } => {
+                self.print_text("[");
+                self.print_elements(ty, value, None, span);
+                self.print_text("]");
+            }
+            Ty::Struct(name, _) => {
+                let fields: Vec<_> = self
+                    .codegen
+                    .layout
+                    .structure(name)
+                    .map(|structure| {
+                        let fields = &structure.fields;
+
+                        fields.iter().map(|(field, _)| field.clone()).collect()
+                    })
+                    .unwrap_or_default();
+
+                self.print_text(&format!("{} {{ ", name));
+                self.print_elements(ty, value, Some(&fields), span);
+                self.print_text(" }");
+            }
+            Ty::Enum(name) => self.print_variant(ty, name, value, span),
+            Ty::Optional(inner) => {
+                let some = self.builder.create_block();
+                let none = self.builder.create_block();
+                let end = self.builder.create_block();
+
+                self.branch(value[0], some, none);
+                self.start(some);
+                self.print_text("Some(");
+                self.print_value(inner, &value[1..], false, span);
+                self.print_text(")");
+                self.jump(end);
+                self.start(none);
+                self.print_text("None");
+                self.start(end);
+            }
+            Ty::Function { .. } => self.print_text("<func>"),
+            Ty::Never => {}
+            Ty::Param(_) | Ty::Unknown => {
+                self.codegen
+                    .unsupported("printing values of unknown types", span);
+            }
+        }
+    }
+
+    /// Print the values in an aggregate separated by commas, named by
+    /// `fields` if they are given.
+    fn print_elements(&mut self, ty: &Ty, value: &[Value], fields: Option<&[String]>, span: Span) {
+        for (i, (element, range)) in self.codegen.slices(ty, span).into_iter().enumerate() {
+            if i > 0 {
+                self.print_text(", ");
+            }
+
+            if let Some(field) = fields.and_then(|fields| fields.get(i)) {
+                self.print_text(&format!("{}: ", field));
+            }
+
+            self.print_value(&element, &value[range], false, span);
+        }
+    }
+
+    /// Print the variant an enum is, with its payload.
+    fn print_variant(&mut self, ty: &Ty, name: &str, value: &[Value], span: Span) {
+        let Some(variants) = self.codegen.layout.variants(name) else {
+            return;
+        };
+
+        let slices = self.codegen.slices(ty, span);
+        let end = self.builder.create_block();
+
+        for (i, (variant, payload)) in variants.iter().enumerate() {
+            let (this, next) = (self.builder.create_block(), self.builder.create_block());
+            let is = self.is(value[0], IntTy::U32, i as i128);
+
+            self.branch(is, this, next);
+            self.start(this);
+            self.print_text(&format!("{}::{}", name, variant));
+
+            if let (false, Some((payload_ty, range))) = (payload.is_empty(), slices.get(i + 1)) {
+                self.print_text("(");
+                self.print_elements(payload_ty, &value[range.clone()], None, span);
+                self.print_text(")");
+            }
+
+            self.jump(end);
+            self.start(next);
+        }
+
+        self.start(end);
+    }
+
+    fn print_str(&mut self, string: &[Value]) {
+        self.printf("%.*s", Some(string[1]), Some(string[0]));
+    }
+
+    /// Stop the program if an index isn't less than a length.
+    fn bounds(&mut self, index: Value, length: Value, span: Span) {
+        let outside = self
+            .ins()
+            .icmp(IntCC::UnsignedGreaterThanOrEqual, index, length);
+
+        self.check(outside, &Failure::OutOfBounds, span);
+    }
+
+    fn index(&mut self, object: &Expr, index: &Expr, expr: &Expr) -> Vec<Value> {
+        let value = self.expr(object);
+        let at = self.expr(index)[0];
+
+        // Negative indexes are huge when they are compared unsigned, so they
+        // are out of bounds too
+        let at = self.convert(at, int(&index.ty), IntTy::I64);
+
+        match &object.ty {
+            Ty::Array {
+                length: Some(length),
+                ..
+            } => {
+                let length = self.ins().iconst(types::I64, *length as i64);
+
+                self.bounds(at, length, expr.span);
+
+                let slices = self.codegen.slices(&object.ty, object.span);
+
+                self.element(at, &value, &slices)
+            }
+            Ty::Str => {
+                self.bounds(at, value[1], expr.span);
+
+                let pointer = self.ins().iadd(value[0], at);
+                let byte = self.ins().load(types::I8, MemFlags::new(), pointer, 0);
+
+                vec![self.convert(byte, IntTy::U8, int(&expr.ty))]
+            }
+            _ => {
+                self.codegen.unsupported("indexing this", expr.span);
+
+                Vec::new()
+            }
+        }
+    }
+
+    /// The element of an array at an index. Values aren't in memory, so the
+    /// element is chosen by comparing the index with each position.
+    fn element(&mut self, at: Value, array: &[Value], slices: &[(Ty, Range<usize>)]) -> Vec<Value> {
+        let Some((_, first)) = slices.first() else {
+            return Vec::new();
+        };
+
+        let mut element = array[first.clone()].to_vec();
+
+        for (i, (_, range)) in slices.iter().enumerate().skip(1) {
+            let is = self.is(at, IntTy::I64, i as i128);
+
+            for (chosen, &value) in element.iter_mut().zip(&array[range.clone()]) {
+                *chosen = self.ins().select(is, value, *chosen);
+            }
+        }
+
+        element
+    }
+
+    /// Compile an assignment, storing the value where the target is. The
+    /// indexes in the target are evaluated before the value.
+    fn assignment(&mut self, target: &Expr, value: &Expr) {
+        let mut steps = Vec::new();
+
+        let Some((root, ty)) = self.place(target, &mut steps) else {
+            self.codegen.unsupported("assigning to this", target.span);
+
+            return;
+        };
+
+        let value = self.expr(value);
+
+        for step in &steps {
+            if let Step::Index(at, length) = *step {
+                let length = self.ins().iconst(types::I64, length as i64);
+
+                self.bounds(at, length, target.span);
+            }
+        }
+
+        let old = self.load(&root, target.span);
+        let new = self.update(&ty, &old, &steps, &value, target.span);
+
+        self.store(&root, new);
+    }
+
+    /// A value with what is at the end of some steps into it replaced.
+    fn update(
+        &mut self,
+        ty: &Ty,
+        old: &[Value],
+        steps: &[Step],
+        value: &[Value],
+        span: Span,
+    ) -> Vec<Value> {
+        let Some((&step, rest)) = steps.split_first() else {
+            return value.to_vec();
+        };
+
+        let slices = self.codegen.slices(ty, span);
+        let mut new = old.to_vec();
+
+        match step {
+            Step::Field(position) => {
+                if let Some((element, range)) = slices.get(position as usize) {
+                    let element = self.update(element, &old[range.clone()], rest, value, span);
+
+                    new.splice(range.clone(), element);
+                }
+            }
+            Step::Index(at, _) => {
+                for (i, (element, range)) in slices.iter().enumerate() {
+                    let is = self.is(at, IntTy::I64, i as i128);
+                    let element = self.update(element, &old[range.clone()], rest, value, span);
+
+                    for (position, value) in range.clone().zip(element) {
+                        new[position] = self.ins().select(is, value, old[position]);
+                    }
+                }
+            }
+        }
+
+        new
+    }
+
+    /// Where an assignment target is and its type, and the steps into it,
+    /// evaluating its indexes.
+    fn place(&mut self, target: &Expr, steps: &mut Vec<Step>) -> Option<(Root, Ty)> {
+        match &target.kind {
+            ExprKind::Local(local) => {
+                let ty = self.locals[local.index()].0.clone();
+
+                Some((Root::Local(*local), ty))
+            }
+            ExprKind::Global(position) => {
+                let ty = self.codegen.hir.globals[*position].ty.clone();
+
+                Some((Root::Global(*position), ty))
+            }
+            ExprKind::Field { object, field } => {
+                let root = self.place(object, steps)?;
+
+                steps.push(Step::Field(self.codegen.layout.field(&object.ty, field)?));
+
+                Some(root)
+            }
+            ExprKind::Index { object, index } => {
+                let root = self.place(object, steps)?;
+
+                let Ty::Array {
+                    length: Some(length),
+                    ..
+                } = object.ty
+                else {
+                    return None;
+                };
+
+                let at = self.expr(index)[0];
+                let at = self.convert(at, int(&index.ty), IntTy::I64);
+
+                steps.push(Step::Index(at, length));
+
+                Some(root)
+            }
+            _ => None,
+        }
+    }
+
+    /// The memory of a global.
+    fn global(&mut self, position: usize) -> Value {
+        let (_, memory) = self.codegen.globals[position];
+        let memory = self
+            .codegen
+            .module
+            .declare_data_in_func(memory, self.builder.func);
+        let pointer = self.codegen.pointer;
+
+        self.ins().symbol_value(pointer, memory)
+    }
+
+    fn load(&mut self, root: &Root, span: Span) -> Vec<Value> {
+        match *root {
+            Root::Local(local) => {
+                let variables = self.variables(local, span);
+
+                self.get(&variables)
+            }
+            Root::Global(position) => {
+                let ty = self.codegen.hir.globals[position].ty.clone();
+                let types = self.codegen.scalars(&ty, span);
+                let memory = self.global(position);
+
+                types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &ty)| {
+                        self.ins()
+                            .load(ty, MemFlags::trusted(), memory, (i * SLOT) as i32)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn store(&mut self, root: &Root, value: Vec<Value>) {
+        match *root {
+            Root::Local(local) => {
+                let variables = self.variables(local, Span::default());
+
+                self.set(&variables, value);
+            }
+            Root::Global(position) => {
+                let memory = self.global(position);
+
+                for (i, value) in value.into_iter().enumerate() {
+                    self.ins()
+                        .store(MemFlags::trusted(), value, memory, (i * SLOT) as i32);
+                }
+            }
+        }
+    }
+
+    /// Compile a block, running what it deferred once it has its value.
+    fn block(&mut self, block: &Block) -> Vec<Value> {
+        self.scopes.push(Vec::new());
+
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+
+        let value = match &block.value {
+            Some(value) => self.expr(value),
+            None => Vec::new(),
+        };
+
+        let deferred = self.scopes.pop().unwrap_or_default();
+
+        for body in backend::unwinding(&[deferred]) {
+            self.block(&body);
+        }
+
+        value
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { local, value } => {
+                let compiled = self.expr(value);
+                let variables = self.variables(*local, value.span);
+
+                self.set(&variables, compiled);
+            }
+            Stmt::Assert { condition, message } => {
+                let value = self.expr(condition)[0];
+                let failed = self.not(value);
+                let failure = Failure::AssertionFailed(message.clone());
+
+                self.check(failed, &failure, condition.span);
+            }
+            Stmt::Defer(body) => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push(body.clone());
+                }
+            }
+            Stmt::Expr(expr) => {
+                self.expr(expr);
+            }
+        }
+    }
+
+    fn run_defers(&mut self, scope: usize) {
+        for body in backend::unwinding(&self.scopes[scope..]) {
+            self.block(&body);
+        }
+    }
+
+    fn looping(&mut self, body: &Block) -> Vec<Value> {
+        let (header, exit) = (self.builder.create_block(), self.builder.create_block());
+
+        self.start(header);
+        self.loops.push(Loop {
+            header,
+            exit,
+            scopes: self.scopes.len(),
+        });
+
+        self.block(body);
+        self.jump(header);
+
+        self.loops.pop();
+        self.start(exit);
+
+        Vec::new()
+    }
+
+    fn matching(&mut self, value: &Expr, arms: &[Arm], expr: &Expr) -> Vec<Value> {
+        let scrutinee = self.expr(value);
+        let types = self.codegen.scalars(&expr.ty, expr.span);
+        let result = self.declare(&types);
+        let end = self.builder.create_block();
+
+        for arm in arms {
+            let next = self.builder.create_block();
+
+            self.pattern(&arm.pattern, &value.ty, &scrutinee, next, value.span);
+
+            let arm_value = self.expr(&arm.value);
+
+            self.set(&result, arm_value);
+            self.jump(end);
+            self.start(next);
+        }
+
+        // The arms cover every value
+        self.trap();
+        self.start(end);
+
+        if expr.ty == Ty::Never {
+            self.trap();
+        }
+
+        self.get(&result)
+    }
+
+    /// Go on if a condition is true, or jump to `fail`.
+    fn test(&mut self, condition: Value, fail: ir::Block) {
+        let ok = self.builder.create_block();
+
+        self.branch(condition, ok, fail);
+        self.start(ok);
+    }
+
+    /// Compile the checks of a pattern matched against the scalars of a value
+    /// of a type, jumping to `fail` when one fails.
+    fn pattern(
+        &mut self,
+        pattern: &Pattern,
+        ty: &Ty,
+        value: &[Value],
+        fail: ir::Block,
+        span: Span,
+    ) {
+        let checks = match self.codegen.layout.checks(pattern, ty) {
+            Ok(checks) => checks,
+            Err((enum_name, variant)) => {
+                self.codegen
+                    .unsupported(&format!("the variant '{}::{}'", enum_name, variant), span);
+
+                return;
+            }
+        };
+
+        for check in checks {
+            let mut part = value;
+            let mut part_ty = ty.clone();
+
+            for step in &check.path {
+                let slices = self.codegen.slices(&part_ty, span);
+                let Some((element, range)) = slices.get(step.position()).cloned() else {
+                    break;
+                };
+
+                part = &part[range];
+                part_ty = element;
+            }
+
+            let condition = match check.kind {
+                CheckKind::Bind(local) => {
+                    let variables = self.variables(local, span);
+
+                    self.set(&variables, part.to_vec());
+
+                    continue;
+                }
+                CheckKind::Bool(true) | CheckKind::Some => part[0],
+                CheckKind::Bool(false) | CheckKind::None => self.not(part[0]),
+                CheckKind::Int(constant) => self.is(part[0], int(&check.ty), constant),
+                CheckKind::Str(string) => {
+                    let string = self.string(string);
+
+                    self.equal(&Ty::Str, part, &string, span)
+                }
+                CheckKind::Variant(position) => self.is(part[0], IntTy::U32, position as i128),
+            };
+
+            self.test(condition, fail);
+        }
+    }
+}
+
+/// The type of integers of a type in Cranelift.
+fn int_type(ty: IntTy) -> Type {
+    Type::int(ty.bits() as u16).unwrap_or(types::I64)
+}
@@ -0,0 +1,177 @@
+//! Running programs as soon as they are compiled, with Cranelift's JIT,
+//! without writing or linking an object file.
+//!
+//! The code is the same as the code in object files. The C functions it
+//! calls for builtins are the host's in `runtime`, so a program prints where
+//! the virtual machine would print, and other C functions, like `malloc`,
+//! are those of the C library the compiler is linked with.
+//!
+//! How to unwind each function is registered with the unwinder while the
+//! program runs, so a runtime error can unwind from `exit` back to `run`.
+use crate::backend::CodegenError;
+use crate::cranelift::{define, SETTINGS};
+use crate::hir;
+use crate::runtime;
+use crate::source_map::SourceMap;
+use cranelift_codegen::isa::unwind::UnwindInfo;
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module, ModuleError};
+use gimli::write::{Address, EhFrame, EndianVec, FrameTable};
+use gimli::RunTimeEndian;
+use std::io::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How a program run in memory ended.
+pub enum Exit {
+    /// Its C `main` returned the code
+    Returned(i32),
+    /// It failed at runtime, once it had printed the error, with the code an
+    /// executable would exit with
+    Failed(i32),
+}
+
+impl Exit {
+    /// The code the process would exit with if the program were compiled to
+    /// an executable.
+    pub fn code(self) -> i32 {
+        match self {
+            Exit::Returned(code) | Exit::Failed(code) => code,
+        }
+    }
+}
+
+/// Compile a lowered program into memory and run it, writing what it prints
+/// to `output`, and returning how it ended. The spans of runtime errors are
+/// located in `sources`, if there are any. A program that fails at runtime
+/// is stopped, but the compiler's process isn't.
+pub fn run(
+    program: &hir::Program,
+    sources: Option<&SourceMap>,
+    output: &mut dyn Write,
+) -> Result<Exit, Vec<CodegenError>> {
+    let fail = |error: ModuleError| vec![CodegenError::jit(error.to_string())];
+
+    let mut builder = JITBuilder::with_flags(&SETTINGS, default_libcall_names()).map_err(fail)?;
+
+    builder.symbols(runtime::symbols());
+
+    let mut module = JITModule::new(builder);
+
+    let mut unwind = Vec::new();
+
+    let Some(main) = define(&mut module, program, sources, None, Some(&mut unwind))? else {
+        return Err(vec![CodegenError::jit(
+            "it has no `main` function to start at",
+        )]);
+    };
+
+    module.finalize_definitions().map_err(fail)?;
+
+    let mut table = FrameTable::default();
+
+    if let Some(cie) = module.isa().create_systemv_cie() {
+        let cie = table.add_cie(cie);
+
+        for (id, info) in unwind {
+            if let UnwindInfo::SystemV(info) = info {
+                let address = module.get_finalized_function(id) as u64;
+
+                table.add_fde(cie, info.to_fde(Address::Constant(address)));
+            }
+        }
+    }
+
+    let mut eh_frame = EhFrame(EndianVec::new(RunTimeEndian::default()));
+
+    table
+        .write_eh_frame(&mut eh_frame)
+        .map_err(|error| vec![CodegenError::jit(error.to_string())])?;
+
+    let frames = Frames::register(eh_frame.0.into_vec());
+
+    // SAFETY: the entry point is a C `main` without parameters, which
+    // `exit` unwinds out of
+    let main = unsafe {
+        std::mem::transmute::<*const u8, extern "C-unwind" fn() -> i32>(
+            module.get_finalized_function(main),
+        )
+    };
+
+    let exit = match runtime::with_output(output, || main()) {
+        Ok(code) => Exit::Returned(code),
+        Err(code) => Exit::Failed(code),
+    };
+
+    drop(frames);
+
+    // SAFETY: nothing that was compiled is used once it has run
+    unsafe { module.free_memory() };
+
+    Ok(exit)
+}
+
+extern "C" {
+    fn __register_frame(frame: *const u8);
+    fn __deregister_frame(frame: *const u8);
+}
+
+/// An `.eh_frame` section, with how to unwind the functions compiled into
+/// memory, registered with the unwinder until it is dropped.
+struct Frames {
+    section: Vec<u8>,
+    /// What was registered: the whole section with libgcc's unwinder, or
+    /// each entry describing a function with the others, e.g. on macOS
+    registered: Vec<usize>,
+}
+
+impl Frames {
+    fn register(mut section: Vec<u8>) -> Self {
+        // The section ends with an empty entry
+        section.extend_from_slice(&[0; 4]);
+
+        let registered = match cfg!(all(target_os = "linux", target_env = "gnu")) {
+            true => vec![0],
+            false => {
+                // Every entry starts with its length, and the first is the
+                // one the others share
+                let mut entries = Vec::new();
+                let mut offset = 0;
+
+                while let Some(length) = section.get(offset..offset + 4) {
+                    let length = u32::from_ne_bytes(length.try_into().unwrap()) as usize;
+
+                    if length == 0 {
+                        break;
+                    }
+
+                    if offset != 0 {
+                        entries.push(offset);
+                    }
+
+                    offset += 4 + length;
+                }
+
+                entries
+            }
+        };
+
+        for &offset in &registered {
+            // SAFETY: the section is kept until the entries are deregistered
+            unsafe { __register_frame(section[offset..].as_ptr()) };
+        }
+
+        Self {
+            section,
+            registered,
+        }
+    }
+}
+
+impl Drop for Frames {
+    fn drop(&mut self) {
+        for &offset in self.registered.iter().rev() {
+            // SAFETY: the entry was registered by `register`
+            unsafe { __deregister_frame(self.section[offset..].as_ptr()) };
+        }
+    }
+}
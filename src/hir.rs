@@ -0,0 +1,289 @@
+//! The high-level intermediate representation (HIR), which is what backends
+//! and optimizations work on instead of the AST.
+//!
+//! A program is lowered to HIR once it has been resolved, type checked and
+//! its constants evaluated, so the HIR is simpler than the source it came
+//! from:
+//!
+//! - Every node has its type.
+//! - Names are resolved, so variables are `LocalId`s, and functions and
+//!   globals are positions in the `Program`.
+//! - Loops are desugared to `loop`s and matches, so `while a { ... }` is
+//!   `loop { match a { true => { ... }, _ => break } }`, and `if let` is a
+//!   match too.
+//! - Compound assignments are expanded, so `a += 1` is `a = a + 1`. Parts of
+//!   the target that could have side effects are evaluated once, into
+//!   temporary variables.
+//! - Generic functions are monomorphized, so there is a function for each
+//!   set of types a generic function is used with.
+//! - Methods of impls are functions named after their type, e.g.
+//!   `Circle::area`, and method calls are calls to them with the receiver
+//!   as the first argument.
+//! - Constant expressions are folded into their values, and constants are
+//!   replaced by their values wherever they are used.
+//! - Static assertions are gone, since they were checked already.
+//!
+//! The surface syntax can change without breaking anything that uses the HIR,
+//! as long as it still lowers to the same nodes.
+use crate::arithmetic::Overflow;
+use crate::lexer::Span;
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use crate::typeck::Ty;
+use std::fmt;
+
+pub mod lower;
+pub mod printer;
+
+pub use lower::lower;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A variable, parameter or pattern binding of a function or global, by its
+/// position in their `locals`.
+pub struct LocalId(u32);
+
+impl LocalId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for LocalId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Local {
+    /// The name it was declared with, or a name starting with `$` for a
+    /// temporary the lowering made
+    pub name: String,
+    pub ty: Ty,
+    pub mutable: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A lowered program, with its items in the order they were declared.
+/// Constants, imports and static assertions aren't needed anymore, so they
+/// are left out.
+pub struct Program {
+    pub functions: Vec<Function>,
+    pub globals: Vec<Global>,
+    pub structs: Vec<Struct>,
+    pub enums: Vec<Enum>,
+    /// What arithmetic that doesn't fit its type does, which constants were
+    /// folded with
+    pub overflow: Overflow,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    /// The types the type parameters stand for, if this is an instance of a
+    /// generic function, e.g. `u32` in `max<u32>`
+    pub args: Vec<Ty>,
+    /// The locals that hold the arguments, in order
+    pub params: Vec<LocalId>,
+    /// What the function returns
+    pub ret: Ty,
+    /// Every local of the function and the closures in it
+    pub locals: Vec<Local>,
+    pub body: Expr,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Global {
+    pub name: String,
+    pub ty: Ty,
+    pub mutable: bool,
+    /// The locals of the blocks and closures in the value
+    pub locals: Vec<Local>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Struct {
+    pub name: String,
+    /// The type parameters of a generic struct, which its fields' types can
+    /// use. Its values have types like `Pair<u32, str>`, with the types they
+    /// stand for.
+    pub generics: Vec<String>,
+    pub fields: Vec<(String, Ty)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Enum {
+    pub name: String,
+    /// Each variant, and the types of the values it holds
+    pub variants: Vec<(String, Vec<Ty>)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: Ty,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExprKind {
+    Int(i128),
+    Bool(bool),
+    Str(String),
+    Local(LocalId),
+    /// A function, by its position in the program
+    Function(usize),
+    /// A global, by its position in the program
+    Global(usize),
+    /// A name from another file, which can't be lowered until that file is
+    Import(String),
+    Tuple(Vec<Expr>),
+    Array(Vec<Expr>),
+    Struct {
+        name: String,
+        /// The value of each field, in the order they are evaluated
+        fields: Vec<(String, Expr)>,
+    },
+    Variant {
+        enum_name: String,
+        variant: String,
+        payload: Vec<Expr>,
+    },
+    Block(Block),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// Converting a value to the type of this node
+    Cast(Box<Expr>),
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    /// A method of a value from another file, or a built-in method like
+    /// `len` of a `str`
+    MethodCall {
+        receiver: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+    },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Field {
+        object: Box<Expr>,
+        field: String,
+    },
+    /// An assignment to a local, global, field or element. Compound
+    /// assignments are expanded, so there is no operator.
+    Assign {
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    Closure {
+        params: Vec<LocalId>,
+        /// The locals from around the closure that it uses, in the order
+        /// they are first used
+        captures: Vec<LocalId>,
+        body: Box<Expr>,
+    },
+    /// Evaluates to the value of the first arm whose pattern matches. The
+    /// arms cover every value.
+    Match {
+        value: Box<Expr>,
+        arms: Vec<Arm>,
+    },
+    /// Runs its body over and over, until a `break` or `return`
+    Loop(Block),
+    Break,
+    Continue,
+    Return(Option<Box<Expr>>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub value: Option<Box<Expr>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stmt {
+    Let {
+        local: LocalId,
+        value: Expr,
+    },
+    Assert {
+        condition: Expr,
+        message: Option<String>,
+    },
+    /// A block run when the block the statement is in is left
+    Defer(Block),
+    Expr(Expr),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Arm {
+    pub pattern: Pattern,
+    pub value: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A pattern, with every name that is a variant told apart from the names
+/// it binds.
+pub enum Pattern {
+    Wildcard,
+    Binding(LocalId),
+    Bool(bool),
+    Int(i128),
+    Str(String),
+    Tuple(Vec<Pattern>),
+    /// `Some(pattern)`, matching an optional with a value
+    Some(Box<Pattern>),
+    /// `None`, matching an optional without a value
+    None,
+    Variant {
+        enum_name: String,
+        variant: String,
+        payload: Vec<Pattern>,
+    },
+}
+
+impl Function {
+    /// The function's name, followed by the types of an instance, e.g.
+    /// `max<u32>`, which is different for each function in a program.
+    pub fn full_name(&self) -> String {
+        if self.args.is_empty() {
+            return self.name.clone();
+        }
+
+        let args: Vec<_> = self.args.iter().map(ToString::to_string).collect();
+
+        format!("{}<{}>", self.name, args.join(", "))
+    }
+}
+
+impl Block {
+    /// The type of a block with these statements and value: the type of its
+    /// value, or `Never` if a statement never finishes, or `()`.
+    pub fn ty(&self) -> Ty {
+        match &self.value {
+            Some(value) => value.ty.clone(),
+            None if self
+                .stmts
+                .iter()
+                .any(|stmt| matches!(stmt, Stmt::Expr(expr) if expr.ty == Ty::Never)) =>
+            {
+                Ty::Never
+            }
+            None => Ty::unit(),
+        }
+    }
+}
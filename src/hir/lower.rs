@@ -0,0 +1,934 @@
+use super::{
+    Arm, Block, Enum, Expr, ExprKind, Function, Global, Local, LocalId, Pattern, Program, Stmt,
+    Struct,
+};
+use crate::consteval::{Consts, Value};
+use crate::lexer::Span;
+use crate::parser::ast::{self, BinaryOp, ExprId, ItemKind, StmtKind, UnaryOp};
+use crate::parser::ParseResult;
+use crate::resolve::{Resolution, SymbolId, SymbolKind};
+use crate::typeck::{MethodId, Ty, Types};
+use std::collections::HashMap;
+
+/// Lower a program that was resolved, type checked and had its constants
+/// evaluated without any errors.
+pub fn lower(
+    parsed: &ParseResult,
+    resolution: &Resolution,
+    types: &Types,
+    consts: &Consts,
+) -> Program {
+    let items = &parsed.program.items;
+
+    let mut functions = HashMap::new();
+    let mut instances = vec![0; types.instances.len()];
+    let mut methods = HashMap::new();
+    let mut globals = HashMap::new();
+    let mut position = 0;
+
+    // Generic functions are lowered once for each instance, in the place of
+    // the function
+    for (i, item) in items.iter().enumerate() {
+        match &item.kind {
+            ItemKind::Function(function) if !function.generics.is_empty() => {
+                for (instance, checked) in types.instances.iter().enumerate() {
+                    if checked.item == i {
+                        instances[instance] = position;
+                        position += 1;
+                    }
+                }
+            }
+            ItemKind::Function(_) => {
+                functions.insert(item.span, position);
+                position += 1;
+            }
+            ItemKind::Impl(implementation) => {
+                for method in 0..implementation.methods.len() {
+                    methods.insert(MethodId { item: i, method }, position);
+                    position += 1;
+                }
+            }
+            ItemKind::Global(_) => {
+                globals.insert(item.span, globals.len());
+            }
+            _ => {}
+        }
+    }
+
+    let mut lowerer = Lowerer {
+        parsed,
+        resolution,
+        types,
+        consts,
+        functions,
+        instances,
+        methods,
+        globals,
+        exprs: &types.exprs,
+        symbol_types: &types.symbols,
+        instantiated: &types.instantiated,
+        calls: &types.calls,
+        declarations: resolution
+            .scopes
+            .iter()
+            .flat_map(|scope| &scope.symbols)
+            .map(|&id| {
+                let symbol = resolution.symbol(id);
+
+                ((symbol.span, symbol.name.as_str()), id)
+            })
+            .collect(),
+        locals: Vec::new(),
+        symbols: HashMap::new(),
+        closures: Vec::new(),
+        temporaries: 0,
+    };
+
+    let mut program = Program {
+        overflow: consts.overflow,
+        ..Program::default()
+    };
+
+    for (i, item) in items.iter().enumerate() {
+        let ty = || types.items.get(&i).cloned().unwrap_or(Ty::Unknown);
+
+        match &item.kind {
+            ItemKind::Function(function) if !function.generics.is_empty() => {
+                for instance in types.instances.iter().filter(|instance| instance.item == i) {
+                    lowerer.exprs = &instance.exprs;
+                    lowerer.symbol_types = &instance.symbols;
+                    lowerer.instantiated = &instance.instantiated;
+                    lowerer.calls = &instance.calls;
+
+                    let lowered = lowerer.function(function, instance.args.clone(), item.span);
+
+                    program.functions.push(Function {
+                        ret: instance.ret.clone(),
+                        ..lowered
+                    });
+                }
+
+                lowerer.exprs = &types.exprs;
+                lowerer.symbol_types = &types.symbols;
+                lowerer.instantiated = &types.instantiated;
+                lowerer.calls = &types.calls;
+            }
+            ItemKind::Impl(implementation) => {
+                for (position, method) in implementation.methods.iter().enumerate() {
+                    let lowered = lowerer.function(&method.function, Vec::new(), method.span);
+                    let id = MethodId {
+                        item: i,
+                        method: position,
+                    };
+
+                    program.functions.push(Function {
+                        name: format!("{}::{}", implementation.ty, lowered.name),
+                        ret: types.methods.get(&id).cloned().unwrap_or(Ty::Unknown),
+                        ..lowered
+                    });
+                }
+            }
+            ItemKind::Function(function) => {
+                let lowered = lowerer.function(function, Vec::new(), item.span);
+
+                program.functions.push(Function {
+                    ret: ty(),
+                    ..lowered
+                });
+            }
+            ItemKind::Global(decl) => {
+                let value = lowerer.expr(decl.value);
+
+                program.globals.push(Global {
+                    name: decl.name.clone(),
+                    ty: ty(),
+                    mutable: decl.mutable,
+                    locals: lowerer.finish(),
+                    value,
+                    span: item.span,
+                });
+            }
+            ItemKind::Struct(structure) => program.structs.push(Struct {
+                name: structure.name.clone(),
+                generics: structure
+                    .generics
+                    .iter()
+                    .map(|param| param.name.clone())
+                    .collect(),
+                fields: types
+                    .structs
+                    .get(&structure.name)
+                    .cloned()
+                    .unwrap_or_default(),
+            }),
+            ItemKind::Enum(enumeration) => program.enums.push(Enum {
+                name: enumeration.name.clone(),
+                variants: types
+                    .enums
+                    .get(&enumeration.name)
+                    .cloned()
+                    .unwrap_or_default(),
+            }),
+            ItemKind::Const(_)
+            | ItemKind::Import(_)
+            | ItemKind::StaticAssert(_)
+            | ItemKind::Trait(_) => {}
+        }
+    }
+
+    program
+}
+
+struct Lowerer<'a> {
+    parsed: &'a ParseResult,
+    resolution: &'a Resolution,
+    types: &'a Types,
+    consts: &'a Consts,
+    /// The position of each function in the program, by the span of its item
+    functions: HashMap<Span, usize>,
+    /// The position in the program of each instance of a generic function
+    instances: Vec<usize>,
+    /// The position in the program of each method of an impl
+    methods: HashMap<MethodId, usize>,
+    /// The position of each global in the program, by the span of its item
+    globals: HashMap<Span, usize>,
+    /// The types of the expressions being lowered, which are in an instance
+    /// for the body of a generic function
+    exprs: &'a HashMap<ExprId, Ty>,
+    /// The types of the symbols being lowered
+    symbol_types: &'a HashMap<SymbolId, Ty>,
+    /// The instance each use of a generic function being lowered refers to
+    instantiated: &'a HashMap<ExprId, usize>,
+    /// The method each method call being lowered calls
+    calls: &'a HashMap<ExprId, MethodId>,
+    /// The symbol declared by a node with a name
+    declarations: HashMap<(Span, &'a str), SymbolId>,
+    /// The locals of the function or global being lowered
+    locals: Vec<Local>,
+    /// The local each parameter, variable and binding was lowered to
+    symbols: HashMap<SymbolId, LocalId>,
+    /// The closures being lowered, innermost last, with the first local
+    /// declared in each and the locals from around it that it uses
+    closures: Vec<(usize, Vec<LocalId>)>,
+    /// How many temporaries the function or global has
+    temporaries: usize,
+}
+
+impl<'a> Lowerer<'a> {
+    /// Lower a function at `span`, with the types its type parameters stand
+    /// for. What it returns is left for the caller to fill in.
+    fn function(&mut self, function: &ast::Function, args: Vec<Ty>, span: Span) -> Function {
+        let params = function
+            .params
+            .iter()
+            .map(|param| self.declare(span, &param.name))
+            .collect();
+
+        let body = self.block_expr(&function.body, span);
+
+        Function {
+            name: function.name.clone(),
+            args,
+            params,
+            ret: Ty::Unknown,
+            locals: self.finish(),
+            body,
+            span,
+        }
+    }
+
+    /// Finish lowering a function or global, returning its locals.
+    fn finish(&mut self) -> Vec<Local> {
+        self.symbols.clear();
+        self.temporaries = 0;
+
+        std::mem::take(&mut self.locals)
+    }
+
+    fn push_local(&mut self, local: Local) -> LocalId {
+        let id = LocalId(self.locals.len() as u32);
+
+        self.locals.push(local);
+
+        id
+    }
+
+    /// Make a local for the symbol a node with a span declares with a name.
+    fn declare(&mut self, span: Span, name: &str) -> LocalId {
+        let symbol = self.declarations.get(&(span, name)).copied();
+
+        let local = Local {
+            name: name.to_string(),
+            ty: symbol
+                .and_then(|symbol| self.symbol_types.get(&symbol))
+                .cloned()
+                .unwrap_or(Ty::Unknown),
+            mutable: symbol.is_some_and(|symbol| self.resolution.symbol(symbol).mutable),
+        };
+
+        let id = self.push_local(local);
+
+        if let Some(symbol) = symbol {
+            self.symbols.insert(symbol, id);
+        }
+
+        id
+    }
+
+    /// The type the type checker found for an expression.
+    fn ty(&self, id: ExprId) -> Ty {
+        self.exprs.get(&id).cloned().unwrap_or(Ty::Unknown)
+    }
+
+    fn block(&mut self, block: &ast::Block) -> Block {
+        let stmts = block
+            .stmts
+            .iter()
+            .filter_map(|stmt| self.stmt(stmt))
+            .collect();
+
+        Block {
+            stmts,
+            value: block.value.map(|value| Box::new(self.expr(value))),
+        }
+    }
+
+    /// Lower a block to an expression, at the span of the node it is in.
+    fn block_expr(&mut self, block: &ast::Block, span: Span) -> Expr {
+        let block = self.block(block);
+
+        Expr {
+            ty: block.ty(),
+            kind: ExprKind::Block(block),
+            span,
+        }
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Option<Stmt> {
+        let span = stmt.span;
+
+        let stmt = match &stmt.kind {
+            StmtKind::Let(decl) => {
+                // The value is lowered first, since it can use a name the
+                // variable shadows
+                let value = self.expr(decl.value);
+
+                Stmt::Let {
+                    local: self.declare(span, &decl.name),
+                    value,
+                }
+            }
+            StmtKind::Return(value) => {
+                let value = value.map(|value| Box::new(self.expr(value)));
+
+                Stmt::Expr(Expr {
+                    kind: ExprKind::Return(value),
+                    ty: Ty::Never,
+                    span,
+                })
+            }
+            StmtKind::While { condition, body } => {
+                let condition = self.expr(*condition);
+                let body = self.block_expr(body, span);
+
+                Stmt::Expr(self.loop_while(condition, Pattern::Bool(true), body, span))
+            }
+            StmtKind::WhileLet {
+                pattern,
+                value,
+                body,
+            } => {
+                let value = self.expr(*value);
+                let pattern = self.pattern(pattern, &value.ty, span);
+                let body = self.block_expr(body, span);
+
+                Stmt::Expr(self.loop_while(value, pattern, body, span))
+            }
+            StmtKind::Assert(assert) => Stmt::Assert {
+                condition: self.expr(assert.condition),
+                message: assert.message.clone(),
+            },
+            // These were checked when the constants were evaluated
+            StmtKind::StaticAssert(_) => return None,
+            StmtKind::Defer(body) => Stmt::Defer(self.block(body)),
+            StmtKind::Break => Stmt::Expr(Expr {
+                kind: ExprKind::Break,
+                ty: Ty::Never,
+                span,
+            }),
+            StmtKind::Continue => Stmt::Expr(Expr {
+                kind: ExprKind::Continue,
+                ty: Ty::Never,
+                span,
+            }),
+            StmtKind::Expr(expr) => Stmt::Expr(self.expr(*expr)),
+        };
+
+        Some(stmt)
+    }
+
+    /// A loop running `body` while `value` matches `pattern`, i.e. `loop {
+    /// match value { pattern => body, _ => break } }`.
+    fn loop_while(&mut self, value: Expr, pattern: Pattern, body: Expr, span: Span) -> Expr {
+        let done = Expr {
+            kind: ExprKind::Break,
+            ty: Ty::Never,
+            span,
+        };
+
+        let test = Expr {
+            kind: ExprKind::Match {
+                value: Box::new(value),
+                arms: vec![
+                    Arm {
+                        pattern,
+                        value: body,
+                    },
+                    Arm {
+                        pattern: Pattern::Wildcard,
+                        value: done,
+                    },
+                ],
+            },
+            ty: Ty::unit(),
+            span,
+        };
+
+        Expr {
+            kind: ExprKind::Loop(Block {
+                stmts: vec![Stmt::Expr(test)],
+                value: None,
+            }),
+            ty: Ty::unit(),
+            span,
+        }
+    }
+
+    fn expr(&mut self, id: ExprId) -> Expr {
+        let ty = self.ty(id);
+        let span = self.parsed.arena[id].span;
+
+        // Constant expressions are replaced by their values
+        if let Some(value) = self.consts.expr(id) {
+            if let Some(expr) = self.value(value, &ty, span) {
+                return expr;
+            }
+        }
+
+        let kind = self.expr_kind(id, span);
+
+        Expr { kind, ty, span }
+    }
+
+    fn exprs(&mut self, ids: &[ExprId]) -> Vec<Expr> {
+        ids.iter().map(|&id| self.expr(id)).collect()
+    }
+
+    fn boxed(&mut self, id: ExprId) -> Box<Expr> {
+        Box::new(self.expr(id))
+    }
+
+    fn expr_kind(&mut self, id: ExprId, span: Span) -> ExprKind {
+        match &self.parsed.arena[id].kind {
+            ast::ExprKind::Bool(value) => ExprKind::Bool(*value),
+            ast::ExprKind::Number(number) => ExprKind::Int(*number as i128),
+            ast::ExprKind::String(string) => ExprKind::Str(string.clone()),
+            ast::ExprKind::Identifier(name) => self.identifier(id, name),
+            ast::ExprKind::Tuple(elements) => ExprKind::Tuple(self.exprs(elements)),
+            ast::ExprKind::Array(elements) => ExprKind::Array(self.exprs(elements)),
+            ast::ExprKind::Block(block) => ExprKind::Block(self.block(block)),
+            ast::ExprKind::Unary { op, operand } => {
+                let operand = self.expr(*operand);
+
+                match self.method(id) {
+                    Some(position) => self.call(position, vec![operand], self.ty(id), span),
+                    None => ExprKind::Unary {
+                        op: *op,
+                        operand: Box::new(operand),
+                    },
+                }
+            }
+            ast::ExprKind::Binary { op, left, right } => {
+                let (left, right) = (self.expr(*left), self.expr(*right));
+
+                let Some(position) = self.method(id) else {
+                    return ExprKind::Binary {
+                        op: *op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    };
+                };
+
+                let call = self.call(position, vec![left, right], self.ty(id), span);
+
+                // `a != b` is `!a.eq(b)`
+                match op {
+                    BinaryOp::NotEqual => ExprKind::Unary {
+                        op: UnaryOp::Not,
+                        operand: Box::new(Expr {
+                            kind: call,
+                            ty: Ty::Bool,
+                            span,
+                        }),
+                    },
+                    _ => call,
+                }
+            }
+            ast::ExprKind::StructLiteral { name, fields } => ExprKind::Struct {
+                name: name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), self.expr(*value)))
+                    .collect(),
+            },
+            ast::ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => ExprKind::Variant {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                payload: self.exprs(payload),
+            },
+            ast::ExprKind::Closure(closure) => {
+                self.closures.push((self.locals.len(), Vec::new()));
+
+                let params = closure
+                    .params
+                    .iter()
+                    .map(|param| self.declare(span, &param.name))
+                    .collect();
+
+                let body = self.boxed(closure.body);
+
+                let (_, captures) = self.closures.pop().unwrap_or_default();
+
+                ExprKind::Closure {
+                    params,
+                    captures,
+                    body,
+                }
+            }
+            ast::ExprKind::Call { callee, args } => ExprKind::Call {
+                callee: self.boxed(*callee),
+                args: self.exprs(args),
+            },
+            ast::ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                let receiver = self.expr(*receiver);
+                let args = self.exprs(args);
+
+                let Some(position) = self.method(id) else {
+                    return ExprKind::MethodCall {
+                        receiver: Box::new(receiver),
+                        method: method.clone(),
+                        args,
+                    };
+                };
+
+                // The receiver is the method's first argument
+                let args = std::iter::once(receiver).chain(args).collect();
+
+                self.call(position, args, self.ty(id), span)
+            }
+            ast::ExprKind::Index { object, index } => {
+                let (object, index) = (self.expr(*object), self.expr(*index));
+
+                match self.method(id) {
+                    Some(position) => self.call(position, vec![object, index], self.ty(id), span),
+                    None => ExprKind::Index {
+                        object: Box::new(object),
+                        index: Box::new(index),
+                    },
+                }
+            }
+            ast::ExprKind::Cast { value, .. } => ExprKind::Cast(self.boxed(*value)),
+            ast::ExprKind::Field { object, field } => ExprKind::Field {
+                object: self.boxed(*object),
+                field: field.clone(),
+            },
+            ast::ExprKind::Assign {
+                op: None,
+                target,
+                value,
+            } => ExprKind::Assign {
+                target: self.boxed(*target),
+                value: self.boxed(*value),
+            },
+            ast::ExprKind::Assign {
+                op: Some(op),
+                target,
+                value,
+            } => self.compound(id, *op, *target, *value, span),
+            ast::ExprKind::IfLet {
+                pattern,
+                value,
+                then,
+                otherwise,
+            } => {
+                let value = self.expr(*value);
+                let pattern = self.pattern(pattern, &value.ty, span);
+                let then = self.block_expr(then, span);
+
+                // Without an `else`, nothing happens when the pattern
+                // doesn't match
+                let otherwise = match otherwise {
+                    Some(otherwise) => self.block_expr(otherwise, span),
+                    None => Expr {
+                        kind: ExprKind::Block(Block::default()),
+                        ty: Ty::unit(),
+                        span,
+                    },
+                };
+
+                ExprKind::Match {
+                    value: Box::new(value),
+                    arms: vec![
+                        Arm {
+                            pattern,
+                            value: then,
+                        },
+                        Arm {
+                            pattern: Pattern::Wildcard,
+                            value: otherwise,
+                        },
+                    ],
+                }
+            }
+            ast::ExprKind::Match { value, arms } => {
+                let value = self.expr(*value);
+
+                let arms = arms
+                    .iter()
+                    .map(|arm| Arm {
+                        pattern: self.pattern(&arm.pattern, &value.ty, arm.span),
+                        value: self.expr(arm.value),
+                    })
+                    .collect();
+
+                ExprKind::Match {
+                    value: Box::new(value),
+                    arms,
+                }
+            }
+        }
+    }
+
+    /// What a name used in an expression refers to.
+    fn identifier(&mut self, id: ExprId, name: &str) -> ExprKind {
+        let Some(&symbol) = self.resolution.uses.get(&id) else {
+            return ExprKind::Import(name.to_string());
+        };
+
+        let declared = self.resolution.symbol(symbol);
+
+        match declared.kind {
+            SymbolKind::Function => match self.instantiated.get(&id) {
+                Some(&instance) => ExprKind::Function(self.instances[instance]),
+                None => match self.functions.get(&declared.span) {
+                    Some(&position) => ExprKind::Function(position),
+                    None => ExprKind::Import(name.to_string()),
+                },
+            },
+            SymbolKind::Global => match self.globals.get(&declared.span) {
+                Some(&position) => ExprKind::Global(position),
+                None => ExprKind::Import(name.to_string()),
+            },
+            SymbolKind::Param
+            | SymbolKind::ClosureParam
+            | SymbolKind::Local
+            | SymbolKind::Binding => match self.symbols.get(&symbol) {
+                Some(&local) => {
+                    self.capture(local);
+
+                    ExprKind::Local(local)
+                }
+                None => ExprKind::Import(name.to_string()),
+            },
+            // Constants are folded, and types can't be used as values
+            SymbolKind::Const
+            | SymbolKind::Struct
+            | SymbolKind::Enum
+            | SymbolKind::Trait
+            | SymbolKind::Import => ExprKind::Import(name.to_string()),
+        }
+    }
+
+    /// The position of the method an expression calls, which is a method
+    /// call or an operator used on a struct or enum.
+    fn method(&self, id: ExprId) -> Option<usize> {
+        self.calls
+            .get(&id)
+            .and_then(|method| self.methods.get(method))
+            .copied()
+    }
+
+    /// A call of the function at `position`, which returns `ret`.
+    fn call(&self, position: usize, args: Vec<Expr>, ret: Ty, span: Span) -> ExprKind {
+        let callee = Expr {
+            kind: ExprKind::Function(position),
+            ty: Ty::Function {
+                params: args.iter().map(|arg| arg.ty.clone()).collect(),
+                ret: Box::new(ret),
+            },
+            span,
+        };
+
+        ExprKind::Call {
+            callee: Box::new(callee),
+            args,
+        }
+    }
+
+    /// Record a local being used, so closures it is from outside of capture
+    /// it.
+    fn capture(&mut self, local: LocalId) {
+        for (start, captures) in &mut self.closures {
+            if local.index() < *start && !captures.contains(&local) {
+                captures.push(local);
+            }
+        }
+    }
+
+    /// Expand a compound assignment like `target += value` to `target =
+    /// target + value`, evaluating the parts of the target once. On a struct
+    /// or enum, it is `target = target.add(value)`.
+    fn compound(
+        &mut self,
+        id: ExprId,
+        op: BinaryOp,
+        target: ExprId,
+        value: ExprId,
+        span: Span,
+    ) -> ExprKind {
+        let mut temporaries = Vec::new();
+
+        let place = self.place(target, &mut temporaries);
+        let value = self.expr(value);
+
+        let kind = match self.method(id) {
+            Some(position) => {
+                self.call(position, vec![place.clone(), value], place.ty.clone(), span)
+            }
+            None => ExprKind::Binary {
+                op,
+                left: Box::new(place.clone()),
+                right: Box::new(value),
+            },
+        };
+
+        let combined = Expr {
+            kind,
+            ty: place.ty.clone(),
+            span,
+        };
+
+        let assign = ExprKind::Assign {
+            target: Box::new(place),
+            value: Box::new(combined),
+        };
+
+        if temporaries.is_empty() {
+            return assign;
+        }
+
+        temporaries.push(Stmt::Expr(Expr {
+            kind: assign,
+            ty: Ty::unit(),
+            span,
+        }));
+
+        ExprKind::Block(Block {
+            stmts: temporaries,
+            value: None,
+        })
+    }
+
+    /// Lower the target of a compound assignment so it can be read and
+    /// written, moving the parts that could have side effects into
+    /// temporaries, e.g. `f()` in `xs[f()]`.
+    fn place(&mut self, id: ExprId, temporaries: &mut Vec<Stmt>) -> Expr {
+        let ty = self.ty(id);
+        let span = self.parsed.arena[id].span;
+
+        let kind = match &self.parsed.arena[id].kind {
+            ast::ExprKind::Identifier(_) => return self.expr(id),
+            ast::ExprKind::Field { object, field } => ExprKind::Field {
+                object: Box::new(self.place(*object, temporaries)),
+                field: field.clone(),
+            },
+            ast::ExprKind::Index { object, index } => {
+                let object = self.place(*object, temporaries);
+                let index = self.expr(*index);
+
+                ExprKind::Index {
+                    object: Box::new(object),
+                    index: Box::new(self.temporary(index, temporaries)),
+                }
+            }
+            _ => {
+                let value = self.expr(id);
+
+                return self.temporary(value, temporaries);
+            }
+        };
+
+        Expr { kind, ty, span }
+    }
+
+    /// Store a value in a temporary, returning what reads it. Values that
+    /// can be evaluated again without a difference are returned as they are.
+    fn temporary(&mut self, value: Expr, temporaries: &mut Vec<Stmt>) -> Expr {
+        if matches!(
+            value.kind,
+            ExprKind::Int(_)
+                | ExprKind::Bool(_)
+                | ExprKind::Str(_)
+                | ExprKind::Local(_)
+                | ExprKind::Global(_)
+        ) {
+            return value;
+        }
+
+        let local = self.push_local(Local {
+            name: format!("${}", self.temporaries),
+            ty: value.ty.clone(),
+            mutable: false,
+        });
+
+        self.temporaries += 1;
+
+        let read = Expr {
+            kind: ExprKind::Local(local),
+            ty: value.ty.clone(),
+            span: value.span,
+        };
+
+        temporaries.push(Stmt::Let { local, value });
+
+        read
+    }
+
+    /// Lower a pattern matched against a value of a type, declaring the
+    /// locals it binds, which are declared by the node at `span`.
+    fn pattern(&mut self, pattern: &ast::Pattern, ty: &Ty, span: Span) -> Pattern {
+        match (pattern, ty) {
+            (ast::Pattern::Wildcard, _) => Pattern::Wildcard,
+            // A name could be a variant without a payload, which is only
+            // known from the type it is matched against
+            (ast::Pattern::Binding(name), Ty::Optional(_)) if name == "None" => Pattern::None,
+            (ast::Pattern::Binding(name), Ty::Enum(enum_name))
+                if self
+                    .variant(enum_name, name)
+                    .is_some_and(|types| types.is_empty()) =>
+            {
+                Pattern::Variant {
+                    enum_name: enum_name.clone(),
+                    variant: name.clone(),
+                    payload: Vec::new(),
+                }
+            }
+            (ast::Pattern::Binding(name), _) => Pattern::Binding(self.declare(span, name)),
+            (ast::Pattern::Bool(value), _) => Pattern::Bool(*value),
+            (ast::Pattern::Number(number), _) => Pattern::Int(*number as i128),
+            (ast::Pattern::String(string), _) => Pattern::Str(string.clone()),
+            (ast::Pattern::Tuple(patterns), _) => {
+                let types = match ty {
+                    Ty::Tuple(types) => types.clone(),
+                    _ => Vec::new(),
+                };
+
+                Pattern::Tuple(self.patterns(patterns, &types, span))
+            }
+            (
+                ast::Pattern::Variant {
+                    enum_name: None,
+                    variant,
+                    payload,
+                },
+                Ty::Optional(inner),
+            ) if variant == "Some" && payload.len() == 1 => {
+                Pattern::Some(Box::new(self.pattern(&payload[0], inner, span)))
+            }
+            (
+                ast::Pattern::Variant {
+                    enum_name,
+                    variant,
+                    payload,
+                },
+                _,
+            ) => {
+                let enum_name = match ty {
+                    Ty::Enum(name) => name.clone(),
+                    _ => enum_name.clone().unwrap_or_default(),
+                };
+
+                let types = self.variant(&enum_name, variant).unwrap_or_default();
+
+                Pattern::Variant {
+                    payload: self.patterns(payload, &types, span),
+                    enum_name,
+                    variant: variant.clone(),
+                }
+            }
+        }
+    }
+
+    /// Lower patterns matched against the values of a tuple or variant,
+    /// whose types are `types` when they are known.
+    fn patterns(&mut self, patterns: &[ast::Pattern], types: &[Ty], span: Span) -> Vec<Pattern> {
+        patterns
+            .iter()
+            .enumerate()
+            .map(|(i, pattern)| self.pattern(pattern, types.get(i).unwrap_or(&Ty::Unknown), span))
+            .collect()
+    }
+
+    /// The payload types of a variant of an enum, if it has the variant.
+    fn variant(&self, enum_name: &str, variant: &str) -> Option<Vec<Ty>> {
+        self.types
+            .enums
+            .get(enum_name)?
+            .iter()
+            .find(|(name, _)| name == variant)
+            .map(|(_, types)| types.clone())
+    }
+
+    /// An expression for a value known when compiling, which has a type.
+    /// Integers that don't fit their type are left to be evaluated when the
+    /// program runs.
+    fn value(&self, value: &Value, ty: &Ty, span: Span) -> Option<Expr> {
+        let kind = match (value, ty) {
+            (&Value::Int(int), Ty::Int(int_ty)) if (int_ty.min()..=int_ty.max()).contains(&int) => {
+                ExprKind::Int(int)
+            }
+            (Value::Bool(value), Ty::Bool) => ExprKind::Bool(*value),
+            (Value::Str(string), Ty::Str) => ExprKind::Str(string.clone()),
+            (Value::Tuple(values), Ty::Tuple(types)) if values.len() == types.len() => {
+                ExprKind::Tuple(
+                    values
+                        .iter()
+                        .zip(types)
+                        .map(|(value, ty)| self.value(value, ty, span))
+                        .collect::<Option<_>>()?,
+                )
+            }
+            (Value::Array(values), Ty::Array { element, .. }) => ExprKind::Array(
+                values
+                    .iter()
+                    .map(|value| self.value(value, element, span))
+                    .collect::<Option<_>>()?,
+            ),
+            _ => return None,
+        };
+
+        Some(Expr {
+            kind,
+            ty: ty.clone(),
+            span,
+        })
+    }
+}
@@ -0,0 +1,328 @@
+use super::{Block, Expr, ExprKind, Local, LocalId, Pattern, Program, Stmt};
+
+/// Print a lowered program as an indented tree, one node per line with its
+/// type, for `--emit hir`. Locals are printed with their names and ids.
+///
+/// E.g. `func f(a : u32) : u32 = { a + 1 };` is printed as:
+///
+/// ```text
+/// Program
+///   Function f(a#0) : u32
+///     Block : u32
+///       Value
+///         Binary + : u32
+///           Local a#0 : u32
+///           Int 1 : u32
+/// ```
+pub fn print_tree(program: &Program) -> String {
+    let mut printer = Printer {
+        output: String::new(),
+        depth: 0,
+        program,
+        locals: &[],
+    };
+
+    printer.node("Program", |printer| {
+        for structure in &program.structs {
+            let name = if structure.generics.is_empty() {
+                structure.name.clone()
+            } else {
+                format!("{}<{}>", structure.name, structure.generics.join(", "))
+            };
+
+            printer.node(format!("Struct {}", name), |printer| {
+                for (name, ty) in &structure.fields {
+                    printer.leaf(format!("Field {} : {}", name, ty));
+                }
+            });
+        }
+
+        for enumeration in &program.enums {
+            printer.node(format!("Enum {}", enumeration.name), |printer| {
+                for (name, payload) in &enumeration.variants {
+                    if payload.is_empty() {
+                        printer.leaf(format!("Variant {}", name));
+                    } else {
+                        let payload: Vec<_> = payload.iter().map(ToString::to_string).collect();
+
+                        printer.leaf(format!("Variant {}({})", name, payload.join(", ")));
+                    }
+                }
+            });
+        }
+
+        for global in &program.globals {
+            printer.locals = &global.locals;
+
+            let name = mutable(&global.name, global.mutable);
+
+            printer.node(format!("Global {} : {}", name, global.ty), |printer| {
+                printer.expr(&global.value)
+            });
+        }
+
+        for function in &program.functions {
+            printer.locals = &function.locals;
+
+            let params: Vec<_> = function
+                .params
+                .iter()
+                .map(|&param| printer.local(param))
+                .collect();
+
+            let label = format!(
+                "Function {}({}) : {}",
+                function.full_name(),
+                params.join(", "),
+                function.ret
+            );
+
+            printer.node(label, |printer| printer.expr(&function.body));
+        }
+    });
+
+    printer.output
+}
+
+struct Printer<'a> {
+    output: String,
+    /// How many levels the current node is nested
+    depth: usize,
+    /// The program being printed, for the names of functions and globals
+    program: &'a Program,
+    /// The locals of the function or global being printed
+    locals: &'a [Local],
+}
+
+impl Printer<'_> {
+    /// Write a line for a node, with its children indented under it.
+    fn node(&mut self, label: impl AsRef<str>, children: impl FnOnce(&mut Self)) {
+        self.output.push_str(&"  ".repeat(self.depth));
+        self.output.push_str(label.as_ref());
+        self.output.push('\n');
+
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    /// Write a line for a node without any children.
+    fn leaf(&mut self, label: impl AsRef<str>) {
+        self.node(label, |_| {});
+    }
+
+    /// A local's name and id, e.g. `a#0`.
+    fn local(&self, id: LocalId) -> String {
+        match self.locals.get(id.index()) {
+            Some(local) => format!("{}{}", mutable(&local.name, local.mutable), id),
+            None => id.to_string(),
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+
+        if let Some(value) = &block.value {
+            self.node("Value", |printer| printer.expr(value));
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { local, value } => {
+                let ty = &self.locals[local.index()].ty;
+                let label = format!("Let {} : {}", self.local(*local), ty);
+
+                self.node(label, |printer| printer.expr(value));
+            }
+            Stmt::Assert { condition, message } => {
+                let label = match message {
+                    Some(message) => format!("Assert {:?}", message),
+                    None => "Assert".to_string(),
+                };
+
+                self.node(label, |printer| printer.expr(condition));
+            }
+            Stmt::Defer(body) => self.node("Defer", |printer| printer.block(body)),
+            Stmt::Expr(expr) => self.expr(expr),
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        let label = |name: String| format!("{} : {}", name, expr.ty);
+
+        match &expr.kind {
+            ExprKind::Int(int) => self.leaf(label(format!("Int {}", int))),
+            ExprKind::Bool(value) => self.leaf(label(format!("Bool {}", value))),
+            ExprKind::Str(string) => self.leaf(label(format!("Str {:?}", string))),
+            ExprKind::Local(local) => {
+                let name = self.local(*local);
+
+                self.leaf(label(format!("Local {}", name)));
+            }
+            ExprKind::Function(function) => {
+                let name = self.program.functions[*function].full_name();
+
+                self.leaf(label(format!("Function {}", name)));
+            }
+            ExprKind::Global(global) => {
+                let name = &self.program.globals[*global].name;
+
+                self.leaf(label(format!("Global {}", name)));
+            }
+            ExprKind::Import(name) => self.leaf(label(format!("Import {}", name))),
+            ExprKind::Tuple(elements) => self.node(label("Tuple".to_string()), |printer| {
+                printer.exprs(elements)
+            }),
+            ExprKind::Array(elements) => self.node(label("Array".to_string()), |printer| {
+                printer.exprs(elements)
+            }),
+            ExprKind::Struct { name, fields } => {
+                self.node(label(format!("Struct {}", name)), |printer| {
+                    for (field, value) in fields {
+                        printer.node(format!("Field {}", field), |printer| printer.expr(value));
+                    }
+                });
+            }
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => self.node(
+                label(format!("Variant {}::{}", enum_name, variant)),
+                |printer| printer.exprs(payload),
+            ),
+            ExprKind::Block(block) => {
+                self.node(label("Block".to_string()), |printer| printer.block(block))
+            }
+            ExprKind::Unary { op, operand } => {
+                self.node(label(format!("Unary {}", op.symbol())), |printer| {
+                    printer.expr(operand)
+                });
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.node(label(format!("Binary {}", op.symbol())), |printer| {
+                    printer.expr(left);
+                    printer.expr(right);
+                });
+            }
+            ExprKind::Cast(value) => {
+                self.node(label("Cast".to_string()), |printer| printer.expr(value))
+            }
+            ExprKind::Call { callee, args } => self.node(label("Call".to_string()), |printer| {
+                printer.expr(callee);
+                printer.exprs(args);
+            }),
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => self.node(label(format!("MethodCall {}", method)), |printer| {
+                printer.expr(receiver);
+                printer.exprs(args);
+            }),
+            ExprKind::Index { object, index } => self.node(label("Index".to_string()), |printer| {
+                printer.expr(object);
+                printer.expr(index);
+            }),
+            ExprKind::Field { object, field } => self
+                .node(label(format!("Field {}", field)), |printer| {
+                    printer.expr(object)
+                }),
+            ExprKind::Assign { target, value } => {
+                self.node(label("Assign".to_string()), |printer| {
+                    printer.expr(target);
+                    printer.expr(value);
+                })
+            }
+            ExprKind::Closure {
+                params,
+                captures,
+                body,
+            } => {
+                let params: Vec<_> = params.iter().map(|&param| self.local(param)).collect();
+                let mut name = format!("Closure({})", params.join(", "));
+
+                if !captures.is_empty() {
+                    let captures: Vec<_> =
+                        captures.iter().map(|&local| self.local(local)).collect();
+
+                    name = format!("{} capturing {}", name, captures.join(", "));
+                }
+
+                self.node(label(name), |printer| printer.expr(body));
+            }
+            ExprKind::Match { value, arms } => self.node(label("Match".to_string()), |printer| {
+                printer.expr(value);
+
+                for arm in arms {
+                    let pattern = printer.pattern(&arm.pattern);
+
+                    printer.node(format!("Arm {}", pattern), |printer| {
+                        printer.expr(&arm.value)
+                    });
+                }
+            }),
+            ExprKind::Loop(body) => {
+                self.node(label("Loop".to_string()), |printer| printer.block(body))
+            }
+            ExprKind::Break => self.leaf(label("Break".to_string())),
+            ExprKind::Continue => self.leaf(label("Continue".to_string())),
+            ExprKind::Return(value) => self.node(label("Return".to_string()), |printer| {
+                if let Some(value) = value {
+                    printer.expr(value);
+                }
+            }),
+        }
+    }
+
+    /// A pattern as it would be written, with its bindings' ids.
+    fn pattern(&self, pattern: &Pattern) -> String {
+        let list = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .map(|pattern| self.pattern(pattern))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match pattern {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Binding(local) => self.local(*local),
+            Pattern::Bool(value) => value.to_string(),
+            Pattern::Int(int) => int.to_string(),
+            Pattern::Str(string) => format!("{:?}", string),
+            Pattern::Tuple(patterns) if patterns.len() == 1 => format!("({},)", list(patterns)),
+            Pattern::Tuple(patterns) => format!("({})", list(patterns)),
+            Pattern::Some(inner) => format!("Some({})", self.pattern(inner)),
+            Pattern::None => "None".to_string(),
+            Pattern::Variant {
+                enum_name,
+                variant,
+                payload,
+            } if payload.is_empty() => format!("{}::{}", enum_name, variant),
+            Pattern::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => format!("{}::{}({})", enum_name, variant, list(payload)),
+        }
+    }
+}
+
+/// A name, with `mut` before it if it can be assigned to.
+fn mutable(name: &str, mutable: bool) -> String {
+    if mutable {
+        format!("mut {}", name)
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,312 @@
+//! The SSA intermediate representation, between the HIR and the backends,
+//! which optimizations and code generators can analyze without walking
+//! trees.
+//!
+//! A function is a list of basic blocks, the first of which it starts at:
+//!
+//! - Every value is defined once, by a parameter, a phi node or an
+//!   instruction, and has a type.
+//! - A block is phi nodes, then instructions, then a terminator that jumps
+//!   to other blocks, returns or fails.
+//! - A phi node picks a value by the block that jumped to its block, so
+//!   variables that are assigned in loops and branches are values too.
+//! - Arithmetic is checked like it is by the bytecode VM, so `add` fails
+//!   with a runtime error if the result doesn't fit its type.
+//! - Values of type `()` are only made when something needs one, e.g. an
+//!   argument, so calls of functions returning `()`, prints and stores
+//!   have no result.
+//!
+//! A program is lowered by `lower`, checked by `verify` and printed by
+//! `print`.
+use crate::lexer::Span;
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use crate::typeck::IntTy;
+use std::fmt;
+
+pub mod lower;
+pub mod printer;
+pub mod verify;
+
+pub use lower::lower;
+pub use printer::print;
+pub use verify::verify;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A value of a function, by its position in the function's `values`.
+pub struct Value(u32);
+
+impl Value {
+    pub fn new(index: usize) -> Self {
+        Self(index as u32)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A basic block of a function, by its position in the function's
+/// `blocks`.
+pub struct BlockId(u32);
+
+impl BlockId {
+    /// The block a function starts at.
+    pub const ENTRY: BlockId = BlockId(0);
+
+    pub fn new(index: usize) -> Self {
+        Self(index as u32)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "block{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The type of a value. Only types whose values fit in a register, and
+/// strings, have values in the IR.
+pub enum Type {
+    Int(IntTy),
+    Bool,
+    Unit,
+    Str,
+    Function { params: Vec<Type>, ret: Box<Type> },
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int(ty) => write!(f, "{}", ty.name()),
+            Type::Bool => write!(f, "bool"),
+            Type::Unit => write!(f, "()"),
+            Type::Str => write!(f, "str"),
+            Type::Function { params, ret } => {
+                let params: Vec<_> = params.iter().map(ToString::to_string).collect();
+
+                write!(f, "func({}) -> {}", params.join(", "), ret)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A lowered program, with its functions and globals in the same order as
+/// in the HIR, so they have the same positions.
+pub struct Program {
+    pub functions: Vec<Function>,
+    pub globals: Vec<Global>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Global {
+    pub name: String,
+    pub ty: Type,
+    pub mutable: bool,
+    /// The function computing the global's value, which is called before
+    /// `main`
+    pub init: Function,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    /// The function's full name, e.g. `max<u32>`
+    pub name: String,
+    /// The values the arguments are, in order
+    pub params: Vec<Value>,
+    pub ret: Type,
+    /// The type of every value, by position
+    pub values: Vec<Type>,
+    /// The function's blocks, starting with the one it starts at
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub phis: Vec<Phi>,
+    pub insts: Vec<Inst>,
+    pub terminator: Terminator,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A value that is one of `incoming`, depending on which block jumped to
+/// the phi's block. There is one for each block that jumps to it.
+pub struct Phi {
+    pub value: Value,
+    pub incoming: Vec<(BlockId, Value)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inst {
+    /// The value the instruction defines, if its result isn't `()`
+    pub result: Option<Value>,
+    pub kind: InstKind,
+    /// The code it was lowered from, for runtime errors
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstKind {
+    /// An integer of the result's type
+    Int(i128),
+    Bool(bool),
+    Str(String),
+    Unit,
+    /// A value that is never used, e.g. a variable read in code that can't
+    /// be reached
+    Undef,
+    /// `-` of an integer, which is checked, or `!` of a boolean
+    Unary {
+        op: UnaryOp,
+        operand: Value,
+    },
+    /// Checked arithmetic or a comparison. `&&` and `||` are branches.
+    Binary {
+        op: BinaryOp,
+        left: Value,
+        right: Value,
+    },
+    /// Converting a value to the result's type
+    Cast(Value),
+    /// A function of the program, by its position
+    Function(usize),
+    Call {
+        callee: Callee,
+        args: Vec<Value>,
+    },
+    /// The value of a global, by its position
+    Global(usize),
+    SetGlobal {
+        global: usize,
+        value: Value,
+    },
+    /// Print values on a line, separated by spaces
+    Print(Vec<Value>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Callee {
+    /// A function of the program, by its position, called directly
+    Function(usize),
+    /// A function value
+    Value(Value),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: Value,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    /// Return from the function, with a value unless it returns `()`
+    Return(Option<Value>),
+    /// Stop the program with a runtime error, e.g. when an assertion fails
+    Fail {
+        message: String,
+        span: Span,
+    },
+    /// Code that can't be reached, e.g. after the arms of a match
+    Unreachable,
+}
+
+impl InstKind {
+    /// The values the instruction uses, in order.
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            InstKind::Unary { operand: value, .. }
+            | InstKind::Cast(value)
+            | InstKind::SetGlobal { value, .. } => vec![*value],
+            InstKind::Binary { left, right, .. } => vec![*left, *right],
+            InstKind::Call { callee, args } => match callee {
+                Callee::Function(_) => args.clone(),
+                Callee::Value(callee) => std::iter::once(*callee).chain(args.clone()).collect(),
+            },
+            InstKind::Print(args) => args.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Change every value the instruction uses.
+    pub fn map_operands(&mut self, mut map: impl FnMut(Value) -> Value) {
+        match self {
+            InstKind::Unary { operand: value, .. }
+            | InstKind::Cast(value)
+            | InstKind::SetGlobal { value, .. } => *value = map(*value),
+            InstKind::Binary { left, right, .. } => {
+                *left = map(*left);
+                *right = map(*right);
+            }
+            InstKind::Call { callee, args } => {
+                if let Callee::Value(callee) = callee {
+                    *callee = map(*callee);
+                }
+
+                args.iter_mut().for_each(|arg| *arg = map(*arg));
+            }
+            InstKind::Print(args) => args.iter_mut().for_each(|arg| *arg = map(*arg)),
+            _ => {}
+        }
+    }
+}
+
+impl Terminator {
+    /// The blocks the terminator can jump to.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![*then, *otherwise],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The value the terminator uses, if any.
+    pub fn operand(&self) -> Option<Value> {
+        match self {
+            Terminator::Branch { condition, .. } => Some(*condition),
+            Terminator::Return(value) => *value,
+            _ => None,
+        }
+    }
+}
+
+impl Function {
+    pub fn ty(&self, value: Value) -> &Type {
+        &self.values[value.index()]
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.index()]
+    }
+
+    /// The blocks that jump to each block, by position. A block that jumps
+    /// to another one from both sides of a branch is listed twice.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            for successor in block.terminator.successors() {
+                if let Some(list) = predecessors.get_mut(successor.index()) {
+                    list.push(BlockId::new(i));
+                }
+            }
+        }
+
+        predecessors
+    }
+}
@@ -0,0 +1,989 @@
+use super::{
+    Block, BlockId, Callee, Function, Global, Inst, InstKind, Phi, Program, Terminator, Type, Value,
+};
+use crate::backend::{self, CodegenError, CodegenErrorKind, Failure};
+use crate::hir::{self, Arm, Expr, ExprKind, Local, LocalId, Pattern, Stmt};
+use crate::lexer::Span;
+use crate::parser::ast::BinaryOp;
+use crate::typeck::Ty;
+use std::collections::{HashMap, HashSet};
+
+/// Lower a program to SSA, or report what it uses that the IR can't
+/// represent yet, e.g. closures.
+///
+/// Variables become values with the algorithm of Braun et al., "Simple and
+/// Efficient Construction of Static Single Assignment Form": reading a
+/// variable looks for its value in the blocks that jump to the current one,
+/// making a phi node where they disagree. A block is sealed once every
+/// block that jumps to it is known, e.g. a loop's header once its body is
+/// lowered, and phi nodes that turn out to pick the same value every time
+/// are removed at the end.
+pub fn lower(program: &hir::Program) -> Result<Program, Vec<CodegenError>> {
+    let mut errors = Vec::new();
+    let mut lowered = Program::default();
+
+    for function in &program.functions {
+        let mut builder = Builder::new(&function.locals, &mut errors);
+        let ret = builder.ty(&function.ret, function.span);
+
+        lowered.functions.push(builder.function(
+            function.full_name(),
+            &function.params,
+            ret,
+            &function.body,
+        ));
+    }
+
+    for global in &program.globals {
+        let mut builder = Builder::new(&global.locals, &mut errors);
+        let ty = builder.ty(&global.ty, global.span);
+        let init = builder.function(
+            format!("init.{}", global.name),
+            &[],
+            ty.clone(),
+            &global.value,
+        );
+
+        lowered.globals.push(Global {
+            name: global.name.clone(),
+            ty,
+            mutable: global.mutable,
+            init,
+        });
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(lowered)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Something that is assigned and read, and becomes values.
+enum Var {
+    Local(LocalId),
+    /// A temporary the lowering made, e.g. for the value of a match, by its
+    /// position in `temporaries`
+    Temporary(usize),
+}
+
+/// A block being built.
+#[derive(Default)]
+struct BlockData {
+    phis: Vec<Phi>,
+    insts: Vec<Inst>,
+    terminator: Option<Terminator>,
+    predecessors: Vec<BlockId>,
+    /// Whether every block that jumps to it is known
+    sealed: bool,
+    /// The phi nodes made for variables read before it was sealed, which
+    /// are given their incoming values when it is
+    incomplete: Vec<(Var, Value)>,
+}
+
+struct Loop {
+    /// The block `continue` jumps to
+    header: BlockId,
+    /// The block `break` jumps to
+    exit: BlockId,
+    /// How many blocks were being lowered when the loop started
+    scopes: usize,
+}
+
+/// A function being lowered.
+struct Builder<'a, 'e> {
+    locals: &'a [Local],
+    errors: &'e mut Vec<CodegenError>,
+    /// The type of each value
+    values: Vec<Type>,
+    blocks: Vec<BlockData>,
+    /// The block instructions are added to
+    current: BlockId,
+    /// The value each variable has at the end of each block, as far as it
+    /// has been lowered
+    definitions: HashMap<(BlockId, Var), Value>,
+    /// The type of each temporary
+    temporaries: Vec<Type>,
+    /// What the function returns
+    ret: Type,
+    /// The deferred blocks of each block being lowered, innermost last
+    scopes: Vec<Vec<&'a hir::Block>>,
+    /// The loops being lowered, innermost last
+    loops: Vec<Loop>,
+}
+
+impl<'a, 'e> Builder<'a, 'e> {
+    fn new(locals: &'a [Local], errors: &'e mut Vec<CodegenError>) -> Self {
+        Self {
+            locals,
+            errors,
+            values: Vec::new(),
+            blocks: Vec::new(),
+            current: BlockId::ENTRY,
+            definitions: HashMap::new(),
+            temporaries: Vec::new(),
+            ret: Type::Unit,
+            scopes: Vec::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    /// Lower a function whose arguments are in `params`, returning the
+    /// value of `body`.
+    fn function(mut self, name: String, params: &[LocalId], ret: Type, body: &'a Expr) -> Function {
+        self.ret = ret.clone();
+
+        let entry = self.new_block();
+
+        self.seal(entry);
+
+        let params: Vec<_> = params
+            .iter()
+            .map(|&param| {
+                let local = &self.locals[param.index()];
+                let ty = self.ty(&local.ty, body.span);
+                let value = self.new_value(ty);
+
+                self.write(Var::Local(param), value);
+
+                value
+            })
+            .collect();
+
+        let value = match ret {
+            Type::Unit => {
+                self.expr(body);
+
+                None
+            }
+            _ => Some(self.operand(body)),
+        };
+
+        self.finish(Terminator::Return(value));
+
+        self.build(name, params, ret)
+    }
+
+    /// Report something the IR can't represent yet.
+    fn unsupported(&mut self, what: &str, span: Span) {
+        let error = CodegenError::new(
+            CodegenErrorKind::Unsupported {
+                backend: "SSA IR",
+                what: what.to_string(),
+            },
+            span,
+        );
+
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    /// The IR type of a type, reporting types it has no values of.
+    fn ty(&mut self, ty: &Ty, span: Span) -> Type {
+        match convert(ty) {
+            Ok(ty) => ty,
+            Err(what) => {
+                self.unsupported(&what, span);
+
+                Type::Unit
+            }
+        }
+    }
+
+    fn var_type(&self, var: Var) -> Type {
+        match var {
+            Var::Local(local) => convert(&self.locals[local.index()].ty).unwrap_or(Type::Unit),
+            Var::Temporary(position) => self.temporaries[position].clone(),
+        }
+    }
+
+    fn temporary(&mut self, ty: Type) -> Var {
+        self.temporaries.push(ty);
+
+        Var::Temporary(self.temporaries.len() - 1)
+    }
+
+    fn new_value(&mut self, ty: Type) -> Value {
+        self.values.push(ty);
+
+        Value::new(self.values.len() - 1)
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BlockData::default());
+
+        BlockId::new(self.blocks.len() - 1)
+    }
+
+    fn block_data(&mut self, block: BlockId) -> &mut BlockData {
+        &mut self.blocks[block.index()]
+    }
+
+    /// Add an instruction to the current block, with a result of type `ty`
+    /// if there is one.
+    fn emit(&mut self, kind: InstKind, ty: Option<Type>, span: Span) -> Option<Value> {
+        let result = ty.map(|ty| self.new_value(ty));
+        let current = self.current;
+
+        self.block_data(current)
+            .insts
+            .push(Inst { result, kind, span });
+
+        result
+    }
+
+    /// Add an instruction with a result to the current block.
+    fn value(&mut self, kind: InstKind, ty: Type, span: Span) -> Value {
+        let result = self.new_value(ty);
+        let current = self.current;
+
+        self.block_data(current).insts.push(Inst {
+            result: Some(result),
+            kind,
+            span,
+        });
+
+        result
+    }
+
+    /// End the current block, and start a new one. Nothing jumps to the new
+    /// block unless it is jumped to later, so what is lowered after e.g. a
+    /// `return` is removed with it.
+    fn finish(&mut self, terminator: Terminator) {
+        let current = self.current;
+
+        for successor in terminator.successors() {
+            self.block_data(successor).predecessors.push(current);
+        }
+
+        self.block_data(current).terminator = Some(terminator);
+        self.current = self.new_block();
+        self.seal(self.current);
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = block;
+    }
+
+    fn write(&mut self, var: Var, value: Value) {
+        self.definitions.insert((self.current, var), value);
+    }
+
+    fn read(&mut self, var: Var) -> Value {
+        self.read_in(var, self.current)
+    }
+
+    /// The value a variable has at the end of a block.
+    fn read_in(&mut self, var: Var, block: BlockId) -> Value {
+        if let Some(&value) = self.definitions.get(&(block, var)) {
+            return value;
+        }
+
+        let data = &self.blocks[block.index()];
+
+        let value = if !data.sealed {
+            let phi = self.phi(var, block);
+
+            self.block_data(block).incomplete.push((var, phi));
+
+            phi
+        } else if let [predecessor] = data.predecessors[..] {
+            self.read_in(var, predecessor)
+        } else if data.predecessors.is_empty() {
+            // Only blocks that can't be reached have no predecessors, besides
+            // the entry, where every variable that is read was written
+            let value = self.new_value(self.var_type(var));
+
+            self.block_data(block).insts.insert(
+                0,
+                Inst {
+                    result: Some(value),
+                    kind: InstKind::Undef,
+                    span: Span::default(),
+                },
+            );
+
+            value
+        } else {
+            let phi = self.phi(var, block);
+
+            // Loops read the variable again before the phi has its values
+            self.definitions.insert((block, var), phi);
+            self.add_incoming(var, phi, block);
+
+            phi
+        };
+
+        self.definitions.insert((block, var), value);
+
+        value
+    }
+
+    fn phi(&mut self, var: Var, block: BlockId) -> Value {
+        let value = self.new_value(self.var_type(var));
+
+        self.block_data(block).phis.push(Phi {
+            value,
+            incoming: Vec::new(),
+        });
+
+        value
+    }
+
+    /// Give a phi node the value of its variable in each predecessor.
+    fn add_incoming(&mut self, var: Var, phi: Value, block: BlockId) {
+        for predecessor in self.blocks[block.index()].predecessors.clone() {
+            let value = self.read_in(var, predecessor);
+
+            if let Some(phi) = self
+                .block_data(block)
+                .phis
+                .iter_mut()
+                .find(|node| node.value == phi)
+            {
+                phi.incoming.push((predecessor, value));
+            }
+        }
+    }
+
+    /// Note that every block jumping to a block is known.
+    fn seal(&mut self, block: BlockId) {
+        for (var, phi) in std::mem::take(&mut self.block_data(block).incomplete) {
+            self.add_incoming(var, phi, block);
+        }
+
+        self.block_data(block).sealed = true;
+    }
+
+    /// Lower an expression, returning its value unless it is `()`, never
+    /// finishes or can't be lowered.
+    fn expr(&mut self, expr: &'a Expr) -> Option<Value> {
+        // Calls of imports have no type
+        let imported = matches!(
+            &expr.kind,
+            ExprKind::Call { callee, .. } if matches!(callee.kind, ExprKind::Import(_))
+        );
+
+        let ty = match imported {
+            true => Type::Unit,
+            false => self.ty(&expr.ty, expr.span),
+        };
+        let span = expr.span;
+
+        match &expr.kind {
+            ExprKind::Int(value) => Some(self.value(InstKind::Int(*value), ty, span)),
+            ExprKind::Bool(value) => Some(self.value(InstKind::Bool(*value), ty, span)),
+            ExprKind::Str(text) => Some(self.value(InstKind::Str(text.clone()), ty, span)),
+            ExprKind::Local(local) => Some(self.read(Var::Local(*local))),
+            ExprKind::Function(position) => {
+                Some(self.value(InstKind::Function(*position), ty, span))
+            }
+            ExprKind::Global(position) => Some(self.value(InstKind::Global(*position), ty, span)),
+            ExprKind::Import(name) => {
+                self.unsupported(&format!("'{}' from another file", name), span);
+
+                None
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Unary { op, operand } => {
+                let operand = self.operand(operand);
+
+                Some(self.value(InstKind::Unary { op: *op, operand }, ty, span))
+            }
+            ExprKind::Binary { op, left, right } => Some(self.binary(*op, left, right, ty, span)),
+            ExprKind::Cast(value) => {
+                let value = self.operand(value);
+
+                Some(self.value(InstKind::Cast(value), ty, span))
+            }
+            ExprKind::Call { callee, args } => self.call(callee, args, ty, span),
+            ExprKind::MethodCall { method, .. } => {
+                self.unsupported(&format!("the method '{}'", method), span);
+
+                None
+            }
+            ExprKind::Index { .. } => {
+                self.unsupported("indexing", span);
+
+                None
+            }
+            ExprKind::Field { .. } => {
+                self.unsupported("fields", span);
+
+                None
+            }
+            ExprKind::Assign { target, value } => {
+                self.assignment(target, value);
+
+                None
+            }
+            ExprKind::Closure { .. } => {
+                self.unsupported("closures", span);
+
+                None
+            }
+            ExprKind::Match { value, arms } => self.matching(value, arms, ty),
+            ExprKind::Loop(body) => {
+                self.looping(body);
+
+                None
+            }
+            ExprKind::Break | ExprKind::Continue => {
+                let Some(innermost) = self.loops.last() else {
+                    self.unsupported("leaving a loop outside of one", span);
+
+                    return None;
+                };
+
+                let scopes = innermost.scopes;
+                let target = match expr.kind {
+                    ExprKind::Break => innermost.exit,
+                    _ => innermost.header,
+                };
+
+                self.run_defers(scopes);
+                self.finish(Terminator::Jump(target));
+
+                None
+            }
+            ExprKind::Return(value) => {
+                let value = match (value, &self.ret) {
+                    (Some(value), Type::Unit) => {
+                        self.expr(value);
+
+                        None
+                    }
+                    (Some(value), _) => Some(self.operand(value)),
+                    (None, _) => None,
+                };
+
+                self.run_defers(0);
+                self.finish(Terminator::Return(value));
+
+                None
+            }
+            // `()` has no value, and the types of the others have no values
+            // in the IR, which is reported above
+            ExprKind::Tuple(_)
+            | ExprKind::Array(_)
+            | ExprKind::Struct { .. }
+            | ExprKind::Variant { .. } => None,
+        }
+    }
+
+    /// Lower an expression whose value is needed, making one if it has
+    /// none.
+    fn operand(&mut self, expr: &'a Expr) -> Value {
+        if let Some(value) = self.expr(expr) {
+            return value;
+        }
+
+        match convert(&expr.ty) {
+            Ok(Type::Unit) => self.value(InstKind::Unit, Type::Unit, expr.span),
+            ty => self.value(InstKind::Undef, ty.unwrap_or(Type::Unit), expr.span),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: BinaryOp,
+        left: &'a Expr,
+        right: &'a Expr,
+        ty: Type,
+        span: Span,
+    ) -> Value {
+        if let Some(decided) = backend::short_circuit(op) {
+            let result = self.temporary(Type::Bool);
+            let left = self.operand(left);
+            let (rest, end) = (self.new_block(), self.new_block());
+
+            self.write(result, left);
+
+            let (then, otherwise) = match decided {
+                true => (end, rest),
+                false => (rest, end),
+            };
+
+            self.finish(Terminator::Branch {
+                condition: left,
+                then,
+                otherwise,
+            });
+            self.seal(rest);
+            self.switch_to(rest);
+
+            let right = self.operand(right);
+
+            self.write(result, right);
+            self.finish(Terminator::Jump(end));
+            self.seal(end);
+            self.switch_to(end);
+
+            return self.read(result);
+        }
+
+        let (left, right) = (self.operand(left), self.operand(right));
+
+        self.value(InstKind::Binary { op, left, right }, ty, span)
+    }
+
+    fn call(&mut self, callee: &'a Expr, args: &'a [Expr], ty: Type, span: Span) -> Option<Value> {
+        if let ExprKind::Import(name) = &callee.kind {
+            if name == "print" {
+                let args = args.iter().map(|arg| self.operand(arg)).collect();
+
+                return self.emit(InstKind::Print(args), None, span);
+            }
+
+            self.unsupported(&format!("'{}' from another file", name), callee.span);
+
+            return None;
+        }
+
+        // Functions are called directly, and anything else by its value
+        let callee = match &callee.kind {
+            ExprKind::Function(position) => Callee::Function(*position),
+            _ => Callee::Value(self.operand(callee)),
+        };
+        let args = args.iter().map(|arg| self.operand(arg)).collect();
+        let ty = match ty {
+            Type::Unit => None,
+            ty => Some(ty),
+        };
+
+        self.emit(InstKind::Call { callee, args }, ty, span)
+    }
+
+    fn assignment(&mut self, target: &'a Expr, value: &'a Expr) {
+        match &target.kind {
+            ExprKind::Local(local) => {
+                let value = self.operand(value);
+
+                self.write(Var::Local(*local), value);
+            }
+            ExprKind::Global(global) => {
+                let value = self.operand(value);
+
+                self.emit(
+                    InstKind::SetGlobal {
+                        global: *global,
+                        value,
+                    },
+                    None,
+                    target.span,
+                );
+            }
+            // Anything else is in a value the IR can't represent, which is
+            // reported when it is lowered
+            _ => {
+                self.expr(target);
+            }
+        }
+    }
+
+    /// Lower a block, running what it deferred once it has its value.
+    fn block(&mut self, block: &'a hir::Block) -> Option<Value> {
+        self.scopes.push(Vec::new());
+
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+
+        let value = block.value.as_ref().and_then(|value| self.expr(value));
+        let deferred = self.scopes.pop().unwrap_or_default();
+
+        for body in backend::unwinding(&[deferred]) {
+            self.block(body);
+        }
+
+        value
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Let { local, value } => {
+                let value = self.operand(value);
+
+                self.write(Var::Local(*local), value);
+            }
+            Stmt::Assert { condition, message } => {
+                let message = Failure::AssertionFailed(message.clone()).message();
+
+                let value = self.operand(condition);
+                let (passed, failed) = (self.new_block(), self.new_block());
+
+                self.finish(Terminator::Branch {
+                    condition: value,
+                    then: passed,
+                    otherwise: failed,
+                });
+                self.seal(passed);
+                self.seal(failed);
+                self.switch_to(failed);
+                self.finish(Terminator::Fail {
+                    message,
+                    span: condition.span,
+                });
+                self.switch_to(passed);
+            }
+            Stmt::Defer(body) => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push(body);
+                }
+            }
+            Stmt::Expr(expr) => {
+                self.expr(expr);
+            }
+        }
+    }
+
+    fn run_defers(&mut self, scope: usize) {
+        for body in backend::unwinding(&self.scopes[scope..]) {
+            self.block(body);
+        }
+    }
+
+    fn looping(&mut self, body: &'a hir::Block) {
+        let (header, exit) = (self.new_block(), self.new_block());
+
+        self.finish(Terminator::Jump(header));
+        self.switch_to(header);
+        self.loops.push(Loop {
+            header,
+            exit,
+            scopes: self.scopes.len(),
+        });
+
+        self.block(body);
+        self.finish(Terminator::Jump(header));
+
+        // Every `continue` and `break` is known once the body is lowered
+        self.loops.pop();
+        self.seal(header);
+        self.seal(exit);
+        self.switch_to(exit);
+    }
+
+    fn matching(&mut self, value: &'a Expr, arms: &'a [Arm], ty: Type) -> Option<Value> {
+        let scrutinee = self.operand(value);
+        let end = self.new_block();
+        let result = match ty {
+            Type::Unit => None,
+            ty => Some(self.temporary(ty)),
+        };
+
+        for arm in arms {
+            let next = self.pattern(&arm.pattern, scrutinee, arm.value.span);
+
+            if let Some(result) = result {
+                let value = self.operand(&arm.value);
+
+                self.write(result, value);
+            } else {
+                self.expr(&arm.value);
+            }
+
+            self.finish(Terminator::Jump(end));
+
+            // The arms after one that matches everything are never tried
+            let Some(next) = next else {
+                break;
+            };
+
+            self.switch_to(next);
+        }
+
+        // The arms cover every value
+        self.finish(Terminator::Unreachable);
+        self.seal(end);
+        self.switch_to(end);
+
+        result.map(|result| self.read(result))
+    }
+
+    /// Lower a pattern matched against a value, binding what it binds and
+    /// continuing where it matched. Returns the block where it didn't, if
+    /// it can fail to match.
+    fn pattern(&mut self, pattern: &Pattern, scrutinee: Value, span: Span) -> Option<BlockId> {
+        let (condition, matches) = match pattern {
+            Pattern::Wildcard => return None,
+            Pattern::Binding(local) => {
+                self.write(Var::Local(*local), scrutinee);
+
+                return None;
+            }
+            Pattern::Tuple(patterns) if patterns.is_empty() => return None,
+            Pattern::Bool(value) => (scrutinee, *value),
+            Pattern::Int(value) => {
+                let ty = self.values[scrutinee.index()].clone();
+                let constant = self.value(InstKind::Int(*value), ty, span);
+                let equal = InstKind::Binary {
+                    op: BinaryOp::Equal,
+                    left: scrutinee,
+                    right: constant,
+                };
+
+                (self.value(equal, Type::Bool, span), true)
+            }
+            Pattern::Str(_) => {
+                self.unsupported("matching strings", span);
+
+                return None;
+            }
+            // They match values the IR can't represent, which is reported
+            // when they are lowered
+            _ => return None,
+        };
+
+        let (matched, next) = (self.new_block(), self.new_block());
+        let (then, otherwise) = match matches {
+            true => (matched, next),
+            false => (next, matched),
+        };
+
+        self.finish(Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        });
+        self.seal(matched);
+        self.seal(next);
+        self.switch_to(matched);
+
+        Some(next)
+    }
+
+    /// Make the function out of the blocks that can be reached, without the
+    /// phi nodes that turned out to pick the same value every time, and
+    /// with its values numbered in the order they are defined.
+    fn build(self, name: String, params: Vec<Value>, ret: Type) -> Function {
+        let Builder {
+            mut blocks, values, ..
+        } = self;
+
+        // The blocks that can be reached, in reverse postorder, so a block
+        // comes before the blocks it jumps to, besides the headers of loops
+        let successors = |block: BlockId| {
+            blocks[block.index()]
+                .terminator
+                .as_ref()
+                .map(Terminator::successors)
+                .unwrap_or_default()
+        };
+
+        let mut reached = HashSet::from([BlockId::ENTRY]);
+        let mut postorder = Vec::new();
+        let mut stack = vec![(BlockId::ENTRY, successors(BlockId::ENTRY))];
+
+        while let Some((block, pending)) = stack.last_mut() {
+            let block = *block;
+
+            // The last successor is visited first, so the first one comes
+            // first
+            match pending.pop() {
+                Some(successor) => {
+                    if reached.insert(successor) {
+                        stack.push((successor, successors(successor)));
+                    }
+                }
+                None => {
+                    postorder.push(block);
+                    stack.pop();
+                }
+            }
+        }
+
+        let order: Vec<_> = postorder.into_iter().rev().collect();
+        let positions: HashMap<BlockId, BlockId> = order
+            .iter()
+            .enumerate()
+            .map(|(position, &block)| (block, BlockId::new(position)))
+            .collect();
+
+        let mut kept: Vec<BlockData> = order
+            .iter()
+            .map(|block| std::mem::take(&mut blocks[block.index()]))
+            .collect();
+
+        for block in &mut kept {
+            for phi in &mut block.phis {
+                phi.incoming
+                    .retain(|(predecessor, _)| positions.contains_key(predecessor));
+
+                for (predecessor, _) in &mut phi.incoming {
+                    *predecessor = positions[predecessor];
+                }
+            }
+
+            block.terminator = Some(match block.terminator.take() {
+                Some(Terminator::Jump(target)) => Terminator::Jump(positions[&target]),
+                Some(Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                }) => Terminator::Branch {
+                    condition,
+                    then: positions[&then],
+                    otherwise: positions[&otherwise],
+                },
+                Some(terminator) => terminator,
+                None => Terminator::Unreachable,
+            });
+        }
+
+        // A phi node whose values are all the same value, or itself, is that
+        // value. Removing one can make others trivial, so this is repeated
+        // until none are.
+        let mut aliases: HashMap<Value, Value> = HashMap::new();
+        let resolve = |aliases: &HashMap<Value, Value>, mut value: Value| {
+            while let Some(&alias) = aliases.get(&value) {
+                value = alias;
+            }
+
+            value
+        };
+
+        loop {
+            let mut removed = false;
+
+            for block in &mut kept {
+                let mut undefined = Vec::new();
+
+                block.phis.retain(|phi| {
+                    let mut values: Vec<_> = phi
+                        .incoming
+                        .iter()
+                        .map(|&(_, value)| resolve(&aliases, value))
+                        .filter(|&value| value != phi.value)
+                        .collect();
+
+                    values.sort();
+                    values.dedup();
+
+                    match values[..] {
+                        [value] => {
+                            aliases.insert(phi.value, value);
+                        }
+                        [] => undefined.push(phi.value),
+                        _ => return true,
+                    }
+
+                    removed = true;
+
+                    false
+                });
+
+                for value in undefined {
+                    block.insts.insert(
+                        0,
+                        Inst {
+                            result: Some(value),
+                            kind: InstKind::Undef,
+                            span: Span::default(),
+                        },
+                    );
+                }
+            }
+
+            if !removed {
+                break;
+            }
+        }
+
+        // Number the values in the order they are defined
+        let mut numbers: HashMap<Value, Value> = HashMap::new();
+        let mut types = Vec::new();
+        let mut number = |value: Value| {
+            *numbers.entry(value).or_insert_with(|| {
+                types.push(values[value.index()].clone());
+
+                Value::new(types.len() - 1)
+            })
+        };
+
+        let params: Vec<_> = params.into_iter().map(&mut number).collect();
+
+        for block in &kept {
+            for phi in &block.phis {
+                number(phi.value);
+            }
+
+            for inst in &block.insts {
+                if let Some(result) = inst.result {
+                    number(result);
+                }
+            }
+        }
+
+        let mut rename = |value: Value| number(resolve(&aliases, value));
+
+        let blocks = kept
+            .into_iter()
+            .map(|block| Block {
+                phis: block
+                    .phis
+                    .into_iter()
+                    .map(|phi| Phi {
+                        value: rename(phi.value),
+                        incoming: phi
+                            .incoming
+                            .into_iter()
+                            .map(|(predecessor, value)| (predecessor, rename(value)))
+                            .collect(),
+                    })
+                    .collect(),
+                insts: block
+                    .insts
+                    .into_iter()
+                    .map(|mut inst| {
+                        inst.result = inst.result.map(&mut rename);
+                        inst.kind.map_operands(&mut rename);
+
+                        inst
+                    })
+                    .collect(),
+                terminator: match block.terminator.unwrap_or(Terminator::Unreachable) {
+                    Terminator::Branch {
+                        condition,
+                        then,
+                        otherwise,
+                    } => Terminator::Branch {
+                        condition: rename(condition),
+                        then,
+                        otherwise,
+                    },
+                    Terminator::Return(value) => Terminator::Return(value.map(&mut rename)),
+                    terminator => terminator,
+                },
+            })
+            .collect();
+
+        Function {
+            name,
+            params,
+            ret,
+            values: types,
+            blocks,
+        }
+    }
+}
+
+/// The IR type of a type, or what the IR can't represent about it. Code
+/// that never finishes has no value, like `()`.
+fn convert(ty: &Ty) -> Result<Type, String> {
+    match ty {
+        Ty::Int(ty) => Ok(Type::Int(*ty)),
+        Ty::Bool => Ok(Type::Bool),
+        Ty::Str => Ok(Type::Str),
+        Ty::Never => Ok(Type::Unit),
+        Ty::Tuple(types) if types.is_empty() => Ok(Type::Unit),
+        Ty::Function { params, ret } => Ok(Type::Function {
+            params: params.iter().map(convert).collect::<Result<_, _>>()?,
+            ret: Box::new(convert(ret)?),
+        }),
+        Ty::Param(_) | Ty::Unknown => Err("values of unknown types".to_string()),
+        ty => Err(format!("values of type '{}'", ty)),
+    }
+}
@@ -0,0 +1,180 @@
+use super::{Block, BlockId, Callee, Function, Inst, InstKind, Program, Terminator, Value};
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use std::fmt::Write;
+
+/// Print a lowered program as text, for `--emit ir`. Each block is printed
+/// with its phi nodes, instructions and terminator, one per line, and every
+/// value with its type. Functions and globals are called by name.
+///
+/// E.g. `func f(a : u32) : u32 = { a + 1 };` is printed as:
+///
+/// ```text
+/// func f(v0: u32) -> u32 {
+/// block0:
+///     v1: u32 = int 1
+///     v2: u32 = add v0, v1
+///     return v2
+/// }
+/// ```
+pub fn print(program: &Program) -> String {
+    let mut output = String::new();
+
+    for global in &program.globals {
+        let name = match global.mutable {
+            true => format!("mut {}", global.name),
+            false => global.name.clone(),
+        };
+
+        let _ = writeln!(output, "global {}: {} {{", name, global.ty);
+        body(&mut output, program, &global.init);
+        output.push_str("}\n\n");
+    }
+
+    for function in &program.functions {
+        let params: Vec<_> = function
+            .params
+            .iter()
+            .map(|&param| format!("{}: {}", param, function.ty(param)))
+            .collect();
+
+        let _ = writeln!(
+            output,
+            "func {}({}) -> {} {{",
+            function.name,
+            params.join(", "),
+            function.ret
+        );
+        body(&mut output, program, function);
+        output.push_str("}\n\n");
+    }
+
+    // No blank line after the last item
+    output.truncate(output.trim_end().len());
+
+    if !output.is_empty() {
+        output.push('\n');
+    }
+
+    output
+}
+
+fn body(output: &mut String, program: &Program, function: &Function) {
+    for (i, block) in function.blocks.iter().enumerate() {
+        self::block(output, program, function, BlockId::new(i), block);
+    }
+}
+
+fn block(output: &mut String, program: &Program, function: &Function, id: BlockId, block: &Block) {
+    let _ = writeln!(output, "{}:", id);
+
+    for phi in &block.phis {
+        let incoming: Vec<_> = phi
+            .incoming
+            .iter()
+            .map(|(block, value)| format!("[{}: {}]", block, value))
+            .collect();
+
+        let _ = writeln!(
+            output,
+            "    {}: {} = phi {}",
+            phi.value,
+            function.ty(phi.value),
+            incoming.join(", ")
+        );
+    }
+
+    for inst in &block.insts {
+        let _ = writeln!(output, "    {}", self::inst(program, function, inst));
+    }
+
+    let terminator = match &block.terminator {
+        Terminator::Jump(target) => format!("jump {}", target),
+        Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        } => format!("branch {}, {}, {}", condition, then, otherwise),
+        Terminator::Return(Some(value)) => format!("return {}", value),
+        Terminator::Return(None) => "return".to_string(),
+        Terminator::Fail { message, .. } => format!("fail {:?}", message),
+        Terminator::Unreachable => "unreachable".to_string(),
+    };
+
+    let _ = writeln!(output, "    {}", terminator);
+}
+
+fn inst(program: &Program, function: &Function, inst: &Inst) -> String {
+    let function_name = |position: usize| match program.functions.get(position) {
+        Some(function) => function.name.clone(),
+        None => format!("<function {}>", position),
+    };
+    let global_name = |position: usize| match program.globals.get(position) {
+        Some(global) => global.name.clone(),
+        None => format!("<global {}>", position),
+    };
+    let list = |values: &[Value]| {
+        values
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let text = match &inst.kind {
+        InstKind::Int(value) => format!("int {}", value),
+        InstKind::Bool(value) => format!("bool {}", value),
+        InstKind::Str(text) => format!("str {:?}", text),
+        InstKind::Unit => "unit".to_string(),
+        InstKind::Undef => "undef".to_string(),
+        InstKind::Unary { op, operand } => {
+            let name = match op {
+                UnaryOp::Negate => "neg",
+                UnaryOp::Not => "not",
+            };
+
+            format!("{} {}", name, operand)
+        }
+        InstKind::Binary { op, left, right } => {
+            format!("{} {}, {}", binary_name(*op), left, right)
+        }
+        InstKind::Cast(value) => format!("cast {}", value),
+        InstKind::Function(position) => format!("func {}", function_name(*position)),
+        InstKind::Call { callee, args } => {
+            let callee = match callee {
+                Callee::Function(position) => function_name(*position),
+                Callee::Value(value) => value.to_string(),
+            };
+
+            format!("call {}({})", callee, list(args))
+        }
+        InstKind::Global(position) => format!("global {}", global_name(*position)),
+        InstKind::SetGlobal { global, value } => {
+            format!("set_global {}, {}", global_name(*global), value)
+        }
+        InstKind::Print(args) => format!("print {}", list(args)).trim_end().to_string(),
+    };
+
+    match inst.result {
+        Some(result) => format!("{}: {} = {}", result, function.ty(result), text),
+        None => text,
+    }
+}
+
+/// The name of the instruction for an operator.
+fn binary_name(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "add",
+        BinaryOp::Subtract => "sub",
+        BinaryOp::Multiply => "mul",
+        BinaryOp::Divide => "div",
+        BinaryOp::Modulo => "rem",
+        BinaryOp::Equal => "eq",
+        BinaryOp::NotEqual => "ne",
+        BinaryOp::Less => "lt",
+        BinaryOp::LessEqual => "le",
+        BinaryOp::Greater => "gt",
+        BinaryOp::GreaterEqual => "ge",
+        BinaryOp::And => "and",
+        BinaryOp::Or => "or",
+    }
+}
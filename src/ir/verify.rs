@@ -0,0 +1,440 @@
+use super::{BlockId, Callee, Function, Inst, InstKind, Program, Terminator, Type, Value};
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Something wrong with the IR of a function, which is a bug in whatever
+/// made or changed it.
+pub struct VerifyError {
+    /// The function it is in, by its full name
+    pub function: String,
+    pub message: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "In '{}': {}.", self.function, self.message)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Check that a program is well-formed SSA:
+///
+/// - Every block a terminator jumps to exists, and no block jumps to the
+///   entry.
+/// - Every value is defined once, and every use of a value is dominated by
+///   its definition. The value a phi node picks for a block only needs to
+///   be defined at the end of that block.
+/// - Every phi node has one value for each block that jumps to its block.
+/// - Every instruction's operands and result have the types it needs, and
+///   functions return what their signature says.
+pub fn verify(program: &Program) -> Result<(), Vec<VerifyError>> {
+    let mut errors = Vec::new();
+
+    for function in &program.functions {
+        Verifier::new(program, function, &mut errors).verify();
+    }
+
+    for global in &program.globals {
+        let mut verifier = Verifier::new(program, &global.init, &mut errors);
+
+        if global.init.ret != global.ty {
+            verifier.error(format!(
+                "it computes a {}, but the global '{}' is a {}",
+                global.init.ret, global.name, global.ty
+            ));
+        }
+
+        verifier.verify();
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(())
+}
+
+/// The type of calls of a function.
+fn signature(function: &Function) -> Type {
+    Type::Function {
+        params: function
+            .params
+            .iter()
+            .map(|&param| function.ty(param).clone())
+            .collect(),
+        ret: Box::new(function.ret.clone()),
+    }
+}
+
+struct Verifier<'a, 'e> {
+    program: &'a Program,
+    function: &'a Function,
+    errors: &'e mut Vec<VerifyError>,
+    /// Where each value is defined: its block, and 0 for a parameter or
+    /// phi node, or the position of its instruction after that
+    definitions: Vec<Option<(BlockId, usize)>>,
+    /// The blocks that dominate each block, by position
+    dominators: Vec<Vec<bool>>,
+}
+
+impl<'a, 'e> Verifier<'a, 'e> {
+    fn new(program: &'a Program, function: &'a Function, errors: &'e mut Vec<VerifyError>) -> Self {
+        Self {
+            program,
+            function,
+            errors,
+            definitions: vec![None; function.values.len()],
+            dominators: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(VerifyError {
+            function: self.function.name.clone(),
+            message,
+        });
+    }
+
+    fn verify(mut self) {
+        let (function, errors) = (self.function, self.errors.len());
+
+        if function.blocks.is_empty() {
+            return self.error("it has no blocks".to_string());
+        }
+
+        let predecessors = function.predecessors();
+
+        for (i, block) in function.blocks.iter().enumerate() {
+            for successor in block.terminator.successors() {
+                if successor.index() >= function.blocks.len() {
+                    self.error(format!(
+                        "{} jumps to {}, which doesn't exist",
+                        BlockId::new(i),
+                        successor
+                    ));
+                }
+            }
+        }
+
+        if !predecessors[BlockId::ENTRY.index()].is_empty() {
+            self.error(format!(
+                "{} is jumped to, but the function starts at it",
+                BlockId::ENTRY
+            ));
+        }
+
+        // Nothing else can be checked without the blocks
+        if self.errors.len() > errors {
+            return;
+        }
+
+        self.define_values();
+        self.dominators = dominators(function, &predecessors);
+
+        for (i, block) in function.blocks.iter().enumerate() {
+            let id = BlockId::new(i);
+
+            for phi in &block.phis {
+                let mut incoming: Vec<_> = phi.incoming.iter().map(|&(block, _)| block).collect();
+                let mut expected = predecessors[i].clone();
+
+                incoming.sort();
+                expected.sort();
+
+                if incoming != expected {
+                    self.error(format!(
+                        "the phi {} in {} doesn't have one value for each block jumping to it",
+                        phi.value, id
+                    ));
+                }
+
+                for &(predecessor, value) in &phi.incoming {
+                    // The value is picked at the end of the predecessor
+                    self.check_use(value, predecessor, usize::MAX);
+                    self.expect(
+                        value,
+                        function.ty(phi.value),
+                        &format!("the phi {}", phi.value),
+                    );
+                }
+            }
+
+            for (position, inst) in block.insts.iter().enumerate() {
+                for operand in inst.kind.operands() {
+                    self.check_use(operand, id, position + 1);
+                }
+
+                self.check_inst(inst);
+            }
+
+            if let Some(operand) = block.terminator.operand() {
+                self.check_use(operand, id, usize::MAX);
+            }
+
+            self.check_terminator(&block.terminator, id);
+        }
+    }
+
+    /// Note where every value is defined, reporting values defined more than
+    /// once or without a type.
+    fn define_values(&mut self) {
+        let function = self.function;
+
+        let params = function
+            .params
+            .iter()
+            .map(|&param| (param, BlockId::ENTRY, 0));
+        let phis = function.blocks.iter().enumerate().flat_map(|(i, block)| {
+            block
+                .phis
+                .iter()
+                .map(move |phi| (phi.value, BlockId::new(i), 0))
+        });
+        let insts = function.blocks.iter().enumerate().flat_map(|(i, block)| {
+            block
+                .insts
+                .iter()
+                .enumerate()
+                .filter_map(move |(position, inst)| {
+                    Some((inst.result?, BlockId::new(i), position + 1))
+                })
+        });
+
+        for (value, block, position) in params.chain(phis).chain(insts) {
+            match self.definitions.get_mut(value.index()) {
+                None => self.error(format!("{} has no type", value)),
+                Some(Some(_)) => self.error(format!("{} is defined more than once", value)),
+                Some(definition) => *definition = Some((block, position)),
+            }
+        }
+    }
+
+    /// Report a use of a value at a position in a block that its definition
+    /// doesn't dominate.
+    fn check_use(&mut self, value: Value, block: BlockId, position: usize) {
+        let Some(&Some((defined, at))) = self.definitions.get(value.index()) else {
+            return self.error(format!("{} is used in {}, but never defined", value, block));
+        };
+
+        let dominated = match defined == block {
+            true => at < position,
+            false => self.dominators[block.index()][defined.index()],
+        };
+
+        if !dominated {
+            self.error(format!(
+                "{} is used in {} where it isn't always defined",
+                value, block
+            ));
+        }
+    }
+
+    /// Report a value that doesn't have the type something needs.
+    fn expect(&mut self, value: Value, ty: &Type, what: &str) {
+        let Some(actual) = self.function.values.get(value.index()) else {
+            return;
+        };
+
+        if actual != ty {
+            self.error(format!(
+                "{} needs a {}, but {} is a {}",
+                what, ty, value, actual
+            ));
+        }
+    }
+
+    fn check_inst(&mut self, inst: &Inst) {
+        let function = self.function;
+        let ty = |value: Value| function.values.get(value.index()).cloned();
+
+        let name = match inst.result {
+            Some(result) => format!("the instruction defining {}", result),
+            None => "an instruction".to_string(),
+        };
+
+        // The type of the result, if it has one
+        let result = match &inst.kind {
+            InstKind::Call { .. } | InstKind::SetGlobal { .. } | InstKind::Print(_) => None,
+            _ => match inst.result.and_then(ty) {
+                Some(result) => Some(result),
+                None => return self.error(format!("{} has no result", name)),
+            },
+        };
+
+        match &inst.kind {
+            InstKind::Int(value) => match result {
+                Some(Type::Int(int)) if (int.min()..=int.max()).contains(value) => {}
+                _ => self.error(format!("{} is an integer that doesn't fit its type", name)),
+            },
+            InstKind::Bool(_) => self.check_result(&name, result, Type::Bool),
+            InstKind::Str(_) => self.check_result(&name, result, Type::Str),
+            InstKind::Unit => self.check_result(&name, result, Type::Unit),
+            InstKind::Undef => {}
+            InstKind::Unary { op, operand } => {
+                let operand = ty(*operand);
+                let valid = matches!(
+                    (op, &operand),
+                    (UnaryOp::Negate, Some(Type::Int(_)))
+                        | (UnaryOp::Not, Some(Type::Bool | Type::Int(_)))
+                );
+
+                if !valid || operand != result {
+                    self.error(format!("{} has an operand of the wrong type", name));
+                }
+            }
+            InstKind::Binary { op, left, right } => {
+                let (left, right) = (ty(*left), ty(*right));
+                let valid = match op {
+                    BinaryOp::Add
+                    | BinaryOp::Subtract
+                    | BinaryOp::Multiply
+                    | BinaryOp::Divide
+                    | BinaryOp::Modulo => matches!(left, Some(Type::Int(_))) && result == left,
+                    BinaryOp::And | BinaryOp::Or => {
+                        return self.error(format!(
+                            "{} is a '{}', which is a branch",
+                            name,
+                            op.symbol()
+                        ));
+                    }
+                    _ => {
+                        matches!(left, Some(Type::Int(_) | Type::Bool))
+                            && result == Some(Type::Bool)
+                    }
+                };
+
+                if !valid || left != right {
+                    self.error(format!("{} has operands of the wrong types", name));
+                }
+            }
+            InstKind::Cast(value) => {
+                let valid = matches!(ty(*value), Some(Type::Int(_) | Type::Bool))
+                    && matches!(result, Some(Type::Int(_)));
+
+                if !valid {
+                    self.error(format!("{} converts between the wrong types", name));
+                }
+            }
+            InstKind::Function(position) => match self.program.functions.get(*position) {
+                Some(callee) => self.check_result(&name, result, signature(callee)),
+                None => self.error(format!("{} is a function that doesn't exist", name)),
+            },
+            InstKind::Call { callee, args } => {
+                let signature = match callee {
+                    Callee::Function(position) => {
+                        self.program.functions.get(*position).map(signature)
+                    }
+                    Callee::Value(value) => ty(*value),
+                };
+
+                let Some(Type::Function { params, ret }) = signature else {
+                    return self.error(format!("{} calls something that isn't a function", name));
+                };
+
+                let types: Vec<_> = args.iter().map(|&arg| ty(arg)).collect();
+
+                if types.len() != params.len()
+                    || types
+                        .iter()
+                        .zip(&params)
+                        .any(|(arg, param)| arg.as_ref() != Some(param))
+                {
+                    self.error(format!("{} passes arguments of the wrong types", name));
+                }
+
+                match (inst.result.map(ty), *ret) {
+                    (None, Type::Unit) => {}
+                    (Some(result), ret) if result.as_ref() == Some(&ret) => {}
+                    _ => self.error(format!(
+                        "{} has a result that isn't what it calls returns",
+                        name
+                    )),
+                }
+            }
+            InstKind::Global(position) => match self.program.globals.get(*position) {
+                Some(global) => self.check_result(&name, result, global.ty.clone()),
+                None => self.error(format!("{} is a global that doesn't exist", name)),
+            },
+            InstKind::SetGlobal { global, value } => {
+                if inst.result.is_some() {
+                    self.error(format!("{} sets a global, but has a result", name));
+                }
+
+                match self.program.globals.get(*global) {
+                    Some(global) => {
+                        self.expect(*value, &global.ty, &format!("the global '{}'", global.name))
+                    }
+                    None => self.error(format!("{} is a global that doesn't exist", name)),
+                }
+            }
+            InstKind::Print(_) => {
+                if inst.result.is_some() {
+                    self.error(format!("{} prints, but has a result", name));
+                }
+            }
+        }
+    }
+
+    fn check_result(&mut self, name: &str, result: Option<Type>, ty: Type) {
+        if result.as_ref() != Some(&ty) {
+            self.error(format!("{} has a result that isn't a {}", name, ty));
+        }
+    }
+
+    fn check_terminator(&mut self, terminator: &Terminator, block: BlockId) {
+        let ret = &self.function.ret;
+
+        match terminator {
+            Terminator::Branch { condition, .. } => {
+                self.expect(*condition, &Type::Bool, &format!("the branch of {}", block));
+            }
+            Terminator::Return(Some(value)) => {
+                self.expect(*value, ret, &format!("the return of {}", block));
+            }
+            Terminator::Return(None) if *ret != Type::Unit => {
+                self.error(format!(
+                    "{} returns nothing, but the function returns a {}",
+                    block, ret
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The blocks that dominate each block, which every path from the entry to
+/// it goes through. Blocks that can't be reached are dominated by every
+/// block.
+fn dominators(function: &Function, predecessors: &[Vec<BlockId>]) -> Vec<Vec<bool>> {
+    let count = function.blocks.len();
+    let mut dominators = vec![vec![true; count]; count];
+
+    dominators[BlockId::ENTRY.index()] = (0..count).map(|i| i == BlockId::ENTRY.index()).collect();
+
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for block in 1..count {
+            let mut dominated = vec![true; count];
+
+            for predecessor in &predecessors[block] {
+                for (i, dominates) in dominators[predecessor.index()].iter().enumerate() {
+                    dominated[i] &= dominates;
+                }
+            }
+
+            dominated[block] = true;
+
+            if dominated != dominators[block] {
+                dominators[block] = dominated;
+                changed = true;
+            }
+        }
+    }
+
+    dominators
+}
@@ -1,69 +1,130 @@
 use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Read};
+use std::path::Path;
 
 /// A position type to keep track of where we are in the source code.
 type Position = (usize, usize);
 
-#[derive(Debug)]
-/// Errors that can occur during lexing.
-pub enum LexerError<'error> {
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kinds of errors that can occur during lexing.
+pub enum LexerErrorKind {
     /// An invalid character was encountered.
-    InvalidCharacter(&'error Location, char),
+    InvalidCharacter(char),
     /// An invalid identifier was encountered.
-    InvalidIdentifier(&'error Location, String),
+    InvalidIdentifier(String),
     /// An invalid escape sequence was encountered.
-    InvalidEscapeSequence(&'error Location, char),
+    InvalidEscapeSequence(char),
     /// Unexpected end of input.
-    UnexpectedEOF(&'error Location),
+    UnexpectedEOF,
+    /// A string was longer than the maximum allowed length.
+    StringTooLong(usize),
+    /// An identifier was longer than the maximum allowed length.
+    IdentifierTooLong(usize),
+    /// A number was too large to be represented.
+    NumberTooLarge,
+    /// A number's digit separators were misplaced, holding why.
+    MalformedNumber(String),
 }
 
-impl<'error> fmt::Display for LexerError<'error> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            LexerError::InvalidCharacter(loc, c) => {
-                write!(
-                    f,
-                    "[{}:{}:{}] Invalid character '{}'.",
-                    loc.source, loc.line, loc.column, c
-                )
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An error that occurred during lexing.
+///
+/// Each error holds a snapshot of where the lexer was when it occurred, so
+/// errors can outlive the lexer that produced them.
+pub struct LexerError {
+    pub kind: LexerErrorKind,
+    /// Where the lexer was when the error occurred
+    pub location: Location,
+    /// The source code that caused the error
+    pub span: Span,
+}
+
+impl LexerError {
+    /// Create a new error.
+    pub fn new(kind: LexerErrorKind, location: Location, span: Span) -> Self {
+        Self {
+            kind,
+            location,
+            span,
+        }
+    }
+
+    /// A stable code identifying the kind of error, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            LexerErrorKind::InvalidCharacter(_) => "E0001",
+            LexerErrorKind::InvalidIdentifier(_) => "E0002",
+            LexerErrorKind::InvalidEscapeSequence(_) => "E0003",
+            LexerErrorKind::UnexpectedEOF => "E0004",
+            LexerErrorKind::StringTooLong(_) => "E0005",
+            LexerErrorKind::IdentifierTooLong(_) => "E0006",
+            LexerErrorKind::NumberTooLarge => "E0007",
+            LexerErrorKind::MalformedNumber(_) => "E0008",
+        }
+    }
+
+    /// The source code that caused the error.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Where the lexer was when the error occurred.
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            LexerErrorKind::InvalidCharacter(c) => format!("Invalid character '{}'", c),
+            LexerErrorKind::InvalidIdentifier(s) => format!("Invalid identifier '{}'", s),
+            LexerErrorKind::InvalidEscapeSequence(c) => {
+                format!("Invalid escape sequence '{}'", c)
             }
-            LexerError::InvalidIdentifier(loc, s) => {
-                write!(
-                    f,
-                    "[{}:{}:{}] Invalid identifier '{}'.",
-                    loc.source, loc.line, loc.column, s
-                )
+            LexerErrorKind::UnexpectedEOF => "Unexpected end of file".to_string(),
+            LexerErrorKind::StringTooLong(max) => {
+                format!("String is longer than the maximum of {} bytes", max)
             }
-            LexerError::InvalidEscapeSequence(loc, c) => {
-                write!(
-                    f,
-                    "[{}:{}:{}] Invalid escape sequence '{}'.",
-                    loc.source, loc.line, loc.column, c
-                )
+            LexerErrorKind::IdentifierTooLong(max) => {
+                format!("Identifier is longer than the maximum of {} bytes", max)
             }
-            LexerError::UnexpectedEOF(loc) => {
-                write!(
-                    f,
-                    "[{}:{}:{}] Unexpected end of file.",
-                    loc.source, loc.line, loc.column
-                )
+            LexerErrorKind::NumberTooLarge => {
+                format!("Number is larger than the maximum of {}", usize::MAX)
             }
+            LexerErrorKind::MalformedNumber(reason) => format!("Malformed number, {}", reason),
         }
     }
 }
 
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}:{}:{}] {}.",
+            self.location.source,
+            self.location.line,
+            self.location.column,
+            self.message()
+        )
+    }
+}
+
+impl std::error::Error for LexerError {}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A token is a single lexical unit of the language.
 pub enum TokenKind {
-    /// A semicolon (:), typically followed by a type or equal sign
+    /// A colon (:), typically followed by a type or equal sign
+    ///
+    /// E.g. `let a : u32 = 10;`, where the parser reads the type between the
+    /// colon and equal sign.
     TypeAssignment, // :
     /// An equal sign, typically preceded by a type or TypeAssignment
     LetAssignment, // =
-    /// An assignment that contains a type
-    ///
-    /// E.g. `let a : u32 = 10;`
-    TypedAssignment(String), // : u32 =
     /// An assignment that does not contain a type
     ///
     /// E.g. `let a := "Waddle";`
@@ -72,26 +133,45 @@ pub enum TokenKind {
     Semicolon, // ;
     /// Any string of characters that are not symbols in the language
     ///
-    /// E.g. `let` is an identifier.
+    /// E.g. `x` is an identifier. Raw identifiers like `r#let` are never
+    /// keywords, the name being everything after the `r#`.
     Identifier,
     /// `let`
     Assign, // let
     /// Any single (') or double (") quoted strings, allows for escape sequences
-    String,
+    ///
+    /// Holds the contents of the string with the escape sequences resolved.
+    String(String),
+    /// A string prefixed with `b`, holding the bytes of its contents
+    ///
+    /// E.g. `b"Waddle"`
+    ByteString(Vec<u8>),
+    /// A string prefixed with `f`, whose contents are formatted
+    ///
+    /// E.g. `f"Hello, {name}!"`
+    FormatString(String),
 
     /// A number
     Number(usize),
+    /// True, the boolean literal
+    True, // true
+    /// False, the boolean literal
+    False, // false
 
     // Arithmetic
     /// Addition (+)
     Plus, // +
     /// Addition assignment (+=)
     ShortIncrement, // +=
+    /// Increment (++)
+    Increment, // ++
 
     /// Subtraction (-)
     Minus, // -
     /// Subtraction assignment (-=)
     ShortDecrement, // -=
+    /// Decrement (--)
+    Decrement, // --
 
     /// Multiplication (*)
     Multiply, // *
@@ -108,6 +188,28 @@ pub enum TokenKind {
     /// Modulo assignment (%=)
     ShortModulo, // %=
 
+    // Comparison
+    /// Equality (==)
+    Equal, // ==
+    /// Inequality (!=)
+    NotEqual, // !=
+    /// Less than (<)
+    LessThan, // <
+    /// Less than or equal to (<=)
+    LessEqual, // <=
+    /// Greater than (>)
+    GreaterThan, // >
+    /// Greater than or equal to (>=)
+    GreaterEqual, // >=
+
+    // Logical
+    /// Logical and (&&)
+    And, // &&
+    /// Logical or (||)
+    Or, // ||
+    /// Logical not (!)
+    Not, // !
+
     /// Open parenthesis
     OpenParen, // (
     /// Close parenthesis
@@ -125,6 +227,16 @@ pub enum TokenKind {
 
     /// A comma
     Comma, // ,
+    /// A dot, for accessing fields
+    Dot, // .
+    /// Two colons, separating an enum from its variant
+    PathSeparator, // ::
+    /// An arrow, separating a closure's parameters from its body
+    FatArrow, // =>
+    /// An arrow, separating a function type's parameters from its return type
+    Arrow, // ->
+    /// A question mark, making a type optional
+    Question, // ?
 
     /// Function Function, // func
     Function, // func
@@ -132,35 +244,257 @@ pub enum TokenKind {
     /// Return
     Return, // return
 
+    /// Defer, running a block when its scope ends
+    Defer, // defer
+
+    /// Assert, checking a condition when the program runs
+    Assert, // assert
+    /// Static assert, checking a condition when the program is compiled
+    StaticAssert, // static_assert
+
     /// Import
     Import, // import
+    /// Pub, making an item usable from other modules
+    Pub, // pub
+
+    /// Const, declaring a constant
+    Const, // const
+    /// Mut, declaring a variable or parameter that can be assigned to
+    Mut, // mut
+
+    /// If
+    If, // if
+    /// Else
+    Else, // else
+    /// Match, choosing a branch by which pattern a value matches
+    Match, // match
+
+    /// While
+    While, // while
+    /// For
+    For, // for
+    /// In, used by `for` loops
+    In, // in
+    /// Loop
+    Loop, // loop
+    /// Break
+    Break, // break
+    /// Continue
+    Continue, // continue
+
+    /// Struct
+    Struct, // struct
+    /// Enum
+    Enum, // enum
+    /// Impl
+    Impl, // impl
+    /// Trait
+    Trait, // trait
+
+    /// As, used for explicit casts
+    ///
+    /// E.g. `x as u64`
+    As, // as
+
+    /// An attribute attached to the following item
+    ///
+    /// E.g. `@inline` or `@test`.
+    Attribute, // @name
+
+    /// A run of whitespace, only produced when keeping trivia
+    Whitespace,
+    /// A single or multi-line comment, only produced when keeping trivia
+    Comment,
+}
+
+/// All of the keywords in the language, and the kind of token they produce.
+static KEYWORDS: phf::Map<&'static str, TokenKind> = phf::phf_map! {
+    "let" => TokenKind::Assign,
+    "func" => TokenKind::Function,
+    "return" => TokenKind::Return,
+    "defer" => TokenKind::Defer,
+    "assert" => TokenKind::Assert,
+    "static_assert" => TokenKind::StaticAssert,
+    "import" => TokenKind::Import,
+    "pub" => TokenKind::Pub,
+    "const" => TokenKind::Const,
+    "mut" => TokenKind::Mut,
+    "if" => TokenKind::If,
+    "else" => TokenKind::Else,
+    "match" => TokenKind::Match,
+    "true" => TokenKind::True,
+    "false" => TokenKind::False,
+    "while" => TokenKind::While,
+    "for" => TokenKind::For,
+    "in" => TokenKind::In,
+    "loop" => TokenKind::Loop,
+    "break" => TokenKind::Break,
+    "continue" => TokenKind::Continue,
+    "struct" => TokenKind::Struct,
+    "enum" => TokenKind::Enum,
+    "impl" => TokenKind::Impl,
+    "trait" => TokenKind::Trait,
+    "as" => TokenKind::As,
+};
+
+/// The length of the longest keyword in `KEYWORDS`.
+const MAX_KEYWORD_LENGTH: usize = "static_assert".len();
+
+/// The characters that can follow a backslash in a string, and the character
+/// they stand for.
+static ESCAPES: &[(char, char)] = &[
+    ('n', '\n'),
+    ('t', '\t'),
+    ('r', '\r'),
+    ('0', '\0'),
+    ('"', '"'),
+    ('\'', '\''),
+    ('\\', '\\'),
+];
+
+/// Regular expressions for the tokens that aren't a fixed keyword or symbol.
+/// These aren't used by the lexer, but describe what it accepts for tooling.
+static PATTERNS: &[(&str, &str)] = &[
+    ("Identifier", r"(r#)?[_a-zA-Z][_\p{L}\p{N}]*"),
+    ("Attribute", r"@[_a-zA-Z][_\p{L}\p{N}]*"),
+    ("Number", r"[0-9](_?[0-9])*"),
+    ("String", r#""(\\.|[^"\\])*"|'(\\.|[^'\\])*'"#),
+    ("ByteString", r#"b("(\\.|[^"\\])*"|'(\\.|[^'\\])*')"#),
+    ("FormatString", r#"f("(\\.|[^"\\])*"|'(\\.|[^'\\])*')"#),
+    (
+        "UnTypedAssignment",
+        r":(\s|\\\r?\n|//[^\n]*|/\*[\s\S]*?\*/)*=",
+    ),
+    ("Comment", r"//[^\n]*|/\*[\s\S]*?\*/"),
+    ("Whitespace", r"(\s|\\\r?\n)+"),
+];
+
+/// All of the operators and punctuation in the language, and the kind of token
+/// they produce. Longer symbols come before any symbol that is a prefix of
+/// them, so that `+=` is matched before `+`.
+static SYMBOLS: &[(&str, TokenKind)] = &[
+    ("++", TokenKind::Increment),
+    ("+=", TokenKind::ShortIncrement),
+    ("+", TokenKind::Plus),
+    ("--", TokenKind::Decrement),
+    ("->", TokenKind::Arrow),
+    ("-=", TokenKind::ShortDecrement),
+    ("-", TokenKind::Minus),
+    ("*=", TokenKind::ShortMultiply),
+    ("*", TokenKind::Multiply),
+    ("/=", TokenKind::ShortDivide),
+    ("/", TokenKind::Divide),
+    ("%=", TokenKind::ShortModulo),
+    ("%", TokenKind::Modulo),
+    ("==", TokenKind::Equal),
+    ("!=", TokenKind::NotEqual),
+    ("!", TokenKind::Not),
+    ("<=", TokenKind::LessEqual),
+    ("<", TokenKind::LessThan),
+    (">=", TokenKind::GreaterEqual),
+    (">", TokenKind::GreaterThan),
+    ("&&", TokenKind::And),
+    ("||", TokenKind::Or),
+    ("::", TokenKind::PathSeparator),
+    (":", TokenKind::TypeAssignment),
+    ("=>", TokenKind::FatArrow),
+    ("=", TokenKind::LetAssignment),
+    (";", TokenKind::Semicolon),
+    ("(", TokenKind::OpenParen),
+    (")", TokenKind::CloseParen),
+    ("{", TokenKind::OpenBrace),
+    ("}", TokenKind::CloseBrace),
+    ("[", TokenKind::OpenBracket),
+    ("]", TokenKind::CloseBracket),
+    (",", TokenKind::Comma),
+    (".", TokenKind::Dot),
+    ("?", TokenKind::Question),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A range of byte offsets into the source code, `start` inclusive and `end`
+/// exclusive.
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a new span.
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The number of bytes covered by this span.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Check if the span doesn't cover any bytes.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The smallest span covering both spans, e.g. the span of a binary
+    /// expression from the spans of its operands.
+    pub fn merge(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// The span moved `offset` bytes later, e.g. from the start of a file to
+    /// where the file starts in a `SourceMap`.
+    pub fn shift(self, offset: usize) -> Span {
+        Span::new(self.start + offset, self.end + offset)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Token {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Token<'source> {
     // The kind of token
     pub kind: TokenKind,
 
-    // The characters that were used to create this token. This should be
-    // unchanged from the original source code.
-    pub literal: String,
+    // The characters that were used to create this token. This is a slice of
+    // the original source code, so it is unchanged from it. When deserialized
+    // it borrows from the input, so literals that a format has to escape
+    // (like quotes in JSON) can't be read back.
+    pub literal: &'source str,
+
+    // Where in the source code the literal was found
+    pub span: Span,
 }
 
-impl Token {
+impl<'source> Token<'source> {
     /// Create a new token.
-    pub fn new(kind: TokenKind, literal: String) -> Self {
-        Self { kind, literal }
+    pub fn new(kind: TokenKind, literal: &'source str, span: Span) -> Self {
+        Self {
+            kind,
+            literal,
+            span,
+        }
+    }
+
+    /// The name of an identifier, without the `r#` of a raw identifier.
+    /// Returns `None` for any other kind of token.
+    pub fn identifier(&self) -> Option<&'source str> {
+        match self.kind {
+            TokenKind::Identifier => Some(self.literal.strip_prefix("r#").unwrap_or(self.literal)),
+            _ => None,
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location {
     pub line: usize,
     pub column: usize,
+    /// The byte offset into the source code
     pub index: usize,
-    pub prev_line_length: usize,
-    pub current_line_length: usize,
     pub source: String,
+    /// How many columns a tab character counts as
+    pub tab_width: usize,
 }
 
 impl Location {
@@ -169,12 +503,8 @@ impl Location {
             line,
             column,
             index: 0,
-            // TODO: We might want to rememeber all of the previous lines
-            // lengths for diagnostics, but for now we only need the length of
-            // the previous line
-            prev_line_length: 0,
-            current_line_length: 0,
             source,
+            tab_width: 1,
         }
     }
 
@@ -183,419 +513,645 @@ impl Location {
             if current == '\n' {
                 self.line += 1;
                 self.column = 0;
-                self.prev_line_length = self.current_line_length;
-                self.current_line_length = 0;
+            } else if current == '\t' {
+                self.column += self.tab_width;
             } else {
                 self.column += 1;
-                self.current_line_length += 1;
             }
 
-            self.index += 1;
+            self.index += current.len_utf8();
         }
     }
 
-    pub fn retreat(&mut self, current: Option<char>) {
-        if let Some(current) = current {
-            if current == '\n' {
-                self.line -= 1;
-                self.column = 0;
-                self.current_line_length = self.prev_line_length;
-                self.prev_line_length = 0;
-            } else {
-                self.column -= 1;
-            }
+    pub fn current_location(&self) -> Position {
+        (self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+/// Source code, along with the name of where it came from. The lexer borrows
+/// from this, so it needs to outlive any tokens that are produced.
+pub struct Source {
+    pub name: String,
+    pub contents: String,
+}
+
+impl Source {
+    /// Create a new source from a name and its contents.
+    pub fn new(name: &str, contents: String) -> Self {
+        Self {
+            name: name.to_string(),
+            contents,
+        }
+    }
+
+    /// Read the source from a file, using the file name as the name.
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        Ok(Self { name, contents })
+    }
+
+    /// Read the source from anything that can be read from, such as stdin,
+    /// a socket, or an in-memory buffer.
+    pub fn from_reader(mut reader: impl Read, name: &str) -> io::Result<Self> {
+        let mut contents = String::new();
+
+        reader.read_to_string(&mut contents)?;
+
+        Ok(Self::new(name, contents))
+    }
+
+    /// Create a lexer over the contents of this source.
+    pub fn lexer(&self) -> Lexer<'_> {
+        Lexer::new(&self.contents, &self.name)
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+/// Source code that is memory-mapped from a file rather than read into
+/// memory, so very large files can be lexed without copying them.
+pub struct MappedSource {
+    pub name: String,
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedSource {
+    /// Map a file into memory, checking that it is valid UTF-8.
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
 
-            self.index -= 1;
+        // SAFETY: The map is read-only. If the file is changed by another
+        // process while it is mapped, the contents are checked to be valid
+        // UTF-8 again before they are lexed.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+
+        std::str::from_utf8(&map)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        Ok(Self { name, map })
+    }
+
+    /// The mapped contents of the file.
+    pub fn contents(&self) -> io::Result<&str> {
+        std::str::from_utf8(&self.map)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Create a lexer over the mapped contents of the file.
+    pub fn lexer(&self) -> io::Result<Lexer<'_>> {
+        Ok(Lexer::new(self.contents()?, &self.name))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Options controlling how the lexer behaves. Built up from the defaults:
+///
+/// ```
+/// use penguin::lexer::LexerOptions;
+///
+/// let options = LexerOptions::new().keep_trivia(true).tab_width(4);
+/// ```
+pub struct LexerOptions {
+    /// Whether `LET` and `let` are both the `let` keyword. Only meant for
+    /// sources written before keywords were case-sensitive.
+    pub case_insensitive_keywords: bool,
+    /// Whether to produce whitespace and comment tokens
+    pub keep_trivia: bool,
+    /// The maximum length of a string's contents, in bytes
+    pub max_string_length: Option<usize>,
+    /// The maximum length of an identifier, in bytes
+    pub max_identifier_length: Option<usize>,
+    /// How many columns a tab counts as when reporting locations
+    pub tab_width: usize,
+    /// Whether digit separators in numbers have to be between two digits,
+    /// rejecting numbers like `1__000` and `1_`
+    pub strict_digit_separators: bool,
+}
+
+impl Default for LexerOptions {
+    fn default() -> Self {
+        Self {
+            case_insensitive_keywords: false,
+            keep_trivia: false,
+            max_string_length: None,
+            max_identifier_length: None,
+            tab_width: 1,
+            strict_digit_separators: true,
         }
     }
+}
 
-    pub fn current_location(&self) -> Position {
-        (self.line, self.column)
+impl LexerOptions {
+    /// Create the default set of options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn case_insensitive_keywords(mut self, case_insensitive_keywords: bool) -> Self {
+        self.case_insensitive_keywords = case_insensitive_keywords;
+        self
+    }
+
+    pub fn keep_trivia(mut self, keep_trivia: bool) -> Self {
+        self.keep_trivia = keep_trivia;
+        self
+    }
+
+    pub fn max_string_length(mut self, max_string_length: usize) -> Self {
+        self.max_string_length = Some(max_string_length);
+        self
+    }
+
+    pub fn max_identifier_length(mut self, max_identifier_length: usize) -> Self {
+        self.max_identifier_length = Some(max_identifier_length);
+        self
+    }
+
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    pub fn strict_digit_separators(mut self, strict_digit_separators: bool) -> Self {
+        self.strict_digit_separators = strict_digit_separators;
+        self
     }
 }
 
 #[derive(Debug)]
-pub struct Lexer {
+pub struct Lexer<'source> {
     pub loc: Location,
-    source: Vec<char>,
+    source: &'source str,
     current: Option<char>,
+    options: LexerOptions,
+    // Set once the lexer has hit an error, so iteration stops there
+    failed: bool,
 }
 
-impl Lexer {
-    /// Create a new lexer from a string.
-    pub fn new(file: PathBuf) -> Self {
-        let source = fs::read_to_string(&file).unwrap().chars().collect();
-        let file_name = file.file_name().unwrap().to_str().unwrap().to_string();
-
+impl<'source> Lexer<'source> {
+    /// Create a new lexer over the contents of a named source, such as a file.
+    pub fn new(source: &'source str, name: &str) -> Self {
         Self {
             source,
             current: None,
-            loc: Location::new(1, 0, file_name),
+            loc: Location::new(1, 0, name.to_string()),
+            options: LexerOptions::default(),
+            failed: false,
         }
     }
 
-    pub fn lex_from_string(source: String) -> Self {
-        Self {
-            source: source.chars().collect(),
-            current: None,
-            loc: Location::new(1, 0, "string".to_string()),
-        }
+    /// Change the options the lexer uses.
+    pub fn with_options(mut self, options: LexerOptions) -> Self {
+        self.loc.tab_width = options.tab_width;
+        self.options = options;
+        self
+    }
+
+    pub fn lex_from_string(source: &'source str) -> Self {
+        Self::new(source, "string")
     }
 
     /// Lex the source code into a list of tokens.
-    pub fn lex(&mut self) -> Result<Vec<Token>, LexerError> {
-        let mut tokens = vec![];
-
-        // While we are not at the end of the contents
-        while self.source.len() > self.loc.index {
-            // Check if there is no current character, if so, we are at the end
-            // of the file and we should break out of the loop
-            if self.current_char().is_none() {
-                break;
-            }
+    pub fn lex(&mut self) -> Result<Vec<Token<'source>>, LexerError> {
+        self.collect()
+    }
 
-            self.current = self.current_char();
-            let current = self.current.unwrap();
+    /// Lex a single token from the source, skipping over whitespace and
+    /// comments unless trivia is being kept. Returns `None` once the end of
+    /// the source is reached.
+    fn next_token(&mut self) -> Result<Option<Token<'source>>, LexerError> {
+        while let Some(current) = self.current_char() {
+            self.current = Some(current);
 
-            match current {
-                ':' => {
-                    tokens.push(Token::new(TokenKind::TypeAssignment, current.to_string()));
+            // Where the token being lexed starts, used to slice its literal
+            // out of the source once we know where it ends.
+            let start = self.loc.index;
 
-                    // Increment the location
-                    self.next();
-                }
-                '=' => {
-                    // Right now, the only way to tell if an assignment is
-                    // typed or not is to check if the previous token is a
-                    // TypeAssignment. If it is, then this is a TypedAssignment.
-                    //
-                    // I want to handle this logic in the ':' case, but I'm not
-                    // sure how to do that yet, or if it's even possible.
-                    // Maybe this is something that can be handled in the
-                    // parser?
-                    let previous_token = tokens.last().unwrap();
-
-                    if previous_token.kind == TokenKind::TypeAssignment {
-                        tokens.pop();
-
-                        tokens.push(Token::new(TokenKind::UnTypedAssignment, ":=".to_string()));
-                    } else {
-                        tokens.push(Token::new(TokenKind::LetAssignment, current.to_string()));
-                    }
+            // Comments start like a division, so they need to be checked for
+            // before any of the symbols.
+            if current == '/' && matches!(self.peek(), Some('/') | Some('*')) {
+                self.skip_comment()?;
 
-                    self.next();
+                if self.options.keep_trivia {
+                    return Ok(Some(self.token(TokenKind::Comment, start)));
                 }
-                ';' => {
-                    tokens.push(Token::new(TokenKind::Semicolon, current.to_string()));
 
-                    self.next();
-                }
-                '\'' | '"' => {
-                    let mut found_close = false;
-                    let mut buffer = String::new();
+                continue;
+            }
 
-                    self.next();
+            // A colon followed by an equal sign is an untyped assignment, even
+            // if there are comments or whitespace between them, e.g.
+            // `: /* u32 */ =`.
+            if current == ':' {
+                if let Some(end) = self.untyped_assignment_end() {
+                    self.advance_to(end);
 
-                    while let Some(next) = self.current_char() {
-                        // Check if the current string quote is the same as the
-                        // starting quote, if so, we have found the end of the
-                        // string.
-                        if next == current {
-                            found_close = true;
+                    return Ok(Some(self.token(TokenKind::UnTypedAssignment, start)));
+                }
+            }
 
-                            break;
-                        }
+            // Operators and punctuation
+            if let Some((symbol, kind)) = Lexer::symbol(&self.source[start..]) {
+                return Ok(Some(self.operator(kind.clone(), symbol.len())));
+            }
 
-                        // Check if the current character is an escape sequence
-                        // otherwise, just add it to the buffer
-                        if next == '\\' {
-                            self.next();
-
-                            // Match the type of escape sequence
-                            if let Some(next) = self.current_char() {
-                                match next {
-                                    'n' => buffer.push('\n'),
-                                    't' => buffer.push('\t'),
-                                    'r' => buffer.push('\r'),
-                                    '0' => buffer.push('\0'),
-                                    '"' => buffer.push('"'),
-                                    '\'' => buffer.push('\''),
-                                    '\\' => buffer.push('\\'),
-                                    // Ignore new lines, just continue. I
-                                    // actually don't know if this is the
-                                    // correct way to handle this, but it
-                                    // works for now.
-                                    '\n' => self.next(),
-                                    _ => {
-                                        self.next();
-
-                                        return Err(LexerError::InvalidEscapeSequence(
-                                            &self.loc, next,
-                                        ));
-                                    }
-                                }
-                            }
-                        } else {
-                            buffer.push(next);
-                        }
+            match current {
+                '\'' | '"' => {
+                    let contents = self.string(start)?;
 
-                        self.next();
-                    }
+                    return Ok(Some(self.token(TokenKind::String(contents), start)));
+                }
+                // A prefix on a string changes what kind of string it is,
+                // e.g. `b"bytes"` or `f"format"`
+                'b' | 'f' if matches!(self.peek(), Some('\'' | '"')) => {
+                    self.advance();
 
-                    // If we didn't find the end of the string, return an error
-                    if !found_close {
-                        return Err(LexerError::UnexpectedEOF(&self.loc));
-                    }
+                    let contents = self.string(start)?;
 
-                    tokens.push(Token::new(TokenKind::String, buffer));
+                    let kind = match current {
+                        'b' => TokenKind::ByteString(contents.into_bytes()),
+                        _ => TokenKind::FormatString(contents),
+                    };
 
-                    self.next();
+                    return Ok(Some(self.token(kind, start)));
                 }
                 // Identifiers start with a letter (underscore in the future)
                 // and can contain numbers.
                 '_' | 'a'..='z' | 'A'..='Z' => {
-                    let mut buffer = String::new();
+                    // A raw identifier, e.g. `r#match`, is never a keyword, so
+                    // names that collide with keywords can still be used
+                    let raw = current == 'r'
+                        && self.peek() == Some('#')
+                        && matches!(self.peek_n(2), Some('_' | 'a'..='z' | 'A'..='Z'));
+
+                    if raw {
+                        self.advance();
+                        self.advance();
+                    }
+
+                    let name_start = self.loc.index;
 
                     while let Some(cur) = self.current_char() {
                         if cur.is_alphanumeric() || cur == '_' {
-                            buffer.push(cur);
-
-                            self.next();
+                            self.advance();
                         } else {
                             break;
                         }
                     }
 
-                    // Check if the buffer is a keyword, otherwise, it is an
-                    // identifier
-                    let token = Lexer::identify(&buffer);
+                    if let Some(max) = self.options.max_identifier_length {
+                        if self.loc.index - name_start > max {
+                            return Err(self.error(LexerErrorKind::IdentifierTooLong(max), start));
+                        }
+                    }
 
-                    tokens.push(token);
+                    // Check if the identifier is a keyword, otherwise, it is
+                    // an identifier
+                    let kind = if raw {
+                        TokenKind::Identifier
+                    } else {
+                        self.identify(&self.source[name_start..self.loc.index])
+                    };
+
+                    return Ok(Some(self.token(kind, start)));
                 }
-                // TODO: Add support for floats
-                _ if current.is_numeric() => {
-                    let mut buffer = String::new();
+                '@' => {
+                    self.advance();
+
+                    // The attribute name follows the same rules as an
+                    // identifier, so it can't start with a number.
+                    match self.current_char() {
+                        Some(next) if next == '_' || next.is_ascii_alphabetic() => {}
+                        _ => {
+                            return Err(self.error(LexerErrorKind::InvalidCharacter(current), start))
+                        }
+                    }
 
-                    buffer.push(current);
+                    while let Some(next) = self.current_char() {
+                        if next.is_alphanumeric() || next == '_' {
+                            self.advance();
+                        } else {
+                            break;
+                        }
+                    }
 
-                    self.next();
+                    return Ok(Some(self.token(TokenKind::Attribute, start)));
+                }
+                // TODO: Add support for floats
+                '0'..='9' => {
+                    let mut num: Option<usize> = Some(0);
+                    let mut after_separator = false;
 
                     while let Some(next) = self.current_char() {
                         // Check if the current character is a number or an
                         // underscore. Underscores are used to make numbers
                         // more readable, for example, 1_000_000.
-                        if next.is_numeric() || next == '_' {
-                            buffer.push(next);
+                        if let Some(digit) = next.to_digit(10) {
+                            num = num
+                                .and_then(|num| num.checked_mul(10))
+                                .and_then(|num| num.checked_add(digit as usize));
+
+                            after_separator = false;
+                            self.advance();
+                        } else if next == '_' {
+                            let separator = self.loc.index;
+
+                            self.advance();
+
+                            // Separators have to be between two digits, so the
+                            // error points at the one that isn't
+                            if self.options.strict_digit_separators {
+                                let reason = if after_separator {
+                                    Some("digit separators can't be repeated")
+                                } else if !matches!(self.current_char(), Some('0'..='9' | '_')) {
+                                    Some("digit separators can't end a number")
+                                } else {
+                                    None
+                                };
+
+                                if let Some(reason) = reason {
+                                    return Err(self.error(
+                                        LexerErrorKind::MalformedNumber(reason.to_string()),
+                                        separator,
+                                    ));
+                                }
+                            }
 
-                            self.next();
+                            after_separator = true;
                         } else {
                             break;
                         }
                     }
 
-                    // Strip the underscores from the number, then parse it
-                    let num = buffer.replace('_', "").parse::<usize>().unwrap();
+                    let Some(num) = num else {
+                        return Err(self.error(LexerErrorKind::NumberTooLarge, start));
+                    };
 
-                    tokens.push(Token::new(TokenKind::Number(num), buffer));
+                    return Ok(Some(self.token(TokenKind::Number(num), start)));
                 }
-                '+' => {
-                    self.next();
-
-                    // Check if the next character is an equals sign, if so,
-                    // this is a short increment
-                    if let Some(next) = self.current_char() {
-                        if next == '=' {
-                            tokens.push(Token::new(TokenKind::ShortIncrement, "+=".to_string()));
+                // A backslash at the end of a line continues the line, so it
+                // is skipped along with the newline like any other whitespace
+                _ if current.is_whitespace() || self.line_continuation_end().is_some() => {
+                    loop {
+                        if let Some(end) = self.line_continuation_end() {
+                            self.advance_to(end);
+                        } else if self.current_char().is_some_and(char::is_whitespace) {
+                            self.advance();
                         } else {
-                            // Otherwise, this is a normal plus. Also decrement
-                            // the location so that the next token is not
-                            // skipped
-                            self.prev();
-
-                            tokens.push(Token::new(TokenKind::Plus, current.to_string()));
+                            break;
                         }
                     }
 
-                    self.next();
-                }
-                '-' => {
-                    self.next();
-
-                    // Check if the next character is an equals sign, if so,
-                    // this is a short decrement
-                    if let Some(next) = self.current_char() {
-                        if next == '=' {
-                            tokens.push(Token::new(TokenKind::ShortDecrement, "-=".to_string()));
-                        } else {
-                            // Otherwise, this is a normal minus. Also decrement
-                            // the location so that the next token is not
-                            // skipped
-                            self.prev();
-
-                            tokens.push(Token::new(TokenKind::Minus, current.to_string()));
-                        }
+                    // Whitespace is only kept if asked for, otherwise it is
+                    // ignored
+                    if self.options.keep_trivia {
+                        return Ok(Some(self.token(TokenKind::Whitespace, start)));
                     }
+                }
+                _ => {
+                    self.advance();
 
-                    self.next();
+                    return Err(self.error(LexerErrorKind::InvalidCharacter(current), start));
                 }
-                '*' => {
-                    self.next();
-
-                    // Check if the next character is an equals sign, if so,
-                    // this is a short multiply
-                    if let Some(next) = self.current_char() {
-                        if next == '=' {
-                            tokens.push(Token::new(TokenKind::ShortMultiply, "*=".to_string()));
-                        } else {
-                            // Otherwise, this is a normal multiplication. Also
-                            // decrementthe location so that the next token is
-                            // not skipped
-                            self.prev();
+            }
+        }
 
-                            tokens.push(Token::new(TokenKind::Multiply, current.to_string()));
-                        }
-                    }
+        Ok(None)
+    }
 
-                    self.next();
-                }
-                '%' => {
-                    self.next();
-
-                    // Check if the next character is an modulo, if so,
-                    // this is a short modulo
-                    if let Some(next) = self.current_char() {
-                        if next == '=' {
-                            tokens.push(Token::new(TokenKind::ShortModulo, "%=".to_string()));
-                        } else {
-                            // Otherwise, this is a normal modulo. Also decrement
-                            // the location so that the next token is not
-                            // skipped
-                            self.prev();
+    /// Create a token of the given kind, with its literal spanning from
+    /// `start` to the current position in the source
+    fn token(&self, kind: TokenKind, start: usize) -> Token<'source> {
+        let span = Span::new(start, self.loc.index);
 
-                            tokens.push(Token::new(TokenKind::Modulo, current.to_string()));
-                        }
-                    }
+        Token::new(kind, &self.source[start..self.loc.index], span)
+    }
 
-                    self.next();
+    /// Create an error of the given kind, caused by the source code from
+    /// `start` to the current position in the source
+    fn error(&self, kind: LexerErrorKind, start: usize) -> LexerError {
+        LexerError::new(kind, self.loc.clone(), Span::new(start, self.loc.index))
+    }
+
+    /// Find the symbol that the source starts with, if any
+    fn symbol(source: &str) -> Option<&'static (&'static str, TokenKind)> {
+        SYMBOLS
+            .iter()
+            .find(|(symbol, _)| source.starts_with(symbol))
+    }
+
+    /// Create an operator token that is `length` characters long, starting
+    /// at the current character
+    fn operator(&mut self, kind: TokenKind, length: usize) -> Token<'source> {
+        let start = self.loc.index;
+
+        for _ in 0..length {
+            self.advance();
+        }
+
+        self.token(kind, start)
+    }
+
+    /// Skip over a single or multi-line comment, starting at its first `/`
+    fn skip_comment(&mut self) -> Result<(), LexerError> {
+        let start = self.loc.index;
+
+        if self.peek() == Some('/') {
+            // This is a comment, skip until the end of the line
+            while let Some(next) = self.current_char() {
+                if next == '\n' {
+                    break;
                 }
-                '/' => {
-                    self.next();
-
-                    if let Some(next) = self.current_char() {
-                        if next == '/' {
-                            // This is a comment, skip until the end of the line
-                            while let Some(next) = self.current_char() {
-                                if next == '\n' {
-                                    break;
-                                }
 
-                                self.next();
-                            }
-                        } else if next == '*' {
-                            // This is a multi-line comment, skip until the end
-                            let mut found_close = false;
-                            // Check if there is a closing comment tag,
-                            // if so, break out of the loop.
-                            //
-                            // TODO: Do we want to check for a closing
-                            // comment tag? Or allow the user to forget
-                            // to close the comment?
-                            while let Some(next) = self.current_char() {
-                                if next == '*' {
-                                    self.next();
-
-                                    if let Some(next) = self.current_char() {
-                                        if next == '/' {
-                                            found_close = true;
-                                            break;
-                                        }
-                                    }
-                                }
+                self.advance();
+            }
 
-                                self.next();
-                            }
+            return Ok(());
+        }
 
-                            if !found_close {
-                                return Err(LexerError::UnexpectedEOF(&self.loc));
-                            }
-                        } else if next == '=' {
-                            tokens.push(Token::new(TokenKind::ShortDivide, "/=".to_string()));
-                        } else {
-                            // This is a division, push the token and move back
-                            // because we probably need to check what it was
-                            // dividing by.
-                            tokens.push(Token::new(TokenKind::Divide, current.to_string()));
+        // This is a multi-line comment, skip past the opening `/*` and then
+        // until the closing `*/`.
+        //
+        // TODO: Do we want to check for a closing comment tag? Or allow the
+        // user to forget to close the comment?
+        self.advance();
+        self.advance();
+
+        while let Some(next) = self.current_char() {
+            if next == '*' && self.peek() == Some('/') {
+                self.advance();
+                self.advance();
+
+                return Ok(());
+            }
+
+            self.advance();
+        }
+
+        Err(self.error(LexerErrorKind::UnexpectedEOF, start))
+    }
+
+    /// Lex a string starting at the quote it is in, returning its contents
+    /// with the escape sequences resolved. `start` is where the token the
+    /// string is part of starts, which is before the quote if it is prefixed.
+    fn string(&mut self, start: usize) -> Result<String, LexerError> {
+        let quote = self.current_char();
+        let mut found_close = false;
+        let mut buffer = String::new();
 
-                            self.prev();
+        self.advance();
+
+        while let Some(next) = self.current_char() {
+            // Check if the current string quote is the same as the
+            // starting quote, if so, we have found the end of the
+            // string.
+            if Some(next) == quote {
+                found_close = true;
+
+                break;
+            }
+
+            // Check if the current character is an escape sequence
+            // otherwise, just add it to the buffer
+            if next == '\\' {
+                let escape_start = self.loc.index;
+
+                self.advance();
+
+                // Match the type of escape sequence
+                if let Some(next) = self.current_char() {
+                    let escaped = ESCAPES
+                        .iter()
+                        .find(|(escape, _)| *escape == next)
+                        .map(|(_, escaped)| *escaped);
+
+                    match escaped {
+                        Some(escaped) => buffer.push(escaped),
+                        // Ignore new lines, just continue. I
+                        // actually don't know if this is the
+                        // correct way to handle this, but it
+                        // works for now.
+                        None if next == '\n' => self.advance(),
+                        None => {
+                            self.advance();
+
+                            return Err(self
+                                .error(LexerErrorKind::InvalidEscapeSequence(next), escape_start));
                         }
                     }
-
-                    self.next();
                 }
-                '(' => {
-                    tokens.push(Token::new(TokenKind::OpenParen, current.to_string()));
+            } else {
+                buffer.push(next);
+            }
 
-                    self.next();
-                }
-                ')' => {
-                    tokens.push(Token::new(TokenKind::CloseParen, current.to_string()));
+            self.advance();
+        }
 
-                    self.next();
-                }
-                '{' => {
-                    tokens.push(Token::new(TokenKind::OpenBrace, current.to_string()));
+        // If we didn't find the end of the string, return an error
+        if !found_close {
+            return Err(self.error(LexerErrorKind::UnexpectedEOF, start));
+        }
 
-                    self.next();
-                }
-                '}' => {
-                    tokens.push(Token::new(TokenKind::CloseBrace, current.to_string()));
+        if let Some(max) = self.options.max_string_length {
+            if buffer.len() > max {
+                self.advance();
 
-                    self.next();
-                }
-                '[' => {
-                    tokens.push(Token::new(TokenKind::OpenBracket, current.to_string()));
+                return Err(self.error(LexerErrorKind::StringTooLong(max), start));
+            }
+        }
 
-                    self.next();
-                }
-                ']' => {
-                    tokens.push(Token::new(TokenKind::CloseBracket, current.to_string()));
+        self.advance();
 
-                    self.next();
-                }
-                ',' => {
-                    tokens.push(Token::new(TokenKind::Comma, current.to_string()));
+        Ok(buffer)
+    }
 
-                    self.next();
-                }
-                _ if current.is_whitespace() => {
-                    // TODO: Should we include whitespace tokens?
-                    // For now, we will ignore them
-                    self.next();
+    /// Starting at a `:`, look ahead past any whitespace and comments for an
+    /// `=`. If there is one, this is an untyped assignment and the byte
+    /// offset just past the `=` is returned.
+    fn untyped_assignment_end(&self) -> Option<usize> {
+        let mut chars = self.source[self.loc.index..]
+            .char_indices()
+            .skip(1)
+            .peekable();
+
+        while let Some((index, next)) = chars.next() {
+            match next {
+                '=' => return Some(self.loc.index + index + 1),
+                _ if next.is_whitespace() => {}
+                '\\' => {
+                    chars.next_if(|&(_, next)| next == '\r');
+                    chars.next_if(|&(_, next)| next == '\n')?;
                 }
-                _ => {
-                    self.next();
+                '/' => match chars.next()? {
+                    // A line comment runs until the newline
+                    (_, '/') => while chars.next_if(|&(_, next)| next != '\n').is_some() {},
+                    (_, '*') => loop {
+                        let (_, next) = chars.next()?;
 
-                    return Err(LexerError::InvalidCharacter(&self.loc, current));
-                }
+                        if next == '*' && chars.next_if(|&(_, next)| next == '/').is_some() {
+                            break;
+                        }
+                    },
+                    _ => return None,
+                },
+                _ => return None,
             }
         }
 
-        Ok(tokens)
+        None
+    }
+
+    /// If the lexer is at a line continuation, a backslash followed by a
+    /// newline, get the byte offset just past the newline.
+    fn line_continuation_end(&self) -> Option<usize> {
+        let rest = &self.source[self.loc.index..];
+        let newline = rest.strip_prefix('\\')?;
+        let after = newline
+            .strip_prefix("\r\n")
+            .or_else(|| newline.strip_prefix('\n'))?;
+
+        Some(self.source.len() - after.len())
     }
 
     /// Get the current character in the source
     fn current_char(&self) -> Option<char> {
-        self.source.get(self.loc.index).cloned()
+        self.source[self.loc.index..].chars().next()
+    }
+
+    /// Get the character after the current one, without moving the lexer
+    fn peek(&self) -> Option<char> {
+        self.peek_n(1)
+    }
+
+    /// Get the character `n` characters after the current one, without moving
+    /// the lexer
+    fn peek_n(&self, n: usize) -> Option<char> {
+        self.source[self.loc.index..].chars().nth(n)
     }
 
     /// Move the lexer to the next character
-    fn next(&mut self) {
+    fn advance(&mut self) {
         self.loc.advance(self.current_char());
     }
 
-    /// Move the lexer to the previous character
-    fn prev(&mut self) {
-        self.loc.retreat(self.current_char());
+    /// Move the lexer forward until it reaches the given byte offset
+    fn advance_to(&mut self, index: usize) {
+        while self.loc.index < index {
+            self.advance();
+        }
     }
 
     /// Identify a keyword based on a buffer
@@ -604,17 +1160,131 @@ impl Lexer {
     /// * `buffer` - The buffer to identify
     ///
     /// # Returns
-    /// The keyword if it exists, otherwise None
-    fn identify(buffer: &str) -> Token {
-        // Change the buffer to lowercase to make it easier to compare
-        let buffer_copied = buffer.to_owned().to_lowercase();
-
-        match buffer_copied.as_str() {
-            "let" => Token::new(TokenKind::Assign, buffer.to_string()),
-            "func" => Token::new(TokenKind::Function, buffer.to_string()),
-            "return" => Token::new(TokenKind::Return, buffer.to_string()),
-            "import" => Token::new(TokenKind::Import, buffer.to_string()),
-            _ => Token::new(TokenKind::Identifier, buffer.to_string()),
+    /// The keyword if it exists, otherwise an identifier
+    fn identify(&self, buffer: &str) -> TokenKind {
+        if let Some(kind) = KEYWORDS.get(buffer) {
+            return kind.clone();
         }
+
+        if !self.options.case_insensitive_keywords {
+            return TokenKind::Identifier;
+        }
+
+        // Keywords can be case insensitive. Anything longer than the longest
+        // keyword can't be one, so only short buffers are lowercased, and on
+        // the stack to avoid allocating for every identifier.
+        let mut lowercase = [0u8; MAX_KEYWORD_LENGTH];
+
+        if buffer.len() > MAX_KEYWORD_LENGTH || !buffer.bytes().any(|b| b.is_ascii_uppercase()) {
+            return TokenKind::Identifier;
+        }
+
+        for (lower, byte) in lowercase.iter_mut().zip(buffer.bytes()) {
+            *lower = byte.to_ascii_lowercase();
+        }
+
+        std::str::from_utf8(&lowercase[..buffer.len()])
+            .ok()
+            .and_then(|lowercase| KEYWORDS.get(lowercase))
+            .cloned()
+            .unwrap_or(TokenKind::Identifier)
     }
 }
+
+impl<'source> Iterator for Lexer<'source> {
+    type Item = Result<Token<'source>, LexerError>;
+
+    /// Lex the next token on demand. Iteration stops after the first error,
+    /// since the lexer can't recover from it yet.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let token = self.next_token();
+
+        if token.is_err() {
+            self.failed = true;
+        }
+
+        token.transpose()
+    }
+}
+
+/// Describe every kind of token the lexer produces as JSON, generated from the
+/// same tables the lexer uses, so that external grammars and highlighters can
+/// be kept in sync with it.
+pub fn token_spec() -> String {
+    let mut keywords: Vec<_> = KEYWORDS.entries().collect();
+    keywords.sort_by_key(|(literal, _)| **literal);
+
+    let keywords: Vec<String> = keywords
+        .iter()
+        .map(|(literal, kind)| {
+            format!(
+                "{{\"kind\": {}, \"literal\": {}}}",
+                json_string(&format!("{:?}", kind)),
+                json_string(literal)
+            )
+        })
+        .collect();
+
+    let symbols: Vec<String> = SYMBOLS
+        .iter()
+        .map(|(literal, kind)| {
+            format!(
+                "{{\"kind\": {}, \"literal\": {}}}",
+                json_string(&format!("{:?}", kind)),
+                json_string(literal)
+            )
+        })
+        .collect();
+
+    let patterns: Vec<String> = PATTERNS
+        .iter()
+        .map(|(kind, pattern)| {
+            format!(
+                "{{\"kind\": {}, \"pattern\": {}}}",
+                json_string(kind),
+                json_string(pattern)
+            )
+        })
+        .collect();
+
+    let escapes: Vec<String> = ESCAPES
+        .iter()
+        .map(|(escape, _)| json_string(&format!("\\{}", escape)))
+        .collect();
+
+    format!(
+        "{{\n  \"case_insensitive_keywords\": {},\n  \"keywords\": [\n    {}\n  ],\n  \"symbols\": [\n    {}\n  ],\n  \"patterns\": [\n    {}\n  ],\n  \"escapes\": [{}]\n}}",
+        LexerOptions::default().case_insensitive_keywords,
+        keywords.join(",\n    "),
+        symbols.join(",\n    "),
+        patterns.join(",\n    "),
+        escapes.join(", ")
+    )
+}
+
+/// Quote and escape a string for use in JSON.
+fn json_string(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+
+    quoted.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
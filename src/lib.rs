@@ -1 +1,25 @@
+pub mod arithmetic;
+pub mod asm;
+pub mod backend;
+pub mod bytecode;
+pub mod consteval;
+#[cfg(feature = "cranelift")]
+pub mod cranelift;
+pub mod hir;
+pub mod ir;
 pub mod lexer;
+pub mod lint;
+#[cfg(feature = "llvm")]
+pub mod llvm;
+pub mod module;
+pub mod ops;
+pub mod parser;
+pub mod resolve;
+#[cfg(feature = "resolve-cache")]
+pub mod resolve_cache;
+pub mod runtime;
+pub mod source_map;
+pub mod suggest;
+#[cfg(feature = "token-cache")]
+pub mod token_cache;
+pub mod typeck;
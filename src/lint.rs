@@ -0,0 +1,1011 @@
+//! Lints, which warn about code that is allowed but probably a mistake, like
+//! a variable that is never read.
+//!
+//! Every lint has a name, and a level deciding what happens when it finds
+//! something: it can be allowed and ignored, reported as a warning, or
+//! denied and reported as an error that stops the program from compiling.
+//! Each lint has a default level, which the command line can change, and
+//! which the attributes of an item change inside of it, e.g.
+//! `@allow(shadowing)`.
+//!
+//! Every lint is listed in `Lint::ALL`, and finds what it is about with a
+//! `LintPass`, usually by walking the program with a `Visitor`. Adding a lint
+//! is adding it to the list, giving it a name, a level and a pass, and the
+//! kinds of warnings it reports.
+use crate::lexer::Span;
+use crate::parser::ast::{
+    Arena, ClosureParam, Enum, ExprId, ExprKind, Field, Function, Impl, Item, ItemKind, Let, Param,
+    Signature, Stmt, Struct, Trait, TypeParam, Variant,
+};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::ParseResult;
+use crate::resolve::{Resolution, SymbolId, SymbolKind};
+use crate::suggest::{self, Suggestion};
+use crate::typeck::{IntTy, Ty, Types};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A kind of mistake the lints look for.
+pub enum Lint {
+    /// Variables, parameters and pattern bindings that are never read
+    UnusedVariables,
+    /// Imported names that are never used
+    UnusedImports,
+    /// Variables and parameters with the name of something declared in a
+    /// scope around them, which they hide
+    Shadowing,
+    /// Modules that import each other, directly or through other modules.
+    /// Every module is merged into one program, so their items can still
+    /// use each other, but the cycle usually means they aren't split well.
+    ImportCycles,
+    /// Names that aren't written in the case used for what they name, e.g.
+    /// a function named `doThing` instead of `do_thing`
+    NamingConventions,
+    /// Variables declared with `:=`, whose integer type is inferred, cast to
+    /// a type that can't hold all of its values to give a variable declared
+    /// with one, e.g. `let a := 300; let b : u8 = a as u8;`
+    Narrowing,
+    /// Attributes naming a lint that doesn't exist
+    UnknownLints,
+}
+
+impl Lint {
+    /// Every lint, in the order they are listed.
+    pub const ALL: &'static [Lint] = &[
+        Lint::UnusedVariables,
+        Lint::UnusedImports,
+        Lint::Shadowing,
+        Lint::ImportCycles,
+        Lint::NamingConventions,
+        Lint::Narrowing,
+        Lint::UnknownLints,
+    ];
+
+    /// The name of the lint, as it is given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnusedImports => "unused_imports",
+            Lint::Shadowing => "shadowing",
+            Lint::ImportCycles => "import_cycles",
+            Lint::NamingConventions => "naming_conventions",
+            Lint::Narrowing => "narrowing",
+            Lint::UnknownLints => "unknown_lints",
+        }
+    }
+
+    /// The level of the lint when the command line doesn't give it one.
+    /// Shadowing is allowed, since it is often done on purpose, e.g. `let a
+    /// := a + 1;`.
+    pub fn default_level(self) -> Level {
+        match self {
+            Lint::UnusedVariables
+            | Lint::UnusedImports
+            | Lint::ImportCycles
+            | Lint::NamingConventions
+            | Lint::Narrowing
+            | Lint::UnknownLints => Level::Warn,
+            Lint::Shadowing => Level::Allow,
+        }
+    }
+
+    /// The lint with a name, if there is one.
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+
+    /// How the lint finds what it is about in a resolved program, or a type
+    /// checked one for `narrowing`. Import
+    /// cycles have no pass, since they are found while the modules are
+    /// loaded, before there is a program to look through.
+    pub fn pass(self) -> Option<Box<dyn LintPass>> {
+        match self {
+            Lint::UnusedVariables | Lint::UnusedImports => Some(Box::new(Unused(self))),
+            Lint::Shadowing => Some(Box::new(Shadowing)),
+            Lint::ImportCycles => None,
+            Lint::NamingConventions => Some(Box::<Naming>::default()),
+            Lint::Narrowing => Some(Box::new(Narrowing)),
+            Lint::UnknownLints => Some(Box::new(UnknownLints)),
+        }
+    }
+}
+
+/// What a lint pass looks through.
+pub struct LintContext<'a> {
+    pub parsed: &'a ParseResult,
+    pub resolution: &'a Resolution,
+    /// The types of the program, which passes that need them find nothing
+    /// without
+    pub types: Option<&'a Types>,
+}
+
+/// How a lint finds what it is about. Passes walk the program with a
+/// `Visitor`, or look through what resolving it found, and report everything
+/// they find whatever the level of their lint is.
+pub trait LintPass {
+    fn check(&mut self, cx: &LintContext) -> Vec<Warning>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// What happens when a lint finds something.
+pub enum Level {
+    /// Nothing is reported
+    Allow,
+    /// It is reported as a warning
+    Warn,
+    /// It is reported as an error, and the program doesn't compile
+    Deny,
+}
+
+impl Level {
+    /// The level an attribute sets, e.g. `@allow(...)`, if it sets one.
+    pub fn from_attribute(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The level of every lint. Lints that weren't given one have their default
+/// level.
+pub struct Levels {
+    levels: HashMap<Lint, Level>,
+    /// The levels set by the attributes of items, which are only used for
+    /// what is found inside of the item, in the order they were set
+    scoped: Vec<(Span, Lint, Level)>,
+}
+
+impl Levels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the level of a lint.
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    /// Set the level of every lint that warns by default, which is what
+    /// `warnings` stands for on the command line. Lints that are allowed by
+    /// default have to be named to change their level.
+    pub fn set_warnings(&mut self, level: Level) {
+        for &lint in Lint::ALL {
+            if lint.default_level() == Level::Warn {
+                self.set(lint, level);
+            }
+        }
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+
+    /// Set the level of a lint for what is found inside of `span`, over the
+    /// level it has everywhere else.
+    pub fn set_in(&mut self, span: Span, lint: Lint, level: Level) {
+        self.scoped.push((span, lint, level));
+    }
+
+    /// Set the levels the attributes of each item give, inside of the item.
+    /// `warnings` stands for every lint that warns by default, like it does
+    /// on the command line, and a lint given several levels by the
+    /// attributes of an item takes the last one. Lints that don't exist are
+    /// left out, and found by `unknown_lints`.
+    pub fn set_from_attributes(&mut self, parsed: &ParseResult) {
+        for item in &parsed.program.items {
+            for attribute in &item.attributes {
+                let Some(level) = Level::from_attribute(&attribute.name) else {
+                    continue;
+                };
+
+                for (name, _) in &attribute.args {
+                    if name == "warnings" {
+                        for &lint in Lint::ALL {
+                            if lint.default_level() == Level::Warn {
+                                self.set_in(item.span, lint, level);
+                            }
+                        }
+                    } else if let Some(lint) = Lint::from_name(name) {
+                        self.set_in(item.span, lint, level);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The level of a lint for what it found at `span`, which is the level
+    /// the last attribute around it gave, or its level everywhere else if
+    /// none did.
+    pub fn level_at(&self, lint: Lint, span: Span) -> Level {
+        self.scoped
+            .iter()
+            .rev()
+            .find(|(scope, scoped, _)| {
+                *scoped == lint && scope.start <= span.start && span.end <= scope.end
+            })
+            .map_or_else(|| self.level(lint), |&(_, _, level)| level)
+    }
+
+    /// Split what the lints found into warnings and errors, by the level of
+    /// the lint that found each where it was found. What is allowed is left
+    /// out.
+    pub fn split(&self, warnings: Vec<Warning>) -> (Vec<Warning>, Vec<Warning>) {
+        let mut errors = Vec::new();
+
+        let warnings = warnings
+            .into_iter()
+            .filter_map(
+                |warning| match self.level_at(warning.lint(), warning.span) {
+                    Level::Allow => None,
+                    Level::Warn => Some(warning),
+                    Level::Deny => {
+                        errors.push(warning);
+
+                        None
+                    }
+                },
+            )
+            .collect();
+
+        (warnings, errors)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A variable or pattern binding is never read.
+    UnusedVariable { name: String },
+    /// A parameter of a function or closure is never read.
+    UnusedParameter { name: String },
+    /// An imported name is never used.
+    UnusedImport { name: String },
+    /// A variable or parameter hides something with the same name, declared
+    /// in a scope around it.
+    Shadowed {
+        name: String,
+        /// What is hidden, e.g. "parameter"
+        what: String,
+    },
+    /// An import leads back to the module it is in, through the modules
+    /// imported on the way.
+    ImportCycle {
+        /// The file of each module in the cycle, starting and ending with
+        /// the same one
+        cycle: Vec<String>,
+    },
+    /// A name isn't written in the case used for what it names.
+    NamingConvention {
+        name: String,
+        /// What is named, e.g. "function"
+        what: String,
+        case: Case,
+        /// The name written in the case
+        suggested: String,
+    },
+    /// A variable whose integer type was inferred is cast to a type that
+    /// can't hold all of its values, to give a variable declared with it.
+    Narrowed {
+        name: String,
+        from: IntTy,
+        to: IntTy,
+    },
+    /// An attribute names a lint that doesn't exist.
+    UnknownLint { name: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A way of writing names made of several words.
+pub enum Case {
+    /// `snake_case`, for functions, methods, variables, parameters and fields
+    Snake,
+    /// `UpperCamelCase`, for structs, enums, variants, traits and type
+    /// parameters
+    UpperCamel,
+    /// `SCREAMING_SNAKE_CASE`, for constants
+    ScreamingSnake,
+}
+
+impl Case {
+    pub fn name(self) -> &'static str {
+        match self {
+            Case::Snake => "snake_case",
+            Case::UpperCamel => "UpperCamelCase",
+            Case::ScreamingSnake => "SCREAMING_SNAKE_CASE",
+        }
+    }
+
+    /// Whether a name is written in the case. Underscores around the name
+    /// are left out, so `_unused` is snake case.
+    pub fn matches(self, name: &str) -> bool {
+        let name = name.trim_matches('_');
+
+        match self {
+            Case::Snake => !name.contains("__") && !name.chars().any(char::is_uppercase),
+            Case::UpperCamel => {
+                !name.contains('_') && name.chars().next().is_none_or(char::is_uppercase)
+            }
+            Case::ScreamingSnake => !name.contains("__") && !name.chars().any(char::is_lowercase),
+        }
+    }
+
+    /// A name written in the case, keeping the underscores it starts with.
+    ///
+    /// E.g. `doThing` is `do_thing` in snake case, and `http_server` is
+    /// `HttpServer` in upper camel case.
+    pub fn convert(self, name: &str) -> String {
+        let rest = name.trim_start_matches('_');
+        let prefix = &name[..name.len() - rest.len()];
+        let words = words(rest);
+
+        let converted = match self {
+            Case::Snake => words
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            Case::UpperCamel => words
+                .iter()
+                .map(|word| {
+                    let mut chars = word.chars();
+
+                    chars.next().map_or_else(String::new, |first| {
+                        first
+                            .to_uppercase()
+                            .chain(chars.flat_map(char::to_lowercase))
+                            .collect()
+                    })
+                })
+                .collect(),
+            Case::ScreamingSnake => words
+                .iter()
+                .map(|word| word.to_uppercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+        };
+
+        format!("{}{}", prefix, converted)
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The words of a name, split at underscores and where the case changes,
+/// e.g. `parseHTTPRequest_fast` is `parse`, `HTTP`, `Request` and `fast`.
+fn words(name: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+
+    for part in name.split('_').filter(|part| !part.is_empty()) {
+        let chars: Vec<(usize, char)> = part.char_indices().collect();
+        let mut start = 0;
+
+        for i in 1..chars.len() {
+            let (at, c) = chars[i];
+            let previous = chars[i - 1].1;
+            let next = chars.get(i + 1).map(|&(_, next)| next);
+
+            // A word starts at an uppercase letter after a lowercase one or
+            // a digit, or at the last uppercase letter of an acronym that a
+            // word follows
+            let starts = c.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_numeric()
+                    || (previous.is_uppercase() && next.is_some_and(char::is_lowercase)));
+
+            if starts {
+                words.push(&part[start..at]);
+                start = at;
+            }
+        }
+
+        words.push(&part[start..]);
+    }
+
+    words
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Something a lint found.
+pub struct Warning {
+    pub kind: WarningKind,
+    span: Span,
+    declared: Option<Span>,
+    suggestion: Option<Suggestion>,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            declared: None,
+            suggestion: None,
+        }
+    }
+
+    /// Point at the declaration the warning is about too, e.g. what a
+    /// variable shadows.
+    pub fn declared_at(mut self, span: Span) -> Self {
+        self.declared = Some(span);
+        self
+    }
+
+    /// Suggest a fix for the warning.
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestion = Some(suggestion);
+        self
+    }
+
+    /// The lint that found it, which decides its level.
+    pub fn lint(&self) -> Lint {
+        match self.kind {
+            WarningKind::UnusedVariable { .. } | WarningKind::UnusedParameter { .. } => {
+                Lint::UnusedVariables
+            }
+            WarningKind::UnusedImport { .. } => Lint::UnusedImports,
+            WarningKind::Shadowed { .. } => Lint::Shadowing,
+            WarningKind::ImportCycle { .. } => Lint::ImportCycles,
+            WarningKind::NamingConvention { .. } => Lint::NamingConventions,
+            WarningKind::Narrowed { .. } => Lint::Narrowing,
+            WarningKind::UnknownLint { .. } => Lint::UnknownLints,
+        }
+    }
+
+    /// A stable code identifying the kind of warning, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            WarningKind::UnusedVariable { .. } => "W0501",
+            WarningKind::UnusedParameter { .. } => "W0502",
+            WarningKind::UnusedImport { .. } => "W0503",
+            WarningKind::Shadowed { .. } => "W0504",
+            WarningKind::ImportCycle { .. } => "W0505",
+            WarningKind::NamingConvention { .. } => "W0506",
+            WarningKind::UnknownLint { .. } => "W0507",
+            WarningKind::Narrowed { .. } => "W0508",
+        }
+    }
+
+    /// The code that caused the warning.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The declaration the warning is about, if there is one.
+    pub fn declared(&self) -> Option<Span> {
+        self.declared
+    }
+
+    /// A fix for the warning, if there is one that is sure to work.
+    pub fn suggestion(&self) -> Option<&Suggestion> {
+        self.suggestion.as_ref()
+    }
+
+    /// A description of the warning, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            WarningKind::UnusedVariable { name } => {
+                format!("The variable '{}' is never read", name)
+            }
+            WarningKind::UnusedParameter { name } => {
+                format!("The parameter '{}' is never read", name)
+            }
+            WarningKind::UnusedImport { name } => format!("The import '{}' is never used", name),
+            WarningKind::Shadowed { name, what } => {
+                format!("'{}' shadows the {} with the same name", name, what)
+            }
+            WarningKind::ImportCycle { cycle } => {
+                format!("The modules import each other: {}", cycle.join(" -> "))
+            }
+            WarningKind::NamingConvention {
+                name,
+                what,
+                case,
+                suggested,
+            } => format!(
+                "The {} '{}' should be named in {}, like '{}'",
+                what, name, case, suggested
+            ),
+            WarningKind::Narrowed { name, from, to } => format!(
+                "'{}' is inferred as {}, which is narrowed to {} here",
+                name,
+                from.name(),
+                to.name()
+            ),
+            WarningKind::UnknownLint { name } => format!("There is no lint '{}'", name),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.", self.message())?;
+
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " {}.", suggestion.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Run every lint over a resolved program, whatever their levels are, with
+/// its types if it was type checked. What they find is in the order it is in
+/// the source.
+pub fn check(parsed: &ParseResult, resolution: &Resolution, types: Option<&Types>) -> Vec<Warning> {
+    let cx = LintContext {
+        parsed,
+        resolution,
+        types,
+    };
+
+    let mut warnings: Vec<_> = Lint::ALL
+        .iter()
+        .filter_map(|lint| lint.pass())
+        .flat_map(|mut pass| pass.check(&cx))
+        .collect();
+
+    warnings.sort_by_key(|warning| warning.span.start);
+
+    warnings
+}
+
+/// Finds the variables and parameters that are never read for
+/// `unused_variables`, or the imports that are never used for
+/// `unused_imports`. Names starting with `_` are meant to be unused, so they
+/// are left out.
+struct Unused(Lint);
+
+impl LintPass for Unused {
+    fn check(&mut self, cx: &LintContext) -> Vec<Warning> {
+        unused(cx.parsed, cx.resolution)
+            .into_iter()
+            .filter(|warning| warning.lint() == self.0)
+            .collect()
+    }
+}
+
+fn unused(parsed: &ParseResult, resolution: &Resolution) -> Vec<Warning> {
+    let mut assigned = Assigned::default();
+
+    assigned.visit_program(parsed);
+
+    // Assigning to a variable doesn't read it, unless the assignment is
+    // compound, like `a += 1`
+    let read: HashSet<_> = resolution
+        .uses
+        .iter()
+        .filter(|(expr, _)| !assigned.0.contains(expr))
+        .map(|(_, &symbol)| symbol)
+        .collect();
+
+    resolution
+        .scopes
+        .iter()
+        .flat_map(|scope| &scope.symbols)
+        .map(|&id| (id, resolution.symbol(id)))
+        // A method's `self` is how it is called, so it is needed even when
+        // the method doesn't read it
+        .filter(|(id, symbol)| {
+            !read.contains(id) && !symbol.name.starts_with('_') && symbol.name != "self"
+        })
+        .filter_map(|(id, symbol)| {
+            let name = symbol.name.clone();
+
+            let kind = match symbol.kind {
+                SymbolKind::Local | SymbolKind::Binding => WarningKind::UnusedVariable { name },
+                SymbolKind::Param | SymbolKind::ClosureParam => {
+                    WarningKind::UnusedParameter { name }
+                }
+                SymbolKind::Import => WarningKind::UnusedImport { name },
+                _ => return None,
+            };
+
+            // Variables and parameters point at their name, where an `_`
+            // can be added
+            let warning = match resolution.names.get(&id) {
+                Some(name) => Warning::new(kind, *name).with_suggestion(Suggestion::insert(
+                    format!(
+                        "Rename it to '_{}' if it is meant to be unused",
+                        symbol.name
+                    ),
+                    name.start,
+                    "_",
+                )),
+                None => Warning::new(kind, symbol.span),
+            };
+
+            Some(warning)
+        })
+        .collect()
+}
+
+/// Finds the variables and parameters that shadow something declared in a
+/// scope around them, before them.
+struct Shadowing;
+
+impl LintPass for Shadowing {
+    fn check(&mut self, cx: &LintContext) -> Vec<Warning> {
+        shadowing(cx.resolution)
+    }
+}
+
+fn shadowing(resolution: &Resolution) -> Vec<Warning> {
+    // Where a symbol's name is, or the node declaring it if that isn't known
+    let span = |id: SymbolId| {
+        resolution
+            .names
+            .get(&id)
+            .copied()
+            .unwrap_or(resolution.symbol(id).span)
+    };
+
+    let mut warnings = Vec::new();
+
+    for scope in &resolution.scopes {
+        for &id in &scope.symbols {
+            let symbol = resolution.symbol(id);
+
+            if !matches!(
+                symbol.kind,
+                SymbolKind::Local
+                    | SymbolKind::Binding
+                    | SymbolKind::Param
+                    | SymbolKind::ClosureParam
+            ) {
+                continue;
+            }
+
+            // Symbols are numbered in the order they are declared, so only
+            // earlier ones can be shadowed
+            let shadowed =
+                std::iter::successors(scope.parent, |&parent| resolution.scope(parent).parent)
+                    .find_map(|parent| {
+                        resolution
+                            .scope(parent)
+                            .symbols
+                            .iter()
+                            .copied()
+                            .find(|&other| {
+                                other < id && resolution.symbol(other).name == symbol.name
+                            })
+                    });
+
+            let Some(shadowed) = shadowed else {
+                continue;
+            };
+
+            let what = match resolution.symbol(shadowed).kind {
+                SymbolKind::Function => "function",
+                SymbolKind::Global => "global",
+                SymbolKind::Const => "constant",
+                SymbolKind::Struct => "struct",
+                SymbolKind::Enum => "enum",
+                SymbolKind::Trait => "trait",
+                SymbolKind::Import => "import",
+                SymbolKind::Param | SymbolKind::ClosureParam => "parameter",
+                SymbolKind::Local | SymbolKind::Binding => "variable",
+            };
+
+            let warning = Warning::new(
+                WarningKind::Shadowed {
+                    name: symbol.name.clone(),
+                    what: what.to_string(),
+                },
+                span(id),
+            )
+            .declared_at(span(shadowed));
+
+            warnings.push(warning);
+        }
+    }
+
+    warnings
+}
+
+/// Finds the names that aren't written in the case used for what they name.
+///
+/// Pattern bindings are left out, since a binding can't be told apart from a
+/// variant without a payload, like `None`, until the program is type
+/// checked. Names from other modules are qualified by their module, which
+/// is left out too.
+#[derive(Default)]
+struct Naming {
+    warnings: Vec<Warning>,
+    /// The innermost item, method, statement or expression being visited,
+    /// which is pointed at for names without a span of their own
+    span: Span,
+}
+
+impl Naming {
+    fn name(&mut self, name: &str, what: &str, case: Case, span: Span) {
+        let name = name.rsplit("::").next().unwrap_or(name);
+
+        if case.matches(name) {
+            return;
+        }
+
+        self.warnings.push(Warning::new(
+            WarningKind::NamingConvention {
+                name: name.to_string(),
+                what: what.to_string(),
+                case,
+                suggested: case.convert(name),
+            },
+            span,
+        ));
+    }
+
+    fn generics(&mut self, generics: &[TypeParam]) {
+        for param in generics {
+            self.name(&param.name, "type parameter", Case::UpperCamel, self.span);
+        }
+    }
+
+    /// Visit a node with `span` as the innermost one.
+    fn within(&mut self, span: Span, visit: impl FnOnce(&mut Self)) {
+        let outer = std::mem::replace(&mut self.span, span);
+
+        visit(self);
+
+        self.span = outer;
+    }
+}
+
+impl LintPass for Naming {
+    fn check(&mut self, cx: &LintContext) -> Vec<Warning> {
+        self.visit_program(cx.parsed);
+
+        std::mem::take(&mut self.warnings)
+    }
+}
+
+impl Visitor for Naming {
+    fn visit_item(&mut self, arena: &Arena, item: &Item) {
+        self.within(item.span, |naming| match &item.kind {
+            // Constants are declared like variables, but named differently
+            ItemKind::Const(decl) => {
+                naming.name(&decl.name, "constant", Case::ScreamingSnake, decl.name_span);
+
+                visit::walk_let(naming, arena, decl);
+            }
+            _ => visit::walk_item(naming, arena, item),
+        });
+    }
+
+    fn visit_function(&mut self, arena: &Arena, function: &Function) {
+        self.name(&function.name, "function", Case::Snake, self.span);
+        self.generics(&function.generics);
+
+        visit::walk_function(self, arena, function);
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        if param.name != "self" {
+            self.name(&param.name, "parameter", Case::Snake, param.name_span);
+        }
+
+        visit::walk_param(self, param);
+    }
+
+    fn visit_struct(&mut self, structure: &Struct) {
+        self.name(&structure.name, "struct", Case::UpperCamel, self.span);
+        self.generics(&structure.generics);
+
+        visit::walk_struct(self, structure);
+    }
+
+    fn visit_field(&mut self, field: &Field) {
+        self.name(&field.name, "field", Case::Snake, self.span);
+
+        visit::walk_field(self, field);
+    }
+
+    fn visit_enum(&mut self, enumeration: &Enum) {
+        self.name(&enumeration.name, "enum", Case::UpperCamel, self.span);
+
+        visit::walk_enum(self, enumeration);
+    }
+
+    fn visit_variant(&mut self, variant: &Variant) {
+        self.name(&variant.name, "variant", Case::UpperCamel, self.span);
+
+        visit::walk_variant(self, variant);
+    }
+
+    fn visit_trait(&mut self, declaration: &Trait) {
+        self.name(&declaration.name, "trait", Case::UpperCamel, self.span);
+
+        visit::walk_trait(self, declaration);
+    }
+
+    fn visit_signature(&mut self, signature: &Signature) {
+        self.name(&signature.name, "method", Case::Snake, self.span);
+
+        visit::walk_signature(self, signature);
+    }
+
+    /// Methods are named like functions, but are pointed at on their own.
+    fn visit_impl(&mut self, arena: &Arena, implementation: &Impl) {
+        self.visit_type(&implementation.ty);
+
+        for method in &implementation.methods {
+            self.within(method.span, |naming| {
+                let function = &method.function;
+
+                naming.name(&function.name, "method", Case::Snake, method.span);
+                naming.generics(&function.generics);
+
+                visit::walk_function(naming, arena, function);
+            });
+        }
+    }
+
+    /// Globals are named like variables.
+    fn visit_let(&mut self, arena: &Arena, decl: &Let) {
+        self.name(&decl.name, "variable", Case::Snake, decl.name_span);
+
+        visit::walk_let(self, arena, decl);
+    }
+
+    fn visit_stmt(&mut self, arena: &Arena, stmt: &Stmt) {
+        self.within(stmt.span, |naming| visit::walk_stmt(naming, arena, stmt));
+    }
+
+    fn visit_expr(&mut self, arena: &Arena, id: ExprId) {
+        self.within(arena[id].span, |naming| visit::walk_expr(naming, arena, id));
+    }
+
+    fn visit_closure_param(&mut self, param: &ClosureParam) {
+        self.name(&param.name, "parameter", Case::Snake, self.span);
+
+        visit::walk_closure_param(self, param);
+    }
+}
+
+/// Finds the variables declared with `:=` that are cast to a narrower integer
+/// type as the value of a variable declared with one, pointing at the cast
+/// and at the variable. Programs that weren't type checked are left out.
+struct Narrowing;
+
+impl LintPass for Narrowing {
+    fn check(&mut self, cx: &LintContext) -> Vec<Warning> {
+        let Some(types) = cx.types else {
+            return Vec::new();
+        };
+
+        let mut casts = Casts {
+            resolution: cx.resolution,
+            types,
+            inferred: HashSet::new(),
+            warnings: Vec::new(),
+        };
+
+        casts.visit_program(cx.parsed);
+
+        casts.warnings
+    }
+}
+
+/// Walks a program for `Narrowing`. Variables are declared before they are
+/// used, so the ones declared with `:=` are known by the time they are cast.
+struct Casts<'a> {
+    resolution: &'a Resolution,
+    types: &'a Types,
+    /// Where the names of the variables declared with `:=` are
+    inferred: HashSet<Span>,
+    warnings: Vec<Warning>,
+}
+
+impl Casts<'_> {
+    /// The warning for the value of a variable declared with a type, if it
+    /// casts a variable declared with `:=` to a type that can't hold all of
+    /// its values.
+    fn narrowed(&self, arena: &Arena, value: ExprId) -> Option<Warning> {
+        let ExprKind::Cast { value: cast, .. } = &arena[value].kind else {
+            return None;
+        };
+
+        let symbol = *self.resolution.uses.get(cast)?;
+        let declared = *self.resolution.names.get(&symbol)?;
+
+        if !self.inferred.contains(&declared) {
+            return None;
+        }
+
+        let (Ty::Int(from), Ty::Int(to)) =
+            (self.types.exprs.get(cast)?, self.types.exprs.get(&value)?)
+        else {
+            return None;
+        };
+
+        if to.min() <= from.min() && from.max() <= to.max() {
+            return None;
+        }
+
+        let warning = Warning::new(
+            WarningKind::Narrowed {
+                name: self.resolution.symbol(symbol).name.clone(),
+                from: *from,
+                to: *to,
+            },
+            arena[value].span,
+        );
+
+        Some(warning.declared_at(declared))
+    }
+}
+
+impl Visitor for Casts<'_> {
+    fn visit_let(&mut self, arena: &Arena, decl: &Let) {
+        match decl.ty {
+            None => {
+                self.inferred.insert(decl.name_span);
+            }
+            Some(_) => self.warnings.extend(self.narrowed(arena, decl.value)),
+        }
+
+        visit::walk_let(self, arena, decl);
+    }
+}
+
+/// Finds the lints the attributes of items name that don't exist, which
+/// would otherwise be ignored.
+struct UnknownLints;
+
+impl LintPass for UnknownLints {
+    fn check(&mut self, cx: &LintContext) -> Vec<Warning> {
+        let names = || std::iter::once("warnings").chain(Lint::ALL.iter().map(|lint| lint.name()));
+
+        cx.parsed
+            .program
+            .items
+            .iter()
+            .flat_map(|item| &item.attributes)
+            .filter(|attribute| Level::from_attribute(&attribute.name).is_some())
+            .flat_map(|attribute| &attribute.args)
+            .filter(|(name, _)| !names().any(|lint| lint == name))
+            .map(|(name, span)| {
+                let warning = Warning::new(WarningKind::UnknownLint { name: name.clone() }, *span);
+
+                match suggest::closest(name, names()) {
+                    Some(closest) => warning.with_suggestion(Suggestion::replace(
+                        format!("Replace it with '{}'", closest),
+                        *span,
+                        closest,
+                    )),
+                    None => warning,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Finds the names that are only assigned to with `=`.
+#[derive(Default)]
+struct Assigned(HashSet<ExprId>);
+
+impl Visitor for Assigned {
+    fn visit_expr(&mut self, arena: &Arena, id: ExprId) {
+        if let ExprKind::Assign {
+            op: None, target, ..
+        } = &arena[id].kind
+        {
+            if let ExprKind::Identifier(_) = arena[*target].kind {
+                self.0.insert(*target);
+            }
+        }
+
+        visit::walk_expr(self, arena, id);
+    }
+}
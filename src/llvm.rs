@@ -0,0 +1,109 @@
+//! A backend compiling the HIR of a program to LLVM IR, which LLVM turns
+//! into native object files, so programs run as fast as ones written in a
+//! systems language.
+//!
+//! The IR is written as text and compiled by `llc`, instead of being built
+//! through bindings to LLVM's C API like `inkwell`, so the compiler doesn't
+//! link against a particular version of LLVM and builds without one. Any
+//! LLVM from 14 on can compile it. That is why the `llvm` feature has no
+//! dependencies: it only decides whether this backend is built.
+//!
+//! - `codegen` turns a lowered program into a module of IR.
+//!
+//! Values are kept in registers, and aggregates like tuples, structs and
+//! arrays are first-class values, so locals are the only memory a function
+//! uses besides strings.
+use crate::backend::{Backend, CodegenError, Target};
+use crate::hir;
+use crate::source_map::SourceMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+pub mod codegen;
+
+pub use codegen::compile;
+
+/// The program compiling IR to object files, which has to be on the path.
+pub const LLC: &str = "llc";
+
+/// The LLVM backend, which compiles programs to IR and the IR to object
+/// files with `llc`.
+pub struct Llvm;
+
+impl Backend for Llvm {
+    fn name(&self) -> &'static str {
+        "LLVM"
+    }
+
+    fn write_object(
+        &self,
+        program: &hir::Program,
+        sources: Option<&SourceMap>,
+        target: Target,
+        path: &Path,
+    ) -> Result<(), Vec<CodegenError>> {
+        let ir = compile(program, sources)?;
+
+        write_object(&ir, target, path).map_err(|error| vec![error])
+    }
+}
+
+/// Compile a module of IR to a native object file for a target, with `llc`.
+pub fn write_object(ir: &str, target: Target, path: &Path) -> Result<(), CodegenError> {
+    let fail = |message: String| CodegenError::object(message);
+
+    let version = Command::new(LLC)
+        .arg("--version")
+        .output()
+        .map_err(|error| fail(format!("couldn't run '{}': {}", LLC, error)))?;
+
+    let major = llc_version(&String::from_utf8_lossy(&version.stdout))
+        .ok_or_else(|| fail(format!("couldn't tell which version '{}' is", LLC)))?;
+
+    if major < 14 {
+        return Err(fail(format!("LLVM 14 or newer is needed, found {}", major)));
+    }
+
+    let mut command = Command::new(LLC);
+
+    // Pointers without a type are only the default from LLVM 15 on
+    if major == 14 {
+        command.arg("-opaque-pointers");
+    }
+
+    let mut child = command
+        .arg(format!("-mtriple={}", target.triple()))
+        .args(["-filetype=obj", "-relocation-model=pic", "-o"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| fail(format!("couldn't run '{}': {}", LLC, error)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(ir.as_bytes())
+            .map_err(|error| fail(error.to_string()))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| fail(error.to_string()))?;
+
+    if !output.status.success() {
+        return Err(fail(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The major version of LLVM from what `llc --version` prints, e.g. 14 from
+/// `LLVM version 14.0.6`.
+pub fn llc_version(text: &str) -> Option<u32> {
+    let (_, version) = text.split_once("LLVM version ")?;
+
+    version.split('.').next()?.trim().parse().ok()
+}
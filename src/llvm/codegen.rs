@@ -0,0 +1,1520 @@
+use crate::arithmetic;
+use crate::backend::{self, int, CheckKind, CodegenError, CodegenErrorKind, Failure, Layout};
+use crate::hir::{self, Arm, Block, Expr, ExprKind, LocalId, Pattern, Stmt};
+use crate::lexer::Span;
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use crate::source_map::SourceMap;
+use crate::typeck::{IntTy, Ty};
+use std::collections::{BTreeSet, HashMap};
+
+/// The type strings are, a pointer to their bytes and their length.
+const STR: &str = "{ ptr, i64 }";
+
+/// Compile a lowered program to a module of LLVM IR. The spans of runtime
+/// errors are located in `sources`, if there are any, so they can say where
+/// the code that failed is.
+pub fn compile(
+    program: &hir::Program,
+    sources: Option<&SourceMap>,
+) -> Result<String, Vec<CodegenError>> {
+    let mut codegen = Codegen {
+        hir: program,
+        sources,
+        layout: Layout::new(program),
+        constants: Vec::new(),
+        bytes: HashMap::new(),
+        declarations: BTreeSet::new(),
+        globals: Vec::new(),
+        functions: Vec::new(),
+        errors: Vec::new(),
+        builder: Builder::default(),
+    };
+
+    for (i, function) in program.functions.iter().enumerate() {
+        codegen.function(i, function);
+    }
+
+    for global in &program.globals {
+        codegen.global(global);
+    }
+
+    codegen.entry_point();
+
+    if !codegen.errors.is_empty() {
+        return Err(codegen.errors);
+    }
+
+    Ok(codegen.module())
+}
+
+struct Codegen<'a> {
+    hir: &'a hir::Program,
+    sources: Option<&'a SourceMap>,
+    layout: Layout<'a>,
+    /// The definitions of constant bytes, for strings and messages
+    constants: Vec<String>,
+    /// The name of the constant with some bytes
+    bytes: HashMap<Vec<u8>, String>,
+    /// The functions from outside the module that are used
+    declarations: BTreeSet<String>,
+    /// The definitions of globals
+    globals: Vec<String>,
+    /// The definitions of functions
+    functions: Vec<String>,
+    errors: Vec<CodegenError>,
+    /// The function being compiled
+    builder: Builder,
+}
+
+#[derive(Default)]
+/// A function being compiled.
+struct Builder {
+    /// The type of each local, and its name
+    locals: Vec<(String, Ty)>,
+    /// Whether each local has been given memory
+    allocated: Vec<bool>,
+    /// The memory of locals and temporaries, at the start of the function so
+    /// loops don't grow the stack
+    allocas: Vec<String>,
+    code: Vec<String>,
+    /// How many values and labels have been named
+    next: usize,
+    /// The label of the block being compiled
+    block: String,
+    /// Whether the block being compiled has ended, so the code after it is
+    /// never run and needs a block of its own
+    terminated: bool,
+    /// The type the function returns
+    ret: String,
+    /// The deferred blocks of each block being compiled, innermost last
+    scopes: Vec<Vec<Block>>,
+    /// The loops being compiled, innermost last
+    loops: Vec<Loop>,
+}
+
+struct Loop {
+    /// The label `continue` jumps to
+    header: String,
+    /// The label `break` jumps to
+    exit: String,
+    /// How many blocks were being compiled when the loop started
+    scopes: usize,
+}
+
+/// A step from a value to a value in it, when assigning to it.
+enum Step {
+    /// A field of a struct or element of a tuple, by position
+    Field(u32),
+    /// An element of an array, at an index that has been evaluated, with the
+    /// array's length
+    Index(String, usize),
+}
+
+impl<'a> Codegen<'a> {
+    /// The definitions of everything that was compiled, as a module.
+    fn module(&mut self) -> String {
+        let fail = self.cstring("[RUNTIME ERROR]: %s.\n");
+
+        let mut module = String::from("; ModuleID = 'penguin'\nsource_filename = \"penguin\"\n\n");
+
+        for constant in &self.constants {
+            module.push_str(constant);
+            module.push('\n');
+        }
+
+        for global in &self.globals {
+            module.push_str(global);
+            module.push('\n');
+        }
+
+        module.push('\n');
+
+        self.declarations
+            .insert("declare i32 @printf(ptr, ...)".to_string());
+        self.declarations
+            .insert("declare void @exit(i32) noreturn".to_string());
+
+        for declaration in &self.declarations {
+            module.push_str(declaration);
+            module.push('\n');
+        }
+
+        module.push_str(&format!(
+            "\ndefine internal void @pg.fail(ptr %message) cold noreturn {{\n\
+             entry:\n\
+             \x20 %printed = call i32 (ptr, ...) @printf(ptr {}, ptr %message)\n\
+             \x20 call void @exit(i32 1)\n\
+             \x20 unreachable\n\
+             }}\n",
+            fail
+        ));
+
+        for function in &self.functions {
+            module.push('\n');
+            module.push_str(function);
+        }
+
+        module
+    }
+
+    fn function(&mut self, position: usize, function: &hir::Function) {
+        let ret = self.ty(&function.ret, function.span);
+
+        self.start_function(&function.locals, &ret);
+        let mut params = Vec::new();
+
+        for (i, &param) in function.params.iter().enumerate() {
+            let ty = self.local_ty(param, function.span);
+            let local = self.local(param, function.span);
+
+            params.push(format!("{} %arg{}", ty, i));
+            self.emit(format!("store {} %arg{}, ptr {}", ty, i, local));
+        }
+
+        let value = self.expr(&function.body);
+
+        self.terminate(format!("ret {} {}", ret, value));
+
+        let name = function_name(self.hir, position);
+        let definition = self.finish_function(format!("{} {}({})", ret, name, params.join(", ")));
+
+        self.functions.push(definition);
+    }
+
+    /// Compile the function computing the value of a global, which is called
+    /// before `main`.
+    fn global(&mut self, global: &hir::Global) {
+        let ty = self.ty(&global.ty, global.span);
+
+        self.start_function(&global.locals, &ty);
+        let value = self.expr(&global.value);
+
+        self.terminate(format!("ret {} {}", ty, value));
+
+        let definition =
+            self.finish_function(format!("internal {} @\"pg.init.{}\"()", ty, global.name));
+
+        self.functions.push(definition);
+        self.globals.push(format!(
+            "@\"pg.global.{}\" = internal global {} zeroinitializer",
+            global.name, ty
+        ));
+    }
+
+    /// Compile the `main` the program starts at, which sets the globals in
+    /// order and calls the program's `main`, exiting with what it returns.
+    fn entry_point(&mut self) {
+        let Some(position) = backend::entry_point(self.hir) else {
+            return;
+        };
+
+        let main = &self.hir.functions[position];
+
+        self.start_function(&[], "i32");
+
+        for global in &self.hir.globals {
+            let ty = self.ty(&global.ty, global.span);
+            let value = self.assign(format!("call {} @\"pg.init.{}\"()", ty, global.name));
+
+            self.emit(format!(
+                "store {} {}, ptr @\"pg.global.{}\"",
+                ty, value, global.name
+            ));
+        }
+
+        let ret = self.ty(&main.ret, main.span);
+        let value = self.assign(format!(
+            "call {} {}()",
+            ret,
+            function_name(self.hir, position)
+        ));
+
+        let code = match main.ret {
+            Ty::Int(ty) => self.convert(&value, ty, IntTy::I32),
+            _ => "0".to_string(),
+        };
+
+        self.terminate(format!("ret i32 {}", code));
+
+        let definition = self.finish_function("i32 @main()".to_string());
+
+        self.functions.push(definition);
+    }
+
+    fn start_function(&mut self, locals: &[hir::Local], ret: &str) {
+        self.builder = Builder {
+            locals: locals
+                .iter()
+                .map(|local| (local.name.clone(), local.ty.clone()))
+                .collect(),
+            allocated: vec![false; locals.len()],
+            block: "entry".to_string(),
+            ret: ret.to_string(),
+            ..Builder::default()
+        };
+    }
+
+    /// The definition of the function that was being compiled, with its
+    /// signature.
+    fn finish_function(&mut self, signature: String) -> String {
+        let builder = std::mem::take(&mut self.builder);
+
+        let mut definition = format!("define {} {{\nentry:\n", signature);
+
+        for line in builder.allocas.iter().chain(&builder.code) {
+            definition.push_str(line);
+            definition.push('\n');
+        }
+
+        definition.push_str("}\n");
+
+        definition
+    }
+
+    /// The type of a value in LLVM, or what isn't supported about it.
+    fn llvm_ty(&self, ty: &Ty) -> Result<String, String> {
+        self.llvm_ty_in(ty, 0)
+    }
+
+    fn llvm_ty_in(&self, ty: &Ty, depth: usize) -> Result<String, String> {
+        // Values of a type that contains itself would never end
+        if depth > 32 {
+            return Err("types that contain themselves".to_string());
+        }
+
+        let ty = match ty {
+            Ty::Int(ty) => format!("i{}", ty.bits()),
+            Ty::Bool => "i1".to_string(),
+            Ty::Str => STR.to_string(),
+            Ty::Never => "{}".to_string(),
+            Ty::Function { .. } => "ptr".to_string(),
+            Ty::Array {
+                element,
+                length: Some(length),
+            } => format!("[{} x {}]", length, self.llvm_ty_in(element, depth + 1)?),
+            Ty::Array { length: None, .. } => {
+                return Err("arrays without a length".to_string());
+            }
+            Ty::Param(_) | Ty::Unknown => return Err("values of unknown types".to_string()),
+            Ty::Tuple(_) | Ty::Struct(..) | Ty::Enum(_) | Ty::Optional(_) => {
+                let elements = self
+                    .layout
+                    .elements(ty)
+                    .iter()
+                    .map(|element| self.llvm_ty_in(element, depth + 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if elements.is_empty() {
+                    "{}".to_string()
+                } else {
+                    format!("{{ {} }}", elements.join(", "))
+                }
+            }
+        };
+
+        Ok(ty)
+    }
+
+    /// The type of a value in LLVM, reporting it if it isn't supported.
+    fn ty(&mut self, ty: &Ty, span: Span) -> String {
+        match self.llvm_ty(ty) {
+            Ok(ty) => ty,
+            Err(what) => {
+                self.unsupported(&what, span);
+
+                "{}".to_string()
+            }
+        }
+    }
+
+    fn local_ty(&mut self, local: LocalId, span: Span) -> String {
+        let ty = self.builder.locals[local.index()].1.clone();
+
+        self.ty(&ty, span)
+    }
+
+    /// Report something that isn't supported, returning a value that stands
+    /// for it so compiling can go on.
+    fn unsupported(&mut self, what: &str, span: Span) -> String {
+        let error = CodegenError::new(
+            CodegenErrorKind::Unsupported {
+                backend: "LLVM",
+                what: what.to_string(),
+            },
+            span,
+        );
+
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+
+        "undef".to_string()
+    }
+
+    /// Add an instruction to the block being compiled, or a new one if it
+    /// has ended.
+    fn emit(&mut self, instr: String) {
+        if self.builder.terminated {
+            let dead = self.label("dead");
+
+            self.start(&dead);
+        }
+
+        self.builder.code.push(format!("  {}", instr));
+    }
+
+    /// Add an instruction with a value, returning its name.
+    fn assign(&mut self, instr: String) -> String {
+        let name = format!("%t{}", self.builder.next);
+
+        self.builder.next += 1;
+        self.emit(format!("{} = {}", name, instr));
+
+        name
+    }
+
+    /// Add the instruction ending a block.
+    fn terminate(&mut self, instr: String) {
+        self.emit(instr);
+        self.builder.terminated = true;
+    }
+
+    fn label(&mut self, hint: &str) -> String {
+        self.builder.next += 1;
+
+        format!("{}{}", hint, self.builder.next)
+    }
+
+    /// Start compiling a block, which the block before it falls through to
+    /// if it hasn't ended.
+    fn start(&mut self, label: &str) {
+        if !self.builder.terminated {
+            self.builder.code.push(format!("  br label %{}", label));
+        }
+
+        self.builder.code.push(format!("{}:", label));
+        self.builder.block = label.to_string();
+        self.builder.terminated = false;
+    }
+
+    /// The memory of a local, giving it some if it has none yet.
+    fn local(&mut self, local: LocalId, span: Span) -> String {
+        let (name, _) = &self.builder.locals[local.index()];
+        let pointer = format!("%\"{}.{}\"", name, local.index());
+
+        if !self.builder.allocated[local.index()] {
+            let ty = self.local_ty(local, span);
+
+            self.builder.allocated[local.index()] = true;
+            self.builder
+                .allocas
+                .push(format!("  {} = alloca {}", pointer, ty));
+        }
+
+        pointer
+    }
+
+    /// Memory for a value the compiled code needs to have in memory.
+    fn temporary(&mut self, ty: &str) -> String {
+        let pointer = format!("%tmp{}", self.builder.next);
+
+        self.builder.next += 1;
+        self.builder
+            .allocas
+            .push(format!("  {} = alloca {}", pointer, ty));
+
+        pointer
+    }
+
+    /// The name of a constant with some bytes, adding it if there isn't one.
+    fn constant(&mut self, bytes: &[u8]) -> String {
+        if let Some(name) = self.bytes.get(bytes) {
+            return name.clone();
+        }
+
+        let name = format!("@str.{}", self.constants.len());
+        let text: String = bytes
+            .iter()
+            .map(|&byte| match byte {
+                b'"' | b'\\' => format!("\\{:02X}", byte),
+                0x20..=0x7e => (byte as char).to_string(),
+                _ => format!("\\{:02X}", byte),
+            })
+            .collect();
+
+        self.constants.push(format!(
+            "{} = private unnamed_addr constant [{} x i8] c\"{}\"",
+            name,
+            bytes.len(),
+            text
+        ));
+        self.bytes.insert(bytes.to_vec(), name.clone());
+
+        name
+    }
+
+    /// A string value.
+    fn string(&mut self, string: &str) -> String {
+        let constant = self.constant(string.as_bytes());
+
+        format!("{{ ptr {}, i64 {} }}", constant, string.len())
+    }
+
+    /// A pointer to text ending in a zero byte, for C functions.
+    fn cstring(&mut self, text: &str) -> String {
+        let mut bytes = text.as_bytes().to_vec();
+
+        bytes.push(0);
+
+        self.constant(&bytes)
+    }
+
+    /// Stop the program with a runtime error if `failed` is true.
+    fn check(&mut self, failed: &str, failure: &Failure, span: Span) {
+        let fail = self.label("fail");
+        let ok = self.label("ok");
+
+        self.terminate(format!("br i1 {}, label %{}, label %{}", failed, fail, ok));
+        self.start(&fail);
+
+        let message = backend::runtime_error(self.sources, span, failure);
+        let message = self.cstring(&message);
+
+        self.emit(format!("call void @pg.fail(ptr {})", message));
+        self.terminate("unreachable".to_string());
+        self.start(&ok);
+    }
+
+    /// Compile an expression, returning its value.
+    fn expr(&mut self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Int(int) => int.to_string(),
+            ExprKind::Bool(value) => value.to_string(),
+            ExprKind::Str(string) => self.string(string),
+            ExprKind::Local(local) => {
+                let ty = self.local_ty(*local, expr.span);
+                let pointer = self.local(*local, expr.span);
+
+                self.assign(format!("load {}, ptr {}", ty, pointer))
+            }
+            ExprKind::Function(position) => function_name(self.hir, *position),
+            ExprKind::Global(position) => {
+                let ty = self.ty(&expr.ty, expr.span);
+                let name = &self.hir.globals[*position].name;
+
+                self.assign(format!("load {}, ptr @\"pg.global.{}\"", ty, name))
+            }
+            ExprKind::Import(name) => {
+                self.unsupported(&format!("'{}' from another file", name), expr.span)
+            }
+            ExprKind::Tuple(elements) | ExprKind::Array(elements) => {
+                let values: Vec<_> = elements.iter().map(|element| self.expr(element)).collect();
+
+                self.aggregate(&expr.ty, &values, expr.span)
+            }
+            ExprKind::Struct { name, fields } => {
+                let values: Vec<_> = fields
+                    .iter()
+                    .map(|(field, value)| (field.as_str(), self.expr(value)))
+                    .collect();
+
+                let Some(structure) = self.layout.structure(name) else {
+                    return self.unsupported(&format!("the struct '{}'", name), expr.span);
+                };
+
+                // The fields are evaluated in the order they are written, but
+                // stored in the order they are declared
+                let ordered: Vec<_> = structure
+                    .fields
+                    .iter()
+                    .map(|(field, _)| {
+                        values
+                            .iter()
+                            .find(|(name, _)| name == field)
+                            .map_or("undef".to_string(), |(_, value)| value.clone())
+                    })
+                    .collect();
+
+                self.aggregate(&expr.ty, &ordered, expr.span)
+            }
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                let values: Vec<_> = payload.iter().map(|value| self.expr(value)).collect();
+
+                self.variant(&expr.ty, enum_name, variant, &values, expr.span)
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Unary { op, operand } => {
+                let value = self.expr(operand);
+
+                match op {
+                    UnaryOp::Negate => {
+                        self.arithmetic(BinaryOp::Subtract, int(&expr.ty), "0", &value, expr.span)
+                    }
+                    UnaryOp::Not => self.assign(format!("xor i1 {}, true", value)),
+                }
+            }
+            ExprKind::Binary { op, left, right } => self.binary(*op, left, right, expr),
+            ExprKind::Cast(value) => {
+                let compiled = self.expr(value);
+
+                match (&value.ty, &expr.ty) {
+                    (Ty::Int(from), Ty::Int(to)) => self.convert(&compiled, *from, *to),
+                    (Ty::Bool, Ty::Int(to)) => {
+                        self.assign(format!("zext i1 {} to i{}", compiled, to.bits()))
+                    }
+                    _ => compiled,
+                }
+            }
+            ExprKind::Call { callee, args } => self.call(callee, args, expr),
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                if receiver.ty == Ty::Str && method == "len" && args.is_empty() {
+                    let string = self.expr(receiver);
+                    let length = self.assign(format!("extractvalue {} {}, 1", STR, string));
+
+                    return self.convert(&length, IntTy::U64, int(&expr.ty));
+                }
+
+                self.unsupported(
+                    &format!("the method '{}' from another file", method),
+                    expr.span,
+                )
+            }
+            ExprKind::Index { object, index } => self.index(object, index, expr),
+            ExprKind::Field { object, field } => {
+                let value = self.expr(object);
+                let ty = self.ty(&object.ty, object.span);
+
+                match self.layout.field(&object.ty, field) {
+                    Some(position) => {
+                        self.assign(format!("extractvalue {} {}, {}", ty, value, position))
+                    }
+                    None => self.unsupported(&format!("the field '{}'", field), expr.span),
+                }
+            }
+            ExprKind::Assign { target, value } => {
+                self.assignment(target, value);
+
+                "zeroinitializer".to_string()
+            }
+            ExprKind::Closure { .. } => self.unsupported("closures", expr.span),
+            ExprKind::Match { value, arms } => self.matching(value, arms, expr),
+            ExprKind::Loop(body) => self.looping(body),
+            ExprKind::Break | ExprKind::Continue => {
+                let Some(innermost) = self.builder.loops.last() else {
+                    return self.unsupported("leaving a loop outside of one", expr.span);
+                };
+
+                let target = match expr.kind {
+                    ExprKind::Break => innermost.exit.clone(),
+                    _ => innermost.header.clone(),
+                };
+
+                self.run_defers(innermost.scopes);
+                self.terminate(format!("br label %{}", target));
+
+                "undef".to_string()
+            }
+            ExprKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value),
+                    None => "zeroinitializer".to_string(),
+                };
+                let ret = self.builder.ret.clone();
+
+                self.run_defers(0);
+                self.terminate(format!("ret {} {}", ret, value));
+
+                "undef".to_string()
+            }
+        }
+    }
+
+    /// A tuple, array or struct with some values, in the order they are
+    /// stored.
+    fn aggregate(&mut self, ty: &Ty, values: &[String], span: Span) -> String {
+        let aggregate = self.ty(ty, span);
+        let elements = self.layout.elements(ty);
+        let mut value = "zeroinitializer".to_string();
+
+        for (i, (element, ty)) in values.iter().zip(&elements).enumerate() {
+            let ty = self.ty(ty, span);
+
+            value = self.assign(format!(
+                "insertvalue {} {}, {} {}, {}",
+                aggregate, value, ty, element, i
+            ));
+        }
+
+        value
+    }
+
+    /// A variant of an enum with its payload. Every other variant's payload
+    /// is zero, so variants can be compared by all of their values.
+    fn variant(
+        &mut self,
+        ty: &Ty,
+        enum_name: &str,
+        variant: &str,
+        payload: &[String],
+        span: Span,
+    ) -> String {
+        let Some((position, types)) = self.layout.variant(enum_name, variant) else {
+            return self.unsupported(&format!("the variant '{}::{}'", enum_name, variant), span);
+        };
+
+        let enumeration = self.ty(ty, span);
+        let payload_ty = Ty::Tuple(types);
+        let payload = self.aggregate(&payload_ty, payload, span);
+        let payload_ty = self.ty(&payload_ty, span);
+
+        let tagged = self.assign(format!(
+            "insertvalue {} zeroinitializer, i32 {}, 0",
+            enumeration, position
+        ));
+
+        self.assign(format!(
+            "insertvalue {} {}, {} {}, {}",
+            enumeration,
+            tagged,
+            payload_ty,
+            payload,
+            position + 1
+        ))
+    }
+
+    /// Convert an integer from one type to another, keeping its low bits if
+    /// the new type is narrower.
+    fn convert(&mut self, value: &str, from: IntTy, to: IntTy) -> String {
+        let (bits, to_bits) = (from.bits(), to.bits());
+
+        if to_bits < bits {
+            self.assign(format!("trunc i{} {} to i{}", bits, value, to_bits))
+        } else if to_bits > bits && from.is_signed() {
+            self.assign(format!("sext i{} {} to i{}", bits, value, to_bits))
+        } else if to_bits > bits {
+            self.assign(format!("zext i{} {} to i{}", bits, value, to_bits))
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: &Expr, right: &Expr, expr: &Expr) -> String {
+        if let Some(decided) = backend::short_circuit(op) {
+            let value = self.expr(left);
+            let (rest, end) = (self.label("rhs"), self.label("end"));
+            let (then, otherwise) = match decided {
+                true => (&end, &rest),
+                false => (&rest, &end),
+            };
+
+            self.terminate(format!(
+                "br i1 {}, label %{}, label %{}",
+                value, then, otherwise
+            ));
+
+            let skipped = self.builder.block.clone();
+
+            self.start(&rest);
+
+            let right = self.expr(right);
+
+            self.terminate(format!("br label %{}", end));
+
+            let evaluated = self.builder.block.clone();
+
+            self.start(&end);
+
+            return self.assign(format!(
+                "phi i1 [ {}, %{} ], [ {}, %{} ]",
+                decided, skipped, right, evaluated
+            ));
+        }
+
+        let l = self.expr(left);
+        let r = self.expr(right);
+
+        match op {
+            BinaryOp::Add if left.ty == Ty::Str => self.concat(&l, &r),
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulo => self.arithmetic(op, int(&expr.ty), &l, &r, expr.span),
+            BinaryOp::Equal => self.equal(&left.ty, &l, &r),
+            BinaryOp::NotEqual => {
+                let equal = self.equal(&left.ty, &l, &r);
+
+                self.assign(format!("xor i1 {}, true", equal))
+            }
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
+                let ty = int(&left.ty);
+                let sign = if ty.is_signed() { 's' } else { 'u' };
+                let condition = match op {
+                    BinaryOp::Less => "lt",
+                    BinaryOp::LessEqual => "le",
+                    BinaryOp::Greater => "gt",
+                    _ => "ge",
+                };
+
+                self.assign(format!(
+                    "icmp {}{} i{} {}, {}",
+                    sign,
+                    condition,
+                    ty.bits(),
+                    l,
+                    r
+                ))
+            }
+            BinaryOp::And | BinaryOp::Or => unreachable!("Short-circuited above"),
+        }
+    }
+
+    /// Integer arithmetic, stopping the program if it divides by zero, or if
+    /// the result doesn't fit its type with overflow checks.
+    fn arithmetic(
+        &mut self,
+        op: BinaryOp,
+        ty: IntTy,
+        left: &str,
+        right: &str,
+        span: Span,
+    ) -> String {
+        let (bits, sign) = (ty.bits(), if ty.is_signed() { 's' } else { 'u' });
+        let overflow = Failure::Overflow(ty);
+        let checks = arithmetic::checks(op, ty, self.hir.overflow);
+
+        let name = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Subtract => "sub",
+            BinaryOp::Multiply => "mul",
+            _ => {
+                let zero = self.assign(format!("icmp eq i{} {}, 0", bits, right));
+
+                self.check(&zero, &Failure::DivisionByZero, span);
+
+                if !ty.is_signed() {
+                    let instr = if op == BinaryOp::Divide {
+                        "udiv"
+                    } else {
+                        "urem"
+                    };
+
+                    return self.assign(format!("{} i{} {}, {}", instr, bits, left, right));
+                }
+
+                let minus_one = self.assign(format!("icmp eq i{} {}, -1", bits, right));
+
+                if checks.overflow {
+                    let smallest = self.assign(format!("icmp eq i{} {}, {}", bits, left, ty.min()));
+                    let both = self.assign(format!("and i1 {}, {}", smallest, minus_one));
+
+                    self.check(&both, &overflow, span);
+
+                    return self.assign(format!("sdiv i{} {}, {}", bits, left, right));
+                }
+
+                // Dividing the smallest value by -1 is undefined, so it
+                // divides by 1 instead, and negates the dividend for -1
+                let divisor = self.assign(format!(
+                    "select i1 {}, i{} 1, i{} {}",
+                    minus_one, bits, bits, right
+                ));
+
+                if op == BinaryOp::Modulo {
+                    return self.assign(format!("srem i{} {}, {}", bits, left, divisor));
+                }
+
+                let quotient = self.assign(format!("sdiv i{} {}, {}", bits, left, divisor));
+                let negated = self.assign(format!("sub i{} 0, {}", bits, left));
+
+                return self.assign(format!(
+                    "select i1 {}, i{} {}, i{} {}",
+                    minus_one, bits, negated, bits, quotient
+                ));
+            }
+        };
+
+        if !checks.overflow {
+            return self.assign(format!("{} i{} {}, {}", name, bits, left, right));
+        }
+
+        let intrinsic = format!("llvm.{}{}.with.overflow.i{}", sign, name, bits);
+
+        self.declarations.insert(format!(
+            "declare {{ i{0}, i1 }} @{1}(i{0}, i{0})",
+            bits, intrinsic
+        ));
+
+        let result = self.assign(format!(
+            "call {{ i{0}, i1 }} @{1}(i{0} {2}, i{0} {3})",
+            bits, intrinsic, left, right
+        ));
+        let value = self.assign(format!("extractvalue {{ i{}, i1 }} {}, 0", bits, result));
+        let overflowed = self.assign(format!("extractvalue {{ i{}, i1 }} {}, 1", bits, result));
+
+        self.check(&overflowed, &overflow, span);
+
+        value
+    }
+
+    /// Join two strings into a new one.
+    fn concat(&mut self, left: &str, right: &str) -> String {
+        self.declarations
+            .insert("declare ptr @malloc(i64)".to_string());
+        self.declarations
+            .insert("declare ptr @memcpy(ptr, ptr, i64)".to_string());
+
+        let (left_bytes, left_length) = self.parts(left);
+        let (right_bytes, right_length) = self.parts(right);
+
+        let length = self.assign(format!("add i64 {}, {}", left_length, right_length));
+        let bytes = self.assign(format!("call ptr @malloc(i64 {})", length));
+        let rest = self.assign(format!(
+            "getelementptr i8, ptr {}, i64 {}",
+            bytes, left_length
+        ));
+
+        self.assign(format!(
+            "call ptr @memcpy(ptr {}, ptr {}, i64 {})",
+            bytes, left_bytes, left_length
+        ));
+        self.assign(format!(
+            "call ptr @memcpy(ptr {}, ptr {}, i64 {})",
+            rest, right_bytes, right_length
+        ));
+
+        let string = self.assign(format!("insertvalue {} undef, ptr {}, 0", STR, bytes));
+
+        self.assign(format!("insertvalue {} {}, i64 {}, 1", STR, string, length))
+    }
+
+    /// The bytes and length of a string.
+    fn parts(&mut self, string: &str) -> (String, String) {
+        let bytes = self.assign(format!("extractvalue {} {}, 0", STR, string));
+        let length = self.assign(format!("extractvalue {} {}, 1", STR, string));
+
+        (bytes, length)
+    }
+
+    /// Whether two values of a type are equal, comparing aggregates by each
+    /// of their values.
+    fn equal(&mut self, ty: &Ty, left: &str, right: &str) -> String {
+        match ty {
+            Ty::Int(int) => self.assign(format!("icmp eq i{} {}, {}", int.bits(), left, right)),
+            Ty::Bool => self.assign(format!("icmp eq i1 {}, {}", left, right)),
+            Ty::Function { .. } => self.assign(format!("icmp eq ptr {}, {}", left, right)),
+            Ty::Str => {
+                self.declarations
+                    .insert("declare i32 @memcmp(ptr, ptr, i64)".to_string());
+
+                let (left_bytes, left_length) = self.parts(left);
+                let (right_bytes, right_length) = self.parts(right);
+
+                // Only the bytes of strings of the same length are compared,
+                // so neither is read past its end
+                let same = self.assign(format!("icmp eq i64 {}, {}", left_length, right_length));
+                let length = self.assign(format!("select i1 {}, i64 {}, i64 0", same, left_length));
+                let compared = self.assign(format!(
+                    "call i32 @memcmp(ptr {}, ptr {}, i64 {})",
+                    left_bytes, right_bytes, length
+                ));
+                let bytes = self.assign(format!("icmp eq i32 {}, 0", compared));
+
+                self.assign(format!("and i1 {}, {}", same, bytes))
+            }
+            _ => {
+                let Ok(aggregate) = self.llvm_ty(ty) else {
+                    return "false".to_string();
+                };
+
+                let mut equal = "true".to_string();
+
+                for (i, element) in self.layout.elements(ty).iter().enumerate() {
+                    let l = self.assign(format!("extractvalue {} {}, {}", aggregate, left, i));
+                    let r = self.assign(format!("extractvalue {} {}, {}", aggregate, right, i));
+                    let same = self.equal(element, &l, &r);
+
+                    equal = self.assign(format!("and i1 {}, {}", equal, same));
+                }
+
+                equal
+            }
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr], expr: &Expr) -> String {
+        if let ExprKind::Import(name) = &callee.kind {
+            if name == "print" {
+                return self.print(args);
+            }
+        }
+
+        let values: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+
+        let (name, params, ret) = match (&callee.kind, &callee.ty) {
+            (ExprKind::Function(position), _) => {
+                let function = &self.hir.functions[*position];
+                let params: Vec<_> = function
+                    .params
+                    .iter()
+                    .map(|&param| function.locals[param.index()].ty.clone())
+                    .collect();
+
+                (
+                    function_name(self.hir, *position),
+                    params,
+                    function.ret.clone(),
+                )
+            }
+            (_, Ty::Function { params, ret }) => {
+                (self.expr(callee), params.clone(), (**ret).clone())
+            }
+            _ => return self.unsupported("calling this", callee.span),
+        };
+
+        let args: Vec<_> = params
+            .iter()
+            .zip(&values)
+            .map(|(ty, value)| format!("{} {}", self.ty(ty, expr.span), value))
+            .collect();
+        let ret = self.ty(&ret, expr.span);
+
+        self.assign(format!("call {} {}({})", ret, name, args.join(", ")))
+    }
+
+    /// Print values on a line, separated by spaces.
+    fn print(&mut self, args: &[Expr]) -> String {
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                self.print_text(" ");
+            }
+
+            let value = self.expr(arg);
+
+            self.print_value(&arg.ty, &value, true, arg.span);
+        }
+
+        self.print_text("\n");
+
+        "zeroinitializer".to_string()
+    }
+
+    fn print_text(&mut self, text: &str) {
+        let format = self.cstring(&text.replace('%', "%%"));
+
+        self.assign(format!("call i32 (ptr, ...) @printf(ptr {})", format));
+    }
+
+    /// Print a value as it is written in the source. Strings are printed as
+    /// they are when `raw`, and quoted otherwise.
+    fn print_value(&mut self, ty: &Ty, value: &str, raw: bool, span: Span) {
+        match ty {
+            Ty::Int(int) => {
+                let (wide, format) = if int.is_signed() {
+                    (IntTy::I64, "%lld")
+                } else {
+                    (IntTy::U64, "%llu")
+                };
+                let value = self.convert(value, *int, wide);
+                let format = self.cstring(format);
+
+                self.assign(format!(
+                    "call i32 (ptr, ...) @printf(ptr {}, i64 {})",
+                    format, value
+                ));
+            }
+            Ty::Bool => {
+                let (yes, no) = (self.string("true"), self.string("false"));
+                let string = self.assign(format!(
+                    "select i1 {}, {} {}, {} {}",
+                    value, STR, yes, STR, no
+                ));
+
+                self.print_str(&string);
+            }
+            Ty::Str if raw => self.print_str(value),
+            Ty::Str => {
+                self.print_text("\"");
+                self.print_str(value);
+                self.print_text("\"");
+            }
+            Ty::Tuple(types) if types.is_empty() => self.print_text("()"),
+            Ty::Tuple(_) => {
+                let elements = self.layout.elements(ty);
+
+                self.print_text("(");
+                self.print_elements(ty, value, &elements, None, span);
+                self.print_text(if elements.len() == 1 { ",)" } else { ")" });
+            }
+            Ty::Array { .. } => {
+                let elements = self.layout.elements(ty);
+
+                self.print_text("[");
+                self.print_elements(ty, value, &elements, None, span);
+                self.print_text("]");
+            }
+            Ty::Struct(name, _) => {
+                let elements = self.layout.elements(ty);
+                let fields: Vec<_> = self
+                    .layout
+                    .structure(name)
+                    .map(|structure| {
+                        let fields = &structure.fields;
+
+                        fields.iter().map(|(field, _)| field.clone()).collect()
+                    })
+                    .unwrap_or_default();
+
+                self.print_text(&format!("{} {{ ", name));
+                self.print_elements(ty, value, &elements, Some(&fields), span);
+                self.print_text(" }");
+            }
+            Ty::Enum(name) => self.print_variant(ty, name, value, span),
+            Ty::Optional(inner) => {
+                let aggregate = self.ty(ty, span);
+                let has = self.assign(format!("extractvalue {} {}, 0", aggregate, value));
+                let (some, none, end) = (self.label("some"), self.label("none"), self.label("end"));
+
+                self.terminate(format!("br i1 {}, label %{}, label %{}", has, some, none));
+                self.start(&some);
+
+                let inner_value = self.assign(format!("extractvalue {} {}, 1", aggregate, value));
+
+                self.print_text("Some(");
+                self.print_value(inner, &inner_value, false, span);
+                self.print_text(")");
+                self.terminate(format!("br label %{}", end));
+                self.start(&none);
+                self.print_text("None");
+                self.start(&end);
+            }
+            Ty::Function { .. } => self.print_text("<func>"),
+            Ty::Never => {}
+            Ty::Param(_) | Ty::Unknown => {
+                self.unsupported("printing values of unknown types", span);
+            }
+        }
+    }
+
+    /// Print the values in an aggregate separated by commas, named by
+    /// `fields` if they are given.
+    fn print_elements(
+        &mut self,
+        ty: &Ty,
+        value: &str,
+        elements: &[Ty],
+        fields: Option<&[String]>,
+        span: Span,
+    ) {
+        let aggregate = self.ty(ty, span);
+
+        for (i, element) in elements.iter().enumerate() {
+            if i > 0 {
+                self.print_text(", ");
+            }
+
+            if let Some(field) = fields.and_then(|fields| fields.get(i)) {
+                self.print_text(&format!("{}: ", field));
+            }
+
+            let element_value = self.assign(format!("extractvalue {} {}, {}", aggregate, value, i));
+
+            self.print_value(element, &element_value, false, span);
+        }
+    }
+
+    /// Print the variant an enum is, with its payload.
+    fn print_variant(&mut self, ty: &Ty, name: &str, value: &str, span: Span) {
+        let Some(variants) = self.layout.variants(name) else {
+            return;
+        };
+
+        let aggregate = self.ty(ty, span);
+        let tag = self.assign(format!("extractvalue {} {}, 0", aggregate, value));
+        let end = self.label("end");
+
+        for (i, (variant, payload)) in variants.iter().enumerate() {
+            let (this, next) = (self.label("variant"), self.label("next"));
+            let is = self.assign(format!("icmp eq i32 {}, {}", tag, i));
+
+            self.terminate(format!("br i1 {}, label %{}, label %{}", is, this, next));
+            self.start(&this);
+            self.print_text(&format!("{}::{}", name, variant));
+
+            if !payload.is_empty() {
+                let payload_ty = Ty::Tuple(payload.clone());
+                let payload_value =
+                    self.assign(format!("extractvalue {} {}, {}", aggregate, value, i + 1));
+
+                self.print_text("(");
+                self.print_elements(&payload_ty, &payload_value, payload, None, span);
+                self.print_text(")");
+            }
+
+            self.terminate(format!("br label %{}", end));
+            self.start(&next);
+        }
+
+        self.start(&end);
+    }
+
+    fn print_str(&mut self, string: &str) {
+        let (bytes, length) = self.parts(string);
+        let length = self.assign(format!("trunc i64 {} to i32", length));
+        let format = self.cstring("%.*s");
+
+        self.assign(format!(
+            "call i32 (ptr, ...) @printf(ptr {}, i32 {}, ptr {})",
+            format, length, bytes
+        ));
+    }
+
+    /// Stop the program if an index isn't less than a length.
+    fn bounds(&mut self, index: &str, length: &str, span: Span) {
+        let outside = self.assign(format!("icmp uge i64 {}, {}", index, length));
+
+        self.check(&outside, &Failure::OutOfBounds, span);
+    }
+
+    fn index(&mut self, object: &Expr, index: &Expr, expr: &Expr) -> String {
+        let value = self.expr(object);
+        let at = self.expr(index);
+
+        // Negative indexes are huge when they are compared unsigned, so they
+        // are out of bounds too
+        let at = self.convert(&at, int(&index.ty), IntTy::I64);
+
+        match &object.ty {
+            Ty::Array {
+                element,
+                length: Some(length),
+            } => {
+                let array = self.ty(&object.ty, object.span);
+                let element = self.ty(element, expr.span);
+
+                self.bounds(&at, &length.to_string(), expr.span);
+
+                let memory = self.temporary(&array);
+
+                self.emit(format!("store {} {}, ptr {}", array, value, memory));
+
+                let pointer = self.assign(format!(
+                    "getelementptr {}, ptr {}, i64 0, i64 {}",
+                    array, memory, at
+                ));
+
+                self.assign(format!("load {}, ptr {}", element, pointer))
+            }
+            Ty::Str => {
+                let (bytes, length) = self.parts(&value);
+
+                self.bounds(&at, &length, expr.span);
+
+                let pointer = self.assign(format!("getelementptr i8, ptr {}, i64 {}", bytes, at));
+                let byte = self.assign(format!("load i8, ptr {}", pointer));
+
+                self.convert(&byte, IntTy::U8, int(&expr.ty))
+            }
+            _ => self.unsupported("indexing this", expr.span),
+        }
+    }
+
+    /// Compile an assignment, storing the value where the target is in
+    /// memory. The indexes in the target are evaluated before the value.
+    fn assignment(&mut self, target: &Expr, value: &Expr) {
+        let mut steps = Vec::new();
+
+        let Some((root, ty)) = self.place(target, &mut steps) else {
+            self.unsupported("assigning to this", target.span);
+
+            return;
+        };
+
+        let value = self.expr(value);
+        let root_ty = self.ty(&ty, target.span);
+        let target_ty = self.ty(&target.ty, target.span);
+
+        if steps.is_empty() {
+            return self.emit(format!("store {} {}, ptr {}", target_ty, value, root));
+        }
+
+        let mut indexes = vec!["i32 0".to_string()];
+
+        for step in steps {
+            match step {
+                Step::Field(position) => indexes.push(format!("i32 {}", position)),
+                Step::Index(at, length) => {
+                    self.bounds(&at, &length.to_string(), target.span);
+                    indexes.push(format!("i64 {}", at));
+                }
+            }
+        }
+
+        let pointer = self.assign(format!(
+            "getelementptr {}, ptr {}, {}",
+            root_ty,
+            root,
+            indexes.join(", ")
+        ));
+
+        self.emit(format!("store {} {}, ptr {}", target_ty, value, pointer));
+    }
+
+    /// The memory an assignment target is in and its type, and the steps
+    /// into it, evaluating its indexes.
+    fn place(&mut self, target: &Expr, steps: &mut Vec<Step>) -> Option<(String, Ty)> {
+        match &target.kind {
+            ExprKind::Local(local) => {
+                let ty = self.builder.locals[local.index()].1.clone();
+
+                Some((self.local(*local, target.span), ty))
+            }
+            ExprKind::Global(position) => {
+                let global = &self.hir.globals[*position];
+
+                Some((format!("@\"pg.global.{}\"", global.name), global.ty.clone()))
+            }
+            ExprKind::Field { object, field } => {
+                let root = self.place(object, steps)?;
+
+                steps.push(Step::Field(self.layout.field(&object.ty, field)?));
+
+                Some(root)
+            }
+            ExprKind::Index { object, index } => {
+                let root = self.place(object, steps)?;
+
+                let Ty::Array {
+                    length: Some(length),
+                    ..
+                } = object.ty
+                else {
+                    return None;
+                };
+
+                let at = self.expr(index);
+                let at = self.convert(&at, int(&index.ty), IntTy::I64);
+
+                steps.push(Step::Index(at, length));
+
+                Some(root)
+            }
+            _ => None,
+        }
+    }
+
+    /// Compile a block, running what it deferred once it has its value.
+    fn block(&mut self, block: &Block) -> String {
+        self.builder.scopes.push(Vec::new());
+
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+
+        let value = match &block.value {
+            Some(value) => self.expr(value),
+            None => "zeroinitializer".to_string(),
+        };
+
+        let deferred = self.builder.scopes.pop().unwrap_or_default();
+
+        for body in backend::unwinding(&[deferred]) {
+            self.block(&body);
+        }
+
+        value
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { local, value } => {
+                let compiled = self.expr(value);
+                let ty = self.local_ty(*local, value.span);
+                let pointer = self.local(*local, value.span);
+
+                self.emit(format!("store {} {}, ptr {}", ty, compiled, pointer));
+            }
+            Stmt::Assert { condition, message } => {
+                let value = self.expr(condition);
+                let failed = self.assign(format!("xor i1 {}, true", value));
+                let failure = Failure::AssertionFailed(message.clone());
+
+                self.check(&failed, &failure, condition.span);
+            }
+            Stmt::Defer(body) => {
+                if let Some(scope) = self.builder.scopes.last_mut() {
+                    scope.push(body.clone());
+                }
+            }
+            Stmt::Expr(expr) => {
+                self.expr(expr);
+            }
+        }
+    }
+
+    fn run_defers(&mut self, scope: usize) {
+        for body in backend::unwinding(&self.builder.scopes[scope..]) {
+            self.block(&body);
+        }
+    }
+
+    fn looping(&mut self, body: &Block) -> String {
+        let (header, exit) = (self.label("loop"), self.label("exit"));
+
+        self.start(&header);
+        self.builder.loops.push(Loop {
+            header: header.clone(),
+            exit: exit.clone(),
+            scopes: self.builder.scopes.len(),
+        });
+
+        self.block(body);
+        self.terminate(format!("br label %{}", header));
+
+        self.builder.loops.pop();
+        self.start(&exit);
+
+        "zeroinitializer".to_string()
+    }
+
+    fn matching(&mut self, value: &Expr, arms: &[Arm], expr: &Expr) -> String {
+        let scrutinee = self.expr(value);
+        let ty = self.ty(&expr.ty, expr.span);
+        let result = (expr.ty != Ty::Never).then(|| self.temporary(&ty));
+        let end = self.label("end");
+
+        for arm in arms {
+            let next = self.label("arm");
+
+            self.pattern(&arm.pattern, &value.ty, &scrutinee, &next, value.span);
+
+            let arm_value = self.expr(&arm.value);
+
+            if let Some(result) = &result {
+                self.emit(format!("store {} {}, ptr {}", ty, arm_value, result));
+            }
+
+            self.terminate(format!("br label %{}", end));
+            self.start(&next);
+        }
+
+        // The arms cover every value
+        self.terminate("unreachable".to_string());
+        self.start(&end);
+
+        match result {
+            Some(result) => self.assign(format!("load {}, ptr {}", ty, result)),
+            None => {
+                self.terminate("unreachable".to_string());
+
+                "undef".to_string()
+            }
+        }
+    }
+
+    /// Go on if a condition is true, or jump to `fail`.
+    fn test(&mut self, condition: &str, fail: &str) {
+        let ok = self.label("ok");
+
+        self.terminate(format!(
+            "br i1 {}, label %{}, label %{}",
+            condition, ok, fail
+        ));
+        self.start(&ok);
+    }
+
+    /// Compile a pattern matched against a value of a type, binding what it
+    /// binds, and jumping to `fail` if it doesn't match.
+    fn pattern(&mut self, pattern: &Pattern, ty: &Ty, value: &str, fail: &str, span: Span) {
+        let checks = match self.layout.checks(pattern, ty) {
+            Ok(checks) => checks,
+            Err((enum_name, variant)) => {
+                self.unsupported(&format!("the variant '{}::{}'", enum_name, variant), span);
+
+                return;
+            }
+        };
+
+        for check in checks {
+            let part = self.part(value, ty, &check.path, span);
+
+            let condition = match check.kind {
+                CheckKind::Bind(local) => {
+                    let local_ty = self.local_ty(local, span);
+                    let pointer = self.local(local, span);
+
+                    self.emit(format!("store {} {}, ptr {}", local_ty, part, pointer));
+
+                    continue;
+                }
+                CheckKind::Bool(true) => part,
+                CheckKind::Bool(false) => self.assign(format!("xor i1 {}, true", part)),
+                CheckKind::Int(int) => {
+                    let llvm_ty = self.ty(&check.ty, span);
+
+                    self.assign(format!("icmp eq {} {}, {}", llvm_ty, part, int))
+                }
+                CheckKind::Str(string) => {
+                    let string = self.string(string);
+
+                    self.equal(&Ty::Str, &part, &string)
+                }
+                CheckKind::Some | CheckKind::None => {
+                    let aggregate = self.ty(&check.ty, span);
+                    let has = self.assign(format!("extractvalue {} {}, 0", aggregate, part));
+
+                    match check.kind {
+                        CheckKind::Some => has,
+                        _ => self.assign(format!("xor i1 {}, true", has)),
+                    }
+                }
+                CheckKind::Variant(position) => {
+                    let aggregate = self.ty(&check.ty, span);
+                    let tag = self.assign(format!("extractvalue {} {}, 0", aggregate, part));
+
+                    self.assign(format!("icmp eq i32 {}, {}", tag, position))
+                }
+            };
+
+            self.test(&condition, fail);
+        }
+    }
+
+    /// The part of a value of a type that a pattern checks, after taking
+    /// `path` into it.
+    fn part(&mut self, value: &str, ty: &Ty, path: &[backend::Step], span: Span) -> String {
+        let (mut value, mut ty) = (value.to_string(), ty.clone());
+
+        for step in path {
+            let aggregate = self.ty(&ty, span);
+            let position = step.position();
+
+            value = self.assign(format!(
+                "extractvalue {} {}, {}",
+                aggregate, value, position
+            ));
+            ty = self
+                .layout
+                .elements(&ty)
+                .get(position)
+                .cloned()
+                .unwrap_or(Ty::Unknown);
+        }
+
+        value
+    }
+}
+
+/// The name of a function of the program in the module. Names start with
+/// `pg.`, so they can't clash with the C library's.
+fn function_name(program: &hir::Program, position: usize) -> String {
+    format!("@\"pg.{}\"", program.functions[position].full_name())
+}
@@ -1,19 +1,57 @@
 #![allow(dead_code)]
-use clap::Parser;
-use lexer::Lexer;
-use std::path::PathBuf;
+use arithmetic::Overflow;
+use backend::Target;
+use clap::builder::BoolishValueParser;
+use clap::{ArgAction, Parser, ValueEnum};
+use consteval::Limits;
+use lexer::{Lexer, LexerOptions, Source, Span, Token};
+use lint::{Level, Levels, Lint, Warning};
+use module::LoadError;
+use parser::ParseResult;
+use source_map::{SourceFile, SourceMap};
+use std::io;
+use std::path::{Path, PathBuf};
 use std::time;
 
+mod arithmetic;
+mod asm;
+mod backend;
+mod bytecode;
+mod consteval;
+#[cfg(feature = "cranelift")]
+mod cranelift;
+mod hir;
+mod ir;
 mod lexer;
+mod lint;
+#[cfg(feature = "llvm")]
+mod llvm;
+mod module;
+mod ops;
+mod parser;
+mod resolve;
+#[cfg(feature = "resolve-cache")]
+mod resolve_cache;
+mod runtime;
+mod source_map;
+mod suggest;
+#[cfg(feature = "token-cache")]
+mod token_cache;
+mod typeck;
 
 #[derive(Parser, Debug)]
 #[command(name = "Penguin Compiler")]
 #[command(about = "A compiler for the Penguin programming language")]
 #[command(version)]
 struct Args {
-    /// The input file to compile
-    #[arg(short, long, value_name = "source.pg")]
-    file: PathBuf,
+    /// The input file to compile, or `-` to read from stdin
+    #[arg(
+        short,
+        long,
+        value_name = "source.pg",
+        required_unless_present = "emit"
+    )]
+    file: Option<PathBuf>,
 
     /// Print the tokens
     #[arg(long)]
@@ -22,37 +60,870 @@ struct Args {
     /// Time the compilation
     #[arg(long)]
     time: bool,
+
+    /// Accept keywords in any case, e.g. `LET` as `let`, for sources written
+    /// before keywords were case-sensitive
+    #[arg(long)]
+    legacy_case_insensitive_keywords: bool,
+
+    /// A directory to look for imported modules in, when they aren't next to
+    /// the file importing them. Directories are searched in the order they
+    /// are given, and then those in `PENGUIN_PATH`.
+    #[arg(short = 'I', long, value_name = "DIR")]
+    include: Vec<PathBuf>,
+
+    /// What to compile the input file to. Executables have to have a `main`
+    /// function to start at, which libraries don't need.
+    #[arg(long, value_enum, default_value_t = CrateType::Bin)]
+    crate_type: CrateType,
+
+    /// The most steps evaluating each constant can take, each expression
+    /// being one, so a constant that takes too long is an error
+    #[arg(long, value_name = "STEPS", default_value_t = Limits::default().steps)]
+    const_eval_limit: usize,
+
+    /// The most expressions evaluating a constant can go through inside of
+    /// each other, counting the constants it uses
+    #[arg(long, value_name = "DEPTH", default_value_t = Limits::default().depth)]
+    const_eval_depth: usize,
+
+    /// Stop the program when arithmetic doesn't fit its type. Turned off, it
+    /// wraps around instead, in constants too. Dividing by zero stops the
+    /// program either way.
+    #[arg(
+        long,
+        value_name = "on|off",
+        default_value = "on",
+        value_parser = BoolishValueParser::new(),
+        action = ArgAction::Set
+    )]
+    overflow_checks: bool,
+
+    /// Emit information about the compiler instead of compiling
+    #[arg(long)]
+    emit: Option<Emit>,
+
+    /// Run the program with the bytecode virtual machine once it compiles,
+    /// exiting with what `main` returns
+    #[arg(long)]
+    run: bool,
+
+    /// Run the program with `--run` by compiling it into memory with
+    /// Cranelift, instead of with the virtual machine
+    #[cfg(feature = "cranelift")]
+    #[arg(long, requires = "run")]
+    jit: bool,
+
+    /// The backend compiling object files with `--emit obj`. LLVM is used
+    /// if the compiler was built with it, then Cranelift, then the assembler
+    #[arg(long, value_enum)]
+    backend: Option<BackendName>,
+
+    /// The machine and operating system to compile native code for, e.g.
+    /// `aarch64-apple-darwin`. The machine the compiler runs on by default
+    #[arg(long, value_name = "TRIPLE", value_parser = target_triple)]
+    target: Option<Target>,
+
+    /// Don't report what a lint finds, or what any lint that warns by
+    /// default finds with `warnings`
+    #[arg(short = 'A', long, value_name = "LINT", value_parser = lint_name)]
+    allow: Vec<LintName>,
+
+    /// Report what a lint finds as warnings, or what any lint that warns by
+    /// default finds with `warnings`
+    #[arg(short = 'W', long, value_name = "LINT", value_parser = lint_name)]
+    warn: Vec<LintName>,
+
+    /// Report what a lint finds as errors, so the program doesn't compile,
+    /// or what any lint that warns by default finds with `warnings`
+    #[arg(short = 'D', long, value_name = "LINT", value_parser = lint_name)]
+    deny: Vec<LintName>,
+
+    /// Memory-map the input file instead of reading it into memory
+    #[cfg(feature = "mmap")]
+    #[arg(long)]
+    mmap: bool,
+
+    /// The directory to cache the name resolution of modules in, so modules
+    /// that haven't changed aren't resolved again
+    #[cfg(feature = "resolve-cache")]
+    #[arg(long, value_name = "DIR", default_value = resolve_cache::DIRECTORY)]
+    cache_dir: PathBuf,
+
+    /// Resolve every module, without reading or writing caches
+    #[cfg(feature = "resolve-cache")]
+    #[arg(long)]
+    no_cache: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BackendName {
+    /// LLVM, which makes the fastest code but needs `llc` installed
+    #[cfg(feature = "llvm")]
+    Llvm,
+    /// Cranelift, which is built into the compiler and compiles quickly
+    #[cfg(feature = "cranelift")]
+    Cranelift,
+    /// Assembly written by the compiler and assembled with `as`, which only
+    /// compiles integers, functions and control flow
+    Asm,
+}
+
+impl BackendName {
+    #[cfg(feature = "llvm")]
+    const DEFAULT: Self = Self::Llvm;
+    #[cfg(all(not(feature = "llvm"), feature = "cranelift"))]
+    const DEFAULT: Self = Self::Cranelift;
+    #[cfg(not(any(feature = "llvm", feature = "cranelift")))]
+    const DEFAULT: Self = Self::Asm;
+
+    fn backend(self) -> Box<dyn backend::Backend> {
+        match self {
+            #[cfg(feature = "llvm")]
+            Self::Llvm => Box::new(llvm::Llvm),
+            #[cfg(feature = "cranelift")]
+            Self::Cranelift => Box::new(cranelift::Cranelift),
+            Self::Asm => Box::new(asm::Asm),
+        }
+    }
+}
+
+impl Args {
+    /// The options to lex the input with.
+    fn lexer_options(&self) -> LexerOptions {
+        LexerOptions::new().case_insensitive_keywords(self.legacy_case_insensitive_keywords)
+    }
+
+    /// The limits on evaluating constants.
+    fn const_limits(&self) -> Limits {
+        Limits {
+            steps: self.const_eval_limit,
+            depth: self.const_eval_depth,
+        }
+    }
+
+    /// What happens to arithmetic that doesn't fit its type.
+    fn overflow(&self) -> Overflow {
+        match self.overflow_checks {
+            true => Overflow::Checked,
+            false => Overflow::Wrapping,
+        }
+    }
+
+    /// The directories to look for modules in, those given with `--include`
+    /// first.
+    fn include_paths(&self) -> Vec<PathBuf> {
+        let mut paths = self.include.clone();
+
+        paths.extend(module::penguin_path());
+
+        paths
+    }
+
+    /// The level of each lint. A lint named on its own overrides
+    /// `warnings`, and a lint given several levels takes the strictest.
+    fn lint_levels(&self) -> Levels {
+        let mut levels = Levels::new();
+
+        let flags = [
+            (&self.allow, Level::Allow),
+            (&self.warn, Level::Warn),
+            (&self.deny, Level::Deny),
+        ];
+
+        for (names, level) in flags {
+            if names.contains(&LintName::Warnings) {
+                levels.set_warnings(level);
+            }
+        }
+
+        for (names, level) in flags {
+            for name in names {
+                if let LintName::Lint(lint) = name {
+                    levels.set(*lint, level);
+                }
+            }
+        }
+
+        levels
+    }
+}
+
+/// A lint given on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LintName {
+    /// `warnings`, standing for every lint
+    Warnings,
+    Lint(Lint),
+}
+
+fn lint_name(name: &str) -> Result<LintName, String> {
+    if name == "warnings" {
+        return Ok(LintName::Warnings);
+    }
+
+    Lint::from_name(name).map(LintName::Lint).ok_or_else(|| {
+        let names: Vec<_> = Lint::ALL.iter().map(|lint| lint.name()).collect();
+
+        format!(
+            "there is no lint '{}', the lints are: warnings, {}",
+            name,
+            names.join(", ")
+        )
+    })
+}
+
+fn target_triple(triple: &str) -> Result<Target, String> {
+    Target::parse(triple).ok_or_else(|| {
+        let triples: Vec<_> = Target::ALL.iter().map(|target| target.triple()).collect();
+
+        format!(
+            "there is no target '{}', the targets are: {}",
+            triple,
+            triples.join(", ")
+        )
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum CrateType {
+    /// An executable, which starts at its `main` function
+    Bin,
+    /// A library, which other programs import
+    Lib,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// Every kind of token the lexer produces, as JSON
+    TokenSpec,
+    /// The parsed input file, as an indented tree
+    Ast,
+    /// The parsed input file, as JSON
+    #[cfg(feature = "ast-json")]
+    AstJson,
+    /// The input file lowered to HIR, with the type of every node, as an
+    /// indented tree
+    Hir,
+    /// The input file lowered to SSA IR, with basic blocks and phi nodes,
+    /// once it is verified
+    Ir,
+    /// The input file compiled to bytecode, one instruction per line
+    Bytecode,
+    /// The input file compiled to LLVM IR
+    #[cfg(feature = "llvm")]
+    LlvmIr,
+    /// The input file compiled to Cranelift IR
+    #[cfg(feature = "cranelift")]
+    Clif,
+    /// The input file compiled to assembly for the target chosen by
+    /// `--target`
+    Asm,
+    /// Compile the input file to a native object file next to it, with the
+    /// backend chosen by `--backend`
+    Obj,
+    /// Cache the tokens of the input file next to it, so unchanged files
+    /// don't need to be lexed again
+    #[cfg(feature = "token-cache")]
+    TokensBin,
 }
 
 fn main() {
     let args = Args::parse();
-    let file = args.file;
 
-    let mut lexer = Lexer::new(file);
+    if args.emit == Some(Emit::TokenSpec) {
+        println!("{}", lexer::token_spec());
+
+        return;
+    }
+
+    // Clap makes sure there is a file when nothing is being emitted, but some
+    // of the things that can be emitted need one too
+    let Some(file) = &args.file else {
+        eprintln!("[ERROR]: No input file was given.");
+
+        std::process::exit(1);
+    };
+
+    if args.emit == Some(Emit::Obj) && file.as_os_str() == "-" {
+        eprintln!("[ERROR]: Object files can only be written for files, not stdin.");
+
+        std::process::exit(1);
+    }
+
+    #[cfg(feature = "token-cache")]
+    if args.emit == Some(Emit::TokensBin) && file.as_os_str() == "-" {
+        eprintln!("[ERROR]: Tokens can only be cached for files, not stdin.");
+
+        std::process::exit(1);
+    }
+
+    #[cfg(feature = "mmap")]
+    if args.mmap {
+        let source = lexer::MappedSource::from_path(file);
+
+        // The mapped file isn't in a source map, so it can't import modules
+        let parsed =
+            source.and_then(|source| source.lexer().map(|lexer| compile(lexer, None, &args)));
+
+        match parsed {
+            Ok(Some(parsed)) => finish(&parsed, Vec::new(), None, &args),
+            Ok(None) => {}
+            Err(error) => {
+                eprintln!("[IO ERROR]: Could not read '{}': {}", file.display(), error);
+
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    let source = if file.as_os_str() == "-" {
+        Source::from_reader(io::stdin().lock(), "stdin")
+    } else {
+        Source::from_path(file)
+    };
+
+    let mut sources = SourceMap::new();
+
+    let id = match source {
+        Ok(source) => sources.add(source),
+        Err(error) => {
+            eprintln!("[IO ERROR]: Could not read '{}': {}", file.display(), error);
+
+            std::process::exit(1);
+        }
+    };
+
+    let Some(source) = sources.get(id) else {
+        return;
+    };
+
+    #[cfg(feature = "token-cache")]
+    if file.as_os_str() != "-" {
+        let cache = token_cache::path_for(file);
+
+        if args.emit == Some(Emit::TokensBin) {
+            emit_token_cache(source, &cache, &args.lexer_options());
+
+            return;
+        }
+
+        if let Some(tokens) = read_token_cache(source, &cache, &args.lexer_options()) {
+            if let Some(parsed) = parse(&tokens, Some(&sources), &args) {
+                check(parsed, file, &mut sources, &args);
+            }
+
+            return;
+        }
+    }
+
+    if let Some(parsed) = compile(source.lexer(), Some(&sources), &args) {
+        check(parsed, file, &mut sources, &args);
+    }
+}
+
+/// Load the modules a program parsed from `file` imports, adding them to
+/// `sources`, and finish compiling it along with them. A program read from
+/// stdin has no directory to find modules in, so it is compiled alone.
+fn check(parsed: ParseResult, file: &Path, sources: &mut SourceMap, args: &Args) {
+    if file.as_os_str() == "-" {
+        finish(&parsed, Vec::new(), Some(sources), args);
+
+        return;
+    }
+
+    let include = args.include_paths();
+    let options = args.lexer_options();
+
+    #[cfg(feature = "resolve-cache")]
+    let loaded = if args.no_cache {
+        module::load(parsed, file, &include, sources, &options)
+    } else {
+        module::load_cached(parsed, file, &include, sources, &options, &args.cache_dir)
+    };
+
+    #[cfg(not(feature = "resolve-cache"))]
+    let loaded = module::load(parsed, file, &include, sources, &options);
+
+    match loaded {
+        Ok(loaded) => finish(&loaded.parsed, loaded.warnings, Some(sources), args),
+        Err(errors) => {
+            for error in &errors {
+                let label = match error {
+                    LoadError::Module(_) => "MODULE ERROR",
+                    LoadError::Lexer(_) => "LEXER ERROR",
+                    LoadError::Parser(_) => "PARSER ERROR",
+                    LoadError::Resolve(_) => "RESOLVE ERROR",
+                };
+
+                // Lexer errors already say where they are
+                if let LoadError::Lexer(error) = error {
+                    eprintln!("[{}]: {}", label, error);
+                    print_located(sources, error.span());
+
+                    continue;
+                }
+
+                report_errors(
+                    label,
+                    std::slice::from_ref(error),
+                    |error| (error.span(), error.declared()),
+                    Some(sources),
+                );
+            }
+
+            std::process::exit(1);
+        }
+    }
+}
 
+/// Run the lexer over the source, and parse the tokens if it succeeds,
+/// reporting the results. Errors are shown in the source file when it is in
+/// a source map.
+fn compile(lexer: Lexer, sources: Option<&SourceMap>, args: &Args) -> Option<ParseResult> {
+    let mut lexer = lexer.with_options(args.lexer_options());
     let start = time::Instant::now();
     let tokens = lexer.lex();
     let end = start.elapsed();
 
     match tokens {
         Ok(tokens) => {
-            if args.tokens {
-                println!("Tokens: {:#?}", tokens);
-            }
-
             if args.time {
-                let chars_per_second = ((lexer.loc.index as f64) / (end.as_secs_f64())) as usize;
+                let bytes_per_second = ((lexer.loc.index as f64) / (end.as_secs_f64())) as usize;
 
                 println!(
-                    "Lexing took: {:?}\n\t -> or {} chars per second.",
-                    end, chars_per_second
+                    "Lexing took: {:?}\n\t -> or {} bytes per second.",
+                    end, bytes_per_second
+                );
+            }
+
+            parse(&tokens, sources, args)
+        }
+        Err(error) => {
+            eprintln!("[LEXER ERROR]: {}", error);
+
+            if let Some(sources) = sources {
+                print_located(sources, error.span());
+            }
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse the tokens of a source that was lexed successfully, reporting any
+/// errors. Emitting the AST of the source is done here, so nothing is
+/// returned to compile further then.
+fn parse(tokens: &[Token], sources: Option<&SourceMap>, args: &Args) -> Option<ParseResult> {
+    if args.tokens {
+        println!("Tokens: {:#?}", tokens);
+    }
+
+    let parsed = match parser::Parser::parse(tokens) {
+        Ok(parsed) => parsed,
+        Err(errors) => fail(
+            "PARSER ERROR",
+            &errors,
+            |error| (error.span(), None),
+            sources,
+        ),
+    };
+
+    if args.emit == Some(Emit::Ast) {
+        print!("{}", parser::printer::print_tree(&parsed));
+
+        return None;
+    }
+
+    #[cfg(feature = "ast-json")]
+    if args.emit == Some(Emit::AstJson) {
+        match serde_json::to_string_pretty(&parsed) {
+            Ok(json) => println!("{}", json),
+            Err(error) => {
+                eprintln!("[ERROR]: Could not write the AST as JSON: {}", error);
+
+                std::process::exit(1);
+            }
+        }
+
+        return None;
+    }
+
+    Some(parsed)
+}
+
+/// Resolve, check and lower a parsed program, along with the modules it
+/// imports, reporting the results. `lints` is what the lints found while the
+/// modules were loaded. Errors are shown in the files they are in when there
+/// are any.
+fn finish(parsed: &ParseResult, mut lints: Vec<Warning>, sources: Option<&SourceMap>, args: &Args) {
+    let resolution = match resolve::Resolver::resolve(parsed) {
+        Ok(resolution) => resolution,
+        Err(errors) => fail(
+            "RESOLVE ERROR",
+            &errors,
+            |error| (error.span(), error.declared()),
+            sources,
+        ),
+    };
+
+    let types = match typeck::TypeChecker::check(parsed, &resolution) {
+        Ok(types) => types,
+        Err(errors) => fail(
+            "TYPE ERROR",
+            &errors,
+            |error| (error.span(), error.declared()),
+            sources,
+        ),
+    };
+
+    if args.crate_type == CrateType::Bin {
+        if let Err(errors) = typeck::check_entry_point(parsed, &types) {
+            fail(
+                "TYPE ERROR",
+                &errors,
+                |error| (error.span(), error.declared()),
+                sources,
+            );
+        }
+    }
+
+    report_errors(
+        "WARNING",
+        &types.warnings,
+        |warning| (warning.span(), warning.declared()),
+        sources,
+    );
+
+    lints.extend(lint::check(parsed, &resolution, Some(&types)));
+
+    let mut levels = args.lint_levels();
+
+    levels.set_from_attributes(parsed);
+
+    let (warnings, denied) = levels.split(lints);
+
+    report_errors(
+        "WARNING",
+        &warnings,
+        |warning| (warning.span(), warning.declared()),
+        sources,
+    );
+
+    // Denied lints fail like any other error, so they can fail a build
+    if !denied.is_empty() {
+        fail(
+            "LINT ERROR",
+            &denied,
+            |error| (error.span(), error.declared()),
+            sources,
+        );
+    }
+
+    let consts = match consteval::evaluate_with(
+        parsed,
+        &resolution,
+        &types,
+        args.const_limits(),
+        args.overflow(),
+    ) {
+        Ok(consts) => consts,
+        Err(errors) => fail(
+            "CONST ERROR",
+            &errors,
+            |error| (error.span(), error.declared()),
+            sources,
+        ),
+    };
+
+    let target = args.target.unwrap_or_else(Target::host);
+
+    if args.emit == Some(Emit::Hir) {
+        let program = hir::lower(parsed, &resolution, &types, &consts);
+
+        print!("{}", hir::printer::print_tree(&program));
+
+        return;
+    }
+
+    if args.emit == Some(Emit::Ir) {
+        return emit_ir(&hir::lower(parsed, &resolution, &types, &consts), sources);
+    }
+
+    #[cfg(feature = "llvm")]
+    if args.emit == Some(Emit::LlvmIr) {
+        let program = hir::lower(parsed, &resolution, &types, &consts);
+
+        return print_codegen(llvm::compile(&program, sources), sources);
+    }
+
+    #[cfg(feature = "cranelift")]
+    if args.emit == Some(Emit::Clif) {
+        let program = hir::lower(parsed, &resolution, &types, &consts);
+
+        return print_codegen(cranelift::clif(&program, sources, target), sources);
+    }
+
+    if args.emit == Some(Emit::Asm) {
+        let program = hir::lower(parsed, &resolution, &types, &consts);
+
+        return print_codegen(asm::compile(&program, sources, target), sources);
+    }
+
+    if args.emit == Some(Emit::Obj) {
+        return emit_object(
+            &hir::lower(parsed, &resolution, &types, &consts),
+            sources,
+            target,
+            args,
+        );
+    }
+
+    #[cfg(feature = "cranelift")]
+    if args.run && args.jit {
+        let program = hir::lower(parsed, &resolution, &types, &consts);
+
+        match cranelift::jit::run(&program, sources, &mut io::stdout()) {
+            Ok(exit) => std::process::exit(exit.code()),
+            Err(errors) => fail(
+                "CODEGEN ERROR",
+                &errors,
+                |error| (error.span(), error.declared()),
+                sources,
+            ),
+        }
+    }
+
+    if args.emit == Some(Emit::Bytecode) || args.run {
+        let program = bytecode::compile(&hir::lower(parsed, &resolution, &types, &consts));
+
+        if args.emit == Some(Emit::Bytecode) {
+            print!("{}", bytecode::disassemble(&program));
+
+            return;
+        }
+
+        match bytecode::run(&program, &mut io::stdout()) {
+            Ok(bytecode::Value::Int(code)) => std::process::exit(code as i32),
+            Ok(_) => return,
+            Err(error) => {
+                report_errors(
+                    "RUNTIME ERROR",
+                    &[error],
+                    |error| (error.span(), error.declared()),
+                    sources,
                 );
+
+                std::process::exit(1);
             }
+        }
+    }
+
+    println!("[✔] Sucessfully compiled.");
+}
 
-            println!("[✔] Sucessfully compiled.");
+/// Print the errors from a phase of compiling a program to stderr, labelled
+/// with `label`, showing where they are when the program's files are in a
+/// source map. `spans` gives the code that caused an error, and the declaration it
+/// is about if there is one, which can be in different files.
+fn report_errors<E: std::fmt::Display>(
+    label: &str,
+    errors: &[E],
+    spans: impl Fn(&E) -> (Span, Option<Span>),
+    sources: Option<&SourceMap>,
+) {
+    for error in errors {
+        let (span, declared) = spans(error);
+
+        let Some((source, span)) = sources.and_then(|sources| sources.locate(span)) else {
+            eprintln!("[{}]: {}", label, error);
+
+            continue;
+        };
+
+        let (line, column) = source.line_column(span.start);
+
+        eprintln!(
+            "[{}]: [{}:{}:{}] {}",
+            label,
+            source.name(),
+            line,
+            column,
+            error
+        );
+        print_snippet(source, span);
+
+        let declared = declared.and_then(|span| sources?.locate(span));
+
+        if let Some((source, declared)) = declared {
+            let (line, column) = source.line_column(declared.start);
+
+            eprintln!(
+                "[NOTE]: [{}:{}:{}] Declared here",
+                source.name(),
+                line,
+                column
+            );
+            print_snippet(source, declared);
         }
+    }
+}
+
+/// Print the errors that stopped a program from compiling, like
+/// `report_errors`, and exit with a failure.
+fn fail<E: std::fmt::Display>(
+    label: &str,
+    errors: &[E],
+    spans: impl Fn(&E) -> (Span, Option<Span>),
+    sources: Option<&SourceMap>,
+) -> ! {
+    report_errors(label, errors, spans, sources);
+
+    std::process::exit(1);
+}
+
+/// Print the IR a backend compiled a program to, or why it couldn't.
+fn print_codegen(ir: Result<String, Vec<backend::CodegenError>>, sources: Option<&SourceMap>) {
+    match ir {
+        Ok(ir) => print!("{}", ir),
+        Err(errors) => fail(
+            "CODEGEN ERROR",
+            &errors,
+            |error| (error.span(), error.declared()),
+            sources,
+        ),
+    }
+}
+
+/// Lower a program to SSA IR and print it, if it can be lowered and
+/// verified. The IR not being valid is a bug in the lowering.
+fn emit_ir(program: &hir::Program, sources: Option<&SourceMap>) {
+    let program = match ir::lower(program) {
+        Ok(program) => program,
+        Err(errors) => fail(
+            "CODEGEN ERROR",
+            &errors,
+            |error| (error.span(), error.declared()),
+            sources,
+        ),
+    };
+
+    if let Err(errors) = ir::verify(&program) {
+        for error in errors {
+            eprintln!("[IR ERROR]: {}", error);
+        }
+
+        std::process::exit(1);
+    }
+
+    print!("{}", ir::print(&program));
+}
+
+/// Compile a program to an object file next to the input file, with the
+/// backend chosen on the command line.
+fn emit_object(program: &hir::Program, sources: Option<&SourceMap>, target: Target, args: &Args) {
+    let Some(file) = &args.file else {
+        return;
+    };
+    let object = file.with_extension("o");
+    let backend = args.backend.unwrap_or(BackendName::DEFAULT).backend();
+
+    match backend.write_object(program, sources, target, &object) {
+        Ok(()) => println!("[✔] Wrote '{}' with {}.", object.display(), backend.name()),
+        Err(errors) => fail(
+            "CODEGEN ERROR",
+            &errors,
+            |error| (error.span(), error.declared()),
+            sources,
+        ),
+    }
+}
+
+/// Print the code a span from any file in the map covers.
+fn print_located(sources: &SourceMap, span: Span) {
+    if let Some((file, span)) = sources.locate(span) {
+        print_snippet(file, span);
+    }
+}
+
+#[cfg(feature = "token-cache")]
+/// Lex the source and write its tokens to the cache.
+fn emit_token_cache(source: &SourceFile, cache: &std::path::Path, options: &LexerOptions) {
+    let tokens = match source.lexer().with_options(options.clone()).lex() {
+        Ok(tokens) => tokens,
         Err(error) => {
-            println!("[LEXER ERROR]: {}", error);
+            eprintln!("[LEXER ERROR]: {}", error);
+            print_snippet(source, error.span());
+
+            std::process::exit(1);
         }
+    };
+
+    let written = std::fs::File::create(cache)
+        .map_err(Into::into)
+        .and_then(|file| {
+            token_cache::write(
+                source.contents(),
+                options,
+                &tokens,
+                io::BufWriter::new(file),
+            )
+        });
+
+    match written {
+        Ok(()) => println!(
+            "[✔] Cached {} tokens in '{}'.",
+            tokens.len(),
+            cache.display()
+        ),
+        Err(error) => {
+            eprintln!(
+                "[IO ERROR]: Could not write '{}': {}",
+                cache.display(),
+                error
+            );
+
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "token-cache")]
+/// Read the tokens of the source from its cache, if there is one and it was
+/// made from the same source code.
+fn read_token_cache<'source>(
+    source: &'source SourceFile,
+    cache: &std::path::Path,
+    options: &LexerOptions,
+) -> Option<Vec<Token<'source>>> {
+    let file = std::fs::File::open(cache).ok()?;
+
+    // A cache that can't be read is no worse than not having one
+    token_cache::read(source.contents(), options, io::BufReader::new(file))
+        .ok()
+        .flatten()
+}
+
+/// Print the line an error happened on to stderr, pointing at the code that
+/// caused it.
+fn print_snippet(file: &SourceFile, span: Span) {
+    let (line, column) = file.line_column(span.start);
+
+    if let Some(text) = file.line(line) {
+        let gutter = line.to_string();
+        let width = file
+            .slice(span)
+            .map_or(1, |code| code.lines().next().unwrap_or("").chars().count())
+            .max(1);
+
+        eprintln!("{} | {}", gutter, text);
+        eprintln!(
+            "{} | {}{}",
+            " ".repeat(gutter.len()),
+            " ".repeat(column - 1),
+            "^".repeat(width)
+        );
     }
 }
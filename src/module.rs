@@ -0,0 +1,1090 @@
+//! Loading the modules a program imports from other files, and merging their
+//! items into it.
+//!
+//! `import utils;` loads `utils.pg` from the directory of the importing file,
+//! or `utils/mod.pg` when `utils` is a directory, and `import a::b;` loads
+//! `a/b.pg` or `a/b/mod.pg`. `import "lib/math.pg";` loads the file at that
+//! path, relative to the importing file. Imports starting with `std`, and
+//! strings without the `.pg` extension like `"std.print"`, are left to the
+//! standard library.
+//!
+//! A module that isn't next to the importing file is looked for in the
+//! include directories, in order, which is how libraries are shared between
+//! projects. They are given with `--include`, and then by the
+//! `PENGUIN_PATH` environment variable.
+//!
+//! Each module is loaded once, however many files import it, and is named by
+//! its path from the directory of the file being compiled, or from the
+//! include directory it is in, e.g. `utils` for `utils.pg` or
+//! `utils/mod.pg`, and `utils::strings` for `utils/strings.pg`. Its items are renamed to their qualified names, e.g.
+//! `helper` in `utils.pg` becomes `utils::helper`, along with everything that
+//! uses them. Uses from other modules, like `utils::helper(1)` or a name
+//! imported with `import utils::{helper};`, are renamed too, and have to be
+//! of items declared `pub`. The merged program can then be resolved and
+//! checked as if it was one file.
+//!
+//! Modules can import each other in a cycle, since each is only loaded once
+//! and items can be used before they are declared. The `import_cycles` lint
+//! reports each cycle, with the files in it, and can be denied to reject
+//! them.
+//!
+//! With the `resolve-cache` feature, the resolution of each module can be
+//! cached, so modules that haven't changed aren't resolved again.
+use crate::lexer::{LexerError, LexerOptions, Source, Span};
+use crate::lint::{Warning, WarningKind};
+use crate::parser::ast::{
+    Arena, Expr, ExprId, ExprKind, Function, Import, ImportPath, Item, ItemKind, Length, Let,
+    Pattern, Program, Struct, Type, TypeParam,
+};
+use crate::parser::visit::{self, VisitMut};
+use crate::parser::{ParseError, ParseResult, Parser};
+use crate::resolve::{Resolution, ResolveError, Resolver, SymbolKind};
+#[cfg(feature = "resolve-cache")]
+use crate::resolve_cache::{self, Origin};
+use crate::source_map::SourceMap;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+#[cfg(feature = "resolve-cache")]
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// The environment variable listing include directories, separated like
+/// `PATH` is on the platform.
+pub const PENGUIN_PATH: &str = "PENGUIN_PATH";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModuleErrorKind {
+    /// An import names a module that has no file.
+    NotFound {
+        module: String,
+        /// Every file the module could have been in, in the order they were
+        /// looked for
+        searched: Vec<PathBuf>,
+    },
+    /// The file of a module was found, but couldn't be read.
+    Unreadable { path: PathBuf, message: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An error that occurred while finding the file of a module.
+pub struct ModuleError {
+    pub kind: ModuleErrorKind,
+    /// The import of the module
+    span: Span,
+}
+
+impl ModuleError {
+    pub fn new(kind: ModuleErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// A stable code identifying the kind of error, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            ModuleErrorKind::NotFound { .. } => "E0601",
+            ModuleErrorKind::Unreadable { .. } => "E0602",
+        }
+    }
+
+    /// The import that caused the error.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ModuleErrorKind::NotFound { module, .. } => {
+                format!("Can't find the module '{}'", module)
+            }
+            ModuleErrorKind::Unreadable { path, message } => {
+                format!("Can't read '{}': {}", path.display(), message)
+            }
+        }
+    }
+
+    /// What might fix the error, if there is a likely fix.
+    pub fn help(&self) -> Option<String> {
+        match &self.kind {
+            ModuleErrorKind::NotFound { searched, .. } => {
+                let searched: Vec<_> = searched
+                    .iter()
+                    .map(|path| format!("'{}'", path.display()))
+                    .collect();
+
+                Some(format!(
+                    "It was looked for at {}. Add the directory it is in with '--include' or to {}",
+                    searched.join(", "),
+                    PENGUIN_PATH
+                ))
+            }
+            ModuleErrorKind::Unreadable { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.", self.message())?;
+
+        if let Some(help) = self.help() {
+            write!(f, " {}.", help)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ModuleError {}
+
+#[derive(Debug)]
+/// Something that stopped the modules of a program from being loaded. Every
+/// span is where the code is in the `SourceMap` the modules were added to.
+pub enum LoadError {
+    Module(ModuleError),
+    /// A module couldn't be lexed
+    Lexer(LexerError),
+    /// A module couldn't be parsed
+    Parser(ParseError),
+    /// A name in a module couldn't be resolved, or one from another module
+    /// isn't `pub`
+    Resolve(ResolveError),
+}
+
+impl LoadError {
+    /// The code that caused the error.
+    pub fn span(&self) -> Span {
+        match self {
+            LoadError::Module(error) => error.span(),
+            LoadError::Lexer(error) => error.span(),
+            LoadError::Parser(error) => error.span(),
+            LoadError::Resolve(error) => error.span(),
+        }
+    }
+
+    /// The declaration the error is about, if there is one.
+    pub fn declared(&self) -> Option<Span> {
+        match self {
+            LoadError::Resolve(error) => error.declared(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Module(error) => write!(f, "{}", error),
+            LoadError::Lexer(error) => write!(f, "{}", error),
+            LoadError::Parser(error) => write!(f, "{}", error),
+            LoadError::Resolve(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+#[derive(Debug)]
+/// A program merged with the modules it imports.
+pub struct LoadResult {
+    pub parsed: ParseResult,
+    /// What the lints about modules found, e.g. import cycles
+    pub warnings: Vec<Warning>,
+    /// How many modules had their resolution read from a cache
+    #[cfg(feature = "resolve-cache")]
+    pub cached: usize,
+}
+
+/// A file of the program, and what it imports.
+struct Module {
+    /// The qualified name of the module, which its items are prefixed with,
+    /// or an empty string for the file being compiled
+    prefix: String,
+    /// What to call the module in errors
+    name: String,
+    /// The file of the module, the way errors show it
+    file: String,
+    path: PathBuf,
+    program: Program,
+    resolution: Resolution,
+    /// The module each import that loaded one loads, by the position of the
+    /// import in the items
+    imports: HashMap<usize, usize>,
+    /// Where the module starts in the source map and arena
+    #[cfg(feature = "resolve-cache")]
+    origin: Origin,
+    /// The number of expressions in the module
+    #[cfg(feature = "resolve-cache")]
+    exprs: usize,
+}
+
+/// The include directories listed by `PENGUIN_PATH`, or none if it isn't
+/// set.
+pub fn penguin_path() -> Vec<PathBuf> {
+    env::var_os(PENGUIN_PATH)
+        .map(|paths| {
+            env::split_paths(&paths)
+                .filter(|path| !path.as_os_str().is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Load every module the program imports, directly or through other
+/// modules, adding their files to `sources`, and merge them into the
+/// program. `path` is the file the program was parsed from, and the first
+/// file in `sources`, so spans in the program are already where they are in
+/// the map. Modules that aren't next to the files importing them are looked
+/// for in the `include` directories, in order.
+///
+/// A program that doesn't import any modules is returned as it is.
+pub fn load(
+    parsed: ParseResult,
+    path: &Path,
+    include: &[PathBuf],
+    sources: &mut SourceMap,
+    options: &LexerOptions,
+) -> Result<LoadResult, Vec<LoadError>> {
+    Loader::new(parsed, path, include, sources, options).load()
+}
+
+#[cfg(feature = "resolve-cache")]
+/// Load the modules a program imports like `load` does, reading the
+/// resolution of modules that haven't changed from the caches in the `cache`
+/// directory, and caching the others there.
+pub fn load_cached(
+    parsed: ParseResult,
+    path: &Path,
+    include: &[PathBuf],
+    sources: &mut SourceMap,
+    options: &LexerOptions,
+    cache: &Path,
+) -> Result<LoadResult, Vec<LoadError>> {
+    let mut loader = Loader::new(parsed, path, include, sources, options);
+
+    loader.cache = Some(cache.to_path_buf());
+    loader.load()
+}
+
+struct Loader<'a> {
+    sources: &'a mut SourceMap,
+    options: &'a LexerOptions,
+    /// The directory of the file being compiled, which modules are named by
+    /// their path from
+    root: PathBuf,
+    /// The directories modules are looked for in when they aren't next to
+    /// the file importing them
+    include: Vec<PathBuf>,
+    modules: Vec<Module>,
+    /// The expressions of every module
+    arena: Arena,
+    errors: Vec<LoadError>,
+    /// The directory the resolution of modules is cached in, if it is
+    #[cfg(feature = "resolve-cache")]
+    cache: Option<PathBuf>,
+    /// How many modules had their resolution read from a cache
+    #[cfg(feature = "resolve-cache")]
+    cached: usize,
+}
+
+impl<'a> Loader<'a> {
+    /// A loader for the modules of a program parsed from the file at `path`.
+    fn new(
+        parsed: ParseResult,
+        path: &Path,
+        include: &[PathBuf],
+        sources: &'a mut SourceMap,
+        options: &'a LexerOptions,
+    ) -> Self {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let include = include
+            .iter()
+            .map(|directory| fs::canonicalize(directory).unwrap_or_else(|_| directory.clone()))
+            .collect();
+        let name = path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        let file = path
+            .file_name()
+            .map_or_else(String::new, |file| file.to_string_lossy().into_owned());
+
+        Loader {
+            sources,
+            options,
+            root,
+            include,
+            modules: vec![Module {
+                prefix: String::new(),
+                name,
+                file,
+                path,
+                program: parsed.program,
+                resolution: Resolution::default(),
+                imports: HashMap::new(),
+                #[cfg(feature = "resolve-cache")]
+                origin: Origin::default(),
+                #[cfg(feature = "resolve-cache")]
+                exprs: parsed.arena.len(),
+            }],
+            arena: parsed.arena,
+            errors: Vec::new(),
+            #[cfg(feature = "resolve-cache")]
+            cache: None,
+            #[cfg(feature = "resolve-cache")]
+            cached: 0,
+        }
+    }
+
+    /// Load the modules of the program and merge them into it.
+    fn load(mut self) -> Result<LoadResult, Vec<LoadError>> {
+        // Modules are added as they are found, so this reaches the modules
+        // they import too
+        let mut next = 0;
+
+        while next < self.modules.len() {
+            self.load_imports(next);
+            next += 1;
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        if self.modules.len() == 1 && self.modules[0].imports.is_empty() {
+            let root = self.modules.remove(0);
+
+            return Ok(LoadResult {
+                parsed: ParseResult {
+                    program: root.program,
+                    arena: self.arena,
+                },
+                warnings: Vec::new(),
+                #[cfg(feature = "resolve-cache")]
+                cached: 0,
+            });
+        }
+
+        let warnings = self.cycles();
+
+        for module in 0..self.modules.len() {
+            self.resolve(module);
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        for module in 0..self.modules.len() {
+            self.qualify(module);
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        Ok(LoadResult {
+            #[cfg(feature = "resolve-cache")]
+            cached: self.cached,
+            parsed: self.merge(),
+            warnings,
+        })
+    }
+
+    /// Load the modules imported by a module that aren't loaded yet.
+    fn load_imports(&mut self, module: usize) {
+        let directory = self.modules[module]
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let imports: Vec<_> = self.modules[module]
+            .program
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(position, item)| match &item.kind {
+                ItemKind::Import(import) => Some((position, import.path.clone(), item.span)),
+                _ => None,
+            })
+            .collect();
+
+        // The directory of the importing file is searched first
+        let directories: Vec<_> = std::iter::once(&directory)
+            .chain(&self.include)
+            .cloned()
+            .collect();
+
+        for (position, path, span) in imports {
+            let (name, candidates): (_, Vec<_>) = match &path {
+                ImportPath::Module(path) if path.first().is_some_and(|name| name == "std") => {
+                    continue
+                }
+                ImportPath::Module(path) => {
+                    let files = directories.iter().flat_map(|directory| {
+                        let file = path
+                            .iter()
+                            .fold(directory.clone(), |file, name| file.join(name));
+
+                        [file.with_extension("pg"), file.join("mod.pg")]
+                    });
+
+                    (path.join("::"), files.collect())
+                }
+                ImportPath::File(file) if file.ends_with(".pg") => (
+                    file.clone(),
+                    directories
+                        .iter()
+                        .map(|directory| directory.join(file))
+                        .collect(),
+                ),
+                ImportPath::File(_) => continue,
+            };
+
+            let Some(file) = candidates.iter().find(|file| file.is_file()) else {
+                self.errors.push(LoadError::Module(ModuleError::new(
+                    ModuleErrorKind::NotFound {
+                        module: name,
+                        searched: candidates,
+                    },
+                    span,
+                )));
+
+                continue;
+            };
+
+            let file = fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+
+            let loaded = match self.modules.iter().position(|module| module.path == file) {
+                Some(loaded) => Some(loaded),
+                None => self.read(file, span),
+            };
+
+            if let Some(loaded) = loaded {
+                self.modules[module].imports.insert(position, loaded);
+            }
+        }
+    }
+
+    /// Read, lex and parse the file of a module imported at `span`, returning
+    /// the module it was added as.
+    fn read(&mut self, path: PathBuf, span: Span) -> Option<usize> {
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                self.errors.push(LoadError::Module(ModuleError::new(
+                    ModuleErrorKind::Unreadable {
+                        path,
+                        message: error.to_string(),
+                    },
+                    span,
+                )));
+
+                return None;
+            }
+        };
+
+        // Modules are named by their path from the directory they were found
+        // in, and shown by their path from the file being compiled if they
+        // are under its directory
+        let prefix = std::iter::once(&self.root)
+            .chain(&self.include)
+            .filter_map(|directory| path.strip_prefix(directory).ok())
+            .min_by_key(|relative| relative.components().count())
+            .map_or_else(|| qualified_name(&path), qualified_name);
+        let shown = path.strip_prefix(&self.root).unwrap_or(&path);
+
+        let id = self
+            .sources
+            .add(Source::new(&shown.display().to_string(), contents));
+        let file = self.sources.get(id)?;
+        let start = file.start();
+
+        let mut tokens = match file.lexer().with_options(self.options.clone()).lex() {
+            Ok(tokens) => tokens,
+            Err(mut error) => {
+                error.span = error.span.shift(start);
+                self.errors.push(LoadError::Lexer(error));
+
+                return None;
+            }
+        };
+
+        for token in &mut tokens {
+            token.span = token.span.shift(start);
+        }
+
+        #[cfg(feature = "resolve-cache")]
+        let first = self.arena.len();
+
+        let parsed = match Parser::parse_with(&tokens, std::mem::take(&mut self.arena)) {
+            Ok(parsed) => parsed,
+            Err(errors) => {
+                self.errors
+                    .extend(errors.into_iter().map(LoadError::Parser));
+
+                return None;
+            }
+        };
+
+        self.arena = parsed.arena;
+        self.modules.push(Module {
+            name: prefix.clone(),
+            prefix,
+            file: shown.display().to_string(),
+            path,
+            program: parsed.program,
+            resolution: Resolution::default(),
+            imports: HashMap::new(),
+            #[cfg(feature = "resolve-cache")]
+            origin: Origin {
+                offset: start,
+                expr: first,
+            },
+            #[cfg(feature = "resolve-cache")]
+            exprs: self.arena.len() - first,
+        });
+
+        Some(self.modules.len() - 1)
+    }
+
+    /// Find the imports that lead back to a module they were reached from,
+    /// going through the imports of each module in order from the root.
+    fn cycles(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        let mut visited = vec![false; self.modules.len()];
+
+        self.visit(0, &mut Vec::new(), &mut visited, &mut warnings);
+
+        warnings
+    }
+
+    /// Visit the modules imported by a module that haven't been yet, where
+    /// `path` is the modules imported to reach it, which it is the last of.
+    fn visit(
+        &self,
+        module: usize,
+        path: &mut Vec<usize>,
+        visited: &mut [bool],
+        warnings: &mut Vec<Warning>,
+    ) {
+        visited[module] = true;
+        path.push(module);
+
+        let mut imports: Vec<_> = self.modules[module].imports.iter().collect();
+
+        imports.sort();
+
+        for (&position, &imported) in imports {
+            if let Some(start) = path.iter().position(|&module| module == imported) {
+                let cycle = path[start..]
+                    .iter()
+                    .chain([&imported])
+                    .map(|&module| self.modules[module].file.clone())
+                    .collect();
+
+                let span = self.modules[module].program.items[position].span;
+
+                warnings.push(Warning::new(WarningKind::ImportCycle { cycle }, span));
+            } else if !visited[imported] {
+                self.visit(imported, path, visited, warnings);
+            }
+        }
+
+        path.pop();
+    }
+
+    /// Resolve the names of a module on its own, so it is known which of
+    /// them are its items.
+    fn resolve(&mut self, module: usize) {
+        #[cfg(feature = "resolve-cache")]
+        if let Some(resolution) = self.read_cache(module) {
+            self.modules[module].resolution = resolution;
+            self.cached += 1;
+
+            return;
+        }
+
+        let parsed = ParseResult {
+            program: std::mem::take(&mut self.modules[module].program),
+            arena: std::mem::take(&mut self.arena),
+        };
+
+        match Resolver::resolve(&parsed) {
+            Ok(resolution) => {
+                #[cfg(feature = "resolve-cache")]
+                self.write_cache(module, &resolution);
+
+                self.modules[module].resolution = resolution;
+            }
+            Err(errors) => self
+                .errors
+                .extend(errors.into_iter().map(LoadError::Resolve)),
+        }
+
+        self.modules[module].program = parsed.program;
+        self.arena = parsed.arena;
+    }
+
+    /// Rename the items of a module to their qualified names, along with
+    /// every name in it referring to an item of a module.
+    fn qualify(&mut self, module: usize) {
+        let mut names = HashMap::new();
+        let current = &self.modules[module];
+
+        // The root's items keep their names
+        if !current.prefix.is_empty() {
+            let items = current
+                .resolution
+                .scopes
+                .first()
+                .map_or(&[][..], |scope| &scope.symbols);
+
+            for symbol in items
+                .iter()
+                .map(|&symbol| current.resolution.symbol(symbol))
+            {
+                if symbol.kind != SymbolKind::Import {
+                    names.insert(
+                        symbol.name.clone(),
+                        Name::Item(qualify(&current.prefix, &symbol.name)),
+                    );
+                }
+            }
+        }
+
+        for (position, item) in current.program.items.iter().enumerate() {
+            let (ItemKind::Import(import), Some(&imported)) =
+                (&item.kind, current.imports.get(&position))
+            else {
+                continue;
+            };
+
+            let target = &self.modules[imported];
+
+            let Some(imported_names) = &import.names else {
+                // The module itself, by the last name of its path
+                if let Some(alias) = alias(import) {
+                    names.insert(alias, Name::Module(imported));
+                }
+
+                continue;
+            };
+
+            for name in imported_names {
+                match target.resolution.export(name, &target.name, item.span) {
+                    Ok(_) => {
+                        names.insert(name.clone(), Name::Item(qualify(&target.prefix, name)));
+                    }
+                    Err(error) => self.errors.push(LoadError::Resolve(*error)),
+                }
+            }
+        }
+
+        let mut program = std::mem::take(&mut self.modules[module].program);
+        let mut qualifier = Qualifier {
+            modules: &self.modules,
+            module,
+            names,
+            sources: self.sources,
+            generics: Vec::new(),
+            item: Span::default(),
+            errors: Vec::new(),
+        };
+
+        for item in &mut program.items {
+            qualifier.visit_item_mut(&mut self.arena, item);
+        }
+
+        let errors = qualifier.errors;
+
+        self.errors
+            .extend(errors.into_iter().map(LoadError::Resolve));
+        self.modules[module].program = program;
+    }
+
+    /// Merge the items of every module into one program, the root's first.
+    /// The imports that loaded modules are left out, and so are imports from
+    /// the standard library that an earlier module made too.
+    fn merge(self) -> ParseResult {
+        let mut items: Vec<Item> = Vec::new();
+        let mut imports: Vec<Import> = Vec::new();
+
+        for module in self.modules {
+            for (position, item) in module.program.items.into_iter().enumerate() {
+                if module.imports.contains_key(&position) {
+                    continue;
+                }
+
+                if let ItemKind::Import(import) = &item.kind {
+                    if imports.contains(import) {
+                        continue;
+                    }
+
+                    imports.push(import.clone());
+                }
+
+                items.push(item);
+            }
+        }
+
+        ParseResult {
+            program: Program { items },
+            arena: self.arena,
+        }
+    }
+}
+
+#[cfg(feature = "resolve-cache")]
+impl Loader<'_> {
+    /// The key the resolution of a module is cached by, from its source and
+    /// the sources of the modules it imports, and the file it is cached in.
+    fn cache_for(&self, module: usize) -> Option<(u64, PathBuf)> {
+        let directory = self.cache.as_ref()?;
+        let source = |module: &Module| {
+            let start = Span::new(module.origin.offset, module.origin.offset);
+
+            self.sources.locate(start).map(|(file, _)| file.contents())
+        };
+
+        let current = &self.modules[module];
+        let mut imports: Vec<_> = current.imports.iter().collect();
+
+        imports.sort();
+
+        let imports = imports
+            .into_iter()
+            .map(|(_, &imported)| source(&self.modules[imported]))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some((
+            resolve_cache::key(source(current)?, &imports),
+            resolve_cache::path_for(directory, &current.path),
+        ))
+    }
+
+    /// The resolution of a module from its cache, if it is still valid.
+    fn read_cache(&self, module: usize) -> Option<Resolution> {
+        let (key, path) = self.cache_for(module)?;
+        let file = fs::File::open(path).ok()?;
+        let current = &self.modules[module];
+
+        // A cache that can't be read is no worse than not having one
+        resolve_cache::read(
+            key,
+            self.options,
+            current.origin,
+            current.exprs,
+            io::BufReader::new(file),
+        )
+        .ok()
+        .flatten()
+    }
+
+    /// Cache the resolution of a module.
+    fn write_cache(&self, module: usize, resolution: &Resolution) {
+        let Some((key, path)) = self.cache_for(module) else {
+            return;
+        };
+
+        let current = &self.modules[module];
+
+        // A cache that can't be written only means the module is resolved
+        // again next time
+        let _ = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::File::create(&path))
+            .map_err(Into::into)
+            .and_then(|file| {
+                resolve_cache::write(
+                    key,
+                    self.options,
+                    resolution,
+                    current.origin,
+                    current.exprs,
+                    io::BufWriter::new(file),
+                )
+            });
+    }
+}
+
+/// What a name used at the top level of a module refers to.
+enum Name {
+    /// An item of a module, by its qualified name
+    Item(String),
+    /// A module imported without naming what is imported from it, e.g.
+    /// `utils` after `import utils;`
+    Module(usize),
+}
+
+/// Renames the items of a module and the names referring to items of
+/// modules to their qualified names.
+struct Qualifier<'a> {
+    modules: &'a [Module],
+    /// The module being renamed
+    module: usize,
+    /// What each name declared at the top level of the module refers to
+    names: HashMap<String, Name>,
+    sources: &'a SourceMap,
+    /// The type parameters in scope, which shadow types of the same name
+    generics: Vec<String>,
+    /// The item being renamed, which errors in types are reported at
+    item: Span,
+    errors: Vec<ResolveError>,
+}
+
+impl Qualifier<'_> {
+    /// The qualified name of a name or path used at `span`, or `None` if it
+    /// doesn't refer to an item of a module or is a module itself. A path
+    /// into another module has to name an item it declares `pub`.
+    fn qualify(&mut self, path: &str, span: Span) -> Option<String> {
+        let (first, rest) = match path.split_once("::") {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (path, None),
+        };
+
+        match (self.names.get(first)?, rest) {
+            (Name::Item(qualified), None) => Some(qualified.clone()),
+            (Name::Item(qualified), Some(rest)) => Some(format!("{}::{}", qualified, rest)),
+            (Name::Module(_), None) => None,
+            (&Name::Module(module), Some(rest)) => {
+                let target = &self.modules[module];
+                let item = rest.split("::").next().unwrap_or(rest);
+
+                match target.resolution.export(item, &target.name, span) {
+                    Ok(_) => Some(qualify(&target.prefix, rest)),
+                    Err(error) => {
+                        self.errors.push(*error);
+
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether a name used by an expression refers to something declared
+    /// at the top level of the module, rather than e.g. a local variable
+    /// shadowing it.
+    fn top_level(&self, id: ExprId) -> bool {
+        self.modules[self.module]
+            .resolution
+            .resolved(id)
+            .is_some_and(|symbol| symbol.scope.index() == 0)
+    }
+
+    /// Qualify the traits type parameters are bounded by, and bring the
+    /// parameters into scope until `generics` is truncated to `len`.
+    fn open_generics(&mut self, generics: &mut [TypeParam]) -> usize {
+        let len = self.generics.len();
+
+        for param in generics {
+            for bound in &mut param.bounds {
+                if let Some(qualified) = self.qualify(bound, self.item) {
+                    *bound = qualified;
+                }
+            }
+
+            self.generics.push(param.name.clone());
+        }
+
+        len
+    }
+
+    /// Qualify a type name, unless it is a type parameter.
+    fn qualify_type(&mut self, name: &mut String) {
+        let first = name.split("::").next().unwrap_or(name);
+
+        if self.generics.iter().any(|param| param == first) {
+            return;
+        }
+
+        if let Some(qualified) = self.qualify(name, self.item) {
+            *name = qualified;
+        }
+    }
+}
+
+impl VisitMut for Qualifier<'_> {
+    fn visit_item_mut(&mut self, arena: &mut Arena, item: &mut Item) {
+        self.item = item.span;
+
+        let name = match &mut item.kind {
+            ItemKind::Function(Function { name, .. })
+            | ItemKind::Global(Let { name, .. })
+            | ItemKind::Const(Let { name, .. })
+            | ItemKind::Struct(Struct { name, .. }) => Some(name),
+            ItemKind::Enum(enumeration) => Some(&mut enumeration.name),
+            ItemKind::Trait(declaration) => Some(&mut declaration.name),
+            ItemKind::Impl(implementation) => {
+                if let Some(qualified) = self.qualify(&implementation.trait_name, item.span) {
+                    implementation.trait_name = qualified;
+                }
+
+                None
+            }
+            ItemKind::Import(_) | ItemKind::StaticAssert(_) => None,
+        };
+
+        if let Some(name) = name {
+            if let Some(Name::Item(qualified)) = self.names.get(name.as_str()) {
+                *name = qualified.clone();
+            }
+        }
+
+        visit::walk_item_mut(self, arena, item);
+    }
+
+    fn visit_function_mut(&mut self, arena: &mut Arena, function: &mut Function) {
+        let len = self.open_generics(&mut function.generics);
+
+        visit::walk_function_mut(self, arena, function);
+
+        self.generics.truncate(len);
+    }
+
+    fn visit_struct_mut(&mut self, structure: &mut Struct) {
+        let len = self.open_generics(&mut structure.generics);
+
+        visit::walk_struct_mut(self, structure);
+
+        self.generics.truncate(len);
+    }
+
+    fn visit_expr_mut(&mut self, arena: &mut Arena, id: ExprId) {
+        visit::walk_expr_mut(self, arena, id);
+
+        if !self.top_level(id) {
+            return;
+        }
+
+        let span = arena[id].span;
+
+        match &mut arena[id].kind {
+            ExprKind::Identifier(name) | ExprKind::StructLiteral { name, .. } => {
+                if let Some(qualified) = self.qualify(name, span) {
+                    *name = qualified;
+                }
+            }
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                let is_module = matches!(self.names.get(enum_name.as_str()), Some(Name::Module(_)));
+
+                if !is_module {
+                    if let Some(qualified) = self.qualify(enum_name, span) {
+                        *enum_name = qualified;
+                    }
+
+                    return;
+                }
+
+                // An item of another module, e.g. `utils::helper(1)`, which
+                // is only a call if it has arguments or parentheses
+                let path = format!("{}::{}", enum_name, variant);
+                let args = std::mem::take(payload);
+
+                let Some(qualified) = self.qualify(&path, span) else {
+                    return;
+                };
+
+                let called = !args.is_empty()
+                    || self
+                        .sources
+                        .locate(span)
+                        .and_then(|(file, span)| file.slice(span))
+                        .is_some_and(|code| code.ends_with(')'));
+
+                if !called {
+                    arena[id].kind = ExprKind::Identifier(qualified);
+
+                    return;
+                }
+
+                let callee = arena.alloc(Expr {
+                    kind: ExprKind::Identifier(qualified),
+                    span,
+                });
+
+                arena[id].kind = ExprKind::Call { callee, args };
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        match ty {
+            Type::Named(name) | Type::Generic { name, .. } => self.qualify_type(name),
+            Type::Array {
+                length: Some(Length::Const(name)),
+                ..
+            } => {
+                if let Some(qualified) = self.qualify(name, self.item) {
+                    *name = qualified;
+                }
+            }
+            _ => {}
+        }
+
+        visit::walk_type_mut(self, ty);
+    }
+
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        if let Pattern::Variant {
+            enum_name: Some(name),
+            ..
+        } = pattern
+        {
+            if let Some(qualified) = self.qualify(name, self.item) {
+                *name = qualified;
+            }
+        }
+
+        visit::walk_pattern_mut(self, pattern);
+    }
+}
+
+/// The name an import without braces brings in, e.g. `strings` for
+/// `import utils::strings;` or `math` for `import "lib/math.pg";`.
+fn alias(import: &Import) -> Option<String> {
+    match &import.path {
+        ImportPath::Module(path) => path.last().cloned(),
+        ImportPath::File(file) => Path::new(file)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned()),
+    }
+}
+
+/// A name prefixed with the module it is in, or the name itself in the root.
+fn qualify(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}::{}", prefix, name)
+    }
+}
+
+/// The name of a module from its path, e.g. `utils::strings` for
+/// `utils/strings.pg`, or `utils` for `utils/mod.pg`.
+fn qualified_name(path: &Path) -> String {
+    let mut names: Vec<_> = path
+        .with_extension("")
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    if names.len() > 1 && names.last().is_some_and(|name| name == "mod") {
+        names.pop();
+    }
+
+    names.join("::")
+}
@@ -0,0 +1,168 @@
+//! The precedence and associativity of every operator.
+//!
+//! This is the one place that knows how tightly operators bind. The parser
+//! uses it to nest expressions, and anything that turns a tree back into
+//! source, or rewrites one, uses it to know where parentheses are needed, so
+//! the phases can't disagree about what `a - b * c` means.
+use crate::lexer::TokenKind;
+use crate::parser::ast::{BinaryOp, UnaryOp};
+
+/// How tightly an operator binds, loosest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Precedence {
+    /// `=` and compound assignments like `+=`
+    Assignment = 1,
+    /// `||`
+    Or,
+    /// `&&`
+    And,
+    /// `==` and `!=`
+    Equality,
+    /// `<`, `<=`, `>` and `>=`
+    Comparison,
+    /// `+` and `-`
+    Sum,
+    /// `*`, `/` and `%`
+    Product,
+    /// `as`, so `a * b as u64` casts `b`, and `-a as u64` casts `-a`
+    Cast,
+    /// Unary `-` and `!`
+    Prefix,
+    /// Field accesses, calls and indexing
+    Postfix,
+}
+
+/// Which way a chain of operators with the same precedence is nested.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a = b = c` is `a = (b = c)`
+    Right,
+    /// `a < b < c` is an error
+    None,
+}
+
+/// What an infix operator builds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Infix {
+    Binary(BinaryOp),
+    /// An assignment, holding the operator of a compound assignment like `+=`
+    Assign(Option<BinaryOp>),
+}
+
+/// Which operand of an infix operator an expression is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// The infix operator a token stands for, if any.
+pub fn infix(kind: &TokenKind) -> Option<Infix> {
+    let infix = match kind {
+        TokenKind::LetAssignment => Infix::Assign(None),
+        TokenKind::ShortIncrement => Infix::Assign(Some(BinaryOp::Add)),
+        TokenKind::ShortDecrement => Infix::Assign(Some(BinaryOp::Subtract)),
+        TokenKind::ShortMultiply => Infix::Assign(Some(BinaryOp::Multiply)),
+        TokenKind::ShortDivide => Infix::Assign(Some(BinaryOp::Divide)),
+        TokenKind::ShortModulo => Infix::Assign(Some(BinaryOp::Modulo)),
+        TokenKind::Or => Infix::Binary(BinaryOp::Or),
+        TokenKind::And => Infix::Binary(BinaryOp::And),
+        TokenKind::Equal => Infix::Binary(BinaryOp::Equal),
+        TokenKind::NotEqual => Infix::Binary(BinaryOp::NotEqual),
+        TokenKind::LessThan => Infix::Binary(BinaryOp::Less),
+        TokenKind::LessEqual => Infix::Binary(BinaryOp::LessEqual),
+        TokenKind::GreaterThan => Infix::Binary(BinaryOp::Greater),
+        TokenKind::GreaterEqual => Infix::Binary(BinaryOp::GreaterEqual),
+        TokenKind::Plus => Infix::Binary(BinaryOp::Add),
+        TokenKind::Minus => Infix::Binary(BinaryOp::Subtract),
+        TokenKind::Multiply => Infix::Binary(BinaryOp::Multiply),
+        TokenKind::Divide => Infix::Binary(BinaryOp::Divide),
+        TokenKind::Modulo => Infix::Binary(BinaryOp::Modulo),
+        _ => return None,
+    };
+
+    Some(infix)
+}
+
+/// The prefix operator a token stands for, if any.
+pub fn prefix(kind: &TokenKind) -> Option<UnaryOp> {
+    match kind {
+        TokenKind::Minus => Some(UnaryOp::Negate),
+        TokenKind::Not => Some(UnaryOp::Not),
+        _ => None,
+    }
+}
+
+/// The precedence and associativity of a binary operator.
+pub fn binary(op: BinaryOp) -> (Precedence, Associativity) {
+    match op {
+        BinaryOp::Or => (Precedence::Or, Associativity::Left),
+        BinaryOp::And => (Precedence::And, Associativity::Left),
+        BinaryOp::Equal | BinaryOp::NotEqual => (Precedence::Equality, Associativity::None),
+        BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
+            (Precedence::Comparison, Associativity::None)
+        }
+        BinaryOp::Add | BinaryOp::Subtract => (Precedence::Sum, Associativity::Left),
+        BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => {
+            (Precedence::Product, Associativity::Left)
+        }
+    }
+}
+
+impl Infix {
+    pub fn precedence(self) -> Precedence {
+        match self {
+            Infix::Binary(op) => binary(op).0,
+            Infix::Assign(_) => Precedence::Assignment,
+        }
+    }
+
+    pub fn associativity(self) -> Associativity {
+        match self {
+            Infix::Binary(op) => binary(op).1,
+            Infix::Assign(_) => Associativity::Right,
+        }
+    }
+
+    /// The left and right binding power of the operator, for a Pratt parser.
+    /// A higher power binds tighter. Left associative operators have a
+    /// higher right power, so `a - b - c` is `(a - b) - c`, and right
+    /// associative ones have a higher left power, so `a = b = c` is
+    /// `a = (b = c)`. Non-associative operators bind like left associative
+    /// ones, and the parser rejects the chain.
+    pub fn binding_power(self) -> (u8, u8) {
+        let power = self.precedence() as u8 * 2;
+
+        match self.associativity() {
+            Associativity::Left | Associativity::None => (power - 1, power),
+            Associativity::Right => (power, power - 1),
+        }
+    }
+}
+
+impl Precedence {
+    /// How tightly an operator with this precedence binds its operand on
+    /// the right, e.g. how much of `-a * b` the `-` takes.
+    pub fn binding_power(self) -> u8 {
+        self as u8 * 2 - 1
+    }
+}
+
+/// Whether an operand of `parent` needs parentheses to keep its meaning when
+/// written back out, e.g. `a + b` as the left operand of `*`.
+pub fn needs_parentheses(parent: Infix, child: Precedence, side: Side) -> bool {
+    let parent_precedence = parent.precedence();
+
+    if child != parent_precedence {
+        return child < parent_precedence;
+    }
+
+    // With the same precedence, only the side the operator nests towards
+    // can go without them
+    !matches!(
+        (parent.associativity(), side),
+        (Associativity::Left, Side::Left) | (Associativity::Right, Side::Right)
+    )
+}
@@ -0,0 +1,2121 @@
+use crate::lexer::{Span, Token, TokenKind};
+use crate::ops::{self, Associativity, Infix, Precedence};
+use crate::suggest::Suggestion;
+use ast::{
+    Arena, Arm, Assert, Attribute, Block, Closure, ClosureParam, Enum, Expr, ExprId, ExprKind,
+    Field, Function, Impl, Import, ImportPath, Item, ItemKind, Length, Let, Method, Param, Pattern,
+    Program, Signature, Stmt, StmtKind, Struct, Trait, Type, TypeParam, Variant,
+};
+use std::fmt;
+
+pub mod ast;
+pub mod printer;
+pub mod sexp;
+pub mod visit;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kinds of errors that can occur during parsing.
+pub enum ParseErrorKind {
+    /// A token was found where something else was expected.
+    UnexpectedToken {
+        /// A description of what was expected, e.g. "an expression"
+        expected: String,
+        /// The literal of the token that was found
+        found: String,
+    },
+    /// The tokens ended where something else was expected.
+    UnexpectedEOF {
+        /// A description of what was expected
+        expected: String,
+    },
+    /// A statement or item wasn't ended with a semicolon.
+    MissingSemicolon {
+        /// What the semicolon should have come after, e.g. "a return"
+        after: String,
+    },
+    /// Comparisons were chained, like `a < b < c`.
+    ChainedComparison {
+        /// The comparisons joined with `&&` instead, e.g. `a < b && b < c`
+        replacement: String,
+    },
+    /// Something other than a variable, field or element was assigned to.
+    InvalidAssignmentTarget,
+    /// `break` or `continue` was used outside of a loop.
+    OutsideOfLoop {
+        /// The keyword that was used
+        keyword: String,
+    },
+    /// `return`, `break` or `continue` would leave a `defer` block, which
+    /// runs when its scope is already ending.
+    LeavesDefer {
+        /// The keyword that was used
+        keyword: String,
+    },
+    /// An item has an attribute that doesn't exist.
+    UnknownAttribute {
+        /// The name of the attribute, without the `@`
+        name: String,
+    },
+    /// Expressions, blocks, patterns or types are nested more deeply than
+    /// `MAX_NESTING`.
+    NestingTooDeep,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An error that occurred during parsing.
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The tokens that caused the error
+    pub span: Span,
+    /// Boxed, since most errors don't have one and they are returned a lot
+    suggestion: Option<Box<Suggestion>>,
+}
+
+impl ParseError {
+    /// Create a new error.
+    pub fn new(kind: ParseErrorKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            suggestion: None,
+        }
+    }
+
+    /// Suggest a fix for the error.
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestion = Some(Box::new(suggestion));
+        self
+    }
+
+    /// A stable code identifying the kind of error, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            ParseErrorKind::UnexpectedToken { .. } => "E0101",
+            ParseErrorKind::UnexpectedEOF { .. } => "E0102",
+            ParseErrorKind::ChainedComparison { .. } => "E0103",
+            ParseErrorKind::InvalidAssignmentTarget => "E0104",
+            ParseErrorKind::MissingSemicolon { .. } => "E0105",
+            ParseErrorKind::OutsideOfLoop { .. } => "E0106",
+            ParseErrorKind::LeavesDefer { .. } => "E0107",
+            ParseErrorKind::UnknownAttribute { .. } => "E0108",
+            ParseErrorKind::NestingTooDeep => "E0109",
+        }
+    }
+
+    /// The tokens that caused the error.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// A fix for the error, if there is one that is sure to work.
+    pub fn suggestion(&self) -> Option<&Suggestion> {
+        self.suggestion.as_deref()
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ParseErrorKind::UnexpectedToken { expected, found } => {
+                format!("Expected {}, found '{}'", expected, found)
+            }
+            ParseErrorKind::UnexpectedEOF { expected } => {
+                format!("Expected {}, found the end of the file", expected)
+            }
+            ParseErrorKind::MissingSemicolon { after } => {
+                format!("Expected ';' after {}", after)
+            }
+            ParseErrorKind::ChainedComparison { replacement } => {
+                format!(
+                    "Comparisons can't be chained, use `{}` instead",
+                    replacement
+                )
+            }
+            ParseErrorKind::InvalidAssignmentTarget => {
+                "Only variables, fields and elements can be assigned to".to_string()
+            }
+            ParseErrorKind::OutsideOfLoop { keyword } => {
+                format!("'{}' can only be used inside of a loop", keyword)
+            }
+            ParseErrorKind::LeavesDefer { keyword } => {
+                format!("'{}' can't be used to leave a defer block", keyword)
+            }
+            ParseErrorKind::UnknownAttribute { name } => format!(
+                "There is no attribute '@{}', the attributes are: {}",
+                name,
+                ATTRIBUTES
+                    .iter()
+                    .map(|name| format!("@{}", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ParseErrorKind::NestingTooDeep => format!(
+                "This is nested too deeply, only {} levels are allowed",
+                MAX_NESTING
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.", self.message())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The attributes an item can have, which set the level of the lints they
+/// name inside of it.
+pub const ATTRIBUTES: &[&str] = &["allow", "warn", "deny"];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A parsed program, and the arena holding its expressions. Owning both
+/// means the tree can be passed around without a lifetime tying it to
+/// the arena.
+pub struct ParseResult {
+    pub program: Program,
+    pub arena: Arena,
+}
+
+/// Turns the tokens from the lexer into a `Program`.
+pub struct Parser<'tokens, 'source> {
+    /// The tokens being parsed, without any trivia
+    tokens: Vec<&'tokens Token<'source>>,
+    /// The index of the current token
+    position: usize,
+    /// Where the tokens end, for errors at the end of the file
+    end: usize,
+    /// How many loops the current token is inside of, in the current function
+    loop_depth: usize,
+    /// Whether the current token is inside of a `defer` block, in the
+    /// current function
+    in_defer: bool,
+    /// Whether an identifier followed by `{` is a struct literal. It isn't
+    /// in a loop's condition, where the brace starts the body instead.
+    struct_literals: bool,
+    /// The parameters and variables in scope in the current function, in the
+    /// order they were declared
+    locals: Vec<String>,
+    /// The closures the current token is inside of, innermost last
+    closures: Vec<ClosureScope>,
+    /// How deeply the current token is nested in expressions, blocks,
+    /// patterns and types
+    depth: usize,
+    /// Where the parsed expressions are allocated
+    arena: Arena,
+}
+
+/// How deeply expressions, blocks, patterns and types can be nested. Every
+/// pass after parsing walks the tree recursively, so without a limit deeply
+/// nested code would overflow the stack instead of being an error.
+pub const MAX_NESTING: usize = 128;
+
+/// A closure being parsed, and what it captures so far.
+struct ClosureScope {
+    /// The number of locals declared outside of the closure
+    base: usize,
+    captures: Vec<String>,
+}
+
+impl<'tokens, 'source> Parser<'tokens, 'source> {
+    /// Parse a whole program. If any items fail to parse, every error that was
+    /// found is returned.
+    pub fn parse(tokens: &'tokens [Token<'source>]) -> Result<ParseResult, Vec<ParseError>> {
+        Self::parse_with(tokens, Arena::new())
+    }
+
+    /// Parse a program into an arena that already holds expressions, e.g.
+    /// those of the other files of a program, so the handles of every
+    /// expression stay distinct.
+    pub fn parse_with(
+        tokens: &'tokens [Token<'source>],
+        arena: Arena,
+    ) -> Result<ParseResult, Vec<ParseError>> {
+        let mut parser = Parser::new(tokens);
+
+        parser.arena = arena;
+
+        let mut program = Program::default();
+        let mut errors = Vec::new();
+
+        while parser.current().is_some() {
+            let start = parser.position;
+
+            match parser.item() {
+                Ok(item) => program.items.push(item),
+                Err(error) => {
+                    errors.push(error);
+                    parser.synchronize(start);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(ParseResult {
+                program,
+                arena: parser.arena,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn new(tokens: &'tokens [Token<'source>]) -> Self {
+        // Whitespace and comments are only there if the lexer kept them, and
+        // they don't mean anything to the parser
+        let tokens: Vec<_> = tokens
+            .iter()
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+            .collect();
+        let end = tokens.last().map_or(0, |token| token.span.end);
+
+        Self {
+            tokens,
+            position: 0,
+            end,
+            loop_depth: 0,
+            in_defer: false,
+            struct_literals: true,
+            locals: Vec::new(),
+            closures: Vec::new(),
+            depth: 0,
+            arena: Arena::new(),
+        }
+    }
+
+    /// Parse a top level item.
+    fn item(&mut self) -> Result<Item, ParseError> {
+        let start = self.current_span();
+        let attributes = self.attributes()?;
+        let public = self.eat(&TokenKind::Pub);
+
+        // Only what has a name can be used from other modules
+        if public
+            && matches!(
+                self.current_kind(),
+                Some(TokenKind::Import | TokenKind::Impl | TokenKind::StaticAssert)
+            )
+        {
+            return Err(self.unexpected("a function, struct, enum, trait, constant or variable"));
+        }
+
+        let kind = match self.current_kind() {
+            Some(TokenKind::Function) => self.function().map(ItemKind::Function),
+            Some(TokenKind::Import) => self.import().map(ItemKind::Import),
+            Some(TokenKind::Assign) => {
+                self.advance();
+
+                let mutable = self.eat(&TokenKind::Mut);
+
+                self.declaration(mutable).map(ItemKind::Global)
+            }
+            // Constants are declared like variables, but whether their value
+            // is constant is checked after parsing
+            Some(TokenKind::Const) => {
+                self.advance();
+
+                self.declaration(false).map(ItemKind::Const)
+            }
+            Some(TokenKind::Struct) => self.structure().map(ItemKind::Struct),
+            Some(TokenKind::Enum) => self.enumeration().map(ItemKind::Enum),
+            Some(TokenKind::Trait) => self.trait_declaration().map(ItemKind::Trait),
+            Some(TokenKind::Impl) => self.implementation().map(ItemKind::Impl),
+            Some(TokenKind::StaticAssert) => {
+                self.advance();
+
+                self.assertion("a static assertion")
+                    .map(ItemKind::StaticAssert)
+            }
+            _ => Err(self
+                .unexpected("a function, struct, enum, trait, impl, import, constant or variable")),
+        }?;
+
+        Ok(Item {
+            kind,
+            public,
+            attributes,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parse the attributes before an item, if there are any.
+    ///
+    /// E.g. `@allow(unused_variables)` or `@deny(warnings, shadowing)`
+    fn attributes(&mut self) -> Result<Vec<Attribute>, ParseError> {
+        let mut attributes = Vec::new();
+
+        while let Some(token) = self.current() {
+            if token.kind != TokenKind::Attribute {
+                break;
+            }
+
+            let name = token.literal.trim_start_matches('@');
+
+            if !ATTRIBUTES.contains(&name) {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnknownAttribute {
+                        name: name.to_string(),
+                    },
+                    token.span,
+                ));
+            }
+
+            self.advance();
+            self.expect(TokenKind::OpenParen, "'('")?;
+
+            let mut args = Vec::new();
+
+            loop {
+                let span = self.current_span();
+
+                args.push((self.identifier("the name of a lint")?, span));
+
+                if !self.eat(&TokenKind::Comma) || self.check(&TokenKind::CloseParen) {
+                    break;
+                }
+            }
+
+            self.expect(TokenKind::CloseParen, "')' or ','")?;
+
+            attributes.push(Attribute {
+                name: name.to_string(),
+                args,
+                span: self.span_from(token.span),
+            });
+        }
+
+        Ok(attributes)
+    }
+
+    /// Parse an import, starting at `import`.
+    ///
+    /// E.g. `import "file.pg";`, `import std::io;` or
+    /// `import math::{sin, cos};`
+    fn import(&mut self) -> Result<Import, ParseError> {
+        self.expect(TokenKind::Import, "'import'")?;
+
+        let mut names = None;
+
+        let path = if let Some(TokenKind::String(path)) = self.current_kind() {
+            self.advance();
+
+            ImportPath::File(path.clone())
+        } else {
+            let mut path = vec![self.identifier("a path to import")?];
+
+            while self.eat(&TokenKind::PathSeparator) {
+                if !self.eat(&TokenKind::OpenBrace) {
+                    path.push(self.identifier("a module name or '{'")?);
+
+                    continue;
+                }
+
+                // The names imported from the module end the path
+                let mut imported = Vec::new();
+
+                while !self.check(&TokenKind::CloseBrace) {
+                    imported.push(self.identifier("a name to import")?);
+
+                    if !self.eat(&TokenKind::Comma) {
+                        break;
+                    }
+                }
+
+                self.expect(TokenKind::CloseBrace, "'}' or ','")?;
+
+                names = Some(imported);
+
+                break;
+            }
+
+            ImportPath::Module(path)
+        };
+
+        self.semicolon("an import")?;
+
+        Ok(Import { path, names })
+    }
+
+    /// Parse a function, starting at `func`.
+    ///
+    /// E.g. `func add(a : u32, b : u32) : u32 = { return a + b; };`
+    fn function(&mut self) -> Result<Function, ParseError> {
+        self.expect(TokenKind::Function, "'func'")?;
+
+        let name = self.identifier("a function name")?;
+        let generics = self.generics()?;
+
+        self.function_rest(name, generics)
+    }
+
+    /// Parse the rest of a function after its name and type parameters,
+    /// starting at its parameters.
+    fn function_rest(
+        &mut self,
+        name: String,
+        generics: Vec<TypeParam>,
+    ) -> Result<Function, ParseError> {
+        let params = self.params()?;
+
+        // Functions are assigned to their body like variables, with the
+        // return type between the colon and equal sign
+        let return_type = if self.untyped_assignment() {
+            None
+        } else {
+            self.expect(TokenKind::TypeAssignment, "':=' or ':'")?;
+
+            let ty = self.ty("a return type")?;
+
+            self.expect(TokenKind::LetAssignment, "'='")?;
+
+            Some(ty)
+        };
+
+        // Loops and defers outside of the function don't continue into it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let in_defer = std::mem::take(&mut self.in_defer);
+        let locals = params.iter().map(|param| param.name.clone()).collect();
+        let locals = std::mem::replace(&mut self.locals, locals);
+        let body = self.block();
+
+        self.loop_depth = loop_depth;
+        self.in_defer = in_defer;
+        self.locals = locals;
+
+        let body = body?;
+
+        // Functions end with a semicolon, like any other assignment
+        self.semicolon("a function")?;
+
+        Ok(Function {
+            name,
+            generics,
+            params,
+            return_type,
+            body,
+        })
+    }
+
+    /// Parse the parameters of a function or method, in parentheses. `self`
+    /// can be written without a type, since it is always `Self`.
+    ///
+    /// E.g. `(a : u32, mut b : str)` or `(self, other : Self)`
+    fn params(&mut self) -> Result<Vec<Param>, ParseError> {
+        let mut params = Vec::new();
+
+        self.expect(TokenKind::OpenParen, "'('")?;
+
+        while !self.check(&TokenKind::CloseParen) {
+            let mutable = self.eat(&TokenKind::Mut);
+            let name_span = self.current_span();
+            let name = self.identifier("a parameter name")?;
+
+            let ty = if name == "self" && !self.check(&TokenKind::TypeAssignment) {
+                Type::Named("Self".to_string())
+            } else {
+                self.expect(TokenKind::TypeAssignment, "':'")?;
+
+                self.ty("a parameter type")?
+            };
+
+            params.push(Param {
+                name,
+                name_span,
+                mutable,
+                ty,
+            });
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.expect(TokenKind::CloseParen, "')'")?;
+
+        Ok(params)
+    }
+
+    /// Parse the type parameters after the name of a function or struct, if
+    /// it has any, with the traits their types have to implement.
+    ///
+    /// E.g. `<A, B>` or `<T : Shape + Named>`
+    fn generics(&mut self) -> Result<Vec<TypeParam>, ParseError> {
+        let mut generics = Vec::new();
+
+        if !self.eat(&TokenKind::LessThan) {
+            return Ok(generics);
+        }
+
+        while !self.check(&TokenKind::GreaterThan) {
+            let name = self.identifier("a type parameter")?;
+            let mut bounds = Vec::new();
+
+            if self.eat(&TokenKind::TypeAssignment) {
+                bounds.push(self.identifier("a trait name")?);
+
+                while self.eat(&TokenKind::Plus) {
+                    bounds.push(self.identifier("a trait name")?);
+                }
+            }
+
+            generics.push(TypeParam { name, bounds });
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.expect(TokenKind::GreaterThan, "'>'")?;
+
+        Ok(generics)
+    }
+
+    /// Parse a struct declaration, starting at `struct`.
+    ///
+    /// E.g. `struct Point { x : i32, y : i32 }`
+    fn structure(&mut self) -> Result<Struct, ParseError> {
+        self.expect(TokenKind::Struct, "'struct'")?;
+
+        let name = self.identifier("a struct name")?;
+        let generics = self.generics()?;
+        let mut fields = Vec::new();
+
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+
+        while !self.check(&TokenKind::CloseBrace) {
+            let name = self.identifier("a field name")?;
+
+            self.expect(TokenKind::TypeAssignment, "':'")?;
+
+            let ty = self.ty("a field type")?;
+
+            fields.push(Field { name, ty });
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.expect(TokenKind::CloseBrace, "'}'")?;
+
+        // Like blocks, structs don't need a semicolon after them
+        self.eat(&TokenKind::Semicolon);
+
+        Ok(Struct {
+            name,
+            generics,
+            fields,
+        })
+    }
+
+    /// Parse an enum declaration, starting at `enum`.
+    ///
+    /// E.g. `enum Color { Red, Green, Rgb(u8, u8, u8) }`
+    fn enumeration(&mut self) -> Result<Enum, ParseError> {
+        self.expect(TokenKind::Enum, "'enum'")?;
+
+        let name = self.identifier("an enum name")?;
+        let mut variants = Vec::new();
+
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+
+        while !self.check(&TokenKind::CloseBrace) {
+            let name = self.identifier("a variant name")?;
+            let mut payload = Vec::new();
+
+            if self.eat(&TokenKind::OpenParen) {
+                while !self.check(&TokenKind::CloseParen) {
+                    payload.push(self.ty("a type")?);
+
+                    if !self.eat(&TokenKind::Comma) {
+                        break;
+                    }
+                }
+
+                self.expect(TokenKind::CloseParen, "')'")?;
+            }
+
+            variants.push(Variant { name, payload });
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.expect(TokenKind::CloseBrace, "'}'")?;
+
+        // Like blocks, enums don't need a semicolon after them
+        self.eat(&TokenKind::Semicolon);
+
+        Ok(Enum { name, variants })
+    }
+
+    /// Parse a trait declaration, starting at `trait`.
+    ///
+    /// E.g. `trait Shape { func area(self) : u32; func name(self) : str; }`
+    fn trait_declaration(&mut self) -> Result<Trait, ParseError> {
+        self.expect(TokenKind::Trait, "'trait'")?;
+
+        let name = self.identifier("a trait name")?;
+        let mut methods = Vec::new();
+
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+
+        while !self.check(&TokenKind::CloseBrace) {
+            self.expect(TokenKind::Function, "'func' or '}'")?;
+
+            let name = self.identifier("a method name")?;
+            let params = self.params()?;
+
+            let return_type = if self.eat(&TokenKind::TypeAssignment) {
+                Some(self.ty("a return type")?)
+            } else {
+                None
+            };
+
+            self.semicolon("a method")?;
+
+            methods.push(Signature {
+                name,
+                params,
+                return_type,
+            });
+        }
+
+        self.expect(TokenKind::CloseBrace, "'}'")?;
+
+        // Like blocks, traits don't need a semicolon after them
+        self.eat(&TokenKind::Semicolon);
+
+        Ok(Trait { name, methods })
+    }
+
+    /// Parse the methods of a trait for a type, starting at `impl`. Methods
+    /// are functions, but can't have type parameters of their own.
+    ///
+    /// E.g. `impl Shape for Circle { func area(self) : u32 = { ... }; }`
+    fn implementation(&mut self) -> Result<Impl, ParseError> {
+        self.expect(TokenKind::Impl, "'impl'")?;
+
+        let trait_name = self.identifier("a trait name")?;
+
+        self.expect(TokenKind::For, "'for'")?;
+
+        let ty = self.ty("a type")?;
+        let mut methods = Vec::new();
+
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+
+        while !self.check(&TokenKind::CloseBrace) {
+            let start = self.current_span();
+
+            self.expect(TokenKind::Function, "'func' or '}'")?;
+
+            let name = self.identifier("a method name")?;
+            let function = self.function_rest(name, Vec::new())?;
+
+            methods.push(Method {
+                function,
+                span: self.span_from(start),
+            });
+        }
+
+        self.expect(TokenKind::CloseBrace, "'}'")?;
+
+        // Like blocks, impls don't need a semicolon after them
+        self.eat(&TokenKind::Semicolon);
+
+        Ok(Impl {
+            trait_name,
+            ty,
+            methods,
+        })
+    }
+
+    /// Parse a block of statements between curly braces, and the expression
+    /// it ends with if there is one.
+    fn block(&mut self) -> Result<Block, ParseError> {
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+
+        // Variables declared in the block go out of scope at its end
+        let scope = self.locals.len();
+
+        let block = self.nested(|parser| {
+            parser.with_struct_literals(true, |parser| {
+                let mut block = Block::default();
+
+                while !parser.check(&TokenKind::CloseBrace) {
+                    if parser.current().is_none() {
+                        return Err(parser.unexpected("'}'"));
+                    }
+
+                    match parser.statement()? {
+                        Statement::Stmt(stmt) => block.stmts.push(stmt),
+                        Statement::Value(value) => block.value = Some(value),
+                    }
+                }
+
+                parser.expect(TokenKind::CloseBrace, "'}'")?;
+
+                Ok(block)
+            })
+        });
+
+        self.locals.truncate(scope);
+
+        block
+    }
+
+    /// Parse a statement, including its semicolon. An expression without a
+    /// semicolon at the end of a block is the value of the block instead.
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+
+        let kind = match self.current_kind() {
+            Some(TokenKind::Assign) => {
+                self.advance();
+
+                self.local()?
+            }
+            // `let` is optional when declaring a variable in a function, e.g.
+            // `c := bar(a, b);` or `mut c := 1;`
+            Some(TokenKind::Mut) => self.local()?,
+            Some(TokenKind::Identifier)
+                if matches!(
+                    self.peek_kind(),
+                    Some(TokenKind::UnTypedAssignment | TokenKind::TypeAssignment)
+                ) =>
+            {
+                self.local()?
+            }
+            Some(TokenKind::Return) => {
+                if self.in_defer {
+                    return Err(self.leaves_defer());
+                }
+
+                self.advance();
+
+                let value = if self.check(&TokenKind::Semicolon) {
+                    None
+                } else {
+                    Some(self.expression()?)
+                };
+
+                self.semicolon("a return")?;
+
+                StmtKind::Return(value)
+            }
+            Some(TokenKind::While) => {
+                self.advance();
+
+                if self.eat(&TokenKind::Assign) {
+                    let (pattern, value) = self.let_condition()?;
+
+                    self.loop_depth += 1;
+
+                    let body = self.block_with_bindings(&pattern);
+
+                    self.loop_depth -= 1;
+
+                    self.eat(&TokenKind::Semicolon);
+
+                    StmtKind::WhileLet {
+                        pattern,
+                        value,
+                        body: body?,
+                    }
+                } else {
+                    let condition = self.with_struct_literals(false, Parser::expression)?;
+
+                    self.loop_depth += 1;
+
+                    let body = self.block();
+
+                    self.loop_depth -= 1;
+
+                    // Like other blocks, loops don't need a semicolon after them
+                    self.eat(&TokenKind::Semicolon);
+
+                    StmtKind::While {
+                        condition,
+                        body: body?,
+                    }
+                }
+            }
+            Some(TokenKind::Assert) => {
+                self.advance();
+
+                StmtKind::Assert(self.assertion("an assertion")?)
+            }
+            Some(TokenKind::StaticAssert) => {
+                self.advance();
+
+                StmtKind::StaticAssert(self.assertion("a static assertion")?)
+            }
+            Some(TokenKind::Defer) => {
+                self.advance();
+
+                let loop_depth = std::mem::take(&mut self.loop_depth);
+                let in_defer = std::mem::replace(&mut self.in_defer, true);
+                let body = self.block();
+
+                self.loop_depth = loop_depth;
+                self.in_defer = in_defer;
+
+                self.eat(&TokenKind::Semicolon);
+
+                StmtKind::Defer(body?)
+            }
+            Some(kind @ (TokenKind::Break | TokenKind::Continue)) => {
+                let Some(token) = self.current() else {
+                    return Err(self.unexpected("a statement"));
+                };
+
+                // Loops outside of the defer don't continue into it
+                if self.loop_depth == 0 && self.in_defer {
+                    return Err(self.leaves_defer());
+                }
+
+                if self.loop_depth == 0 {
+                    return Err(ParseError::new(
+                        ParseErrorKind::OutsideOfLoop {
+                            keyword: token.literal.to_string(),
+                        },
+                        token.span,
+                    ));
+                }
+
+                self.advance();
+
+                if *kind == TokenKind::Break {
+                    self.semicolon("a break")?;
+
+                    StmtKind::Break
+                } else {
+                    self.semicolon("a continue")?;
+
+                    StmtKind::Continue
+                }
+            }
+            _ => {
+                let expr = self.expression()?;
+
+                if self.check(&TokenKind::CloseBrace) {
+                    return Ok(Statement::Value(expr));
+                }
+
+                // Blocks end with a brace, so they don't need a semicolon
+                // after them when they aren't the value
+                if !matches!(
+                    self.arena[expr].kind,
+                    ExprKind::Block(_) | ExprKind::IfLet { .. } | ExprKind::Match { .. }
+                ) || self.check(&TokenKind::Semicolon)
+                {
+                    self.semicolon("an expression")?;
+                }
+
+                StmtKind::Expr(expr)
+            }
+        };
+
+        Ok(Statement::Stmt(Stmt {
+            kind,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parse a variable declaration in a function after any `let`, bringing
+    /// the variable into scope.
+    fn local(&mut self) -> Result<StmtKind, ParseError> {
+        let mutable = self.eat(&TokenKind::Mut);
+        let decl = self.declaration(mutable)?;
+
+        self.locals.push(decl.name.clone());
+
+        Ok(StmtKind::Let(decl))
+    }
+
+    /// Parse the condition and message of an assertion after its keyword,
+    /// including its semicolon.
+    ///
+    /// E.g. `(a < b);` or `(a < b, "a is too big");`
+    fn assertion(&mut self, after: &str) -> Result<Assert, ParseError> {
+        self.expect(TokenKind::OpenParen, "'('")?;
+
+        let assert = self.with_struct_literals(true, |parser| {
+            let condition = parser.expression()?;
+            let mut message = None;
+
+            if parser.eat(&TokenKind::Comma) && !parser.check(&TokenKind::CloseParen) {
+                let Some(TokenKind::String(string)) = parser.current_kind() else {
+                    return Err(parser.unexpected("a message"));
+                };
+
+                message = Some(string.clone());
+
+                parser.advance();
+                parser.eat(&TokenKind::Comma);
+            }
+
+            parser.expect(TokenKind::CloseParen, "')' or ','")?;
+
+            Ok(Assert { condition, message })
+        })?;
+
+        self.semicolon(after)?;
+
+        Ok(assert)
+    }
+
+    /// Parse a variable declaration after any `let` and `mut`, including its
+    /// semicolon.
+    ///
+    /// E.g. `a := 1;` or `a : u32 = 1;`
+    fn declaration(&mut self, mutable: bool) -> Result<Let, ParseError> {
+        let name_span = self.current_span();
+        let name = self.identifier("a variable name")?;
+
+        let ty = if self.untyped_assignment() {
+            None
+        } else {
+            self.expect(TokenKind::TypeAssignment, "':=' or ':'")?;
+
+            let ty = self.ty("a type")?;
+
+            self.expect(TokenKind::LetAssignment, "'='")?;
+
+            Some(ty)
+        };
+
+        let value = self.expression()?;
+
+        self.semicolon("a variable declaration")?;
+
+        Ok(Let {
+            name,
+            name_span,
+            mutable,
+            ty,
+            value,
+        })
+    }
+
+    /// Parse an expression, allocating it in the arena.
+    fn expression(&mut self) -> Result<ExprId, ParseError> {
+        self.expression_with_power(0)
+    }
+
+    /// Parse an expression made of operators that bind at least as tightly as
+    /// `min_power`. This is a Pratt parser, so each operator's binding powers
+    /// decide how the tree is nested.
+    fn expression_with_power(&mut self, min_power: u8) -> Result<ExprId, ParseError> {
+        self.nested(|parser| parser.operators(min_power))
+    }
+
+    /// The body of `expression_with_power`, one level deeper than where the
+    /// expression is.
+    fn operators(&mut self, min_power: u8) -> Result<ExprId, ParseError> {
+        let mut left = self.prefix()?;
+
+        // The precedence of the last non-associative operator, since they
+        // can't be chained
+        let mut non_associative = None;
+
+        while let Some(token) = self.current() {
+            // Postfix operators span from the start of their operand
+            let start = self.arena[left].span;
+
+            if Precedence::Postfix.binding_power() >= min_power {
+                match token.kind {
+                    TokenKind::Dot => {
+                        self.advance();
+                        self.deeper()?;
+
+                        let field = self.identifier("a field or method name")?;
+
+                        // A field followed by arguments is a method call,
+                        // e.g. `list.map(f)`
+                        let expr = if self.check(&TokenKind::OpenParen) {
+                            ExprKind::MethodCall {
+                                receiver: left,
+                                method: field,
+                                args: self.arguments()?,
+                            }
+                        } else {
+                            ExprKind::Field {
+                                object: left,
+                                field,
+                            }
+                        };
+
+                        left = self.alloc(expr, start);
+
+                        continue;
+                    }
+                    TokenKind::OpenBracket => {
+                        self.advance();
+                        self.deeper()?;
+
+                        let index = self.with_struct_literals(true, Parser::expression)?;
+
+                        self.expect(TokenKind::CloseBracket, "']'")?;
+
+                        left = self.alloc(
+                            ExprKind::Index {
+                                object: left,
+                                index,
+                            },
+                            start,
+                        );
+
+                        continue;
+                    }
+                    TokenKind::OpenParen => {
+                        self.deeper()?;
+
+                        let args = self.arguments()?;
+
+                        left = self.alloc(ExprKind::Call { callee: left, args }, start);
+
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+
+            if token.kind == TokenKind::As {
+                if Precedence::Cast.binding_power() < min_power {
+                    break;
+                }
+
+                self.advance();
+                self.deeper()?;
+
+                let ty = self.cast_type()?;
+
+                left = self.alloc(ExprKind::Cast { value: left, ty }, start);
+
+                continue;
+            }
+
+            let Some(infix) = ops::infix(&token.kind) else {
+                break;
+            };
+
+            let (left_power, right_power) = infix.binding_power();
+
+            if left_power < min_power {
+                break;
+            }
+
+            // Every operator puts the expression before it one level
+            // deeper, e.g. in `a + b + c`, even though this is a loop
+            self.advance();
+            self.deeper()?;
+
+            let precedence = infix.precedence();
+
+            if non_associative == Some(precedence) {
+                return Err(self.chained_comparison(left, token, right_power));
+            }
+
+            non_associative = (infix.associativity() == Associativity::None).then_some(precedence);
+
+            let right = self.expression_with_power(right_power)?;
+
+            let span = self.arena[left].span.merge(self.arena[right].span);
+
+            let kind = match infix {
+                Infix::Binary(op) => ExprKind::Binary { op, left, right },
+                Infix::Assign(op) => {
+                    if !matches!(
+                        self.arena[left].kind,
+                        ExprKind::Identifier(_) | ExprKind::Field { .. } | ExprKind::Index { .. }
+                    ) {
+                        return Err(ParseError::new(
+                            ParseErrorKind::InvalidAssignmentTarget,
+                            token.span,
+                        ));
+                    }
+
+                    ExprKind::Assign {
+                        op,
+                        target: left,
+                        value: right,
+                    }
+                }
+            };
+
+            left = self.arena.alloc(Expr { kind, span });
+        }
+
+        Ok(left)
+    }
+
+    /// Parse a unary operator and its operand, or a primary expression.
+    fn prefix(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.current_span();
+
+        let Some(op) = self.current_kind().and_then(ops::prefix) else {
+            return self.primary();
+        };
+
+        self.advance();
+
+        let operand = self.expression_with_power(Precedence::Prefix.binding_power())?;
+
+        Ok(self.alloc(ExprKind::Unary { op, operand }, start))
+    }
+
+    /// Parse a literal, a variable, a tuple, an array, a block, a closure, an
+    /// `if let`, a match, or an expression in parentheses.
+    fn primary(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.current_span();
+
+        let kind = match self.current_kind() {
+            Some(TokenKind::True) => ExprKind::Bool(true),
+            Some(TokenKind::False) => ExprKind::Bool(false),
+            Some(TokenKind::Number(number)) => ExprKind::Number(*number),
+            Some(TokenKind::String(string)) => ExprKind::String(string.clone()),
+            Some(TokenKind::Identifier) => {
+                let name = self.identifier("an expression")?;
+
+                if self.struct_literals && self.check(&TokenKind::OpenBrace) {
+                    return self.struct_literal(name, start);
+                }
+
+                if self.eat(&TokenKind::PathSeparator) {
+                    return self.variant(name, start);
+                }
+
+                self.use_variable(&name);
+
+                return Ok(self.alloc(ExprKind::Identifier(name), start));
+            }
+            Some(TokenKind::OpenBrace) => {
+                let block = self.block()?;
+
+                return Ok(self.alloc(ExprKind::Block(block), start));
+            }
+            Some(TokenKind::Function) => {
+                let closure = self.closure()?;
+
+                return Ok(self.alloc(ExprKind::Closure(closure), start));
+            }
+            Some(TokenKind::If) => return self.if_let(),
+            Some(TokenKind::Match) => return self.match_expression(),
+            Some(TokenKind::OpenParen) => {
+                self.advance();
+
+                return self.with_struct_literals(true, |parser| {
+                    if parser.eat(&TokenKind::CloseParen) {
+                        return Ok(parser.alloc(ExprKind::Tuple(Vec::new()), start));
+                    }
+
+                    let first = parser.expression()?;
+
+                    // Without a comma, the parentheses are only grouping
+                    if !parser.eat(&TokenKind::Comma) {
+                        parser.expect(TokenKind::CloseParen, "')' or ','")?;
+
+                        return Ok(first);
+                    }
+
+                    let mut elements = vec![first];
+
+                    while !parser.check(&TokenKind::CloseParen) {
+                        elements.push(parser.expression()?);
+
+                        if !parser.eat(&TokenKind::Comma) {
+                            break;
+                        }
+                    }
+
+                    parser.expect(TokenKind::CloseParen, "')' or ','")?;
+
+                    Ok(parser.alloc(ExprKind::Tuple(elements), start))
+                });
+            }
+            Some(TokenKind::OpenBracket) => {
+                self.advance();
+
+                return self.with_struct_literals(true, |parser| {
+                    let mut elements = Vec::new();
+
+                    while !parser.check(&TokenKind::CloseBracket) {
+                        elements.push(parser.expression()?);
+
+                        if !parser.eat(&TokenKind::Comma) {
+                            break;
+                        }
+                    }
+
+                    parser.expect(TokenKind::CloseBracket, "']' or ','")?;
+
+                    Ok(parser.alloc(ExprKind::Array(elements), start))
+                });
+            }
+            _ => return Err(self.unexpected("an expression")),
+        };
+
+        self.advance();
+
+        Ok(self.alloc(kind, start))
+    }
+
+    /// Parse the fields of a struct literal after its name, which is at
+    /// `start`.
+    ///
+    /// E.g. `{ x: 1, y: 2 }`
+    fn struct_literal(&mut self, name: String, start: Span) -> Result<ExprId, ParseError> {
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+
+        self.with_struct_literals(true, |parser| {
+            let mut fields = Vec::new();
+
+            while !parser.check(&TokenKind::CloseBrace) {
+                let field = parser.identifier("a field name")?;
+
+                parser.expect(TokenKind::TypeAssignment, "':'")?;
+
+                fields.push((field, parser.expression()?));
+
+                if !parser.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+
+            parser.expect(TokenKind::CloseBrace, "'}'")?;
+
+            Ok(parser.alloc(ExprKind::StructLiteral { name, fields }, start))
+        })
+    }
+
+    /// Parse a value of an enum after its name, which is at `start`, and
+    /// `::`. A longer path is an item of another module, which the path
+    /// before the last name is kept as the enum name of.
+    ///
+    /// E.g. `Red`, `Rgb(1, 2, 3)`, `Color::Red` after `colors::` or
+    /// `Point { x: 1, y: 2 }` after `shapes::`
+    fn variant(&mut self, mut enum_name: String, start: Span) -> Result<ExprId, ParseError> {
+        let mut variant = self.identifier("a variant name")?;
+
+        while self.eat(&TokenKind::PathSeparator) {
+            enum_name = format!("{}::{}", enum_name, variant);
+            variant = self.identifier("a name")?;
+        }
+
+        if self.struct_literals && self.check(&TokenKind::OpenBrace) {
+            return self.struct_literal(format!("{}::{}", enum_name, variant), start);
+        }
+
+        let payload = if self.check(&TokenKind::OpenParen) {
+            self.arguments()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(self.alloc(
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            },
+            start,
+        ))
+    }
+
+    /// Parse an `if let`, starting at `if`, and any `else` after it.
+    ///
+    /// E.g. `if let Some(x) = f() { x } else { 0 }`
+    fn if_let(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.current_span();
+
+        self.expect(TokenKind::If, "'if'")?;
+        self.expect(TokenKind::Assign, "'let'")?;
+
+        let (pattern, value) = self.let_condition()?;
+        let then = self.block_with_bindings(&pattern)?;
+
+        let otherwise = if !self.eat(&TokenKind::Else) {
+            None
+        } else if self.check(&TokenKind::If) {
+            // Each `else if` is nested in the one before it
+            Some(Block {
+                stmts: Vec::new(),
+                value: Some(self.nested(Parser::if_let)?),
+            })
+        } else {
+            Some(self.block()?)
+        };
+
+        Ok(self.alloc(
+            ExprKind::IfLet {
+                pattern,
+                value,
+                then,
+                otherwise,
+            },
+            start,
+        ))
+    }
+
+    /// Parse a match, starting at `match`.
+    ///
+    /// E.g. `match shape { Shape::Circle(r) => r, _ => 0 }`
+    fn match_expression(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.current_span();
+
+        self.expect(TokenKind::Match, "'match'")?;
+
+        // Like an `if let`, the brace after the value starts the arms
+        let value = self.with_struct_literals(false, Parser::expression)?;
+
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+
+        let arms = self.with_struct_literals(true, |parser| {
+            let mut arms = Vec::new();
+
+            while !parser.check(&TokenKind::CloseBrace) {
+                let arm = parser.arm()?;
+
+                // An arm ending with a block doesn't need a comma after it
+                let block = matches!(parser.arena[arm.value].kind, ExprKind::Block(_));
+
+                arms.push(arm);
+
+                if !parser.eat(&TokenKind::Comma) && !block {
+                    break;
+                }
+            }
+
+            parser.expect(TokenKind::CloseBrace, "'}' or ','")?;
+
+            Ok(arms)
+        })?;
+
+        Ok(self.alloc(ExprKind::Match { value, arms }, start))
+    }
+
+    /// Parse an arm of a match, with the names its pattern binds in scope in
+    /// its value.
+    ///
+    /// E.g. `Some(x) => x + 1`
+    fn arm(&mut self) -> Result<Arm, ParseError> {
+        let start = self.current_span();
+        let pattern = self.pattern()?;
+
+        self.expect(TokenKind::FatArrow, "'=>'")?;
+
+        let scope = self.locals.len();
+
+        self.locals
+            .extend(pattern.bindings().into_iter().map(String::from));
+
+        // A block ends the arm, so the next arm isn't taken as a call
+        let value = if self.check(&TokenKind::OpenBrace) {
+            let start = self.current_span();
+
+            self.block()
+                .map(|block| self.alloc(ExprKind::Block(block), start))
+        } else {
+            self.expression()
+        };
+
+        self.locals.truncate(scope);
+
+        Ok(Arm {
+            pattern,
+            value: value?,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parse the pattern and value of an `if let` or `while let`, after
+    /// `let`.
+    ///
+    /// E.g. `Some(x) = iter.next()`
+    fn let_condition(&mut self) -> Result<(Pattern, ExprId), ParseError> {
+        let pattern = self.pattern()?;
+
+        self.expect(TokenKind::LetAssignment, "'='")?;
+
+        // Like a loop's condition, the brace after the value starts the body
+        let value = self.with_struct_literals(false, Parser::expression)?;
+
+        Ok((pattern, value))
+    }
+
+    /// Parse a block with the names a pattern binds in scope.
+    fn block_with_bindings(&mut self, pattern: &Pattern) -> Result<Block, ParseError> {
+        let scope = self.locals.len();
+
+        self.locals
+            .extend(pattern.bindings().into_iter().map(String::from));
+
+        let block = self.block();
+
+        self.locals.truncate(scope);
+
+        block
+    }
+
+    /// Parse a pattern.
+    ///
+    /// E.g. `x`, `_`, `1`, `true`, `(a, b)`, `Some(x)` or
+    /// `Shape::Circle(p, _)`
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        let pattern = match self.current() {
+            Some(token) if token.kind == TokenKind::Identifier && token.literal == "_" => {
+                Pattern::Wildcard
+            }
+            Some(token) if token.kind == TokenKind::Identifier => {
+                let name = self.identifier("a pattern")?;
+
+                let mut enum_name = None;
+                let mut variant = name;
+
+                // Every name before the last is the path to the enum, e.g.
+                // `colors::Color` in `colors::Color::Red`
+                while self.eat(&TokenKind::PathSeparator) {
+                    enum_name = Some(match enum_name {
+                        Some(path) => format!("{}::{}", path, variant),
+                        None => variant,
+                    });
+                    variant = self.identifier("a variant name")?;
+                }
+
+                return Ok(if self.check(&TokenKind::OpenParen) {
+                    Pattern::Variant {
+                        enum_name,
+                        variant,
+                        payload: self.patterns()?.0,
+                    }
+                } else if enum_name.is_some() {
+                    Pattern::Variant {
+                        enum_name,
+                        variant,
+                        payload: Vec::new(),
+                    }
+                } else {
+                    Pattern::Binding(variant)
+                });
+            }
+            Some(token) => match &token.kind {
+                TokenKind::True => Pattern::Bool(true),
+                TokenKind::False => Pattern::Bool(false),
+                TokenKind::Number(number) => Pattern::Number(*number),
+                TokenKind::String(string) => Pattern::String(string.clone()),
+                TokenKind::OpenParen => {
+                    let (mut patterns, comma) = self.patterns()?;
+
+                    // Without a comma, the parentheses are only grouping
+                    return Ok(match patterns.pop() {
+                        Some(pattern) if patterns.is_empty() && !comma => pattern,
+                        Some(pattern) => {
+                            patterns.push(pattern);
+
+                            Pattern::Tuple(patterns)
+                        }
+                        None => Pattern::Tuple(patterns),
+                    });
+                }
+                _ => return Err(self.unexpected("a pattern")),
+            },
+            None => return Err(self.unexpected("a pattern")),
+        };
+
+        self.advance();
+
+        Ok(pattern)
+    }
+
+    /// Parse a list of patterns between parentheses, which can end with a
+    /// trailing comma, and whether there were any commas.
+    ///
+    /// E.g. `(x, _)`
+    fn patterns(&mut self) -> Result<(Vec<Pattern>, bool), ParseError> {
+        self.expect(TokenKind::OpenParen, "'('")?;
+
+        let mut patterns = Vec::new();
+        let mut comma = false;
+
+        while !self.check(&TokenKind::CloseParen) {
+            patterns.push(self.nested(Parser::pattern)?);
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+
+            comma = true;
+        }
+
+        self.expect(TokenKind::CloseParen, "')' or ','")?;
+
+        Ok((patterns, comma))
+    }
+
+    /// Parse a closure, starting at `func`.
+    ///
+    /// E.g. `func(x, y : u32) => x + y`
+    fn closure(&mut self) -> Result<Closure, ParseError> {
+        self.expect(TokenKind::Function, "'func'")?;
+        self.expect(TokenKind::OpenParen, "'('")?;
+
+        let mut params = Vec::new();
+
+        while !self.check(&TokenKind::CloseParen) {
+            let name = self.identifier("a parameter name")?;
+
+            let ty = if self.eat(&TokenKind::TypeAssignment) {
+                Some(self.ty("a parameter type")?)
+            } else {
+                None
+            };
+
+            params.push(ClosureParam { name, ty });
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.expect(TokenKind::CloseParen, "')' or ','")?;
+        self.expect(TokenKind::FatArrow, "'=>'")?;
+
+        let base = self.locals.len();
+
+        self.locals
+            .extend(params.iter().map(|param| param.name.clone()));
+        self.closures.push(ClosureScope {
+            base,
+            captures: Vec::new(),
+        });
+
+        // Like functions, loops and defers outside of the closure don't
+        // continue into it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let in_defer = std::mem::take(&mut self.in_defer);
+        let body = self.expression();
+
+        self.loop_depth = loop_depth;
+        self.in_defer = in_defer;
+        self.locals.truncate(base);
+
+        let captures = self
+            .closures
+            .pop()
+            .map_or_else(Vec::new, |scope| scope.captures);
+
+        Ok(Closure {
+            params,
+            body: body?,
+            captures,
+        })
+    }
+
+    /// Record that a variable is used, so closures it is declared outside of
+    /// capture it. Names that aren't locals, like globals and functions, are
+    /// never captured.
+    fn use_variable(&mut self, name: &str) {
+        let Some(index) = self.locals.iter().rposition(|local| local == name) else {
+            return;
+        };
+
+        // Every closure between the use and the declaration captures it
+        for closure in self.closures.iter_mut().rev() {
+            if closure.base <= index {
+                break;
+            }
+
+            if !closure.captures.iter().any(|capture| capture == name) {
+                closure.captures.push(name.to_string());
+            }
+        }
+    }
+
+    /// Parse a list of arguments between parentheses, which can end with a
+    /// trailing comma.
+    ///
+    /// E.g. `(1, bar(2), "x")`
+    fn arguments(&mut self) -> Result<Vec<ExprId>, ParseError> {
+        self.expect(TokenKind::OpenParen, "'('")?;
+
+        self.with_struct_literals(true, |parser| {
+            let mut args = Vec::new();
+
+            while !parser.check(&TokenKind::CloseParen) {
+                args.push(parser.expression()?);
+
+                if !parser.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+
+            parser.expect(TokenKind::CloseParen, "')' or ','")?;
+
+            Ok(args)
+        })
+    }
+
+    /// Run `parse` with struct literals allowed or not, restoring what was
+    /// allowed before afterwards.
+    fn with_struct_literals<T>(&mut self, allowed: bool, parse: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = parse(self);
+
+        self.struct_literals = previous;
+
+        result
+    }
+
+    /// Parse something one level deeper than the current token, failing if
+    /// that is too deep.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let depth = self.depth;
+
+        let result = self.deeper().and_then(|()| parse(self));
+
+        self.depth = depth;
+
+        result
+    }
+
+    /// Go a level deeper, until `nested` returns to the level it started at.
+    fn deeper(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+
+        if self.depth > MAX_NESTING {
+            return Err(ParseError::new(
+                ParseErrorKind::NestingTooDeep,
+                self.current_span(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Parse a type, and any `?`s making it optional.
+    ///
+    /// E.g. `u32`, `List<T>`, `(u32, str)`, `[T; 4]`, `T?` or
+    /// `func(u32) -> u32`
+    fn ty(&mut self, expected: &str) -> Result<Type, ParseError> {
+        self.nested(|parser| parser.optional_type(expected))
+    }
+
+    /// The body of `ty`, one level deeper than where the type is.
+    fn optional_type(&mut self, expected: &str) -> Result<Type, ParseError> {
+        let mut ty = match self.current_kind() {
+            Some(TokenKind::OpenParen) => self.tuple_type()?,
+            Some(TokenKind::OpenBracket) => {
+                self.advance();
+
+                let element = Box::new(self.ty("a type")?);
+
+                let length = if self.eat(&TokenKind::Semicolon) {
+                    match self.current_kind() {
+                        Some(TokenKind::Number(length)) => {
+                            self.advance();
+
+                            Some(Length::Number(*length))
+                        }
+                        Some(TokenKind::Identifier) => {
+                            Some(Length::Const(self.identifier("an array length")?))
+                        }
+                        _ => return Err(self.unexpected("an array length")),
+                    }
+                } else {
+                    None
+                };
+
+                self.expect(TokenKind::CloseBracket, "']' or ';'")?;
+
+                Type::Array { element, length }
+            }
+            Some(TokenKind::Function) => {
+                self.advance();
+
+                self.expect(TokenKind::OpenParen, "'('")?;
+
+                let params = self.type_list(TokenKind::CloseParen, "')'")?;
+
+                let return_type = if self.eat(&TokenKind::Arrow) {
+                    Some(Box::new(self.ty("a return type")?))
+                } else {
+                    None
+                };
+
+                Type::Function {
+                    params,
+                    return_type,
+                }
+            }
+            _ => {
+                let name = self.type_name(expected)?;
+
+                if self.eat(&TokenKind::LessThan) {
+                    let args = self.type_list(TokenKind::GreaterThan, "'>'")?;
+
+                    Type::Generic { name, args }
+                } else {
+                    Type::Named(name)
+                }
+            }
+        };
+
+        while self.eat(&TokenKind::Question) {
+            self.deeper()?;
+
+            ty = Type::Optional(Box::new(ty));
+        }
+
+        Ok(ty)
+    }
+
+    /// Parse the type a value is cast to. A name isn't followed by generic
+    /// arguments, so `a as u32 < b` is a comparison.
+    fn cast_type(&mut self) -> Result<Type, ParseError> {
+        if self.check(&TokenKind::Identifier) {
+            return Ok(Type::Named(self.type_name("a type")?));
+        }
+
+        self.ty("a type")
+    }
+
+    /// Parse the name of a type, which can be a path to a type in another
+    /// module, kept as a single qualified name.
+    ///
+    /// E.g. `Point` or `shapes::Point`
+    fn type_name(&mut self, expected: &str) -> Result<String, ParseError> {
+        let mut name = self.identifier(expected)?;
+
+        while self.eat(&TokenKind::PathSeparator) {
+            name.push_str("::");
+            name.push_str(&self.identifier("a type name")?);
+        }
+
+        Ok(name)
+    }
+
+    /// Parse a list of types up to and including `close`, which can end with
+    /// a trailing comma.
+    ///
+    /// E.g. `u32, str)` or `K, V>`
+    fn type_list(
+        &mut self,
+        close: TokenKind,
+        expected_close: &str,
+    ) -> Result<Vec<Type>, ParseError> {
+        let mut types = Vec::new();
+
+        while !self.check(&close) {
+            types.push(self.ty("a type")?);
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.expect(close, &format!("{} or ','", expected_close))?;
+
+        Ok(types)
+    }
+
+    /// Parse a tuple type, or a type in parentheses.
+    ///
+    /// E.g. `(u32, str)` or `(func() -> u32)?`
+    fn tuple_type(&mut self) -> Result<Type, ParseError> {
+        self.expect(TokenKind::OpenParen, "'('")?;
+
+        if self.eat(&TokenKind::CloseParen) {
+            return Ok(Type::Tuple(Vec::new()));
+        }
+
+        let first = self.ty("a type")?;
+
+        // Like expressions, a type in parentheses is only a tuple with a comma
+        if !self.eat(&TokenKind::Comma) {
+            self.expect(TokenKind::CloseParen, "')' or ','")?;
+
+            return Ok(first);
+        }
+
+        let mut types = vec![first];
+
+        types.extend(self.type_list(TokenKind::CloseParen, "')'")?);
+
+        Ok(Type::Tuple(types))
+    }
+
+    /// Move past a `:=` if the current token starts one, returning whether it
+    /// did. The lexer joins `:` and `=` into one token, even with trivia
+    /// between them, but they are accepted as separate tokens too.
+    fn untyped_assignment(&mut self) -> bool {
+        if self.eat(&TokenKind::UnTypedAssignment) {
+            return true;
+        }
+
+        let split = self.check(&TokenKind::TypeAssignment)
+            && self.peek_kind() == Some(&TokenKind::LetAssignment);
+
+        if split {
+            self.advance();
+            self.advance();
+        }
+
+        split
+    }
+
+    /// Expect an identifier, returning its name.
+    fn identifier(&mut self, expected: &str) -> Result<String, ParseError> {
+        match self.current().and_then(|token| token.identifier()) {
+            Some(name) => {
+                self.advance();
+
+                Ok(name.to_string())
+            }
+            None => Err(self.unexpected(expected)),
+        }
+    }
+
+    /// Expect the current token to be of the given kind, moving past it.
+    fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<(), ParseError> {
+        if self.eat(&kind) {
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    /// Expect a semicolon ending a statement or item. The error points just
+    /// after the previous token, where the semicolon is missing from.
+    fn semicolon(&mut self, after: &str) -> Result<(), ParseError> {
+        if self.eat(&TokenKind::Semicolon) {
+            return Ok(());
+        }
+
+        let end = self.previous_span().end;
+
+        Err(ParseError::new(
+            ParseErrorKind::MissingSemicolon {
+                after: after.to_string(),
+            },
+            Span::new(end, end),
+        ))
+    }
+
+    /// Move past the current token if it is of the given kind, returning
+    /// whether it was.
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        let found = self.check(kind);
+
+        if found {
+            self.advance();
+        }
+
+        found
+    }
+
+    /// Check if the current token is of the given kind.
+    fn check(&self, kind: &TokenKind) -> bool {
+        self.current_kind() == Some(kind)
+    }
+
+    /// An error for the current token not being what was expected.
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let expected = expected.to_string();
+
+        match self.current() {
+            Some(token) => ParseError::new(
+                ParseErrorKind::UnexpectedToken {
+                    expected,
+                    found: token.literal.to_string(),
+                },
+                token.span,
+            ),
+            None => ParseError::new(
+                ParseErrorKind::UnexpectedEOF { expected },
+                Span::new(self.end, self.end),
+            ),
+        }
+    }
+
+    /// An error for the current `return`, `break` or `continue` leaving a
+    /// `defer` block.
+    fn leaves_defer(&self) -> ParseError {
+        match self.current() {
+            Some(token) => ParseError::new(
+                ParseErrorKind::LeavesDefer {
+                    keyword: token.literal.to_string(),
+                },
+                token.span,
+            ),
+            None => self.unexpected("a statement"),
+        }
+    }
+
+    /// After an error in the item starting at `start`, skip to where the next
+    /// item probably starts, so more errors can be found. This is after the
+    /// next semicolon outside of any braces, or before the next `func`,
+    /// `import`, `const`, `struct` or `enum` outside of any braces.
+    fn synchronize(&mut self, start: usize) {
+        let mut depth = 0usize;
+
+        self.position = start;
+
+        // Always move forward, so an error at the start of an item can't
+        // cause the same error forever
+        while let Some(kind) = self.current_kind() {
+            if depth == 0
+                && self.position > start
+                && matches!(
+                    kind,
+                    TokenKind::Function
+                        | TokenKind::Attribute
+                        | TokenKind::Pub
+                        | TokenKind::Import
+                        | TokenKind::Const
+                        | TokenKind::Struct
+                        | TokenKind::Enum
+                        | TokenKind::Trait
+                        | TokenKind::Impl
+                        | TokenKind::StaticAssert
+                )
+            {
+                break;
+            }
+
+            self.advance();
+
+            match kind {
+                TokenKind::OpenBrace => depth += 1,
+                TokenKind::CloseBrace => depth = depth.saturating_sub(1),
+                TokenKind::Semicolon if depth == 0 => break,
+                _ => {}
+            }
+        }
+    }
+
+    /// Allocate an expression, spanning from `start` to the last token moved
+    /// past.
+    fn alloc(&mut self, kind: ExprKind, start: Span) -> ExprId {
+        let span = self.span_from(start);
+
+        self.arena.alloc(Expr { kind, span })
+    }
+
+    /// A span from `start` to the end of the last token moved past.
+    fn span_from(&self, start: Span) -> Span {
+        start.merge(self.previous_span())
+    }
+
+    /// The span of the current token, or an empty span at the end of the
+    /// file.
+    fn current_span(&self) -> Span {
+        self.current()
+            .map_or(Span::new(self.end, self.end), |token| token.span)
+    }
+
+    /// Report a comparison chained onto `left`, another comparison, by its
+    /// operator, suggesting comparing the operand they share twice instead,
+    /// e.g. `a < b && b < c` for `a < b < c`.
+    fn chained_comparison(
+        &mut self,
+        left: ExprId,
+        operator: &Token,
+        right_power: u8,
+    ) -> ParseError {
+        let right = match self.expression_with_power(right_power) {
+            Ok(right) => right,
+            Err(error) => return error,
+        };
+
+        let shared = match self.arena[left].kind {
+            ExprKind::Binary { right, .. } => right,
+            _ => left,
+        };
+
+        let span = self.arena[left].span.merge(self.arena[right].span);
+        let replacement = format!(
+            "{} && {} {} {}",
+            self.text(self.arena[left].span),
+            self.text(self.arena[shared].span),
+            operator.literal,
+            self.text(self.arena[right].span)
+        );
+
+        ParseError::new(
+            ParseErrorKind::ChainedComparison {
+                replacement: replacement.clone(),
+            },
+            operator.span,
+        )
+        .with_suggestion(Suggestion::replace(
+            "Compare the shared operand twice",
+            span,
+            replacement,
+        ))
+    }
+
+    /// The code of the tokens in a span, with one space wherever there was
+    /// space between them.
+    fn text(&self, span: Span) -> String {
+        let mut text = String::new();
+        let mut end = None;
+
+        for token in &self.tokens {
+            if token.span.start < span.start || token.span.end > span.end {
+                continue;
+            }
+
+            if end.is_some_and(|end| end < token.span.start) {
+                text.push(' ');
+            }
+
+            text.push_str(token.literal);
+            end = Some(token.span.end);
+        }
+
+        text
+    }
+
+    /// The span of the last token moved past, or an empty span at the start
+    /// of the file.
+    fn previous_span(&self) -> Span {
+        self.position
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map_or(Span::default(), |token| token.span)
+    }
+
+    fn current(&self) -> Option<&'tokens Token<'source>> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn current_kind(&self) -> Option<&'tokens TokenKind> {
+        self.current().map(|token| &token.kind)
+    }
+
+    fn peek_kind(&self) -> Option<&'tokens TokenKind> {
+        self.tokens.get(self.position + 1).map(|token| &token.kind)
+    }
+
+    fn advance(&mut self) {
+        if self.position < self.tokens.len() {
+            self.position += 1;
+        }
+    }
+}
+
+/// Something parsed inside of a block.
+enum Statement {
+    Stmt(Stmt),
+    /// The expression a block ends with, without a semicolon
+    Value(ExprId),
+}
@@ -0,0 +1,767 @@
+use crate::lexer::Span;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A whole source file. Names in the tree are owned, so it doesn't borrow from
+/// the tokens it was parsed from, but its expressions are in the `Arena` it
+/// was parsed into.
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A handle to an expression in an `Arena`. It is only meaningful for the
+/// arena that allocated it.
+pub struct ExprId(u32);
+
+impl ExprId {
+    /// Where the expression is in its arena, e.g. for tables with an entry
+    /// for every expression.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The handle of the expression at a position in an arena, e.g. for one
+    /// read back from a cache.
+    pub fn from_index(index: usize) -> Self {
+        ExprId(u32::try_from(index).expect("Too many expressions for the arena"))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The expressions of a program, stored next to each other instead of each
+/// in its own box. Expressions refer to their children by `ExprId`, and are
+/// looked up by indexing the arena, e.g. `arena[id]`.
+///
+/// Children are allocated before their parents, so a large file is one
+/// growing allocation instead of one for every node.
+pub struct Arena {
+    exprs: Vec<Expr>,
+}
+
+impl Arena {
+    /// Create an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move an expression into the arena, returning its handle.
+    pub fn alloc(&mut self, expr: Expr) -> ExprId {
+        let id = u32::try_from(self.exprs.len()).expect("Too many expressions for the arena");
+
+        self.exprs.push(expr);
+
+        ExprId(id)
+    }
+
+    /// The number of expressions in the arena.
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    /// Every expression in the arena and its handle, in the order they were
+    /// allocated.
+    pub fn iter(&self) -> impl Iterator<Item = (ExprId, &Expr)> {
+        self.exprs
+            .iter()
+            .enumerate()
+            .map(|(i, expr)| (ExprId(i as u32), expr))
+    }
+}
+
+impl Index<ExprId> for Arena {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.index()]
+    }
+}
+
+impl IndexMut<ExprId> for Arena {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.index()]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An item, and where it is in the source from its first token to its last,
+/// including any semicolon.
+pub struct Item {
+    pub kind: ItemKind,
+    /// Whether the item is declared `pub`, so other modules can use it
+    pub public: bool,
+    /// The attributes before the item, in order
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An attribute before an item, which changes how the item is checked.
+///
+/// E.g. `@allow(unused_variables, shadowing)`
+pub struct Attribute {
+    /// The name of the attribute, without the `@`
+    pub name: String,
+    /// The names in its parentheses, and where each is
+    pub args: Vec<(String, Span)>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Something that can be declared at the top level of a file.
+pub enum ItemKind {
+    /// `func name(params) : Type = { ... };`
+    Function(Function),
+    /// `import "std.print";`, `import math;` or `import math::{sin, cos};`
+    Import(Import),
+    /// A variable declared outside of any function
+    ///
+    /// E.g. `let x := 0;`
+    Global(Let),
+    /// A named constant, whose value has to be known when compiling
+    ///
+    /// E.g. `const MAX : u32 = 100;`
+    Const(Let),
+    /// `struct Point { x : i32, y : i32 }`
+    Struct(Struct),
+    /// `enum Color { Red, Green, Rgb(u8, u8, u8) }`
+    Enum(Enum),
+    /// `static_assert(size < 64, "too big");`, checked when the program is
+    /// compiled
+    StaticAssert(Assert),
+    /// `trait Shape { func area(self) : u32; }`
+    Trait(Trait),
+    /// `impl Shape for Circle { func area(self) : u32 = { ... }; }`
+    Impl(Impl),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Import {
+    pub path: ImportPath,
+    /// The names imported from a module, or `None` to import the module
+    /// itself
+    ///
+    /// E.g. `sin` and `cos` for `import math::{sin, cos};`
+    pub names: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What an import refers to.
+pub enum ImportPath {
+    /// A file, relative to the importing one, e.g. `"file.pg"`
+    File(String),
+    /// A module by name, e.g. `math` or `std::io`
+    Module(Vec<String>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Function {
+    pub name: String,
+    /// The type parameters, e.g. `T` in `func max<T>(...)`
+    pub generics: Vec<TypeParam>,
+    pub params: Vec<Param>,
+    /// The declared return type, or `None` when declared with `:=`
+    pub return_type: Option<Type>,
+    pub body: Block,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A function parameter, e.g. `a : u32` or `mut a : u32`.
+pub struct Param {
+    pub name: String,
+    /// Where the name is
+    pub name_span: Span,
+    /// Whether the parameter can be assigned to
+    pub mutable: bool,
+    pub ty: Type,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An anonymous function, e.g. `func(x) => x + 1`.
+pub struct Closure {
+    pub params: Vec<ClosureParam>,
+    pub body: ExprId,
+    /// The variables of enclosing functions and closures that the body uses,
+    /// in the order they are first used
+    pub captures: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A closure parameter, which can leave out its type, e.g. `x` or `x : u32`.
+pub struct ClosureParam {
+    pub name: String,
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A user-defined type made of named fields. Structs are nominal, so two
+/// structs with the same fields are still different types.
+pub struct Struct {
+    pub name: String,
+    /// The type parameters, e.g. `A` and `B` in `struct Pair<A, B>`
+    pub generics: Vec<TypeParam>,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A type parameter of a function or struct, with the traits every type it
+/// stands for has to implement, e.g. `T` or `T : Shape + Named`.
+pub struct TypeParam {
+    pub name: String,
+    pub bounds: Vec<String>,
+}
+
+impl fmt::Display for TypeParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if !self.bounds.is_empty() {
+            write!(f, " : {}", self.bounds.join(" + "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A set of methods that types can implement, so generic code can call them
+/// on any type that does.
+pub struct Trait {
+    pub name: String,
+    /// The methods an impl of the trait has to have
+    pub methods: Vec<Signature>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A method required by a trait, without a body, e.g. `func area(self) :
+/// u32;`. `Self` stands for the type implementing the trait.
+pub struct Signature {
+    pub name: String,
+    pub params: Vec<Param>,
+    /// The declared return type, or `None` when it returns nothing
+    pub return_type: Option<Type>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The methods of a trait for a type, e.g. `impl Shape for Circle { ... }`.
+pub struct Impl {
+    pub trait_name: String,
+    pub ty: Type,
+    pub methods: Vec<Method>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A method in an impl. `self.m(a)` calls it with `self` as its first
+/// parameter, which is written without a type, as `Self`.
+pub struct Method {
+    pub function: Function,
+    /// Where the method is, which declares its parameters
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A field of a struct, e.g. `x : i32`.
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A user-defined type whose values are one of its variants.
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A variant of an enum, e.g. `Red` or `Rgb(u8, u8, u8)`.
+pub struct Variant {
+    pub name: String,
+    /// The types of the values the variant holds, empty if it holds none
+    pub payload: Vec<Type>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A list of statements between curly braces.
+///
+/// A block can end with an expression without a semicolon, which is the value
+/// of the block, e.g. `{ compute(); 5 }`. In a function body, this is what
+/// the function returns.
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    /// The expression the block ends with
+    pub value: Option<ExprId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A variable declaration, with or without `let`.
+///
+/// E.g. `let a := 1;`, `b : u32 = 2;` or `let mut c := 3;`
+pub struct Let {
+    pub name: String,
+    /// Where the name is
+    pub name_span: Span,
+    /// Whether the variable can be assigned to
+    pub mutable: bool,
+    /// The declared type, or `None` when declared with `:=`
+    pub ty: Option<Type>,
+    pub value: ExprId,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A condition that has to hold, and what to report if it doesn't.
+pub struct Assert {
+    pub condition: ExprId,
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A type written in the source, e.g. in a declaration or parameter.
+pub enum Type {
+    /// A type referred to by name, e.g. `u32` or `Point`
+    Named(String),
+    /// A generic type applied to arguments, e.g. `List<T>`
+    Generic { name: String, args: Vec<Type> },
+    /// `(u32, str)`, or `()` for the empty tuple. A single type needs a
+    /// trailing comma to be a tuple, e.g. `(u32,)`.
+    Tuple(Vec<Type>),
+    /// `[T]`, or `[T; N]` for an array of a fixed length
+    Array {
+        element: Box<Type>,
+        length: Option<Length>,
+    },
+    /// `T?`, which is either a `T` or nothing
+    Optional(Box<Type>),
+    /// `func(u32) -> u32`, or `func(u32)` for a function that returns nothing
+    Function {
+        params: Vec<Type>,
+        return_type: Option<Box<Type>>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The length of an array type, which is known when the program is compiled.
+pub enum Length {
+    /// `[u8; 4]`
+    Number(usize),
+    /// `[u8; SIZE]`, the value of a constant
+    Const(String),
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Length::Number(length) => write!(f, "{}", length),
+            Length::Const(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    /// Write the type the way it is written in the source.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Generic { name, args } => write!(f, "{}<{}>", name, List(args)),
+            // A tuple of one type needs its comma, so it isn't just grouping
+            Type::Tuple(types) if types.len() == 1 => write!(f, "({},)", types[0]),
+            Type::Tuple(types) => write!(f, "({})", List(types)),
+            Type::Array {
+                element,
+                length: Some(length),
+            } => write!(f, "[{}; {}]", element, length),
+            Type::Array {
+                element,
+                length: None,
+            } => write!(f, "[{}]", element),
+            // Function types need parentheses, or the `?` would belong to
+            // their return type
+            Type::Optional(ty) if matches!(**ty, Type::Function { .. }) => {
+                write!(f, "({})?", ty)
+            }
+            Type::Optional(ty) => write!(f, "{}?", ty),
+            Type::Function {
+                params,
+                return_type,
+            } => {
+                write!(f, "func({})", List(params))?;
+
+                match return_type {
+                    Some(ty) => write!(f, " -> {}", ty),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Writes a list of types or patterns separated by commas.
+struct List<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for List<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, ty) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", ty)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A statement, and where it is in the source from its first token to its
+/// last, including any semicolon.
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StmtKind {
+    Let(Let),
+    /// `return;` or `return value;`
+    Return(Option<ExprId>),
+    /// `while condition { ... }`
+    While {
+        condition: ExprId,
+        body: Block,
+    },
+    /// `while let pattern = value { ... }`, which loops for as long as the
+    /// value matches the pattern
+    WhileLet {
+        pattern: Pattern,
+        value: ExprId,
+        body: Block,
+    },
+    /// `defer { ... }`, which runs the block when the scope it is in ends,
+    /// however it ends. Deferred blocks run in the reverse of the order
+    /// they were written.
+    Defer(Block),
+    /// `assert(a < b, "a is too big");`, checked when it runs
+    Assert(Assert),
+    /// `static_assert(MAX > 0);`, checked when the program is compiled
+    StaticAssert(Assert),
+    /// `break;`, only inside of a loop
+    Break,
+    /// `continue;`, only inside of a loop
+    Continue,
+    /// An expression followed by a semicolon, e.g. `print(c);`
+    Expr(ExprId),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An expression, and where it is in the source from its first token to its
+/// last. Expressions live in an `Arena`, and refer to each other by `ExprId`.
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExprKind {
+    /// `true` or `false`
+    Bool(bool),
+    Number(usize),
+    /// A string, with its escape sequences resolved
+    String(String),
+    Identifier(String),
+    /// `(1, "a", b)`, or `()` for the empty tuple. A single element needs a
+    /// trailing comma to be a tuple, e.g. `(1,)`.
+    Tuple(Vec<ExprId>),
+    /// `[1, 2, 3]`, or `[]` for an empty array
+    Array(Vec<ExprId>),
+    /// A block, which evaluates to the expression it ends with
+    Block(Block),
+    Unary {
+        op: UnaryOp,
+        operand: ExprId,
+    },
+    Binary {
+        op: BinaryOp,
+        left: ExprId,
+        right: ExprId,
+    },
+    /// A value of a struct, e.g. `Point { x: 1, y: 2 }`
+    StructLiteral {
+        name: String,
+        /// The value of each field, in the order they were written
+        fields: Vec<(String, ExprId)>,
+    },
+    /// A value of an enum, e.g. `Color::Red` or `Color::Rgb(1, 2, 3)`
+    Variant {
+        enum_name: String,
+        variant: String,
+        /// The values the variant holds, empty if it holds none
+        payload: Vec<ExprId>,
+    },
+    Closure(Closure),
+    /// Calling a function, e.g. `foo(1, bar(2), "x")`
+    Call {
+        callee: ExprId,
+        args: Vec<ExprId>,
+    },
+    /// Calling a method, e.g. `list.map(f)`
+    MethodCall {
+        receiver: ExprId,
+        method: String,
+        args: Vec<ExprId>,
+    },
+    /// Indexing into a value, e.g. `list[0]`
+    Index {
+        object: ExprId,
+        index: ExprId,
+    },
+    /// Converting a value to another type, e.g. `a as u64`
+    Cast {
+        value: ExprId,
+        ty: Type,
+    },
+    /// Accessing a field of a struct, e.g. `p.x`
+    Field {
+        object: ExprId,
+        field: String,
+    },
+    /// An assignment to a variable, field or element, e.g. `a = 1`,
+    /// `p.x += 1` or `list[0] = 2`
+    Assign {
+        /// The operator of a compound assignment, or `None` for `=`
+        op: Option<BinaryOp>,
+        target: ExprId,
+        value: ExprId,
+    },
+    /// `if let pattern = value { ... } else { ... }`, which evaluates to
+    /// the first block if the value matches the pattern, and to the second
+    /// otherwise. An `else if let` is an `else` block holding only another
+    /// `IfLet`.
+    IfLet {
+        pattern: Pattern,
+        value: ExprId,
+        then: Block,
+        otherwise: Option<Block>,
+    },
+    /// `match value { pattern => value, ... }`, which evaluates to the value
+    /// of the first arm whose pattern matches
+    Match {
+        value: ExprId,
+        arms: Vec<Arm>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An arm of a match, e.g. `Some(x) => x + 1`. The value can use the names
+/// the pattern binds.
+pub struct Arm {
+    pub pattern: Pattern,
+    pub value: ExprId,
+    /// From the start of the pattern to the end of the value
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What an `if let`, `while let` or match arm matches a value against.
+pub enum Pattern {
+    /// `_`, which matches anything without binding it
+    Wildcard,
+    /// A name, which matches anything and binds it. This could also be a
+    /// variant without a payload like `None`, which the parser can't tell
+    /// apart from a binding.
+    Binding(String),
+    Bool(bool),
+    Number(usize),
+    String(String),
+    /// `(a, _, 1)`
+    Tuple(Vec<Pattern>),
+    /// A variant of an enum, e.g. `Some(x)` or `Shape::Circle(p, _)`
+    Variant {
+        /// The enum the variant is in, if it was written
+        enum_name: Option<String>,
+        variant: String,
+        /// The patterns for the values the variant holds
+        payload: Vec<Pattern>,
+    },
+}
+
+impl Pattern {
+    /// The names the pattern binds, in the order they are written.
+    pub fn bindings(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+
+        self.collect_bindings(&mut names);
+
+        names
+    }
+
+    fn collect_bindings<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Pattern::Binding(name) => names.push(name),
+            Pattern::Tuple(patterns)
+            | Pattern::Variant {
+                payload: patterns, ..
+            } => {
+                for pattern in patterns {
+                    pattern.collect_bindings(names);
+                }
+            }
+            Pattern::Wildcard | Pattern::Bool(_) | Pattern::Number(_) | Pattern::String(_) => {}
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    /// Write the pattern the way it is written in the source.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Binding(name) => write!(f, "{}", name),
+            Pattern::Bool(value) => write!(f, "{}", value),
+            Pattern::Number(number) => write!(f, "{}", number),
+            Pattern::String(string) => write!(f, "{:?}", string),
+            // Like tuple types, a tuple of one pattern needs its comma
+            Pattern::Tuple(patterns) if patterns.len() == 1 => write!(f, "({},)", patterns[0]),
+            Pattern::Tuple(patterns) => write!(f, "({})", List(patterns)),
+            Pattern::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                if let Some(enum_name) = enum_name {
+                    write!(f, "{}::", enum_name)?;
+                }
+
+                write!(f, "{}", variant)?;
+
+                if payload.is_empty() {
+                    Ok(())
+                } else {
+                    write!(f, "({})", List(payload))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnaryOp {
+    Negate, // -
+    Not,    // !
+}
+
+impl UnaryOp {
+    /// The operator as it is written in the source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Negate => "-",
+            UnaryOp::Not => "!",
+        }
+    }
+
+    /// The method a struct or enum implements to overload the operator.
+    pub fn method(&self) -> &'static str {
+        match self {
+            UnaryOp::Negate => "neg",
+            UnaryOp::Not => "not",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BinaryOp {
+    Add,      // +
+    Subtract, // -
+    Multiply, // *
+    Divide,   // /
+    Modulo,   // %
+
+    Equal,        // ==
+    NotEqual,     // !=
+    Less,         // <
+    LessEqual,    // <=
+    Greater,      // >
+    GreaterEqual, // >=
+
+    And, // &&
+    Or,  // ||
+}
+
+impl BinaryOp {
+    /// The operator as it is written in the source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        }
+    }
+
+    /// The method a struct or enum implements to overload the operator.
+    /// `!=` is the opposite of `eq`, and `&&` and `||` can't be overloaded,
+    /// since they don't always evaluate their right side.
+    pub fn method(&self) -> Option<&'static str> {
+        match self {
+            BinaryOp::Add => Some("add"),
+            BinaryOp::Subtract => Some("sub"),
+            BinaryOp::Multiply => Some("mul"),
+            BinaryOp::Divide => Some("div"),
+            BinaryOp::Modulo => Some("rem"),
+            BinaryOp::Equal | BinaryOp::NotEqual => Some("eq"),
+            BinaryOp::Less => Some("lt"),
+            BinaryOp::LessEqual => Some("le"),
+            BinaryOp::Greater => Some("gt"),
+            BinaryOp::GreaterEqual => Some("ge"),
+            BinaryOp::And | BinaryOp::Or => None,
+        }
+    }
+}
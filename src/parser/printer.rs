@@ -0,0 +1,424 @@
+use super::ast::{
+    Arena, Assert, Block, ExprId, ExprKind, Function, ImportPath, Item, ItemKind, Let, Param, Stmt,
+    StmtKind, TypeParam,
+};
+use super::ParseResult;
+use crate::lexer::Span;
+
+/// Print a program as an indented tree, one node per line, for
+/// `--emit ast`. Nodes with a place in the source are followed by where they
+/// start and end, as byte offsets.
+///
+/// E.g. `let a := b + 1;` is printed as:
+///
+/// ```text
+/// Program
+///   Global a [0..15]
+///     Binary + [9..14]
+///       Identifier b [9..10]
+///       Number 1 [13..14]
+/// ```
+pub fn print_tree(parsed: &ParseResult) -> String {
+    let mut printer = Printer {
+        output: String::new(),
+        depth: 0,
+        arena: &parsed.arena,
+    };
+
+    printer.node("Program", |printer| {
+        for item in &parsed.program.items {
+            printer.item(item);
+        }
+    });
+
+    printer.output
+}
+
+struct Printer<'a> {
+    output: String,
+    /// How many levels the current node is nested
+    depth: usize,
+    /// Where the expressions being printed are
+    arena: &'a Arena,
+}
+
+impl Printer<'_> {
+    /// Write a line for a node, with its children indented under it.
+    fn node(&mut self, label: impl AsRef<str>, children: impl FnOnce(&mut Self)) {
+        self.output.push_str(&"  ".repeat(self.depth));
+        self.output.push_str(label.as_ref());
+        self.output.push('\n');
+
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    /// Write a line for a node without any children.
+    fn leaf(&mut self, label: impl AsRef<str>) {
+        self.node(label, |_| {});
+    }
+
+    fn item(&mut self, item: &Item) {
+        for attribute in &item.attributes {
+            let args: Vec<_> = attribute
+                .args
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect();
+
+            self.leaf(spanned(
+                format!("@{}({})", attribute.name, args.join(", ")),
+                attribute.span,
+            ));
+        }
+
+        if item.public {
+            self.node(spanned("Pub", item.span), |printer| {
+                printer.item_kind(&item.kind, item.span)
+            });
+        } else {
+            self.item_kind(&item.kind, item.span);
+        }
+    }
+
+    fn item_kind(&mut self, kind: &ItemKind, span: Span) {
+        match kind {
+            ItemKind::Function(function) => self.function(function, span),
+            ItemKind::Import(import) => {
+                let path = match &import.path {
+                    ImportPath::File(path) => format!("{:?}", path),
+                    ImportPath::Module(path) => path.join("::"),
+                };
+
+                match &import.names {
+                    Some(names) => self.leaf(spanned(
+                        format!("Import {}::{{{}}}", path, names.join(", ")),
+                        span,
+                    )),
+                    None => self.leaf(spanned(format!("Import {}", path), span)),
+                }
+            }
+            ItemKind::Global(decl) => self.declaration("Global", decl, span),
+            ItemKind::Const(decl) => self.declaration("Const", decl, span),
+            ItemKind::Struct(structure) => {
+                let name = generic(&structure.name, &structure.generics);
+
+                self.node(spanned(format!("Struct {}", name), span), |printer| {
+                    for field in &structure.fields {
+                        printer.leaf(format!("Field {} : {}", field.name, field.ty));
+                    }
+                });
+            }
+            ItemKind::StaticAssert(assert) => self.assertion("StaticAssert", assert, span),
+            ItemKind::Enum(enumeration) => {
+                let label = spanned(format!("Enum {}", enumeration.name), span);
+
+                self.node(label, |printer| {
+                    for variant in &enumeration.variants {
+                        if variant.payload.is_empty() {
+                            printer.leaf(format!("Variant {}", variant.name));
+                        } else {
+                            let payload: Vec<_> =
+                                variant.payload.iter().map(ToString::to_string).collect();
+
+                            printer.leaf(format!(
+                                "Variant {}({})",
+                                variant.name,
+                                payload.join(", ")
+                            ));
+                        }
+                    }
+                });
+            }
+            ItemKind::Trait(declaration) => {
+                let label = spanned(format!("Trait {}", declaration.name), span);
+
+                self.node(label, |printer| {
+                    for method in &declaration.methods {
+                        let label = match &method.return_type {
+                            Some(ty) => format!("Method {} : {}", method.name, ty),
+                            None => format!("Method {}", method.name),
+                        };
+
+                        printer.node(label, |printer| printer.params(&method.params));
+                    }
+                });
+            }
+            ItemKind::Impl(implementation) => {
+                let label = format!(
+                    "Impl {} for {}",
+                    implementation.trait_name, implementation.ty
+                );
+
+                self.node(spanned(label, span), |printer| {
+                    for method in &implementation.methods {
+                        printer.function(&method.function, method.span);
+                    }
+                });
+            }
+        }
+    }
+
+    fn function(&mut self, function: &Function, span: Span) {
+        let name = generic(&function.name, &function.generics);
+        let label = match &function.return_type {
+            Some(ty) => format!("Function {} : {}", name, ty),
+            None => format!("Function {}", name),
+        };
+
+        self.node(spanned(label, span), |printer| {
+            printer.params(&function.params);
+            printer.block(&function.body);
+        });
+    }
+
+    fn params(&mut self, params: &[Param]) {
+        for param in params {
+            let name = mutable(&param.name, param.mutable);
+
+            self.leaf(spanned(
+                format!("Param {} : {}", name, param.ty),
+                param.name_span,
+            ));
+        }
+    }
+
+    /// Write a variable or constant declaration at `span`, labelled with
+    /// `kind`.
+    fn declaration(&mut self, kind: &str, decl: &Let, span: Span) {
+        let name = mutable(&decl.name, decl.mutable);
+
+        let label = match &decl.ty {
+            Some(ty) => format!("{} {} : {}", kind, name, ty),
+            None => format!("{} {}", kind, name),
+        };
+
+        self.node(spanned(label, span), |printer| printer.expr(decl.value));
+    }
+
+    /// Write an assertion at `span`, labelled with `kind` and its message.
+    fn assertion(&mut self, kind: &str, assert: &Assert, span: Span) {
+        let label = match &assert.message {
+            Some(message) => format!("{} {:?}", kind, message),
+            None => kind.to_string(),
+        };
+
+        self.node(spanned(label, span), |printer| {
+            printer.expr(assert.condition)
+        });
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.node("Block", |printer| {
+            for stmt in &block.stmts {
+                printer.stmt(stmt);
+            }
+
+            if let Some(value) = &block.value {
+                printer.node("Value", |printer| printer.expr(*value));
+            }
+        });
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        let at = |label: &str| spanned(label, stmt.span);
+
+        match &stmt.kind {
+            StmtKind::Let(decl) => self.declaration("Let", decl, stmt.span),
+            StmtKind::Return(value) => self.node(at("Return"), |printer| {
+                if let Some(value) = value {
+                    printer.expr(*value);
+                }
+            }),
+            StmtKind::While { condition, body } => self.node(at("While"), |printer| {
+                printer.expr(*condition);
+                printer.block(body);
+            }),
+            StmtKind::WhileLet {
+                pattern,
+                value,
+                body,
+            } => self.node(at(&format!("WhileLet {}", pattern)), |printer| {
+                printer.expr(*value);
+                printer.block(body);
+            }),
+            StmtKind::Assert(assert) => self.assertion("Assert", assert, stmt.span),
+            StmtKind::StaticAssert(assert) => self.assertion("StaticAssert", assert, stmt.span),
+            StmtKind::Defer(body) => self.node(at("Defer"), |printer| printer.block(body)),
+            StmtKind::Break => self.leaf(at("Break")),
+            StmtKind::Continue => self.leaf(at("Continue")),
+            StmtKind::Expr(expr) => self.expr(*expr),
+        }
+    }
+
+    fn expr(&mut self, id: ExprId) {
+        let span = self.arena[id].span;
+        let at = |label: &str| spanned(label, span);
+
+        match &self.arena[id].kind {
+            ExprKind::Bool(value) => self.leaf(at(&format!("Bool {}", value))),
+            ExprKind::Number(number) => self.leaf(at(&format!("Number {}", number))),
+            ExprKind::String(string) => self.leaf(at(&format!("String {:?}", string))),
+            ExprKind::Identifier(name) => self.leaf(at(&format!("Identifier {}", name))),
+            ExprKind::Tuple(elements) => self.node(at("Tuple"), |printer| {
+                for element in elements {
+                    printer.expr(*element);
+                }
+            }),
+            ExprKind::Array(elements) => self.node(at("Array"), |printer| {
+                for element in elements {
+                    printer.expr(*element);
+                }
+            }),
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Unary { op, operand } => {
+                self.node(at(&format!("Unary {}", op.symbol())), |printer| {
+                    printer.expr(*operand)
+                });
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.node(at(&format!("Binary {}", op.symbol())), |printer| {
+                    printer.expr(*left);
+                    printer.expr(*right);
+                });
+            }
+            ExprKind::StructLiteral { name, fields } => {
+                self.node(at(&format!("StructLiteral {}", name)), |printer| {
+                    for (field, value) in fields {
+                        printer.node(format!("Field {}", field), |printer| printer.expr(*value));
+                    }
+                });
+            }
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                self.node(
+                    at(&format!("Variant {}::{}", enum_name, variant)),
+                    |printer| {
+                        for value in payload {
+                            printer.expr(*value);
+                        }
+                    },
+                );
+            }
+            ExprKind::Closure(closure) => {
+                let label = if closure.captures.is_empty() {
+                    at("Closure")
+                } else {
+                    at(&format!(
+                        "Closure capturing {}",
+                        closure.captures.join(", ")
+                    ))
+                };
+
+                self.node(label, |printer| {
+                    for param in &closure.params {
+                        match &param.ty {
+                            Some(ty) => printer.leaf(format!("Param {} : {}", param.name, ty)),
+                            None => printer.leaf(format!("Param {}", param.name)),
+                        }
+                    }
+
+                    printer.expr(closure.body);
+                });
+            }
+            ExprKind::Call { callee, args } => self.node(at("Call"), |printer| {
+                printer.expr(*callee);
+
+                for arg in args {
+                    printer.expr(*arg);
+                }
+            }),
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                self.node(at(&format!("MethodCall {}", method)), |printer| {
+                    printer.expr(*receiver);
+
+                    for arg in args {
+                        printer.expr(*arg);
+                    }
+                });
+            }
+            ExprKind::Index { object, index } => self.node(at("Index"), |printer| {
+                printer.expr(*object);
+                printer.expr(*index);
+            }),
+            ExprKind::Cast { value, ty } => {
+                self.node(at(&format!("Cast {}", ty)), |printer| printer.expr(*value));
+            }
+            ExprKind::Field { object, field } => {
+                self.node(at(&format!("Field {}", field)), |printer| {
+                    printer.expr(*object)
+                });
+            }
+            ExprKind::Assign { op, target, value } => {
+                let label = match op {
+                    Some(op) => at(&format!("Assign {}=", op.symbol())),
+                    None => at("Assign ="),
+                };
+
+                self.node(label, |printer| {
+                    printer.expr(*target);
+                    printer.expr(*value);
+                });
+            }
+            ExprKind::IfLet {
+                pattern,
+                value,
+                then,
+                otherwise,
+            } => {
+                self.node(at(&format!("IfLet {}", pattern)), |printer| {
+                    printer.expr(*value);
+                    printer.block(then);
+
+                    if let Some(otherwise) = otherwise {
+                        printer.node("Else", |printer| printer.block(otherwise));
+                    }
+                });
+            }
+            ExprKind::Match { value, arms } => self.node(at("Match"), |printer| {
+                printer.expr(*value);
+
+                for arm in arms {
+                    printer.node(
+                        spanned(format!("Arm {}", arm.pattern), arm.span),
+                        |printer| printer.expr(arm.value),
+                    );
+                }
+            }),
+        }
+    }
+}
+
+/// The name of a function or struct, followed by its type parameters if it
+/// has any, e.g. `Pair<A, B>` or `max<T : Ord>`.
+fn generic(name: &str, generics: &[TypeParam]) -> String {
+    if generics.is_empty() {
+        return name.to_string();
+    }
+
+    let generics: Vec<_> = generics.iter().map(ToString::to_string).collect();
+
+    format!("{}<{}>", name, generics.join(", "))
+}
+
+/// A name, with `mut` before it if it can be assigned to.
+fn mutable(name: &str, mutable: bool) -> String {
+    if mutable {
+        format!("mut {}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// A node's label, followed by where the node is in the source.
+fn spanned(label: impl AsRef<str>, span: Span) -> String {
+    format!("{} [{}..{}]", label.as_ref(), span.start, span.end)
+}
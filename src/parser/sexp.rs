@@ -0,0 +1,393 @@
+use super::ast::{
+    Arena, Assert, Block, ExprId, ExprKind, Function, ImportPath, Item, ItemKind, Let, Param,
+    Pattern, Stmt, StmtKind, Type, TypeParam,
+};
+use super::ParseResult;
+
+/// Write a program as s-expressions, one item per line.
+///
+/// The output only changes when the tree does, so it is meant for golden
+/// tests, where the JSON would be too long to review. E.g. `let x :=
+/// foo(5);` is written as `(global x (call (ident foo) (num 5)))`.
+pub fn program(parsed: &ParseResult) -> String {
+    parsed
+        .program
+        .items
+        .iter()
+        .map(|item| item_sexp(&parsed.arena, item) + "\n")
+        .collect()
+}
+
+/// Write an expression from an arena as an s-expression.
+pub fn expr(arena: &Arena, id: ExprId) -> String {
+    expr_sexp(arena, id)
+}
+
+/// Write a list of parts, e.g. `(call (ident foo) (num 5))`.
+fn list(parts: Vec<String>) -> String {
+    format!("({})", parts.join(" "))
+}
+
+/// A list starting with an atom, followed by the rest of the parts.
+fn tagged(tag: &str, rest: impl IntoIterator<Item = String>) -> String {
+    list(std::iter::once(tag.to_string()).chain(rest).collect())
+}
+
+fn item_sexp(arena: &Arena, item: &Item) -> String {
+    let sexp = item_kind_sexp(arena, &item.kind);
+
+    let sexp = if item.public {
+        tagged("pub", [sexp])
+    } else {
+        sexp
+    };
+
+    // The first attribute is the outermost, e.g. `(@allow (shadowing) (func
+    // ...))`
+    item.attributes.iter().rev().fold(sexp, |sexp, attribute| {
+        tagged(
+            &format!("@{}", attribute.name),
+            [
+                list(
+                    attribute
+                        .args
+                        .iter()
+                        .map(|(name, _)| name.clone())
+                        .collect(),
+                ),
+                sexp,
+            ],
+        )
+    })
+}
+
+fn item_kind_sexp(arena: &Arena, kind: &ItemKind) -> String {
+    match kind {
+        ItemKind::Function(function) => function_sexp(arena, function),
+        ItemKind::Import(import) => {
+            let path = match &import.path {
+                ImportPath::File(path) => format!("{:?}", path),
+                ImportPath::Module(path) => path.join("::"),
+            };
+
+            tagged(
+                "import",
+                std::iter::once(path).chain(import.names.as_ref().map(|names| list(names.clone()))),
+            )
+        }
+        ItemKind::Global(decl) => declaration_sexp(arena, "global", decl),
+        ItemKind::Const(decl) => declaration_sexp(arena, "const", decl),
+        ItemKind::Struct(structure) => tagged(
+            "struct",
+            std::iter::once(generic(&structure.name, &structure.generics)).chain(
+                structure
+                    .fields
+                    .iter()
+                    .map(|field| list(vec![field.name.clone(), type_sexp(&field.ty)])),
+            ),
+        ),
+        ItemKind::StaticAssert(assert) => assertion_sexp(arena, "static-assert", assert),
+        ItemKind::Enum(enumeration) => tagged(
+            "enum",
+            std::iter::once(enumeration.name.clone()).chain(enumeration.variants.iter().map(
+                |variant| {
+                    if variant.payload.is_empty() {
+                        variant.name.clone()
+                    } else {
+                        tagged(&variant.name, variant.payload.iter().map(type_sexp))
+                    }
+                },
+            )),
+        ),
+        ItemKind::Trait(declaration) => tagged(
+            "trait",
+            std::iter::once(declaration.name.clone()).chain(declaration.methods.iter().map(
+                |method| {
+                    tagged(
+                        &method.name,
+                        std::iter::once(params_sexp(&method.params))
+                            .chain(method.return_type.as_ref().map(type_sexp)),
+                    )
+                },
+            )),
+        ),
+        ItemKind::Impl(implementation) => tagged(
+            "impl",
+            [
+                implementation.trait_name.clone(),
+                type_sexp(&implementation.ty),
+            ]
+            .into_iter()
+            .chain(
+                implementation
+                    .methods
+                    .iter()
+                    .map(|method| function_sexp(arena, &method.function)),
+            ),
+        ),
+    }
+}
+
+fn function_sexp(arena: &Arena, function: &Function) -> String {
+    tagged(
+        "func",
+        [
+            generic(&function.name, &function.generics),
+            params_sexp(&function.params),
+        ]
+        .into_iter()
+        .chain(function.return_type.as_ref().map(type_sexp))
+        .chain([block_sexp(arena, &function.body)]),
+    )
+}
+
+/// Write the parameters of a function or method, e.g. `((a u32) (mut b
+/// str))`.
+fn params_sexp(params: &[Param]) -> String {
+    list(
+        params
+            .iter()
+            .map(|param| {
+                let mutable = param.mutable.then(|| "mut".to_string());
+
+                list(
+                    mutable
+                        .into_iter()
+                        .chain([param.name.clone(), type_sexp(&param.ty)])
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// The name of a function or struct, written like a generic type if it has
+/// type parameters, e.g. `(Pair A B)`. A parameter with bounds is written
+/// with them, e.g. `(max (T Ord))`.
+fn generic(name: &str, generics: &[TypeParam]) -> String {
+    if generics.is_empty() {
+        return name.to_string();
+    }
+
+    tagged(
+        name,
+        generics.iter().map(|param| {
+            if param.bounds.is_empty() {
+                param.name.clone()
+            } else {
+                tagged(&param.name, param.bounds.iter().cloned())
+            }
+        }),
+    )
+}
+
+/// Write a declaration, leaving out the type when there isn't one.
+fn declaration_sexp(arena: &Arena, tag: &str, decl: &Let) -> String {
+    let mutable = decl.mutable.then(|| "mut".to_string());
+
+    tagged(
+        tag,
+        mutable
+            .into_iter()
+            .chain([decl.name.clone()])
+            .chain(decl.ty.as_ref().map(type_sexp))
+            .chain([expr_sexp(arena, decl.value)]),
+    )
+}
+
+/// Write an assertion, leaving out the message when there isn't one.
+fn assertion_sexp(arena: &Arena, tag: &str, assert: &Assert) -> String {
+    tagged(
+        tag,
+        std::iter::once(expr_sexp(arena, assert.condition)).chain(
+            assert
+                .message
+                .as_ref()
+                .map(|message| format!("{:?}", message)),
+        ),
+    )
+}
+
+fn type_sexp(ty: &Type) -> String {
+    match ty {
+        Type::Named(name) => name.clone(),
+        Type::Generic { name, args } => tagged(name, args.iter().map(type_sexp)),
+        Type::Tuple(types) => tagged("tuple", types.iter().map(type_sexp)),
+        Type::Array { element, length } => tagged(
+            "array",
+            std::iter::once(type_sexp(element))
+                .chain(length.as_ref().map(|length| length.to_string())),
+        ),
+        Type::Optional(ty) => tagged("optional", [type_sexp(ty)]),
+        Type::Function {
+            params,
+            return_type,
+        } => tagged(
+            "func",
+            std::iter::once(list(params.iter().map(type_sexp).collect()))
+                .chain(return_type.as_deref().map(type_sexp)),
+        ),
+    }
+}
+
+fn block_sexp(arena: &Arena, block: &Block) -> String {
+    tagged(
+        "block",
+        block.stmts.iter().map(|stmt| stmt_sexp(arena, stmt)).chain(
+            block
+                .value
+                .map(|value| tagged("value", [expr_sexp(arena, value)])),
+        ),
+    )
+}
+
+fn stmt_sexp(arena: &Arena, stmt: &Stmt) -> String {
+    match &stmt.kind {
+        StmtKind::Let(decl) => declaration_sexp(arena, "let", decl),
+        StmtKind::Return(value) => tagged("return", value.map(|value| expr_sexp(arena, value))),
+        StmtKind::While { condition, body } => tagged(
+            "while",
+            [expr_sexp(arena, *condition), block_sexp(arena, body)],
+        ),
+        StmtKind::WhileLet {
+            pattern,
+            value,
+            body,
+        } => tagged(
+            "while-let",
+            [
+                pattern_sexp(pattern),
+                expr_sexp(arena, *value),
+                block_sexp(arena, body),
+            ],
+        ),
+        StmtKind::Assert(assert) => assertion_sexp(arena, "assert", assert),
+        StmtKind::StaticAssert(assert) => assertion_sexp(arena, "static-assert", assert),
+        StmtKind::Defer(body) => tagged("defer", [block_sexp(arena, body)]),
+        StmtKind::Break => "(break)".to_string(),
+        StmtKind::Continue => "(continue)".to_string(),
+        StmtKind::Expr(expr) => expr_sexp(arena, *expr),
+    }
+}
+
+fn expr_sexp(arena: &Arena, id: ExprId) -> String {
+    let sexp = |id: &ExprId| expr_sexp(arena, *id);
+
+    match &arena[id].kind {
+        ExprKind::Bool(value) => value.to_string(),
+        ExprKind::Number(number) => tagged("num", [number.to_string()]),
+        ExprKind::String(string) => tagged("str", [format!("{:?}", string)]),
+        ExprKind::Identifier(name) => tagged("ident", [name.clone()]),
+        ExprKind::Tuple(elements) => tagged("tuple", elements.iter().map(sexp)),
+        ExprKind::Array(elements) => tagged("array", elements.iter().map(sexp)),
+        ExprKind::Block(block) => block_sexp(arena, block),
+        ExprKind::Unary { op, operand } => tagged(op.symbol(), [sexp(operand)]),
+        ExprKind::Binary { op, left, right } => tagged(op.symbol(), [sexp(left), sexp(right)]),
+        ExprKind::StructLiteral { name, fields } => tagged(
+            "struct-lit",
+            std::iter::once(name.clone()).chain(
+                fields
+                    .iter()
+                    .map(|(field, value)| list(vec![field.clone(), sexp(value)])),
+            ),
+        ),
+        ExprKind::Variant {
+            enum_name,
+            variant,
+            payload,
+        } => tagged(
+            "variant",
+            [enum_name.clone(), variant.clone()]
+                .into_iter()
+                .chain(payload.iter().map(sexp)),
+        ),
+        ExprKind::Closure(closure) => {
+            let params = closure
+                .params
+                .iter()
+                .map(|param| match &param.ty {
+                    Some(ty) => list(vec![param.name.clone(), type_sexp(ty)]),
+                    None => param.name.clone(),
+                })
+                .collect();
+
+            tagged(
+                "closure",
+                [
+                    list(params),
+                    tagged("captures", closure.captures.iter().cloned()),
+                    sexp(&closure.body),
+                ],
+            )
+        }
+        ExprKind::Call { callee, args } => tagged(
+            "call",
+            std::iter::once(sexp(callee)).chain(args.iter().map(sexp)),
+        ),
+        ExprKind::MethodCall {
+            receiver,
+            method,
+            args,
+        } => tagged(
+            "method-call",
+            [sexp(receiver), method.clone()]
+                .into_iter()
+                .chain(args.iter().map(sexp)),
+        ),
+        ExprKind::Index { object, index } => tagged("index", [sexp(object), sexp(index)]),
+        ExprKind::Cast { value, ty } => tagged("as", [sexp(value), type_sexp(ty)]),
+        ExprKind::Field { object, field } => tagged("field", [sexp(object), field.clone()]),
+        ExprKind::Assign { op, target, value } => {
+            let op = match op {
+                Some(op) => format!("{}=", op.symbol()),
+                None => "=".to_string(),
+            };
+
+            tagged(&op, [sexp(target), sexp(value)])
+        }
+        ExprKind::IfLet {
+            pattern,
+            value,
+            then,
+            otherwise,
+        } => tagged(
+            "if-let",
+            [pattern_sexp(pattern), sexp(value), block_sexp(arena, then)]
+                .into_iter()
+                .chain(otherwise.as_ref().map(|block| block_sexp(arena, block))),
+        ),
+        ExprKind::Match { value, arms } => tagged(
+            "match",
+            std::iter::once(sexp(value)).chain(
+                arms.iter()
+                    .map(|arm| tagged("arm", [pattern_sexp(&arm.pattern), sexp(&arm.value)])),
+            ),
+        ),
+    }
+}
+
+fn pattern_sexp(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Wildcard => "_".to_string(),
+        Pattern::Binding(name) => name.clone(),
+        Pattern::Bool(value) => value.to_string(),
+        Pattern::Number(number) => tagged("num", [number.to_string()]),
+        Pattern::String(string) => tagged("str", [format!("{:?}", string)]),
+        Pattern::Tuple(patterns) => tagged("tuple", patterns.iter().map(pattern_sexp)),
+        Pattern::Variant {
+            enum_name,
+            variant,
+            payload,
+        } => {
+            let path = match enum_name {
+                Some(enum_name) => format!("{}::{}", enum_name, variant),
+                None => variant.clone(),
+            };
+
+            tagged(
+                "variant",
+                std::iter::once(path).chain(payload.iter().map(pattern_sexp)),
+            )
+        }
+    }
+}
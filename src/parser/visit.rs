@@ -0,0 +1,766 @@
+use super::ast::{
+    Arena, Assert, Block, Closure, ClosureParam, Enum, ExprId, ExprKind, Field, Function, Impl,
+    Import, Item, ItemKind, Let, Param, Pattern, Signature, Stmt, StmtKind, Struct, Trait, Type,
+    Variant,
+};
+use super::ParseResult;
+
+/// Visits the nodes of the AST by reference, so passes only have to handle
+/// the nodes they care about.
+///
+/// Every `visit_*` method calls the matching `walk_*` function by default,
+/// which visits the children of the node. A pass overrides the methods for
+/// the nodes it needs, and calls `walk_*` itself to keep going deeper.
+///
+/// Expressions are visited by their `ExprId`, so passes can record what they
+/// find about each one. The arena they are in is passed along to every node
+/// that can contain expressions.
+pub trait Visitor {
+    fn visit_program(&mut self, parsed: &ParseResult) {
+        walk_program(self, parsed);
+    }
+
+    fn visit_item(&mut self, arena: &Arena, item: &Item) {
+        walk_item(self, arena, item);
+    }
+
+    /// Imports have no children.
+    fn visit_import(&mut self, _import: &Import) {}
+
+    fn visit_function(&mut self, arena: &Arena, function: &Function) {
+        walk_function(self, arena, function);
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        walk_param(self, param);
+    }
+
+    fn visit_struct(&mut self, structure: &Struct) {
+        walk_struct(self, structure);
+    }
+
+    fn visit_field(&mut self, field: &Field) {
+        walk_field(self, field);
+    }
+
+    fn visit_enum(&mut self, enumeration: &Enum) {
+        walk_enum(self, enumeration);
+    }
+
+    fn visit_variant(&mut self, variant: &Variant) {
+        walk_variant(self, variant);
+    }
+
+    fn visit_trait(&mut self, declaration: &Trait) {
+        walk_trait(self, declaration);
+    }
+
+    /// A method required by a trait.
+    fn visit_signature(&mut self, signature: &Signature) {
+        walk_signature(self, signature);
+    }
+
+    /// The methods of an impl are visited as functions.
+    fn visit_impl(&mut self, arena: &Arena, implementation: &Impl) {
+        walk_impl(self, arena, implementation);
+    }
+
+    /// A variable or constant declaration, anywhere in the program.
+    fn visit_let(&mut self, arena: &Arena, decl: &Let) {
+        walk_let(self, arena, decl);
+    }
+
+    /// A runtime or static assertion, anywhere in the program.
+    fn visit_assert(&mut self, arena: &Arena, assert: &Assert) {
+        walk_assert(self, arena, assert);
+    }
+
+    fn visit_block(&mut self, arena: &Arena, block: &Block) {
+        walk_block(self, arena, block);
+    }
+
+    fn visit_stmt(&mut self, arena: &Arena, stmt: &Stmt) {
+        walk_stmt(self, arena, stmt);
+    }
+
+    fn visit_expr(&mut self, arena: &Arena, id: ExprId) {
+        walk_expr(self, arena, id);
+    }
+
+    fn visit_closure(&mut self, arena: &Arena, closure: &Closure) {
+        walk_closure(self, arena, closure);
+    }
+
+    fn visit_closure_param(&mut self, param: &ClosureParam) {
+        walk_closure_param(self, param);
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        walk_type(self, ty);
+    }
+
+    /// The pattern of an `if let` or `while let`.
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, parsed: &ParseResult) {
+    for item in &parsed.program.items {
+        visitor.visit_item(&parsed.arena, item);
+    }
+}
+
+pub fn walk_item<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, item: &Item) {
+    match &item.kind {
+        ItemKind::Function(function) => visitor.visit_function(arena, function),
+        ItemKind::Import(import) => visitor.visit_import(import),
+        ItemKind::Global(decl) | ItemKind::Const(decl) => visitor.visit_let(arena, decl),
+        ItemKind::Struct(structure) => visitor.visit_struct(structure),
+        ItemKind::Enum(enumeration) => visitor.visit_enum(enumeration),
+        ItemKind::StaticAssert(assert) => visitor.visit_assert(arena, assert),
+        ItemKind::Trait(declaration) => visitor.visit_trait(declaration),
+        ItemKind::Impl(implementation) => visitor.visit_impl(arena, implementation),
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, function: &Function) {
+    for param in &function.params {
+        visitor.visit_param(param);
+    }
+
+    if let Some(ty) = &function.return_type {
+        visitor.visit_type(ty);
+    }
+
+    visitor.visit_block(arena, &function.body);
+}
+
+pub fn walk_param<V: Visitor + ?Sized>(visitor: &mut V, param: &Param) {
+    visitor.visit_type(&param.ty);
+}
+
+pub fn walk_struct<V: Visitor + ?Sized>(visitor: &mut V, structure: &Struct) {
+    for field in &structure.fields {
+        visitor.visit_field(field);
+    }
+}
+
+pub fn walk_field<V: Visitor + ?Sized>(visitor: &mut V, field: &Field) {
+    visitor.visit_type(&field.ty);
+}
+
+pub fn walk_enum<V: Visitor + ?Sized>(visitor: &mut V, enumeration: &Enum) {
+    for variant in &enumeration.variants {
+        visitor.visit_variant(variant);
+    }
+}
+
+pub fn walk_variant<V: Visitor + ?Sized>(visitor: &mut V, variant: &Variant) {
+    for ty in &variant.payload {
+        visitor.visit_type(ty);
+    }
+}
+
+pub fn walk_trait<V: Visitor + ?Sized>(visitor: &mut V, declaration: &Trait) {
+    for signature in &declaration.methods {
+        visitor.visit_signature(signature);
+    }
+}
+
+pub fn walk_signature<V: Visitor + ?Sized>(visitor: &mut V, signature: &Signature) {
+    for param in &signature.params {
+        visitor.visit_param(param);
+    }
+
+    if let Some(ty) = &signature.return_type {
+        visitor.visit_type(ty);
+    }
+}
+
+pub fn walk_impl<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, implementation: &Impl) {
+    visitor.visit_type(&implementation.ty);
+
+    for method in &implementation.methods {
+        visitor.visit_function(arena, &method.function);
+    }
+}
+
+pub fn walk_let<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, decl: &Let) {
+    if let Some(ty) = &decl.ty {
+        visitor.visit_type(ty);
+    }
+
+    visitor.visit_expr(arena, decl.value);
+}
+
+pub fn walk_assert<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, assert: &Assert) {
+    visitor.visit_expr(arena, assert.condition);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, block: &Block) {
+    for stmt in &block.stmts {
+        visitor.visit_stmt(arena, stmt);
+    }
+
+    if let Some(value) = &block.value {
+        visitor.visit_expr(arena, *value);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::Let(decl) => visitor.visit_let(arena, decl),
+        StmtKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(arena, *value);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            visitor.visit_expr(arena, *condition);
+            visitor.visit_block(arena, body);
+        }
+        StmtKind::WhileLet {
+            pattern,
+            value,
+            body,
+        } => {
+            visitor.visit_pattern(pattern);
+            visitor.visit_expr(arena, *value);
+            visitor.visit_block(arena, body);
+        }
+        StmtKind::Assert(assert) | StmtKind::StaticAssert(assert) => {
+            visitor.visit_assert(arena, assert)
+        }
+        StmtKind::Defer(body) => visitor.visit_block(arena, body),
+        StmtKind::Break | StmtKind::Continue => {}
+        StmtKind::Expr(expr) => visitor.visit_expr(arena, *expr),
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, id: ExprId) {
+    match &arena[id].kind {
+        ExprKind::Bool(_) | ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Identifier(_) => {
+        }
+        ExprKind::Tuple(elements) | ExprKind::Array(elements) => {
+            for element in elements {
+                visitor.visit_expr(arena, *element);
+            }
+        }
+        ExprKind::Block(block) => visitor.visit_block(arena, block),
+        ExprKind::Unary { operand, .. } => visitor.visit_expr(arena, *operand),
+        ExprKind::Binary { left, right, .. } => {
+            visitor.visit_expr(arena, *left);
+            visitor.visit_expr(arena, *right);
+        }
+        ExprKind::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expr(arena, *value);
+            }
+        }
+        ExprKind::Variant { payload, .. } => {
+            for value in payload {
+                visitor.visit_expr(arena, *value);
+            }
+        }
+        ExprKind::Closure(closure) => visitor.visit_closure(arena, closure),
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr(arena, *callee);
+
+            for arg in args {
+                visitor.visit_expr(arena, *arg);
+            }
+        }
+        ExprKind::MethodCall { receiver, args, .. } => {
+            visitor.visit_expr(arena, *receiver);
+
+            for arg in args {
+                visitor.visit_expr(arena, *arg);
+            }
+        }
+        ExprKind::Index { object, index } => {
+            visitor.visit_expr(arena, *object);
+            visitor.visit_expr(arena, *index);
+        }
+        ExprKind::Cast { value, ty } => {
+            visitor.visit_expr(arena, *value);
+            visitor.visit_type(ty);
+        }
+        ExprKind::Field { object, .. } => visitor.visit_expr(arena, *object),
+        ExprKind::Assign { target, value, .. } => {
+            visitor.visit_expr(arena, *target);
+            visitor.visit_expr(arena, *value);
+        }
+        ExprKind::IfLet {
+            pattern,
+            value,
+            then,
+            otherwise,
+        } => {
+            visitor.visit_pattern(pattern);
+            visitor.visit_expr(arena, *value);
+            visitor.visit_block(arena, then);
+
+            if let Some(otherwise) = otherwise {
+                visitor.visit_block(arena, otherwise);
+            }
+        }
+        ExprKind::Match { value, arms } => {
+            visitor.visit_expr(arena, *value);
+
+            for arm in arms {
+                visitor.visit_pattern(&arm.pattern);
+                visitor.visit_expr(arena, arm.value);
+            }
+        }
+    }
+}
+
+pub fn walk_closure<V: Visitor + ?Sized>(visitor: &mut V, arena: &Arena, closure: &Closure) {
+    for param in &closure.params {
+        visitor.visit_closure_param(param);
+    }
+
+    visitor.visit_expr(arena, closure.body);
+}
+
+pub fn walk_closure_param<V: Visitor + ?Sized>(visitor: &mut V, param: &ClosureParam) {
+    if let Some(ty) = &param.ty {
+        visitor.visit_type(ty);
+    }
+}
+
+pub fn walk_type<V: Visitor + ?Sized>(visitor: &mut V, ty: &Type) {
+    match ty {
+        Type::Named(_) => {}
+        Type::Generic { args, .. } => {
+            for arg in args {
+                visitor.visit_type(arg);
+            }
+        }
+        Type::Tuple(types) => {
+            for ty in types {
+                visitor.visit_type(ty);
+            }
+        }
+        Type::Array { element, .. } => visitor.visit_type(element),
+        Type::Optional(ty) => visitor.visit_type(ty),
+        Type::Function {
+            params,
+            return_type,
+        } => {
+            for param in params {
+                visitor.visit_type(param);
+            }
+
+            if let Some(ty) = return_type {
+                visitor.visit_type(ty);
+            }
+        }
+    }
+}
+
+pub fn walk_pattern<V: Visitor + ?Sized>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Wildcard
+        | Pattern::Binding(_)
+        | Pattern::Bool(_)
+        | Pattern::Number(_)
+        | Pattern::String(_) => {}
+        Pattern::Tuple(patterns)
+        | Pattern::Variant {
+            payload: patterns, ..
+        } => {
+            for pattern in patterns {
+                visitor.visit_pattern(pattern);
+            }
+        }
+    }
+}
+
+/// Visits the nodes of the AST mutably, so passes can rewrite the tree in
+/// place, e.g. to fold constants. This works the same way as `Visitor`, and
+/// an expression is rewritten by assigning to `arena[id].kind`.
+pub trait VisitMut {
+    fn visit_program_mut(&mut self, parsed: &mut ParseResult) {
+        walk_program_mut(self, parsed);
+    }
+
+    fn visit_item_mut(&mut self, arena: &mut Arena, item: &mut Item) {
+        walk_item_mut(self, arena, item);
+    }
+
+    /// Imports have no children.
+    fn visit_import_mut(&mut self, _import: &mut Import) {}
+
+    fn visit_function_mut(&mut self, arena: &mut Arena, function: &mut Function) {
+        walk_function_mut(self, arena, function);
+    }
+
+    fn visit_param_mut(&mut self, param: &mut Param) {
+        walk_param_mut(self, param);
+    }
+
+    fn visit_struct_mut(&mut self, structure: &mut Struct) {
+        walk_struct_mut(self, structure);
+    }
+
+    fn visit_field_mut(&mut self, field: &mut Field) {
+        walk_field_mut(self, field);
+    }
+
+    fn visit_enum_mut(&mut self, enumeration: &mut Enum) {
+        walk_enum_mut(self, enumeration);
+    }
+
+    fn visit_variant_mut(&mut self, variant: &mut Variant) {
+        walk_variant_mut(self, variant);
+    }
+
+    fn visit_trait_mut(&mut self, declaration: &mut Trait) {
+        walk_trait_mut(self, declaration);
+    }
+
+    fn visit_signature_mut(&mut self, signature: &mut Signature) {
+        walk_signature_mut(self, signature);
+    }
+
+    fn visit_impl_mut(&mut self, arena: &mut Arena, implementation: &mut Impl) {
+        walk_impl_mut(self, arena, implementation);
+    }
+
+    /// A variable or constant declaration, anywhere in the program.
+    fn visit_let_mut(&mut self, arena: &mut Arena, decl: &mut Let) {
+        walk_let_mut(self, arena, decl);
+    }
+
+    fn visit_assert_mut(&mut self, arena: &mut Arena, assert: &mut Assert) {
+        walk_assert_mut(self, arena, assert);
+    }
+
+    fn visit_block_mut(&mut self, arena: &mut Arena, block: &mut Block) {
+        walk_block_mut(self, arena, block);
+    }
+
+    fn visit_stmt_mut(&mut self, arena: &mut Arena, stmt: &mut Stmt) {
+        walk_stmt_mut(self, arena, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, arena: &mut Arena, id: ExprId) {
+        walk_expr_mut(self, arena, id);
+    }
+
+    fn visit_closure_mut(&mut self, arena: &mut Arena, closure: &mut Closure) {
+        walk_closure_mut(self, arena, closure);
+    }
+
+    fn visit_closure_param_mut(&mut self, param: &mut ClosureParam) {
+        walk_closure_param_mut(self, param);
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        walk_type_mut(self, ty);
+    }
+
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        walk_pattern_mut(self, pattern);
+    }
+}
+
+pub fn walk_program_mut<V: VisitMut + ?Sized>(visitor: &mut V, parsed: &mut ParseResult) {
+    let ParseResult { program, arena } = parsed;
+
+    for item in &mut program.items {
+        visitor.visit_item_mut(arena, item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitMut + ?Sized>(visitor: &mut V, arena: &mut Arena, item: &mut Item) {
+    match &mut item.kind {
+        ItemKind::Function(function) => visitor.visit_function_mut(arena, function),
+        ItemKind::Import(import) => visitor.visit_import_mut(import),
+        ItemKind::Global(decl) | ItemKind::Const(decl) => visitor.visit_let_mut(arena, decl),
+        ItemKind::Struct(structure) => visitor.visit_struct_mut(structure),
+        ItemKind::Enum(enumeration) => visitor.visit_enum_mut(enumeration),
+        ItemKind::StaticAssert(assert) => visitor.visit_assert_mut(arena, assert),
+        ItemKind::Trait(declaration) => visitor.visit_trait_mut(declaration),
+        ItemKind::Impl(implementation) => visitor.visit_impl_mut(arena, implementation),
+    }
+}
+
+pub fn walk_function_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    arena: &mut Arena,
+    function: &mut Function,
+) {
+    for param in &mut function.params {
+        visitor.visit_param_mut(param);
+    }
+
+    if let Some(ty) = &mut function.return_type {
+        visitor.visit_type_mut(ty);
+    }
+
+    visitor.visit_block_mut(arena, &mut function.body);
+}
+
+pub fn walk_param_mut<V: VisitMut + ?Sized>(visitor: &mut V, param: &mut Param) {
+    visitor.visit_type_mut(&mut param.ty);
+}
+
+pub fn walk_struct_mut<V: VisitMut + ?Sized>(visitor: &mut V, structure: &mut Struct) {
+    for field in &mut structure.fields {
+        visitor.visit_field_mut(field);
+    }
+}
+
+pub fn walk_field_mut<V: VisitMut + ?Sized>(visitor: &mut V, field: &mut Field) {
+    visitor.visit_type_mut(&mut field.ty);
+}
+
+pub fn walk_enum_mut<V: VisitMut + ?Sized>(visitor: &mut V, enumeration: &mut Enum) {
+    for variant in &mut enumeration.variants {
+        visitor.visit_variant_mut(variant);
+    }
+}
+
+pub fn walk_variant_mut<V: VisitMut + ?Sized>(visitor: &mut V, variant: &mut Variant) {
+    for ty in &mut variant.payload {
+        visitor.visit_type_mut(ty);
+    }
+}
+
+pub fn walk_trait_mut<V: VisitMut + ?Sized>(visitor: &mut V, declaration: &mut Trait) {
+    for signature in &mut declaration.methods {
+        visitor.visit_signature_mut(signature);
+    }
+}
+
+pub fn walk_signature_mut<V: VisitMut + ?Sized>(visitor: &mut V, signature: &mut Signature) {
+    for param in &mut signature.params {
+        visitor.visit_param_mut(param);
+    }
+
+    if let Some(ty) = &mut signature.return_type {
+        visitor.visit_type_mut(ty);
+    }
+}
+
+pub fn walk_impl_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    arena: &mut Arena,
+    implementation: &mut Impl,
+) {
+    visitor.visit_type_mut(&mut implementation.ty);
+
+    for method in &mut implementation.methods {
+        visitor.visit_function_mut(arena, &mut method.function);
+    }
+}
+
+pub fn walk_let_mut<V: VisitMut + ?Sized>(visitor: &mut V, arena: &mut Arena, decl: &mut Let) {
+    if let Some(ty) = &mut decl.ty {
+        visitor.visit_type_mut(ty);
+    }
+
+    visitor.visit_expr_mut(arena, decl.value);
+}
+
+pub fn walk_assert_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    arena: &mut Arena,
+    assert: &mut Assert,
+) {
+    visitor.visit_expr_mut(arena, assert.condition);
+}
+
+pub fn walk_block_mut<V: VisitMut + ?Sized>(visitor: &mut V, arena: &mut Arena, block: &mut Block) {
+    for stmt in &mut block.stmts {
+        visitor.visit_stmt_mut(arena, stmt);
+    }
+
+    if let Some(value) = &mut block.value {
+        visitor.visit_expr_mut(arena, *value);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitMut + ?Sized>(visitor: &mut V, arena: &mut Arena, stmt: &mut Stmt) {
+    match &mut stmt.kind {
+        StmtKind::Let(decl) => visitor.visit_let_mut(arena, decl),
+        StmtKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr_mut(arena, *value);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            visitor.visit_expr_mut(arena, *condition);
+            visitor.visit_block_mut(arena, body);
+        }
+        StmtKind::WhileLet {
+            pattern,
+            value,
+            body,
+        } => {
+            visitor.visit_pattern_mut(pattern);
+            visitor.visit_expr_mut(arena, *value);
+            visitor.visit_block_mut(arena, body);
+        }
+        StmtKind::Assert(assert) | StmtKind::StaticAssert(assert) => {
+            visitor.visit_assert_mut(arena, assert)
+        }
+        StmtKind::Defer(body) => visitor.visit_block_mut(arena, body),
+        StmtKind::Break | StmtKind::Continue => {}
+        StmtKind::Expr(expr) => visitor.visit_expr_mut(arena, *expr),
+    }
+}
+
+pub fn walk_expr_mut<V: VisitMut + ?Sized>(visitor: &mut V, arena: &mut Arena, id: ExprId) {
+    // The expression is taken out of the arena while its children are
+    // visited, so blocks and closures in it can be borrowed along with the
+    // arena. An empty tuple takes its place in the meantime.
+    let mut kind = std::mem::replace(&mut arena[id].kind, ExprKind::Tuple(Vec::new()));
+
+    match &mut kind {
+        ExprKind::Bool(_) | ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Identifier(_) => {
+        }
+        ExprKind::Tuple(elements) | ExprKind::Array(elements) => {
+            for element in elements {
+                visitor.visit_expr_mut(arena, *element);
+            }
+        }
+        ExprKind::Block(block) => visitor.visit_block_mut(arena, block),
+        ExprKind::Unary { operand, .. } => visitor.visit_expr_mut(arena, *operand),
+        ExprKind::Binary { left, right, .. } => {
+            visitor.visit_expr_mut(arena, *left);
+            visitor.visit_expr_mut(arena, *right);
+        }
+        ExprKind::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expr_mut(arena, *value);
+            }
+        }
+        ExprKind::Variant { payload, .. } => {
+            for value in payload {
+                visitor.visit_expr_mut(arena, *value);
+            }
+        }
+        ExprKind::Closure(closure) => visitor.visit_closure_mut(arena, closure),
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr_mut(arena, *callee);
+
+            for arg in args {
+                visitor.visit_expr_mut(arena, *arg);
+            }
+        }
+        ExprKind::MethodCall { receiver, args, .. } => {
+            visitor.visit_expr_mut(arena, *receiver);
+
+            for arg in args {
+                visitor.visit_expr_mut(arena, *arg);
+            }
+        }
+        ExprKind::Index { object, index } => {
+            visitor.visit_expr_mut(arena, *object);
+            visitor.visit_expr_mut(arena, *index);
+        }
+        ExprKind::Cast { value, ty } => {
+            visitor.visit_expr_mut(arena, *value);
+            visitor.visit_type_mut(ty);
+        }
+        ExprKind::Field { object, .. } => visitor.visit_expr_mut(arena, *object),
+        ExprKind::Assign { target, value, .. } => {
+            visitor.visit_expr_mut(arena, *target);
+            visitor.visit_expr_mut(arena, *value);
+        }
+        ExprKind::IfLet {
+            pattern,
+            value,
+            then,
+            otherwise,
+        } => {
+            visitor.visit_pattern_mut(pattern);
+            visitor.visit_expr_mut(arena, *value);
+            visitor.visit_block_mut(arena, then);
+
+            if let Some(otherwise) = otherwise {
+                visitor.visit_block_mut(arena, otherwise);
+            }
+        }
+        ExprKind::Match { value, arms } => {
+            visitor.visit_expr_mut(arena, *value);
+
+            for arm in arms {
+                visitor.visit_pattern_mut(&mut arm.pattern);
+                visitor.visit_expr_mut(arena, arm.value);
+            }
+        }
+    }
+
+    arena[id].kind = kind;
+}
+
+pub fn walk_closure_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    arena: &mut Arena,
+    closure: &mut Closure,
+) {
+    for param in &mut closure.params {
+        visitor.visit_closure_param_mut(param);
+    }
+
+    visitor.visit_expr_mut(arena, closure.body);
+}
+
+pub fn walk_closure_param_mut<V: VisitMut + ?Sized>(visitor: &mut V, param: &mut ClosureParam) {
+    if let Some(ty) = &mut param.ty {
+        visitor.visit_type_mut(ty);
+    }
+}
+
+pub fn walk_type_mut<V: VisitMut + ?Sized>(visitor: &mut V, ty: &mut Type) {
+    match ty {
+        Type::Named(_) => {}
+        Type::Generic { args, .. } => {
+            for arg in args {
+                visitor.visit_type_mut(arg);
+            }
+        }
+        Type::Tuple(types) => {
+            for ty in types {
+                visitor.visit_type_mut(ty);
+            }
+        }
+        Type::Array { element, .. } => visitor.visit_type_mut(element),
+        Type::Optional(ty) => visitor.visit_type_mut(ty),
+        Type::Function {
+            params,
+            return_type,
+        } => {
+            for param in params {
+                visitor.visit_type_mut(param);
+            }
+
+            if let Some(ty) = return_type {
+                visitor.visit_type_mut(ty);
+            }
+        }
+    }
+}
+
+pub fn walk_pattern_mut<V: VisitMut + ?Sized>(visitor: &mut V, pattern: &mut Pattern) {
+    match pattern {
+        Pattern::Wildcard
+        | Pattern::Binding(_)
+        | Pattern::Bool(_)
+        | Pattern::Number(_)
+        | Pattern::String(_) => {}
+        Pattern::Tuple(patterns)
+        | Pattern::Variant {
+            payload: patterns, ..
+        } => {
+            for pattern in patterns {
+                visitor.visit_pattern_mut(pattern);
+            }
+        }
+    }
+}
@@ -0,0 +1,827 @@
+//! Name resolution, which binds every name used in a program to where it is
+//! declared.
+//!
+//! Items can be used anywhere in the file they are declared in, before or
+//! after their declaration. Parameters and variables can only be used after
+//! they are declared, until the end of the function, closure or block they
+//! are declared in.
+//!
+//! A name can only be declared once in each scope, so two items, two
+//! parameters of a function or two variables in a block can't share a name.
+//! Names from outer scopes can be shadowed though, e.g. a variable in a
+//! function body can have the name of a parameter or an item, and one in a
+//! nested block the name of a variable outside of it.
+//!
+//! Other modules can only use the items declared `pub`, which
+//! `Resolution::export` checks.
+use crate::lexer::Span;
+use crate::parser::ast::{
+    Arena, Block, Closure, ExprId, ExprKind, Function, Impl, ImportPath, Item, ItemKind, Let,
+    Pattern, Stmt, StmtKind,
+};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::ParseResult;
+use crate::suggest::{self, Suggestion};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Where a symbol is in the `Resolution` it was declared in.
+pub struct SymbolId(u32);
+
+impl SymbolId {
+    /// The position of the symbol, in the order they were declared.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Where a scope is in the `Resolution` it was built in.
+pub struct ScopeId(u32);
+
+impl ScopeId {
+    /// The position of the scope, in the order they were opened.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What declared a symbol.
+pub enum SymbolKind {
+    Function,
+    Global,
+    Const,
+    Struct,
+    Enum,
+    Trait,
+    /// A name brought in by an import, e.g. `print` from `import "std.print";`
+    /// or `sin` from `import math::{sin, cos};`
+    Import,
+    /// A parameter of a function
+    Param,
+    /// A parameter of a closure
+    ClosureParam,
+    /// A variable declared in a function
+    Local,
+    /// A name bound by the pattern of an `if let` or `while let`
+    Binding,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Something a name can refer to.
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The item, statement or expression that declares the symbol
+    pub span: Span,
+    /// Whether the symbol can be assigned to, e.g. `a` in `let mut a := 1;`
+    pub mutable: bool,
+    /// Whether the symbol is an item declared `pub`, which other modules
+    /// can use
+    pub public: bool,
+    /// The scope the symbol is declared in
+    pub scope: ScopeId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScopeKind {
+    /// The items of a file
+    Module,
+    /// The parameters of a function
+    Function,
+    /// The parameters of a closure
+    Closure,
+    /// The statements of a block, or the bindings of an `if let` or
+    /// `while let` around their block
+    Block,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scope {
+    pub kind: ScopeKind,
+    /// The scope this one is nested in, or `None` for the module
+    pub parent: Option<ScopeId>,
+    /// The symbols declared in the scope, in order
+    pub symbols: Vec<SymbolId>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The scopes and symbols of a program, and what each name in it refers to.
+pub struct Resolution {
+    pub symbols: Vec<Symbol>,
+    pub scopes: Vec<Scope>,
+    /// The symbol each identifier, struct literal and enum variant refers
+    /// to, by the expression it is used in
+    pub uses: HashMap<ExprId, SymbolId>,
+    /// Where the name of each global, variable and parameter is written,
+    /// inside of the node declaring it
+    pub names: HashMap<SymbolId, Span>,
+}
+
+impl Resolution {
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.index()]
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.index()]
+    }
+
+    /// The symbol a name in an expression refers to, e.g. for going to its
+    /// definition.
+    pub fn resolved(&self, expr: ExprId) -> Option<&Symbol> {
+        self.uses.get(&expr).map(|&id| self.symbol(id))
+    }
+
+    /// The item named `name` in this module, for another module using it at
+    /// `span`. Only items declared `pub` can be used from other modules, so
+    /// the others are errors naming `module`, the module they are private
+    /// to.
+    pub fn export(
+        &self,
+        name: &str,
+        module: &str,
+        span: Span,
+    ) -> Result<SymbolId, Box<ResolveError>> {
+        let items = self.scopes.first().map_or(&[][..], |scope| &scope.symbols);
+
+        let Some(&id) = items
+            .iter()
+            .find(|&&symbol| self.symbol(symbol).name == name)
+        else {
+            let exported = items
+                .iter()
+                .map(|&symbol| self.symbol(symbol))
+                .filter(|symbol| symbol.public)
+                .map(|symbol| symbol.name.as_str());
+
+            return Err(Box::new(ResolveError::new(
+                ResolveErrorKind::Undefined {
+                    name: name.to_string(),
+                    suggestion: suggest::closest(name, exported).map(str::to_string),
+                },
+                span,
+            )));
+        };
+
+        let symbol = self.symbol(id);
+
+        if symbol.public {
+            return Ok(id);
+        }
+
+        Err(Box::new(
+            ResolveError::new(
+                ResolveErrorKind::Private {
+                    name: name.to_string(),
+                    module: module.to_string(),
+                },
+                span,
+            )
+            .declared_at(symbol.span)
+            .with_suggestion(Suggestion::insert(
+                format!("Add 'pub' to make '{}' usable from other modules", name),
+                symbol.span.start,
+                "pub ",
+            )),
+        ))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolveErrorKind {
+    /// A name was used that isn't declared anywhere it can be seen from.
+    Undefined {
+        name: String,
+        /// A name that can be seen from there which is close enough that it
+        /// was probably meant instead
+        suggestion: Option<String>,
+    },
+    /// A name was used as something it isn't, e.g. a function as a struct.
+    NotA {
+        name: String,
+        /// What the name had to be, e.g. "a struct"
+        expected: String,
+    },
+    /// A name was declared twice in the same scope.
+    Duplicate { name: String },
+    /// Something was assigned to that can't be, e.g. a variable declared
+    /// without `mut`.
+    Immutable { name: String },
+    /// An item of another module was used that isn't declared `pub`.
+    Private {
+        name: String,
+        /// The module that declares the item
+        module: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An error that occurred while resolving names.
+pub struct ResolveError {
+    pub kind: ResolveErrorKind,
+    span: Span,
+    declared: Option<Span>,
+    suggestion: Option<Suggestion>,
+}
+
+impl ResolveError {
+    pub fn new(kind: ResolveErrorKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            declared: None,
+            suggestion: None,
+        }
+    }
+
+    /// Suggest a fix for the error.
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestion = Some(suggestion);
+        self
+    }
+
+    /// Point at the declaration the error is about too, e.g. the first
+    /// declaration of a name that was declared twice.
+    pub fn declared_at(mut self, span: Span) -> Self {
+        self.declared = Some(span);
+        self
+    }
+
+    /// A stable code identifying the kind of error, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            ResolveErrorKind::Undefined { .. } => "E0201",
+            ResolveErrorKind::NotA { .. } => "E0202",
+            ResolveErrorKind::Duplicate { .. } => "E0203",
+            ResolveErrorKind::Immutable { .. } => "E0204",
+            ResolveErrorKind::Private { .. } => "E0205",
+        }
+    }
+
+    /// The code that caused the error.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The declaration the error is about, if there is one.
+    pub fn declared(&self) -> Option<Span> {
+        self.declared
+    }
+
+    /// A fix for the error, if there is one that is sure to work.
+    pub fn suggestion(&self) -> Option<&Suggestion> {
+        self.suggestion.as_ref()
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ResolveErrorKind::Undefined { name, .. } => {
+                format!("Can't find '{}' in this scope", name)
+            }
+            ResolveErrorKind::NotA { name, expected } => {
+                format!("'{}' isn't {}", name, expected)
+            }
+            ResolveErrorKind::Duplicate { name } => {
+                format!("'{}' is already declared in this scope", name)
+            }
+            ResolveErrorKind::Immutable { name } => {
+                format!("Can't assign to '{}', since it isn't mutable", name)
+            }
+            ResolveErrorKind::Private { name, module } => {
+                format!("'{}' is private to the module '{}'", name, module)
+            }
+        }
+    }
+
+    /// What might fix the error, if there is a likely fix.
+    pub fn help(&self) -> Option<String> {
+        match &self.kind {
+            ResolveErrorKind::Undefined {
+                suggestion: Some(suggestion),
+                ..
+            } => Some(format!("Did you mean '{}'?", suggestion)),
+            _ => self
+                .suggestion
+                .as_ref()
+                .map(|suggestion| format!("{}.", suggestion.message)),
+        }
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.", self.message())?;
+
+        if let Some(help) = self.help() {
+            write!(f, " {}", help)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// Builds the `Resolution` of a parsed program.
+pub struct Resolver {
+    resolution: Resolution,
+    /// The scopes the current node is inside of, innermost last
+    open: Vec<ScopeId>,
+    /// The item, statement or expression being resolved, which declares
+    /// any symbols found in it
+    declaring: Span,
+    errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+    /// Resolve every name in a program. If any can't be resolved, every
+    /// error that was found is returned.
+    pub fn resolve(parsed: &ParseResult) -> Result<Resolution, Vec<ResolveError>> {
+        let mut resolver = Resolver {
+            resolution: Resolution::default(),
+            open: Vec::new(),
+            declaring: Span::default(),
+            errors: Vec::new(),
+        };
+
+        resolver.visit_program(parsed);
+
+        if resolver.errors.is_empty() {
+            Ok(resolver.resolution)
+        } else {
+            Err(resolver.errors)
+        }
+    }
+
+    /// Open a scope inside of the current one, until `close` is called.
+    fn open(&mut self, kind: ScopeKind) {
+        let id = ScopeId(self.resolution.scopes.len() as u32);
+
+        self.resolution.scopes.push(Scope {
+            kind,
+            parent: self.open.last().copied(),
+            symbols: Vec::new(),
+        });
+        self.open.push(id);
+    }
+
+    fn close(&mut self) {
+        self.open.pop();
+    }
+
+    /// Declare a symbol in the current scope, declared by the node being
+    /// resolved. A name that is already declared in the scope is an error.
+    fn declare(&mut self, name: &str, kind: SymbolKind) {
+        let Some(&scope) = self.open.last() else {
+            return;
+        };
+
+        let previous = self
+            .resolution
+            .scope(scope)
+            .symbols
+            .iter()
+            .map(|&symbol| self.resolution.symbol(symbol))
+            .find(|symbol| symbol.name == name);
+
+        if let Some(previous) = previous {
+            let error = ResolveError::new(
+                ResolveErrorKind::Duplicate {
+                    name: name.to_string(),
+                },
+                self.declaring,
+            )
+            .declared_at(previous.span);
+
+            self.errors.push(error);
+
+            return;
+        }
+
+        let id = SymbolId(self.resolution.symbols.len() as u32);
+
+        self.resolution.symbols.push(Symbol {
+            name: name.to_string(),
+            kind,
+            span: self.declaring,
+            mutable: false,
+            public: false,
+            scope,
+        });
+        self.resolution.scopes[scope.index()].symbols.push(id);
+    }
+
+    /// Declare a global, variable or parameter, whose name is at
+    /// `name_span`.
+    fn declare_variable(&mut self, name: &str, kind: SymbolKind, mutable: bool, name_span: Span) {
+        let id = SymbolId(self.resolution.symbols.len() as u32);
+
+        self.declare(name, kind);
+
+        // It wasn't declared if it was a duplicate
+        if id.index() == self.resolution.symbols.len() {
+            return;
+        }
+
+        self.resolution.symbols[id.index()].mutable = mutable;
+        self.resolution.names.insert(id, name_span);
+    }
+
+    /// Check that the target of an assignment can be assigned to. Assigning
+    /// to a field or element assigns to the variable it is in.
+    fn check_assignment(&mut self, arena: &Arena, target: ExprId) {
+        let mut root = target;
+
+        while let ExprKind::Field { object, .. } | ExprKind::Index { object, .. } =
+            &arena[root].kind
+        {
+            root = *object;
+        }
+
+        let Some(&id) = self.resolution.uses.get(&root) else {
+            return;
+        };
+
+        let symbol = self.resolution.symbol(id);
+
+        // Imported names could be anything
+        if symbol.mutable || symbol.kind == SymbolKind::Import {
+            return;
+        }
+
+        let error = ResolveError::new(
+            ResolveErrorKind::Immutable {
+                name: symbol.name.clone(),
+            },
+            arena[target].span,
+        );
+
+        // Variables and parameters can be made mutable, but other items
+        // can't be assigned to at all
+        let error = match self.resolution.names.get(&id) {
+            Some(name) => error.declared_at(*name).with_suggestion(Suggestion::insert(
+                format!("Add 'mut' to make '{}' mutable", symbol.name),
+                name.start,
+                "mut ",
+            )),
+            None => error.declared_at(symbol.span),
+        };
+
+        self.errors.push(error);
+    }
+
+    /// The symbol a name refers to from the current scope. Inner scopes
+    /// shadow outer ones.
+    fn lookup(&self, name: &str) -> Option<SymbolId> {
+        self.open.iter().rev().find_map(|scope| {
+            self.resolution
+                .scope(*scope)
+                .symbols
+                .iter()
+                .rev()
+                .find(|&&symbol| self.resolution.symbol(symbol).name == name)
+                .copied()
+        })
+    }
+
+    /// Record what the name used by an expression refers to. If it has to be
+    /// a certain kind of symbol, `expected` is the kind and how to describe
+    /// it, e.g. "a struct".
+    fn use_name(
+        &mut self,
+        arena: &Arena,
+        id: ExprId,
+        name: &str,
+        expected: Option<(SymbolKind, &str)>,
+    ) {
+        let span = arena[id].span;
+
+        // A path into another module, e.g. `shapes::Point`, is resolved by
+        // the module it starts with until the modules are merged
+        let symbol = self.lookup(name).or_else(|| {
+            let (module, _) = name.split_once("::")?;
+
+            self.lookup(module)
+                .filter(|&symbol| self.resolution.symbol(symbol).kind == SymbolKind::Import)
+        });
+
+        let Some(symbol) = symbol else {
+            let suggestion = self.suggest(name, expected.map(|(kind, _)| kind));
+
+            self.errors.push(ResolveError::new(
+                ResolveErrorKind::Undefined {
+                    name: name.to_string(),
+                    suggestion,
+                },
+                span,
+            ));
+
+            return;
+        };
+
+        let kind = self.resolution.symbol(symbol).kind;
+
+        // Imported names could be anything until the files they are from
+        // are resolved too
+        let mismatch =
+            expected.filter(|(expected, _)| kind != *expected && kind != SymbolKind::Import);
+
+        if let Some((_, expected)) = mismatch {
+            self.errors.push(ResolveError::new(
+                ResolveErrorKind::NotA {
+                    name: name.to_string(),
+                    expected: expected.to_string(),
+                },
+                span,
+            ));
+
+            return;
+        }
+
+        self.resolution.uses.insert(id, symbol);
+    }
+
+    /// The name that can be seen from the current scope which is most likely
+    /// what a misspelled name was meant to be, of the kind of symbol it had
+    /// to be if it had to be one. Names in inner scopes are preferred.
+    fn suggest(&self, name: &str, expected: Option<SymbolKind>) -> Option<String> {
+        let visible = self.open.iter().rev().flat_map(|scope| {
+            self.resolution
+                .scope(*scope)
+                .symbols
+                .iter()
+                .rev()
+                .map(|&symbol| self.resolution.symbol(symbol))
+        });
+
+        let candidates = visible
+            .filter(|symbol| {
+                expected.is_none_or(|kind| symbol.kind == kind || symbol.kind == SymbolKind::Import)
+            })
+            .map(|symbol| symbol.name.as_str());
+
+        suggest::closest(name, candidates).map(str::to_string)
+    }
+
+    /// Declare the names a pattern binds in the current scope.
+    fn declare_bindings(&mut self, pattern: &Pattern) {
+        for name in pattern.bindings() {
+            self.declare(name, SymbolKind::Binding);
+        }
+    }
+}
+
+/// The names an import brings into scope.
+fn imported_names(path: &ImportPath, names: Option<&Vec<String>>) -> Vec<String> {
+    if let Some(names) = names {
+        return names.clone();
+    }
+
+    match path {
+        // `import "std.print";` brings in `print`, and `import "lib/math.pg";`
+        // brings in `math`
+        ImportPath::File(file) => {
+            let file = file.strip_suffix(".pg").unwrap_or(file);
+            let name = file.rsplit(['.', '/', '\\']).next().unwrap_or(file);
+
+            vec![name.to_string()]
+        }
+        // `import std::io;` brings in `io`
+        ImportPath::Module(path) => path.last().cloned().into_iter().collect(),
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_program(&mut self, parsed: &ParseResult) {
+        self.open(ScopeKind::Module);
+
+        // Items can be used before they are declared, so they are all
+        // declared first
+        for item in &parsed.program.items {
+            self.declaring = item.span;
+
+            let first = self.resolution.symbols.len();
+
+            match &item.kind {
+                ItemKind::Function(function) => self.declare(&function.name, SymbolKind::Function),
+                ItemKind::Import(import) => {
+                    for name in imported_names(&import.path, import.names.as_ref()) {
+                        self.declare(&name, SymbolKind::Import);
+                    }
+                }
+                ItemKind::Global(decl) => self.declare_variable(
+                    &decl.name,
+                    SymbolKind::Global,
+                    decl.mutable,
+                    decl.name_span,
+                ),
+                ItemKind::Const(decl) => self.declare(&decl.name, SymbolKind::Const),
+                ItemKind::Struct(structure) => self.declare(&structure.name, SymbolKind::Struct),
+                ItemKind::Enum(enumeration) => self.declare(&enumeration.name, SymbolKind::Enum),
+                ItemKind::Trait(declaration) => self.declare(&declaration.name, SymbolKind::Trait),
+                ItemKind::StaticAssert(_) | ItemKind::Impl(_) => {}
+            }
+
+            for symbol in &mut self.resolution.symbols[first..] {
+                symbol.public = item.public;
+            }
+        }
+
+        visit::walk_program(self, parsed);
+
+        self.close();
+    }
+
+    fn visit_item(&mut self, arena: &Arena, item: &Item) {
+        self.declaring = item.span;
+
+        match &item.kind {
+            // Globals and constants were already declared, unlike variables
+            ItemKind::Global(decl) | ItemKind::Const(decl) => self.visit_expr(arena, decl.value),
+            _ => visit::walk_item(self, arena, item),
+        }
+    }
+
+    fn visit_function(&mut self, arena: &Arena, function: &Function) {
+        self.open(ScopeKind::Function);
+
+        for (i, param) in function.params.iter().enumerate() {
+            // Parameters are all declared by their function, so one declared
+            // twice is pointed out by its name
+            if let Some(first) = function.params[..i]
+                .iter()
+                .find(|first| first.name == param.name)
+            {
+                let error = ResolveError::new(
+                    ResolveErrorKind::Duplicate {
+                        name: param.name.clone(),
+                    },
+                    param.name_span,
+                )
+                .declared_at(first.name_span);
+
+                self.errors.push(error);
+
+                continue;
+            }
+
+            self.declare_variable(
+                &param.name,
+                SymbolKind::Param,
+                param.mutable,
+                param.name_span,
+            );
+        }
+
+        self.visit_block(arena, &function.body);
+
+        self.close();
+    }
+
+    /// Each method declares its own parameters. Methods aren't in any scope,
+    /// since they are only called with `.`, but an impl can't have two with
+    /// the same name.
+    fn visit_impl(&mut self, arena: &Arena, implementation: &Impl) {
+        let mut declared: HashMap<&str, Span> = HashMap::new();
+
+        for method in &implementation.methods {
+            let name = method.function.name.as_str();
+
+            if let Some(&previous) = declared.get(name) {
+                let error = ResolveError::new(
+                    ResolveErrorKind::Duplicate {
+                        name: name.to_string(),
+                    },
+                    method.span,
+                )
+                .declared_at(previous);
+
+                self.errors.push(error);
+            } else {
+                declared.insert(name, method.span);
+            }
+
+            self.declaring = method.span;
+            self.visit_function(arena, &method.function);
+        }
+    }
+
+    fn visit_block(&mut self, arena: &Arena, block: &Block) {
+        self.open(ScopeKind::Block);
+
+        visit::walk_block(self, arena, block);
+
+        self.close();
+    }
+
+    fn visit_stmt(&mut self, arena: &Arena, stmt: &Stmt) {
+        self.declaring = stmt.span;
+
+        match &stmt.kind {
+            StmtKind::WhileLet {
+                pattern,
+                value,
+                body,
+            } => {
+                self.visit_expr(arena, *value);
+
+                self.open(ScopeKind::Block);
+                self.declaring = stmt.span;
+                self.declare_bindings(pattern);
+                self.visit_block(arena, body);
+                self.close();
+            }
+            _ => visit::walk_stmt(self, arena, stmt),
+        }
+    }
+
+    /// A variable is declared after its value, so `let a := a;` uses an
+    /// earlier `a`.
+    fn visit_let(&mut self, arena: &Arena, decl: &Let) {
+        let declaring = self.declaring;
+
+        visit::walk_let(self, arena, decl);
+
+        self.declaring = declaring;
+        self.declare_variable(&decl.name, SymbolKind::Local, decl.mutable, decl.name_span);
+    }
+
+    fn visit_expr(&mut self, arena: &Arena, id: ExprId) {
+        match &arena[id].kind {
+            ExprKind::Identifier(name) => self.use_name(arena, id, name, None),
+            ExprKind::StructLiteral { name, .. } => {
+                self.use_name(arena, id, name, Some((SymbolKind::Struct, "a struct")));
+
+                visit::walk_expr(self, arena, id);
+            }
+            ExprKind::Variant { enum_name, .. } => {
+                self.use_name(arena, id, enum_name, Some((SymbolKind::Enum, "an enum")));
+
+                visit::walk_expr(self, arena, id);
+            }
+            ExprKind::Assign { target, .. } => {
+                visit::walk_expr(self, arena, id);
+
+                self.check_assignment(arena, *target);
+            }
+            ExprKind::Closure(closure) => {
+                self.declaring = arena[id].span;
+
+                self.visit_closure(arena, closure);
+            }
+            ExprKind::IfLet {
+                pattern,
+                value,
+                then,
+                otherwise,
+            } => {
+                self.visit_expr(arena, *value);
+
+                self.open(ScopeKind::Block);
+                self.declaring = arena[id].span;
+                self.declare_bindings(pattern);
+                self.visit_block(arena, then);
+                self.close();
+
+                if let Some(otherwise) = otherwise {
+                    self.visit_block(arena, otherwise);
+                }
+            }
+            ExprKind::Match { value, arms } => {
+                self.visit_expr(arena, *value);
+
+                for arm in arms {
+                    self.open(ScopeKind::Block);
+                    self.declaring = arm.span;
+                    self.declare_bindings(&arm.pattern);
+                    self.visit_expr(arena, arm.value);
+                    self.close();
+                }
+            }
+            _ => visit::walk_expr(self, arena, id),
+        }
+    }
+
+    fn visit_closure(&mut self, arena: &Arena, closure: &Closure) {
+        self.open(ScopeKind::Closure);
+
+        for param in &closure.params {
+            self.declare(&param.name, SymbolKind::ClosureParam);
+        }
+
+        self.visit_expr(arena, closure.body);
+
+        self.close();
+    }
+}
@@ -0,0 +1,172 @@
+//! Caching the name resolution of modules, so a program whose modules
+//! haven't changed since it was last compiled doesn't resolve them again.
+//!
+//! Each module is cached in a file of its own in the cache directory,
+//! `target/penguin/` by default, named by a hash of the module's path. A
+//! cache is keyed by the source of the module and the sources of the modules
+//! it imports, so changing a file only resolves it and the modules importing
+//! it again.
+//!
+//! A module's expressions and spans depend on where it is in the arena and
+//! source map, which can change when other modules do, so what is cached is
+//! relative to where the module starts in both.
+use crate::lexer::{LexerOptions, Span};
+use crate::parser::ast::ExprId;
+use crate::resolve::Resolution;
+use crate::source_map::hash_source;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Written at the start of every cache, so other files aren't mistaken for
+/// one. The last byte is the version of the format, which has to change when
+/// the way it is stored does.
+const MAGIC: [u8; 4] = *b"PGR\x01";
+
+/// The version of the compiler, which is stored in every cache too, since
+/// resolution can change between versions even when the format doesn't.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The directory caches are kept in, relative to where the compiler is run.
+pub const DIRECTORY: &str = "target/penguin";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+/// What is stored in a cache after the magic bytes.
+struct ResolveCache {
+    version: String,
+    key: u64,
+    /// The options the module was lexed with, as they can change what it
+    /// parses to
+    options: LexerOptions,
+    /// The number of expressions in the module
+    exprs: usize,
+    resolution: Resolution,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Where a module starts, which what is cached about it is relative to.
+pub struct Origin {
+    /// Where the file of the module starts in the source map
+    pub offset: usize,
+    /// The first expression of the module in the arena
+    pub expr: usize,
+}
+
+/// The key a module is cached by, from its source and the sources of the
+/// modules it imports, in order.
+pub fn key(source: &str, imports: &[&str]) -> u64 {
+    imports.iter().fold(hash_source(source), |key, import| {
+        (key ^ hash_source(import)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Where the cache for a module is kept in the cache directory.
+///
+/// E.g. `src/utils.pg` is cached in `target/penguin/<hash>.resolve`, the
+/// hash being of its path.
+pub fn path_for(directory: &Path, module: &Path) -> PathBuf {
+    let hash = hash_source(&module.to_string_lossy());
+
+    directory.join(format!("{:016x}.resolve", hash))
+}
+
+/// Write the resolution of a module starting at `origin`, with `exprs`
+/// expressions, to a cache.
+pub fn write(
+    key: u64,
+    options: &LexerOptions,
+    resolution: &Resolution,
+    origin: Origin,
+    exprs: usize,
+    mut writer: impl Write,
+) -> bincode::Result<()> {
+    let cache = ResolveCache {
+        version: VERSION.to_string(),
+        key,
+        options: options.clone(),
+        exprs,
+        resolution: relocate(
+            resolution.clone(),
+            |offset| offset.wrapping_sub(origin.offset),
+            |index| index - origin.expr,
+        ),
+    };
+
+    writer.write_all(&MAGIC)?;
+
+    bincode::serialize_into(writer, &cache)
+}
+
+/// Read the resolution of a module starting at `origin`, with `exprs`
+/// expressions, from a cache. Returns `None` if the cache was made from
+/// different sources or options, or by another version of the compiler, in
+/// which case the module needs to be resolved again.
+pub fn read(
+    key: u64,
+    options: &LexerOptions,
+    origin: Origin,
+    exprs: usize,
+    mut reader: impl Read,
+) -> bincode::Result<Option<Resolution>> {
+    let mut magic = [0; 4];
+
+    reader.read_exact(&mut magic)?;
+
+    if magic != MAGIC {
+        return Ok(None);
+    }
+
+    let cache: ResolveCache = bincode::deserialize_from(reader)?;
+
+    if cache.version != VERSION
+        || cache.key != key
+        || cache.options != *options
+        || cache.exprs != exprs
+    {
+        return Ok(None);
+    }
+
+    // A use of an expression or symbol that isn't there means the cache is
+    // corrupt, so it is treated the same as a stale one
+    let symbols = cache.resolution.symbols.len();
+    let fits = cache
+        .resolution
+        .uses
+        .iter()
+        .all(|(expr, symbol)| expr.index() < exprs && symbol.index() < symbols);
+
+    if !fits {
+        return Ok(None);
+    }
+
+    Ok(Some(relocate(
+        cache.resolution,
+        |offset| offset.wrapping_add(origin.offset),
+        |index| index + origin.expr,
+    )))
+}
+
+/// Move the spans and expressions a resolution refers to, by moving each
+/// offset and the position of each expression.
+fn relocate(
+    mut resolution: Resolution,
+    offset: impl Fn(usize) -> usize,
+    expr: impl Fn(usize) -> usize,
+) -> Resolution {
+    let span = |span: Span| Span::new(offset(span.start), offset(span.end));
+
+    for symbol in &mut resolution.symbols {
+        symbol.span = span(symbol.span);
+    }
+
+    for name in resolution.names.values_mut() {
+        *name = span(*name);
+    }
+
+    resolution.uses = resolution
+        .uses
+        .into_iter()
+        .map(|(id, symbol)| (ExprId::from_index(expr(id.index())), symbol))
+        .collect();
+
+    resolution
+}
@@ -0,0 +1,158 @@
+//! The host side of the builtins programs call, like `print`, shared by
+//! every way of running a program in the compiler so they behave the same.
+//!
+//! The virtual machine calls `print` directly. Native code compiled into
+//! memory calls the functions `symbols` gives instead, which have the names
+//! and signatures of the C library functions object files are linked with,
+//! so the same code can be compiled to either. Both write to the output a
+//! program is run with.
+//!
+//! A program that fails at runtime prints its error and calls `exit`, which
+//! unwinds back to `with_output` instead of ending the compiler's process.
+use std::cell::Cell;
+use std::ffi::{c_char, CStr};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+
+thread_local! {
+    /// Where native code run by `with_output` writes what it prints.
+    static OUTPUT: Cell<Option<*mut (dyn Write + 'static)>> = const { Cell::new(None) };
+}
+
+/// Write what a program prints. A program isn't stopped because its output
+/// can't be written.
+pub fn write(output: &mut dyn Write, bytes: &[u8]) {
+    let _ = output.write_all(bytes);
+}
+
+/// Print a line of values, separated by spaces.
+pub fn print(output: &mut dyn Write, values: &[String]) {
+    write(output, format!("{}\n", values.join(" ")).as_bytes());
+}
+
+/// The code native code called `exit` with, which is unwound to
+/// `with_output` as a panic's payload.
+struct Exit(i32);
+
+/// Run native code that prints to `output`, through the functions
+/// `symbols` gives, returning what it returns, or the code it called `exit`
+/// with as an error.
+pub fn with_output<T>(output: &mut dyn Write, run: impl FnOnce() -> T) -> Result<T, i32> {
+    let output: *mut (dyn Write + '_) = output;
+    // SAFETY: the pointer is only used while `run` runs, when `output` is
+    // still borrowed, and is taken away before it returns
+    let output: *mut (dyn Write + 'static) = unsafe { std::mem::transmute(output) };
+
+    let previous = OUTPUT.replace(Some(output));
+    let result = panic::catch_unwind(AssertUnwindSafe(run));
+
+    OUTPUT.set(previous);
+
+    match result {
+        Ok(value) => Ok(value),
+        Err(payload) => match payload.downcast::<Exit>() {
+            Ok(exit) => Err(exit.0),
+            Err(payload) => panic::resume_unwind(payload),
+        },
+    }
+}
+
+/// Write to the output native code is run with, or stdout if it isn't run
+/// by `with_output`.
+fn write_output(bytes: &[u8]) {
+    match OUTPUT.get() {
+        // SAFETY: `with_output` only sets outputs that are borrowed until it
+        // returns
+        Some(output) => write(unsafe { &mut *output }, bytes),
+        None => write(&mut io::stdout(), bytes),
+    }
+}
+
+/// The functions native code calls for builtins, by the names it calls
+/// them by.
+pub fn symbols() -> [(&'static str, *const u8); 2] {
+    [("printf", printf as *const u8), ("exit", exit as *const u8)]
+}
+
+/// `printf`, as native code calls it: always with a number and a pointer
+/// after the format, which are only used if it has conversions using them.
+#[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+unsafe extern "C" fn printf(format: *const c_char, number: i64, pointer: *const u8) -> i32 {
+    unsafe { print_formatted(format, number, pointer) }
+}
+
+/// `printf`, as native code calls it on macOS on AArch64, where what comes
+/// after the format is passed on the stack, after as many arguments as are
+/// passed in registers.
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn printf(
+    format: *const c_char,
+    _: i64,
+    _: i64,
+    _: i64,
+    _: i64,
+    _: i64,
+    _: i64,
+    _: i64,
+    number: i64,
+    pointer: *const u8,
+) -> i32 {
+    unsafe { print_formatted(format, number, pointer) }
+}
+
+/// Print text with the format of `printf`, returning how many bytes were
+/// printed. Only the conversions code generators write are understood:
+/// `%lld`, `%llu`, `%.*s` and `%%`.
+unsafe fn print_formatted(format: *const c_char, number: i64, pointer: *const u8) -> i32 {
+    const CONVERSIONS: [&[u8]; 4] = [b"lld", b"llu", b".*s", b"%"];
+
+    // SAFETY: native code passes formats ending in a zero byte
+    let format = unsafe { CStr::from_ptr(format) }.to_bytes();
+    let mut text = Vec::new();
+    let mut rest = format;
+
+    while let Some(position) = rest.iter().position(|&byte| byte == b'%') {
+        text.extend_from_slice(&rest[..position]);
+        rest = &rest[position + 1..];
+
+        let conversion = CONVERSIONS
+            .into_iter()
+            .find(|&conversion| rest.starts_with(conversion))
+            .unwrap_or_default();
+
+        match conversion {
+            b"lld" => text.extend_from_slice(number.to_string().as_bytes()),
+            b"llu" => text.extend_from_slice((number as u64).to_string().as_bytes()),
+            // SAFETY: strings are passed with their length
+            b".*s" => text
+                .extend_from_slice(unsafe { std::slice::from_raw_parts(pointer, number as usize) }),
+            _ => text.push(b'%'),
+        }
+
+        rest = &rest[conversion.len()..];
+    }
+
+    text.extend_from_slice(rest);
+    write_output(&text);
+
+    text.len() as i32
+}
+
+/// `exit`, which native code calls once it has printed a runtime error.
+/// What was printed is flushed, and the code is unwound to `with_output`,
+/// through the native code, which has unwind tables.
+extern "C-unwind" fn exit(code: i32) -> ! {
+    match OUTPUT.get() {
+        // SAFETY: `with_output` only sets outputs that are borrowed until it
+        // returns
+        Some(output) => {
+            let _ = unsafe { &mut *output }.flush();
+        }
+        None => {
+            let _ = io::stdout().flush();
+        }
+    }
+
+    panic::resume_unwind(Box::new(Exit(code)))
+}
@@ -0,0 +1,186 @@
+use crate::lexer::{Lexer, Source, Span};
+use std::io;
+use std::path::Path;
+
+/// A stable hash of source code, to check if a cache made from it is still
+/// valid. This is 64-bit FNV-1a, since the standard library's hasher may
+/// change between releases.
+pub fn hash_source(source: &str) -> u64 {
+    source.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Identifies a file that has been added to a `SourceMap`.
+pub struct FileId(usize);
+
+impl FileId {
+    /// The position of the file in the map it was added to.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+/// A source file owned by a `SourceMap`, along with where each of its lines
+/// start so byte offsets can be turned into lines and columns.
+pub struct SourceFile {
+    pub id: FileId,
+    /// Where the file starts in its map. Each file has its own range of
+    /// offsets, so a span from any of them identifies the file it is in.
+    start: usize,
+    source: Source,
+    /// The byte offset of the start of each line, the first always being 0
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(id: FileId, start: usize, source: Source) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.contents.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            id,
+            start,
+            source,
+            line_starts,
+        }
+    }
+
+    /// The offset the file starts at in its map, which spans in it have to
+    /// be shifted by. The first file starts at 0.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The name of the file.
+    pub fn name(&self) -> &str {
+        &self.source.name
+    }
+
+    /// The contents of the file.
+    pub fn contents(&self) -> &str {
+        &self.source.contents
+    }
+
+    /// The number of lines in the file.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The line and column of a byte offset, both starting at 1. Columns are
+    /// counted in characters. Offsets past the end of the file are clamped to
+    /// the end.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.contents().len());
+
+        // The line is the last one starting at or before the offset
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let column = self
+            .contents()
+            .get(start..offset)
+            .map_or(offset - start, |prefix| prefix.chars().count());
+
+        (line + 1, column + 1)
+    }
+
+    /// The text of a line, starting at 1, without its line ending.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.contents().len());
+
+        let text = self.contents().get(start..end)?;
+
+        Some(text.trim_end_matches('\n').trim_end_matches('\r'))
+    }
+
+    /// The source code a span covers.
+    pub fn slice(&self, span: Span) -> Option<&str> {
+        self.contents().get(span.start..span.end)
+    }
+
+    /// Create a lexer over the contents of this file.
+    pub fn lexer(&self) -> Lexer<'_> {
+        self.source.lexer()
+    }
+}
+
+#[derive(Debug, Default)]
+/// Owns every file that is part of a compilation, so tokens and diagnostics
+/// can refer to them by `FileId`.
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Create an empty source map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source to the map, returning the id it can be looked up by.
+    pub fn add(&mut self, source: Source) -> FileId {
+        let id = FileId(self.files.len());
+
+        // A span can end where its file does, so the next file starts after
+        // that
+        let start = self
+            .files
+            .last()
+            .map_or(0, |file| file.start + file.contents().len() + 1);
+
+        self.files.push(SourceFile::new(id, start, source));
+
+        id
+    }
+
+    /// Read a file from disk and add it to the map.
+    pub fn load(&mut self, path: &Path) -> io::Result<FileId> {
+        Ok(self.add(Source::from_path(path)?))
+    }
+
+    /// Get a file that was added to this map, or `None` if the id came from
+    /// a different map.
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0)
+    }
+
+    /// The file a span from any of the files is in, and the span within
+    /// the file.
+    pub fn locate(&self, span: Span) -> Option<(&SourceFile, Span)> {
+        let after = self.files.partition_point(|file| file.start <= span.start);
+        let file = self.files.get(after.checked_sub(1)?)?;
+
+        Some((
+            file,
+            Span::new(span.start - file.start, span.end - file.start),
+        ))
+    }
+
+    /// Find a file by its name.
+    pub fn find(&self, name: &str) -> Option<&SourceFile> {
+        self.files.iter().find(|file| file.name() == name)
+    }
+
+    /// All of the files in the map, in the order they were added.
+    pub fn files(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+
+    /// The number of files in the map.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Check if no files have been added.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
@@ -0,0 +1,95 @@
+//! Suggestions for names that were misspelled, e.g. "Did you mean 'count'?"
+//! when `cuont` can't be found, and fixes that tools can apply.
+use crate::lexer::Span;
+
+/// The number of characters that have to be inserted, deleted or replaced,
+/// or pairs of neighbouring characters that have to be swapped, to turn one
+/// string into the other, i.e. their optimal string alignment distance. A
+/// swap is one edit, since it is one of the most common typos.
+pub fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // The distances from the starts of `a` read so far, the one before and
+    // this one, to each start of `b`
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 0..a.len() {
+        current[0] = i + 1;
+
+        for j in 0..b.len() {
+            let replace = previous[j] + usize::from(a[i] != b[j]);
+
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                current[j + 1] = current[j + 1].min(before[j - 1] + 1);
+            }
+        }
+
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// The candidate most likely to be what `name` was meant to be, if any are
+/// close enough to be a typo of it. Longer names can be further off, a
+/// name with every character replaced is never close, and ties go to the
+/// candidate that came first.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let length = name.chars().count();
+    let limit = (length / 3).max(1).min(length.saturating_sub(1));
+
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A fix for an error that can be applied without a person checking it, by
+/// replacing the code at `span` with `replacement`. An empty span inserts
+/// the replacement instead.
+pub struct Suggestion {
+    /// What the fix does, e.g. "Add 'mut' to make 'a' mutable"
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl Suggestion {
+    /// A fix inserting `text` at a position in the source.
+    pub fn insert(message: impl Into<String>, at: usize, text: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            span: Span::new(at, at),
+            replacement: text.into(),
+        }
+    }
+
+    /// A fix replacing the code at `span` with `text`.
+    pub fn replace(message: impl Into<String>, span: Span, text: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            replacement: text.into(),
+        }
+    }
+
+    /// The source with the fix applied, if the source is the one the fix
+    /// was made for.
+    pub fn apply(&self, source: &str) -> Option<String> {
+        let before = source.get(..self.span.start)?;
+        let after = source.get(self.span.end..)?;
+
+        Some(format!("{}{}{}", before, self.replacement, after))
+    }
+}
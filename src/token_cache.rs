@@ -0,0 +1,88 @@
+use crate::lexer::{LexerOptions, Span, Token, TokenKind};
+pub use crate::source_map::hash_source;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Written at the start of every cache, so other files aren't mistaken for
+/// one. The last byte is the version of the format, which has to change when
+/// `TokenKind` does, since kinds are stored by their position in the enum.
+const MAGIC: [u8; 4] = *b"PGT\x06";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+/// What is stored in a cache after the magic bytes. Literals aren't stored, as
+/// they are slices of the source that can be recovered from the spans.
+struct TokenCache {
+    hash: u64,
+    /// The options the tokens were lexed with, as they can change the tokens
+    options: LexerOptions,
+    tokens: Vec<(TokenKind, Span)>,
+}
+
+/// Where the cache for a source file is kept, next to the file itself.
+///
+/// E.g. `examples/hello.pg` is cached in `examples/hello.pg.tokens`.
+pub fn path_for(path: &Path) -> PathBuf {
+    let mut cache = path.as_os_str().to_owned();
+
+    cache.push(".tokens");
+
+    PathBuf::from(cache)
+}
+
+/// Write the tokens lexed from the source with the given options to a cache.
+pub fn write(
+    source: &str,
+    options: &LexerOptions,
+    tokens: &[Token],
+    mut writer: impl Write,
+) -> bincode::Result<()> {
+    let cache = TokenCache {
+        hash: hash_source(source),
+        options: options.clone(),
+        tokens: tokens
+            .iter()
+            .map(|token| (token.kind.clone(), token.span))
+            .collect(),
+    };
+
+    writer.write_all(&MAGIC)?;
+
+    bincode::serialize_into(writer, &cache)
+}
+
+/// Read the tokens for the source from a cache. Returns `None` if the cache
+/// was made from different source code or options, or from another version
+/// of the format, in which case the source needs to be lexed again.
+pub fn read<'source>(
+    source: &'source str,
+    options: &LexerOptions,
+    mut reader: impl Read,
+) -> bincode::Result<Option<Vec<Token<'source>>>> {
+    let mut magic = [0; 4];
+
+    reader.read_exact(&mut magic)?;
+
+    if magic != MAGIC {
+        return Ok(None);
+    }
+
+    let cache: TokenCache = bincode::deserialize_from(reader)?;
+
+    if cache.hash != hash_source(source) || cache.options != *options {
+        return Ok(None);
+    }
+
+    // A span that doesn't fit the source means the cache is corrupt, so it
+    // is treated the same as a stale one.
+    let tokens = cache
+        .tokens
+        .into_iter()
+        .map(|(kind, span)| {
+            let literal = source.get(span.start..span.end)?;
+
+            Some(Token::new(kind, literal, span))
+        })
+        .collect();
+
+    Ok(tokens)
+}
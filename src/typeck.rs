@@ -0,0 +1,3231 @@
+//! Type checking, which gives every expression in a resolved program a type
+//! and reports where types don't fit together.
+//!
+//! Types flow from declarations to uses: a variable has the type it was
+//! declared with, or the type of its value when declared with `:=`. Integer
+//! literals take the type they are expected to have, e.g. `a : u8 = 1;`, and
+//! are `u32` otherwise.
+use crate::consteval::{Evaluator, Value};
+use crate::lexer::Span;
+use crate::ops::{self, Precedence};
+use crate::parser::ast::{
+    Arm, BinaryOp, Block, Closure, ExprId, ExprKind, Function, Impl, ItemKind, Length, Method,
+    Pattern, Stmt, StmtKind, Type, TypeParam, UnaryOp,
+};
+use crate::parser::ParseResult;
+use crate::resolve::{Resolution, SymbolId, SymbolKind};
+use std::collections::HashMap;
+use std::fmt;
+
+pub mod exhaustiveness;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntTy {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+}
+
+impl IntTy {
+    /// The integer type a name refers to, e.g. `u32`.
+    pub fn from_name(name: &str) -> Option<IntTy> {
+        let ty = match name {
+            "u8" => IntTy::U8,
+            "u16" => IntTy::U16,
+            "u32" => IntTy::U32,
+            "u64" => IntTy::U64,
+            "i8" => IntTy::I8,
+            "i16" => IntTy::I16,
+            "i32" => IntTy::I32,
+            "i64" => IntTy::I64,
+            _ => return None,
+        };
+
+        Some(ty)
+    }
+
+    /// The type as it is written in the source.
+    pub fn name(self) -> &'static str {
+        match self {
+            IntTy::U8 => "u8",
+            IntTy::U16 => "u16",
+            IntTy::U32 => "u32",
+            IntTy::U64 => "u64",
+            IntTy::I8 => "i8",
+            IntTy::I16 => "i16",
+            IntTy::I32 => "i32",
+            IntTy::I64 => "i64",
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(self, IntTy::I8 | IntTy::I16 | IntTy::I32 | IntTy::I64)
+    }
+
+    /// How many bits a value of the type has.
+    pub fn bits(self) -> u32 {
+        match self {
+            IntTy::U8 | IntTy::I8 => 8,
+            IntTy::U16 | IntTy::I16 => 16,
+            IntTy::U32 | IntTy::I32 => 32,
+            IntTy::U64 | IntTy::I64 => 64,
+        }
+    }
+
+    /// The smallest value of the type.
+    pub fn min(self) -> i128 {
+        if self.is_signed() {
+            -(1 << (self.bits() - 1))
+        } else {
+            0
+        }
+    }
+
+    /// The largest value of the type.
+    pub fn max(self) -> i128 {
+        if self.is_signed() {
+            (1 << (self.bits() - 1)) - 1
+        } else {
+            (1 << self.bits()) - 1
+        }
+    }
+
+    /// The value an integer has once it is cast to this type. Only its low
+    /// bits are kept, which are read as a signed number if the type is
+    /// signed, so `300 as u8` is 44 and `255 as i8` is -1. Casting to a
+    /// wider type keeps the value.
+    pub fn truncate(self, value: i128) -> i128 {
+        let bits = self.bits();
+        let low = value & ((1 << bits) - 1);
+
+        if self.is_signed() && low >= 1 << (bits - 1) {
+            low - (1 << bits)
+        } else {
+            low
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// The type of a value, after the names written in the source are resolved.
+pub enum Ty {
+    Int(IntTy),
+    Bool,
+    Str,
+    /// `(u32, str)`, or `()` for the unit type of expressions without a
+    /// value
+    Tuple(Vec<Ty>),
+    /// `[u8; 4]`, or `[u8]` without a length
+    Array {
+        element: Box<Ty>,
+        length: Option<usize>,
+    },
+    Optional(Box<Ty>),
+    Function {
+        params: Vec<Ty>,
+        ret: Box<Ty>,
+    },
+    /// A struct, by name, with the types its type parameters stand for
+    Struct(String, Vec<Ty>),
+    /// An enum, by name
+    Enum(String),
+    /// A type parameter of a generic function or struct, which stands for
+    /// the type it is given where it is used
+    Param(String),
+    /// The type of code that never finishes, like a block that always
+    /// returns. It fits anywhere, since there is never a value.
+    Never,
+    /// A type that can't be known, because of an earlier error or because
+    /// it comes from an import. It fits anywhere, so one mistake isn't
+    /// reported over and over.
+    Unknown,
+}
+
+impl Ty {
+    /// The type of expressions without a value, `()`.
+    pub fn unit() -> Ty {
+        Ty::Tuple(Vec::new())
+    }
+
+    pub fn is_unit(&self) -> bool {
+        matches!(self, Ty::Tuple(types) if types.is_empty())
+    }
+
+    /// Whether a value of one type can be used where the other is expected.
+    /// This is equality, except that `Never` and `Unknown` fit anywhere and
+    /// an array without a length fits arrays of any length.
+    pub fn matches(&self, other: &Ty) -> bool {
+        match (self, other) {
+            (Ty::Never | Ty::Unknown, _) | (_, Ty::Never | Ty::Unknown) => true,
+            (Ty::Tuple(a), Ty::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.matches(b))
+            }
+            (
+                Ty::Array { element, length },
+                Ty::Array {
+                    element: other_element,
+                    length: other_length,
+                },
+            ) => {
+                (length.is_none() || other_length.is_none() || length == other_length)
+                    && element.matches(other_element)
+            }
+            (Ty::Optional(a), Ty::Optional(b)) => a.matches(b),
+            (Ty::Struct(name, args), Ty::Struct(other_name, other_args)) => {
+                name == other_name
+                    && args.len() == other_args.len()
+                    && args.iter().zip(other_args).all(|(a, b)| a.matches(b))
+            }
+            (
+                Ty::Function { params, ret },
+                Ty::Function {
+                    params: other_params,
+                    ret: other_ret,
+                },
+            ) => {
+                params.len() == other_params.len()
+                    && params.iter().zip(other_params).all(|(a, b)| a.matches(b))
+                    && ret.matches(other_ret)
+            }
+            _ => self == other,
+        }
+    }
+
+    /// Whether the type uses any type parameters.
+    pub fn has_params(&self) -> bool {
+        match self {
+            Ty::Param(_) => true,
+            Ty::Tuple(types) | Ty::Struct(_, types) => types.iter().any(Ty::has_params),
+            Ty::Array { element, .. } => element.has_params(),
+            Ty::Optional(ty) => ty.has_params(),
+            Ty::Function { params, ret } => params.iter().any(Ty::has_params) || ret.has_params(),
+            _ => false,
+        }
+    }
+
+    /// Replace the type parameters in the type with the types they stand
+    /// for. Parameters that aren't in `args` are left as they are.
+    pub fn substitute(&self, args: &HashMap<String, Ty>) -> Ty {
+        let all = |types: &[Ty]| types.iter().map(|ty| ty.substitute(args)).collect();
+
+        match self {
+            Ty::Param(name) => args.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Ty::Tuple(types) => Ty::Tuple(all(types)),
+            Ty::Struct(name, types) => Ty::Struct(name.clone(), all(types)),
+            Ty::Array { element, length } => Ty::Array {
+                element: Box::new(element.substitute(args)),
+                length: *length,
+            },
+            Ty::Optional(ty) => Ty::Optional(Box::new(ty.substitute(args))),
+            Ty::Function { params, ret } => Ty::Function {
+                params: all(params),
+                ret: Box::new(ret.substitute(args)),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Find what the type parameters in this type stand for, from the type a
+    /// value of it has, e.g. `T` is `u8` if `[T]` is `[u8; 3]`. Parameters
+    /// that already stand for a type are left alone, and parts that don't
+    /// fit are skipped, to be reported when the value is checked.
+    pub fn bind(&self, ty: &Ty, args: &mut HashMap<String, Ty>) {
+        let all = |params: &[Ty], types: &[Ty], args: &mut HashMap<String, Ty>| {
+            if params.len() == types.len() {
+                for (param, ty) in params.iter().zip(types) {
+                    param.bind(ty, args);
+                }
+            }
+        };
+
+        match (self, ty) {
+            (_, Ty::Unknown | Ty::Never) => {}
+            (Ty::Param(name), _) => {
+                args.entry(name.clone()).or_insert_with(|| ty.clone());
+            }
+            (Ty::Tuple(params), Ty::Tuple(types)) => all(params, types, args),
+            (Ty::Struct(name, params), Ty::Struct(other, types)) if name == other => {
+                all(params, types, args)
+            }
+            (Ty::Array { element, .. }, Ty::Array { element: other, .. }) => {
+                element.bind(other, args)
+            }
+            (Ty::Optional(param), Ty::Optional(ty)) => param.bind(ty, args),
+            (
+                Ty::Function { params, ret },
+                Ty::Function {
+                    params: types,
+                    ret: other,
+                },
+            ) => {
+                all(params, types, args);
+                ret.bind(other, args);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    /// Write the type the way it is written in the source.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ty::Int(int) => write!(f, "{}", int.name()),
+            Ty::Bool => write!(f, "bool"),
+            Ty::Str => write!(f, "str"),
+            Ty::Tuple(types) if types.len() == 1 => write!(f, "({},)", types[0]),
+            Ty::Tuple(types) => write!(f, "({})", List(types)),
+            Ty::Array {
+                element,
+                length: Some(length),
+            } => write!(f, "[{}; {}]", element, length),
+            Ty::Array {
+                element,
+                length: None,
+            } => write!(f, "[{}]", element),
+            Ty::Optional(ty) if matches!(**ty, Ty::Function { .. }) => write!(f, "({})?", ty),
+            Ty::Optional(ty) => write!(f, "{}?", ty),
+            Ty::Function { params, ret } if ret.is_unit() => write!(f, "func({})", List(params)),
+            Ty::Function { params, ret } => write!(f, "func({}) -> {}", List(params), ret),
+            Ty::Struct(name, args) if args.is_empty() => write!(f, "{}", name),
+            Ty::Struct(name, args) => write!(f, "{}<{}>", name, List(args)),
+            Ty::Enum(name) | Ty::Param(name) => write!(f, "{}", name),
+            Ty::Never => write!(f, "!"),
+            Ty::Unknown => write!(f, "{{unknown}}"),
+        }
+    }
+}
+
+/// Writes a list of types separated by commas.
+struct List<'a>(&'a [Ty]);
+
+impl fmt::Display for List<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, ty) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", ty)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The types found by checking a program.
+pub struct Types {
+    /// The type of every expression
+    pub exprs: HashMap<ExprId, Ty>,
+    /// The type of every parameter, variable and pattern binding
+    pub symbols: HashMap<SymbolId, Ty>,
+    /// The instance of a generic function each use of one refers to, by its
+    /// position in `instances`
+    pub instantiated: HashMap<ExprId, usize>,
+    /// Every instance of a generic function that is used. The bodies of
+    /// generic functions are checked once for each instance, with the types
+    /// they are used with, so their types are found there instead.
+    pub instances: Vec<Instance>,
+    /// The method each method call calls, which is always known when
+    /// compiling. Built-in methods, like `len` of a `str`, aren't here.
+    pub calls: HashMap<ExprId, MethodId>,
+    /// What every method of an impl returns
+    pub methods: HashMap<MethodId, Ty>,
+    /// The type of every global's and constant's value, and what every
+    /// function returns, by the position of the item
+    pub items: HashMap<usize, Ty>,
+    /// The fields of every struct, in order
+    pub structs: HashMap<String, Vec<(String, Ty)>>,
+    /// The variants of every enum, in order, and the types of their payloads
+    pub enums: HashMap<String, Vec<(String, Vec<Ty>)>>,
+    /// Code that is allowed but probably a mistake, like a match arm that
+    /// can never match
+    pub warnings: Vec<TypeError>,
+}
+
+impl Types {
+    pub fn expr(&self, id: ExprId) -> Option<&Ty> {
+        self.exprs.get(&id)
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> Option<&Ty> {
+        self.symbols.get(&id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A generic function with the types its type parameters stand for, e.g.
+/// `max<u32>`.
+pub struct Instance {
+    /// The position of the function's item
+    pub item: usize,
+    /// The type each type parameter stands for, in order
+    pub args: Vec<Ty>,
+    /// What the instance returns
+    pub ret: Ty,
+    /// The type of every expression in the body
+    pub exprs: HashMap<ExprId, Ty>,
+    /// The type of every parameter, variable and pattern binding
+    pub symbols: HashMap<SymbolId, Ty>,
+    /// The instance each use of a generic function in the body refers to
+    pub instantiated: HashMap<ExprId, usize>,
+    /// The method each method call in the body calls
+    pub calls: HashMap<ExprId, MethodId>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A method of an impl, by the position of the impl's item and of the method
+/// in it.
+pub struct MethodId {
+    pub item: usize,
+    pub method: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    /// A value doesn't have the type it needs to.
+    Mismatch { expected: Ty, found: Ty },
+    /// A type was written that doesn't exist.
+    UnknownType { name: String },
+    /// An operator was used on a type it doesn't work with, e.g. `-` on a
+    /// string.
+    InvalidOperand { op: String, ty: Ty },
+    /// Something that isn't a function was called.
+    NotCallable { ty: Ty },
+    /// A function or variant was given the wrong number of values.
+    ArgumentCount { expected: usize, found: usize },
+    /// A field was used that the type doesn't have.
+    NoField { ty: Ty, field: String },
+    /// A struct literal left out some of the fields.
+    MissingFields { name: String, fields: Vec<String> },
+    /// A variant was used that the enum doesn't have.
+    UnknownVariant { enum_name: String, variant: String },
+    /// Something that can't be indexed was indexed.
+    NotIndexable { ty: Ty },
+    /// An integer was needed, e.g. for an index.
+    NotAnInteger { ty: Ty },
+    /// A struct or enum was used as a value.
+    NotAValue { name: String },
+    /// A pattern can never match the value it is matched against.
+    PatternMismatch { pattern: String, ty: Ty },
+    /// A value has nothing to infer its type from, e.g. an empty array
+    /// assigned with `:=`.
+    CannotInfer {
+        /// What the value is, e.g. "an empty array"
+        what: String,
+    },
+    /// A function that returns a value has a path through it that doesn't.
+    MissingReturn { name: String, ty: Ty },
+    /// A value was returned from a function that doesn't return one.
+    ReturnValue { ty: Ty },
+    /// A match doesn't have an arm for some values, written as patterns.
+    NonExhaustive { patterns: Vec<String> },
+    /// The length of an array type isn't a constant integer.
+    ArrayLength { name: String },
+    /// A value was cast to a type it can't be converted to, e.g. `"a" as
+    /// u32`.
+    InvalidCast { from: Ty, to: Ty },
+    /// A generic type was given the wrong number of types, e.g. `Pair<u8>`.
+    TypeArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A generic function keeps being used with new types from inside
+    /// itself, e.g. with `(T, T)` when called with `T`, so it would have
+    /// endless instances.
+    InstantiationLimit { name: String },
+    /// A trait was used that doesn't exist.
+    UnknownTrait { name: String },
+    /// A trait was implemented for the same type twice.
+    DuplicateImpl { trait_name: String, ty: Ty },
+    /// An impl left out some of the methods its trait requires.
+    MissingMethods {
+        trait_name: String,
+        ty: Ty,
+        methods: Vec<String>,
+    },
+    /// An impl has a method its trait doesn't.
+    NotInTrait { trait_name: String, method: String },
+    /// A method in an impl doesn't take or return what its trait says it
+    /// does.
+    MethodSignature {
+        trait_name: String,
+        method: String,
+        expected: Ty,
+        found: Ty,
+    },
+    /// A method was called that no trait implemented for the type has.
+    NoMethod { ty: Ty, method: String },
+    /// A method was called that more than one trait implemented for the
+    /// type has, so which one is meant isn't known.
+    AmbiguousMethod {
+        ty: Ty,
+        method: String,
+        traits: Vec<String>,
+    },
+    /// A type parameter was given a type that doesn't implement one of the
+    /// traits it has to.
+    UnsatisfiedBound { ty: Ty, trait_name: String },
+    /// The method an operator is overloaded with doesn't take the operands,
+    /// e.g. an `add` without a second parameter.
+    OperatorMethod {
+        op: String,
+        method: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A match arm matches nothing the arms before it don't, so it is never
+    /// used. This is a warning.
+    UnreachableArm,
+    /// A program compiled to an executable has no `main` function to start
+    /// at.
+    NoMain,
+    /// The `main` function can't be called to start the program, e.g. since
+    /// it takes parameters.
+    InvalidMain {
+        /// What is wrong with it, e.g. "can't take parameters"
+        problem: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An error that occurred while type checking.
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    span: Span,
+    declared: Option<Span>,
+}
+
+impl TypeError {
+    pub fn new(kind: TypeErrorKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            declared: None,
+        }
+    }
+
+    /// Point at the declaration the error is about too, e.g. the annotation
+    /// a value doesn't match.
+    pub fn declared_at(mut self, span: Span) -> Self {
+        self.declared = Some(span);
+        self
+    }
+
+    /// A stable code identifying the kind of error, for tooling and docs.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            TypeErrorKind::Mismatch { .. } => "E0301",
+            TypeErrorKind::UnknownType { .. } => "E0302",
+            TypeErrorKind::InvalidOperand { .. } => "E0303",
+            TypeErrorKind::NotCallable { .. } => "E0304",
+            TypeErrorKind::ArgumentCount { .. } => "E0305",
+            TypeErrorKind::NoField { .. } => "E0306",
+            TypeErrorKind::MissingFields { .. } => "E0307",
+            TypeErrorKind::UnknownVariant { .. } => "E0308",
+            TypeErrorKind::NotIndexable { .. } => "E0309",
+            TypeErrorKind::NotAnInteger { .. } => "E0310",
+            TypeErrorKind::NotAValue { .. } => "E0311",
+            TypeErrorKind::PatternMismatch { .. } => "E0312",
+            TypeErrorKind::CannotInfer { .. } => "E0313",
+            TypeErrorKind::MissingReturn { .. } => "E0314",
+            TypeErrorKind::ReturnValue { .. } => "E0315",
+            TypeErrorKind::NonExhaustive { .. } => "E0316",
+            TypeErrorKind::ArrayLength { .. } => "E0317",
+            TypeErrorKind::InvalidCast { .. } => "E0318",
+            TypeErrorKind::TypeArgumentCount { .. } => "E0319",
+            TypeErrorKind::InstantiationLimit { .. } => "E0320",
+            TypeErrorKind::UnknownTrait { .. } => "E0321",
+            TypeErrorKind::DuplicateImpl { .. } => "E0322",
+            TypeErrorKind::MissingMethods { .. } => "E0323",
+            TypeErrorKind::NotInTrait { .. } => "E0324",
+            TypeErrorKind::MethodSignature { .. } => "E0325",
+            TypeErrorKind::NoMethod { .. } => "E0326",
+            TypeErrorKind::AmbiguousMethod { .. } => "E0327",
+            TypeErrorKind::UnsatisfiedBound { .. } => "E0328",
+            TypeErrorKind::OperatorMethod { .. } => "E0329",
+            TypeErrorKind::UnreachableArm => "W0301",
+            TypeErrorKind::NoMain => "E0330",
+            TypeErrorKind::InvalidMain { .. } => "E0331",
+        }
+    }
+
+    /// The code that caused the error.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The declaration the error is about, if there is one.
+    pub fn declared(&self) -> Option<Span> {
+        self.declared
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            // Integers are never widened implicitly, so say how to convert
+            TypeErrorKind::Mismatch {
+                expected: expected @ Ty::Int(_),
+                found: found @ Ty::Int(_),
+            } => format!(
+                "Expected {}, found {}, which has to be converted with 'as {}'",
+                expected, found, expected
+            ),
+            TypeErrorKind::Mismatch { expected, found } => {
+                format!("Expected {}, found {}", expected, found)
+            }
+            TypeErrorKind::UnknownType { name } => format!("Can't find the type '{}'", name),
+            TypeErrorKind::InvalidOperand { op, ty } => format!("Can't use '{}' on {}", op, ty),
+            TypeErrorKind::NotCallable { ty } => format!("{} isn't a function", ty),
+            TypeErrorKind::ArgumentCount { expected, found } => {
+                format!("Expected {} values, found {}", expected, found)
+            }
+            TypeErrorKind::NoField { ty, field } => format!("{} has no field '{}'", ty, field),
+            TypeErrorKind::MissingFields { name, fields } => {
+                format!("Missing fields in {}: {}", name, fields.join(", "))
+            }
+            TypeErrorKind::UnknownVariant { enum_name, variant } => {
+                format!("{} has no variant '{}'", enum_name, variant)
+            }
+            TypeErrorKind::NotIndexable { ty } => format!("{} can't be indexed", ty),
+            TypeErrorKind::NotAnInteger { ty } => format!("Expected an integer, found {}", ty),
+            TypeErrorKind::NotAValue { name } => format!("'{}' is a type, not a value", name),
+            TypeErrorKind::PatternMismatch { pattern, ty } => {
+                format!("The pattern '{}' can't match {}", pattern, ty)
+            }
+            TypeErrorKind::CannotInfer { what } => {
+                format!(
+                    "Can't infer the type of {}, so it needs to be written",
+                    what
+                )
+            }
+            TypeErrorKind::MissingReturn { name, ty } => {
+                format!("'{}' doesn't return {} on every path", name, ty)
+            }
+            TypeErrorKind::ReturnValue { ty } => {
+                format!("Can't return {} from a function that returns nothing", ty)
+            }
+            TypeErrorKind::NonExhaustive { patterns } => {
+                format!("Missing patterns in the match: {}", patterns.join(", "))
+            }
+            TypeErrorKind::ArrayLength { name } => format!(
+                "'{}' isn't a constant integer, so it can't be the length of an array",
+                name
+            ),
+            TypeErrorKind::InvalidCast { from, to } => format!("Can't cast {} to {}", from, to),
+            TypeErrorKind::TypeArgumentCount {
+                name,
+                expected,
+                found,
+            } => format!(
+                "'{}' takes {} type arguments, found {}",
+                name, expected, found
+            ),
+            TypeErrorKind::InstantiationLimit { name } => format!(
+                "'{}' is used with new types from inside itself too many times",
+                name
+            ),
+            TypeErrorKind::UnknownTrait { name } => format!("Can't find the trait '{}'", name),
+            TypeErrorKind::DuplicateImpl { trait_name, ty } => {
+                format!("'{}' is already implemented for {}", trait_name, ty)
+            }
+            TypeErrorKind::MissingMethods {
+                trait_name,
+                ty,
+                methods,
+            } => format!(
+                "Missing methods of '{}' for {}: {}",
+                trait_name,
+                ty,
+                methods.join(", ")
+            ),
+            TypeErrorKind::NotInTrait { trait_name, method } => {
+                format!("'{}' isn't a method of '{}'", method, trait_name)
+            }
+            TypeErrorKind::MethodSignature {
+                trait_name,
+                method,
+                expected,
+                found,
+            } => format!(
+                "'{}' has to be {} to implement '{}', found {}",
+                method, expected, trait_name, found
+            ),
+            TypeErrorKind::NoMethod { ty, method } => {
+                format!("{} has no method '{}'", ty, method)
+            }
+            TypeErrorKind::AmbiguousMethod { ty, method, traits } => format!(
+                "{} has a method '{}' from more than one trait: {}",
+                ty,
+                method,
+                traits.join(", ")
+            ),
+            TypeErrorKind::UnsatisfiedBound { ty, trait_name } => {
+                format!("{} doesn't implement '{}'", ty, trait_name)
+            }
+            TypeErrorKind::OperatorMethod {
+                op,
+                method,
+                expected,
+                found,
+            } => format!(
+                "'{}' has to take {} parameters to overload '{}', found {}",
+                method, expected, op, found
+            ),
+            TypeErrorKind::UnreachableArm => {
+                "This arm is never used, since the arms before it match everything it does"
+                    .to_string()
+            }
+            TypeErrorKind::NoMain => {
+                "There is no 'main' function to start the program at, which only libraries \
+                 can leave out"
+                    .to_string()
+            }
+            TypeErrorKind::InvalidMain { problem } => format!("'main' {}", problem),
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.", self.message())
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+/// Check that a type checked program has an entry point, so it can be
+/// compiled to an executable. That is a function `main` without parameters
+/// or type parameters, which returns nothing or an integer, its exit code.
+pub fn check_entry_point(parsed: &ParseResult, types: &Types) -> Result<(), Vec<TypeError>> {
+    let main = parsed
+        .program
+        .items
+        .iter()
+        .enumerate()
+        .find(|(_, item)| item_name(&item.kind) == Some("main"));
+
+    let Some((i, item)) = main else {
+        return Err(vec![TypeError::new(TypeErrorKind::NoMain, Span::default())]);
+    };
+
+    let invalid =
+        |problem: String| TypeError::new(TypeErrorKind::InvalidMain { problem }, item.span);
+
+    let ItemKind::Function(function) = &item.kind else {
+        return Err(vec![invalid("has to be a function".to_string())]);
+    };
+
+    let mut errors = Vec::new();
+
+    if !function.generics.is_empty() {
+        errors.push(invalid("can't have type parameters".to_string()));
+    }
+
+    if !function.params.is_empty() {
+        errors.push(invalid(format!(
+            "can't take parameters, found {}",
+            function.params.len()
+        )));
+    }
+
+    // A generic `main` has no return type to check
+    if let Some(ty) = types.items.get(&i) {
+        if !matches!(ty, Ty::Int(_) | Ty::Never) && !ty.is_unit() {
+            errors.push(invalid(format!(
+                "has to return nothing or an integer, found {}",
+                ty
+            )));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// The name an item declares, if it declares one.
+fn item_name(kind: &ItemKind) -> Option<&str> {
+    match kind {
+        ItemKind::Function(function) => Some(&function.name),
+        ItemKind::Global(decl) | ItemKind::Const(decl) => Some(&decl.name),
+        ItemKind::Struct(structure) => Some(&structure.name),
+        ItemKind::Enum(enumeration) => Some(&enumeration.name),
+        ItemKind::Trait(declaration) => Some(&declaration.name),
+        ItemKind::Import(_) | ItemKind::StaticAssert(_) | ItemKind::Impl(_) => None,
+    }
+}
+
+/// What is known about an item before its body or value is checked.
+enum Declared {
+    Function {
+        params: Vec<Ty>,
+        ret: Option<Ty>,
+    },
+    Value(Option<Ty>),
+    /// The methods a trait requires, with `Self` standing for itself
+    Trait(Vec<MethodTy>),
+    /// The type an impl is for, and the types of its methods
+    Impl {
+        ty: Ty,
+        methods: Vec<MethodTy>,
+    },
+    /// Structs, enums, imports and static assertions have no type
+    None,
+}
+
+/// The types of a method of a trait or impl.
+#[derive(Clone)]
+struct MethodTy {
+    name: String,
+    params: Vec<Ty>,
+    ret: Ty,
+}
+
+/// How far an item has been checked, so each is only checked once.
+#[derive(Clone)]
+enum State {
+    Unchecked,
+    /// Being checked, so using it again is a cycle
+    Checking,
+    /// Checked, with the type of its value or what it returns
+    Checked(Ty),
+}
+
+/// Where the types found in a function body are recorded: in `Types` for
+/// most code, or in an instance of a generic function.
+#[derive(Default)]
+struct Body {
+    exprs: HashMap<ExprId, Ty>,
+    symbols: HashMap<SymbolId, Ty>,
+    instantiated: HashMap<ExprId, usize>,
+    calls: HashMap<ExprId, MethodId>,
+}
+
+/// An instance of a generic function, for a use of it.
+struct Instantiated {
+    /// Its position in `Types::instances`, unless it couldn't be checked
+    position: Option<usize>,
+    /// What each type parameter stands for
+    bindings: HashMap<String, Ty>,
+    /// What it returns
+    ret: Ty,
+}
+
+/// How many instances of the same generic function can be checked inside
+/// each other, so a function that keeps using itself with new types is
+/// stopped. The types can double in size each time, e.g. `T` then `(T, T)`,
+/// so this is kept small.
+const INSTANTIATION_LIMIT: usize = 8;
+
+/// What a `return` in the current function or closure has to return.
+struct Return {
+    /// The declared return type, or the type of the first `return` when
+    /// it isn't declared
+    ty: Option<Ty>,
+    /// Where the return type was declared
+    declared: Option<Span>,
+}
+
+/// Checks the types of a resolved program.
+pub struct TypeChecker<'a> {
+    parsed: &'a ParseResult,
+    resolution: &'a Resolution,
+    /// The position of each item, by name
+    items: HashMap<&'a str, usize>,
+    declared: Vec<Declared>,
+    states: Vec<State>,
+    /// The fields of each struct, in order
+    structs: HashMap<&'a str, Vec<(String, Ty)>>,
+    /// The variants of each enum, and the types of their payloads
+    enums: HashMap<&'a str, Vec<(String, Vec<Ty>)>>,
+    /// The type parameters of each generic struct
+    struct_generics: HashMap<&'a str, &'a [TypeParam]>,
+    /// The position of each impl, with the trait it implements and the type
+    /// it implements it for
+    impls: Vec<(usize, &'a str, Ty)>,
+    /// The type parameters that can be used in the types being checked, and
+    /// what they stand for: themselves in the signature of a generic item,
+    /// or the types of an instance in its body
+    generics: HashMap<String, Ty>,
+    /// The types found in top-level code, then in the instances being
+    /// checked, innermost last
+    bodies: Vec<Body>,
+    /// Which of `bodies` the types being found are recorded in
+    current: usize,
+    /// The position of every instance in `types.instances`, by the position
+    /// of its function and its types
+    instance_keys: HashMap<(usize, Vec<Ty>), usize>,
+    /// The positions of the generic functions whose instances are being
+    /// checked, innermost last
+    instantiating: Vec<usize>,
+    /// The symbol declared by a node with a name, to find the symbols of
+    /// parameters, variables and bindings
+    declarations: HashMap<(Span, &'a str), SymbolId>,
+    /// The functions and closures the current node is in, innermost last
+    returns: Vec<Return>,
+    /// Evaluates the constants used as the lengths of arrays
+    consts: Evaluator<'a>,
+    types: Types,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> TypeChecker<'a> {
+    /// Check the types in a program. If any don't fit, every error that was
+    /// found is returned.
+    pub fn check(
+        parsed: &'a ParseResult,
+        resolution: &'a Resolution,
+    ) -> Result<Types, Vec<TypeError>> {
+        let items = &parsed.program.items;
+
+        let mut checker = TypeChecker {
+            parsed,
+            resolution,
+            items: HashMap::new(),
+            declared: Vec::new(),
+            states: vec![State::Unchecked; items.len()],
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            struct_generics: HashMap::new(),
+            impls: Vec::new(),
+            generics: HashMap::new(),
+            bodies: vec![Body::default()],
+            current: 0,
+            instance_keys: HashMap::new(),
+            instantiating: Vec::new(),
+            declarations: resolution
+                .scopes
+                .iter()
+                .flat_map(|scope| &scope.symbols)
+                .map(|&id| {
+                    let symbol = resolution.symbol(id);
+
+                    ((symbol.span, symbol.name.as_str()), id)
+                })
+                .collect(),
+            returns: Vec::new(),
+            consts: Evaluator::new(parsed, resolution),
+            types: Types::default(),
+            errors: Vec::new(),
+        };
+
+        for (i, item) in items.iter().enumerate() {
+            if let Some(name) = item_name(&item.kind) {
+                checker.items.insert(name, i);
+            }
+        }
+
+        for item in items {
+            if let ItemKind::Struct(structure) = &item.kind {
+                if !structure.generics.is_empty() {
+                    checker
+                        .struct_generics
+                        .insert(&structure.name, &structure.generics);
+                }
+            }
+        }
+
+        // Every type written outside of a function body is found up front,
+        // so items can be used in any order. The type parameters of generic
+        // items stand for themselves until they are used, and so does `Self`
+        // in a trait.
+        for (i, item) in items.iter().enumerate() {
+            let generics = match &item.kind {
+                ItemKind::Function(function) => &function.generics[..],
+                ItemKind::Struct(structure) => &structure.generics,
+                _ => &[],
+            };
+
+            checker.generics = generics
+                .iter()
+                .map(|param| (param.name.clone(), Ty::Param(param.name.clone())))
+                .collect();
+
+            for bound in generics.iter().flat_map(|param| &param.bounds) {
+                checker.check_trait(bound, item.span);
+            }
+
+            match &item.kind {
+                ItemKind::Trait(_) => {
+                    checker
+                        .generics
+                        .insert("Self".to_string(), Ty::Param("Self".to_string()));
+                }
+                // In an impl, `Self` is the type it is for
+                ItemKind::Impl(implementation) => {
+                    let ty = checker.lower(&implementation.ty, item.span);
+
+                    checker
+                        .impls
+                        .push((i, &implementation.trait_name, ty.clone()));
+                    checker.generics.insert("Self".to_string(), ty);
+                }
+                _ => {}
+            }
+
+            let declared = match &item.kind {
+                ItemKind::Function(function) => Declared::Function {
+                    params: function
+                        .params
+                        .iter()
+                        .map(|param| checker.lower(&param.ty, item.span))
+                        .collect(),
+                    ret: function
+                        .return_type
+                        .as_ref()
+                        .map(|ty| checker.lower(ty, item.span)),
+                },
+                ItemKind::Global(decl) | ItemKind::Const(decl) => {
+                    Declared::Value(decl.ty.as_ref().map(|ty| checker.lower(ty, item.span)))
+                }
+                ItemKind::Struct(structure) => {
+                    let fields = structure
+                        .fields
+                        .iter()
+                        .map(|field| (field.name.clone(), checker.lower(&field.ty, item.span)))
+                        .collect();
+
+                    checker.structs.insert(&structure.name, fields);
+
+                    Declared::None
+                }
+                ItemKind::Enum(enumeration) => {
+                    let variants = enumeration
+                        .variants
+                        .iter()
+                        .map(|variant| {
+                            let payload = variant
+                                .payload
+                                .iter()
+                                .map(|ty| checker.lower(ty, item.span))
+                                .collect();
+
+                            (variant.name.clone(), payload)
+                        })
+                        .collect();
+
+                    checker.enums.insert(&enumeration.name, variants);
+
+                    Declared::None
+                }
+                ItemKind::Trait(declaration) => Declared::Trait(
+                    declaration
+                        .methods
+                        .iter()
+                        .map(|method| MethodTy {
+                            name: method.name.clone(),
+                            params: method
+                                .params
+                                .iter()
+                                .map(|param| checker.lower(&param.ty, item.span))
+                                .collect(),
+                            ret: match &method.return_type {
+                                Some(ty) => checker.lower(ty, item.span),
+                                None => Ty::unit(),
+                            },
+                        })
+                        .collect(),
+                ),
+                ItemKind::Impl(implementation) => Declared::Impl {
+                    ty: checker.generics["Self"].clone(),
+                    methods: implementation
+                        .methods
+                        .iter()
+                        .map(|method| checker.method_ty(implementation, method))
+                        .collect(),
+                },
+                ItemKind::Import(_) | ItemKind::StaticAssert(_) => Declared::None,
+            };
+
+            checker.declared.push(declared);
+        }
+
+        checker.generics.clear();
+
+        for i in 0..items.len() {
+            checker.item(i);
+        }
+
+        for (i, state) in checker.states.iter().enumerate() {
+            let has_type = match &items[i].kind {
+                ItemKind::Function(function) => function.generics.is_empty(),
+                ItemKind::Global(_) | ItemKind::Const(_) => true,
+                _ => false,
+            };
+
+            if let (State::Checked(ty), true) = (state, has_type) {
+                checker.types.items.insert(i, ty.clone());
+            }
+        }
+
+        let body = std::mem::take(&mut checker.bodies[0]);
+
+        checker.types.exprs = body.exprs;
+        checker.types.symbols = body.symbols;
+        checker.types.instantiated = body.instantiated;
+        checker.types.calls = body.calls;
+
+        // Generic functions are checked once for each instance, which can
+        // find the same mistake more than once
+        dedup(&mut checker.errors);
+        dedup(&mut checker.types.warnings);
+
+        checker.types.structs = checker
+            .structs
+            .iter()
+            .map(|(name, fields)| (name.to_string(), fields.clone()))
+            .collect();
+        checker.types.enums = checker
+            .enums
+            .iter()
+            .map(|(name, variants)| (name.to_string(), variants.clone()))
+            .collect();
+
+        if checker.errors.is_empty() {
+            Ok(checker.types)
+        } else {
+            Err(checker.errors)
+        }
+    }
+
+    fn error(&mut self, kind: TypeErrorKind, span: Span) {
+        self.errors.push(TypeError::new(kind, span));
+    }
+
+    /// Where the types being found are recorded.
+    fn body(&mut self) -> &mut Body {
+        &mut self.bodies[self.current]
+    }
+
+    /// The type a written type refers to. Unknown names are reported at
+    /// `span`, the node the type is written in.
+    fn lower(&mut self, ty: &Type, span: Span) -> Ty {
+        match ty {
+            Type::Named(name) => {
+                if let Some(ty) = self.generics.get(name) {
+                    return ty.clone();
+                }
+
+                if let Some(int) = IntTy::from_name(name) {
+                    return Ty::Int(int);
+                }
+
+                match name.as_str() {
+                    "bool" => return Ty::Bool,
+                    "str" => return Ty::Str,
+                    _ => {}
+                }
+
+                let kind = self
+                    .items
+                    .get(name.as_str())
+                    .map(|&i| &self.parsed.program.items[i].kind);
+
+                match kind {
+                    // A generic struct has to be given its types
+                    Some(ItemKind::Struct(structure)) if !structure.generics.is_empty() => {
+                        self.error(
+                            TypeErrorKind::TypeArgumentCount {
+                                name: name.clone(),
+                                expected: structure.generics.len(),
+                                found: 0,
+                            },
+                            span,
+                        );
+
+                        Ty::Unknown
+                    }
+                    Some(ItemKind::Struct(_)) => Ty::Struct(name.clone(), Vec::new()),
+                    Some(ItemKind::Enum(_)) => Ty::Enum(name.clone()),
+                    // Types from other files can't be known yet
+                    _ if self.is_imported(name) => Ty::Unknown,
+                    _ => {
+                        self.error(TypeErrorKind::UnknownType { name: name.clone() }, span);
+
+                        Ty::Unknown
+                    }
+                }
+            }
+            Type::Generic { name, args } => {
+                let args: Vec<_> = args.iter().map(|ty| self.lower(ty, span)).collect();
+
+                let kind = self
+                    .items
+                    .get(name.as_str())
+                    .map(|&i| &self.parsed.program.items[i].kind);
+
+                let expected = match kind {
+                    Some(ItemKind::Struct(structure)) => structure.generics.len(),
+                    // Enums aren't generic
+                    Some(ItemKind::Enum(_)) => 0,
+                    _ if self.is_imported(name) => return Ty::Unknown,
+                    _ => {
+                        self.error(TypeErrorKind::UnknownType { name: name.clone() }, span);
+
+                        return Ty::Unknown;
+                    }
+                };
+
+                if args.len() != expected {
+                    self.error(
+                        TypeErrorKind::TypeArgumentCount {
+                            name: name.clone(),
+                            expected,
+                            found: args.len(),
+                        },
+                        span,
+                    );
+
+                    return Ty::Unknown;
+                }
+
+                match kind {
+                    Some(ItemKind::Struct(structure)) => {
+                        self.check_bounds(&structure.generics, &args, span);
+
+                        Ty::Struct(name.clone(), args)
+                    }
+                    _ => Ty::Enum(name.clone()),
+                }
+            }
+            Type::Tuple(types) => Ty::Tuple(types.iter().map(|ty| self.lower(ty, span)).collect()),
+            Type::Array { element, length } => Ty::Array {
+                element: Box::new(self.lower(element, span)),
+                length: match length {
+                    Some(Length::Number(length)) => Some(*length),
+                    Some(Length::Const(name)) => self.length(name, span),
+                    None => None,
+                },
+            },
+            Type::Optional(ty) => Ty::Optional(Box::new(self.lower(ty, span))),
+            Type::Function {
+                params,
+                return_type,
+            } => Ty::Function {
+                params: params.iter().map(|ty| self.lower(ty, span)).collect(),
+                ret: Box::new(match return_type {
+                    Some(ty) => self.lower(ty, span),
+                    None => Ty::unit(),
+                }),
+            },
+        }
+    }
+
+    /// The value of a constant used as the length of an array, which is
+    /// written at `span`. A constant that can't be evaluated is reported
+    /// when constants are, so its length is left unknown.
+    fn length(&mut self, name: &str, span: Span) -> Option<usize> {
+        let index = self
+            .items
+            .get(name)
+            .copied()
+            .filter(|&i| matches!(self.parsed.program.items[i].kind, ItemKind::Const(_)));
+
+        let length = match index {
+            Some(index) => match self.consts.item(index) {
+                Some(Value::Int(length)) => usize::try_from(length).ok(),
+                Some(_) => None,
+                None => return None,
+            },
+            None => None,
+        };
+
+        if length.is_none() {
+            self.error(
+                TypeErrorKind::ArrayLength {
+                    name: name.to_string(),
+                },
+                span,
+            );
+        }
+
+        length
+    }
+
+    /// Whether a name was brought in by an import.
+    fn is_imported(&self, name: &str) -> bool {
+        self.resolution
+            .symbols
+            .iter()
+            .any(|symbol| symbol.kind == SymbolKind::Import && symbol.name == name)
+    }
+
+    /// The symbol a node with a span declares with a name.
+    fn declaration(&self, span: Span, name: &str) -> Option<SymbolId> {
+        self.declarations.get(&(span, name)).copied()
+    }
+
+    /// Give the symbol a node declares with a name a type.
+    fn declare(&mut self, span: Span, name: &str, ty: Ty) {
+        if let Some(symbol) = self.declaration(span, name) {
+            self.body().symbols.insert(symbol, ty);
+        }
+    }
+
+    /// Check an item, if it hasn't been already, returning the type of its
+    /// value or what it returns.
+    fn item(&mut self, index: usize) -> Ty {
+        match &self.states[index] {
+            State::Checked(ty) => return ty.clone(),
+            // An item whose type depends on itself, which is only known if
+            // it was declared
+            State::Checking => return Ty::Unknown,
+            State::Unchecked => {}
+        }
+
+        self.states[index] = State::Checking;
+
+        // An item can be checked when it is first used, which could be in
+        // an instance of a generic function, whose types it doesn't share
+        let current = std::mem::replace(&mut self.current, 0);
+        let generics = std::mem::take(&mut self.generics);
+
+        let item = &self.parsed.program.items[index];
+
+        let ty = match &item.kind {
+            // The bodies of generic functions are checked for each instance
+            ItemKind::Function(function) if !function.generics.is_empty() => Ty::Unknown,
+            ItemKind::Function(function) => {
+                let (params, ret) = self.signature(index);
+
+                self.function(function, params, ret, item.span)
+            }
+            ItemKind::Global(decl) | ItemKind::Const(decl) => match &self.declared[index] {
+                Declared::Value(Some(ty)) => {
+                    let ty = ty.clone();
+
+                    self.expect(decl.value, &ty, Some(item.span));
+
+                    ty
+                }
+                _ => self.infer(decl.value, item.span),
+            },
+            ItemKind::StaticAssert(assert) => {
+                self.expect(assert.condition, &Ty::Bool, None);
+
+                Ty::unit()
+            }
+            ItemKind::Impl(implementation) => {
+                self.implementation(index, implementation, item.span);
+
+                Ty::unit()
+            }
+            ItemKind::Import(_) | ItemKind::Struct(_) | ItemKind::Enum(_) | ItemKind::Trait(_) => {
+                Ty::unit()
+            }
+        };
+
+        self.current = current;
+        self.generics = generics;
+        self.states[index] = State::Checked(ty.clone());
+
+        ty
+    }
+
+    /// The type of an item used as a value.
+    fn item_ty(&mut self, index: usize) -> Ty {
+        match &self.declared[index] {
+            Declared::Function {
+                params,
+                ret: Some(ret),
+            } => Ty::Function {
+                params: params.clone(),
+                ret: Box::new(ret.clone()),
+            },
+            Declared::Function { params, ret: None } => {
+                let params = params.clone();
+
+                Ty::Function {
+                    params,
+                    ret: Box::new(self.item(index)),
+                }
+            }
+            Declared::Value(Some(ty)) => ty.clone(),
+            Declared::Value(None) => self.item(index),
+            Declared::Trait(_) | Declared::Impl { .. } | Declared::None => Ty::Unknown,
+        }
+    }
+
+    /// The position of the generic function an expression names, if it names
+    /// one.
+    fn generic_function(&self, id: ExprId) -> Option<usize> {
+        let ExprKind::Identifier(name) = &self.parsed.arena[id].kind else {
+            return None;
+        };
+
+        let &symbol = self.resolution.uses.get(&id)?;
+
+        if self.resolution.symbol(symbol).kind != SymbolKind::Function {
+            return None;
+        }
+
+        let &index = self.items.get(name.as_str())?;
+
+        match &self.parsed.program.items[index].kind {
+            ItemKind::Function(function) if !function.generics.is_empty() => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Check a call at `span` to the generic function at `index`, finding
+    /// its type parameters from the arguments, or from what it is expected
+    /// to return.
+    fn generic_call(
+        &mut self,
+        index: usize,
+        callee: ExprId,
+        args: &[ExprId],
+        hint: Option<&Ty>,
+        span: Span,
+    ) -> Ty {
+        let (params, ret) = match &self.declared[index] {
+            Declared::Function { params, ret } => (params.clone(), ret.clone()),
+            _ => (Vec::new(), None),
+        };
+
+        let mut bindings = HashMap::new();
+
+        // E.g. `T` is `u8` in `let a : u8 = max(1, 2);`
+        if let (Some(ret), Some(hint)) = (&ret, hint) {
+            ret.bind(hint, &mut bindings);
+        }
+
+        if params.len() != args.len() {
+            self.error(
+                TypeErrorKind::ArgumentCount {
+                    expected: params.len(),
+                    found: args.len(),
+                },
+                span,
+            );
+        }
+
+        // Integer literals are checked last, so they take the types the
+        // other arguments decide, like the operands of a binary operator
+        let mut order: Vec<_> = (0..args.len()).collect();
+
+        order.sort_by_key(|&i| self.is_literal(args[i]));
+
+        for i in order {
+            match params.get(i) {
+                Some(param) => self.expect_generic(args[i], param, &mut bindings),
+                None => {
+                    self.expr(args[i], None);
+                }
+            }
+        }
+
+        let instance = self.instantiate(index, bindings, span);
+
+        let ty = Ty::Function {
+            params: params
+                .iter()
+                .map(|param| param.substitute(&instance.bindings))
+                .collect(),
+            ret: Box::new(instance.ret.clone()),
+        };
+
+        self.body().exprs.insert(callee, ty);
+
+        if let Some(position) = instance.position {
+            self.body().instantiated.insert(callee, position);
+        }
+
+        instance.ret
+    }
+
+    /// The type of the generic function at `index` used as a value at
+    /// `span`, without calling it, which has to be expected to have a
+    /// function type that decides its type parameters.
+    fn generic_value(&mut self, index: usize, id: ExprId, hint: Option<&Ty>, span: Span) -> Ty {
+        let ty = self.item_ty(index);
+        let mut bindings = HashMap::new();
+
+        if let Some(hint) = hint {
+            ty.bind(hint, &mut bindings);
+        }
+
+        let instance = self.instantiate(index, bindings, span);
+
+        if let Some(position) = instance.position {
+            self.body().instantiated.insert(id, position);
+        }
+
+        match ty {
+            Ty::Function { params, .. } => Ty::Function {
+                params: params
+                    .iter()
+                    .map(|param| param.substitute(&instance.bindings))
+                    .collect(),
+                ret: Box::new(instance.ret),
+            },
+            ty => ty,
+        }
+    }
+
+    /// Check a value against a type that can use type parameters, finding
+    /// the ones it decides.
+    fn expect_generic(&mut self, id: ExprId, expected: &Ty, bindings: &mut HashMap<String, Ty>) {
+        let expected = expected.substitute(bindings);
+
+        if !expected.has_params() {
+            self.expect(id, &expected, None);
+
+            return;
+        }
+
+        let found = self.expr(id, None);
+
+        expected.bind(&found, bindings);
+
+        let expected = expected.substitute(bindings);
+
+        if !found.matches(&expected) {
+            self.mismatch(&expected, found, id, None);
+        }
+    }
+
+    /// The types the type parameters of a generic item stand for, in order,
+    /// reporting the ones that couldn't be found at `span`.
+    fn type_args(
+        &mut self,
+        name: &str,
+        generics: &[TypeParam],
+        bindings: &HashMap<String, Ty>,
+        span: Span,
+    ) -> Vec<Ty> {
+        generics
+            .iter()
+            .map(|param| match bindings.get(&param.name) {
+                Some(ty) => ty.clone(),
+                None => {
+                    self.error(
+                        TypeErrorKind::CannotInfer {
+                            what: format!("'{}' for '{}'", param.name, name),
+                        },
+                        span,
+                    );
+
+                    Ty::Unknown
+                }
+            })
+            .collect()
+    }
+
+    /// Check the body of the generic function at `index`, used at `span`,
+    /// with the types its type parameters stand for, unless it was already
+    /// checked with them.
+    fn instantiate(
+        &mut self,
+        index: usize,
+        bindings: HashMap<String, Ty>,
+        span: Span,
+    ) -> Instantiated {
+        let item = &self.parsed.program.items[index];
+
+        let ItemKind::Function(function) = &item.kind else {
+            return Instantiated {
+                position: None,
+                bindings,
+                ret: Ty::Unknown,
+            };
+        };
+
+        let args = self.type_args(&function.name, &function.generics, &bindings, span);
+
+        let bindings: HashMap<_, _> = function
+            .generics
+            .iter()
+            .map(|param| param.name.clone())
+            .zip(args.iter().cloned())
+            .collect();
+
+        let declared = match &self.declared[index] {
+            Declared::Function { ret: Some(ret), .. } => ret.substitute(&bindings),
+            _ => Ty::Unknown,
+        };
+
+        // The body would only find the same mistake, once for each method it
+        // calls that the types don't have
+        if !self.check_bounds(&function.generics, &args, span) {
+            return Instantiated {
+                position: None,
+                bindings,
+                ret: declared,
+            };
+        }
+
+        // An instance that is being checked returns what it is declared to,
+        // if it is declared
+        if let Some(&instance) = self.instance_keys.get(&(index, args.clone())) {
+            return Instantiated {
+                position: Some(instance),
+                bindings,
+                ret: self.types.instances[instance].ret.clone(),
+            };
+        }
+
+        let nested = self.instantiating.iter().filter(|&&i| i == index).count();
+
+        if nested >= INSTANTIATION_LIMIT {
+            self.error(
+                TypeErrorKind::InstantiationLimit {
+                    name: function.name.clone(),
+                },
+                span,
+            );
+
+            return Instantiated {
+                position: None,
+                bindings,
+                ret: Ty::Unknown,
+            };
+        }
+
+        let instance = self.types.instances.len();
+
+        self.types.instances.push(Instance {
+            item: index,
+            args: args.clone(),
+            ret: declared,
+            exprs: HashMap::new(),
+            symbols: HashMap::new(),
+            instantiated: HashMap::new(),
+            calls: HashMap::new(),
+        });
+        self.instance_keys.insert((index, args), instance);
+
+        let generics = std::mem::replace(&mut self.generics, bindings.clone());
+
+        self.bodies.push(Body::default());
+
+        let current = std::mem::replace(&mut self.current, self.bodies.len() - 1);
+
+        self.instantiating.push(index);
+
+        let (params, ret) = self.signature(index);
+        let ret = self.function(function, params, ret, item.span);
+
+        self.instantiating.pop();
+        self.current = current;
+        self.generics = generics;
+
+        let body = self.bodies.pop().unwrap_or_default();
+        let checked = &mut self.types.instances[instance];
+
+        checked.ret = ret.clone();
+        checked.exprs = body.exprs;
+        checked.symbols = body.symbols;
+        checked.instantiated = body.instantiated;
+        checked.calls = body.calls;
+
+        Instantiated {
+            position: Some(instance),
+            bindings,
+            ret,
+        }
+    }
+
+    /// The position of the trait with a name, if it is declared in the file.
+    fn trait_declaration(&self, name: &str) -> Option<usize> {
+        self.items
+            .get(name)
+            .copied()
+            .filter(|&i| matches!(self.parsed.program.items[i].kind, ItemKind::Trait(_)))
+    }
+
+    /// Report a trait written at `span` that doesn't exist. Traits from
+    /// other files can't be known yet.
+    fn check_trait(&mut self, name: &str, span: Span) {
+        if self.trait_declaration(name).is_none() && !self.is_imported(name) {
+            self.error(
+                TypeErrorKind::UnknownTrait {
+                    name: name.to_string(),
+                },
+                span,
+            );
+        }
+    }
+
+    /// Whether a type implements a trait declared in the file. Types that
+    /// aren't known yet might, and so do type parameters, whose types are
+    /// checked when they are used.
+    fn implements(&self, ty: &Ty, trait_name: &str) -> bool {
+        if matches!(ty, Ty::Unknown | Ty::Never) || ty.has_params() {
+            return true;
+        }
+
+        if self.trait_declaration(trait_name).is_none() {
+            return true;
+        }
+
+        self.impls
+            .iter()
+            .any(|(_, name, implemented)| *name == trait_name && implemented == ty)
+    }
+
+    /// Check that the types given to type parameters at `span` implement the
+    /// traits they have to, returning whether they all do.
+    fn check_bounds(&mut self, generics: &[TypeParam], args: &[Ty], span: Span) -> bool {
+        let mut holds = true;
+
+        for (param, ty) in generics.iter().zip(args) {
+            for bound in &param.bounds {
+                if !self.implements(ty, bound) {
+                    self.error(
+                        TypeErrorKind::UnsatisfiedBound {
+                            ty: ty.clone(),
+                            trait_name: bound.clone(),
+                        },
+                        span,
+                    );
+
+                    holds = false;
+                }
+            }
+        }
+
+        holds
+    }
+
+    /// The types of a method in an impl, while `Self` stands for the type of
+    /// the impl. A method declared with `:=` returns what its trait says it
+    /// does.
+    fn method_ty(&mut self, implementation: &Impl, method: &Method) -> MethodTy {
+        let function = &method.function;
+
+        let params = function
+            .params
+            .iter()
+            .map(|param| self.lower(&param.ty, method.span))
+            .collect();
+
+        let ret = match &function.return_type {
+            Some(ty) => self.lower(ty, method.span),
+            None => {
+                let required = self
+                    .trait_declaration(&implementation.trait_name)
+                    .map(|i| &self.parsed.program.items[i])
+                    .and_then(|item| match &item.kind {
+                        ItemKind::Trait(declaration) => declaration
+                            .methods
+                            .iter()
+                            .find(|required| required.name == function.name)
+                            .and_then(|required| required.return_type.as_ref())
+                            .map(|ty| (ty, item.span)),
+                        _ => None,
+                    });
+
+                match required {
+                    Some((ty, span)) => self.lower(ty, span),
+                    None => Ty::unit(),
+                }
+            }
+        };
+
+        MethodTy {
+            name: function.name.clone(),
+            params,
+            ret,
+        }
+    }
+
+    /// Check that the impl at `index` has the methods its trait requires,
+    /// with the types it requires, then check their bodies.
+    fn implementation(&mut self, index: usize, implementation: &Impl, span: Span) {
+        let Declared::Impl { ty, methods } = &self.declared[index] else {
+            return;
+        };
+
+        let (ty, methods) = (ty.clone(), methods.clone());
+        let trait_name = &implementation.trait_name;
+
+        let required = match self
+            .trait_declaration(trait_name)
+            .map(|i| &self.declared[i])
+        {
+            Some(Declared::Trait(required)) => Some(required.clone()),
+            _ => None,
+        };
+
+        match required {
+            Some(required) => self.conforms(index, implementation, &ty, &methods, &required, span),
+            None => self.check_trait(trait_name, span),
+        }
+
+        self.generics.insert("Self".to_string(), ty);
+
+        for (i, (method, declared)) in implementation.methods.iter().zip(methods).enumerate() {
+            self.function(
+                &method.function,
+                declared.params,
+                Some(declared.ret.clone()),
+                method.span,
+            );
+
+            self.types.methods.insert(
+                MethodId {
+                    item: index,
+                    method: i,
+                },
+                declared.ret,
+            );
+        }
+    }
+
+    /// Check that an impl at `span`, for `ty`, is the only one of its trait
+    /// for the type, and has the methods the trait requires.
+    fn conforms(
+        &mut self,
+        index: usize,
+        implementation: &Impl,
+        ty: &Ty,
+        methods: &[MethodTy],
+        required: &[MethodTy],
+        span: Span,
+    ) {
+        let trait_name = &implementation.trait_name;
+
+        let previous = self
+            .impls
+            .iter()
+            .find(|(other, name, other_ty)| {
+                *other < index && name == trait_name && other_ty == ty && *ty != Ty::Unknown
+            })
+            .map(|&(other, ..)| self.parsed.program.items[other].span);
+
+        if let Some(previous) = previous {
+            self.errors.push(
+                TypeError::new(
+                    TypeErrorKind::DuplicateImpl {
+                        trait_name: trait_name.clone(),
+                        ty: ty.clone(),
+                    },
+                    span,
+                )
+                .declared_at(previous),
+            );
+        }
+
+        let missing: Vec<_> = required
+            .iter()
+            .filter(|method| !methods.iter().any(|other| other.name == method.name))
+            .map(|method| method.name.clone())
+            .collect();
+
+        if !missing.is_empty() {
+            self.error(
+                TypeErrorKind::MissingMethods {
+                    trait_name: trait_name.clone(),
+                    ty: ty.clone(),
+                    methods: missing,
+                },
+                span,
+            );
+        }
+
+        // `Self` in the trait is the type of the impl
+        let bindings = HashMap::from([("Self".to_string(), ty.clone())]);
+
+        for (method, declared) in implementation.methods.iter().zip(methods) {
+            let Some(expected) = required.iter().find(|other| other.name == declared.name) else {
+                self.error(
+                    TypeErrorKind::NotInTrait {
+                        trait_name: trait_name.clone(),
+                        method: declared.name.clone(),
+                    },
+                    method.span,
+                );
+
+                continue;
+            };
+
+            let expected = Ty::Function {
+                params: expected
+                    .params
+                    .iter()
+                    .map(|ty| ty.substitute(&bindings))
+                    .collect(),
+                ret: Box::new(expected.ret.substitute(&bindings)),
+            };
+
+            let found = Ty::Function {
+                params: declared.params.clone(),
+                ret: Box::new(declared.ret.clone()),
+            };
+
+            if !found.matches(&expected) {
+                self.error(
+                    TypeErrorKind::MethodSignature {
+                        trait_name: trait_name.clone(),
+                        method: declared.name.clone(),
+                        expected,
+                        found,
+                    },
+                    method.span,
+                );
+            }
+        }
+    }
+
+    /// The method a call at `span` calls on a value of type `ty`, from the
+    /// traits implemented for the type, reporting it if there isn't exactly
+    /// one.
+    fn method(&mut self, ty: &Ty, name: &str, span: Span) -> Option<(MethodId, MethodTy)> {
+        // Imported values could have any methods
+        if matches!(ty, Ty::Unknown | Ty::Never) {
+            return None;
+        }
+
+        let mut found = self.implemented(ty, name);
+
+        match found.len() {
+            0 => {
+                self.error(
+                    TypeErrorKind::NoMethod {
+                        ty: ty.clone(),
+                        method: name.to_string(),
+                    },
+                    span,
+                );
+
+                None
+            }
+            1 => found.pop().map(|(id, method, _)| (id, method)),
+            _ => {
+                self.ambiguous(ty, name, found, span);
+
+                None
+            }
+        }
+    }
+
+    /// The methods named `name` of the traits implemented for `ty`, with the
+    /// trait each is from.
+    fn implemented(&self, ty: &Ty, name: &str) -> Vec<(MethodId, MethodTy, String)> {
+        let mut found = Vec::new();
+
+        for (item, trait_name, implemented) in &self.impls {
+            let Declared::Impl { methods, .. } = &self.declared[*item] else {
+                continue;
+            };
+
+            let position = methods.iter().position(|method| method.name == name);
+
+            if let (Some(method), true) = (position, implemented == ty) {
+                let id = MethodId {
+                    item: *item,
+                    method,
+                };
+
+                found.push((id, methods[method].clone(), trait_name.to_string()));
+            }
+        }
+
+        found
+    }
+
+    /// Report a method call at `span` that could call a method of more than
+    /// one trait.
+    fn ambiguous(
+        &mut self,
+        ty: &Ty,
+        name: &str,
+        found: Vec<(MethodId, MethodTy, String)>,
+        span: Span,
+    ) {
+        self.error(
+            TypeErrorKind::AmbiguousMethod {
+                ty: ty.clone(),
+                method: name.to_string(),
+                traits: found.into_iter().map(|(.., name)| name).collect(),
+            },
+            span,
+        );
+    }
+
+    /// Check a call at `span` of a method found for the receiver, which has
+    /// been checked and is passed as the first parameter, returning what the
+    /// method returns.
+    fn call_method(
+        &mut self,
+        id: ExprId,
+        (found, method): (MethodId, MethodTy),
+        receiver: ExprId,
+        args: &[ExprId],
+        span: Span,
+    ) -> Ty {
+        let receiver_ty = self.body().exprs[&receiver].clone();
+
+        self.body().calls.insert(id, found);
+
+        match method.params.split_first() {
+            Some((first, rest)) => {
+                if !receiver_ty.matches(first) {
+                    self.mismatch(first, receiver_ty, receiver, None);
+                }
+
+                self.arguments(rest, args, span);
+            }
+            // A method without parameters can't be given a receiver
+            None => {
+                self.error(
+                    TypeErrorKind::ArgumentCount {
+                        expected: 0,
+                        found: args.len() + 1,
+                    },
+                    span,
+                );
+
+                for arg in args {
+                    self.expr(*arg, None);
+                }
+            }
+        }
+
+        method.ret
+    }
+
+    /// Check an operator at `span` used on a struct or enum, which calls the
+    /// method named for it with the operands, e.g. `a + b` calls `a.add(b)`.
+    /// The first operand has already been checked.
+    fn operator(
+        &mut self,
+        id: ExprId,
+        op: &str,
+        name: &str,
+        receiver: ExprId,
+        operands: &[ExprId],
+        span: Span,
+    ) -> Ty {
+        let receiver_ty = self.body().exprs[&receiver].clone();
+        let mut found = self.implemented(&receiver_ty, name);
+
+        if found.len() != 1 {
+            for operand in operands {
+                self.expr(*operand, None);
+            }
+
+            if found.is_empty() {
+                return self.invalid_operand(op, receiver_ty, span);
+            }
+
+            self.ambiguous(&receiver_ty, name, found, span);
+
+            return Ty::Unknown;
+        }
+
+        let (found, method, _) = found.remove(0);
+
+        if method.params.len() != operands.len() + 1 {
+            self.error(
+                TypeErrorKind::OperatorMethod {
+                    op: op.to_string(),
+                    method: name.to_string(),
+                    expected: operands.len() + 1,
+                    found: method.params.len(),
+                },
+                span,
+            );
+
+            for operand in operands {
+                self.expr(*operand, None);
+            }
+
+            return method.ret;
+        }
+
+        self.call_method(id, (found, method), receiver, operands, span)
+    }
+
+    /// The declared parameters and return type of the function at `index`.
+    /// A generic function's are given the types its type parameters stand
+    /// for in `generics`.
+    fn signature(&self, index: usize) -> (Vec<Ty>, Option<Ty>) {
+        match &self.declared[index] {
+            Declared::Function { params, ret } => (
+                params
+                    .iter()
+                    .map(|ty| ty.substitute(&self.generics))
+                    .collect(),
+                ret.as_ref().map(|ty| ty.substitute(&self.generics)),
+            ),
+            _ => (Vec::new(), None),
+        }
+    }
+
+    /// Check the body of a function or method at `span`, which takes
+    /// `params`, returning what it returns.
+    fn function(
+        &mut self,
+        function: &Function,
+        params: Vec<Ty>,
+        ret: Option<Ty>,
+        span: Span,
+    ) -> Ty {
+        for (param, ty) in function.params.iter().zip(params) {
+            self.declare(span, &param.name, ty);
+        }
+
+        self.returns.push(Return {
+            declared: ret.as_ref().map(|_| span),
+            ty: ret.clone(),
+        });
+
+        // The value the body ends with is what the function returns, so
+        // literals there take the declared type
+        let body = self.block(&function.body, ret.as_ref());
+
+        // An `if let` without an `else` has no value when its pattern
+        // doesn't match, so a body ending with one can reach its end
+        // without a value too
+        let value = function.body.value.filter(|&value| {
+            !matches!(
+                self.parsed.arena[value].kind,
+                ExprKind::IfLet {
+                    otherwise: None,
+                    ..
+                }
+            )
+        });
+
+        let ret = self.finish_return(body.clone(), value);
+
+        // A body without a value has to return on every path, unless there
+        // is nothing to return. Loops might not run at all, so only a
+        // `return`, or an `if let` returning from both branches, counts.
+        let missing = !(ret.matches(&Ty::unit()) || body == Ty::Never);
+
+        if value.is_none() && missing {
+            self.error(
+                TypeErrorKind::MissingReturn {
+                    name: function.name.clone(),
+                    ty: ret.clone(),
+                },
+                span,
+            );
+        }
+
+        ret
+    }
+
+    /// Check the value a function or closure ends with against what it
+    /// returns, and stop checking its returns. Returns its return type.
+    fn finish_return(&mut self, body: Ty, value: Option<ExprId>) -> Ty {
+        let Some(slot) = self.returns.pop() else {
+            return body;
+        };
+
+        match (slot.ty, value) {
+            (Some(ty), Some(value)) => {
+                if !body.matches(&ty) {
+                    self.mismatch(&ty, body, value, slot.declared);
+                }
+
+                ty
+            }
+            (Some(ty), None) => ty,
+            (None, _) => body,
+        }
+    }
+
+    /// Report a value that doesn't have the type it should, declared at
+    /// `declared` if it was.
+    fn mismatch(&mut self, expected: &Ty, found: Ty, id: ExprId, declared: Option<Span>) {
+        let mut error = TypeError::new(
+            TypeErrorKind::Mismatch {
+                expected: expected.clone(),
+                found,
+            },
+            self.parsed.arena[id].span,
+        );
+
+        if let Some(declared) = declared {
+            error = error.declared_at(declared);
+        }
+
+        self.errors.push(error);
+    }
+
+    /// Check that an expression has a type, which was declared at
+    /// `declared` if it was, returning the type it has.
+    fn expect(&mut self, id: ExprId, expected: &Ty, declared: Option<Span>) -> Ty {
+        let found = self.expr(id, Some(expected));
+
+        if !found.matches(expected) {
+            self.mismatch(expected, found.clone(), id, declared);
+        }
+
+        found
+    }
+
+    /// Infer the type of the value a `:=` declaration at `span` gives a
+    /// name, pointing at the declaration if there's nothing to infer it from.
+    fn infer(&mut self, value: ExprId, span: Span) -> Ty {
+        let start = self.errors.len();
+        let ty = self.expr(value, None);
+
+        for error in &mut self.errors[start..] {
+            if matches!(error.kind, TypeErrorKind::CannotInfer { .. }) {
+                error.declared = Some(span);
+            }
+        }
+
+        ty
+    }
+
+    /// Check a block, returning the type of its value, or `Never` if it
+    /// always returns before reaching the end.
+    fn block(&mut self, block: &Block, hint: Option<&Ty>) -> Ty {
+        let mut diverges = false;
+
+        for stmt in &block.stmts {
+            diverges |= self.stmt(stmt);
+        }
+
+        let ty = match block.value {
+            Some(value) => self.expr(value, hint),
+            None => Ty::unit(),
+        };
+
+        if diverges {
+            Ty::Never
+        } else {
+            ty
+        }
+    }
+
+    /// Check a statement, returning whether it always returns.
+    fn stmt(&mut self, stmt: &Stmt) -> bool {
+        match &stmt.kind {
+            StmtKind::Let(decl) => {
+                let ty = match &decl.ty {
+                    Some(ty) => {
+                        let ty = self.lower(ty, stmt.span);
+
+                        self.expect(decl.value, &ty, Some(stmt.span));
+
+                        ty
+                    }
+                    None => self.infer(decl.value, stmt.span),
+                };
+
+                let diverges = self.bodies[self.current].exprs.get(&decl.value) == Some(&Ty::Never);
+
+                self.declare(stmt.span, &decl.name, ty);
+
+                diverges
+            }
+            StmtKind::Return(value) => {
+                let found = match value {
+                    Some(value) => {
+                        let hint = self.returns.last().and_then(|slot| slot.ty.clone());
+
+                        self.expr(*value, hint.as_ref())
+                    }
+                    None => Ty::unit(),
+                };
+
+                let Some(slot) = self.returns.last_mut() else {
+                    return true;
+                };
+
+                let (expected, declared) = match &slot.ty {
+                    Some(expected) => (expected.clone(), slot.declared),
+                    None => {
+                        slot.ty = Some(found);
+
+                        return true;
+                    }
+                };
+
+                let error = match value {
+                    Some(value) if expected.is_unit() && !found.is_unit() => Some(TypeError::new(
+                        TypeErrorKind::ReturnValue { ty: found },
+                        self.parsed.arena[*value].span,
+                    )),
+                    Some(value) if !found.matches(&expected) => Some(TypeError::new(
+                        TypeErrorKind::Mismatch { expected, found },
+                        self.parsed.arena[*value].span,
+                    )),
+                    None if !found.matches(&expected) => Some(TypeError::new(
+                        TypeErrorKind::Mismatch { expected, found },
+                        stmt.span,
+                    )),
+                    _ => None,
+                };
+
+                if let Some(mut error) = error {
+                    error.declared = declared;
+
+                    self.errors.push(error);
+                }
+
+                true
+            }
+            StmtKind::While { condition, body } => {
+                self.expect(*condition, &Ty::Bool, None);
+                self.block(body, None);
+
+                false
+            }
+            StmtKind::WhileLet {
+                pattern,
+                value,
+                body,
+            } => {
+                let ty = self.expr(*value, None);
+
+                self.pattern(pattern, &ty, stmt.span);
+                self.block(body, None);
+
+                false
+            }
+            StmtKind::Defer(body) => {
+                self.block(body, None);
+
+                false
+            }
+            StmtKind::Assert(assert) | StmtKind::StaticAssert(assert) => {
+                self.expect(assert.condition, &Ty::Bool, None);
+
+                false
+            }
+            // These leave a loop, but not the function
+            StmtKind::Break | StmtKind::Continue => false,
+            StmtKind::Expr(expr) => self.expr(*expr, None) == Ty::Never,
+        }
+    }
+
+    /// Check the names a pattern binds against the type of the value it is
+    /// matched with. Errors are reported at `span`, the node with the
+    /// pattern.
+    fn pattern(&mut self, pattern: &Pattern, ty: &Ty, span: Span) {
+        let mismatch = |pattern: &Pattern, ty: &Ty| {
+            TypeError::new(
+                TypeErrorKind::PatternMismatch {
+                    pattern: pattern.to_string(),
+                    ty: ty.clone(),
+                },
+                span,
+            )
+        };
+
+        match (pattern, ty) {
+            (Pattern::Wildcard, _) => {}
+            (Pattern::Binding(name), _) => self.declare(span, name, ty.clone()),
+            (Pattern::Bool(_), Ty::Bool | Ty::Unknown) => {}
+            (Pattern::Number(_), Ty::Int(_) | Ty::Unknown) => {}
+            (Pattern::String(_), Ty::Str | Ty::Unknown) => {}
+            (Pattern::Tuple(patterns), Ty::Tuple(types)) if patterns.len() == types.len() => {
+                for (pattern, ty) in patterns.iter().zip(types) {
+                    self.pattern(pattern, ty, span);
+                }
+            }
+            (
+                Pattern::Tuple(patterns)
+                | Pattern::Variant {
+                    payload: patterns, ..
+                },
+                Ty::Unknown,
+            ) => {
+                for pattern in patterns {
+                    self.pattern(pattern, &Ty::Unknown, span);
+                }
+            }
+            (
+                Pattern::Variant {
+                    enum_name: None,
+                    variant,
+                    payload,
+                },
+                Ty::Optional(inner),
+            ) if variant == "Some" && payload.len() == 1 => {
+                self.pattern(&payload[0], inner, span);
+            }
+            (
+                Pattern::Variant {
+                    enum_name,
+                    variant,
+                    payload,
+                },
+                Ty::Enum(name),
+            ) if enum_name.as_ref().is_none_or(|enum_name| enum_name == name) => {
+                let Some(types) = self.variant(name, variant, span) else {
+                    return;
+                };
+
+                if types.len() != payload.len() {
+                    self.error(
+                        TypeErrorKind::ArgumentCount {
+                            expected: types.len(),
+                            found: payload.len(),
+                        },
+                        span,
+                    );
+                }
+
+                for (pattern, ty) in payload.iter().zip(&types) {
+                    self.pattern(pattern, ty, span);
+                }
+            }
+            _ => {
+                let error = mismatch(pattern, ty);
+
+                self.errors.push(error);
+            }
+        }
+    }
+
+    /// The fields of a struct, with the types its type parameters stand for.
+    fn fields(&self, name: &str, args: &[Ty]) -> Option<Vec<(String, Ty)>> {
+        let fields = self.structs.get(name)?;
+
+        let Some(generics) = self.struct_generics.get(name) else {
+            return Some(fields.clone());
+        };
+
+        let args = generics
+            .iter()
+            .map(|param| param.name.clone())
+            .zip(args.iter().cloned())
+            .collect();
+
+        Some(
+            fields
+                .iter()
+                .map(|(field, ty)| (field.clone(), ty.substitute(&args)))
+                .collect(),
+        )
+    }
+
+    /// The payload types of a variant of an enum, reporting it at `span` if
+    /// there is no such variant.
+    fn variant(&mut self, enum_name: &str, variant: &str, span: Span) -> Option<Vec<Ty>> {
+        let payload = self.enums.get(enum_name).and_then(|variants| {
+            variants
+                .iter()
+                .find(|(name, _)| name == variant)
+                .map(|(_, payload)| payload.clone())
+        });
+
+        if payload.is_none() {
+            self.error(
+                TypeErrorKind::UnknownVariant {
+                    enum_name: enum_name.to_string(),
+                    variant: variant.to_string(),
+                },
+                span,
+            );
+        }
+
+        payload
+    }
+
+    /// Whether an expression is an integer literal, which takes the type it
+    /// is expected to have.
+    fn is_literal(&self, id: ExprId) -> bool {
+        match &self.parsed.arena[id].kind {
+            ExprKind::Number(_) => true,
+            ExprKind::Unary {
+                op: UnaryOp::Negate,
+                operand,
+            } => self.is_literal(*operand),
+            _ => false,
+        }
+    }
+
+    /// Find the type of an expression, and record it. `hint` is the type it
+    /// is expected to have, if that is known, which integer literals take.
+    fn expr(&mut self, id: ExprId, hint: Option<&Ty>) -> Ty {
+        let ty = self.expr_kind(id, hint);
+
+        self.body().exprs.insert(id, ty.clone());
+
+        ty
+    }
+
+    fn expr_kind(&mut self, id: ExprId, hint: Option<&Ty>) -> Ty {
+        let arena = &self.parsed.arena;
+        let span = arena[id].span;
+
+        match &arena[id].kind {
+            ExprKind::Bool(_) => Ty::Bool,
+            ExprKind::Number(_) => match hint {
+                Some(Ty::Int(int)) => Ty::Int(*int),
+                _ => Ty::Int(IntTy::U32),
+            },
+            ExprKind::String(_) => Ty::Str,
+            ExprKind::Identifier(name) => self.identifier(id, name, hint, span),
+            ExprKind::Tuple(elements) => {
+                let hints = match hint {
+                    Some(Ty::Tuple(types)) if types.len() == elements.len() => Some(types),
+                    _ => None,
+                };
+
+                let types = elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, element)| self.expr(*element, hints.map(|hints| &hints[i])))
+                    .collect();
+
+                Ty::Tuple(types)
+            }
+            ExprKind::Array(elements) => {
+                let hint = match hint {
+                    Some(Ty::Array { element, .. }) => Some(&**element),
+                    _ => None,
+                };
+
+                let element = match (elements.first(), hint) {
+                    (Some(&first), _) => {
+                        let ty = self.expr(first, hint);
+
+                        for element in &elements[1..] {
+                            self.expect(*element, &ty, None);
+                        }
+
+                        ty
+                    }
+                    (None, Some(hint)) => hint.clone(),
+                    (None, None) => {
+                        self.error(
+                            TypeErrorKind::CannotInfer {
+                                what: "an empty array".to_string(),
+                            },
+                            span,
+                        );
+
+                        Ty::Unknown
+                    }
+                };
+
+                Ty::Array {
+                    element: Box::new(element),
+                    length: Some(elements.len()),
+                }
+            }
+            ExprKind::Block(block) => self.block(block, hint),
+            ExprKind::Unary { op, operand } => match op {
+                UnaryOp::Negate => {
+                    // Negative literals are `i32` unless they are expected
+                    // to be another type
+                    let hint = match hint {
+                        Some(Ty::Int(int)) => Ty::Int(*int),
+                        _ => Ty::Int(IntTy::I32),
+                    };
+
+                    let ty = self.expr(*operand, Some(&hint));
+
+                    match ty {
+                        Ty::Int(int) if int.is_signed() => ty,
+                        Ty::Unknown => ty,
+                        _ if overloads(&ty) => {
+                            self.operator(id, op.symbol(), op.method(), *operand, &[], span)
+                        }
+                        _ => self.invalid_operand(op.symbol(), ty, span),
+                    }
+                }
+                UnaryOp::Not => {
+                    let ty = self.expr(*operand, Some(&Ty::Bool));
+
+                    if overloads(&ty) {
+                        return self.operator(id, op.symbol(), op.method(), *operand, &[], span);
+                    }
+
+                    if !ty.matches(&Ty::Bool) {
+                        self.mismatch(&Ty::Bool, ty, *operand, None);
+                    }
+
+                    Ty::Bool
+                }
+            },
+            ExprKind::Binary { op, left, right } => self.binary(id, *op, *left, *right, hint, span),
+            ExprKind::StructLiteral { name, fields } => {
+                let Some(declared) = self.structs.get(name.as_str()).cloned() else {
+                    // An imported struct, whose fields can't be known yet
+                    for (_, value) in fields {
+                        self.expr(*value, None);
+                    }
+
+                    return Ty::Unknown;
+                };
+
+                let generics = self
+                    .struct_generics
+                    .get(name.as_str())
+                    .copied()
+                    .unwrap_or_default();
+
+                let mut bindings = HashMap::new();
+
+                // The type parameters of a generic struct are found from the
+                // type it is expected to have, then from its fields
+                if let Some(hint) = hint {
+                    let params = generics
+                        .iter()
+                        .map(|param| Ty::Param(param.name.clone()))
+                        .collect();
+
+                    Ty::Struct(name.clone(), params).bind(hint, &mut bindings);
+                }
+
+                // Integer literals are checked last, like arguments
+                let mut order: Vec<_> = fields.iter().collect();
+
+                order.sort_by_key(|(_, value)| self.is_literal(*value));
+
+                for (field, value) in order {
+                    match declared.iter().find(|(name, _)| name == field) {
+                        Some((_, field_ty)) => self.expect_generic(*value, field_ty, &mut bindings),
+                        None => {
+                            self.expr(*value, None);
+                            self.error(
+                                TypeErrorKind::NoField {
+                                    ty: Ty::Struct(name.clone(), Vec::new()),
+                                    field: field.clone(),
+                                },
+                                self.parsed.arena[*value].span,
+                            );
+                        }
+                    }
+                }
+
+                let missing: Vec<_> = declared
+                    .iter()
+                    .filter(|(name, _)| !fields.iter().any(|(field, _)| field == name))
+                    .map(|(name, _)| name.clone())
+                    .collect();
+
+                if !missing.is_empty() {
+                    self.error(
+                        TypeErrorKind::MissingFields {
+                            name: name.clone(),
+                            fields: missing,
+                        },
+                        span,
+                    );
+                }
+
+                let args = self.type_args(name, generics, &bindings, span);
+
+                self.check_bounds(generics, &args, span);
+
+                Ty::Struct(name.clone(), args)
+            }
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                let is_enum =
+                    self.resolution.uses.get(&id).is_some_and(|&symbol| {
+                        self.resolution.symbol(symbol).kind == SymbolKind::Enum
+                    });
+
+                let types = if is_enum {
+                    self.variant(enum_name, variant, span)
+                } else {
+                    None
+                };
+
+                let Some(types) = types else {
+                    for value in payload {
+                        self.expr(*value, None);
+                    }
+
+                    return Ty::Unknown;
+                };
+
+                self.arguments(&types, payload, span);
+
+                Ty::Enum(enum_name.clone())
+            }
+            ExprKind::Closure(closure) => self.closure(closure, hint, span),
+            ExprKind::Call { callee, args } => {
+                if let Some(index) = self.generic_function(*callee) {
+                    return self.generic_call(index, *callee, args, hint, span);
+                }
+
+                let callee_ty = self.expr(*callee, None);
+
+                match callee_ty {
+                    Ty::Function { params, ret } => {
+                        self.arguments(&params, args, span);
+
+                        *ret
+                    }
+                    Ty::Unknown => {
+                        for arg in args {
+                            self.expr(*arg, None);
+                        }
+
+                        Ty::Unknown
+                    }
+                    ty => {
+                        for arg in args {
+                            self.expr(*arg, None);
+                        }
+
+                        self.error(
+                            TypeErrorKind::NotCallable { ty },
+                            self.parsed.arena[*callee].span,
+                        );
+
+                        Ty::Unknown
+                    }
+                }
+            }
+            // Methods are found from the type of the receiver, which is
+            // passed as the first parameter
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                let receiver_ty = self.expr(*receiver, None);
+
+                // Built-in methods come before the methods of traits, the
+                // way a type's own methods would
+                if let Some((params, ret)) = builtin(&receiver_ty, method) {
+                    self.arguments(&params, args, span);
+
+                    return ret;
+                }
+
+                let Some((found, method)) = self.method(&receiver_ty, method, span) else {
+                    for arg in args {
+                        self.expr(*arg, None);
+                    }
+
+                    return Ty::Unknown;
+                };
+
+                self.call_method(id, (found, method), *receiver, args, span)
+            }
+            ExprKind::Index { object, index } => {
+                let object_ty = self.expr(*object, None);
+
+                if overloads(&object_ty) {
+                    return self.operator(id, "[]", "index", *object, &[*index], span);
+                }
+
+                let index_ty = self.expr(*index, None);
+
+                if !matches!(index_ty, Ty::Int(_) | Ty::Unknown) {
+                    self.error(
+                        TypeErrorKind::NotAnInteger { ty: index_ty },
+                        self.parsed.arena[*index].span,
+                    );
+                }
+
+                match object_ty {
+                    Ty::Array { element, .. } => *element,
+                    Ty::Str => Ty::Int(IntTy::U8),
+                    Ty::Unknown => Ty::Unknown,
+                    ty => {
+                        self.error(TypeErrorKind::NotIndexable { ty }, span);
+
+                        Ty::Unknown
+                    }
+                }
+            }
+            ExprKind::Cast { value, ty } => {
+                let from = self.expr(*value, None);
+                let to = self.lower(ty, span);
+
+                // Integers convert to each other, and `bool`s to integers,
+                // but nothing else converts to a different type
+                let converts = from.matches(&to)
+                    || matches!((&from, &to), (Ty::Int(_) | Ty::Bool, Ty::Int(_)));
+
+                if !converts {
+                    self.error(
+                        TypeErrorKind::InvalidCast {
+                            from,
+                            to: to.clone(),
+                        },
+                        span,
+                    );
+                }
+
+                to
+            }
+            ExprKind::Field { object, field } => {
+                let object_ty = self.expr(*object, None);
+
+                let field_ty = match &object_ty {
+                    Ty::Unknown => return Ty::Unknown,
+                    Ty::Struct(name, args) => self.fields(name, args).and_then(|fields| {
+                        fields
+                            .into_iter()
+                            .find(|(name, _)| name == field)
+                            .map(|(_, ty)| ty)
+                    }),
+                    _ => None,
+                };
+
+                field_ty.unwrap_or_else(|| {
+                    self.error(
+                        TypeErrorKind::NoField {
+                            ty: object_ty,
+                            field: field.clone(),
+                        },
+                        span,
+                    );
+
+                    Ty::Unknown
+                })
+            }
+            ExprKind::Assign { op, target, value } => {
+                let target_ty = self.expr(*target, None);
+
+                // An overloaded index returns a value, not somewhere to
+                // assign it
+                if let ExprKind::Index { object, .. } = &self.parsed.arena[*target].kind {
+                    if self.body().calls.contains_key(target) {
+                        let object_ty = self.body().exprs[object].clone();
+
+                        self.invalid_operand("[]=", object_ty, span);
+                    }
+
+                    // Strings can be read by byte, but not changed
+                    if self.body().exprs[object] == Ty::Str {
+                        self.invalid_operand("[]=", Ty::Str, span);
+                    }
+                }
+
+                if let Some(op) = op {
+                    let symbol = format!("{}=", op.symbol());
+
+                    // `a += b` is `a = a.add(b)` for structs and enums
+                    if let Some(method) = op.method().filter(|_| overloads(&target_ty)) {
+                        let ty = self.operator(id, &symbol, method, *target, &[*value], span);
+
+                        if !ty.matches(&target_ty) {
+                            self.error(
+                                TypeErrorKind::Mismatch {
+                                    expected: target_ty,
+                                    found: ty,
+                                },
+                                span,
+                            );
+                        }
+
+                        return Ty::unit();
+                    }
+
+                    if !self.operand_fits(*op, &target_ty) {
+                        self.invalid_operand(&symbol, target_ty.clone(), span);
+                    }
+                }
+
+                self.expect(*value, &target_ty, None);
+
+                if let Some(op) = op {
+                    self.imported_operands(&format!("{}=", op.symbol()), &[*target, *value], span);
+                }
+
+                Ty::unit()
+            }
+            ExprKind::IfLet {
+                pattern,
+                value,
+                then,
+                otherwise,
+            } => {
+                let value_ty = self.expr(*value, None);
+
+                self.pattern(pattern, &value_ty, span);
+
+                let then_ty = self.block(then, hint);
+
+                match otherwise {
+                    Some(otherwise) => {
+                        // A branch that always returns has no value, so the
+                        // other branch decides the type
+                        if then_ty == Ty::Never {
+                            return self.block(otherwise, hint);
+                        }
+
+                        let otherwise_ty = self.block(otherwise, Some(&then_ty));
+
+                        if !otherwise_ty.matches(&then_ty) {
+                            let error = TypeError::new(
+                                TypeErrorKind::Mismatch {
+                                    expected: then_ty.clone(),
+                                    found: otherwise_ty,
+                                },
+                                self.block_span(otherwise, span),
+                            )
+                            .declared_at(self.block_span(then, span));
+
+                            self.errors.push(error);
+                        }
+
+                        then_ty
+                    }
+                    // Without an `else`, there is no value when the pattern
+                    // doesn't match
+                    None => {
+                        if !then_ty.matches(&Ty::unit()) {
+                            self.error(
+                                TypeErrorKind::Mismatch {
+                                    expected: Ty::unit(),
+                                    found: then_ty,
+                                },
+                                self.block_span(then, span),
+                            );
+                        }
+
+                        Ty::unit()
+                    }
+                }
+            }
+            ExprKind::Match { value, arms } => {
+                let value_ty = self.expr(*value, None);
+
+                self.match_arms(arms, &value_ty, span);
+
+                // Like the branches of an `if let`, arms that always return
+                // have no value, and the first that has one decides the type
+                let mut first: Option<(Ty, ExprId)> = None;
+
+                for arm in arms {
+                    let hint = first.as_ref().map(|(ty, _)| ty.clone()).or(hint.cloned());
+                    let ty = self.expr(arm.value, hint.as_ref());
+
+                    match &first {
+                        _ if ty == Ty::Never => {}
+                        None => first = Some((ty, arm.value)),
+                        Some((expected, declared)) => {
+                            if !ty.matches(expected) {
+                                let declared = self.parsed.arena[*declared].span;
+
+                                self.mismatch(&expected.clone(), ty, arm.value, Some(declared));
+                            }
+                        }
+                    }
+                }
+
+                // Without an arm with a value, either every arm returns or
+                // there are no values to match
+                first.map_or(Ty::Never, |(ty, _)| ty)
+            }
+        }
+    }
+
+    /// Check the patterns of a match at `span` against the type of its
+    /// value, and that together they match every value. Arms that can never
+    /// match are warned about.
+    fn match_arms(&mut self, arms: &[Arm], ty: &Ty, span: Span) {
+        let start = self.errors.len();
+
+        for arm in arms {
+            self.pattern(&arm.pattern, ty, arm.span);
+        }
+
+        // Patterns that don't fit can't say what is covered
+        if self.errors.len() > start || matches!(ty, Ty::Unknown | Ty::Never) {
+            return;
+        }
+
+        let patterns: Vec<_> = arms.iter().map(|arm| &arm.pattern).collect();
+        let report = exhaustiveness::check(&self.enums, &patterns, ty);
+
+        for i in report.unreachable {
+            self.types
+                .warnings
+                .push(TypeError::new(TypeErrorKind::UnreachableArm, arms[i].span));
+        }
+
+        if !report.missing.is_empty() {
+            self.error(
+                TypeErrorKind::NonExhaustive {
+                    patterns: report.missing,
+                },
+                span,
+            );
+        }
+    }
+
+    /// Where the value of a block is, or `span` if it has none.
+    fn block_span(&self, block: &Block, span: Span) -> Span {
+        block
+            .value
+            .map_or(span, |value| self.parsed.arena[value].span)
+    }
+
+    fn identifier(&mut self, id: ExprId, name: &str, hint: Option<&Ty>, span: Span) -> Ty {
+        let Some(&symbol) = self.resolution.uses.get(&id) else {
+            return Ty::Unknown;
+        };
+
+        if let Some(index) = self.generic_function(id) {
+            return self.generic_value(index, id, hint, span);
+        }
+
+        match self.resolution.symbol(symbol).kind {
+            SymbolKind::Function | SymbolKind::Global | SymbolKind::Const => {
+                match self.items.get(name) {
+                    Some(&index) => self.item_ty(index),
+                    None => Ty::Unknown,
+                }
+            }
+            SymbolKind::Struct | SymbolKind::Enum | SymbolKind::Trait => {
+                self.error(
+                    TypeErrorKind::NotAValue {
+                        name: name.to_string(),
+                    },
+                    span,
+                );
+
+                Ty::Unknown
+            }
+            SymbolKind::Import => Ty::Unknown,
+            SymbolKind::Param
+            | SymbolKind::ClosureParam
+            | SymbolKind::Local
+            | SymbolKind::Binding => self.bodies[self.current]
+                .symbols
+                .get(&symbol)
+                .cloned()
+                .unwrap_or(Ty::Unknown),
+        }
+    }
+
+    /// Check the values passed to a function or variant against the types
+    /// it takes.
+    fn arguments(&mut self, params: &[Ty], args: &[ExprId], span: Span) {
+        if params.len() != args.len() {
+            self.error(
+                TypeErrorKind::ArgumentCount {
+                    expected: params.len(),
+                    found: args.len(),
+                },
+                span,
+            );
+        }
+
+        for (i, arg) in args.iter().enumerate() {
+            match params.get(i) {
+                Some(param) => {
+                    self.expect(*arg, param, None);
+                }
+                None => {
+                    self.expr(*arg, None);
+                }
+            }
+        }
+    }
+
+    fn closure(&mut self, closure: &Closure, hint: Option<&Ty>, span: Span) -> Ty {
+        // Parameters without a type take the type the closure is expected to
+        // take, e.g. when it is passed to a function
+        let hints = match hint {
+            Some(Ty::Function { params, .. }) if params.len() == closure.params.len() => {
+                Some(params)
+            }
+            _ => None,
+        };
+
+        let params: Vec<_> = closure
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| {
+                let ty = match &param.ty {
+                    Some(ty) => self.lower(ty, span),
+                    None => hints.map_or(Ty::Unknown, |hints| hints[i].clone()),
+                };
+
+                self.declare(span, &param.name, ty.clone());
+
+                ty
+            })
+            .collect();
+
+        self.returns.push(Return {
+            ty: None,
+            declared: None,
+        });
+
+        let body = self.expr(closure.body, None);
+        let ret = self.finish_return(body, Some(closure.body));
+
+        Ty::Function {
+            params,
+            ret: Box::new(ret),
+        }
+    }
+
+    /// Whether a binary operator works on a type.
+    fn operand_fits(&self, op: BinaryOp, ty: &Ty) -> bool {
+        match ty {
+            Ty::Unknown | Ty::Int(_) => true,
+            // Strings can be concatenated
+            Ty::Str => op == BinaryOp::Add,
+            _ => false,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        id: ExprId,
+        op: BinaryOp,
+        left: ExprId,
+        right: ExprId,
+        hint: Option<&Ty>,
+        span: Span,
+    ) -> Ty {
+        let (precedence, _) = ops::binary(op);
+
+        if matches!(precedence, Precedence::And | Precedence::Or) {
+            self.expect(left, &Ty::Bool, None);
+            self.expect(right, &Ty::Bool, None);
+
+            return Ty::Bool;
+        }
+
+        // Arithmetic results have the type of their operands, so they can
+        // take the type the result is expected to have
+        let hint = match precedence {
+            Precedence::Sum | Precedence::Product => hint,
+            _ => None,
+        };
+
+        // An integer literal takes the type of the other operand, so `1 + a`
+        // is checked like `a + 1`
+        let (first, second) = if self.is_literal(left) && !self.is_literal(right) {
+            (right, left)
+        } else {
+            (left, right)
+        };
+
+        let ty = self.expr(first, hint);
+
+        // Operators on structs and enums call the method named for them,
+        // though values without an `eq` are still compared field by field
+        if let Some(method) = op.method().filter(|_| first == left && overloads(&ty)) {
+            let equality = matches!(op, BinaryOp::Equal | BinaryOp::NotEqual);
+
+            if !equality || !self.implemented(&ty, method).is_empty() {
+                let ret = self.operator(id, op.symbol(), method, left, &[right], span);
+
+                if matches!(precedence, Precedence::Sum | Precedence::Product) {
+                    return ret;
+                }
+
+                if !ret.matches(&Ty::Bool) {
+                    self.error(
+                        TypeErrorKind::Mismatch {
+                            expected: Ty::Bool,
+                            found: ret,
+                        },
+                        span,
+                    );
+                }
+
+                return Ty::Bool;
+            }
+        }
+
+        self.expect(second, &ty, None);
+        self.imported_operands(op.symbol(), &[left, right], span);
+
+        match precedence {
+            Precedence::Sum | Precedence::Product => {
+                if self.operand_fits(op, &ty) {
+                    ty
+                } else {
+                    self.invalid_operand(op.symbol(), ty, span)
+                }
+            }
+            Precedence::Comparison => {
+                if !matches!(ty, Ty::Int(_) | Ty::Unknown) {
+                    self.invalid_operand(op.symbol(), ty, span);
+                }
+
+                Ty::Bool
+            }
+            // Anything can be compared for equality, other than functions
+            _ => {
+                if matches!(ty, Ty::Function { .. }) {
+                    self.invalid_operand(op.symbol(), ty, span);
+                }
+
+                Ty::Bool
+            }
+        }
+    }
+
+    /// Report operands of an operator that are names from other files, like
+    /// `print`, whose types aren't known, since the operator can't be
+    /// compiled without them.
+    fn imported_operands(&mut self, op: &str, operands: &[ExprId], span: Span) {
+        let imported = operands.iter().any(|&operand| {
+            matches!(self.parsed.arena[operand].kind, ExprKind::Identifier(_))
+                && self.resolution.uses.get(&operand).is_some_and(|&symbol| {
+                    self.resolution.symbol(symbol).kind == SymbolKind::Import
+                })
+        });
+
+        if imported {
+            self.invalid_operand(op, Ty::Unknown, span);
+        }
+    }
+
+    /// Report an operator used on a type it doesn't work with, returning the
+    /// unknown type of its result.
+    fn invalid_operand(&mut self, op: &str, ty: Ty, span: Span) -> Ty {
+        self.error(
+            TypeErrorKind::InvalidOperand {
+                op: op.to_string(),
+                ty,
+            },
+            span,
+        );
+
+        Ty::Unknown
+    }
+}
+
+/// The parameters and return type of a built-in method of a type, which
+/// doesn't need a trait: `len` of a `str`, its length in bytes.
+fn builtin(ty: &Ty, method: &str) -> Option<(Vec<Ty>, Ty)> {
+    match (ty, method) {
+        (Ty::Str, "len") => Some((Vec::new(), Ty::Int(IntTy::U32))),
+        _ => None,
+    }
+}
+
+/// Whether operators used on values of a type call its methods, which they
+/// do for structs and enums.
+fn overloads(ty: &Ty) -> bool {
+    matches!(ty, Ty::Struct(..) | Ty::Enum(_))
+}
+
+/// Remove the errors that were found more than once, keeping the first.
+fn dedup(errors: &mut Vec<TypeError>) {
+    let mut seen = Vec::new();
+
+    errors.retain(|error| {
+        if seen.contains(error) {
+            return false;
+        }
+
+        seen.push(error.clone());
+
+        true
+    });
+}
@@ -0,0 +1,379 @@
+//! Checking that the arms of a match cover every value, and that each arm
+//! can match something the arms before it don't.
+//!
+//! Patterns are turned into constructors applied to the patterns for their
+//! fields, e.g. `Some((true, _))` is `Some` applied to a tuple of `true` and
+//! a wildcard. A type like `bool` or an enum has a known set of
+//! constructors, so the arms cover it when they cover each one. Integers and
+//! strings have too many to list, so only a wildcard or a binding covers
+//! them.
+//!
+//! Both checks come down to whether a pattern is *useful* after a list of
+//! others, i.e. whether there is a value it matches that none of them do.
+use super::Ty;
+use crate::parser::ast::Pattern;
+use std::collections::HashMap;
+use std::iter;
+
+/// The variants of each enum, and the types of their payloads.
+pub type Enums<'a> = HashMap<&'a str, Vec<(String, Vec<Ty>)>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A pattern, with the names it binds forgotten since they match anything.
+enum Pat {
+    Wildcard,
+    Constructor(Constructor, Vec<Pat>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// What makes a value of a type, e.g. a variant of an enum.
+enum Constructor {
+    Bool(bool),
+    /// A variant of an enum, by its position in the enum
+    Variant(usize),
+    None,
+    Some,
+    Tuple,
+    Number(usize),
+    String(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// What was found by checking the arms of a match.
+pub struct Report {
+    /// Values no arm matches, written as patterns, e.g. `Shape::Line` or
+    /// `Some(false)`
+    pub missing: Vec<String>,
+    /// The positions of the arms that can never match, since the arms
+    /// before them match everything they do
+    pub unreachable: Vec<usize>,
+}
+
+/// Check the patterns of the arms of a match on a value of a type. The
+/// patterns should already fit the type.
+pub fn check(enums: &Enums, patterns: &[&Pattern], ty: &Ty) -> Report {
+    let matrix = Matrix { enums };
+    let tys = [ty.clone()];
+
+    let mut rows: Vec<Vec<Pat>> = Vec::new();
+    let mut report = Report::default();
+
+    for (i, pattern) in patterns.iter().enumerate() {
+        let row = vec![matrix.lower(pattern, ty)];
+
+        if !matrix.is_useful(&rows, &row, &tys) {
+            report.unreachable.push(i);
+        }
+
+        rows.push(row);
+    }
+
+    report.missing = matrix
+        .missing(&rows, &tys)
+        .iter()
+        .map(|witness| matrix.show(&witness[0], ty))
+        .collect();
+
+    report
+}
+
+/// The patterns of the arms seen so far, one row per arm. Specializing the
+/// rows can give a row more than one column, one for each field of a
+/// constructor.
+struct Matrix<'e, 'a> {
+    enums: &'e Enums<'a>,
+}
+
+impl Matrix<'_, '_> {
+    /// The constructors making up a pattern matched against a type. A
+    /// pattern that doesn't fit the type was already reported, so it is
+    /// taken to match anything.
+    fn lower(&self, pattern: &Pattern, ty: &Ty) -> Pat {
+        let constructor = |constructor, fields| Pat::Constructor(constructor, fields);
+
+        match (pattern, ty) {
+            // A name can't be told apart from a variant without a payload
+            // when parsing, so it is one if the type has a variant called
+            // that
+            (Pattern::Binding(name), Ty::Optional(_)) if name == "None" => {
+                constructor(Constructor::None, Vec::new())
+            }
+            (Pattern::Binding(name), Ty::Enum(enum_name)) => match self.variant(enum_name, name) {
+                Some((index, payload)) if payload.is_empty() => {
+                    constructor(Constructor::Variant(index), Vec::new())
+                }
+                _ => Pat::Wildcard,
+            },
+            (Pattern::Bool(value), Ty::Bool) => constructor(Constructor::Bool(*value), Vec::new()),
+            (Pattern::Number(number), Ty::Int(_)) => {
+                constructor(Constructor::Number(*number), Vec::new())
+            }
+            (Pattern::String(string), Ty::Str) => {
+                constructor(Constructor::String(string.clone()), Vec::new())
+            }
+            (Pattern::Tuple(patterns), Ty::Tuple(types)) if patterns.len() == types.len() => {
+                constructor(Constructor::Tuple, self.lower_all(patterns, types))
+            }
+            (
+                Pattern::Variant {
+                    enum_name: None,
+                    variant,
+                    payload,
+                },
+                Ty::Optional(inner),
+            ) if variant == "Some" && payload.len() == 1 => {
+                constructor(Constructor::Some, vec![self.lower(&payload[0], inner)])
+            }
+            (
+                Pattern::Variant {
+                    variant, payload, ..
+                },
+                Ty::Enum(enum_name),
+            ) => match self.variant(enum_name, variant) {
+                Some((index, types)) if types.len() == payload.len() => {
+                    constructor(Constructor::Variant(index), self.lower_all(payload, &types))
+                }
+                _ => Pat::Wildcard,
+            },
+            _ => Pat::Wildcard,
+        }
+    }
+
+    fn lower_all(&self, patterns: &[Pattern], types: &[Ty]) -> Vec<Pat> {
+        patterns
+            .iter()
+            .zip(types)
+            .map(|(pattern, ty)| self.lower(pattern, ty))
+            .collect()
+    }
+
+    /// The position of a variant in its enum, and the types of its payload.
+    fn variant(&self, enum_name: &str, variant: &str) -> Option<(usize, Vec<Ty>)> {
+        self.enums
+            .get(enum_name)?
+            .iter()
+            .enumerate()
+            .find_map(|(index, (name, payload))| {
+                (name == variant).then(|| (index, payload.clone()))
+            })
+    }
+
+    /// Every constructor of a type, or `None` if there are too many to
+    /// list.
+    fn constructors(&self, ty: &Ty) -> Option<Vec<Constructor>> {
+        match ty {
+            Ty::Bool => Some(vec![Constructor::Bool(true), Constructor::Bool(false)]),
+            Ty::Optional(_) => Some(vec![Constructor::None, Constructor::Some]),
+            Ty::Tuple(_) => Some(vec![Constructor::Tuple]),
+            Ty::Enum(name) => self
+                .enums
+                .get(name.as_str())
+                .map(|variants| (0..variants.len()).map(Constructor::Variant).collect()),
+            _ => None,
+        }
+    }
+
+    /// The types of the fields of a constructor of a type.
+    fn fields(&self, constructor: &Constructor, ty: &Ty) -> Vec<Ty> {
+        match (constructor, ty) {
+            (Constructor::Some, Ty::Optional(inner)) => vec![(**inner).clone()],
+            (Constructor::Tuple, Ty::Tuple(types)) => types.clone(),
+            (Constructor::Variant(index), Ty::Enum(name)) => self
+                .enums
+                .get(name.as_str())
+                .and_then(|variants| variants.get(*index))
+                .map_or_else(Vec::new, |(_, payload)| payload.clone()),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether there is a value `row` matches that none of `rows` do, where
+    /// `tys` are the types of the columns.
+    fn is_useful(&self, rows: &[Vec<Pat>], row: &[Pat], tys: &[Ty]) -> bool {
+        let Some((first, rest)) = row.split_first() else {
+            // Every column matched, so the row is only useful if no other
+            // row got this far
+            return rows.is_empty();
+        };
+
+        match first {
+            Pat::Constructor(constructor, fields) => {
+                let row: Vec<_> = fields.iter().chain(rest).cloned().collect();
+                let tys = self.specialized_types(constructor, tys);
+
+                self.is_useful(&specialize(rows, constructor, fields.len()), &row, &tys)
+            }
+            Pat::Wildcard => match self.covered(rows, &tys[0]) {
+                // A wildcard is useful if it is useful for any of the
+                // constructors, since the rows match each of them
+                Some(constructors) => constructors.iter().any(|constructor| {
+                    let tys = self.specialized_types(constructor, tys);
+                    let arity = tys.len() - rest.len();
+                    let row: Vec<_> = iter::repeat_n(Pat::Wildcard, arity)
+                        .chain(rest.iter().cloned())
+                        .collect();
+
+                    self.is_useful(&specialize(rows, constructor, arity), &row, &tys)
+                }),
+                // Otherwise there is a value none of the rows' constructors
+                // make, which only the rows starting with a wildcard match
+                None => self.is_useful(&default(rows), rest, &tys[1..]),
+            },
+        }
+    }
+
+    /// The values none of the rows match, as one pattern for each column.
+    fn missing(&self, rows: &[Vec<Pat>], tys: &[Ty]) -> Vec<Vec<Pat>> {
+        let Some((ty, rest)) = tys.split_first() else {
+            return if rows.is_empty() {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            };
+        };
+
+        let Some(constructors) = self.constructors(ty) else {
+            // Only a wildcard can stand for the values that aren't listed
+            return self
+                .missing_after_wildcards(rows, rest)
+                .into_iter()
+                .map(|witness| iter::once(Pat::Wildcard).chain(witness).collect())
+                .collect();
+        };
+
+        // A constructor none of the rows start with is only matched by the
+        // rows starting with a wildcard, whatever its fields are
+        let mut after_wildcards = None;
+
+        constructors
+            .into_iter()
+            .flat_map(|constructor| {
+                let tys = self.specialized_types(&constructor, tys);
+                let arity = tys.len() - rest.len();
+
+                let witnesses = if starts_with(rows, &constructor) {
+                    self.missing(&specialize(rows, &constructor, arity), &tys)
+                } else {
+                    let witnesses = after_wildcards
+                        .get_or_insert_with(|| self.missing_after_wildcards(rows, rest));
+
+                    witnesses
+                        .iter()
+                        .map(|witness| {
+                            iter::repeat_n(Pat::Wildcard, arity)
+                                .chain(witness.iter().cloned())
+                                .collect()
+                        })
+                        .collect()
+                };
+
+                witnesses.into_iter().map(move |mut witness: Vec<Pat>| {
+                    let rest = witness.split_off(arity);
+
+                    iter::once(Pat::Constructor(constructor.clone(), witness))
+                        .chain(rest)
+                        .collect()
+                })
+            })
+            .collect()
+    }
+
+    /// The values of the columns after the first that none of the rows
+    /// starting with a wildcard match. Without any such rows, nothing is
+    /// matched, which one row of wildcards stands for.
+    fn missing_after_wildcards(&self, rows: &[Vec<Pat>], tys: &[Ty]) -> Vec<Vec<Pat>> {
+        let rows = default(rows);
+
+        if rows.is_empty() {
+            vec![vec![Pat::Wildcard; tys.len()]]
+        } else {
+            self.missing(&rows, tys)
+        }
+    }
+
+    /// Every constructor of a type, if the rows start with each of them.
+    fn covered(&self, rows: &[Vec<Pat>], ty: &Ty) -> Option<Vec<Constructor>> {
+        self.constructors(ty).filter(|constructors| {
+            constructors
+                .iter()
+                .all(|constructor| starts_with(rows, constructor))
+        })
+    }
+
+    /// The types of the columns after the first is specialized to a
+    /// constructor.
+    fn specialized_types(&self, constructor: &Constructor, tys: &[Ty]) -> Vec<Ty> {
+        self.fields(constructor, &tys[0])
+            .into_iter()
+            .chain(tys[1..].iter().cloned())
+            .collect()
+    }
+
+    /// Write a pattern the way it is written in the source.
+    fn show(&self, pat: &Pat, ty: &Ty) -> String {
+        let Pat::Constructor(constructor, fields) = pat else {
+            return "_".to_string();
+        };
+
+        let types = self.fields(constructor, ty);
+        let fields: Vec<_> = fields
+            .iter()
+            .zip(&types)
+            .map(|(field, ty)| self.show(field, ty))
+            .collect();
+
+        match (constructor, ty) {
+            (Constructor::Bool(value), _) => value.to_string(),
+            (Constructor::None, _) => "None".to_string(),
+            (Constructor::Some, _) => format!("Some({})", fields.join(", ")),
+            (Constructor::Tuple, _) if fields.len() == 1 => format!("({},)", fields[0]),
+            (Constructor::Tuple, _) => format!("({})", fields.join(", ")),
+            (Constructor::Variant(index), Ty::Enum(name)) => {
+                let variant = self
+                    .enums
+                    .get(name.as_str())
+                    .and_then(|variants| variants.get(*index))
+                    .map_or("_", |(variant, _)| variant.as_str());
+
+                if fields.is_empty() {
+                    format!("{}::{}", name, variant)
+                } else {
+                    format!("{}::{}({})", name, variant, fields.join(", "))
+                }
+            }
+            (Constructor::Number(number), _) => number.to_string(),
+            (Constructor::String(string), _) => format!("{:?}", string),
+            (Constructor::Variant(_), _) => "_".to_string(),
+        }
+    }
+}
+
+/// Whether any of the rows starts with a constructor.
+fn starts_with(rows: &[Vec<Pat>], constructor: &Constructor) -> bool {
+    rows.iter()
+        .any(|row| matches!(&row[0], Pat::Constructor(first, _) if first == constructor))
+}
+
+/// The rows that match a constructor, with their first column replaced by
+/// the constructor's fields.
+fn specialize(rows: &[Vec<Pat>], constructor: &Constructor, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let fields = match &row[0] {
+                Pat::Wildcard => vec![Pat::Wildcard; arity],
+                Pat::Constructor(first, fields) if first == constructor => fields.clone(),
+                Pat::Constructor(..) => return None,
+            };
+
+            Some(fields.into_iter().chain(row[1..].iter().cloned()).collect())
+        })
+        .collect()
+}
+
+/// The rows starting with a wildcard, without it.
+fn default(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter(|row| row[0] == Pat::Wildcard)
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
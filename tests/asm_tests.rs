@@ -0,0 +1,272 @@
+use penguin::asm::{self, aarch64, x86_64::abi, Asm};
+use penguin::backend::{Arch, Backend, CodegenErrorKind, Os, Target};
+use std::process::Command;
+
+mod common;
+
+use common::lower;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINUX: Target = Target::new(Arch::X86_64, Os::Linux);
+    const ARM_LINUX: Target = Target::new(Arch::Aarch64, Os::Linux);
+    const ARM_MACOS: Target = Target::new(Arch::Aarch64, Os::MacOs);
+
+    fn assembly(source: &str, target: Target) -> String {
+        asm::compile(&lower(source), None, target).unwrap()
+    }
+
+    /// Compile a source to an executable and run it, returning what it
+    /// printed and its exit code, or `None` if there is no assembler or C
+    /// compiler to build it with.
+    fn run(name: &str, source: &str) -> Option<(String, i32)> {
+        common::run("asm", name, &[asm::ASSEMBLER], |object| {
+            Asm.write_object(&lower(source), None, Target::host(), object)
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn test_short_circuiting() {
+        let Some((output, _)) = run(
+            "short_circuit",
+            "import \"std.print\";\n\
+             func loud(value : bool) : bool = { print(value); value };\n\
+             func main() := { print(loud(false) && loud(true)); print(loud(true) || loud(false)); };",
+        ) else {
+            return;
+        };
+
+        // The right operand is only run when it decides the result
+        assert_eq!(output, "false\nfalse\ntrue\ntrue\n");
+    }
+
+    #[test]
+    fn test_functions() {
+        let assembly = assembly("func double(a : u32) : u32 = { a * 2 };", LINUX);
+
+        assert!(assembly.contains("    .globl \"pg.double\"\n    .p2align 4\n\"pg.double\":\n"));
+        assert!(assembly.contains("    movq %rdi, -8(%rbp)\n"));
+        assert!(assembly.contains("    imulq %rcx, %rax\n    movl %eax, %ecx\n"));
+        // Libraries have no entry point
+        assert!(!assembly.contains("\nmain:\n"));
+    }
+
+    #[test]
+    fn test_entry_point_sets_globals() {
+        let assembly = assembly("let start : u8 = 3; func main() : u8 = { start };", LINUX);
+
+        assert!(assembly.contains("\"pg.global.start\":\n    .zero 8\n"));
+        assert!(assembly.contains(
+            "main:\n    pushq %rbp\n    movq %rsp, %rbp\n    call \"pg.init.start\"\n    \
+             movq %rax, \"pg.global.start\"(%rip)\n    call \"pg.main\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_calling_convention() {
+        let locations = abi::arguments(8);
+
+        assert_eq!(locations[0], abi::Location::Register(abi::Register::Rdi));
+        assert_eq!(locations[5], abi::Location::Register(abi::Register::R9));
+        assert_eq!(
+            locations[6..],
+            [abi::Location::Stack(0), abi::Location::Stack(8)]
+        );
+
+        // The stack stays aligned to 16 bytes
+        assert_eq!(abi::stack_arguments(6), 0);
+        assert_eq!(abi::stack_arguments(7), 16);
+        assert_eq!(abi::stack_arguments(9), 32);
+        assert_eq!(abi::parameter_offset(8), 24);
+        assert_eq!(abi::Register::R9.number(), 9);
+    }
+
+    #[test]
+    fn test_aarch64() {
+        let linux = assembly("func double(a : u32) : u32 = { a * 2 };", ARM_LINUX);
+
+        assert!(linux.contains(
+            "\"pg.double\":\n    stp x29, x30, [sp, #-16]!\n    mov x29, sp\n    \
+             sub sp, sp, #16\n    stur x0, [x29, #-8]\n"
+        ));
+        assert!(linux.contains("    mul x0, x0, x1\n    mov w9, w0\n    cmp x9, x0\n"));
+        assert!(linux.contains("    mov sp, x29\n    ldp x29, x30, [sp], #16\n    ret\n"));
+        assert!(linux.contains("    bl printf\n"));
+
+        let macos = assembly(
+            "let start : u64 = 81985529216486895; func main() : u64 = { start };",
+            ARM_MACOS,
+        );
+
+        // Symbols start with an underscore, and are addressed by page
+        assert!(macos.contains("    .globl _main\n    .p2align 4\n_main:\n"));
+        assert!(macos.contains(
+            "    adrp x16, \"_pg.global.start\"@PAGE\n    \
+             str x0, [x16, \"_pg.global.start\"@PAGEOFF]\n"
+        ));
+        assert!(macos.contains("    movz x0, #52719\n    movk x0, #35243, lsl #16\n"));
+        assert!(macos.ends_with("    .subsections_via_symbols\n"));
+    }
+
+    /// The assembly of every target is assembled by LLVM's assembler, if
+    /// there is one, since there may be no assembler for other machines.
+    #[test]
+    fn test_assembling_every_target() {
+        let source = "import \"std.print\";\n\
+             let mut count : u32 = 40;\n\
+             func apply(f : func(u32) -> u32, a : u32) : u32 = { f(a) };\n\
+             func double(a : u32) : u32 = { a * 2 };\n\
+             func sum(a : i64, b : i64, c : i64, d : i64, e : i64, f : i64, g : i64, h : i64, i : i64) : i64 = { a + b + c + d + e + f + g + h + i };\n\
+             func divide(a : i8, b : i8) : i8 = { a / b + a % b };\n\
+             func main() : u32 = { count += 2; print(count, apply(double, 4), sum(1, 2, 3, 4, 5, 6, 7, 8, 9), divide(-7, 2), true); 0 };";
+
+        for target in Target::ALL {
+            let assembly = assembly(source, target);
+            let triple = match target {
+                ARM_MACOS => "arm64-apple-macos",
+                target => target.triple(),
+            };
+
+            let Ok(mut child) = Command::new("llvm-mc")
+                .args([
+                    &format!("-triple={}", triple),
+                    "-filetype=obj",
+                    "-o",
+                    "/dev/null",
+                ])
+                .stdin(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+            else {
+                return;
+            };
+
+            std::io::Write::write_all(&mut child.stdin.take().unwrap(), assembly.as_bytes())
+                .unwrap();
+
+            let output = child.wait_with_output().unwrap();
+
+            assert!(
+                output.status.success(),
+                "{}: {}",
+                target,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+
+    #[test]
+    fn test_aarch64_calling_convention() {
+        use aarch64::abi::{self, Location, Register};
+
+        let locations = abi::arguments(10, 10, Os::Linux);
+
+        assert_eq!(locations[7], Location::Register(Register(7)));
+        assert_eq!(locations[8..], [Location::Stack(0), Location::Stack(8)]);
+        assert_eq!(abi::stack_arguments(9, 9, Os::Linux), 16);
+
+        // Apple passes the arguments of `printf` after its format on the
+        // stack
+        assert_eq!(
+            abi::arguments(3, 1, Os::MacOs),
+            [
+                Location::Register(Register(0)),
+                Location::Stack(0),
+                Location::Stack(8)
+            ]
+        );
+        assert_eq!(
+            abi::arguments(3, 1, Os::Linux)[2],
+            Location::Register(Register(2))
+        );
+        assert_eq!(abi::stack_arguments(3, 1, Os::MacOs), 16);
+        assert_eq!(abi::parameter_offset(8), 24);
+        assert_eq!(abi::FRAME.name(), "x29");
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let errors = asm::compile(
+            &lower("func f() : u32 = { let g := func(a : u32) => a; g(1) };"),
+            None,
+            LINUX,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors.iter().map(|error| &error.kind).collect::<Vec<_>>(),
+            [&CodegenErrorKind::Unsupported {
+                backend: "assembly",
+                what: "closures".to_string()
+            }]
+        );
+        assert_eq!(errors[0].code(), "E0801");
+
+        let errors =
+            asm::compile(&lower("func f(s : str) : u32 = { s.len() };"), None, LINUX).unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            CodegenErrorKind::Unsupported {
+                backend: "assembly",
+                what: "values of type 'str'".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_running_native_programs() {
+        let Some((output, code)) = run(
+            "program",
+            "import \"std.print\";\n\
+             let mut count : u32 = 40;\n\
+             func fib(n : u32) : u32 = { if let true = n < 2 { return n; } fib(n - 1) + fib(n - 2) };\n\
+             func apply(f : func(u32) -> u32, a : u32) : u32 = { f(a) };\n\
+             func double(a : u32) : u32 = { a * 2 };\n\
+             func sum(a : i64, b : i64, c : i64, d : i64, e : i64, f : i64, g : i64, h : i64) : i64 = { a + b + c + d + e + f + g * 10 + h * 100 };\n\
+             func sign(x : i8) : i8 = { match x { 0 => 0 as i8, n => { if let true = n < 0 { return -1; } 1 as i8 } } };\n\
+             func main() : u32 = {\n\
+                 let mut i := 0;\n\
+                 while i < 3 { defer { i += 1; } print(i, i == 1); }\n\
+                 count += 2;\n\
+                 print(count, apply(double, 4), sum(1, 1, 1, 1, 1, 1, 2, 3), sign(-5), \"100%\");\n\
+                 print(-7 / 2, -7 % 3, 255 as i8, -1 as u8 as u64);\n\
+                 fib(10)\n\
+             };",
+        ) else {
+            return;
+        };
+
+        assert_eq!(
+            output,
+            "0 false\n1 true\n2 false\n42 8 326 -1 100%\n-3 -1 -1 255\n"
+        );
+        assert_eq!(code, 55);
+    }
+
+    #[test]
+    fn test_native_runtime_errors() {
+        let Some((output, code)) = run(
+            "overflow",
+            "func add(a : u8, b : u8) : u8 = { a + b }; func main() := { add(200, 100); };",
+        ) else {
+            return;
+        };
+
+        assert_eq!(output, "[RUNTIME ERROR]: The result doesn't fit in u8.\n");
+        assert_eq!(code, 1);
+
+        let Some((output, code)) = run(
+            "division",
+            "func f(a : i64, b : i64) : i64 = { a / b }; func main() : i64 = { f(-9223372036854775807 - 1, -1) };",
+        ) else {
+            return;
+        };
+
+        assert_eq!(output, "[RUNTIME ERROR]: The result doesn't fit in i64.\n");
+        assert_eq!(code, 1);
+    }
+}
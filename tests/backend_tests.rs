@@ -0,0 +1,300 @@
+use penguin::arithmetic::Overflow;
+use penguin::asm::{self, Asm};
+use penguin::backend::{self, Arch, Backend, Check, CheckKind, Layout, Os, Step, Target};
+use penguin::bytecode;
+#[cfg(feature = "cranelift")]
+use penguin::cranelift::{self, Cranelift};
+use penguin::hir::{self, Pattern};
+#[cfg(feature = "llvm")]
+use penguin::llvm;
+use penguin::parser::ast::BinaryOp;
+use penguin::typeck::{IntTy, Ty};
+
+mod common;
+
+use common::lower;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing_targets() {
+        assert_eq!(
+            Target::parse("aarch64-apple-darwin"),
+            Some(Target::new(Arch::Aarch64, Os::MacOs))
+        );
+        assert_eq!(
+            Target::parse("arm64-apple-macos"),
+            Some(Target::new(Arch::Aarch64, Os::MacOs))
+        );
+        assert_eq!(
+            Target::parse("x86_64-linux-musl"),
+            Some(Target::new(Arch::X86_64, Os::Linux))
+        );
+        assert_eq!(Target::parse("riscv64-unknown-linux-gnu"), None);
+        assert_eq!(Target::parse("x86_64-pc-windows-msvc"), None);
+        assert_eq!(Target::parse("aarch64"), None);
+    }
+
+    #[test]
+    fn test_triples() {
+        for target in Target::ALL {
+            assert_eq!(Target::parse(target.triple()), Some(target));
+        }
+
+        assert_eq!(
+            Target::new(Arch::Aarch64, Os::Linux).to_string(),
+            "aarch64-unknown-linux-gnu"
+        );
+        assert!(Target::ALL.contains(&Target::host()));
+    }
+
+    /// A pattern checks what it takes a part from before taking it, and
+    /// the parts in order.
+    #[test]
+    fn test_pattern_checks() {
+        let program = hir::Program {
+            functions: Vec::new(),
+            globals: Vec::new(),
+            structs: Vec::new(),
+            enums: vec![hir::Enum {
+                name: "E".to_string(),
+                variants: vec![
+                    ("A".to_string(), Vec::new()),
+                    ("B".to_string(), vec![Ty::Bool, Ty::Str]),
+                ],
+            }],
+            ..Default::default()
+        };
+        let layout = Layout::new(&program);
+
+        let ty = Ty::Tuple(vec![
+            Ty::Optional(Box::new(Ty::Int(IntTy::U8))),
+            Ty::Enum("E".to_string()),
+        ]);
+        let variant = |name: &str, payload| Pattern::Variant {
+            enum_name: "E".to_string(),
+            variant: name.to_string(),
+            payload,
+        };
+        let pattern = Pattern::Tuple(vec![
+            Pattern::Some(Box::new(Pattern::Int(1))),
+            variant("B", vec![Pattern::Wildcard, Pattern::Str("s".to_string())]),
+        ]);
+
+        let check = |kind, path: &[Step], ty: &Ty| Check {
+            kind,
+            path: path.to_vec(),
+            ty: ty.clone(),
+        };
+
+        assert_eq!(
+            layout.checks(&pattern, &ty),
+            Ok(vec![
+                check(
+                    CheckKind::Some,
+                    &[Step::Element(0)],
+                    &Ty::Optional(Box::new(Ty::Int(IntTy::U8)))
+                ),
+                check(
+                    CheckKind::Int(1),
+                    &[Step::Element(0), Step::Unwrap],
+                    &Ty::Int(IntTy::U8)
+                ),
+                check(
+                    CheckKind::Variant(1),
+                    &[Step::Element(1)],
+                    &Ty::Enum("E".to_string())
+                ),
+                check(
+                    CheckKind::Str("s"),
+                    &[Step::Element(1), Step::Payload(1), Step::Element(1)],
+                    &Ty::Str
+                ),
+            ])
+        );
+        assert_eq!(
+            [Step::Element(2), Step::Unwrap, Step::Payload(1)].map(Step::position),
+            [2, 1, 2]
+        );
+
+        let unknown = variant("C", Vec::new());
+
+        assert_eq!(
+            layout.checks(&unknown, &Ty::Enum("E".to_string())),
+            Err(("E", "C"))
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert_eq!(backend::short_circuit(BinaryOp::And), Some(false));
+        assert_eq!(backend::short_circuit(BinaryOp::Or), Some(true));
+        assert_eq!(backend::short_circuit(BinaryOp::Add), None);
+
+        // Innermost block first, and the last deferred first
+        assert_eq!(
+            backend::unwinding(&[vec![1, 2], vec![], vec![3, 4]]),
+            vec![4, 3, 2, 1]
+        );
+    }
+
+    /// Run a program with every backend that can run it here, returning
+    /// what it printed and its exit code with each, named by the backend.
+    fn run_everywhere(name: &str, program: &hir::Program) -> Vec<(&'static str, String, i32)> {
+        let mut runs = Vec::new();
+
+        // A runtime error is printed by native programs, and stops them
+        let mut output = Vec::new();
+        let code = match bytecode::run(&bytecode::compile(program), &mut output) {
+            Ok(_) => 0,
+            Err(error) => {
+                output.extend(format!("[RUNTIME ERROR]: {}\n", error).bytes());
+                1
+            }
+        };
+        runs.push(("vm", String::from_utf8(output).unwrap(), code));
+
+        let mut native = |backend, tools: &[&str], write_object: &dyn Fn(&std::path::Path)| {
+            if let Some((output, code)) = common::run(backend, name, tools, write_object) {
+                runs.push((backend, output, code));
+            }
+        };
+
+        native("asm", &[asm::ASSEMBLER], &|object| {
+            Asm.write_object(program, None, Target::host(), object)
+                .unwrap()
+        });
+
+        #[cfg(feature = "llvm")]
+        native("llvm", &[llvm::LLC], &|object| {
+            let ir = llvm::compile(program, None).unwrap();
+            llvm::write_object(&ir, Target::host(), object).unwrap()
+        });
+
+        #[cfg(feature = "cranelift")]
+        {
+            native("cranelift", &[], &|object| {
+                Cranelift
+                    .write_object(program, None, Target::host(), object)
+                    .unwrap()
+            });
+
+            let mut output = Vec::new();
+            let exit = cranelift::jit::run(program, None, &mut output).unwrap();
+            runs.push(("jit", String::from_utf8(output).unwrap(), exit.code()));
+        }
+
+        runs
+    }
+
+    /// Dividing the smallest signed value by -1 doesn't fit, but nothing is
+    /// left over, which every backend agrees with the VM on, although
+    /// machines fault on both.
+    #[test]
+    fn test_dividing_the_smallest_value_by_minus_one() {
+        let functions = "import \"std.print\";\n\
+             func div8(a : i8, b : i8) : i8 = { a / b };\n\
+             func rem8(a : i8, b : i8) : i8 = { a % b };\n\
+             func div32(a : i32, b : i32) : i32 = { a / b };\n\
+             func rem32(a : i32, b : i32) : i32 = { a % b };\n\
+             func div64(a : i64, b : i64) : i64 = { a / b };\n\
+             func rem64(a : i64, b : i64) : i64 = { a % b };\n";
+
+        let remainders = lower(&format!(
+            "{}func main() := {{\n\
+                 print(rem8(-128, -1), rem32(-2147483647 - 1, -1), rem64(-9223372036854775807 - 1, -1));\n\
+                 print(rem8(-7, 2), rem32(7, -2), rem64(-9223372036854775807 - 1, 10));\n\
+                 print(div8(-128, 1), div32(-7, 2), div64(-9223372036854775807 - 1, 2));\n\
+             }};",
+            functions
+        ));
+
+        for (backend, output, code) in run_everywhere("min_rem", &remainders) {
+            assert_eq!(
+                output, "0 0 0\n-1 1 -8\n-128 -3 -4611686018427387904\n",
+                "{}",
+                backend
+            );
+            assert_eq!(code, 0, "{}", backend);
+        }
+
+        for (ty, call) in [
+            ("i8", "div8(-128, -1)"),
+            ("i32", "div32(-2147483647 - 1, -1)"),
+            ("i64", "div64(-9223372036854775807 - 1, -1)"),
+        ] {
+            let quotient = lower(&format!(
+                "{}func main() := {{ print(\"before\"); {}; print(\"after\"); }};",
+                functions, call
+            ));
+
+            for (backend, output, code) in run_everywhere(&format!("min_div_{}", ty), &quotient) {
+                assert_eq!(
+                    output,
+                    format!(
+                        "before\n[RUNTIME ERROR]: The result doesn't fit in {}.\n",
+                        ty
+                    ),
+                    "{}",
+                    backend
+                );
+                assert_eq!(code, 1, "{}", backend);
+            }
+        }
+    }
+
+    /// Without overflow checks, every backend wraps around like the VM, and
+    /// still stops on dividing by zero.
+    #[test]
+    fn test_wrapping_arithmetic() {
+        let functions = "import \"std.print\";\n\
+             func add8(a : u8, b : u8) : u8 = { a + b };\n\
+             func sub8(a : i8, b : i8) : i8 = { a - b };\n\
+             func mul32(a : u32, b : u32) : u32 = { a * b };\n\
+             func neg32(a : i32) : i32 = { -a };\n\
+             func add64(a : u64, b : u64) : u64 = { a + b };\n\
+             func mul64(a : i64, b : i64) : i64 = { a * b };\n\
+             func div8(a : i8, b : i8) : i8 = { a / b };\n\
+             func rem8(a : i8, b : i8) : i8 = { a % b };\n\
+             func div64(a : i64, b : i64) : i64 = { a / b };\n\
+             func udiv(a : u32, b : u32) : u32 = { a / b };\n";
+
+        let mut wrapped = lower(&format!(
+            "{}func main() := {{\n\
+                 print(add8(200, 100), sub8(-128, 1), mul32(65536, 65537), neg32(-2147483647 - 1));\n\
+                 print(add64(18446744073709551615, 2), mul64(9223372036854775807, 3));\n\
+                 print(div8(-128, -1), rem8(-128, -1), div64(-9223372036854775807 - 1, -1), div8(-7, -1));\n\
+             }};",
+            functions
+        ));
+        wrapped.overflow = Overflow::Wrapping;
+
+        for (backend, output, code) in run_everywhere("wrapping", &wrapped) {
+            assert_eq!(
+                output,
+                "44 127 65536 -2147483648\n1 9223372036854775805\n\
+                 -128 0 -9223372036854775808 7\n",
+                "{}",
+                backend
+            );
+            assert_eq!(code, 0, "{}", backend);
+        }
+
+        let mut division = lower(&format!(
+            "{}func main() := {{ print(\"before\"); udiv(1, 0); print(\"after\"); }};",
+            functions
+        ));
+        division.overflow = Overflow::Wrapping;
+
+        for (backend, output, code) in run_everywhere("wrapping_div", &division) {
+            assert_eq!(
+                output, "before\n[RUNTIME ERROR]: Division by zero.\n",
+                "{}",
+                backend
+            );
+            assert_eq!(code, 1, "{}", backend);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use penguin::arithmetic::Overflow;
+use penguin::bytecode::{self, Instr, Program, RuntimeErrorKind, Value};
+use penguin::consteval;
+use penguin::hir;
+use penguin::lexer::{Lexer, Source};
+use penguin::parser::Parser;
+use penguin::resolve::Resolver;
+use penguin::source_map::SourceMap;
+use penguin::typeck::{IntTy, TypeChecker};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compile a source that compiles to bytecode.
+    fn compile(source: &str) -> Program {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+        let resolution = Resolver::resolve(&parsed).unwrap();
+        let types = TypeChecker::check(&parsed, &resolution).unwrap();
+        let consts = consteval::evaluate(&parsed, &resolution, &types).unwrap();
+
+        bytecode::compile(&hir::lower(&parsed, &resolution, &types, &consts))
+    }
+
+    /// Run a source, returning what `main` returns and what it printed.
+    fn run(source: &str) -> (Result<Value, RuntimeErrorKind>, String) {
+        let program = compile(source);
+        let mut output = Vec::new();
+
+        let result = bytecode::run(&program, &mut output).map_err(|error| error.kind);
+
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    /// What a source that runs without failing prints.
+    fn printed(source: &str) -> String {
+        let (result, output) = run(&format!("import \"std.print\";\n{}", source));
+
+        assert!(result.is_ok(), "Failed with {:?}", result);
+
+        output
+    }
+
+    #[test]
+    fn test_main_returns_the_exit_code() {
+        let (result, _) = run("func main() : u32 = { let a := 20; a * 2 + 2 };");
+
+        assert_eq!(result, Ok(Value::Int(42)));
+    }
+
+    #[test]
+    fn test_functions_and_recursion() {
+        let output = printed(
+            "func fib(n : u32) : u32 = { if let true = n < 2 { return n; } fib(n - 1) + fib(n - 2) };\n\
+             func main() := { print(fib(20)); };",
+        );
+
+        assert_eq!(output, "6765\n");
+    }
+
+    #[test]
+    fn test_printing_values() {
+        let output = printed(
+            "struct Point { x : i64, y : i64 }\n\
+             enum Shape { Dot, Rect(i64, i64) }\n\
+             func main() := {\n\
+                 print(Point { y: 2, x: 1 });\n\
+                 print(Shape::Rect(2, 3));\n\
+                 print(Shape::Dot);\n\
+                 print([(1, true), (2, false)]);\n\
+                 print(\"raw\");\n\
+                 print([\"quoted\"]);\n\
+             };",
+        );
+
+        assert_eq!(
+            output,
+            "Point { x: 1, y: 2 }\nShape::Rect(2, 3)\nShape::Dot\n\
+             [(1, true), (2, false)]\nraw\n[\"quoted\"]\n"
+        );
+    }
+
+    #[test]
+    fn test_loops_and_defers() {
+        let output = printed(
+            "func main() := {\n\
+                 let mut i := 0;\n\
+                 let mut runs := 0;\n\
+                 while i < 10 {\n\
+                     defer { runs += 1; }\n\
+                     i += 1;\n\
+                     if let true = i == 3 { continue; }\n\
+                     if let true = i == 6 { break; }\n\
+                     print(i);\n\
+                 }\n\
+                 print(runs);\n\
+             };",
+        );
+
+        assert_eq!(output, "1\n2\n4\n5\n6\n");
+    }
+
+    #[test]
+    fn test_defers_run_when_returning() {
+        let output = printed(
+            "func f() : u32 = { defer { print(\"outer\"); } { defer { print(\"inner\"); } return 1; } };\n\
+             func main() := { print(f()); };",
+        );
+
+        assert_eq!(output, "inner\nouter\n1\n");
+    }
+
+    #[test]
+    fn test_closures_capture_values() {
+        let output = printed(
+            "func apply(f : func(u32) -> u32, a : u32) : u32 = { f(a) };\n\
+             func main() := {\n\
+                 let mut b : u32 = 2;\n\
+                 let g := func(c : u32) => c * b;\n\
+                 b = 10;\n\
+                 print(apply(g, 3));\n\
+                 print(b);\n\
+             };",
+        );
+
+        assert_eq!(output, "6\n10\n");
+    }
+
+    #[test]
+    fn test_matches() {
+        let output = printed(
+            "enum Shape { Circle(i64), Rect(i64, i64) }\n\
+             func area(s : Shape) : i64 = { match s { Shape::Circle(r) => 3 * r * r, Shape::Rect(w, h) => w * h } };\n\
+             func name(n : u32) : str = { match n { 0 => \"zero\", 1 => \"one\", _ => \"many\" } };\n\
+             func main() := {\n\
+                 print(area(Shape::Circle(2)));\n\
+                 print(area(Shape::Rect(2, 5)));\n\
+                 print(name(0) + name(1) + name(7));\n\
+                 print(match (1, true) { (2, _) => 0, (1, false) => 1, (1, true) => 2, _ => 3 });\n\
+             };",
+        );
+
+        assert_eq!(output, "12\n10\nzeroonemany\n2\n");
+    }
+
+    #[test]
+    fn test_assigning_into_values() {
+        let output = printed(
+            "struct Inner { values : [u32; 3] }\n\
+             struct Outer { inner : Inner, n : u32 }\n\
+             func main() := {\n\
+                 let mut a := Outer { inner: Inner { values: [1, 2, 3] }, n: 0 };\n\
+                 let b := a;\n\
+                 a.inner.values[1] = 7;\n\
+                 a.n += 1;\n\
+                 print(a);\n\
+                 print(b);\n\
+             };",
+        );
+
+        assert_eq!(
+            output,
+            "Outer { inner: Inner { values: [1, 7, 3] }, n: 1 }\n\
+             Outer { inner: Inner { values: [1, 2, 3] }, n: 0 }\n"
+        );
+    }
+
+    #[test]
+    fn test_globals_are_set_before_main() {
+        let output = printed(
+            "let mut count : u32 = 40;\n\
+             let twice : u32 = count * 2;\n\
+             func bump() := { count += 1; };\n\
+             func main() := { bump(); bump(); print(count); print(twice); };",
+        );
+
+        assert_eq!(output, "42\n80\n");
+    }
+
+    #[test]
+    fn test_short_circuiting() {
+        let output = printed(
+            "func loud(value : bool) : bool = { print(value); value };\n\
+             func main() := { print(loud(false) && loud(true)); print(loud(true) || loud(false)); };",
+        );
+
+        assert_eq!(output, "false\nfalse\ntrue\ntrue\n");
+    }
+
+    #[test]
+    fn test_casts_and_strings() {
+        let output = printed(
+            "func main() := {\n\
+                 let a : u32 = 300;\n\
+                 print(a as u8);\n\
+                 print(true as u32);\n\
+                 let s := \"pen\" + \"guin\";\n\
+                 print(s.len());\n\
+                 print(s[0]);\n\
+             };",
+        );
+
+        assert_eq!(output, "44\n1\n7\n112\n");
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let overflow =
+            run("func add(a : u8, b : u8) : u8 = { a + b }; func main() := { add(200, 100); };");
+        let division =
+            run("func div(a : u32, b : u32) : u32 = { a / b }; func main() := { div(1, 0); };");
+        let index = run(
+            "func at(a : [u32; 2], i : u32) : u32 = { a[i] }; func main() := { at([1, 2], 2); };",
+        );
+        let assertion = "func main() := {\n    let a := 1;\n    assert(a == 2, \"a is 1\");\n};";
+        let recursion = run("func f(a : u32) : u32 = { f(a) + 1 }; func main() := { f(1); };");
+
+        assert_eq!(
+            overflow.0,
+            Err(RuntimeErrorKind::Overflow { ty: IntTy::U8 })
+        );
+        assert_eq!(division.0, Err(RuntimeErrorKind::DivisionByZero));
+        assert_eq!(
+            index.0,
+            Err(RuntimeErrorKind::IndexOutOfBounds {
+                index: 2,
+                length: 2
+            })
+        );
+        assert_eq!(
+            run(assertion).0,
+            Err(RuntimeErrorKind::AssertionFailed {
+                message: Some("a is 1".to_string())
+            })
+        );
+
+        // A failed assertion is reported at its condition, not its function
+        let error = bytecode::run(&compile(assertion), &mut Vec::new()).unwrap_err();
+        let mut sources = SourceMap::new();
+
+        sources.add(Source::new("main.pg", assertion.to_string()));
+
+        let (file, span) = sources.locate(error.span).unwrap();
+
+        assert_eq!(file.line_column(span.start), (3, 12));
+        assert_eq!(file.slice(span), Some("a == 2"));
+        assert_eq!(
+            recursion.0,
+            Err(RuntimeErrorKind::StackOverflow {
+                depth: bytecode::vm::MAX_DEPTH
+            })
+        );
+    }
+
+    #[test]
+    fn test_wrapping_arithmetic() {
+        let source = "import \"std.print\";\n\
+             func add(a : u8, b : u8) : u8 = { a + b };\n\
+             func neg(a : i16) : i16 = { -a };\n\
+             func div(a : i32, b : i32) : i32 = { a / b };\n\
+             func main() := { print(add(200, 100), neg(-32767 - 1), div(-2147483647 - 1, -1)); div(1, 0); };";
+        let mut program = compile(source);
+        let mut output = Vec::new();
+
+        program.overflow = Overflow::Wrapping;
+
+        // Results wrap around, but dividing by zero still fails
+        let result = bytecode::run(&program, &mut output).map_err(|error| error.kind);
+
+        assert_eq!(result, Err(RuntimeErrorKind::DivisionByZero));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "44 -32768 -2147483648\n"
+        );
+    }
+
+    #[test]
+    fn test_errors_point_at_the_code_that_failed() {
+        let source = "func main() := { let a : u8 = 255; let b := a + 1; };";
+        let program = compile(source);
+
+        let error = bytecode::run(&program, &mut Vec::new()).unwrap_err();
+
+        assert_eq!(&source[error.span.start..error.span.end], "a + 1");
+        assert_eq!(error.code(), "E0702");
+    }
+
+    #[test]
+    fn test_no_main() {
+        let program = compile("func f() := {};");
+
+        let error = bytecode::run(&program, &mut Vec::new()).unwrap_err();
+
+        assert_eq!(error.kind, RuntimeErrorKind::NoMain);
+    }
+
+    #[test]
+    fn test_stack_is_balanced() {
+        let program = compile(
+            "func main() : u32 = { let mut i : u32 = 0; while true { i += 1; if let true = i == 3 { break; } } i };",
+        );
+
+        let main = &program.functions[program.main.unwrap() as usize];
+
+        assert_eq!(main.code.last(), Some(&Instr::Return));
+        assert_eq!(main.code.len(), main.spans.len());
+        assert_eq!(bytecode::run(&program, &mut Vec::new()), Ok(Value::Int(3)));
+    }
+
+    #[test]
+    fn test_disassembly() {
+        let program = compile("func double(a : u32) : u32 = { a * 2 };");
+
+        assert_eq!(
+            bytecode::disassemble(&program),
+            "func double(a) [a]\n\
+             \x20 0000  load 0          ; a\n\
+             \x20 0001  const 0         ; 2\n\
+             \x20 0002  mul u32\n\
+             \x20 0003  return\n"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compile a source read from stdin with the compiler's executable,
+    /// returning what it printed to stdout and stderr, and its exit code.
+    fn penguin(source: &str, args: &[&str]) -> (String, String, Option<i32>) {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_penguin"))
+            .args(["-f", "-"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(source.as_bytes())
+            .unwrap();
+
+        let output = child.wait_with_output().unwrap();
+
+        (
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+            output.status.code(),
+        )
+    }
+
+    #[test]
+    fn test_compiling() {
+        let (stdout, stderr, code) = penguin("func main() := {};", &[]);
+
+        assert_eq!(stdout, "[✔] Sucessfully compiled.\n");
+        assert_eq!(stderr, "");
+        assert_eq!(code, Some(0));
+    }
+
+    #[test]
+    fn test_errors_fail() {
+        for (source, error) in [
+            ("func main() := { let x := $; };", "[LEXER ERROR]: "),
+            (
+                "func main() := { let x := (; };",
+                "[PARSER ERROR]: [stdin:1:28] ",
+            ),
+            (
+                "func main() := { let x := y; };",
+                "[RESOLVE ERROR]: [stdin:1:27] ",
+            ),
+            (
+                "func main() := { let x : u8 = true; };",
+                "[TYPE ERROR]: [stdin:1:31] ",
+            ),
+            ("func start() := {};", "[TYPE ERROR]: "),
+        ] {
+            let (stdout, stderr, code) = penguin(source, &[]);
+
+            // Errors are written to stderr, and nothing is compiled
+            assert_eq!(stdout, "", "{}", source);
+            assert!(stderr.starts_with(error), "{}", stderr);
+            assert_eq!(code, Some(1), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_denied_lints_fail() {
+        let unused = "func main() := { let x := 1; };";
+
+        // Warnings don't stop a program from compiling
+        let (stdout, stderr, code) = penguin(unused, &[]);
+
+        assert_eq!(stdout, "[✔] Sucessfully compiled.\n");
+        assert!(stderr.starts_with("[WARNING]: "), "{}", stderr);
+        assert_eq!(code, Some(0));
+
+        for (source, args) in [
+            (unused, &["-D", "unused_variables"][..]),
+            (unused, &["-D", "warnings"]),
+            (
+                "@deny(unused_variables) func main() := { let x := 1; };",
+                &[],
+            ),
+        ] {
+            let (stdout, stderr, code) = penguin(source, args);
+
+            assert_eq!(stdout, "", "{}", source);
+            assert!(stderr.starts_with("[LINT ERROR]: "), "{}", stderr);
+            assert_eq!(code, Some(1), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_overflow_checks() {
+        let source = "import \"std.print\";\n\
+                      func add(a : u8, b : u8) : u8 = { a + b };\n\
+                      func main() := { print(add(200, 100)); };";
+
+        let (_, stderr, code) = penguin(source, &["--run"]);
+
+        assert!(stderr.starts_with("[RUNTIME ERROR]: "), "{}", stderr);
+        assert!(stderr.contains("doesn't fit in u8"), "{}", stderr);
+        assert_eq!(code, Some(1));
+
+        let (stdout, stderr, code) = penguin(source, &["--run", "--overflow-checks", "off"]);
+
+        assert!(stdout.ends_with("44\n"), "{}", stdout);
+        assert_eq!(stderr, "");
+        assert_eq!(code, Some(0));
+    }
+
+    #[test]
+    fn test_denied_import_cycles_fail() {
+        let directory =
+            std::env::temp_dir().join(format!("penguin-cli-cycles-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        std::fs::write(
+            directory.join("main.pg"),
+            "import other; pub func f() := {}; func main() := { other::g(); };",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("other.pg"),
+            "import main; pub func g() := {};",
+        )
+        .unwrap();
+
+        let compile = |args: &[&str]| {
+            let mut command = Command::new(env!("CARGO_BIN_EXE_penguin"));
+
+            command.arg("-f").arg(directory.join("main.pg")).args(args);
+
+            // Nothing is left behind in the cache
+            if cfg!(feature = "resolve-cache") {
+                command.arg("--no-cache");
+            }
+
+            command.output().unwrap()
+        };
+
+        let warned = compile(&[]);
+        let denied = compile(&["-D", "import_cycles"]);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert!(String::from_utf8(warned.stderr)
+            .unwrap()
+            .contains("The modules import each other"));
+        assert_eq!(warned.status.code(), Some(0));
+        assert!(String::from_utf8(denied.stderr)
+            .unwrap()
+            .starts_with("[LINT ERROR]: "));
+        assert_eq!(denied.status.code(), Some(1));
+    }
+}
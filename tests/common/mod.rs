@@ -0,0 +1,93 @@
+//! Helpers shared by the tests of the backends that build executables.
+use penguin::consteval;
+use penguin::hir;
+use penguin::lexer::Lexer;
+use penguin::parser::Parser;
+use penguin::resolve::Resolver;
+use penguin::typeck::TypeChecker;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Lower a source that compiles.
+pub fn lower(source: &str) -> hir::Program {
+    let tokens = Lexer::lex_from_string(source).lex().unwrap();
+    let parsed = Parser::parse(&tokens).unwrap();
+    let resolution = Resolver::resolve(&parsed).unwrap();
+    let types = TypeChecker::check(&parsed, &resolution).unwrap();
+    let consts = consteval::evaluate(&parsed, &resolution, &types).unwrap();
+
+    hir::lower(&parsed, &resolution, &types, &consts)
+}
+
+/// Build an executable with a backend and run it, returning what it printed
+/// and its exit code. `write_object` writes the object file to the path it
+/// is given, which is linked with `cc`, after checking that it and `tools`
+/// are installed. If one isn't, the test is skipped, which is printed even
+/// when the output of tests is captured, and `None` is returned.
+pub fn run(
+    backend: &str,
+    name: &str,
+    tools: &[&str],
+    write_object: impl FnOnce(&Path),
+) -> Option<(String, i32)> {
+    for tool in tools.iter().copied().chain(["cc"]) {
+        if Command::new(tool).arg("--version").output().is_err() {
+            // Written to stderr directly, since the test harness only
+            // captures what is printed with `eprintln!`
+            let _ = writeln!(
+                std::io::stderr(),
+                "skipping '{}' with {}, since '{}' isn't installed",
+                name,
+                backend,
+                tool
+            );
+
+            return None;
+        }
+    }
+
+    let directory = TempDir::new(&format!("{}-{}", backend, name));
+    let (object, executable) = (
+        directory.0.join(format!("{}.o", name)),
+        directory.0.join(name),
+    );
+
+    write_object(&object);
+
+    let linked = Command::new("cc")
+        .arg(&object)
+        .arg("-o")
+        .arg(&executable)
+        .status()
+        .unwrap();
+
+    assert!(linked.success());
+
+    let output = Command::new(&executable).output().unwrap();
+
+    Some((
+        String::from_utf8(output.stdout).unwrap(),
+        output.status.code().unwrap(),
+    ))
+}
+
+/// A directory of its own for a test, deleted with everything in it when it
+/// is dropped, even if the test fails.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("penguin-{}-{}", name, std::process::id()));
+
+        std::fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,434 @@
+use penguin::arithmetic::Overflow;
+use penguin::consteval::{self, ConstError, ConstErrorKind, Consts, Limits, Value};
+use penguin::lexer::{Lexer, Span};
+use penguin::parser::ast::{ItemKind, Let};
+use penguin::parser::{ParseResult, Parser};
+use penguin::resolve::Resolver;
+use penguin::typeck::{IntTy, TypeChecker};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> ParseResult {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        Parser::parse(&tokens).unwrap()
+    }
+
+    /// Evaluate the constants of a source that type checks.
+    fn evaluate(source: &str) -> (ParseResult, Result<Consts, Vec<ConstError>>) {
+        let parsed = parse(source);
+        let resolution = Resolver::resolve(&parsed).unwrap();
+        let types = TypeChecker::check(&parsed, &resolution).unwrap();
+        let consts = consteval::evaluate(&parsed, &resolution, &types);
+
+        (parsed, consts)
+    }
+
+    fn errors(source: &str) -> Vec<ConstErrorKind> {
+        evaluate(source)
+            .1
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.kind)
+            .collect()
+    }
+
+    /// The value of every global and constant, in order.
+    fn values(source: &str) -> Vec<Option<Value>> {
+        let (parsed, consts) = evaluate(source);
+        let consts = consts.unwrap();
+
+        parsed
+            .program
+            .items
+            .iter()
+            .filter_map(|item| match &item.kind {
+                ItemKind::Global(Let { value, .. }) | ItemKind::Const(Let { value, .. }) => {
+                    Some(consts.expr(*value).cloned())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn not_constant(what: &str) -> ConstErrorKind {
+        ConstErrorKind::NotConstant {
+            what: what.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_folding() {
+        let source = "const A : u32 = 2 + 3 * 4; const B : i32 = -(20 - 14) / 3; \
+                      const C := \"pen\" + \"guin\"; const D := A > 10 && !(B == 2) || false; \
+                      const E := [A, 2, 1][1]; let F := (A, C); let G := f(); \
+                      func f() : u32 = { A };";
+
+        assert_eq!(
+            values(source),
+            [
+                Some(Value::Int(14)),
+                Some(Value::Int(-2)),
+                Some(Value::Str("penguin".to_string())),
+                Some(Value::Bool(true)),
+                Some(Value::Int(2)),
+                Some(Value::Tuple(vec![
+                    Value::Int(14),
+                    Value::Str("penguin".to_string())
+                ])),
+                // Globals don't have to be constant
+                None,
+            ]
+        );
+
+        assert_eq!(Value::Tuple(vec![Value::Int(1)]).to_string(), "(1,)");
+    }
+
+    #[test]
+    fn test_strings() {
+        let source = "const A := \"pen\" + \"guin\"; const B := A.len(); const C := A[0]; \
+                      const D := A == \"penguin\"; const E := \"\".len();";
+
+        assert_eq!(
+            values(source),
+            [
+                Some(Value::Str("penguin".to_string())),
+                Some(Value::Int(7)),
+                Some(Value::Int(112)),
+                Some(Value::Bool(true)),
+                Some(Value::Int(0)),
+            ]
+        );
+
+        assert_eq!(
+            errors("const A := \"pen\"[3];"),
+            [ConstErrorKind::IndexOutOfBounds {
+                index: 3,
+                length: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_casts() {
+        let source = "const A := 300 as u8; const B := 255 as i8; const C := -1 as u64; \
+                      const D := -1 as i64 as u8; const E := true as u32; const F := A as u32;";
+
+        assert_eq!(
+            values(source),
+            [
+                Some(Value::Int(44)),
+                Some(Value::Int(-1)),
+                Some(Value::Int(u64::MAX as i128)),
+                Some(Value::Int(255)),
+                Some(Value::Int(1)),
+                Some(Value::Int(44)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constants_in_functions_are_folded() {
+        let (parsed, consts) = evaluate("const HOUR := 60 * 60; func f(a : u32) := { a * HOUR };");
+        let consts = consts.unwrap();
+
+        let folded: Vec<_> = parsed
+            .arena
+            .iter()
+            .map(|(id, _)| consts.expr(id).cloned())
+            .collect();
+
+        // `a`, and `a * HOUR`, aren't known until `f` is called
+        assert_eq!(
+            folded,
+            [
+                Some(Value::Int(60)),
+                Some(Value::Int(60)),
+                Some(Value::Int(3600)),
+                None,
+                Some(Value::Int(3600)),
+                None,
+            ]
+        );
+        assert_eq!(
+            consts.items.values().collect::<Vec<_>>(),
+            [&Value::Int(3600)]
+        );
+    }
+
+    #[test]
+    fn test_constant_contexts() {
+        let source = "let a := 1; func f() : u32 = { 1 }; \
+                      const B : u32 = a + 1; const C : u32 = f(); \
+                      const D : u32 = { let x := 1; x };";
+        let (_, consts) = evaluate(source);
+        let errors = consts.unwrap_err();
+
+        assert_eq!(errors[0].kind, not_constant("the global 'a'"));
+        assert_eq!(errors[0].code(), "E0401");
+        assert_eq!(
+            errors[0].to_string(),
+            "The global 'a' can't be evaluated when compiling."
+        );
+
+        let b = source.find("const B").unwrap();
+
+        assert_eq!(errors[0].span(), Span::new(b + 16, b + 17));
+        assert_eq!(errors[0].declared(), Some(Span::new(b, b + 22)));
+
+        assert_eq!(errors[1].kind, not_constant("a call"));
+        assert_eq!(errors[2].kind, not_constant("a block with statements"));
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_evaluation_errors() {
+        assert_eq!(
+            errors(
+                "const A : u32 = 1 / (2 - 2); const B : u32 = [1, 2][2]; \
+                 const C : u32 = D; const D : u32 = C + A;"
+            ),
+            [
+                ConstErrorKind::DivisionByZero,
+                ConstErrorKind::IndexOutOfBounds {
+                    index: 2,
+                    length: 2,
+                },
+                ConstErrorKind::Cycle {
+                    name: "C".to_string(),
+                },
+            ]
+        );
+
+        // The right side of `&&` isn't evaluated when it doesn't need to be
+        assert!(evaluate("const A := false && 1 / 0 == 1;").1.is_ok());
+    }
+
+    #[test]
+    fn test_values_have_to_fit_their_types() {
+        let source = "const A : u8 = 250 + 10; const B : i8 = -128; const C : i8 = -129; \
+                      func f() := { let a : u8 = 300; let b : u8 = 250 + 10 + 1; \
+                      let c := 300 as u8; let d : u16 = 65535; };";
+        let out_of_range = |value, ty| ConstErrorKind::OutOfRange { value, ty };
+
+        let (parsed, consts) = evaluate(source);
+        let errors = consts.unwrap_err();
+        let spans: Vec<_> = errors
+            .iter()
+            .map(|error| &source[error.span().start..error.span().end])
+            .collect();
+
+        // Only the innermost value that doesn't fit is reported
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| error.kind.clone())
+                .collect::<Vec<_>>(),
+            [
+                out_of_range(260, IntTy::U8),
+                out_of_range(-129, IntTy::I8),
+                out_of_range(300, IntTy::U8),
+                out_of_range(260, IntTy::U8),
+            ]
+        );
+        assert_eq!(spans, ["250 + 10", "-129", "300", "250 + 10"]);
+        assert_eq!(errors[0].declared(), Some(parsed.program.items[0].span));
+        assert_eq!(
+            errors[0].to_string(),
+            "The value 260 doesn't fit in u8, which holds 0 to 255."
+        );
+    }
+
+    #[test]
+    fn test_folding_without_overflow_checks() {
+        let wrapping = |source: &str| {
+            let parsed = parse(source);
+            let resolution = Resolver::resolve(&parsed).unwrap();
+            let types = TypeChecker::check(&parsed, &resolution).unwrap();
+
+            (
+                consteval::evaluate_with(
+                    &parsed,
+                    &resolution,
+                    &types,
+                    Limits::default(),
+                    Overflow::Wrapping,
+                ),
+                parsed,
+            )
+        };
+
+        let source = "const A : u8 = 250 + 10; const B : i8 = -128 - 1; \
+                      const C : i8 = -(-128); const D : i64 = -9223372036854775807 - 1; \
+                      const E : i64 = D * D + 3; const F : i8 = (-128) / -1; \
+                      const G : i8 = (-128) % -1; const H : u32 = 0 - 1;";
+        let (consts, parsed) = wrapping(source);
+        let consts = consts.unwrap();
+
+        // Results wrap around in their types
+        assert_eq!(
+            parsed
+                .program
+                .items
+                .iter()
+                .filter_map(|item| match &item.kind {
+                    ItemKind::Const(Let { value, .. }) => consts.expr(*value).cloned(),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            [
+                Value::Int(4),
+                Value::Int(127),
+                Value::Int(-128),
+                Value::Int(i64::MIN as i128),
+                Value::Int(3),
+                Value::Int(-128),
+                Value::Int(0),
+                Value::Int(u32::MAX as i128),
+            ]
+        );
+
+        // Values written out still have to fit, and dividing by zero is an
+        // error either way
+        assert_eq!(
+            wrapping("const A : u8 = 300; const B : u8 = 1 / (1 - 1);")
+                .0
+                .unwrap_err()
+                .into_iter()
+                .map(|error| error.kind)
+                .collect::<Vec<_>>(),
+            [
+                ConstErrorKind::OutOfRange {
+                    value: 300,
+                    ty: IntTy::U8
+                },
+                ConstErrorKind::DivisionByZero,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constant_indexes_of_arrays() {
+        let source = "const ARR := [1, 2, 3]; \
+                      func f(i : u32) := { let xs : [u32; 2] = [1, 2]; xs[2]; xs[1]; xs[i]; \
+                      ARR[5]; [4, 5][-1]; };";
+
+        let errors = evaluate(source).1.unwrap_err();
+        let text = |span: Option<Span>| span.map(|span| &source[span.start..span.end]);
+
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| error.kind.clone())
+                .collect::<Vec<_>>(),
+            [
+                ConstErrorKind::IndexOutOfBounds {
+                    index: 2,
+                    length: 2,
+                },
+                ConstErrorKind::IndexOutOfBounds {
+                    index: 5,
+                    length: 3,
+                },
+                ConstErrorKind::IndexOutOfBounds {
+                    index: -1,
+                    length: 2,
+                },
+            ]
+        );
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| (text(Some(error.span())), text(error.declared())))
+                .collect::<Vec<_>>(),
+            [
+                (Some("xs[2]"), Some("xs")),
+                (Some("ARR[5]"), Some("const ARR := [1, 2, 3];")),
+                (Some("[4, 5][-1]"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluation_limits() {
+        let limited = |source: &str, limits| {
+            let parsed = parse(source);
+            let resolution = Resolver::resolve(&parsed).unwrap();
+            let types = TypeChecker::check(&parsed, &resolution).unwrap();
+
+            consteval::evaluate_with(&parsed, &resolution, &types, limits, Overflow::Checked)
+                .unwrap_err()
+                .into_iter()
+                .map(|error| error.kind)
+                .collect::<Vec<_>>()
+        };
+
+        // Strings doubling in size take a step for each byte
+        let doubling = "const A := \"ab\"; const B := A + A; const C := B + B; \
+                        const D := C + C;";
+        let steps = Limits {
+            steps: 10,
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            limited(doubling, steps),
+            [ConstErrorKind::TooManySteps { limit: 10 }]
+        );
+
+        // Each constant used is evaluated inside of the one using it
+        let chain = "const A : u32 = B + 1; const B : u32 = C + 1; const C : u32 = 1;";
+        let depth = Limits {
+            depth: 4,
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            limited(chain, depth),
+            [ConstErrorKind::TooDeep { limit: 4 }]
+        );
+        assert_eq!(
+            values(chain),
+            [
+                Some(Value::Int(3)),
+                Some(Value::Int(2)),
+                Some(Value::Int(1))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_static_assertions() {
+        let source = "const SIZE : u32 = 4; static_assert(SIZE % 2 == 0); \
+                      static_assert(SIZE > 4, \"SIZE is too small\"); \
+                      func f() := { static_assert(SIZE == 1); };";
+        let (_, consts) = evaluate(source);
+        let failed = consts.unwrap_err();
+
+        assert_eq!(
+            failed[0].kind,
+            ConstErrorKind::AssertionFailed {
+                message: Some("SIZE is too small".to_string()),
+            }
+        );
+        assert_eq!(failed[0].code(), "E0406");
+        assert_eq!(
+            failed[0].to_string(),
+            "Static assertion failed: SIZE is too small."
+        );
+
+        let condition = source.find("SIZE > 4").unwrap();
+
+        assert_eq!(failed[0].span(), Span::new(condition, condition + 8));
+
+        assert_eq!(failed[1].to_string(), "Static assertion failed.");
+        assert_eq!(failed.len(), 2);
+
+        assert_eq!(
+            errors("func f(a : bool) := { static_assert(a); };"),
+            [not_constant("the parameter 'a'")]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+#![cfg(feature = "cranelift")]
+use penguin::backend::{Arch, Backend, CodegenErrorKind, Os, Target};
+use penguin::bytecode;
+use penguin::cranelift::jit::Exit;
+use penguin::cranelift::{self, Cranelift};
+use penguin::hir;
+use penguin::typeck::Ty;
+use std::process::Command;
+
+mod common;
+
+use common::lower;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clif(source: &str) -> String {
+        cranelift::clif(&lower(source), None, Target::host()).unwrap()
+    }
+
+    /// Compile a source to an executable and run it, returning what it
+    /// printed and its exit code, or `None` if there is no C compiler to
+    /// link it with.
+    fn run(name: &str, source: &str) -> Option<(String, i32)> {
+        common::run("cranelift", name, &[], |object| {
+            Cranelift
+                .write_object(&lower(source), None, Target::host(), object)
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn test_cross_compiling() {
+        let program = lower("import \"std.print\"; func main() : u32 = { print(1 + 2); 0 };");
+
+        let object =
+            cranelift::compile(&program, None, Target::new(Arch::Aarch64, Os::Linux)).unwrap();
+
+        // An ELF file for AArch64, which is machine 183
+        assert_eq!(object[..4], *b"\x7fELF");
+        assert_eq!(u16::from_le_bytes([object[18], object[19]]), 183);
+
+        let object =
+            cranelift::compile(&program, None, Target::new(Arch::Aarch64, Os::MacOs)).unwrap();
+
+        // A 64-bit Mach-O file for ARM64
+        assert_eq!(object[..4], [0xcf, 0xfa, 0xed, 0xfe]);
+        assert_eq!(
+            u32::from_le_bytes([object[4], object[5], object[6], object[7]]),
+            0x0100_000c
+        );
+
+        // `printf` takes what it prints on the stack there
+        let clif = cranelift::clif(&program, None, Target::new(Arch::Aarch64, Os::MacOs)).unwrap();
+
+        assert!(clif.contains(
+            "sig0 = (i64, i64, i64, i64, i64, i64, i64, i64, i64, i64) -> i32 apple_aarch64"
+        ));
+    }
+
+    #[test]
+    fn test_functions() {
+        let clif = clif("func double(a : u32) : u32 = { a * 2 };");
+
+        assert!(clif.starts_with("; pg.double\nfunction u0:0(i32) -> i32"));
+        assert!(clif.contains("umul_overflow v0, v1"));
+        // Libraries have no entry point
+        assert!(!clif.contains("; main\n"));
+    }
+
+    #[test]
+    fn test_values_are_flattened_into_scalars() {
+        let clif = clif(
+            "struct Point { x : i64, y : i64 }\n\
+             enum Shape { Dot, Circle(u32) }\n\
+             func f(p : Point, s : Shape, name : str) : (Point, bool) = { (p, true) };",
+        );
+
+        assert!(clif.contains("function u0:0(i64, i64, i32, i32, i64, i64) -> i64, i64, i8"));
+    }
+
+    #[test]
+    fn test_entry_point_sets_globals() {
+        let clif = clif("let start : u8 = 3; func main() : u8 = { start };");
+
+        assert!(clif.contains("; pg.init.start\n"));
+        assert!(clif.contains("; main\nfunction u0:2() -> i32"));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let errors = cranelift::clif(
+            &lower("func f() : u32 = { let g := func(a : u32) => a; g(1) };"),
+            None,
+            Target::host(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors.iter().map(|error| &error.kind).collect::<Vec<_>>(),
+            [&CodegenErrorKind::Unsupported {
+                backend: "Cranelift",
+                what: "closures".to_string()
+            }]
+        );
+        assert_eq!(errors[0].code(), "E0801");
+    }
+
+    /// Lower a source, with the right operand of every binary operator in
+    /// `main` replaced by a name from another file, whose type is unknown.
+    /// The type checker doesn't let these through, but they must be
+    /// reported if one does.
+    fn lower_with_unknown_operands(source: &str) -> hir::Program {
+        let mut program = lower(source);
+        let main = program
+            .functions
+            .iter_mut()
+            .find(|function| function.name == "main")
+            .unwrap();
+        let hir::ExprKind::Block(block) = &mut main.body.kind else {
+            unreachable!()
+        };
+
+        for stmt in &mut block.stmts {
+            if let hir::Stmt::Let { value, .. } = stmt {
+                if let hir::ExprKind::Binary { right, .. } = &mut value.kind {
+                    right.kind = hir::ExprKind::Import("print".to_string());
+                    right.ty = Ty::Unknown;
+                }
+            }
+        }
+
+        program
+    }
+
+    #[test]
+    fn test_operands_of_unknown_types() {
+        // Arithmetic, a comparison and `&&` each report their operand
+        // instead of panicking
+        let program = lower_with_unknown_operands(
+            "func main() := { let mut x := 1; let mut y := true; \
+             let a := x + x; let b := x < x; let c := y && y; };",
+        );
+        let errors = cranelift::clif(&program, None, Target::host()).unwrap_err();
+
+        assert!(errors.iter().any(|error| error.kind
+            == CodegenErrorKind::Unsupported {
+                backend: "Cranelift",
+                what: "values of unknown types".to_string()
+            }));
+        assert!(errors.iter().all(|error| error.code() == "E0801"));
+    }
+
+    #[test]
+    fn test_running_native_programs() {
+        let Some((output, code)) = run(
+            "program",
+            "import \"std.print\";\n\
+             struct Point { x : i64, y : i64 }\n\
+             enum Shape { Dot, Rect(i64, i64) }\n\
+             let mut count : u32 = 40;\n\
+             func area(s : Shape) : i64 = { match s { Shape::Rect(w, h) => w * h, Shape::Dot => 0 } };\n\
+             func fib(n : u32) : u32 = { if let true = n < 2 { return n; } fib(n - 1) + fib(n - 2) };\n\
+             func apply(f : func(u32) -> u32, a : u32) : u32 = { f(a) };\n\
+             func double(a : u32) : u32 = { a * 2 };\n\
+             func main() : u32 = {\n\
+                 let mut xs := [1, 2, 3];\n\
+                 xs[1] = 7;\n\
+                 let mut i := 0;\n\
+                 while i < 3 { defer { i += 1; } print(xs[i]); }\n\
+                 count += 2;\n\
+                 print(area(Shape::Rect(2, 3)), Shape::Dot, \"done\", count);\n\
+                 print(Point { y: 2, x: 1 }, [(1, true)], [\"quoted\"], apply(double, 4));\n\
+                 let s := \"pen\" + \"guin\";\n\
+                 print(s == \"penguin\", s.len(), s[0]);\n\
+                 fib(10)\n\
+             };",
+        ) else {
+            return;
+        };
+
+        assert_eq!(
+            output,
+            "1\n7\n3\n6 Shape::Dot done 42\n\
+             Point { x: 1, y: 2 } [(1, true)] [\"quoted\"] 8\ntrue 7 112\n"
+        );
+        assert_eq!(code, 55);
+    }
+
+    #[test]
+    fn test_short_circuiting() {
+        let Some((output, _)) = run(
+            "short_circuit",
+            "import \"std.print\";\n\
+             func loud(value : bool) : bool = { print(value); value };\n\
+             func main() := { print(loud(false) && loud(true)); print(loud(true) || loud(false)); };",
+        ) else {
+            return;
+        };
+
+        // The right operand is only run when it decides the result
+        assert_eq!(output, "false\nfalse\ntrue\ntrue\n");
+    }
+
+    #[test]
+    fn test_native_runtime_errors() {
+        let Some((output, code)) = run(
+            "overflow",
+            "func add(a : u8, b : u8) : u8 = { a + b }; func main() := { add(200, 100); };",
+        ) else {
+            return;
+        };
+
+        assert_eq!(output, "[RUNTIME ERROR]: The result doesn't fit in u8.\n");
+        assert_eq!(code, 1);
+
+        let Some((output, code)) = run(
+            "bounds",
+            "func at(a : [u32; 2], i : u32) : u32 = { a[i] }; func main() : u32 = { at([1, 2], 2) };",
+        ) else {
+            return;
+        };
+
+        assert_eq!(output, "[RUNTIME ERROR]: An index is out of bounds.\n");
+        assert_eq!(code, 1);
+    }
+
+    /// Programs run in memory print what they do with the virtual machine.
+    #[test]
+    fn test_jit() {
+        let program = lower(
+            "import \"std.print\";\n\
+             struct Point { x : i64, y : i64 }\n\
+             func fib(n : u32) : u32 = { if let true = n < 2 { return n; } fib(n - 1) + fib(n - 2) };\n\
+             func main() : u32 = {\n\
+                 let s := \"pen\" + \"guin\";\n\
+                 print(Point { y: 2, x: -1 }, [(1, true)], s, \"100%\", s.len());\n\
+                 let big : u64 = 18446744073709551615; let small : i64 = -9223372036854775807 - 1; print(big, small);\n\
+                 fib(10)\n\
+             };",
+        );
+
+        let mut output = Vec::new();
+        let code = cranelift::jit::run(&program, None, &mut output)
+            .unwrap()
+            .code();
+
+        let mut expected = Vec::new();
+        let value = bytecode::run(&bytecode::compile(&program), &mut expected).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+        assert_eq!(value, bytecode::Value::Int(code as i128));
+        assert_eq!(code, 55);
+
+        let errors =
+            cranelift::jit::run(&lower("func f() := {};"), None, &mut Vec::new()).unwrap_err();
+
+        assert_eq!(errors[0].code(), "E0803");
+
+        let errors = cranelift::jit::run(
+            &lower_with_unknown_operands("func main() := { let mut x := 1; let a := x + x; };"),
+            None,
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+
+        assert!(errors.iter().any(|error| error.kind
+            == CodegenErrorKind::Unsupported {
+                backend: "Cranelift",
+                what: "values of unknown types".to_string()
+            }));
+    }
+
+    /// A program run in memory that fails is stopped once it has printed the
+    /// error, and the compiler exits with the code it would have.
+    #[test]
+    fn test_jit_runtime_errors() {
+        let program = lower(
+            "import \"std.print\";\n\
+             func add(a : u8, b : u8) : u8 = { a + b };\n\
+             func twice(a : u8) : u8 = { add(a, a) };\n\
+             func main() := { print(\"before\"); twice(200); print(\"after\"); };",
+        );
+
+        // It can run again, so nothing is left behind by the first run
+        for _ in 0..2 {
+            let mut output = Vec::new();
+            let exit = cranelift::jit::run(&program, None, &mut output).unwrap();
+
+            assert_eq!(exit, Exit::Failed(1));
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                "before\n[RUNTIME ERROR]: The result doesn't fit in u8.\n"
+            );
+        }
+
+        let directory = std::env::temp_dir().join(format!("penguin-jit-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let file = directory.join("overflow.pg");
+        std::fs::write(
+            &file,
+            "import \"std.print\";\n\
+             func add(a : u8, b : u8) : u8 = { a + b };\n\
+             func main() := { print(\"before\"); add(200, 100); };",
+        )
+        .unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_penguin"))
+            .arg("-f")
+            .arg(&file)
+            .args(["--run", "--jit"])
+            .output()
+            .unwrap();
+
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "before\n[RUNTIME ERROR]: [overflow.pg:2:35] The result doesn't fit in u8.\n"
+        );
+        assert_eq!(output.status.code(), Some(1));
+    }
+}
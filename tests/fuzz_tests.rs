@@ -0,0 +1,371 @@
+use penguin::bytecode;
+use penguin::consteval;
+use penguin::hir;
+use penguin::lexer::{Lexer, LexerOptions};
+use penguin::parser::{ParseErrorKind, Parser, MAX_NESTING};
+use penguin::resolve::Resolver;
+use penguin::typeck::TypeChecker;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small xorshift generator, so the fuzzing is repeatable without
+    /// pulling in a dependency.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    /// Characters that are likely to find edge cases in the lexer.
+    const ALPHABET: &[char] = &[
+        'a', 'Z', '_', '0', '9', '@', ':', '=', ';', '\'', '"', '\\', 'n', '/', '*', '+', '-', '%',
+        '(', ')', '{', '}', '[', ']', ',', ' ', '\t', '\n', '\r', '$', 'é', '²', '🐧',
+    ];
+
+    /// Snippets of real programs, so inputs get past the first token.
+    const SNIPPETS: &[&str] = &[
+        "let ",
+        "func ",
+        ":=",
+        ": u32 =",
+        "/*",
+        "*/",
+        "//",
+        "\"str",
+        "'",
+        "1_000",
+        "99999999999999999999999",
+        "+=",
+        "++",
+        "@inline",
+        "LET",
+        "\\\n",
+        "r#",
+        "b\"",
+        "f'",
+    ];
+
+    /// Programs that compile, which are mutated a few tokens at a time so
+    /// most inputs get past parsing and name resolution.
+    const SEEDS: &[&str] = &[
+        "import \"std.print\";\n\
+         func bar(a : u32, b : u32) : u32 = { print(\"Adding\"); return a + b; };\n\
+         func main() := { let mut c := bar(34, 35); c += 1; while c > 2 { c -= 2; } };",
+        "struct Point { x : u32, y : u32 } enum Shape { Dot(Point), Line(u32, str), None }\n\
+         func f(s : Shape, o : u8?) : u32 = {\n\
+         if let Shape::Line(a, b) = s { return a + b.len(); }\n\
+         while let Some(c) = o { return c as u32; }\n\
+         match s { Shape::Dot(p) => p.x * p.y, _ => { let q := Point { x: 1, y: 2 }; q.x } } };",
+        "func max<T>(a : T, b : T) : T = { if let true = a > b { a } else { b } };\n\
+         const N : u32 = 4 * 2;\n\
+         func f(x : u8) := { let a := max(x, 1); let xs : [u32; N] = [1, 2, 3, 4, 5, 6, 7, 8];\n\
+         let g := func(y : u32) => y * 2; let b := xs[2] == 3 && !(a < 2) || false; let s := \"a\" + \"b\"; };",
+        "trait Shape { func area(self) : u32; }\n\
+         struct Square { side : u32 }\n\
+         impl Shape for Square { func area(self) : u32 = { self.side * self.side }; }\n\
+         func total<T : Shape>(a : T) : u32 = { a.area() };\n\
+         func f() := { let y := { let t := total(Square { side: 3 }); t + 1 }; y; };",
+    ];
+
+    fn random_source(rng: &mut Rng) -> String {
+        let length = rng.below(64);
+        let mut source = String::new();
+
+        for _ in 0..length {
+            if rng.below(4) == 0 {
+                source.push_str(SNIPPETS[rng.below(SNIPPETS.len())]);
+            } else {
+                source.push(ALPHABET[rng.below(ALPHABET.len())]);
+            }
+        }
+
+        source
+    }
+
+    fn random_options(rng: &mut Rng) -> LexerOptions {
+        let mut options = LexerOptions::new()
+            .case_insensitive_keywords(rng.below(2) == 0)
+            .keep_trivia(rng.below(2) == 0)
+            .tab_width(rng.below(9))
+            .strict_digit_separators(rng.below(2) == 0);
+
+        if rng.below(2) == 0 {
+            options = options.max_string_length(rng.below(8));
+        }
+
+        if rng.below(2) == 0 {
+            options = options.max_identifier_length(rng.below(8));
+        }
+
+        options
+    }
+
+    #[test]
+    fn test_lexer_never_panics() {
+        let mut rng = Rng(0x5eed_1234_abcd_ef01);
+
+        for _ in 0..20_000 {
+            let source = random_source(&mut rng);
+            let options = random_options(&mut rng);
+
+            // Any result is fine, as long as lexing returns one
+            let mut lexer = Lexer::lex_from_string(&source).with_options(options);
+            let result = lexer.lex();
+
+            if let Err(error) = result {
+                assert!(error.span().end <= source.len());
+
+                // Formatting the error shouldn't panic either
+                let _ = error.to_string();
+            }
+        }
+    }
+
+    /// Delete, duplicate, swap or copy a few tokens of a seed, and put the
+    /// program back together with spaces between them. Most of the time a
+    /// token is swapped for another of its kind, which usually still parses,
+    /// so the later stages see plenty of programs too.
+    fn mutate(rng: &mut Rng, seed: &str) -> String {
+        let tokens = Lexer::lex_from_string(seed).lex().unwrap();
+        let mut literals: Vec<&str> = tokens.iter().map(|token| token.literal).collect();
+
+        for _ in 0..=rng.below(3) {
+            let at = rng.below(literals.len());
+
+            match rng.below(8) {
+                0 => {
+                    literals.remove(at);
+                }
+                1 => literals.insert(at, literals[at]),
+                2 => {
+                    let other = rng.below(literals.len());
+                    literals.swap(at, other);
+                }
+                3 => literals[at] = literals[rng.below(literals.len())],
+                _ => {
+                    let Some(token) = tokens.get(at) else {
+                        continue;
+                    };
+                    let alike: Vec<_> = tokens
+                        .iter()
+                        .filter(|other| other.kind == token.kind)
+                        .collect();
+
+                    literals[at] = alike[rng.below(alike.len())].literal;
+                }
+            }
+        }
+
+        literals.join(" ")
+    }
+
+    /// Ways of nesting an expression of type `u32` in another one, which
+    /// `nest` wraps around `1` in a random order.
+    const NESTINGS: &[(&str, &str)] = &[
+        ("(", ")"),
+        ("{ ", " }"),
+        ("{ let v := ", "; v }"),
+        ("", " + 1"),
+        ("[", "][0]"),
+        ("if let true = true { ", " } else { 0 }"),
+        ("match (", ") { n => n }"),
+        ("f(", ")"),
+        ("(", ") as u32"),
+        ("(func(a : u32) => a)(", ")"),
+        ("P { x: ", " }.x"),
+    ];
+
+    /// A program whose main function nests an expression `depth` times.
+    fn nest(rng: &mut Rng, depth: usize) -> String {
+        let mut expression = "1".to_string();
+
+        for _ in 0..depth {
+            let (open, close) = NESTINGS[rng.below(NESTINGS.len())];
+
+            expression = format!("{}{}{}", open, expression, close);
+        }
+
+        format!(
+            "struct P {{ x : u32 }} func f(a : u32) : u32 = {{ a }};\n\
+             func main() := {{ let x := {}; x; }};",
+            expression
+        )
+    }
+
+    /// How far a program got through the compiler.
+    #[derive(Debug, PartialEq)]
+    enum Stage {
+        Lexing,
+        Parsing,
+        Resolving,
+        Checking,
+        Evaluating,
+        Compiled,
+    }
+
+    /// Compile a program to bytecode, formatting the errors of the stage that
+    /// fails. Each stage only runs when the one before it succeeded.
+    fn compile(source: &str) -> Stage {
+        let tokens = match Lexer::lex_from_string(source).lex() {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                let _ = error.to_string();
+
+                return Stage::Lexing;
+            }
+        };
+        let parsed = match Parser::parse(&tokens) {
+            Ok(parsed) => parsed,
+            Err(errors) => {
+                for error in errors {
+                    let _ = error.to_string();
+                }
+
+                return Stage::Parsing;
+            }
+        };
+        let resolution = match Resolver::resolve(&parsed) {
+            Ok(resolution) => resolution,
+            Err(errors) => {
+                for error in errors {
+                    let _ = error.to_string();
+                }
+
+                return Stage::Resolving;
+            }
+        };
+        let types = match TypeChecker::check(&parsed, &resolution) {
+            Ok(types) => types,
+            Err(errors) => {
+                for error in errors {
+                    let _ = error.to_string();
+                }
+
+                return Stage::Checking;
+            }
+        };
+        let consts = match consteval::evaluate(&parsed, &resolution, &types) {
+            Ok(consts) => consts,
+            Err(errors) => {
+                for error in errors {
+                    let _ = error.to_string();
+                }
+
+                return Stage::Evaluating;
+            }
+        };
+
+        bytecode::compile(&hir::lower(&parsed, &resolution, &types, &consts));
+
+        Stage::Compiled
+    }
+
+    /// Whether a program is nested too deeply to parse.
+    fn too_deep(source: &str) -> bool {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        Parser::parse(&tokens).is_err_and(|errors| {
+            errors
+                .iter()
+                .any(|error| error.kind == ParseErrorKind::NestingTooDeep)
+        })
+    }
+
+    #[test]
+    fn test_seeds_compile() {
+        for seed in SEEDS {
+            assert_eq!(compile(seed), Stage::Compiled, "{}", seed);
+        }
+    }
+
+    #[test]
+    fn test_front_end_never_panics() {
+        let mut rng = Rng(0xfeed_face_dead_beef);
+
+        for _ in 0..5_000 {
+            let seed = SEEDS[rng.below(SEEDS.len())];
+
+            // Any result is fine, as long as there is one
+            compile(&mutate(&mut rng, seed));
+        }
+    }
+
+    #[test]
+    fn test_deep_nesting_never_panics() {
+        let mut rng = Rng(0x0dee_9000_0000_0001);
+
+        for _ in 0..300 {
+            let depth = rng.below(MAX_NESTING * 2);
+            let source = nest(&mut rng, depth);
+
+            // Nesting within the limit compiles, and past it is an error
+            // instead of overflowing the stack
+            if !too_deep(&source) {
+                assert_eq!(compile(&source), Stage::Compiled, "{}", source);
+            }
+
+            if depth < MAX_NESTING / 4 {
+                assert!(!too_deep(&source), "{}", source);
+            }
+        }
+
+        // Far too deep, and mutated
+        for _ in 0..5 {
+            let source = nest(&mut rng, 10_000);
+
+            assert!(too_deep(&source));
+
+            compile(&mutate(&mut rng, &source));
+        }
+
+        for (open, close) in NESTINGS {
+            let source = format!(
+                "func main() := {{ let x := {}1{}; }};",
+                open.repeat(10_000),
+                close.repeat(10_000)
+            );
+
+            assert_eq!(compile(&source), Stage::Parsing);
+        }
+
+        for (open, close) in [("[", "; 1]"), ("", "?"), ("(", ",)")] {
+            let source = format!(
+                "func main(a : {}u32{}) := {{ match a {{ {}x{} => {{}} }} }};",
+                open.repeat(10_000),
+                close.repeat(10_000),
+                "(".repeat(10_000),
+                ")".repeat(10_000)
+            );
+
+            assert!(too_deep(&source));
+        }
+    }
+
+    #[test]
+    fn test_lexer_never_panics_on_random_bytes() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+
+        for _ in 0..20_000 {
+            let length = rng.below(32);
+            let bytes: Vec<u8> = (0..length).map(|_| rng.next() as u8).collect();
+            let source = String::from_utf8_lossy(&bytes);
+
+            let mut lexer = Lexer::lex_from_string(&source);
+
+            for token in lexer.by_ref().flatten() {
+                assert_eq!(token.literal, &source[token.span.start..token.span.end]);
+            }
+        }
+    }
+}
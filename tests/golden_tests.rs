@@ -0,0 +1,80 @@
+use penguin::lexer::Lexer;
+use penguin::parser::{sexp, Parser};
+use std::fs;
+use std::path::Path;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse every `.pg` file in `tests/golden`, and compare its tree to the
+    /// `.sexp` file next to it. Run with `PENGUIN_BLESS=1` to write the trees
+    /// instead, after checking the changes are expected.
+    #[test]
+    fn test_golden_files() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let bless = std::env::var_os("PENGUIN_BLESS").is_some();
+        let mut checked = 0;
+
+        let mut paths: Vec<_> = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "pg"))
+            .collect();
+
+        paths.sort();
+
+        for path in paths {
+            let source = fs::read_to_string(&path).unwrap();
+            let tokens = Lexer::lex_from_string(&source).lex().unwrap();
+            let parsed = Parser::parse(&tokens).unwrap_or_else(|errors| {
+                panic!("{} failed to parse: {:?}", path.display(), errors)
+            });
+
+            let actual = sexp::program(&parsed);
+            let expected_path = path.with_extension("sexp");
+
+            if bless {
+                fs::write(&expected_path, &actual).unwrap();
+            } else {
+                let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+
+                assert_eq!(
+                    actual,
+                    expected,
+                    "{} doesn't match {}",
+                    path.display(),
+                    expected_path.display()
+                );
+            }
+
+            checked += 1;
+        }
+
+        assert!(checked > 0, "No golden files were found");
+    }
+
+    #[test]
+    fn test_expression_sexp() {
+        let tokens = Lexer::lex_from_string("let x := foo(5);").lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+
+        assert_eq!(
+            sexp::program(&parsed),
+            "(global x (call (ident foo) (num 5)))\n"
+        );
+
+        let tokens = Lexer::lex_from_string("let x := -a - b;").lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+
+        let penguin::parser::ast::ItemKind::Global(global) = &parsed.program.items[0].kind else {
+            panic!("Expected a global");
+        };
+
+        // Unary and binary operators are told apart by how many operands they have
+        assert_eq!(
+            sexp::expr(&parsed.arena, global.value),
+            "(- (- (ident a)) (ident b))"
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use penguin::consteval;
+use penguin::hir::{self, Arm, Block, Expr, ExprKind, Pattern, Program, Stmt};
+use penguin::lexer::Lexer;
+use penguin::parser::ast::BinaryOp;
+use penguin::parser::Parser;
+use penguin::resolve::Resolver;
+use penguin::typeck::{IntTy, Ty, TypeChecker};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lower a source that compiles.
+    fn lower(source: &str) -> Program {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+        let resolution = Resolver::resolve(&parsed).unwrap();
+        let types = TypeChecker::check(&parsed, &resolution).unwrap();
+        let consts = consteval::evaluate(&parsed, &resolution, &types).unwrap();
+
+        hir::lower(&parsed, &resolution, &types, &consts)
+    }
+
+    /// The statements of the only function in a source.
+    fn body(source: &str) -> Vec<Stmt> {
+        match lower(source).functions.remove(0).body.kind {
+            ExprKind::Block(block) => block.stmts,
+            kind => panic!("Expected a block, found {:?}", kind),
+        }
+    }
+
+    fn expr(stmt: &Stmt) -> &Expr {
+        match stmt {
+            Stmt::Expr(expr) => expr,
+            stmt => panic!("Expected an expression, found {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_every_node_has_a_type() {
+        let program = lower("const N : u8 = 2; func f(a : u8) : u8 = { a * N };");
+        let function = &program.functions[0];
+
+        assert_eq!(function.ret, Ty::Int(IntTy::U8));
+        assert_eq!(function.locals[0].name, "a");
+        assert_eq!(function.locals[0].ty, Ty::Int(IntTy::U8));
+        assert_eq!(
+            hir::printer::print_tree(&program),
+            "Program\n\
+             \x20 Function f(a#0) : u8\n\
+             \x20   Block : u8\n\
+             \x20     Value\n\
+             \x20       Binary * : u8\n\
+             \x20         Local a#0 : u8\n\
+             \x20         Int 2 : u8\n"
+        );
+    }
+
+    #[test]
+    fn test_names_are_ids() {
+        let program = lower(
+            "let mut count : u32 = 0; func g() : u32 = { 1 }; \
+             func f() := { count = g(); };",
+        );
+        let stmts = match &program.functions[1].body.kind {
+            ExprKind::Block(block) => &block.stmts,
+            kind => panic!("Expected a block, found {:?}", kind),
+        };
+
+        let ExprKind::Assign { target, value } = &expr(&stmts[0]).kind else {
+            panic!("Expected an assignment");
+        };
+
+        assert_eq!(target.kind, ExprKind::Global(0));
+        assert!(matches!(
+            &value.kind,
+            ExprKind::Call { callee, .. } if callee.kind == ExprKind::Function(0)
+        ));
+        assert!(program.globals[0].mutable);
+    }
+
+    #[test]
+    fn test_while_is_a_loop() {
+        let stmts = body("func f(mut a : u32) := { while a > 0 { a -= 1; } };");
+        let looped = expr(&stmts[0]);
+
+        assert_eq!(looped.ty, Ty::unit());
+
+        let ExprKind::Loop(Block { stmts, value: None }) = &looped.kind else {
+            panic!("Expected a loop, found {:?}", looped.kind);
+        };
+
+        let ExprKind::Match { value, arms } = &expr(&stmts[0]).kind else {
+            panic!("Expected a match");
+        };
+
+        assert_eq!(value.ty, Ty::Bool);
+        assert_eq!(arms[0].pattern, Pattern::Bool(true));
+        assert_eq!(arms[1].pattern, Pattern::Wildcard);
+        assert_eq!(arms[1].value.kind, ExprKind::Break);
+        assert_eq!(arms[1].value.ty, Ty::Never);
+    }
+
+    #[test]
+    fn test_if_let_is_a_match() {
+        let program = lower("func f(a : u32?) : u32 = { if let Some(v) = a { v } else { 0 } };");
+        let function = &program.functions[0];
+
+        let ExprKind::Block(Block {
+            value: Some(value), ..
+        }) = &function.body.kind
+        else {
+            panic!("Expected a block with a value");
+        };
+
+        let ExprKind::Match { arms, .. } = &value.kind else {
+            panic!("Expected a match, found {:?}", value.kind);
+        };
+
+        assert_eq!(value.ty, Ty::Int(IntTy::U32));
+
+        let [Arm {
+            pattern: Pattern::Some(binding),
+            ..
+        }, Arm {
+            pattern: Pattern::Wildcard,
+            ..
+        }] = arms.as_slice()
+        else {
+            panic!("Expected `Some(v)` and `_` arms, found {:?}", arms);
+        };
+
+        let Pattern::Binding(v) = **binding else {
+            panic!("Expected a binding");
+        };
+
+        assert_eq!(function.locals[v.index()].name, "v");
+        assert_eq!(function.locals[v.index()].ty, Ty::Int(IntTy::U32));
+    }
+
+    #[test]
+    fn test_compound_assignments_are_expanded() {
+        let stmts = body("func f(mut a : u32) := { a += 2; };");
+
+        let ExprKind::Assign { target, value } = &expr(&stmts[0]).kind else {
+            panic!("Expected an assignment");
+        };
+
+        let ExprKind::Binary {
+            op: BinaryOp::Add,
+            left,
+            right,
+        } = &value.kind
+        else {
+            panic!("Expected an addition, found {:?}", value.kind);
+        };
+
+        assert_eq!(left, target);
+        assert_eq!(right.kind, ExprKind::Int(2));
+    }
+
+    #[test]
+    fn test_compound_assignment_targets_are_evaluated_once() {
+        let program = lower(
+            "func i() : u32 = { 1 }; \
+             func f(mut xs : [u32; 3]) := { xs[i()] += 1; xs[2] *= 3; };",
+        );
+        let function = &program.functions[1];
+        let ExprKind::Block(block) = &function.body.kind else {
+            panic!("Expected a block");
+        };
+
+        // The index is called once, into a temporary
+        let ExprKind::Block(Block { stmts, .. }) = &expr(&block.stmts[0]).kind else {
+            panic!("Expected a block with the temporary");
+        };
+
+        let Stmt::Let { local, value } = &stmts[0] else {
+            panic!("Expected a temporary");
+        };
+
+        assert!(matches!(value.kind, ExprKind::Call { .. }));
+        assert_eq!(function.locals[local.index()].name, "$0");
+
+        let ExprKind::Assign { target, .. } = &expr(&stmts[1]).kind else {
+            panic!("Expected an assignment");
+        };
+
+        let ExprKind::Index { index, .. } = &target.kind else {
+            panic!("Expected an index");
+        };
+
+        assert_eq!(index.kind, ExprKind::Local(*local));
+
+        // Literal indices don't need one
+        assert!(matches!(
+            expr(&block.stmts[1]).kind,
+            ExprKind::Assign { .. }
+        ));
+    }
+
+    #[test]
+    fn test_constants_are_folded() {
+        let program = lower(
+            "const A : u32 = 2 + 3; static_assert(A == 5); \
+             let b := [A, A * 2]; func f() : bool = { A > 4 };",
+        );
+
+        assert_eq!(
+            program.globals[0].value.kind,
+            ExprKind::Array(vec![
+                Expr {
+                    kind: ExprKind::Int(5),
+                    ty: Ty::Int(IntTy::U32),
+                    span: program.globals[0].value.span,
+                },
+                Expr {
+                    kind: ExprKind::Int(10),
+                    ty: Ty::Int(IntTy::U32),
+                    span: program.globals[0].value.span,
+                },
+            ])
+        );
+
+        let ExprKind::Block(Block {
+            value: Some(value), ..
+        }) = &program.functions[0].body.kind
+        else {
+            panic!("Expected a block with a value");
+        };
+
+        assert_eq!(value.kind, ExprKind::Bool(true));
+    }
+
+    #[test]
+    fn test_closures_capture_locals() {
+        let program = lower(
+            "func f(a : u32) : u32 = { let b := 2; \
+             let g := func(c : u32) => a + c + b + a; g(1) };",
+        );
+        let function = &program.functions[0];
+        let ExprKind::Block(block) = &function.body.kind else {
+            panic!("Expected a block");
+        };
+
+        let Stmt::Let { value, .. } = &block.stmts[1] else {
+            panic!("Expected a let");
+        };
+
+        let ExprKind::Closure {
+            params, captures, ..
+        } = &value.kind
+        else {
+            panic!("Expected a closure, found {:?}", value.kind);
+        };
+
+        let name = |id: &hir::LocalId| function.locals[id.index()].name.as_str();
+
+        assert_eq!(params.iter().map(name).collect::<Vec<_>>(), ["c"]);
+        assert_eq!(captures.iter().map(name).collect::<Vec<_>>(), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_generic_functions_are_monomorphized() {
+        let program = lower(
+            "func id<T>(a : T) : T = { a }; func twice<T>(a : T) : (T, T) = { (id(a), a) }; \
+             func f() := { twice(1); twice(\"a\"); id(2); };",
+        );
+
+        let names: Vec<_> = program
+            .functions
+            .iter()
+            .map(|function| function.full_name())
+            .collect();
+
+        assert_eq!(
+            names,
+            ["id<u32>", "id<str>", "twice<u32>", "twice<str>", "f"]
+        );
+        assert_eq!(program.functions[1].ret, Ty::Str);
+        assert_eq!(program.functions[1].locals[0].ty, Ty::Str);
+
+        // Each instance calls the instance with its types
+        let ExprKind::Block(Block {
+            value: Some(value), ..
+        }) = &program.functions[3].body.kind
+        else {
+            panic!("Expected a block with a value");
+        };
+
+        let ExprKind::Tuple(elements) = &value.kind else {
+            panic!("Expected a tuple, found {:?}", value.kind);
+        };
+
+        assert!(matches!(
+            &elements[0].kind,
+            ExprKind::Call { callee, .. } if callee.kind == ExprKind::Function(1)
+        ));
+    }
+
+    #[test]
+    fn test_methods_are_functions() {
+        let program = lower(
+            "trait Area { func area(self) : u32; } struct Square { side : u32 } \
+             impl Area for Square { func area(self) : u32 = { self.side * self.side }; } \
+             func f(s : Square) : u32 = { s.area() };",
+        );
+
+        assert_eq!(program.functions[0].name, "Square::area");
+        assert_eq!(program.functions[0].ret, Ty::Int(IntTy::U32));
+
+        // A method call is a call with the receiver as the first argument
+        let ExprKind::Block(Block {
+            value: Some(value), ..
+        }) = &program.functions[1].body.kind
+        else {
+            panic!("Expected a block with a value");
+        };
+
+        let ExprKind::Call { callee, args } = &value.kind else {
+            panic!("Expected a call, found {:?}", value.kind);
+        };
+
+        assert_eq!(callee.kind, ExprKind::Function(0));
+        assert!(matches!(
+            args[..],
+            [Expr {
+                kind: ExprKind::Local(_),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_operators_call_methods() {
+        let program = lower(
+            "trait Eq { func eq(self, other : Self) : bool; } struct V { x : u32 } \
+             impl Eq for V { func eq(self, other : V) : bool = { self.x == other.x }; } \
+             func f(a : V, b : V) : bool = { a != b };",
+        );
+
+        let ExprKind::Block(Block {
+            value: Some(value), ..
+        }) = &program.functions[1].body.kind
+        else {
+            panic!("Expected a block with a value");
+        };
+
+        // `!=` is the opposite of `eq`
+        let ExprKind::Unary { operand, .. } = &value.kind else {
+            panic!("Expected a unary expression, found {:?}", value.kind);
+        };
+
+        assert!(matches!(
+            &operand.kind,
+            ExprKind::Call { callee, args }
+                if callee.kind == ExprKind::Function(0) && args.len() == 2
+        ));
+        assert_eq!(operand.ty, Ty::Bool);
+    }
+
+    #[test]
+    fn test_variant_names_in_patterns() {
+        let program = lower(
+            "enum Shape { Circle(u32), Empty }; \
+             func f(s : Shape, a : u32?) : u32 = { \
+             match (s, a) { (Empty, None) => 0, (Circle(r), _) => r, (_, other) => 1 } };",
+        );
+        let ExprKind::Block(Block {
+            value: Some(value), ..
+        }) = &program.functions[0].body.kind
+        else {
+            panic!("Expected a block with a value");
+        };
+
+        let ExprKind::Match { arms, .. } = &value.kind else {
+            panic!("Expected a match");
+        };
+
+        assert_eq!(
+            arms[0].pattern,
+            Pattern::Tuple(vec![
+                Pattern::Variant {
+                    enum_name: "Shape".to_string(),
+                    variant: "Empty".to_string(),
+                    payload: Vec::new(),
+                },
+                Pattern::None,
+            ])
+        );
+        assert!(matches!(
+            &arms[1].pattern,
+            Pattern::Tuple(patterns) if matches!(
+                &patterns[0],
+                Pattern::Variant { enum_name, payload, .. }
+                    if enum_name == "Shape" && matches!(payload[0], Pattern::Binding(_))
+            )
+        ));
+        assert!(matches!(
+            &arms[2].pattern,
+            Pattern::Tuple(patterns) if matches!(patterns[1], Pattern::Binding(_))
+        ));
+        assert_eq!(
+            program.enums[0].variants[1],
+            ("Empty".to_string(), Vec::new())
+        );
+    }
+}
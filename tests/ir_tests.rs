@@ -0,0 +1,231 @@
+use penguin::backend::CodegenErrorKind;
+use penguin::consteval;
+use penguin::hir;
+use penguin::ir::{self, BlockId, InstKind, Terminator, Type};
+use penguin::lexer::Lexer;
+use penguin::parser::Parser;
+use penguin::resolve::Resolver;
+use penguin::typeck::{IntTy, TypeChecker};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lower a source that compiles to HIR.
+    fn lower_hir(source: &str) -> hir::Program {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+        let resolution = Resolver::resolve(&parsed).unwrap();
+        let types = TypeChecker::check(&parsed, &resolution).unwrap();
+        let consts = consteval::evaluate(&parsed, &resolution, &types).unwrap();
+
+        hir::lower(&parsed, &resolution, &types, &consts)
+    }
+
+    /// Lower a source to IR that is verified.
+    fn lower(source: &str) -> ir::Program {
+        let program = ir::lower(&lower_hir(source)).unwrap();
+
+        assert_eq!(ir::verify(&program), Ok(()));
+
+        program
+    }
+
+    #[test]
+    fn test_every_value_has_a_type() {
+        let program = lower("let start : u8 = 3; func double(a : u32) : u32 = { a * 2 };");
+
+        assert_eq!(program.functions[0].values[0], Type::Int(IntTy::U32));
+        assert_eq!(
+            ir::print(&program),
+            "global start: u8 {\n\
+             block0:\n    \
+                 v0: u8 = int 3\n    \
+                 return v0\n\
+             }\n\
+             \n\
+             func double(v0: u32) -> u32 {\n\
+             block0:\n    \
+                 v1: u32 = int 2\n    \
+                 v2: u32 = mul v0, v1\n    \
+                 return v2\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_variables_become_phi_nodes() {
+        let program =
+            lower("func count(n : u32) : u32 = { let mut i := 0; while i < n { i += 1; } n + i };");
+
+        assert_eq!(
+            ir::print(&program),
+            "func count(v0: u32) -> u32 {\n\
+             block0:\n    \
+                 v1: u32 = int 0\n    \
+                 jump block1\n\
+             block1:\n    \
+                 v2: u32 = phi [block0: v1], [block3: v5]\n    \
+                 v3: bool = lt v2, v0\n    \
+                 branch v3, block2, block4\n\
+             block2:\n    \
+                 v4: u32 = int 1\n    \
+                 v5: u32 = add v2, v4\n    \
+                 jump block3\n\
+             block3:\n    \
+                 jump block1\n\
+             block4:\n    \
+                 jump block5\n\
+             block5:\n    \
+                 v6: u32 = add v0, v2\n    \
+                 return v6\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_phi_nodes_of_one_value_are_removed() {
+        // `a` isn't assigned in the loop, and `b` is assigned itself
+        let program = lower(
+            "func f(n : u32) : u32 = { let mut a := n; let mut b := 1; let mut i := 0; \
+             while i < n { b = b; i += 1; } a + b + i };",
+        );
+        let phis: usize = program.functions[0]
+            .blocks
+            .iter()
+            .map(|block| block.phis.len())
+            .sum();
+
+        assert_eq!(phis, 1);
+    }
+
+    #[test]
+    fn test_branches() {
+        let program = lower(
+            "func sign(x : i8) : i8 = { match x { 0 => 0 as i8, n => { if let true = n < 0 { return -1; } 1 as i8 } } };\n\
+             func either(a : bool, b : bool) : bool = { a || b };",
+        );
+        let sign = &program.functions[0];
+
+        // Code after the `return` is left out
+        assert!(sign
+            .blocks
+            .iter()
+            .all(|block| !block.insts.iter().any(|inst| inst.kind == InstKind::Undef)));
+        assert_eq!(
+            sign.blocks.last().unwrap().terminator,
+            Terminator::Return(Some(sign.blocks.last().unwrap().phis[0].value))
+        );
+
+        let either = &program.functions[1];
+
+        assert_eq!(
+            either.blocks[0].terminator,
+            Terminator::Branch {
+                condition: either.params[0],
+                then: BlockId::new(2),
+                otherwise: BlockId::new(1)
+            }
+        );
+        assert_eq!(either.blocks[2].phis[0].incoming.len(), 2);
+    }
+
+    #[test]
+    fn test_assertions_and_calls() {
+        let program = lower(
+            "import \"std.print\";\n\
+             let mut count : u32 = 1;\n\
+             func apply(f : func(u32) -> u32, a : u32) : u32 = { f(a) };\n\
+             func double(a : u32) : u32 = { a * 2 };\n\
+             func main() := { count += 1; assert(count == 2, \"two\"); print(apply(double, count), \"done\"); };",
+        );
+        let text = ir::print(&program);
+
+        assert!(text.contains("    v2: u32 = call v0(v1)\n"));
+        assert!(text.contains("    set_global count, v2\n"));
+        assert!(text.contains("    fail \"Assertion failed: two\"\n"));
+        assert!(text.contains("    v6: func(u32) -> u32 = func double\n"));
+        assert!(text.contains("    v8: u32 = call apply(v6, v7)\n"));
+        assert!(text.contains("    print v8, v9\n    return\n"));
+    }
+
+    #[test]
+    fn test_verifier() {
+        let valid =
+            lower("func f(a : u32, b : bool) : u32 = { if let true = b { return a; } a + 1 };");
+
+        // A value used before it is defined
+        let mut program = valid.clone();
+        let function = &mut program.functions[0];
+        let result = function.blocks[3].insts[0].result.unwrap();
+
+        function.blocks[0].terminator = Terminator::Return(Some(result));
+
+        let errors = ir::verify(&program).unwrap_err();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "In 'f': v2 is used in block0 where it isn't always defined."
+        );
+
+        // A branch on something that isn't a boolean
+        let mut program = valid.clone();
+        let function = &mut program.functions[0];
+
+        if let Terminator::Branch { condition, .. } = &mut function.blocks[0].terminator {
+            *condition = function.params[0];
+        }
+
+        assert_eq!(
+            ir::verify(&program).unwrap_err()[0].message,
+            "the branch of block0 needs a bool, but v0 is a u32"
+        );
+
+        // A block jumping to the entry
+        let mut program = valid.clone();
+
+        program.functions[0].blocks[1].terminator = Terminator::Jump(BlockId::ENTRY);
+
+        assert_eq!(
+            ir::verify(&program).unwrap_err()[0].message,
+            "block0 is jumped to, but the function starts at it"
+        );
+
+        // A phi node missing the value for a block
+        let mut program = lower("func f(a : bool, b : bool) : bool = { a && b };");
+        let function = &mut program.functions[0];
+
+        function.blocks[2].phis[0].incoming.pop();
+
+        assert_eq!(
+            ir::verify(&program).unwrap_err()[0].message,
+            "the phi v2 in block2 doesn't have one value for each block jumping to it"
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let errors = ir::lower(&lower_hir(
+            "func f() : u32 = { let g := func(a : u32) => a; g(1) };",
+        ))
+        .unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            CodegenErrorKind::Unsupported {
+                backend: "SSA IR",
+                what: "closures".to_string()
+            }
+        );
+
+        let errors = ir::lower(&lower_hir("func f(a : (u32, u32)) : u32 = { 1 };")).unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            CodegenErrorKind::Unsupported {
+                backend: "SSA IR",
+                what: "values of type '(u32, u32)'".to_string()
+            }
+        );
+    }
+}
@@ -1,161 +1,177 @@
-use penguin::lexer::{Lexer, Token, TokenKind};
+use penguin::lexer::{Lexer, LexerErrorKind, LexerOptions, Source, Span, Token, TokenKind};
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Strip the spans from the tokens so tests can focus on what was lexed.
+    fn kinds_and_literals<'a>(tokens: &[Token<'a>]) -> Vec<(TokenKind, &'a str)> {
+        tokens
+            .iter()
+            .map(|token| (token.kind.clone(), token.literal))
+            .collect()
+    }
+
     #[test]
     fn test_assignment() {
-        let mut lexer = Lexer::lex_from_string(":=".to_string());
+        let mut lexer = Lexer::lex_from_string(":=");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::UnTypedAssignment, ":=".to_string())];
+        let expected = vec![(TokenKind::UnTypedAssignment, ":=")];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_spaced_assignment() {
-        let mut lexer = Lexer::lex_from_string(": =".to_string());
+        let mut lexer = Lexer::lex_from_string(": =");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::UnTypedAssignment, ":=".to_string())];
+        // The literal covers everything between the colon and equal sign
+        let expected = vec![(TokenKind::UnTypedAssignment, ": =")];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_typed_assignment() {
-        let mut lexer = Lexer::lex_from_string(": u32 =".to_string());
+        let mut lexer = Lexer::lex_from_string(": u32 =");
         let tokens = lexer.lex().unwrap();
 
-        // TODO: Should this be a TypedAssignment token with a type of u32?
-        // E.g TokenKind::TypedAssignment("u32"), or would that be done in the parser?
+        // The type is read by the parser, the lexer only produces its pieces
         let expected = vec![
-            Token::new(TokenKind::TypeAssignment, ":".to_string()),
-            Token::new(TokenKind::Identifier, "u32".to_string()),
-            Token::new(TokenKind::LetAssignment, "=".to_string()),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::LetAssignment, "="),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_underscore_identifier() {
-        let mut lexer = Lexer::lex_from_string("_".to_string());
+        let mut lexer = Lexer::lex_from_string("_");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::Identifier, "_".to_string())];
+        let expected = vec![(TokenKind::Identifier, "_")];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_multiple_underscores() {
-        let mut lexer = Lexer::lex_from_string("__foo__bar__baz____".to_string());
+        let mut lexer = Lexer::lex_from_string("__foo__bar__baz____");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(
-            TokenKind::Identifier,
-            "__foo__bar__baz____".to_string(),
-        )];
+        let expected = vec![(TokenKind::Identifier, "__foo__bar__baz____")];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_variable_assignment_with_underscore() {
-        let mut lexer =
-            Lexer::lex_from_string("let __foo__bar__baz____ : u32 = 123456;".to_string());
+        let mut lexer = Lexer::lex_from_string("let __foo__bar__baz____ : u32 = 123456;");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Assign, "let".to_string()),
-            Token::new(TokenKind::Identifier, "__foo__bar__baz____".to_string()),
-            Token::new(TokenKind::TypeAssignment, ":".to_string()),
-            Token::new(TokenKind::Identifier, "u32".to_string()),
-            Token::new(TokenKind::LetAssignment, "=".to_string()),
-            Token::new(TokenKind::Number(123456), "123456".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Assign, "let"),
+            (TokenKind::Identifier, "__foo__bar__baz____"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::LetAssignment, "="),
+            (TokenKind::Number(123456), "123456"),
+            (TokenKind::Semicolon, ";"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn lex_variable_assignment_to_string() {
-        let mut lexer = Lexer::lex_from_string("let x : = \"hello world\";".to_string());
+        let mut lexer = Lexer::lex_from_string("let x : = \"hello world\";");
         let tokens = lexer.lex().unwrap();
 
         let expected_tokens = vec![
-            Token::new(TokenKind::Assign, "let".to_string()),
-            Token::new(TokenKind::Identifier, "x".to_string()),
-            Token::new(TokenKind::UnTypedAssignment, ":=".to_string()),
-            Token::new(TokenKind::String, "hello world".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Assign, "let"),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::UnTypedAssignment, ": ="),
+            (
+                TokenKind::String("hello world".to_string()),
+                "\"hello world\"",
+            ),
+            (TokenKind::Semicolon, ";"),
         ];
 
-        assert_eq!(tokens, expected_tokens);
+        assert_eq!(kinds_and_literals(&tokens), expected_tokens);
     }
 
     #[test]
     fn test_escape_sequences() {
-        let mut lexer = Lexer::lex_from_string("'Don\\'t'".to_string());
+        let mut lexer = Lexer::lex_from_string("'Don\\'t'");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::String, "Don't".to_string())];
+        let expected = vec![(TokenKind::String("Don't".to_string()), "'Don\\'t'")];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_string_with_escaped_quotes() {
-        let mut lexer = Lexer::lex_from_string("\"\\\"hello\\\"\"".to_string());
+        let mut lexer = Lexer::lex_from_string("\"\\\"hello\\\"\"");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::String, "\"hello\"".to_string())];
+        let expected = vec![(
+            TokenKind::String("\"hello\"".to_string()),
+            "\"\\\"hello\\\"\"",
+        )];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_string_with_mixed_quotes() {
-        let mut lexer = Lexer::lex_from_string("\"Hello, 'world!'\"".to_string());
+        let mut lexer = Lexer::lex_from_string("\"Hello, 'world!'\"");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::String, "Hello, 'world!'".to_string())];
+        let expected = vec![(
+            TokenKind::String("Hello, 'world!'".to_string()),
+            "\"Hello, 'world!'\"",
+        )];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
 
-        let mut lexer_flipped = Lexer::lex_from_string("'Hello, \"world!\"'".to_string());
+        let mut lexer_flipped = Lexer::lex_from_string("'Hello, \"world!\"'");
         let tokens_flipped = lexer_flipped.lex().unwrap();
 
-        let expected_flipped = vec![Token::new(
-            TokenKind::String,
-            "Hello, \"world!\"".to_string(),
+        let expected_flipped = vec![(
+            TokenKind::String("Hello, \"world!\"".to_string()),
+            "'Hello, \"world!\"'",
         )];
 
-        assert_eq!(tokens_flipped, expected_flipped);
+        assert_eq!(kinds_and_literals(&tokens_flipped), expected_flipped);
     }
 
     #[test]
     fn test_string_with_escaped_backslash() {
-        let mut lexer = Lexer::lex_from_string("\"\\\\hello\\\\\"".to_string());
+        let mut lexer = Lexer::lex_from_string("\"\\\\hello\\\\\"");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::String, "\\hello\\".to_string())];
+        let expected = vec![(
+            TokenKind::String("\\hello\\".to_string()),
+            "\"\\\\hello\\\\\"",
+        )];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_number() {
-        let mut lexer = Lexer::lex_from_string("123".to_string());
+        let mut lexer = Lexer::lex_from_string("123");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::Number(123), "123".to_string())];
+        let expected = vec![(TokenKind::Number(123), "123")];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
 
         let number = match tokens[0].kind {
             TokenKind::Number(n) => n,
@@ -167,73 +183,213 @@ mod tests {
 
     #[test]
     fn test_number_with_seperator() {
-        let mut lexer = Lexer::lex_from_string("1_000".to_string());
+        let mut lexer = Lexer::lex_from_string("1_000");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![(TokenKind::Number(1000), "1_000")];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_trailing_digit_separator() {
+        let mut lexer = Lexer::lex_from_string("let a := 1_000_;");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(
+            error.kind,
+            LexerErrorKind::MalformedNumber("digit separators can't end a number".to_string())
+        );
+        assert_eq!(error.span(), Span::new(14, 15));
+        assert_eq!(error.code(), "E0008");
+    }
+
+    #[test]
+    fn test_repeated_digit_separator() {
+        let mut lexer = Lexer::lex_from_string("1__000");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(
+            error.kind,
+            LexerErrorKind::MalformedNumber("digit separators can't be repeated".to_string())
+        );
+        // The second separator is the one that's misplaced
+        assert_eq!(error.span(), Span::new(2, 3));
+    }
+
+    #[test]
+    fn test_lenient_digit_separators() {
+        let options = LexerOptions::new().strict_digit_separators(false);
+        let mut lexer = Lexer::lex_from_string("1____ 1__0 _1").with_options(options);
+        let tokens = lexer.lex().unwrap();
+
+        // A leading underscore is still an identifier
+        let expected = vec![
+            (TokenKind::Number(1), "1____"),
+            (TokenKind::Number(10), "1__0"),
+            (TokenKind::Identifier, "_1"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_number_too_large() {
+        let mut lexer = Lexer::lex_from_string("99999999999999999999999");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::NumberTooLarge);
+        assert_eq!(error.span(), Span::new(0, 23));
+    }
+
+    #[test]
+    fn test_non_ascii_digits_are_not_numbers() {
+        let mut lexer = Lexer::lex_from_string("1²");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::InvalidCharacter('²'));
+    }
+
+    #[test]
+    fn test_assignment_without_previous_token() {
+        let mut lexer = Lexer::lex_from_string("= 1");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::Number(1000), "1_000".to_string())];
+        let expected = vec![(TokenKind::LetAssignment, "="), (TokenKind::Number(1), "1")];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_short_increment() {
-        let mut lexer = Lexer::lex_from_string("x += 5;".to_string());
+        let mut lexer = Lexer::lex_from_string("x += 5;");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Identifier, "x".to_string()),
-            Token::new(TokenKind::ShortIncrement, "+=".to_string()),
-            Token::new(TokenKind::Number(5), "5".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::ShortIncrement, "+="),
+            (TokenKind::Number(5), "5"),
+            (TokenKind::Semicolon, ";"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_short_decrement() {
-        let mut lexer = Lexer::lex_from_string("x -= 5;".to_string());
+        let mut lexer = Lexer::lex_from_string("x -= 5;");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "x"),
+            (TokenKind::ShortDecrement, "-="),
+            (TokenKind::Number(5), "5"),
+            (TokenKind::Semicolon, ";"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_increment_and_decrement() {
+        let mut lexer = Lexer::lex_from_string("x++; y--;");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Identifier, "x".to_string()),
-            Token::new(TokenKind::ShortDecrement, "-=".to_string()),
-            Token::new(TokenKind::Number(5), "5".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::Increment, "++"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::Identifier, "y"),
+            (TokenKind::Decrement, "--"),
+            (TokenKind::Semicolon, ";"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_increment_followed_by_plus() {
+        let mut lexer = Lexer::lex_from_string("a+++b");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "a"),
+            (TokenKind::Increment, "++"),
+            (TokenKind::Plus, "+"),
+            (TokenKind::Identifier, "b"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_case_insensitive_keywords() {
-        let mut lexer = Lexer::lex_from_string("LET x : = 123;".to_string());
+        let options = LexerOptions::new().case_insensitive_keywords(true);
+        let mut lexer = Lexer::lex_from_string("LET x : = 123;").with_options(options);
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Assign, "LET".to_string()),
-            Token::new(TokenKind::Identifier, "x".to_string()),
-            Token::new(TokenKind::UnTypedAssignment, ":=".to_string()),
-            Token::new(TokenKind::Number(123), "123".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Assign, "LET"),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::UnTypedAssignment, ": ="),
+            (TokenKind::Number(123), "123"),
+            (TokenKind::Semicolon, ";"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_case_insensitive_longest_keyword() {
+        let options = LexerOptions::new().case_insensitive_keywords(true);
+        let mut lexer = Lexer::lex_from_string("CONTINUE Continues").with_options(options);
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Continue, "CONTINUE"),
+            (TokenKind::Identifier, "Continues"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_case_insensitive_every_keyword() {
+        let spec: serde_json::Value = serde_json::from_str(&penguin::lexer::token_spec()).unwrap();
+        let keywords = spec["keywords"].as_array().unwrap();
+
+        assert!(!keywords.is_empty());
+
+        for keyword in keywords {
+            let literal = keyword["literal"].as_str().unwrap();
+            let kind = keyword["kind"].as_str().unwrap();
+
+            for source in [
+                literal.to_uppercase(),
+                literal[..1].to_uppercase() + &literal[1..],
+            ] {
+                let options = LexerOptions::new().case_insensitive_keywords(true);
+                let mut lexer = Lexer::lex_from_string(&source).with_options(options);
+                let tokens = lexer.lex().unwrap();
+
+                assert_eq!(format!("{:?}", tokens[0].kind), kind, "{}", source);
+            }
+        }
     }
 
     #[test]
     fn test_that_we_can_have_numbers_and_letters() {
-        let mut lexer = Lexer::lex_from_string("x123".to_string());
+        let mut lexer = Lexer::lex_from_string("x123");
         let tokens = lexer.lex().unwrap();
 
-        let expected = vec![Token::new(TokenKind::Identifier, "x123".to_string())];
+        let expected = vec![(TokenKind::Identifier, "x123")];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_string_ending_with_backslash() {
-        let mut lexer = Lexer::lex_from_string("\"hello \\".to_string());
+        let mut lexer = Lexer::lex_from_string("\"hello \\");
 
         // We expect an error on lexing
         assert!(lexer.lex().is_err());
@@ -241,7 +397,7 @@ mod tests {
 
     #[test]
     fn test_string_with_no_end_quote() {
-        let mut lexer = Lexer::lex_from_string("\"hello".to_string());
+        let mut lexer = Lexer::lex_from_string("\"hello");
 
         // We expect an error on lexing
         assert!(lexer.lex().is_err());
@@ -249,7 +405,7 @@ mod tests {
 
     #[test]
     fn test_string_with_no_start_quote() {
-        let mut lexer = Lexer::lex_from_string("hello\"".to_string());
+        let mut lexer = Lexer::lex_from_string("hello\"");
 
         // We expect an error on lexing
         assert!(lexer.lex().is_err());
@@ -257,51 +413,48 @@ mod tests {
 
     #[test]
     fn test_number_with_no_digits() {
-        let mut lexer = Lexer::lex_from_string("1____".to_string());
-        let tokens = lexer.lex().unwrap();
-
-        let expected = vec![Token::new(TokenKind::Number(1), "1____".to_string())];
+        let mut lexer = Lexer::lex_from_string("1____");
+        let error = lexer.lex().unwrap_err();
 
-        assert_eq!(tokens, expected);
+        assert!(matches!(error.kind, LexerErrorKind::MalformedNumber(_)));
+        assert_eq!(error.span(), Span::new(2, 3));
     }
 
     #[test]
     fn test_ending_with_comment() {
-        let mut lexer = Lexer::lex_from_string("x := 123; // This is a comment".to_string());
+        let mut lexer = Lexer::lex_from_string("x := 123; // This is a comment");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Identifier, "x".to_string()),
-            Token::new(TokenKind::UnTypedAssignment, ":=".to_string()),
-            Token::new(TokenKind::Number(123), "123".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::UnTypedAssignment, ":="),
+            (TokenKind::Number(123), "123"),
+            (TokenKind::Semicolon, ";"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_inline_commenting() {
-        let mut lexer =
-            Lexer::lex_from_string("let __foo__bar__baz____ : /* u32 */ = 123;".to_string());
+        let mut lexer = Lexer::lex_from_string("let __foo__bar__baz____ : /* u32 */ = 123;");
 
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Assign, "let".to_string()),
-            Token::new(TokenKind::Identifier, "__foo__bar__baz____".to_string()),
-            Token::new(TokenKind::UnTypedAssignment, ":=".to_string()),
-            Token::new(TokenKind::Number(123), "123".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Assign, "let"),
+            (TokenKind::Identifier, "__foo__bar__baz____"),
+            (TokenKind::UnTypedAssignment, ": /* u32 */ ="),
+            (TokenKind::Number(123), "123"),
+            (TokenKind::Semicolon, ";"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_inline_commenting_with_no_end() {
-        let mut lexer =
-            Lexer::lex_from_string("let __foo__bar__baz____ : /* u32 = 123;".to_string());
+        let mut lexer = Lexer::lex_from_string("let __foo__bar__baz____ : /* u32 = 123;");
 
         // We expect an error on lexing
         assert!(lexer.lex().is_err());
@@ -309,191 +462,942 @@ mod tests {
 
     #[test]
     fn test_arithmetic_lexing() {
-        let mut lexer = Lexer::lex_from_string("1+2-3*4/5%6+=7-=8*=9/=1%=".to_string());
+        let mut lexer = Lexer::lex_from_string("1+2-3*4/5%6+=7-=8*=9/=1%=");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Number(1), "1"),
+            (TokenKind::Plus, "+"),
+            (TokenKind::Number(2), "2"),
+            (TokenKind::Minus, "-"),
+            (TokenKind::Number(3), "3"),
+            (TokenKind::Multiply, "*"),
+            (TokenKind::Number(4), "4"),
+            (TokenKind::Divide, "/"),
+            (TokenKind::Number(5), "5"),
+            (TokenKind::Modulo, "%"),
+            (TokenKind::Number(6), "6"),
+            (TokenKind::ShortIncrement, "+="),
+            (TokenKind::Number(7), "7"),
+            (TokenKind::ShortDecrement, "-="),
+            (TokenKind::Number(8), "8"),
+            (TokenKind::ShortMultiply, "*="),
+            (TokenKind::Number(9), "9"),
+            (TokenKind::ShortDivide, "/="),
+            (TokenKind::Number(1), "1"),
+            (TokenKind::ShortModulo, "%="),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_operators_at_end_of_input() {
+        let mut lexer = Lexer::lex_from_string("1 + - * / %");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Number(1), "1".to_string()),
-            Token::new(TokenKind::Plus, "+".to_string()),
-            Token::new(TokenKind::Number(2), "2".to_string()),
-            Token::new(TokenKind::Minus, "-".to_string()),
-            Token::new(TokenKind::Number(3), "3".to_string()),
-            Token::new(TokenKind::Multiply, "*".to_string()),
-            Token::new(TokenKind::Number(4), "4".to_string()),
-            Token::new(TokenKind::Divide, "/".to_string()),
-            Token::new(TokenKind::Number(5), "5".to_string()),
-            Token::new(TokenKind::Modulo, "%".to_string()),
-            Token::new(TokenKind::Number(6), "6".to_string()),
-            Token::new(TokenKind::ShortIncrement, "+=".to_string()),
-            Token::new(TokenKind::Number(7), "7".to_string()),
-            Token::new(TokenKind::ShortDecrement, "-=".to_string()),
-            Token::new(TokenKind::Number(8), "8".to_string()),
-            Token::new(TokenKind::ShortMultiply, "*=".to_string()),
-            Token::new(TokenKind::Number(9), "9".to_string()),
-            Token::new(TokenKind::ShortDivide, "/=".to_string()),
-            Token::new(TokenKind::Number(1), "1".to_string()),
-            Token::new(TokenKind::ShortModulo, "%=".to_string()),
+            (TokenKind::Number(1), "1"),
+            (TokenKind::Plus, "+"),
+            (TokenKind::Minus, "-"),
+            (TokenKind::Multiply, "*"),
+            (TokenKind::Divide, "/"),
+            (TokenKind::Modulo, "%"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_comparison_and_logical_operators() {
+        let mut lexer = Lexer::lex_from_string("== != < <= > >= && || ! a<=b");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Equal, "=="),
+            (TokenKind::NotEqual, "!="),
+            (TokenKind::LessThan, "<"),
+            (TokenKind::LessEqual, "<="),
+            (TokenKind::GreaterThan, ">"),
+            (TokenKind::GreaterEqual, ">="),
+            (TokenKind::And, "&&"),
+            (TokenKind::Or, "||"),
+            (TokenKind::Not, "!"),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::LessEqual, "<="),
+            (TokenKind::Identifier, "b"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_single_ampersand_and_pipe() {
+        let mut lexer = Lexer::lex_from_string("a & b");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::InvalidCharacter('&'));
+    }
+
+    #[test]
+    fn test_location_after_operator_before_newline() {
+        let mut lexer = Lexer::lex_from_string("a /\n+\n$");
+
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::InvalidCharacter('$'));
+        assert_eq!((error.location.line, error.location.column), (3, 1));
+    }
+
+    #[test]
+    fn test_untyped_assignment_across_line_comment() {
+        let mut lexer = Lexer::lex_from_string("a : // u32\n= 1");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "a"),
+            (TokenKind::UnTypedAssignment, ": // u32\n="),
+            (TokenKind::Number(1), "1"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_unclosed_comment_that_looks_closed() {
+        let mut lexer = Lexer::lex_from_string("/*/");
+
+        assert!(lexer.lex().is_err());
     }
 
     #[test]
     fn test_readme_example() {
-        let mut lexer = Lexer::lex_from_string("let x:u32=5;".to_string());
+        let mut lexer = Lexer::lex_from_string("let x:u32=5;");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Assign, "let".to_string()),
-            Token::new(TokenKind::Identifier, "x".to_string()),
-            Token::new(TokenKind::TypeAssignment, ":".to_string()),
-            Token::new(TokenKind::Identifier, "u32".to_string()),
-            Token::new(TokenKind::LetAssignment, "=".to_string()),
-            Token::new(TokenKind::Number(5), "5".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Assign, "let"),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::LetAssignment, "="),
+            (TokenKind::Number(5), "5"),
+            (TokenKind::Semicolon, ";"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_open_close_braces() {
-        let mut lexer = Lexer::lex_from_string("(){}[]".to_string());
+        let mut lexer = Lexer::lex_from_string("(){}[]");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::OpenParen, "(".to_string()),
-            Token::new(TokenKind::CloseParen, ")".to_string()),
-            Token::new(TokenKind::OpenBrace, "{".to_string()),
-            Token::new(TokenKind::CloseBrace, "}".to_string()),
-            Token::new(TokenKind::OpenBracket, "[".to_string()),
-            Token::new(TokenKind::CloseBracket, "]".to_string()),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::CloseBrace, "}"),
+            (TokenKind::OpenBracket, "["),
+            (TokenKind::CloseBracket, "]"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_function_lexing() {
-        let mut lexer = Lexer::lex_from_string("func main() := {}".to_string());
+        let mut lexer = Lexer::lex_from_string("func main() := {}");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Function, "func".to_string()),
-            Token::new(TokenKind::Identifier, "main".to_string()),
-            Token::new(TokenKind::OpenParen, "(".to_string()),
-            Token::new(TokenKind::CloseParen, ")".to_string()),
-            Token::new(TokenKind::UnTypedAssignment, ":=".to_string()),
-            Token::new(TokenKind::OpenBrace, "{".to_string()),
-            Token::new(TokenKind::CloseBrace, "}".to_string()),
+            (TokenKind::Function, "func"),
+            (TokenKind::Identifier, "main"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::UnTypedAssignment, ":="),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::CloseBrace, "}"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_function_case_sensitivity() {
-        let mut lexer = Lexer::lex_from_string("FuNc main() := {}".to_string());
+        let mut lexer = Lexer::lex_from_string("FuNc main() := {}");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Function, "FuNc".to_string()),
-            Token::new(TokenKind::Identifier, "main".to_string()),
-            Token::new(TokenKind::OpenParen, "(".to_string()),
-            Token::new(TokenKind::CloseParen, ")".to_string()),
-            Token::new(TokenKind::UnTypedAssignment, ":=".to_string()),
-            Token::new(TokenKind::OpenBrace, "{".to_string()),
-            Token::new(TokenKind::CloseBrace, "}".to_string()),
+            (TokenKind::Identifier, "FuNc"),
+            (TokenKind::Identifier, "main"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::UnTypedAssignment, ":="),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::CloseBrace, "}"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_function_with_return_type() {
-        let mut lexer = Lexer::lex_from_string("func main() : u32 = {}".to_string());
+        let mut lexer = Lexer::lex_from_string("func main() : u32 = {}");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Function, "func".to_string()),
-            Token::new(TokenKind::Identifier, "main".to_string()),
-            Token::new(TokenKind::OpenParen, "(".to_string()),
-            Token::new(TokenKind::CloseParen, ")".to_string()),
-            Token::new(TokenKind::TypeAssignment, ":".to_string()),
-            Token::new(TokenKind::Identifier, "u32".to_string()),
-            Token::new(TokenKind::LetAssignment, "=".to_string()),
-            Token::new(TokenKind::OpenBrace, "{".to_string()),
-            Token::new(TokenKind::CloseBrace, "}".to_string()),
+            (TokenKind::Function, "func"),
+            (TokenKind::Identifier, "main"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::LetAssignment, "="),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::CloseBrace, "}"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_function_with_return_type_and_parameters() {
-        let mut lexer = Lexer::lex_from_string("func main() : u32 = { return 5; }".to_string());
+        let mut lexer = Lexer::lex_from_string("func main() : u32 = { return 5; }");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Function, "func"),
+            (TokenKind::Identifier, "main"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::LetAssignment, "="),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::Return, "return"),
+            (TokenKind::Number(5), "5"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::CloseBrace, "}"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let mut lexer = Lexer::lex_from_string("let a := 5;");
+        let tokens = lexer.lex().unwrap();
+
+        let spans: Vec<Span> = tokens.iter().map(|token| token.span).collect();
+
+        let expected = vec![
+            Span::new(0, 3),
+            Span::new(4, 5),
+            Span::new(6, 8),
+            Span::new(9, 10),
+            Span::new(10, 11),
+        ];
+
+        assert_eq!(spans, expected);
+    }
+
+    #[test]
+    fn test_span_merge() {
+        assert_eq!(Span::new(4, 5).merge(Span::new(9, 11)), Span::new(4, 11));
+        assert_eq!(Span::new(9, 11).merge(Span::new(4, 5)), Span::new(4, 11));
+        assert_eq!(Span::new(0, 10).merge(Span::new(2, 3)), Span::new(0, 10));
+    }
+
+    #[test]
+    fn test_literals_are_slices_of_the_source() {
+        let source = "let a := 'héllo'; // ünïcode";
+        let mut lexer = Lexer::lex_from_string(source);
+        let tokens = lexer.lex().unwrap();
+
+        for token in &tokens {
+            assert_eq!(token.literal, &source[token.span.start..token.span.end]);
+        }
+
+        assert_eq!(tokens[3].kind, TokenKind::String("héllo".to_string()));
+        assert_eq!(tokens[3].literal, "'héllo'");
+    }
+
+    #[test]
+    fn test_lexer_as_iterator() {
+        let mut lexer = Lexer::lex_from_string("let a := 5;");
+
+        let first = lexer.next().unwrap().unwrap();
+        assert_eq!(first.kind, TokenKind::Assign);
+
+        // Only the first token has been lexed so far
+        assert_eq!(lexer.loc.index, 3);
+
+        let rest: Vec<TokenKind> = lexer.map(|token| token.unwrap().kind).collect();
+        let expected = vec![
+            TokenKind::Identifier,
+            TokenKind::UnTypedAssignment,
+            TokenKind::Number(5),
+            TokenKind::Semicolon,
+        ];
+
+        assert_eq!(rest, expected);
+    }
+
+    #[test]
+    fn test_iterator_stops_after_error() {
+        let mut lexer = Lexer::lex_from_string("a $ b");
+
+        assert!(lexer.next().unwrap().is_ok());
+        assert!(lexer.next().unwrap().is_err());
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_type_assignment_before_other_token() {
+        let mut lexer = Lexer::lex_from_string("a : u32");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "a"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_source_from_reader() {
+        let reader = "let a := 5;".as_bytes();
+        let source = Source::from_reader(reader, "buffer").unwrap();
+
+        let mut lexer = source.lexer();
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(source.name, "buffer");
+        assert_eq!(lexer.loc.source, "buffer");
+        assert_eq!(tokens.len(), 5);
+    }
+
+    #[test]
+    fn test_source_from_reader_with_invalid_utf8() {
+        let reader: &[u8] = &[0x6c, 0x65, 0x74, 0xff];
+
+        assert!(Source::from_reader(reader, "buffer").is_err());
+    }
+
+    #[test]
+    fn test_source_from_missing_path() {
+        let path = std::path::Path::new("examples/does_not_exist.pg");
+
+        assert!(Source::from_path(path).is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mapped_source() {
+        let path = std::path::Path::new("examples/comments.pg");
+
+        let mapped = penguin::lexer::MappedSource::from_path(path).unwrap();
+        let read = Source::from_path(path).unwrap();
+
+        let mapped_tokens = mapped.lexer().unwrap().lex().unwrap();
+        let read_tokens = read.lexer().lex().unwrap();
+
+        assert_eq!(mapped.name, "comments.pg");
+        assert_eq!(mapped_tokens, read_tokens);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_tokens_round_trip() {
+        let source = "func add(a, b) { return a + b; }";
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        let json = serde_json::to_string(&tokens).unwrap();
+        let read: Vec<Token> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read, tokens);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_token_json() {
+        let tokens = Lexer::lex_from_string("let").lex().unwrap();
+
+        let json = serde_json::to_string(&tokens[0]).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"kind":"Assign","literal":"let","span":{"start":0,"end":3}}"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_error_round_trip() {
+        let error = Lexer::lex_from_string("let $").lex().unwrap_err();
+
+        let json = serde_json::to_string(&error).unwrap();
+        let read: penguin::lexer::LexerError = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read.kind, LexerErrorKind::InvalidCharacter('$'));
+        assert_eq!(read.span(), error.span());
+        assert_eq!(read.to_string(), error.to_string());
+    }
+
+    #[test]
+    fn test_case_sensitive_keywords_by_default() {
+        let mut lexer = Lexer::lex_from_string("LET Let let");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "LET"),
+            (TokenKind::Identifier, "Let"),
+            (TokenKind::Assign, "let"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_raw_identifiers() {
+        let mut lexer = Lexer::lex_from_string("let r#let := r#x + rx;");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Assign, "let"),
+            (TokenKind::Identifier, "r#let"),
+            (TokenKind::UnTypedAssignment, ":="),
+            (TokenKind::Identifier, "r#x"),
+            (TokenKind::Plus, "+"),
+            (TokenKind::Identifier, "rx"),
+            (TokenKind::Semicolon, ";"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+
+        let names: Vec<_> = tokens.iter().filter_map(Token::identifier).collect();
+
+        assert_eq!(names, vec!["let", "x", "rx"]);
+    }
+
+    #[test]
+    fn test_raw_identifier_needs_a_name() {
+        let mut lexer = Lexer::lex_from_string("r#1");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::InvalidCharacter('#'));
+    }
+
+    #[test]
+    fn test_prefixed_strings() {
+        let mut lexer = Lexer::lex_from_string(r#"b"Wad\n" f'Hi {name}' b f bf"x""#);
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::ByteString(b"Wad\n".to_vec()), r#"b"Wad\n""#),
+            (
+                TokenKind::FormatString("Hi {name}".to_string()),
+                "f'Hi {name}'",
+            ),
+            (TokenKind::Identifier, "b"),
+            (TokenKind::Identifier, "f"),
+            (TokenKind::Identifier, "bf"),
+            (TokenKind::String("x".to_string()), r#""x""#),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_unterminated_prefixed_string() {
+        let mut lexer = Lexer::lex_from_string("let a := b\"Waddle");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::UnexpectedEOF);
+        assert_eq!(error.span(), Span::new(9, 17));
+    }
+
+    #[test]
+    fn test_keep_trivia_option() {
+        let options = LexerOptions::new().keep_trivia(true);
+        let mut lexer = Lexer::lex_from_string("a  // hi\nb /* c */").with_options(options);
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "a"),
+            (TokenKind::Whitespace, "  "),
+            (TokenKind::Comment, "// hi"),
+            (TokenKind::Whitespace, "\n"),
+            (TokenKind::Identifier, "b"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Comment, "/* c */"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        let mut lexer = Lexer::lex_from_string("let a := 1 + \\\n    2;\\\r\n");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Assign, "let"),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::UnTypedAssignment, ":="),
+            (TokenKind::Number(1), "1"),
+            (TokenKind::Plus, "+"),
+            (TokenKind::Number(2), "2"),
+            (TokenKind::Semicolon, ";"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_line_continuation_location() {
+        let mut lexer = Lexer::lex_from_string("a \\\n  $");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!((error.location.line, error.location.column), (2, 3));
+        assert_eq!(error.span(), Span::new(6, 7));
+    }
+
+    #[test]
+    fn test_line_continuation_trivia() {
+        let options = LexerOptions::new().keep_trivia(true);
+        let mut lexer = Lexer::lex_from_string("a \\\n b : \\\n = c").with_options(options);
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "a"),
+            (TokenKind::Whitespace, " \\\n "),
+            (TokenKind::Identifier, "b"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::UnTypedAssignment, ": \\\n ="),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Identifier, "c"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_backslash_without_newline() {
+        let mut lexer = Lexer::lex_from_string("a \\ b");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::InvalidCharacter('\\'));
+    }
+
+    #[test]
+    fn test_keep_trivia_around_assignments() {
+        let options = LexerOptions::new().keep_trivia(true);
+        let mut lexer = Lexer::lex_from_string("a : = b : u32").with_options(options);
         let tokens = lexer.lex().unwrap();
 
+        // The trivia inside of an untyped assignment is part of its literal
         let expected = vec![
-            Token::new(TokenKind::Function, "func".to_string()),
-            Token::new(TokenKind::Identifier, "main".to_string()),
-            Token::new(TokenKind::OpenParen, "(".to_string()),
-            Token::new(TokenKind::CloseParen, ")".to_string()),
-            Token::new(TokenKind::TypeAssignment, ":".to_string()),
-            Token::new(TokenKind::Identifier, "u32".to_string()),
-            Token::new(TokenKind::LetAssignment, "=".to_string()),
-            Token::new(TokenKind::OpenBrace, "{".to_string()),
-            Token::new(TokenKind::Return, "return".to_string()),
-            Token::new(TokenKind::Number(5), "5".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
-            Token::new(TokenKind::CloseBrace, "}".to_string()),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::UnTypedAssignment, ": ="),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Identifier, "b"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Identifier, "u32"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_max_length_options() {
+        let options = LexerOptions::new().max_identifier_length(3);
+        let mut lexer = Lexer::lex_from_string("abc abcd").with_options(options);
+
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::IdentifierTooLong(3));
+        assert_eq!(error.span(), Span::new(4, 8));
+
+        let options = LexerOptions::new().max_string_length(5);
+        let mut lexer = Lexer::lex_from_string("'hello' 'hello!'").with_options(options);
+
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::StringTooLong(5));
+        assert_eq!(error.span(), Span::new(8, 16));
+    }
+
+    #[test]
+    fn test_tab_width_option() {
+        let options = LexerOptions::new().tab_width(4);
+        let mut lexer = Lexer::lex_from_string("\t\t$").with_options(options);
+
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::InvalidCharacter('$'));
+        assert_eq!(error.location.column, 9);
+    }
+
+    #[test]
+    fn test_token_spec() {
+        let spec = penguin::lexer::token_spec();
+
+        assert!(spec.contains(r#"{"kind": "Assign", "literal": "let"}"#));
+        assert!(spec.contains(r#"{"kind": "ShortIncrement", "literal": "+="}"#));
+        assert!(spec.contains(r#"{"kind": "Identifier", "pattern": "#));
+        assert!(spec.contains(r#""escapes": ["\\n", "#));
+    }
+
+    #[test]
+    fn test_error_accessors() {
+        let mut lexer = Lexer::lex_from_string("let a := 'ab\\q';");
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(error.kind, LexerErrorKind::InvalidEscapeSequence('q'));
+        assert_eq!(error.code(), "E0003");
+        assert_eq!(error.span(), Span::new(12, 14));
+        assert_eq!(error.message(), "Invalid escape sequence 'q'");
+        assert_eq!(
+            error.to_string(),
+            "[string:1:14] Invalid escape sequence 'q'."
+        );
+    }
+
+    #[test]
+    fn test_error_outlives_lexer() {
+        let error = {
+            let source = String::from("\"unclosed");
+            let mut lexer = Lexer::lex_from_string(&source);
+
+            lexer.lex().unwrap_err()
+        };
+
+        let error: Box<dyn std::error::Error> = Box::new(error);
+
+        assert_eq!(error.to_string(), "[string:1:9] Unexpected end of file.");
+    }
+
+    #[test]
+    fn test_attribute() {
+        let mut lexer = Lexer::lex_from_string("@inline func foo() := {}");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Attribute, "@inline"),
+            (TokenKind::Function, "func"),
+            (TokenKind::Identifier, "foo"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::UnTypedAssignment, ":="),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::CloseBrace, "}"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_attribute_without_name() {
+        let mut lexer = Lexer::lex_from_string("@ func");
+
+        // We expect an error on lexing
+        assert!(lexer.lex().is_err());
+
+        let mut lexer = Lexer::lex_from_string("@1");
+
+        assert!(lexer.lex().is_err());
     }
 
     #[test]
     fn test_importing() {
-        let mut lexer = Lexer::lex_from_string("import \"test\";".to_string());
+        let mut lexer = Lexer::lex_from_string("import \"test\";");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Import, "import"),
+            (TokenKind::String("test".to_string()), "\"test\""),
+            (TokenKind::Semicolon, ";"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_if_else() {
+        let mut lexer = Lexer::lex_from_string("if a { } else { }");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::If, "if"),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::CloseBrace, "}"),
+            (TokenKind::Else, "else"),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::CloseBrace, "}"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_loop_keywords() {
+        let mut lexer = Lexer::lex_from_string("while for x in xs loop { break; continue; }");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::While, "while"),
+            (TokenKind::For, "for"),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::In, "in"),
+            (TokenKind::Identifier, "xs"),
+            (TokenKind::Loop, "loop"),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::Break, "break"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::Continue, "continue"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::CloseBrace, "}"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_type_declaration_keywords() {
+        let mut lexer = Lexer::lex_from_string("struct enum impl trait");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Struct, "struct"),
+            (TokenKind::Enum, "enum"),
+            (TokenKind::Impl, "impl"),
+            (TokenKind::Trait, "trait"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_type_declaration_keywords_as_prefixes() {
+        let mut lexer = Lexer::lex_from_string("structure enumerate implement struct_");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "structure"),
+            (TokenKind::Identifier, "enumerate"),
+            (TokenKind::Identifier, "implement"),
+            (TokenKind::Identifier, "struct_"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_as_cast() {
+        let mut lexer = Lexer::lex_from_string("x as u64 ascii");
         let tokens = lexer.lex().unwrap();
 
         let expected = vec![
-            Token::new(TokenKind::Import, "import".to_string()),
-            Token::new(TokenKind::String, "test".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::As, "as"),
+            (TokenKind::Identifier, "u64"),
+            (TokenKind::Identifier, "ascii"),
         ];
 
-        assert_eq!(tokens, expected);
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_keyword_prefixed_identifiers() {
+        let mut lexer = Lexer::lex_from_string("iffy elsewhere index breaking");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "iffy"),
+            (TokenKind::Identifier, "elsewhere"),
+            (TokenKind::Identifier, "index"),
+            (TokenKind::Identifier, "breaking"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 
     #[test]
     fn test_functions_with_multiple_parameters() {
-        let mut lexer =
-            Lexer::lex_from_string("func main(a: u32, b: u32) : u32 = { return 5; }".to_string());
-        let tokens = lexer.lex().unwrap();
-
-        let expected = vec![
-            Token::new(TokenKind::Function, "func".to_string()),
-            Token::new(TokenKind::Identifier, "main".to_string()),
-            Token::new(TokenKind::OpenParen, "(".to_string()),
-            Token::new(TokenKind::Identifier, "a".to_string()),
-            Token::new(TokenKind::TypeAssignment, ":".to_string()),
-            Token::new(TokenKind::Identifier, "u32".to_string()),
-            Token::new(TokenKind::Comma, ",".to_string()),
-            Token::new(TokenKind::Identifier, "b".to_string()),
-            Token::new(TokenKind::TypeAssignment, ":".to_string()),
-            Token::new(TokenKind::Identifier, "u32".to_string()),
-            Token::new(TokenKind::CloseParen, ")".to_string()),
-            Token::new(TokenKind::TypeAssignment, ":".to_string()),
-            Token::new(TokenKind::Identifier, "u32".to_string()),
-            Token::new(TokenKind::LetAssignment, "=".to_string()),
-            Token::new(TokenKind::OpenBrace, "{".to_string()),
-            Token::new(TokenKind::Return, "return".to_string()),
-            Token::new(TokenKind::Number(5), "5".to_string()),
-            Token::new(TokenKind::Semicolon, ";".to_string()),
-            Token::new(TokenKind::CloseBrace, "}".to_string()),
-        ];
-
-        assert_eq!(tokens, expected);
+        let mut lexer = Lexer::lex_from_string("func main(a: u32, b: u32) : u32 = { return 5; }");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Function, "func"),
+            (TokenKind::Identifier, "main"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::Comma, ","),
+            (TokenKind::Identifier, "b"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::LetAssignment, "="),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::Return, "return"),
+            (TokenKind::Number(5), "5"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::CloseBrace, "}"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_defer_keyword() {
+        let mut lexer = Lexer::lex_from_string("defer { close(f); } deferred");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Defer, "defer"),
+            (TokenKind::OpenBrace, "{"),
+            (TokenKind::Identifier, "close"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::Identifier, "f"),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::CloseBrace, "}"),
+            (TokenKind::Identifier, "deferred"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_assert_keywords() {
+        let mut lexer = Lexer::lex_from_string("assert static_assert asserts");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Assert, "assert"),
+            (TokenKind::StaticAssert, "static_assert"),
+            (TokenKind::Identifier, "asserts"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_field_access() {
+        let mut lexer = Lexer::lex_from_string("p.x.y");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "p"),
+            (TokenKind::Dot, "."),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::Dot, "."),
+            (TokenKind::Identifier, "y"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_path_separator() {
+        let mut lexer = Lexer::lex_from_string("Color::Red a : u32 b := c");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Identifier, "Color"),
+            (TokenKind::PathSeparator, "::"),
+            (TokenKind::Identifier, "Red"),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::TypeAssignment, ":"),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::Identifier, "b"),
+            (TokenKind::UnTypedAssignment, ":="),
+            (TokenKind::Identifier, "c"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_fat_arrow() {
+        let mut lexer = Lexer::lex_from_string("func(x) => x == 1");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Function, "func"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::FatArrow, "=>"),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::Equal, "=="),
+            (TokenKind::Number(1), "1"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_type_symbols() {
+        let mut lexer = Lexer::lex_from_string("func(u32?) -> a-->b");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Function, "func"),
+            (TokenKind::OpenParen, "("),
+            (TokenKind::Identifier, "u32"),
+            (TokenKind::Question, "?"),
+            (TokenKind::CloseParen, ")"),
+            (TokenKind::Arrow, "->"),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::Decrement, "--"),
+            (TokenKind::GreaterThan, ">"),
+            (TokenKind::Identifier, "b"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_const_keyword() {
+        let mut lexer = Lexer::lex_from_string("const MAX := 1; constant");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Const, "const"),
+            (TokenKind::Identifier, "MAX"),
+            (TokenKind::UnTypedAssignment, ":="),
+            (TokenKind::Number(1), "1"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::Identifier, "constant"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
+    }
+
+    #[test]
+    fn test_pub_keyword() {
+        let mut lexer = Lexer::lex_from_string("pub func public");
+        let tokens = lexer.lex().unwrap();
+
+        let expected = vec![
+            (TokenKind::Pub, "pub"),
+            (TokenKind::Function, "func"),
+            (TokenKind::Identifier, "public"),
+        ];
+
+        assert_eq!(kinds_and_literals(&tokens), expected);
     }
 }
@@ -0,0 +1,438 @@
+use penguin::lexer::{Lexer, Span};
+use penguin::lint::{self, Case, Level, Levels, Lint, Warning, WarningKind};
+use penguin::parser::Parser;
+use penguin::resolve::Resolver;
+use penguin::typeck::{IntTy, TypeChecker};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Vec<Warning> {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+        let resolution = Resolver::resolve(&parsed).unwrap();
+
+        lint::check(&parsed, &resolution, None)
+    }
+
+    /// What the lints found in a source that type checks, with its types.
+    fn check_typed(source: &str) -> Vec<Warning> {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+        let resolution = Resolver::resolve(&parsed).unwrap();
+        let types = TypeChecker::check(&parsed, &resolution).unwrap();
+
+        lint::check(&parsed, &resolution, Some(&types))
+    }
+
+    /// Split what the lints found in a source into warnings and errors, with
+    /// the levels the attributes in it give.
+    fn split(source: &str, mut levels: Levels) -> (Vec<Warning>, Vec<Warning>) {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+        let resolution = Resolver::resolve(&parsed).unwrap();
+
+        levels.set_from_attributes(&parsed);
+        levels.split(lint::check(&parsed, &resolution, None))
+    }
+
+    /// The names the naming lint found in a source, with what they name and
+    /// the name it suggests.
+    fn misnamed(source: &str) -> Vec<(String, String, String)> {
+        check(source)
+            .into_iter()
+            .filter_map(|warning| match warning.kind {
+                WarningKind::NamingConvention {
+                    name,
+                    what,
+                    suggested,
+                    ..
+                } => Some((what, name, suggested)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn kinds(source: &str) -> Vec<WarningKind> {
+        check(source)
+            .into_iter()
+            .map(|warning| warning.kind)
+            .collect()
+    }
+
+    fn variable(name: &str) -> WarningKind {
+        WarningKind::UnusedVariable {
+            name: name.to_string(),
+        }
+    }
+
+    fn parameter(name: &str) -> WarningKind {
+        WarningKind::UnusedParameter {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_unused_variables() {
+        let source = "func f(a : u32, b : u32) : u32 = { let c := a; let d := 1; \
+                      if let (e, g) = (1, 2) { e } else { 0 } };";
+
+        assert_eq!(
+            kinds(source),
+            [parameter("b"), variable("c"), variable("d"), variable("g")]
+        );
+
+        let warnings = check(source);
+
+        // Variables and parameters point at their names
+        assert_eq!(warnings[0].span(), Span::new(16, 17));
+        assert_eq!(warnings[0].code(), "W0502");
+        assert_eq!(warnings[1].code(), "W0501");
+        assert_eq!(
+            warnings[0].to_string(),
+            "The parameter 'b' is never read. Rename it to '_b' if it is meant to be unused."
+        );
+        assert_eq!(
+            warnings[0].suggestion().unwrap().apply(source),
+            Some(source.replacen("b :", "_b :", 1))
+        );
+
+        // Closure parameters count too, and items are never warned about
+        assert_eq!(
+            kinds("let g := 1; func f() := { let h := func(x, y) => x; h(1, 2); };"),
+            [parameter("y")]
+        );
+    }
+
+    #[test]
+    fn test_methods_need_not_use_self() {
+        assert!(check(
+            "trait Zero { func zero(self) : u32; } \
+             impl Zero for u32 { func zero(self) : u32 = { 0 }; };"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_underscore_names_are_unused() {
+        assert!(
+            check("func f(_a : u32) := { let _b := 1; if let (_c, _) = (1, 2) {} };").is_empty()
+        );
+    }
+
+    #[test]
+    fn test_assignments_are_not_reads() {
+        // A compound assignment reads the variable it assigns to
+        assert_eq!(
+            kinds("func f() := { let mut a := 1; a = 2; let mut b := 1; b += 1; };"),
+            [variable("a")]
+        );
+        assert!(check("func f() : u32 = { let mut a := 1; a = 2; a };").is_empty());
+    }
+
+    #[test]
+    fn test_unused_imports() {
+        let source = "import \"std.print\"; import math::{sin, cos}; func f() := { sin(1); };";
+        let warnings = check(source);
+
+        assert_eq!(
+            warnings
+                .iter()
+                .map(|warning| warning.kind.clone())
+                .collect::<Vec<_>>(),
+            [
+                WarningKind::UnusedImport {
+                    name: "print".to_string()
+                },
+                WarningKind::UnusedImport {
+                    name: "cos".to_string()
+                },
+            ]
+        );
+        assert_eq!(warnings[1].lint(), Lint::UnusedImports);
+        assert_eq!(warnings[1].code(), "W0503");
+        assert_eq!(warnings[1].span(), Span::new(20, 44));
+        assert_eq!(warnings[1].suggestion(), None);
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(Lint::from_name("unused_imports"), Some(Lint::UnusedImports));
+        assert_eq!(Lint::from_name("warnings"), None);
+
+        let source = "import \"std.print\"; func f(a : u32) := {};";
+
+        // Lints warn by default
+        let (warnings, errors) = Levels::new().split(check(source));
+
+        assert_eq!(warnings.len(), 2);
+        assert!(errors.is_empty());
+
+        let mut levels = Levels::new();
+
+        levels.set_warnings(Level::Allow);
+        levels.set(Lint::UnusedVariables, Level::Deny);
+
+        let (warnings, errors) = levels.split(check(source));
+
+        assert!(warnings.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, parameter("a"));
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let source = "func f(a : u32) : u32 = { let a := a + 1; \
+                      { let a := a * 2; let f := a; f } };";
+        let shadowed: Vec<_> = check(source)
+            .into_iter()
+            .filter(|warning| warning.lint() == Lint::Shadowing)
+            .collect();
+
+        let kinds: Vec<_> = shadowed
+            .iter()
+            .map(|warning| match &warning.kind {
+                WarningKind::Shadowed { name, what } => (name.as_str(), what.as_str()),
+                kind => panic!("Expected shadowing, found {:?}", kind),
+            })
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [("a", "parameter"), ("a", "variable"), ("f", "function")]
+        );
+
+        // Both names are pointed at
+        let inner = source.find("let a := a *").unwrap() + 4;
+
+        assert_eq!(shadowed[1].span(), Span::new(inner, inner + 1));
+        assert_eq!(shadowed[1].declared(), Some(Span::new(30, 31)));
+        assert_eq!(shadowed[1].code(), "W0504");
+        assert_eq!(
+            shadowed[1].to_string(),
+            "'a' shadows the variable with the same name."
+        );
+
+        // A variable declared after a block isn't shadowed by one in it
+        let source = "func f() := { { let a := 1; a; } let a := 2; a; };";
+
+        assert!(check(source).is_empty());
+    }
+
+    #[test]
+    fn test_narrowing() {
+        let source = "func f(c : u32) : u8 = { let a := 300; let b : u8 = a as u8; \
+                      let d : i64 = a as i64; let e : u8 = c as u8; \
+                      let g := -1 as i32; let h : u32 = g as u32; b + d as u8 + e + h as u8 };";
+        let narrowed: Vec<_> = check_typed(source)
+            .into_iter()
+            .filter(|warning| warning.lint() == Lint::Narrowing)
+            .collect();
+
+        // Only variables declared with `:=` are narrowed, to types that can't
+        // hold all of their values
+        assert_eq!(
+            narrowed
+                .iter()
+                .map(|warning| warning.kind.clone())
+                .collect::<Vec<_>>(),
+            [
+                WarningKind::Narrowed {
+                    name: "a".to_string(),
+                    from: IntTy::U32,
+                    to: IntTy::U8
+                },
+                WarningKind::Narrowed {
+                    name: "g".to_string(),
+                    from: IntTy::I32,
+                    to: IntTy::U32
+                },
+            ]
+        );
+
+        // Both the cast and the variable are pointed at
+        let cast = source.find("a as u8").unwrap();
+        let declared = source.find("a := 300").unwrap();
+
+        assert_eq!(narrowed[0].span(), Span::new(cast, cast + 7));
+        assert_eq!(
+            narrowed[0].declared(),
+            Some(Span::new(declared, declared + 1))
+        );
+        assert_eq!(narrowed[0].code(), "W0508");
+        assert_eq!(
+            narrowed[0].to_string(),
+            "'a' is inferred as u32, which is narrowed to u8 here."
+        );
+        assert_eq!(Lint::Narrowing.default_level(), Level::Warn);
+
+        // It needs types
+        assert!(check(source)
+            .iter()
+            .all(|warning| warning.lint() != Lint::Narrowing));
+    }
+
+    #[test]
+    fn test_import_cycles() {
+        assert_eq!(Lint::from_name("import_cycles"), Some(Lint::ImportCycles));
+        assert_eq!(Lint::ImportCycles.default_level(), Level::Warn);
+
+        let cycle = ["a.pg", "b.pg", "a.pg"].map(str::to_string).to_vec();
+        let warning = Warning::new(WarningKind::ImportCycle { cycle }, Span::new(0, 9));
+
+        assert_eq!(warning.lint(), Lint::ImportCycles);
+        assert_eq!(warning.code(), "W0505");
+        assert_eq!(
+            warning.to_string(),
+            "The modules import each other: a.pg -> b.pg -> a.pg."
+        );
+    }
+
+    #[test]
+    fn test_shadowing_is_allowed_by_default() {
+        let source = "func f(a : u32) : u32 = { let a := a + 1; a };";
+
+        assert_eq!(Lint::Shadowing.default_level(), Level::Allow);
+        assert_eq!(Levels::new().split(check(source)), (Vec::new(), Vec::new()));
+
+        // `warnings` only stands for the lints that warn by default
+        let mut levels = Levels::new();
+
+        levels.set_warnings(Level::Deny);
+
+        assert!(levels.split(check(source)).1.is_empty());
+
+        levels.set(Lint::Shadowing, Level::Deny);
+
+        assert_eq!(levels.split(check(source)).1.len(), 1);
+    }
+
+    #[test]
+    fn test_every_lint_has_a_name() {
+        for &lint in Lint::ALL {
+            assert_eq!(Lint::from_name(lint.name()), Some(lint));
+        }
+
+        // Import cycles are found while loading modules
+        assert!(Lint::ImportCycles.pass().is_none());
+        assert!(Lint::NamingConventions.pass().is_some());
+    }
+
+    #[test]
+    fn test_naming_conventions() {
+        let source = "const maxSize : u32 = 1; struct point_2d<t> { X : u32 } \
+                      enum color { dark_red } trait shape { func Area(self) : u32; } \
+                      func doThing(someArg : u32) : u32 = { let HTTPServer := func(inX) => inX; \
+                      HTTPServer(someArg) };";
+
+        let names = [
+            ("constant", "maxSize", "MAX_SIZE"),
+            ("struct", "point_2d", "Point2d"),
+            ("type parameter", "t", "T"),
+            ("field", "X", "x"),
+            ("enum", "color", "Color"),
+            ("variant", "dark_red", "DarkRed"),
+            ("trait", "shape", "Shape"),
+            ("method", "Area", "area"),
+            ("function", "doThing", "do_thing"),
+            ("parameter", "someArg", "some_arg"),
+            ("variable", "HTTPServer", "http_server"),
+            ("parameter", "inX", "in_x"),
+        ]
+        .map(|(what, name, suggested)| (what.to_string(), name.to_string(), suggested.to_string()));
+
+        assert_eq!(misnamed(source), names);
+
+        // Names without a span of their own point at what declares them
+        let warnings = check("let a := 1; struct P { Xy : u32 }");
+        let warning = &warnings[0];
+
+        assert_eq!(warning.span(), Span::new(12, 33));
+        assert_eq!(warning.code(), "W0506");
+        assert_eq!(
+            warning.to_string(),
+            "The field 'Xy' should be named in snake_case, like 'xy'."
+        );
+
+        // Leading underscores are kept, and patterns are left out, since
+        // they can be variants
+        let source = "struct P { x : u32 } trait Shape { func area(self) : u32; } \
+                      impl Shape for P { func area(self) : u32 = { 1 }; } \
+                      enum Opt { Empty, Full(u32) } \
+                      func f(_unused : u32, o : Opt) := { if let Opt::Full(Inner) = o { Inner; } };";
+
+        assert!(misnamed(source).is_empty());
+    }
+
+    #[test]
+    fn test_cases() {
+        assert_eq!(
+            Case::Snake.convert("parseHTTPRequest2D"),
+            "parse_http_request2_d"
+        );
+        assert_eq!(Case::UpperCamel.convert("__my_type"), "__MyType");
+        assert_eq!(Case::ScreamingSnake.convert("maxValue"), "MAX_VALUE");
+
+        assert!(Case::Snake.matches("_a_b2"));
+        assert!(!Case::Snake.matches("a__b"));
+        assert!(Case::UpperCamel.matches("HTTPServer"));
+        assert!(!Case::UpperCamel.matches("Http_Server"));
+        assert!(Case::ScreamingSnake.matches("MAX_2"));
+    }
+
+    #[test]
+    fn test_attributes_set_levels_inside_items() {
+        let source = "@allow(unused_variables) func f(a : u32) := {}; \
+                      func g(b : u32) := {}; \
+                      @deny(warnings) func h(c : u32) := {};";
+
+        let (warnings, errors) = split(source, Levels::new());
+
+        assert_eq!(
+            warnings.into_iter().map(|w| w.kind).collect::<Vec<_>>(),
+            [parameter("b")]
+        );
+        assert_eq!(
+            errors.into_iter().map(|w| w.kind).collect::<Vec<_>>(),
+            [parameter("c")]
+        );
+
+        // Attributes override the command line, and later attributes
+        // override earlier ones
+        let mut levels = Levels::new();
+
+        levels.set(Lint::UnusedVariables, Level::Deny);
+
+        let source = "@warn(unused_variables) @allow(unused_variables) func f(a : u32) := {};";
+
+        assert_eq!(split(source, levels), (Vec::new(), Vec::new()));
+
+        let source = "@warn(shadowing) func f(a : u32) := { let a := a; a; };";
+
+        assert_eq!(split(source, Levels::new()).0.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_lints() {
+        let source = "@allow(unused_variable, warnings, nothing_like_it) func f() := {};";
+        let warnings = check(source);
+
+        assert_eq!(
+            warnings.iter().map(|w| w.kind.clone()).collect::<Vec<_>>(),
+            ["unused_variable", "nothing_like_it"].map(|name| WarningKind::UnknownLint {
+                name: name.to_string()
+            })
+        );
+        assert_eq!(warnings[0].code(), "W0507");
+        assert_eq!(
+            warnings[0].to_string(),
+            "There is no lint 'unused_variable'. Replace it with 'unused_variables'."
+        );
+        assert_eq!(
+            warnings[0].suggestion().unwrap().apply(source).unwrap(),
+            source.replace("unused_variable,", "unused_variables,")
+        );
+        assert_eq!(warnings[1].suggestion(), None);
+    }
+}
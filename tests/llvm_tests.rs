@@ -0,0 +1,147 @@
+#![cfg(feature = "llvm")]
+use penguin::backend::{CodegenErrorKind, Target};
+use penguin::llvm;
+
+mod common;
+
+use common::lower;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ir(source: &str) -> String {
+        llvm::compile(&lower(source), None).unwrap()
+    }
+
+    /// Compile a source to an executable and run it, returning what it
+    /// printed and its exit code, or `None` if there is no LLVM or C
+    /// compiler to build it with.
+    fn run(name: &str, source: &str) -> Option<(String, i32)> {
+        common::run("llvm", name, &[llvm::LLC], |object| {
+            llvm::write_object(&ir(source), Target::host(), object).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_short_circuiting() {
+        let Some((output, _)) = run(
+            "short_circuit",
+            "import \"std.print\";\n\
+             func loud(value : bool) : bool = { print(value); value };\n\
+             func main() := { print(loud(false) && loud(true)); print(loud(true) || loud(false)); };",
+        ) else {
+            return;
+        };
+
+        // The right operand is only run when it decides the result
+        assert_eq!(output, "false\nfalse\ntrue\ntrue\n");
+    }
+
+    #[test]
+    fn test_functions() {
+        let ir = ir("func double(a : u32) : u32 = { a * 2 };");
+
+        assert!(ir.contains("define i32 @\"pg.double\"(i32 %arg0) {"));
+        assert!(ir.contains("call { i32, i1 } @llvm.umul.with.overflow.i32(i32 %t"));
+        assert!(ir.contains("declare { i32, i1 } @llvm.umul.with.overflow.i32(i32, i32)"));
+        // Libraries have no entry point
+        assert!(!ir.contains("@main()"));
+    }
+
+    #[test]
+    fn test_entry_point_sets_globals() {
+        let ir = ir("let start : u8 = 3; func main() : u8 = { start };");
+
+        assert!(ir.contains("@\"pg.global.start\" = internal global i8 zeroinitializer"));
+        assert!(ir.contains("define i32 @main() {"));
+        assert!(ir.contains("call i8 @\"pg.init.start\"()"));
+        assert!(ir.contains("call i8 @\"pg.main\"()"));
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let ir = ir(
+            "struct Point { x : i64, y : i64 }\n\
+             enum Shape { Dot, Circle(u32) }\n\
+             func f() : (Point, Shape, [bool; 2]) = { (Point { y: 1, x: 2 }, Shape::Circle(3), [true, false]) };",
+        );
+
+        assert!(
+            ir.contains("define { { i64, i64 }, { i32, {}, { i32 } }, [2 x i1] } @\"pg.f\"() {")
+        );
+        assert!(ir.contains("insertvalue { i64, i64 } zeroinitializer, i64 2, 0"));
+    }
+
+    #[test]
+    fn test_strings() {
+        let ir = ir("func f(a : str) : str = { \"a\\\"b\" + a };");
+
+        assert!(ir.contains("@str.0 = private unnamed_addr constant [3 x i8] c\"a\\22b\""));
+        assert!(ir.contains("call ptr @malloc(i64 %t"));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let errors = llvm::compile(
+            &lower("func f() : u32 = { let g := func(a : u32) => a; g(1) };"),
+            None,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors.iter().map(|error| &error.kind).collect::<Vec<_>>(),
+            [&CodegenErrorKind::Unsupported {
+                backend: "LLVM",
+                what: "closures".to_string()
+            }]
+        );
+        assert_eq!(errors[0].code(), "E0801");
+    }
+
+    #[test]
+    fn test_llc_version() {
+        let text = "LLVM (http://llvm.org/):\n  LLVM version 14.0.6\n  Optimized build.";
+
+        assert_eq!(llvm::llc_version(text), Some(14));
+        assert_eq!(llvm::llc_version("Homebrew LLVM version 18.1.8"), Some(18));
+        assert_eq!(llvm::llc_version("llc"), None);
+    }
+
+    #[test]
+    fn test_running_native_programs() {
+        let Some((output, code)) = run(
+            "program",
+            "import \"std.print\";\n\
+             enum Shape { Dot, Rect(i64, i64) }\n\
+             func area(s : Shape) : i64 = { match s { Shape::Rect(w, h) => w * h, Shape::Dot => 0 } };\n\
+             func fib(n : u32) : u32 = { if let true = n < 2 { return n; } fib(n - 1) + fib(n - 2) };\n\
+             func main() : u32 = {\n\
+                 let mut xs := [1, 2, 3];\n\
+                 xs[1] = 7;\n\
+                 let mut i := 0;\n\
+                 while i < 3 { defer { i += 1; } print(xs[i]); }\n\
+                 print(area(Shape::Rect(2, 3)), Shape::Dot, \"done\");\n\
+                 fib(10)\n\
+             };",
+        ) else {
+            return;
+        };
+
+        assert_eq!(output, "1\n7\n3\n6 Shape::Dot done\n");
+        assert_eq!(code, 55);
+    }
+
+    #[test]
+    fn test_native_runtime_errors() {
+        let Some((output, code)) = run(
+            "overflow",
+            "func add(a : u8, b : u8) : u8 = { a + b }; func main() := { add(200, 100); };",
+        ) else {
+            return;
+        };
+
+        assert_eq!(output, "[RUNTIME ERROR]: The result doesn't fit in u8.\n");
+        assert_eq!(code, 1);
+    }
+}
@@ -0,0 +1,474 @@
+use penguin::lexer::LexerOptions;
+use penguin::lint::WarningKind;
+use penguin::module::{self, LoadError, LoadResult, ModuleErrorKind};
+use penguin::parser::ast::ItemKind;
+use penguin::parser::{ParseResult, Parser};
+use penguin::resolve::{ResolveErrorKind, Resolver};
+use penguin::source_map::SourceMap;
+use penguin::typeck::TypeChecker;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write the files of a project to a directory of its own, returning
+    /// the path of the first one.
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("penguin-{}-{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&directory);
+
+        for (path, contents) in files {
+            let path = directory.join(path);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        directory.join(files[0].0)
+    }
+
+    /// Parse the file at `path`, and load the modules it imports.
+    fn load(path: &Path, sources: &mut SourceMap) -> Result<LoadResult, Vec<LoadError>> {
+        load_with(path, &[], sources)
+    }
+
+    /// Parse the file at `path`, and load the modules it imports, looking
+    /// in the `include` directories too.
+    fn load_with(
+        path: &Path,
+        include: &[PathBuf],
+        sources: &mut SourceMap,
+    ) -> Result<LoadResult, Vec<LoadError>> {
+        let id = sources.load(path).unwrap();
+        let tokens = sources.get(id).unwrap().lexer().lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+
+        module::load(parsed, path, include, sources, &LexerOptions::new())
+    }
+
+    /// The names of the items of a program, in order.
+    fn names(parsed: &ParseResult) -> Vec<String> {
+        parsed
+            .program
+            .items
+            .iter()
+            .filter_map(|item| match &item.kind {
+                ItemKind::Function(function) => Some(function.name.clone()),
+                ItemKind::Const(decl) | ItemKind::Global(decl) => Some(decl.name.clone()),
+                ItemKind::Struct(structure) => Some(structure.name.clone()),
+                ItemKind::Enum(enumeration) => Some(enumeration.name.clone()),
+                ItemKind::Trait(declaration) => Some(declaration.name.clone()),
+                ItemKind::Import(import) => Some(format!("import {:?}", import.path)),
+                ItemKind::Impl(_) | ItemKind::StaticAssert(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_items_get_qualified_names() {
+        let path = project(
+            "qualified",
+            &[
+                (
+                    "main.pg",
+                    "import utils;
+                     import shapes;
+                     import shapes::{Point, origin};
+
+                     func main() : i32 = {
+                         let p : Point = origin();
+                         let q := shapes::Point { x: utils::double(p.x), y: utils::ZERO };
+                         let c := utils::Color::Red;
+                         return q.x;
+                     };",
+                ),
+                (
+                    "utils.pg",
+                    "pub enum Color { Red, Green }
+                     pub const ZERO : i32 = 0;
+                     pub func double(x : i32) : i32 = { helper(x) + helper(x) };
+                     func helper(x : i32) : i32 = { x };",
+                ),
+                (
+                    "shapes/mod.pg",
+                    "pub struct Point { x : i32, y : i32 }
+                     pub func origin() : Point = { Point { x: 0, y: 0 } };",
+                ),
+            ],
+        );
+
+        let mut sources = SourceMap::new();
+        let parsed = load(&path, &mut sources).unwrap().parsed;
+
+        assert_eq!(sources.len(), 3);
+        assert_eq!(
+            names(&parsed),
+            vec![
+                "main",
+                "utils::Color",
+                "utils::ZERO",
+                "utils::double",
+                "utils::helper",
+                "shapes::Point",
+                "shapes::origin",
+            ]
+        );
+
+        // The merged program is checked as if it was one file
+        let resolution = Resolver::resolve(&parsed).unwrap();
+
+        TypeChecker::check(&parsed, &resolution).unwrap();
+    }
+
+    #[test]
+    fn test_modules_are_loaded_once() {
+        let path = project(
+            "once",
+            &[
+                (
+                    "main.pg",
+                    "import a;
+                     import b;
+                     import \"std.print\";
+                     func main() : i32 = { a::f() + b::g() };",
+                ),
+                (
+                    "a.pg",
+                    "import common;
+                     import \"std.print\";
+                     pub func f() : i32 = { common::one() };",
+                ),
+                (
+                    "b.pg",
+                    "import common::{one};
+                     pub func g() : i32 = { one() };",
+                ),
+                ("common.pg", "pub func one() : i32 = { 1 };"),
+            ],
+        );
+
+        let mut sources = SourceMap::new();
+        let parsed = load(&path, &mut sources).unwrap().parsed;
+
+        // The standard library is only imported once too
+        assert_eq!(
+            names(&parsed),
+            vec![
+                "import File(\"std.print\")",
+                "main",
+                "a::f",
+                "b::g",
+                "common::one"
+            ]
+        );
+        assert!(Resolver::resolve(&parsed).is_ok());
+    }
+
+    #[test]
+    fn test_locals_shadow_items_of_modules() {
+        let path = project(
+            "shadow",
+            &[
+                ("main.pg", "import utils;"),
+                (
+                    "utils.pg",
+                    "pub func size() : i32 = { 1 };
+                     pub func twice(size : i32) : i32 = { size + size };",
+                ),
+            ],
+        );
+
+        let mut sources = SourceMap::new();
+        let parsed = load(&path, &mut sources).unwrap().parsed;
+
+        assert_eq!(names(&parsed), vec!["utils::size", "utils::twice"]);
+        assert!(Resolver::resolve(&parsed).is_ok());
+    }
+
+    #[test]
+    fn test_import_cycles() {
+        let path = project(
+            "cycles",
+            &[
+                ("main.pg", "import a;\nfunc main() : i32 = { a::f() };"),
+                (
+                    "a.pg",
+                    "import b;
+                     pub func f() : i32 = { b::g() };
+                     pub func h() : i32 = { 0 };",
+                ),
+                (
+                    "b.pg",
+                    "import a::{h};
+                     import main;
+                     pub func g() : i32 = { h() };",
+                ),
+            ],
+        );
+
+        let mut sources = SourceMap::new();
+        let loaded = load(&path, &mut sources).unwrap();
+
+        let cycles: Vec<_> = loaded
+            .warnings
+            .iter()
+            .map(|warning| warning.kind.clone())
+            .collect();
+
+        assert_eq!(
+            cycles,
+            vec![
+                WarningKind::ImportCycle {
+                    cycle: vec!["a.pg".to_string(), "b.pg".to_string(), "a.pg".to_string()],
+                },
+                WarningKind::ImportCycle {
+                    cycle: vec![
+                        "main.pg".to_string(),
+                        "a.pg".to_string(),
+                        "b.pg".to_string(),
+                        "main.pg".to_string(),
+                    ],
+                },
+            ]
+        );
+
+        // Each is reported at the import closing the cycle
+        let (file, span) = sources.locate(loaded.warnings[0].span()).unwrap();
+
+        assert_eq!(file.name(), "b.pg");
+        assert_eq!(file.slice(span), Some("import a::{h};"));
+
+        // The modules can still use each other's items
+        assert_eq!(names(&loaded.parsed), vec!["main", "a::f", "a::h", "b::g"]);
+        assert!(Resolver::resolve(&loaded.parsed).is_ok());
+    }
+
+    #[test]
+    fn test_modules_importing_themselves() {
+        let path = project("itself", &[("app.pg", "import app;\nfunc main() := {};")]);
+
+        let mut sources = SourceMap::new();
+        let loaded = load(&path, &mut sources).unwrap();
+
+        assert_eq!(
+            loaded.warnings[0].kind,
+            WarningKind::ImportCycle {
+                cycle: vec!["app.pg".to_string(), "app.pg".to_string()],
+            }
+        );
+        assert_eq!(names(&loaded.parsed), vec!["main"]);
+    }
+
+    #[test]
+    fn test_programs_without_modules_are_unchanged() {
+        let path = project(
+            "unchanged",
+            &[(
+                "main.pg",
+                "import \"std.print\"; import std::io; func main() : i32 = { 0 };",
+            )],
+        );
+
+        let mut sources = SourceMap::new();
+        let parsed = load(&path, &mut sources).unwrap().parsed;
+
+        assert_eq!(sources.len(), 1);
+        assert_eq!(
+            names(&parsed),
+            vec![
+                "import File(\"std.print\")",
+                "import Module([\"std\", \"io\"])",
+                "main"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_private_items() {
+        let path = project(
+            "private",
+            &[
+                (
+                    "main.pg",
+                    "import utils;
+                     import utils::{hidden};
+                     func main() : i32 = { utils::helper(1) };",
+                ),
+                (
+                    "utils.pg",
+                    "func helper(x : i32) : i32 = { x };
+                     const hidden : i32 = 0;",
+                ),
+            ],
+        );
+
+        let mut sources = SourceMap::new();
+        let errors = load(&path, &mut sources).unwrap_err();
+
+        let names: Vec<_> = errors
+            .iter()
+            .map(|error| match error {
+                LoadError::Resolve(error) => error.kind.clone(),
+                error => panic!("Expected a resolve error, found {:?}", error),
+            })
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                ResolveErrorKind::Private {
+                    name: "hidden".to_string(),
+                    module: "utils".to_string(),
+                },
+                ResolveErrorKind::Private {
+                    name: "helper".to_string(),
+                    module: "utils".to_string(),
+                },
+            ]
+        );
+
+        // The declaration is in the module's file
+        let (file, _) = sources.locate(errors[1].declared().unwrap()).unwrap();
+
+        assert_eq!(file.name(), "utils.pg");
+    }
+
+    #[test]
+    fn test_missing_modules() {
+        let path = project(
+            "missing",
+            &[("main.pg", "import utils;\nimport a::b;\nimport \"c.pg\";")],
+        );
+        let directory = fs::canonicalize(path.parent().unwrap()).unwrap();
+        let library = directory.join("lib");
+
+        let mut sources = SourceMap::new();
+        let errors = load_with(&path, std::slice::from_ref(&library), &mut sources).unwrap_err();
+
+        let modules: Vec<_> = errors
+            .iter()
+            .map(|error| match error {
+                LoadError::Module(error) => error.kind.clone(),
+                error => panic!("Expected a module error, found {:?}", error),
+            })
+            .collect();
+
+        assert_eq!(
+            modules,
+            vec![
+                ModuleErrorKind::NotFound {
+                    module: "utils".to_string(),
+                    searched: vec![
+                        directory.join("utils.pg"),
+                        directory.join("utils/mod.pg"),
+                        library.join("utils.pg"),
+                        library.join("utils/mod.pg"),
+                    ],
+                },
+                ModuleErrorKind::NotFound {
+                    module: "a::b".to_string(),
+                    searched: vec![
+                        directory.join("a/b.pg"),
+                        directory.join("a/b/mod.pg"),
+                        library.join("a/b.pg"),
+                        library.join("a/b/mod.pg"),
+                    ],
+                },
+                ModuleErrorKind::NotFound {
+                    module: "c.pg".to_string(),
+                    searched: vec![directory.join("c.pg"), library.join("c.pg")],
+                },
+            ]
+        );
+
+        let LoadError::Module(error) = &errors[0] else {
+            unreachable!();
+        };
+
+        assert_eq!(
+            error.help().unwrap(),
+            format!(
+                "It was looked for at '{}', '{}', '{}', '{}'. Add the directory it is in with \
+                 '--include' or to PENGUIN_PATH",
+                directory.join("utils.pg").display(),
+                directory.join("utils/mod.pg").display(),
+                library.join("utils.pg").display(),
+                library.join("utils/mod.pg").display(),
+            )
+        );
+    }
+
+    #[test]
+    fn test_include_directories() {
+        let path = project(
+            "include",
+            &[
+                (
+                    "app/main.pg",
+                    "import strings;
+                     import local;
+                     func main() : i32 = { strings::length() + local::two() };",
+                ),
+                ("app/local.pg", "pub func two() : i32 = { 2 };"),
+                // Only used if the one next to main.pg wasn't there
+                ("first/local.pg", "pub func three() : i32 = { 3 };"),
+                (
+                    "first/strings/mod.pg",
+                    "import chars;
+                     pub func length() : i32 = { chars::count() };",
+                ),
+                ("first/strings/chars.pg", "pub func count() : i32 = { 1 };"),
+                ("second/strings.pg", "pub func other() : i32 = { 0 };"),
+            ],
+        );
+        let directory = path.parent().unwrap().parent().unwrap();
+
+        let mut sources = SourceMap::new();
+        let parsed = load_with(
+            &path,
+            &[directory.join("first"), directory.join("second")],
+            &mut sources,
+        )
+        .unwrap()
+        .parsed;
+
+        // Modules in include directories are named by their path from it
+        assert_eq!(
+            names(&parsed),
+            vec![
+                "main",
+                "strings::length",
+                "local::two",
+                "strings::chars::count"
+            ]
+        );
+        assert!(Resolver::resolve(&parsed).is_ok());
+    }
+
+    #[test]
+    fn test_errors_in_modules_are_in_their_files() {
+        let path = project(
+            "errors",
+            &[
+                ("main.pg", "import \"lib/broken.pg\";"),
+                ("lib/broken.pg", "\nfunc f( = {};"),
+            ],
+        );
+
+        let mut sources = SourceMap::new();
+        let errors = load(&path, &mut sources).unwrap_err();
+
+        let LoadError::Parser(error) = &errors[0] else {
+            panic!("Expected a parser error, found {:?}", errors[0]);
+        };
+
+        let (file, span) = sources.locate(error.span()).unwrap();
+
+        assert_eq!(file.name(), "lib/broken.pg");
+        assert_eq!(file.line_column(span.start), (2, 9));
+    }
+}
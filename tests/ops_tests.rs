@@ -0,0 +1,122 @@
+use penguin::lexer::TokenKind;
+use penguin::ops::{self, Associativity, Infix, Precedence, Side};
+use penguin::parser::ast::{BinaryOp, UnaryOp};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infix_tokens() {
+        assert_eq!(
+            ops::infix(&TokenKind::Minus),
+            Some(Infix::Binary(BinaryOp::Subtract))
+        );
+        assert_eq!(
+            ops::infix(&TokenKind::ShortMultiply),
+            Some(Infix::Assign(Some(BinaryOp::Multiply)))
+        );
+        assert_eq!(
+            ops::infix(&TokenKind::LetAssignment),
+            Some(Infix::Assign(None))
+        );
+        assert_eq!(ops::infix(&TokenKind::Not), None);
+        assert_eq!(ops::infix(&TokenKind::OpenParen), None);
+    }
+
+    #[test]
+    fn test_prefix_tokens() {
+        assert_eq!(ops::prefix(&TokenKind::Minus), Some(UnaryOp::Negate));
+        assert_eq!(ops::prefix(&TokenKind::Not), Some(UnaryOp::Not));
+        assert_eq!(ops::prefix(&TokenKind::Plus), None);
+    }
+
+    #[test]
+    fn test_binding_powers() {
+        // Left associative operators bind tighter on the right
+        assert_eq!(Infix::Binary(BinaryOp::Add).binding_power(), (11, 12));
+        assert_eq!(Infix::Binary(BinaryOp::Multiply).binding_power(), (13, 14));
+
+        // Right associative operators bind tighter on the left
+        assert_eq!(Infix::Assign(None).binding_power(), (2, 1));
+
+        // Casts bind tighter than any infix operator, unary operators
+        // tighter than casts, and postfix ones tighter still
+        assert_eq!(Precedence::Cast.binding_power(), 15);
+        assert_eq!(Precedence::Prefix.binding_power(), 17);
+        assert_eq!(Precedence::Postfix.binding_power(), 19);
+    }
+
+    #[test]
+    fn test_comparisons_are_non_associative() {
+        assert_eq!(
+            ops::binary(BinaryOp::Less),
+            (Precedence::Comparison, Associativity::None)
+        );
+        assert_eq!(
+            ops::binary(BinaryOp::NotEqual),
+            (Precedence::Equality, Associativity::None)
+        );
+        assert_eq!(
+            Infix::Assign(Some(BinaryOp::Add)).associativity(),
+            Associativity::Right
+        );
+    }
+
+    #[test]
+    fn test_needs_parentheses() {
+        let subtract = Infix::Binary(BinaryOp::Subtract);
+        let multiply = Infix::Binary(BinaryOp::Multiply);
+        let assign = Infix::Assign(None);
+
+        // (a + b) * c, but not a * b + c
+        assert!(ops::needs_parentheses(
+            multiply,
+            Precedence::Sum,
+            Side::Left
+        ));
+        assert!(!ops::needs_parentheses(
+            subtract,
+            Precedence::Product,
+            Side::Right
+        ));
+
+        // (a - b) - c is written a - b - c, but a - (b - c) isn't
+        assert!(!ops::needs_parentheses(
+            subtract,
+            Precedence::Sum,
+            Side::Left
+        ));
+        assert!(ops::needs_parentheses(
+            subtract,
+            Precedence::Sum,
+            Side::Right
+        ));
+
+        // a = (b = c) is written a = b = c
+        assert!(!ops::needs_parentheses(
+            assign,
+            Precedence::Assignment,
+            Side::Right
+        ));
+        assert!(ops::needs_parentheses(
+            assign,
+            Precedence::Assignment,
+            Side::Left
+        ));
+
+        // Chained comparisons are rejected, so they always need them
+        let less = Infix::Binary(BinaryOp::Less);
+
+        assert!(ops::needs_parentheses(
+            less,
+            Precedence::Comparison,
+            Side::Left
+        ));
+        assert!(ops::needs_parentheses(
+            less,
+            Precedence::Comparison,
+            Side::Right
+        ));
+    }
+}
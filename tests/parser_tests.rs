@@ -0,0 +1,1554 @@
+use penguin::lexer::{Lexer, Span, Token, TokenKind};
+use penguin::parser::ast::{
+    Arena, Closure, ClosureParam, Enum, ExprKind, Field, Import, ImportPath, ItemKind, Length, Let,
+    Struct, Type, Variant,
+};
+use penguin::parser::{sexp, ParseError, ParseErrorKind, ParseResult, Parser, MAX_NESTING};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lex and parse the source, which is expected to lex successfully.
+    fn parse(source: &str) -> Result<ParseResult, Vec<ParseError>> {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        Parser::parse(&tokens)
+    }
+
+    /// Parse the source, which is expected to be valid, and write it as
+    /// s-expressions.
+    fn parse_sexp(source: &str) -> String {
+        sexp::program(&parse(source).unwrap())
+    }
+
+    /// The kinds of the parsed items, without their spans.
+    fn kinds(parsed: &ParseResult) -> Vec<ItemKind> {
+        parsed
+            .program
+            .items
+            .iter()
+            .map(|item| item.kind.clone())
+            .collect()
+    }
+
+    fn named(name: &str) -> Type {
+        Type::Named(name.to_string())
+    }
+
+    /// Parse the source as the value of a global, returning the value as an
+    /// s-expression.
+    fn parse_expression(source: &str) -> Result<String, Vec<ParseError>> {
+        let parsed = parse(&format!("let x := {};", source))?;
+
+        match parsed.program.items.first().map(|item| &item.kind) {
+            Some(ItemKind::Global(Let { value, .. })) => Ok(sexp::expr(&parsed.arena, *value)),
+            item => panic!("Expected a global, found {:?}", item),
+        }
+    }
+
+    #[test]
+    fn test_empty_program() {
+        assert_eq!(parse("// Nothing here").unwrap(), ParseResult::default());
+    }
+
+    #[test]
+    fn test_global_variables() {
+        let parsed = parse("let a := 1; let b : u32 = a;").unwrap();
+
+        let ItemKind::Global(b) = &parsed.program.items[1].kind else {
+            panic!("Expected a global, found {:?}", parsed.program.items[1]);
+        };
+
+        assert_eq!(b.name, "b");
+        assert_eq!(b.ty, Some(named("u32")));
+        assert_eq!(
+            parsed.arena[b.value].kind,
+            ExprKind::Identifier("a".to_string())
+        );
+
+        assert_eq!(
+            sexp::program(&parsed),
+            "(global a (num 1))\n(global b u32 (ident a))\n"
+        );
+    }
+
+    #[test]
+    fn test_import() {
+        let parsed = parse("import \"std.print\";").unwrap();
+
+        let expected = ItemKind::Import(Import {
+            path: ImportPath::File("std.print".to_string()),
+            names: None,
+        });
+
+        assert_eq!(kinds(&parsed), vec![expected]);
+    }
+
+    #[test]
+    fn test_function() {
+        let program = parse_sexp(
+            "func add(a : u32, b : u32) : u32 = {
+                c := a + b;
+                return c;
+            };",
+        );
+
+        assert_eq!(
+            program,
+            "(func add ((a u32) (b u32)) u32 \
+             (block (let c (+ (ident a) (ident b))) (return (ident c))))\n"
+        );
+    }
+
+    #[test]
+    fn test_untyped_function() {
+        assert_eq!(
+            parse_sexp("func main() := { return; };"),
+            "(func main () (block (return)))\n"
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_precedence() {
+        assert_eq!(
+            parse_expression("1 + 2 * (3 - 4) % 5").unwrap(),
+            "(+ (num 1) (% (* (num 2) (- (num 3) (num 4))) (num 5)))"
+        );
+    }
+
+    #[test]
+    fn test_children_are_allocated_before_parents() {
+        let parsed = parse("let a := -(1 + b);").unwrap();
+
+        let exprs: Vec<_> = parsed
+            .arena
+            .iter()
+            .map(|(id, _)| sexp::expr(&parsed.arena, id))
+            .collect();
+
+        assert_eq!(
+            exprs,
+            [
+                "(num 1)",
+                "(ident b)",
+                "(+ (num 1) (ident b))",
+                "(- (+ (num 1) (ident b)))",
+            ]
+        );
+
+        let ItemKind::Global(global) = &parsed.program.items[0].kind else {
+            panic!("Expected a global, found {:?}", parsed.program.items[0]);
+        };
+
+        assert_eq!(global.value.index(), 3);
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let source = "let a := -(1 + b) * f(c)[0].d;";
+        let parsed = parse(source).unwrap();
+
+        let spans: Vec<_> = parsed
+            .arena
+            .iter()
+            .map(|(_, expr)| &source[expr.span.start..expr.span.end])
+            .collect();
+
+        assert_eq!(
+            spans,
+            [
+                "1",
+                "b",
+                "1 + b",
+                "-(1 + b)",
+                "f",
+                "c",
+                "f(c)",
+                "0",
+                "f(c)[0]",
+                "f(c)[0].d",
+                "-(1 + b) * f(c)[0].d",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_item_and_statement_spans() {
+        let source = "import math; func f() := { let b := 1; b = 2; while b { break; } b };";
+        let parsed = parse(source).unwrap();
+        let text = |span: Span| &source[span.start..span.end];
+
+        let items: Vec<_> = parsed
+            .program
+            .items
+            .iter()
+            .map(|item| text(item.span))
+            .collect();
+
+        assert_eq!(
+            items,
+            [
+                "import math;",
+                "func f() := { let b := 1; b = 2; while b { break; } b };",
+            ]
+        );
+
+        let ItemKind::Function(function) = &parsed.program.items[1].kind else {
+            panic!("Expected a function, found {:?}", parsed.program.items[1]);
+        };
+
+        let stmts: Vec<_> = function
+            .body
+            .stmts
+            .iter()
+            .map(|stmt| text(stmt.span))
+            .collect();
+
+        assert_eq!(stmts, ["let b := 1;", "b = 2;", "while b { break; }"]);
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let errors = parse("let a := 1 let b := 2;").unwrap_err();
+
+        // The error points at where the semicolon should be
+        assert_eq!(
+            errors,
+            vec![ParseError::new(
+                ParseErrorKind::MissingSemicolon {
+                    after: "a variable declaration".to_string(),
+                },
+                Span::new(10, 10),
+            )]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Expected ';' after a variable declaration."
+        );
+    }
+
+    #[test]
+    fn test_missing_semicolons_in_function() {
+        let errors = parse("func main() := { print\n return 1 }").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::MissingSemicolon {
+                after: "an expression".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(22, 22));
+
+        let errors = parse("func main() := { return 1 }").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::MissingSemicolon {
+                after: "a return".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_statements() {
+        let program = parse_sexp(
+            "func main() := {
+                let a : u32 = 1;
+                b := 2;
+                a += b;
+                a;
+            };",
+        );
+
+        assert_eq!(
+            program,
+            "(func main () (block \
+             (let a u32 (num 1)) (let b (num 2)) (+= (ident a) (ident b)) (ident a)))\n"
+        );
+    }
+
+    #[test]
+    fn test_if_let() {
+        assert_eq!(
+            parse_expression("if let Some(x) = f() { x } else { 0 }").unwrap(),
+            "(if-let (variant Some x) (call (ident f)) (block (value (ident x))) \
+             (block (value (num 0))))"
+        );
+
+        // Without an `else`, and a struct literal isn't allowed before the
+        // block
+        assert_eq!(
+            parse_expression("if let Shape::Circle(_, r) = shape { r }").unwrap(),
+            "(if-let (variant Shape::Circle _ r) (ident shape) (block (value (ident r))))"
+        );
+    }
+
+    #[test]
+    fn test_else_if_let() {
+        assert_eq!(
+            parse_expression("if let (1, a) = t { a } else if let b = u { b } else { 2 }").unwrap(),
+            "(if-let (tuple (num 1) a) (ident t) (block (value (ident a))) \
+             (block (value (if-let b (ident u) (block (value (ident b))) \
+             (block (value (num 2)))))))"
+        );
+    }
+
+    #[test]
+    fn test_while_let() {
+        let program = parse_sexp(
+            "func main() := {
+                while let Some(x) = iter.next() { f(x); break; }
+                if let None = a {}
+                g()
+            };",
+        );
+
+        assert_eq!(
+            program,
+            "(func main () (block \
+             (while-let (variant Some x) (method-call (ident iter) next) \
+             (block (call (ident f) (ident x)) (break))) \
+             (if-let None (ident a) (block)) \
+             (value (call (ident g)))))\n"
+        );
+    }
+
+    #[test]
+    fn test_patterns() {
+        let patterns = [
+            ("x", "x"),
+            ("_", "_"),
+            ("\"a\"", "(str \"a\")"),
+            ("(x)", "x"),
+            ("(x,)", "(tuple x)"),
+            ("()", "(tuple)"),
+            ("true", "true"),
+            ("Color::Red", "(variant Color::Red)"),
+            (
+                "Some((a, Ok(_)))",
+                "(variant Some (tuple a (variant Ok _)))",
+            ),
+        ];
+
+        for (pattern, expected) in patterns {
+            let expression = format!("if let {} = v {{}}", pattern);
+
+            assert_eq!(
+                parse_expression(&expression).unwrap(),
+                format!("(if-let {} (ident v) (block))", expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_match() {
+        assert_eq!(
+            parse_expression(
+                "match s { Shape::Dot => 0, Shape::Line(n) => { n } (_, true) => 1, }"
+            )
+            .unwrap(),
+            "(match (ident s) (arm (variant Shape::Dot) (num 0)) \
+             (arm (variant Shape::Line n) (block (value (ident n)))) \
+             (arm (tuple _ true) (num 1)))"
+        );
+
+        // An arm needs a comma after it unless it ends with a block
+        assert!(parse_expression("match s { a => 1 b => 2 }").is_err());
+
+        // Like a block, a match needs no semicolon after it
+        assert_eq!(
+            parse_sexp("func f() := { match a { _ => g(), } false };"),
+            "(func f () (block (match (ident a) (arm _ (call (ident g)))) (value false)))\n"
+        );
+    }
+
+    #[test]
+    fn test_match_bindings_are_in_scope() {
+        let program = parse_sexp(
+            "func main() := {
+                match a { Some(x) => func() => x, None => func() => x }
+            };",
+        );
+
+        assert_eq!(
+            program,
+            "(func main () (block (value (match (ident a) \
+             (arm (variant Some x) (closure () (captures x) (ident x))) \
+             (arm None (closure () (captures) (ident x)))))))\n"
+        );
+    }
+
+    #[test]
+    fn test_pattern_bindings_are_in_scope() {
+        let program = parse_sexp(
+            "func main() := {
+                if let Some(x) = a { func() => x } else { func() => x }
+            };",
+        );
+
+        // Only the closure in the first block captures the binding
+        assert_eq!(
+            program,
+            "(func main () (block (value (if-let (variant Some x) (ident a) \
+             (block (value (closure () (captures x) (ident x)))) \
+             (block (value (closure () (captures) (ident x))))))))\n"
+        );
+    }
+
+    #[test]
+    fn test_if_needs_let() {
+        let errors = parse("let a := if b { 1 };").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "'let'".to_string(),
+                found: "b".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_compound_assignments() {
+        for symbol in ["+=", "-=", "*=", "/=", "%="] {
+            assert_eq!(
+                parse_expression(&format!("a {} 2", symbol)).unwrap(),
+                format!("({} (ident a) (num 2))", symbol)
+            );
+        }
+    }
+
+    #[test]
+    fn test_mutable_declarations() {
+        assert_eq!(
+            parse_sexp("let mut a := 1; func f(mut b : u32, c : u32) := { let mut d := 2; mut e : u8 = 3; };"),
+            "(global mut a (num 1))\n\
+             (func f ((mut b u32) (c u32)) (block (let mut d (num 2)) (let mut e u8 (num 3))))\n"
+        );
+
+        // Constants can't be mutable
+        assert!(parse("const mut A := 1;").is_err());
+    }
+
+    #[test]
+    fn test_declaration_needs_a_type() {
+        // `: =` is an untyped assignment, but a lone colon needs a type
+        assert!(parse("let a : = 1;").is_ok());
+
+        let errors = parse("let a : 1;").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "a type".to_string(),
+                found: "1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_unexpected_end_of_file() {
+        let errors = parse("func main() := {").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedEOF {
+                expected: "'}'".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(16, 16));
+    }
+
+    #[test]
+    fn test_errors_in_multiple_items() {
+        let errors = parse(
+            "func a() := { let x := ; };
+            func b() := { return 1 };
+            let c := 3;",
+        )
+        .unwrap_err();
+
+        let spans: Vec<_> = errors.iter().map(ParseError::span).collect();
+
+        assert_eq!(spans, vec![Span::new(23, 24), Span::new(62, 62)]);
+    }
+
+    #[test]
+    fn test_logical_precedence() {
+        assert_eq!(
+            parse_expression("a || b && c == d < e + f").unwrap(),
+            "(|| (ident a) (&& (ident b) (== (ident c) (< (ident d) (+ (ident e) (ident f))))))"
+        );
+    }
+
+    #[test]
+    fn test_left_associative() {
+        assert_eq!(
+            parse_expression("a - b - c").unwrap(),
+            "(- (- (ident a) (ident b)) (ident c))"
+        );
+    }
+
+    #[test]
+    fn test_unary_operators() {
+        assert_eq!(
+            parse_expression("-a * !b - - -c").unwrap(),
+            "(- (* (- (ident a)) (! (ident b))) (- (- (ident c))))"
+        );
+    }
+
+    #[test]
+    fn test_casts() {
+        // A cast binds tighter than infix operators, but looser than unary ones
+        assert_eq!(
+            parse_expression("-a as i64 + b as i64").unwrap(),
+            "(+ (as (- (ident a)) i64) (as (ident b) i64))"
+        );
+        assert_eq!(
+            parse_expression("a as u8 as [u32; 2]").unwrap(),
+            "(as (as (ident a) u8) (array u32 2))"
+        );
+
+        // The type isn't followed by generic arguments, so this is a
+        // comparison
+        assert_eq!(
+            parse_expression("a as u32 < b").unwrap(),
+            "(< (as (ident a) u32) (ident b))"
+        );
+
+        let errors = parse_expression("a as 5").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "a type".to_string(),
+                found: "5".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_assignment_is_right_associative() {
+        assert_eq!(
+            parse_expression("a = b += 1").unwrap(),
+            "(= (ident a) (+= (ident b) (num 1)))"
+        );
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        let errors = parse_expression("a + b = c").unwrap_err();
+
+        assert_eq!(errors[0].kind, ParseErrorKind::InvalidAssignmentTarget);
+        assert_eq!(errors[0].span(), Span::new(15, 16));
+    }
+
+    #[test]
+    fn test_chained_comparisons() {
+        let errors = parse_expression("a < b <= c").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::ChainedComparison {
+                replacement: "a < b && b <= c".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(15, 17));
+
+        // The fix is made of the comparisons that were written
+        let source = "let x := f(x) <= y.z   > 1 + 2;";
+        let errors = parse(source).unwrap_err();
+        let suggestion = errors[0].suggestion().unwrap();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "Comparisons can't be chained, use `f(x) <= y.z && y.z > 1 + 2` instead."
+        );
+        assert_eq!(suggestion.span, Span::new(9, 30));
+        assert_eq!(
+            suggestion.apply(source).unwrap(),
+            "let x := f(x) <= y.z && y.z > 1 + 2;"
+        );
+
+        // Different kinds of comparisons can be mixed
+        assert!(parse_expression("a < b == c > d").is_ok());
+        assert!(parse_expression("(a < b) < c").is_ok());
+    }
+
+    #[test]
+    fn test_split_untyped_assignment() {
+        // The lexer always joins `:=`, but the parser accepts it split
+        let tokens = vec![
+            Token::new(TokenKind::Assign, "let", Span::new(0, 3)),
+            Token::new(TokenKind::Identifier, "a", Span::new(4, 5)),
+            Token::new(TokenKind::TypeAssignment, ":", Span::new(6, 7)),
+            Token::new(TokenKind::LetAssignment, "=", Span::new(8, 9)),
+            Token::new(TokenKind::Number(1), "1", Span::new(10, 11)),
+            Token::new(TokenKind::Semicolon, ";", Span::new(11, 12)),
+        ];
+
+        let parsed = Parser::parse(&tokens).unwrap();
+
+        assert_eq!(sexp::program(&parsed), "(global a (num 1))\n");
+    }
+
+    #[test]
+    fn test_block_expression() {
+        assert_eq!(
+            parse_expression("{ compute; 5 }").unwrap(),
+            "(block (ident compute) (value (num 5)))"
+        );
+
+        // Without a trailing expression, the block has no value
+        assert_eq!(
+            parse_expression("{ compute; }").unwrap(),
+            "(block (ident compute))"
+        );
+    }
+
+    #[test]
+    fn test_implicit_and_explicit_returns() {
+        let implicit = parse_sexp("func f() : u32 = { let a := 1; a + 1 };");
+        let explicit = parse_sexp("func f() : u32 = { let a := 1; return a + 1; };");
+
+        assert_eq!(
+            implicit,
+            "(func f () u32 (block (let a (num 1)) (value (+ (ident a) (num 1)))))\n"
+        );
+        assert_eq!(
+            explicit,
+            "(func f () u32 (block (let a (num 1)) (return (+ (ident a) (num 1)))))\n"
+        );
+    }
+
+    #[test]
+    fn test_nested_blocks_need_no_semicolon() {
+        assert_eq!(
+            parse_expression("{ { a; } { 1 }; b }").unwrap(),
+            "(block (block (ident a)) (block (value (num 1))) (value (ident b)))"
+        );
+    }
+
+    #[test]
+    fn test_only_the_last_expression_is_the_value() {
+        let errors = parse_expression("{ a b }").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::MissingSemicolon {
+                after: "an expression".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(12, 12));
+    }
+
+    #[test]
+    fn test_while_loop() {
+        let program = parse_sexp("func f() := { while a < 10 { a += 1; continue; } break_me; };");
+
+        assert_eq!(
+            program,
+            "(func f () (block \
+             (while (< (ident a) (num 10)) (block (+= (ident a) (num 1)) (continue))) \
+             (ident break_me)))\n"
+        );
+    }
+
+    #[test]
+    fn test_break_in_nested_blocks() {
+        let program = parse_sexp("func f() := { while a { { if_only; break; } } };");
+
+        // The inner block is the value of the loop's body
+        assert_eq!(
+            program,
+            "(func f () (block \
+             (while (ident a) (block (value (block (ident if_only) (break)))))))\n"
+        );
+    }
+
+    #[test]
+    fn test_break_and_continue_outside_of_loops() {
+        let errors = parse("func f() := { break; };").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::OutsideOfLoop {
+                keyword: "break".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(14, 19));
+        assert_eq!(errors[0].code(), "E0106");
+
+        // The loop has ended by the time `continue` is reached
+        let errors = parse("func f() := { while a { } continue; };").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::OutsideOfLoop {
+                keyword: "continue".to_string()
+            }
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "'continue' can only be used inside of a loop."
+        );
+    }
+
+    #[test]
+    fn test_defer() {
+        let program = parse_sexp(
+            "func f() := {
+                file := open(path);
+                defer { close(file); }
+                defer { while a { break; } };
+                read(file)
+            };",
+        );
+
+        assert_eq!(
+            program,
+            "(func f () (block \
+             (let file (call (ident open) (ident path))) \
+             (defer (block (call (ident close) (ident file)))) \
+             (defer (block (while (ident a) (block (break))))) \
+             (value (call (ident read) (ident file)))))\n"
+        );
+    }
+
+    #[test]
+    fn test_assertions() {
+        let source = "static_assert(MAX > 0, \"MAX can't be 0\");
+            func f(a : u32) := {
+                assert(a < MAX);
+                assert(a != 1, \"a is 1\",);
+                static_assert(MAX < 100);
+            };";
+        let parsed = parse(source).unwrap();
+
+        assert_eq!(
+            sexp::program(&parsed),
+            "(static-assert (> (ident MAX) (num 0)) \"MAX can't be 0\")\n\
+             (func f ((a u32)) (block \
+             (assert (< (ident a) (ident MAX))) \
+             (assert (!= (ident a) (num 1)) \"a is 1\") \
+             (static-assert (< (ident MAX) (num 100)))))\n"
+        );
+
+        // Runtime failures report where the assertion is
+        let ItemKind::Function(function) = &parsed.program.items[1].kind else {
+            panic!("Expected a function, found {:?}", parsed.program.items[1]);
+        };
+        let span = function.body.stmts[0].span;
+
+        assert_eq!(&source[span.start..span.end], "assert(a < MAX);");
+    }
+
+    #[test]
+    fn test_assertion_message_is_a_string() {
+        let errors = parse("func f() := { assert(a, b); };").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "a message".to_string(),
+                found: "b".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_leaving_a_defer() {
+        let errors = parse("func f() := { defer { return; } };").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::LeavesDefer {
+                keyword: "return".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(22, 28));
+        assert_eq!(errors[0].code(), "E0107");
+
+        // The loop is outside of the defer, so the defer would leave it
+        let errors = parse("func f() := { while a { defer { break; } } };").unwrap_err();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "'break' can't be used to leave a defer block."
+        );
+
+        // Closures in a defer can still return
+        assert!(parse("func f() := { defer { g(func() => { return 1; }); } };").is_ok());
+    }
+
+    #[test]
+    fn test_struct_declaration() {
+        let parsed = parse("struct Point { x: i32, y: i32 } struct Empty {};").unwrap();
+
+        let expected = vec![
+            ItemKind::Struct(Struct {
+                name: "Point".to_string(),
+                generics: vec![],
+                fields: vec![
+                    Field {
+                        name: "x".to_string(),
+                        ty: named("i32"),
+                    },
+                    Field {
+                        name: "y".to_string(),
+                        ty: named("i32"),
+                    },
+                ],
+            }),
+            ItemKind::Struct(Struct {
+                name: "Empty".to_string(),
+                generics: vec![],
+                fields: vec![],
+            }),
+        ];
+
+        assert_eq!(kinds(&parsed), expected);
+    }
+
+    #[test]
+    fn test_generics() {
+        assert_eq!(
+            parse_sexp(
+                "struct Pair<A, B> { first : A, second : B } \
+                 func max<T>(a : T, b : T) : T = { a };"
+            ),
+            "(struct (Pair A B) (first A) (second B))\n\
+             (func (max T) ((a T) (b T)) T (block (value (ident a))))\n"
+        );
+
+        let parsed = parse("func f<T,>(a : Pair<T, u8>) := {};").unwrap();
+
+        let ItemKind::Function(function) = &parsed.program.items[0].kind else {
+            panic!("Expected a function");
+        };
+
+        assert_eq!(function.generics[0].name, "T");
+        assert_eq!(function.params[0].ty.to_string(), "Pair<T, u8>");
+
+        assert!(parse("func f<T(a : T) := {};").is_err());
+        assert!(parse("struct S<> {}").is_ok());
+    }
+
+    #[test]
+    fn test_traits() {
+        assert_eq!(
+            parse_sexp(
+                "trait Shape { func area(self) : u32; func grow(mut self, by : u32); } \
+                 impl Shape for Square { func area(self) : u32 = { self.side }; \
+                 func grow(mut self, by : u32) := { self.side += by; }; }"
+            ),
+            "(trait Shape (area ((self Self)) u32) (grow ((mut self Self) (by u32))))\n\
+             (impl Shape Square \
+             (func area ((self Self)) u32 (block (value (field (ident self) side)))) \
+             (func grow ((mut self Self) (by u32)) (block (+= (field (ident self) side) (ident by)))))\n"
+        );
+
+        let parsed = parse("func f<T : Shape + Named, U>(a : T, b : U) := {};").unwrap();
+
+        let ItemKind::Function(function) = &parsed.program.items[0].kind else {
+            panic!("Expected a function");
+        };
+
+        assert_eq!(function.generics[0].bounds, ["Shape", "Named"]);
+        assert!(function.generics[1].bounds.is_empty());
+        assert_eq!(function.generics[0].to_string(), "T : Shape + Named");
+
+        // Trait methods don't have bodies, and methods can't be generic
+        assert!(parse("trait Shape { func area(self) : u32 = { 1 }; }").is_err());
+        assert!(parse("impl Shape for Square { func area<T>(self) := {}; }").is_err());
+        assert!(parse("impl Shape { }").is_err());
+    }
+
+    #[test]
+    fn test_pub_items() {
+        assert_eq!(
+            parse_sexp(
+                "pub func f() := {}; pub struct A { x : u32 } pub const B : u32 = 1; let c := 2;"
+            ),
+            "(pub (func f () (block)))\n\
+             (pub (struct A (x u32)))\n\
+             (pub (const B u32 (num 1)))\n\
+             (global c (num 2))\n"
+        );
+
+        // Only items with names can be used from other modules
+        assert!(parse("pub import math;").is_err());
+        assert!(parse("pub impl Shape for Square {}").is_err());
+        assert!(parse("pub pub func f() := {};").is_err());
+    }
+
+    #[test]
+    fn test_attributes() {
+        let source = "@allow(shadowing, unused_variables,) @deny(warnings) pub func f() := {};";
+        let parsed = parse(source).unwrap();
+        let item = &parsed.program.items[0];
+
+        assert_eq!(
+            parse_sexp(source),
+            "(@allow (shadowing unused_variables) (@deny (warnings) (pub (func f () (block)))))\n"
+        );
+        assert_eq!(
+            item.attributes[0].args[1],
+            ("unused_variables".to_string(), Span::new(18, 34))
+        );
+        assert_eq!(item.attributes[1].span, Span::new(37, 52));
+
+        // The item starts at its first attribute
+        assert_eq!(item.span, Span::new(0, source.len()));
+
+        let errors = parse("@inline func f() := {};").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnknownAttribute {
+                name: "inline".to_string()
+            }
+        );
+        assert_eq!(errors[0].code(), "E0108");
+        assert_eq!(
+            errors[0].to_string(),
+            "There is no attribute '@inline', the attributes are: @allow, @warn, @deny."
+        );
+
+        // Attributes name at least one lint, and come before an item
+        assert!(parse("@allow() func f() := {};").is_err());
+        assert!(parse("@allow func f() := {};").is_err());
+        assert!(parse("func f() := {}; @allow(shadowing)").is_err());
+    }
+
+    #[test]
+    fn test_struct_literal() {
+        assert_eq!(
+            parse_expression("Point { x: 1, y: a + 2, }").unwrap(),
+            "(struct-lit Point (x (num 1)) (y (+ (ident a) (num 2))))"
+        );
+    }
+
+    #[test]
+    fn test_field_access() {
+        assert_eq!(
+            parse_expression("-a.b.c * 2").unwrap(),
+            "(* (- (field (field (ident a) b) c)) (num 2))"
+        );
+        assert_eq!(
+            parse_expression("p.x += 1").unwrap(),
+            "(+= (field (ident p) x) (num 1))"
+        );
+    }
+
+    #[test]
+    fn test_no_struct_literals_in_loop_conditions() {
+        // The brace starts the body of the loop, not a struct literal
+        assert_eq!(
+            parse_sexp("func f() := { while a { b : u32 = 1; } };"),
+            "(func f () (block (while (ident a) (block (let b u32 (num 1))))))\n"
+        );
+
+        // Unless they are in parentheses
+        assert!(parse("func f() := { while (P { x: 1 }).x == 1 { } };").is_ok());
+    }
+
+    #[test]
+    fn test_enum_declaration() {
+        let parsed = parse("enum Color { Red, Green, Rgb(u8, u8, u8), }").unwrap();
+
+        let expected = ItemKind::Enum(Enum {
+            name: "Color".to_string(),
+            variants: vec![
+                Variant {
+                    name: "Red".to_string(),
+                    payload: vec![],
+                },
+                Variant {
+                    name: "Green".to_string(),
+                    payload: vec![],
+                },
+                Variant {
+                    name: "Rgb".to_string(),
+                    payload: vec![named("u8"); 3],
+                },
+            ],
+        });
+
+        assert_eq!(kinds(&parsed), vec![expected]);
+    }
+
+    #[test]
+    fn test_enum_variants() {
+        assert_eq!(
+            parse_expression("Color::Red").unwrap(),
+            "(variant Color Red)"
+        );
+        assert_eq!(
+            parse_expression("Color::Rgb(1, a + 1, P { x: 3 }.x)").unwrap(),
+            "(variant Color Rgb (num 1) (+ (ident a) (num 1)) \
+             (field (struct-lit P (x (num 3))) x))"
+        );
+    }
+
+    #[test]
+    fn test_variant_needs_a_name() {
+        let errors = parse_expression("Color::1").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "a variant name".to_string(),
+                found: "1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_paths_into_modules() {
+        assert_eq!(
+            parse_expression("colors::Color::Red").unwrap(),
+            "(variant colors::Color Red)"
+        );
+        assert_eq!(
+            parse_expression("shapes::Point { x: 1 }").unwrap(),
+            "(struct-lit shapes::Point (x (num 1)))"
+        );
+        assert_eq!(
+            parse_expression("if let colors::Color::Rgb(r, _, _) = c { r }").unwrap(),
+            "(if-let (variant colors::Color::Rgb r _ _) (ident c) (block (value (ident r))))"
+        );
+        assert_eq!(
+            parse_sexp("let p : shapes::Point = q;"),
+            "(global p shapes::Point (ident q))\n"
+        );
+    }
+
+    #[test]
+    fn test_calls() {
+        assert_eq!(
+            parse_expression("foo(1, bar(2), \"x\")").unwrap(),
+            "(call (ident foo) (num 1) (call (ident bar) (num 2)) (str \"x\"))"
+        );
+    }
+
+    #[test]
+    fn test_call_argument_lists() {
+        assert_eq!(parse_expression("f()").unwrap(), "(call (ident f))");
+        assert_eq!(
+            parse_expression("f(1, 2,)").unwrap(),
+            "(call (ident f) (num 1) (num 2))"
+        );
+        assert_eq!(
+            parse_expression("f(1)(2)").unwrap(),
+            "(call (call (ident f) (num 1)) (num 2))"
+        );
+
+        // Calls bind tighter than any operator
+        assert_eq!(
+            parse_expression("!f() + 1").unwrap(),
+            "(+ (! (call (ident f))) (num 1))"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_call() {
+        let errors = parse_expression("f(1 2)").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "')' or ','".to_string(),
+                found: "2".to_string(),
+            }
+        );
+
+        let errors = parse("let x := f(1,").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedEOF {
+                expected: "an expression".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_method_call_chains() {
+        assert_eq!(
+            parse_expression("list.map(f).sum()").unwrap(),
+            "(method-call (method-call (ident list) map (ident f)) sum)"
+        );
+        assert_eq!(
+            parse_expression("obj.inner.method(x).field").unwrap(),
+            "(field (method-call (field (ident obj) inner) method (ident x)) field)"
+        );
+    }
+
+    #[test]
+    fn test_postfix_precedence() {
+        // Postfix operators bind tighter than unary ones
+        assert_eq!(
+            parse_expression("-a.b[0]").unwrap(),
+            "(- (index (field (ident a) b) (num 0)))"
+        );
+        assert_eq!(
+            parse_expression("!xs[i].done()").unwrap(),
+            "(! (method-call (index (ident xs) (ident i)) done))"
+        );
+
+        // And arithmetic binds looser than all of them
+        assert_eq!(
+            parse_expression("a.b * c[1 + 2]").unwrap(),
+            "(* (field (ident a) b) (index (ident c) (+ (num 1) (num 2))))"
+        );
+        assert_eq!(
+            parse_expression("(a + b).c").unwrap(),
+            "(field (+ (ident a) (ident b)) c)"
+        );
+    }
+
+    #[test]
+    fn test_assign_to_element() {
+        assert_eq!(
+            parse_expression("xs[0].y = 1").unwrap(),
+            "(= (field (index (ident xs) (num 0)) y) (num 1))"
+        );
+
+        let errors = parse_expression("f() = 1").unwrap_err();
+
+        assert_eq!(errors[0].kind, ParseErrorKind::InvalidAssignmentTarget);
+        assert_eq!(
+            errors[0].message(),
+            "Only variables, fields and elements can be assigned to"
+        );
+    }
+
+    #[test]
+    fn test_tuples() {
+        assert_eq!(
+            parse_expression("(1, \"a\", b)").unwrap(),
+            "(tuple (num 1) (str \"a\") (ident b))"
+        );
+
+        // A comma makes the difference between a tuple and grouping
+        assert_eq!(parse_expression("(1)").unwrap(), "(num 1)");
+        assert_eq!(parse_expression("(1,)").unwrap(), "(tuple (num 1))");
+        assert_eq!(parse_expression("()").unwrap(), "(tuple)");
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(
+            parse_expression("[1, [a], []][0]").unwrap(),
+            "(index (array (num 1) (array (ident a)) (array)) (num 0))"
+        );
+        assert_eq!(
+            parse_expression("[1, 2,]").unwrap(),
+            "(array (num 1) (num 2))"
+        );
+    }
+
+    #[test]
+    fn test_tuples_as_arguments() {
+        assert_eq!(
+            parse_expression("f((1, 2), (3))").unwrap(),
+            "(call (ident f) (tuple (num 1) (num 2)) (num 3))"
+        );
+    }
+
+    #[test]
+    fn test_tuple_types() {
+        let parsed =
+            parse("let a : (u32, str) = (1, \"a\"); func f() : ((u32,), ()) = { a };").unwrap();
+        let items = &parsed.program.items;
+
+        let ItemKind::Global(global) = &items[0].kind else {
+            panic!("Expected a global, found {:?}", items[0]);
+        };
+        let ItemKind::Function(function) = &items[1].kind else {
+            panic!("Expected a function, found {:?}", items[1]);
+        };
+
+        assert_eq!(
+            global.ty,
+            Some(Type::Tuple(vec![named("u32"), named("str")]))
+        );
+        assert_eq!(
+            function.return_type,
+            Some(Type::Tuple(vec![
+                Type::Tuple(vec![named("u32")]),
+                Type::Tuple(vec![]),
+            ]))
+        );
+    }
+
+    /// Parse the source as the body of a function with parameters `a` and
+    /// `b`, returning the closure the body ends with and the arena it is in.
+    fn parse_function_value(source: &str) -> (Closure, Arena) {
+        let parsed = parse(&format!("func f(a : u32, b : u32) := {{ {} }};", source)).unwrap();
+
+        let Some(ItemKind::Function(function)) =
+            parsed.program.items.first().map(|item| &item.kind)
+        else {
+            panic!("Expected a function");
+        };
+
+        let closure = match function.body.value.map(|value| &parsed.arena[value].kind) {
+            Some(ExprKind::Closure(closure)) => closure.clone(),
+            value => panic!("Expected a closure, found {:?}", value),
+        };
+
+        (closure, parsed.arena)
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_closures() {
+        let (closure, arena) = parse_function_value("func(x, y : u32) => x + y");
+
+        assert_eq!(
+            closure.params,
+            vec![
+                ClosureParam {
+                    name: "x".to_string(),
+                    ty: None,
+                },
+                ClosureParam {
+                    name: "y".to_string(),
+                    ty: Some(named("u32")),
+                },
+            ]
+        );
+        assert_eq!(sexp::expr(&arena, closure.body), "(+ (ident x) (ident y))");
+        assert!(closure.captures.is_empty());
+
+        // Closures can be called and passed around like any other value
+        assert!(parse_expression("list.map(func(x) => x * 2).sum()").is_ok());
+        assert!(parse_expression("(func() => { 1 })()").is_ok());
+    }
+
+    #[test]
+    fn test_closure_captures() {
+        let (closure, _) = parse_function_value("c := 1; func(x) => x + b + c + a + b + global");
+
+        assert_eq!(closure.captures, strings(&["b", "c", "a"]));
+
+        // Variables declared inside the closure aren't captured, even if they
+        // shadow ones outside of it
+        let (closure, _) = parse_function_value("func(x) => { let a := x; a + b }");
+
+        assert_eq!(closure.captures, strings(&["b"]));
+
+        // Variables in blocks that have ended are out of scope
+        let (closure, _) = parse_function_value("{ let c := 1; } func() => c");
+
+        assert!(closure.captures.is_empty());
+    }
+
+    #[test]
+    fn test_nested_closure_captures() {
+        let (closure, arena) = parse_function_value("func(x) => func(y) => a + x + y");
+
+        assert_eq!(closure.captures, strings(&["a"]));
+
+        let ExprKind::Closure(inner) = &arena[closure.body].kind else {
+            panic!("Expected a closure, found {:?}", arena[closure.body]);
+        };
+
+        assert_eq!(inner.captures, strings(&["a", "x"]));
+    }
+
+    #[test]
+    fn test_break_in_closure() {
+        let errors = parse("func f() := { while a { func() => { break; }; } };").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::OutsideOfLoop {
+                keyword: "break".to_string()
+            }
+        );
+    }
+
+    /// Parse the source as the type of a global.
+    fn parse_type(source: &str) -> Result<Type, Vec<ParseError>> {
+        let parsed = parse(&format!("let x : {} = 0;", source))?;
+
+        match parsed
+            .program
+            .items
+            .into_iter()
+            .next()
+            .map(|item| item.kind)
+        {
+            Some(ItemKind::Global(Let { ty: Some(ty), .. })) => Ok(ty),
+            item => panic!("Expected a typed global, found {:?}", item),
+        }
+    }
+
+    #[test]
+    fn test_array_types() {
+        assert_eq!(
+            parse_type("[u32]").unwrap(),
+            Type::Array {
+                element: Box::new(named("u32")),
+                length: None,
+            }
+        );
+        assert_eq!(
+            parse_type("[[u8; 4]; N]").unwrap(),
+            Type::Array {
+                element: Box::new(Type::Array {
+                    element: Box::new(named("u8")),
+                    length: Some(Length::Number(4)),
+                }),
+                length: Some(Length::Const("N".to_string())),
+            }
+        );
+
+        let errors = parse_type("[u8; \"n\"]").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "an array length".to_string(),
+                found: "\"n\"".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_optional_types() {
+        assert_eq!(
+            parse_type("u32?").unwrap(),
+            Type::Optional(Box::new(named("u32")))
+        );
+        assert_eq!(
+            parse_type("[str?]??").unwrap(),
+            Type::Optional(Box::new(Type::Optional(Box::new(Type::Array {
+                element: Box::new(Type::Optional(Box::new(named("str")))),
+                length: None,
+            }))))
+        );
+    }
+
+    #[test]
+    fn test_function_types() {
+        assert_eq!(
+            parse_type("func(u32, str) -> u32").unwrap(),
+            Type::Function {
+                params: vec![named("u32"), named("str")],
+                return_type: Some(Box::new(named("u32"))),
+            }
+        );
+        assert_eq!(
+            parse_type("func()").unwrap(),
+            Type::Function {
+                params: vec![],
+                return_type: None,
+            }
+        );
+
+        // The `?` belongs to the return type, unless the function type is in
+        // parentheses
+        assert_eq!(
+            parse_type("func() -> u32?").unwrap(),
+            Type::Function {
+                params: vec![],
+                return_type: Some(Box::new(Type::Optional(Box::new(named("u32"))))),
+            }
+        );
+        assert_eq!(
+            parse_type("(func() -> u32)?").unwrap(),
+            Type::Optional(Box::new(Type::Function {
+                params: vec![],
+                return_type: Some(Box::new(named("u32"))),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_generic_types() {
+        assert_eq!(
+            parse_type("Map<str, List<u32>>").unwrap(),
+            Type::Generic {
+                name: "Map".to_string(),
+                args: vec![
+                    named("str"),
+                    Type::Generic {
+                        name: "List".to_string(),
+                        args: vec![named("u32")],
+                    },
+                ],
+            }
+        );
+
+        let errors = parse_type("List<u32 str>").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "'>' or ','".to_string(),
+                found: "str".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        let program = parse_sexp("const MAX : u32 = 100; const HALF := MAX / 2; let a := HALF;");
+
+        assert_eq!(
+            program,
+            "(const MAX u32 (num 100))\n\
+             (const HALF (/ (ident MAX) (num 2)))\n\
+             (global a (ident HALF))\n"
+        );
+    }
+
+    #[test]
+    fn test_constant_needs_a_value() {
+        let errors = parse("const MAX : u32; func main() := { };").unwrap_err();
+
+        // Parsing picks up again at the function
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "'='".to_string(),
+                found: ";".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_module_imports() {
+        let parsed = parse("import math; import std::io; import math::{sin, cos,};").unwrap();
+
+        let expected = vec![
+            ItemKind::Import(Import {
+                path: ImportPath::Module(strings(&["math"])),
+                names: None,
+            }),
+            ItemKind::Import(Import {
+                path: ImportPath::Module(strings(&["std", "io"])),
+                names: None,
+            }),
+            ItemKind::Import(Import {
+                path: ImportPath::Module(strings(&["math"])),
+                names: Some(strings(&["sin", "cos"])),
+            }),
+        ];
+
+        assert_eq!(kinds(&parsed), expected);
+    }
+
+    #[test]
+    fn test_invalid_imports() {
+        let errors = parse("import math::{sin}::cos; import 1; import a").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::MissingSemicolon {
+                after: "an import".to_string()
+            }
+        );
+        assert_eq!(
+            errors[1].kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: "a path to import".to_string(),
+                found: "1".to_string(),
+            }
+        );
+        assert_eq!(
+            errors[2].kind,
+            ParseErrorKind::MissingSemicolon {
+                after: "an import".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_nesting_too_deep() {
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!(
+                "func f() := {{ let x := {}1{}; }};",
+                open.repeat(depth),
+                close.repeat(depth)
+            )
+        };
+
+        // Deeply nested code is an error instead of overflowing the stack
+        for source in [
+            nested("(", ")", 10_000),
+            nested("{ ", " }", 10_000),
+            nested("!", "", 10_000),
+            format!("func f() := {{ let x := 1{}; }};", " + 1".repeat(10_000)),
+            format!("func f() := {{ let x : u32{} = 1; }};", "?".repeat(10_000)),
+        ] {
+            let errors = parse(&source).unwrap_err();
+
+            assert_eq!(errors[0].kind, ParseErrorKind::NestingTooDeep);
+            assert_eq!(errors[0].code(), "E0109");
+        }
+
+        assert_eq!(
+            parse(&nested("(", ")", 10_000)).unwrap_err()[0].to_string(),
+            "This is nested too deeply, only 128 levels are allowed."
+        );
+
+        // The body of the function and the value of `let` are two levels, and
+        // the first token past the limit is pointed at
+        assert!(parse(&nested("(", ")", MAX_NESTING - 2)).is_ok());
+
+        let errors = parse(&nested("(", ")", MAX_NESTING - 1)).unwrap_err();
+        let at = "func f() := { let x := ".len() + MAX_NESTING - 1;
+
+        assert_eq!(errors[0].span, Span::new(at, at + 1));
+        assert_eq!(&nested("(", ")", MAX_NESTING - 1)[at..at + 1], "1");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_program_round_trip() {
+        let parsed = parse(
+            "import math; struct P { x : [u32; 2]? } func f(a : u32) := { g(func(x) => x + a).y };",
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&parsed).unwrap();
+        let read: ParseResult = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read, parsed);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_program_json() {
+        let parsed = parse("let a := -1;").unwrap();
+
+        let json = serde_json::to_string(&parsed).unwrap();
+
+        // Expressions refer to each other by where they are in the arena
+        assert_eq!(
+            json,
+            r#"{"program":{"items":[{"kind":{"Global":{"name":"a","name_span":{"start":4,"end":5},"mutable":false,"ty":null,"value":1}},"public":false,"attributes":[],"span":{"start":0,"end":12}}]},"arena":{"exprs":[{"kind":{"Number":1},"span":{"start":10,"end":11}},{"kind":{"Unary":{"op":"Negate","operand":0}},"span":{"start":9,"end":11}}]}}"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_error_round_trip() {
+        let errors = parse("let a := ;").unwrap_err();
+
+        let json = serde_json::to_string(&errors).unwrap();
+        let read: Vec<ParseError> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read, errors);
+    }
+}
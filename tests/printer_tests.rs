@@ -0,0 +1,189 @@
+use penguin::lexer::Lexer;
+use penguin::parser::ast::Type;
+use penguin::parser::printer::print_tree;
+use penguin::parser::Parser;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lex, parse and print the source, which is expected to be valid.
+    fn tree(source: &str) -> String {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        print_tree(&Parser::parse(&tokens).unwrap())
+    }
+
+    #[test]
+    fn test_print_items() {
+        let source = "
+            import math::{sin, cos};
+            const MAX : u32 = 100;
+            struct Point { x : i32, y : i32 }
+            enum Shape { Empty, Circle(Point, u32) }
+            func add(a : u32, b : u32) : u32 = { a + b };
+        ";
+
+        let expected = "\
+Program
+  Import math::{sin, cos} [13..37]
+  Const MAX : u32 [50..72]
+    Number 100 [68..71]
+  Struct Point [85..118]
+    Field x : i32
+    Field y : i32
+  Enum Shape [131..171]
+    Variant Empty
+    Variant Circle(Point, u32)
+  Function add : u32 [184..229]
+    Param a : u32 [193..194]
+    Param b : u32 [202..203]
+    Block
+      Value
+        Binary + [221..226]
+          Identifier a [221..222]
+          Identifier b [225..226]
+";
+
+        assert_eq!(tree(source), expected);
+    }
+
+    #[test]
+    fn test_print_statements() {
+        let source = "
+            func main() := {
+                let i := 0;
+                while i < 10 { i += 1; continue; }
+                return;
+            };
+        ";
+
+        let expected = "\
+Program
+  Function main [13..147]
+    Block
+      Let i [46..57]
+        Number 0 [55..56]
+      While [74..108]
+        Binary < [80..86]
+          Identifier i [80..81]
+          Number 10 [84..86]
+        Block
+          Assign += [89..95]
+            Identifier i [89..90]
+            Number 1 [94..95]
+          Continue [97..106]
+      Return [125..132]
+";
+
+        assert_eq!(tree(source), expected);
+    }
+
+    #[test]
+    fn test_print_patterns() {
+        let source = "
+            func main() := {
+                while let Some((a, _)) = next() { }
+                if let Shape::Circle(p, 1) = s { p } else { q }
+            };
+        ";
+
+        let expected = "\
+Program
+  Function main [13..160]
+    Block
+      WhileLet Some((a, _)) [46..81]
+        Call [71..77]
+          Identifier next [71..75]
+        Block
+      Value
+        IfLet Shape::Circle(p, 1) [98..145]
+          Identifier s [127..128]
+          Block
+            Value
+              Identifier p [131..132]
+          Else
+            Block
+              Value
+                Identifier q [142..143]
+";
+
+        assert_eq!(tree(source), expected);
+    }
+
+    #[test]
+    fn test_print_expressions() {
+        let source = "
+            func main(a : u32) := {
+                p := Point { x: -a, y: 2 };
+                p.x = list.map(func(x : u32) => x * a)[0];
+                Color::Rgb(1, (2, \"s\"))
+            };
+        ";
+
+        let expected = "\
+Program
+  Function main [13..194]
+    Param a : u32 [23..24]
+    Block
+      Let p [53..80]
+        StructLiteral Point [58..79]
+          Field x
+            Unary - [69..71]
+              Identifier a [70..71]
+          Field y
+            Number 2 [76..77]
+      Assign = [97..138]
+        Field x [97..100]
+          Identifier p [97..98]
+        Index [103..138]
+          MethodCall map [103..135]
+            Identifier list [103..107]
+            Closure capturing a [112..134]
+              Param x : u32
+              Binary * [129..134]
+                Identifier x [129..130]
+                Identifier a [133..134]
+          Number 0 [136..137]
+      Value
+        Variant Color::Rgb [156..179]
+          Number 1 [167..168]
+          Tuple [170..178]
+            Number 2 [171..172]
+            String \"s\" [174..177]
+";
+
+        assert_eq!(tree(source), expected);
+    }
+
+    #[test]
+    fn test_display_types() {
+        let types = [
+            "u32",
+            "List<Map<str, u32>>",
+            "(u32, str)",
+            "(u32,)",
+            "()",
+            "[u8; 4]",
+            "[str?]?",
+            "func(u32, u32) -> u32",
+            "(func())?",
+        ];
+
+        for source in types {
+            let program = tree(&format!("let x : {} = 0;", source));
+
+            assert!(
+                program.contains(&format!("Global x : {} [", source)),
+                "{} was printed as {}",
+                source,
+                program
+            );
+        }
+
+        assert_eq!(
+            Type::Optional(Box::new(Type::Named("u32".to_string()))).to_string(),
+            "u32?"
+        );
+    }
+}
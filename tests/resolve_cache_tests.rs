@@ -0,0 +1,223 @@
+#![cfg(feature = "resolve-cache")]
+use penguin::lexer::{Lexer, LexerOptions, Span};
+use penguin::module::{self, LoadResult};
+use penguin::parser::ast::ExprId;
+use penguin::parser::Parser;
+use penguin::resolve::{Resolution, Resolver};
+use penguin::resolve_cache::{self, Origin};
+use penguin::source_map::SourceMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The resolution of a source, and the number of expressions in it.
+    fn resolve(source: &str) -> (Resolution, usize) {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+
+        (Resolver::resolve(&parsed).unwrap(), parsed.arena.len())
+    }
+
+    /// Write the files of a project to a directory of its own, returning
+    /// the directory.
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("penguin-cache-{}-{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        for (path, contents) in files {
+            fs::write(directory.join(path), contents).unwrap();
+        }
+
+        directory
+    }
+
+    /// Parse the file at `path`, and load the modules it imports with their
+    /// resolution cached in `cache`.
+    fn load(path: &Path, cache: &Path) -> LoadResult {
+        let mut sources = SourceMap::new();
+        let id = sources.load(path).unwrap();
+        let tokens = sources.get(id).unwrap().lexer().lex().unwrap();
+        let parsed = Parser::parse(&tokens).unwrap();
+
+        module::load_cached(parsed, path, &[], &mut sources, &LexerOptions::new(), cache).unwrap()
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let (resolution, exprs) = resolve("func f(a : u32) := { let b := a; b };");
+
+        let mut cache = Vec::new();
+        let options = LexerOptions::new();
+        resolve_cache::write(
+            1,
+            &options,
+            &resolution,
+            Origin::default(),
+            exprs,
+            &mut cache,
+        )
+        .unwrap();
+
+        let read = resolve_cache::read(1, &options, Origin::default(), exprs, cache.as_slice());
+
+        assert_eq!(read.unwrap(), Some(resolution));
+    }
+
+    #[test]
+    fn test_cache_is_relative_to_the_module() {
+        let (resolution, exprs) = resolve("func f(a : u32) := { a };");
+        let options = LexerOptions::new();
+
+        let mut cache = Vec::new();
+        resolve_cache::write(
+            1,
+            &options,
+            &resolution,
+            Origin::default(),
+            exprs,
+            &mut cache,
+        )
+        .unwrap();
+
+        // The same module, loaded after another one
+        let moved = Origin {
+            offset: 100,
+            expr: 10,
+        };
+        let read = resolve_cache::read(1, &options, moved, exprs, cache.as_slice())
+            .unwrap()
+            .unwrap();
+
+        let span = resolution.symbols[0].span;
+
+        assert_eq!(
+            read.symbols[0].span,
+            Span::new(span.start + 100, span.end + 100)
+        );
+        assert_eq!(
+            read.uses.keys().map(|id| id.index()).collect::<Vec<_>>(),
+            resolution
+                .uses
+                .keys()
+                .map(|id| id.index() + 10)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            read.uses.get(&ExprId::from_index(10)),
+            resolution.uses.get(&ExprId::from_index(0))
+        );
+    }
+
+    #[test]
+    fn test_stale_cache() {
+        let (resolution, _) = resolve("let a := 1;");
+        let options = LexerOptions::new();
+        let origin = Origin::default();
+
+        let mut cache = Vec::new();
+        resolve_cache::write(1, &options, &resolution, origin, 1, &mut cache).unwrap();
+
+        let keyed = resolve_cache::read(2, &options, origin, 1, cache.as_slice()).unwrap();
+        let counted = resolve_cache::read(1, &options, origin, 2, cache.as_slice()).unwrap();
+        let other = LexerOptions::new().case_insensitive_keywords(true);
+        let lexed = resolve_cache::read(1, &other, origin, 1, cache.as_slice()).unwrap();
+
+        assert_eq!((keyed, counted, lexed), (None, None, None));
+    }
+
+    #[test]
+    fn test_not_a_cache() {
+        let read = resolve_cache::read(
+            1,
+            &LexerOptions::new(),
+            Origin::default(),
+            1,
+            &b"let a := 1;"[..],
+        );
+
+        assert_eq!(read.unwrap(), None);
+    }
+
+    #[test]
+    fn test_keys() {
+        let key = resolve_cache::key("import a;", &["pub func f() := {};"]);
+
+        assert_eq!(
+            key,
+            resolve_cache::key("import a;", &["pub func f() := {};"])
+        );
+        assert_ne!(
+            key,
+            resolve_cache::key("import a; ", &["pub func f() := {};"])
+        );
+        assert_ne!(
+            key,
+            resolve_cache::key("import a;", &["pub func g() := {};"])
+        );
+        assert_ne!(key, resolve_cache::key("import a;", &[]));
+    }
+
+    #[test]
+    fn test_cache_path() {
+        let path = resolve_cache::path_for(Path::new("target/penguin"), Path::new("a/utils.pg"));
+
+        assert_eq!(path.parent(), Some(Path::new("target/penguin")));
+        assert_eq!(path.extension().unwrap(), "resolve");
+        assert_ne!(
+            path,
+            resolve_cache::path_for(Path::new("target/penguin"), Path::new("b/utils.pg"))
+        );
+    }
+
+    #[test]
+    fn test_unchanged_modules_are_not_resolved_again() {
+        let directory = project(
+            "modules",
+            &[
+                (
+                    "main.pg",
+                    "import utils; func main() := { utils::double(2); };",
+                ),
+                (
+                    "utils.pg",
+                    "pub func double(a : u32) : u32 = { let b := a * 2; b };",
+                ),
+            ],
+        );
+        let (main, cache) = (directory.join("main.pg"), directory.join("target/penguin"));
+
+        let first = load(&main, &cache);
+
+        assert_eq!(first.cached, 0);
+
+        let second = load(&main, &cache);
+
+        assert_eq!(second.cached, 2);
+        assert_eq!(second.parsed, first.parsed);
+        assert_eq!(
+            Resolver::resolve(&second.parsed),
+            Resolver::resolve(&first.parsed)
+        );
+
+        // Only the module that changed is resolved again
+        fs::write(&main, "import utils; func main() := { utils::double(3); };").unwrap();
+
+        assert_eq!(load(&main, &cache).cached, 1);
+
+        // Modules importing one that changed are too
+        fs::write(
+            directory.join("utils.pg"),
+            "pub func double(a : u32) : u32 = { a + a };",
+        )
+        .unwrap();
+
+        assert_eq!(load(&main, &cache).cached, 0);
+        assert_eq!(load(&main, &cache).cached, 2);
+    }
+}
@@ -0,0 +1,440 @@
+use penguin::lexer::{Lexer, Span};
+use penguin::parser::ast::ExprKind;
+use penguin::parser::{ParseResult, Parser};
+use penguin::resolve::{
+    Resolution, ResolveError, ResolveErrorKind, Resolver, ScopeKind, SymbolKind,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> ParseResult {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        Parser::parse(&tokens).unwrap()
+    }
+
+    fn resolve(source: &str) -> Result<Resolution, Vec<ResolveError>> {
+        Resolver::resolve(&parse(source))
+    }
+
+    /// Every identifier in the source, in the order they were parsed, with
+    /// what it refers to and the code declaring that.
+    fn uses(source: &str) -> Vec<(String, SymbolKind, String)> {
+        let parsed = parse(source);
+        let resolution = Resolver::resolve(&parsed).unwrap();
+
+        parsed
+            .arena
+            .iter()
+            .filter_map(|(id, expr)| match &expr.kind {
+                ExprKind::Identifier(name) => {
+                    let symbol = resolution.resolved(id).unwrap();
+                    let span = symbol.span;
+
+                    Some((
+                        name.clone(),
+                        symbol.kind,
+                        source[span.start..span.end].to_string(),
+                    ))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn used(name: &str, kind: SymbolKind, declaration: &str) -> (String, SymbolKind, String) {
+        (name.to_string(), kind, declaration.to_string())
+    }
+
+    #[test]
+    fn test_items_can_be_used_before_they_are_declared() {
+        let source = "func main() := { f(MAX) }; func f(a : u32) := { a + TOTAL }; \
+                      const MAX : u32 = 1; let TOTAL := MAX;";
+
+        assert_eq!(
+            uses(source),
+            [
+                used(
+                    "f",
+                    SymbolKind::Function,
+                    "func f(a : u32) := { a + TOTAL };"
+                ),
+                used("MAX", SymbolKind::Const, "const MAX : u32 = 1;"),
+                used("a", SymbolKind::Param, "func f(a : u32) := { a + TOTAL };"),
+                used("TOTAL", SymbolKind::Global, "let TOTAL := MAX;"),
+                used("MAX", SymbolKind::Const, "const MAX : u32 = 1;"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let source = "func f(a : u32) := { let a := a + 1; b := a; { a := 2; } b + a };";
+
+        assert_eq!(
+            uses(source),
+            [
+                // A variable isn't in scope in its own value
+                used("a", SymbolKind::Param, source),
+                used("a", SymbolKind::Local, "let a := a + 1;"),
+                used("b", SymbolKind::Local, "b := a;"),
+                // The inner `a` went out of scope with its block
+                used("a", SymbolKind::Local, "let a := a + 1;"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variables_go_out_of_scope() {
+        let source = "func f() := { { b := 1; } b };";
+        let errors = resolve(source).unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::Undefined {
+                name: "b".to_string(),
+                suggestion: None,
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(26, 27));
+        assert_eq!(errors[0].code(), "E0201");
+        assert_eq!(errors[0].to_string(), "Can't find 'b' in this scope.");
+
+        // Every error is found
+        assert_eq!(resolve("let a := b + c(d);").unwrap_err().len(), 3);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let source = "struct Point { x : u32 } func f(counter : u32) := { \
+                      { count := 1; } countr; Pont { x: 1 }; f(pint); };";
+        let errors = resolve(source).unwrap_err();
+
+        // Only names that can be seen are suggested, so not `count`
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::Undefined {
+                name: "countr".to_string(),
+                suggestion: Some("counter".to_string()),
+            }
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Can't find 'countr' in this scope. Did you mean 'counter'?"
+        );
+        assert_eq!(
+            errors[0].help(),
+            Some("Did you mean 'counter'?".to_string())
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "Can't find 'Pont' in this scope. Did you mean 'Point'?"
+        );
+
+        // Names that are too different aren't suggested
+        assert_eq!(errors[2].help(), None);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let source = "func f(a : u32, a : u32) := { b := 1; b := 2; }; func f() := {};";
+        let errors = resolve(source).unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::Duplicate {
+                name: "f".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(49, 64));
+        assert_eq!(errors[0].declared(), Some(Span::new(0, 48)));
+        assert_eq!(errors[0].code(), "E0203");
+        assert_eq!(
+            errors[0].to_string(),
+            "'f' is already declared in this scope."
+        );
+
+        // Parameters are declared by their function, so their names are
+        // pointed at instead
+        assert_eq!(
+            errors[1].kind,
+            ResolveErrorKind::Duplicate {
+                name: "a".to_string()
+            }
+        );
+        assert_eq!(errors[1].span(), Span::new(16, 17));
+        assert_eq!(errors[1].declared(), Some(Span::new(7, 8)));
+
+        assert_eq!(errors[2].span(), Span::new(38, 45));
+        assert_eq!(errors[2].declared(), Some(Span::new(30, 37)));
+        assert_eq!(errors.len(), 3);
+
+        // Bindings in one pattern share a scope too
+        assert_eq!(
+            resolve("func f(p : u32) := { if let (x, x) = p { } };")
+                .unwrap_err()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_mutability() {
+        let source = "let mut A := 1; func f(mut b : u32, c : [u32]) := { \
+                      let d := 2; A = 2; b += 1; d *= 2; c[0] = 1; f = f; };";
+        let errors = resolve(source).unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::Immutable {
+                name: "d".to_string()
+            }
+        );
+        assert_eq!(errors[0].code(), "E0204");
+        assert_eq!(
+            errors[0].to_string(),
+            "Can't assign to 'd', since it isn't mutable. Add 'mut' to make 'd' mutable."
+        );
+
+        // The suggestion makes the declaration mutable
+        let suggestion = errors[0].suggestion().unwrap();
+
+        let d = source.find("d := 2").unwrap();
+
+        assert_eq!(errors[0].declared(), Some(Span::new(d, d + 1)));
+        assert!(suggestion
+            .apply(source)
+            .unwrap()
+            .contains("let mut d := 2; A = 2; b += 1; d *= 2;"));
+
+        // Elements are assigned to through the array they are in
+        assert_eq!(
+            errors[1].kind,
+            ResolveErrorKind::Immutable {
+                name: "c".to_string()
+            }
+        );
+        assert_eq!(suggestion_applied(source, &errors[1]), "mut c");
+
+        // Functions can't be made mutable
+        assert_eq!(errors[2].suggestion(), None);
+        assert_eq!(errors.len(), 3);
+    }
+
+    /// The name an error's suggestion makes mutable, after the fix is applied.
+    fn suggestion_applied(source: &str, error: &ResolveError) -> String {
+        let suggestion = error.suggestion().unwrap();
+        let fixed = suggestion.apply(source).unwrap();
+        let span = error.declared().unwrap();
+
+        fixed[span.start..span.end + suggestion.replacement.len()].to_string()
+    }
+
+    #[test]
+    fn test_shadowing_outer_scopes() {
+        let source = "let a := 1; func f(a : u32) := { a := a; { a := a; } g := func(a) => a; };";
+
+        assert!(resolve(source).is_ok());
+    }
+
+    #[test]
+    fn test_closures() {
+        let source = "func f(a : u32) := { g := func(x) => x + a; g(1) };";
+
+        assert_eq!(
+            uses(source),
+            [
+                used("x", SymbolKind::ClosureParam, "func(x) => x + a"),
+                used("a", SymbolKind::Param, source),
+                used("g", SymbolKind::Local, "g := func(x) => x + a;"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pattern_bindings() {
+        let source = "func f(x : u32) := { \
+                      while let Some(y) = x { y; } \
+                      if let (x, _) = x { x } else { x } };";
+
+        assert_eq!(
+            uses(source),
+            [
+                used("x", SymbolKind::Param, source),
+                used("y", SymbolKind::Binding, "while let Some(y) = x { y; }"),
+                used("x", SymbolKind::Param, source),
+                used(
+                    "x",
+                    SymbolKind::Binding,
+                    "if let (x, _) = x { x } else { x }"
+                ),
+                // The bindings aren't in scope in the `else` block
+                used("x", SymbolKind::Param, source),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imports() {
+        let source = "import \"std.print\"; import \"lib/vec.pg\"; import math::{sin}; \
+                      import std::io; \
+                      func main() := { print(sin(1)); io::write(vec::new()); };";
+        let parsed = parse(source);
+        let resolution = Resolver::resolve(&parsed).unwrap();
+
+        let imported: Vec<_> = resolution
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Import)
+            .map(|symbol| symbol.name.as_str())
+            .collect();
+
+        assert_eq!(imported, ["print", "vec", "sin", "io"]);
+
+        // Variants of imported names are allowed, since the names could be
+        // enums
+        assert!(parsed
+            .arena
+            .iter()
+            .filter(|(_, expr)| matches!(expr.kind, ExprKind::Variant { .. }))
+            .all(|(id, _)| resolution.resolved(id).unwrap().kind == SymbolKind::Import));
+    }
+
+    #[test]
+    fn test_structs_and_enums() {
+        let source = "struct Point { x : u32 } enum Shape { Dot(Point) } \
+                      let a := Shape::Dot(Point { x: 1 });";
+        let parsed = parse(source);
+        let resolution = Resolver::resolve(&parsed).unwrap();
+
+        let kinds: Vec<_> = parsed
+            .arena
+            .iter()
+            .filter_map(|(id, _)| resolution.resolved(id))
+            .map(|symbol| (symbol.name.as_str(), symbol.kind))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [("Point", SymbolKind::Struct), ("Shape", SymbolKind::Enum)]
+        );
+
+        let errors = resolve("func f() := {}; let a := f { x: 1 }; let b := a::B;").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::NotA {
+                name: "f".to_string(),
+                expected: "a struct".to_string(),
+            }
+        );
+        assert_eq!(errors[0].code(), "E0202");
+        assert_eq!(errors[1].to_string(), "'a' isn't an enum.");
+    }
+
+    #[test]
+    fn test_traits() {
+        let source = "trait Shape { func area(self) : u32; } impl Shape for u32 { \
+                      func area(self) : u32 = { 1 }; func area(self) : u32 = { self }; }";
+        let errors = resolve(source).unwrap_err();
+
+        // Each method is declared by itself, and `self` is its own parameter
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::Duplicate {
+                name: "area".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(91, 124));
+        assert_eq!(errors[0].declared(), Some(Span::new(60, 90)));
+        assert_eq!(errors.len(), 1);
+
+        assert_eq!(
+            uses("trait Shape { func area(self) : u32; } func f(a : u32) := { Shape; a };"),
+            [
+                used(
+                    "Shape",
+                    SymbolKind::Trait,
+                    "trait Shape { func area(self) : u32; }"
+                ),
+                used("a", SymbolKind::Param, "func f(a : u32) := { Shape; a };"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exports() {
+        let source = "pub func area() := {}; func helper() := {}; pub struct Point { x : u32 }";
+        let resolution = resolve(source).unwrap();
+
+        let area = resolution
+            .export("area", "shapes", Span::new(0, 4))
+            .unwrap();
+
+        assert!(resolution.symbol(area).public);
+
+        // Items that aren't `pub` are private to their module
+        let error = resolution
+            .export("helper", "shapes", Span::new(0, 6))
+            .unwrap_err();
+
+        assert_eq!(
+            error.kind,
+            ResolveErrorKind::Private {
+                name: "helper".to_string(),
+                module: "shapes".to_string(),
+            }
+        );
+        assert_eq!(error.code(), "E0205");
+        assert_eq!(error.declared(), Some(Span::new(23, 43)));
+        assert_eq!(
+            error.to_string(),
+            "'helper' is private to the module 'shapes'. \
+             Add 'pub' to make 'helper' usable from other modules."
+        );
+        assert_eq!(error.suggestion().unwrap().span, Span::new(23, 23));
+
+        let error = resolution
+            .export("aria", "shapes", Span::new(0, 4))
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Can't find 'aria' in this scope. Did you mean 'area'?"
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        let resolution =
+            resolve("func f(a : u32) := { if let b = a { } g := func(c) => c; };").unwrap();
+
+        let scopes: Vec<_> = resolution
+            .scopes
+            .iter()
+            .map(|scope| {
+                let names: Vec<_> = scope
+                    .symbols
+                    .iter()
+                    .map(|&symbol| resolution.symbol(symbol).name.as_str())
+                    .collect();
+
+                (scope.kind, scope.parent.map(|parent| parent.index()), names)
+            })
+            .collect();
+
+        assert_eq!(
+            scopes,
+            [
+                (ScopeKind::Module, None, vec!["f"]),
+                (ScopeKind::Function, Some(0), vec!["a"]),
+                (ScopeKind::Block, Some(1), vec!["g"]),
+                (ScopeKind::Block, Some(2), vec!["b"]),
+                (ScopeKind::Block, Some(3), vec![]),
+                (ScopeKind::Closure, Some(2), vec!["c"]),
+            ]
+        );
+    }
+}
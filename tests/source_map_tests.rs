@@ -0,0 +1,101 @@
+use penguin::lexer::{Source, Span};
+use penguin::source_map::SourceMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_get_their_own_ids() {
+        let mut sources = SourceMap::new();
+
+        let main = sources.add(Source::new("main.pg", "import math;".to_string()));
+        let math = sources.add(Source::new("math.pg", "let pi := 3;".to_string()));
+
+        assert_ne!(main, math);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources.get(main).unwrap().name(), "main.pg");
+        assert_eq!(sources.get(math).unwrap().contents(), "let pi := 3;");
+        assert_eq!(sources.find("math.pg").unwrap().id, math);
+    }
+
+    #[test]
+    fn test_ids_from_other_maps() {
+        let mut sources = SourceMap::new();
+        let mut other = SourceMap::new();
+
+        sources.add(Source::new("main.pg", String::new()));
+        other.add(Source::new("main.pg", String::new()));
+        let id = other.add(Source::new("math.pg", String::new()));
+
+        assert!(sources.get(id).is_none());
+    }
+
+    #[test]
+    fn test_line_column() {
+        let mut sources = SourceMap::new();
+        let id = sources.add(Source::new(
+            "main.pg",
+            "let a := 1;\nlet é := 2;\n".to_string(),
+        ));
+        let file = sources.get(id).unwrap();
+
+        assert_eq!(file.line_count(), 3);
+        assert_eq!(file.line_column(0), (1, 1));
+        assert_eq!(file.line_column(4), (1, 5));
+        assert_eq!(file.line_column(12), (2, 1));
+        // `é` is two bytes, but one column
+        assert_eq!(file.line_column(18), (2, 6));
+        assert_eq!(file.line_column(100), (3, 1));
+    }
+
+    #[test]
+    fn test_lines_and_slices() {
+        let mut sources = SourceMap::new();
+        let id = sources.add(Source::new(
+            "main.pg",
+            "let a := 1;\r\nlet b := 2;".to_string(),
+        ));
+        let file = sources.get(id).unwrap();
+
+        assert_eq!(file.line(0), None);
+        assert_eq!(file.line(1), Some("let a := 1;"));
+        assert_eq!(file.line(2), Some("let b := 2;"));
+        assert_eq!(file.line(3), None);
+        assert_eq!(file.slice(Span::new(4, 5)), Some("a"));
+        assert_eq!(file.slice(Span::new(4, 100)), None);
+    }
+
+    #[test]
+    fn test_locating_spans_of_several_files() {
+        let mut sources = SourceMap::new();
+        let main = sources.add(Source::new("main.pg", "import math;".to_string()));
+        let math = sources.add(Source::new("math.pg", "let pi := 3;".to_string()));
+
+        // Each file starts after the end of the one before it
+        let start = sources.get(math).unwrap().start();
+
+        assert_eq!(sources.get(main).unwrap().start(), 0);
+        assert_eq!(start, 13);
+
+        let (file, span) = sources.locate(Span::new(7, 11)).unwrap();
+
+        assert_eq!((file.id, span), (main, Span::new(7, 11)));
+
+        let (file, span) = sources.locate(Span::new(4, 6).shift(start)).unwrap();
+
+        assert_eq!((file.id, span), (math, Span::new(4, 6)));
+        assert_eq!(file.slice(span), Some("pi"));
+    }
+
+    #[test]
+    fn test_lexing_a_file() {
+        let mut sources = SourceMap::new();
+        let id = sources.add(Source::new("main.pg", "let a := @;".to_string()));
+        let file = sources.get(id).unwrap();
+
+        let error = file.lexer().lex().unwrap_err();
+
+        assert_eq!(file.line_column(error.span().start), (1, 10));
+    }
+}
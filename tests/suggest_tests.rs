@@ -0,0 +1,60 @@
+use penguin::lexer::Span;
+use penguin::suggest::{self, Suggestion};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(suggest::distance("kitten", "sitting"), 3);
+        assert_eq!(suggest::distance("", "abc"), 3);
+        assert_eq!(suggest::distance("abc", ""), 3);
+        assert_eq!(suggest::distance("same", "same"), 0);
+
+        // Characters, not bytes, are counted
+        assert_eq!(suggest::distance("naïve", "naive"), 1);
+
+        // Swapping neighbouring characters is one edit
+        assert_eq!(suggest::distance("cuont", "count"), 1);
+        assert_eq!(suggest::distance("ab", "ba"), 1);
+        assert_eq!(suggest::distance("ca", "abc"), 3);
+    }
+
+    #[test]
+    fn test_closest() {
+        let names = ["count", "counter", "total"];
+
+        assert_eq!(suggest::closest("conter", names), Some("counter"));
+        assert_eq!(suggest::closest("tota", names), Some("total"));
+        assert_eq!(suggest::closest("cuont", ["count"]), Some("count"));
+        assert_eq!(suggest::closest("coutn", names), Some("count"));
+
+        // Names too different to be a typo aren't suggested, and short names
+        // can only be one character off
+        assert_eq!(suggest::closest("index", names), None);
+        assert_eq!(suggest::closest("xy", ["ab", "x"]), Some("x"));
+        assert_eq!(suggest::closest("b", ["a"]), None);
+
+        // Ties go to the first candidate
+        assert_eq!(suggest::closest("cat", ["bat", "hat"]), Some("bat"));
+    }
+
+    #[test]
+    fn test_applying_suggestions() {
+        let insert = Suggestion::insert("Make 'a' mutable", 4, "mut ");
+
+        assert_eq!(insert.apply("let a := 1;").unwrap(), "let mut a := 1;");
+
+        let replace = Suggestion {
+            message: "Use 'b'".to_string(),
+            span: Span::new(0, 1),
+            replacement: "b".to_string(),
+        };
+
+        assert_eq!(replace.apply("a + 1").unwrap(), "b + 1");
+
+        // Suggestions made for other sources can't always be applied
+        assert_eq!(insert.apply("a"), None);
+    }
+}
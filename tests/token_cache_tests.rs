@@ -0,0 +1,85 @@
+#![cfg(feature = "token-cache")]
+use penguin::lexer::{Lexer, LexerOptions};
+use penguin::token_cache;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_is_stable() {
+        assert_eq!(token_cache::hash_source(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(token_cache::hash_source("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let source = "let name := \"Wad\\nle\"; // A penguin";
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        let mut cache = Vec::new();
+        token_cache::write(source, &LexerOptions::new(), &tokens, &mut cache).unwrap();
+
+        let read = token_cache::read(source, &LexerOptions::new(), cache.as_slice()).unwrap();
+
+        assert_eq!(read, Some(tokens));
+    }
+
+    #[test]
+    fn test_stale_cache() {
+        let source = "let a := 1;";
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        let mut cache = Vec::new();
+        token_cache::write(source, &LexerOptions::new(), &tokens, &mut cache).unwrap();
+
+        let read =
+            token_cache::read("let a := 2;", &LexerOptions::new(), cache.as_slice()).unwrap();
+
+        assert_eq!(read, None);
+    }
+
+    #[test]
+    fn test_cache_from_other_options() {
+        let source = "LET a := 1;";
+        let options = LexerOptions::new().case_insensitive_keywords(true);
+        let tokens = Lexer::lex_from_string(source)
+            .with_options(options.clone())
+            .lex()
+            .unwrap();
+
+        let mut cache = Vec::new();
+        token_cache::write(source, &options, &tokens, &mut cache).unwrap();
+
+        let read = token_cache::read(source, &LexerOptions::new(), cache.as_slice()).unwrap();
+
+        assert_eq!(read, None);
+    }
+
+    #[test]
+    fn test_not_a_cache() {
+        let read =
+            token_cache::read("let a := 1;", &LexerOptions::new(), &b"let a := 1;"[..]).unwrap();
+
+        assert_eq!(read, None);
+    }
+
+    #[test]
+    fn test_truncated_cache() {
+        let source = "let a := 1;";
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        let mut cache = Vec::new();
+        token_cache::write(source, &LexerOptions::new(), &tokens, &mut cache).unwrap();
+        cache.truncate(cache.len() / 2);
+
+        assert!(token_cache::read(source, &LexerOptions::new(), cache.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_cache_path() {
+        let path = token_cache::path_for(std::path::Path::new("examples/hello.pg"));
+
+        assert_eq!(path, std::path::Path::new("examples/hello.pg.tokens"));
+    }
+}
@@ -0,0 +1,1004 @@
+use penguin::lexer::{Lexer, Span};
+use penguin::parser::ast::ExprKind;
+use penguin::parser::{ParseResult, Parser};
+use penguin::resolve::Resolver;
+use penguin::typeck::{self, IntTy, MethodId, Ty, TypeChecker, TypeError, TypeErrorKind, Types};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> ParseResult {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        Parser::parse(&tokens).unwrap()
+    }
+
+    fn check(source: &str) -> Result<Types, Vec<TypeError>> {
+        let parsed = parse(source);
+        let resolution = Resolver::resolve(&parsed).unwrap();
+
+        TypeChecker::check(&parsed, &resolution)
+    }
+
+    fn errors(source: &str) -> Vec<TypeErrorKind> {
+        check(source)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.kind)
+            .collect()
+    }
+
+    /// The type of every identifier in the source, in the order they were
+    /// parsed.
+    fn identifiers(source: &str) -> Vec<(String, Ty)> {
+        let parsed = parse(source);
+        let resolution = Resolver::resolve(&parsed).unwrap();
+        let types = TypeChecker::check(&parsed, &resolution).unwrap();
+
+        parsed
+            .arena
+            .iter()
+            .filter_map(|(id, expr)| match &expr.kind {
+                ExprKind::Identifier(name) => Some((name.clone(), types.expr(id)?.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn mismatch(expected: Ty, found: Ty) -> TypeErrorKind {
+        TypeErrorKind::Mismatch { expected, found }
+    }
+
+    const U8: Ty = Ty::Int(IntTy::U8);
+    const U32: Ty = Ty::Int(IntTy::U32);
+    const I32: Ty = Ty::Int(IntTy::I32);
+
+    #[test]
+    fn test_every_expression_has_a_type() {
+        let parsed = parse("func f(a : u8) : bool = { b := (a + 1, \"x\"); b == b };");
+        let resolution = Resolver::resolve(&parsed).unwrap();
+        let types = TypeChecker::check(&parsed, &resolution).unwrap();
+
+        assert!(parsed.arena.iter().all(|(id, _)| types.expr(id).is_some()));
+
+        let b = resolution
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.symbols.iter().copied())
+            .find(|&symbol| resolution.symbol(symbol).name == "b")
+            .unwrap();
+
+        assert_eq!(types.symbol(b), Some(&Ty::Tuple(vec![U8, Ty::Str])));
+    }
+
+    #[test]
+    fn test_literals_take_the_expected_type() {
+        let source = "func f() := { a : u8 = 1; b := 2; c : i64 = -3; d := -4; \
+                      e := 5 + a; a; b; c; d; e };";
+
+        assert_eq!(
+            identifiers(source)[1..],
+            [
+                ("a".to_string(), U8),
+                ("b".to_string(), U32),
+                ("c".to_string(), Ty::Int(IntTy::I64)),
+                ("d".to_string(), I32),
+                // `5 + a` is checked like `a + 5`
+                ("e".to_string(), U8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trailing_literals_take_the_return_type() {
+        let source = "func f() : i64 = { 0 }; func g() : u8 = { 7 }; func h() : i8 = { -1 }; \
+                      func i(a : bool) : u16 = { if let true = a { 1 } else { 2 } };";
+
+        assert!(check(source).is_ok());
+
+        let errors = check("func f() : u8 = { true };").unwrap_err();
+
+        assert_eq!(errors[0].kind, mismatch(U8, Ty::Bool));
+    }
+
+    #[test]
+    fn test_annotation_mismatches_point_at_the_declaration() {
+        let source = "func f(a : u32) := { b : str = a; };";
+        let errors = check(source).unwrap_err();
+
+        assert_eq!(errors[0].kind, mismatch(Ty::Str, U32));
+        assert_eq!(errors[0].span(), Span::new(31, 32));
+        assert_eq!(errors[0].declared(), Some(Span::new(21, 33)));
+        assert_eq!(errors[0].code(), "E0301");
+        assert_eq!(errors[0].to_string(), "Expected str, found u32.");
+
+        let errors = check("func f() : u8 = { return \"a\"; };").unwrap_err();
+
+        assert_eq!(errors[0].kind, mismatch(U8, Ty::Str));
+        assert_eq!(errors[0].declared(), Some(Span::new(0, 32)));
+    }
+
+    #[test]
+    fn test_inferred_declarations() {
+        let source = "let A := \"Waddle\"; func f() := { b := [1, 2]; c := (A, b); c };";
+
+        assert_eq!(
+            identifiers(source),
+            [
+                ("A".to_string(), Ty::Str),
+                (
+                    "b".to_string(),
+                    Ty::Array {
+                        element: Box::new(U32),
+                        length: Some(2),
+                    }
+                ),
+                (
+                    "c".to_string(),
+                    Ty::Tuple(vec![
+                        Ty::Str,
+                        Ty::Array {
+                            element: Box::new(U32),
+                            length: Some(2),
+                        }
+                    ])
+                ),
+            ]
+        );
+
+        // An empty array has nothing to infer its element type from, unless
+        // it is expected to have one
+        let errors = check("func f(a : [u8]) := { b := []; c : [str] = []; f([]); };").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            TypeErrorKind::CannotInfer {
+                what: "an empty array".to_string()
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(27, 29));
+        assert_eq!(errors[0].declared(), Some(Span::new(22, 30)));
+        assert_eq!(errors[0].code(), "E0313");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_constant_array_lengths() {
+        let source = "const N := 2 * 2; func f(a : [u8; N]) := { a };";
+
+        assert_eq!(
+            identifiers(source)[0],
+            (
+                "a".to_string(),
+                Ty::Array {
+                    element: Box::new(U8),
+                    length: Some(4),
+                }
+            )
+        );
+
+        assert_eq!(
+            errors("const N := 2; func f(a : [u8; N]) := { b : [u8; 3] = a; };"),
+            [mismatch(
+                Ty::Array {
+                    element: Box::new(U8),
+                    length: Some(3),
+                },
+                Ty::Array {
+                    element: Box::new(U8),
+                    length: Some(2),
+                }
+            )]
+        );
+
+        let errors = check("let n := 2; const S := \"s\"; func f(a : [u8; n], b : [u8; S]) := {};")
+            .unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            TypeErrorKind::ArrayLength {
+                name: "n".to_string()
+            }
+        );
+        assert_eq!(errors[0].code(), "E0317");
+        assert_eq!(
+            errors[0].to_string(),
+            "'n' isn't a constant integer, so it can't be the length of an array."
+        );
+        assert_eq!(
+            errors[1].kind,
+            TypeErrorKind::ArrayLength {
+                name: "S".to_string()
+            }
+        );
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_casts() {
+        let types = check("func f(a : u8, b : bool) : i64 = { a as i64 + b as i64 };").unwrap();
+        let mut found: Vec<_> = types.exprs.values().map(Ty::to_string).collect();
+
+        found.sort();
+
+        assert_eq!(found, ["bool", "i64", "i64", "i64", "u8"]);
+
+        // Integers convert to each other, and a value can be cast to its
+        // own type
+        assert!(
+            check("func f(a : i8) := { b := a as u64 as u8; c := (a, 1) as (i8, u32); };").is_ok()
+        );
+
+        assert_eq!(
+            errors("func f(a : u32) := { \"1\" as u32; a as bool; a as str; };"),
+            [
+                TypeErrorKind::InvalidCast {
+                    from: Ty::Str,
+                    to: U32,
+                },
+                TypeErrorKind::InvalidCast {
+                    from: U32,
+                    to: Ty::Bool,
+                },
+                TypeErrorKind::InvalidCast {
+                    from: U32,
+                    to: Ty::Str,
+                },
+            ]
+        );
+
+        let errors = check("func f(a : u32) := { a as Missing; \"1\" as u32; };").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            TypeErrorKind::UnknownType {
+                name: "Missing".to_string()
+            }
+        );
+        assert_eq!(errors[1].code(), "E0318");
+        assert_eq!(errors[1].to_string(), "Can't cast str to u32.");
+        assert_eq!(errors[1].span(), Span::new(35, 45));
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_integers_are_not_widened() {
+        // Every place a value is checked against a type treats a narrower
+        // integer as a different type
+        let source = "func g(a : u32) : u64 = { a }; \
+                      func f(a : u8, b : u16) : u32 = { let c : u32 = a; g(b); a + 1 };";
+
+        assert_eq!(
+            errors(source),
+            [
+                mismatch(Ty::Int(IntTy::U64), U32),
+                mismatch(U32, U8),
+                mismatch(U32, Ty::Int(IntTy::U16)),
+                mismatch(U32, U8),
+            ]
+        );
+
+        let errors = check("func f(a : u8) : u32 = { a };").unwrap_err();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "Expected u32, found u8, which has to be converted with 'as u32'."
+        );
+        assert!(check("func f(a : u8) : u32 = { a as u32 + 1 };").is_ok());
+    }
+
+    #[test]
+    fn test_missing_returns() {
+        let source = "func a(o : u32?) : u32 = { if let Some(x) = o { return x; } }; \
+                      func b(o : u32?) : u32 = { while let Some(x) = o { return x; } }; \
+                      func c(o : u32?) := { if let Some(x) = o { return x; } };";
+        let errors = check(source).unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            TypeErrorKind::MissingReturn {
+                name: "a".to_string(),
+                ty: U32,
+            }
+        );
+        assert_eq!(errors[0].span(), Span::new(0, 62));
+        assert_eq!(errors[0].code(), "E0314");
+        assert_eq!(
+            errors[0].to_string(),
+            "'a' doesn't return u32 on every path."
+        );
+
+        // Loops might not run, and functions without a return type return
+        // what their first `return` does
+        assert_eq!(
+            errors[1].kind,
+            TypeErrorKind::MissingReturn {
+                name: "b".to_string(),
+                ty: U32,
+            }
+        );
+        assert_eq!(
+            errors[2].kind,
+            TypeErrorKind::MissingReturn {
+                name: "c".to_string(),
+                ty: U32,
+            }
+        );
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_every_path_returns() {
+        let source =
+            "func a(o : u32?) : u32 = { if let Some(x) = o { return x; } else { return 0; } }; \
+                      func b(o : u32?) : u32 = { if let Some(x) = o { x } else { return 0; } }; \
+                      func c(o : u32?) : u32 = { { return 1; } }; \
+                      func d() := { return; }; func e() := { };";
+
+        assert!(check(source).is_ok());
+    }
+
+    #[test]
+    fn test_return_types() {
+        let errors =
+            check("func f() : () = { return 1; }; func g() : u8 = { return; };").unwrap_err();
+
+        assert_eq!(errors[0].kind, TypeErrorKind::ReturnValue { ty: U32 });
+        assert_eq!(errors[0].span(), Span::new(25, 26));
+        assert_eq!(errors[0].declared(), Some(Span::new(0, 30)));
+        assert_eq!(
+            errors[0].to_string(),
+            "Can't return u32 from a function that returns nothing."
+        );
+
+        assert_eq!(errors[1].kind, mismatch(U8, Ty::unit()));
+        assert_eq!(errors[1].span(), Span::new(49, 56));
+    }
+
+    #[test]
+    fn test_operators_on_names_from_other_files() {
+        let source = "import \"std.print\";\n\
+                      func f() := { let t := 1 + print; let mut u := 1; u += print; print < 2; \
+                      print == print; };";
+        let invalid = |op: &str| TypeErrorKind::InvalidOperand {
+            op: op.to_string(),
+            ty: Ty::Unknown,
+        };
+
+        assert_eq!(
+            errors(source),
+            [invalid("+"), invalid("+="), invalid("<"), invalid("==")]
+        );
+
+        // Closure parameters without types aren't known either, but are
+        // still run by the virtual machine
+        assert!(check("func f() := { let g := func(a) => a + 1; g(2); };").is_ok());
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(
+            errors("func f(a : u32, b : u8, s : str) := { a + b; -a; !a; s * s; a < s; s + s; };"),
+            [
+                mismatch(U32, U8),
+                TypeErrorKind::InvalidOperand {
+                    op: "-".to_string(),
+                    ty: U32,
+                },
+                mismatch(Ty::Bool, U32),
+                TypeErrorKind::InvalidOperand {
+                    op: "*".to_string(),
+                    ty: Ty::Str,
+                },
+                mismatch(U32, Ty::Str),
+            ]
+        );
+
+        assert_eq!(
+            errors("func f(a : u32, mut b : bool) := { b && a; b += 1; };"),
+            [
+                mismatch(Ty::Bool, U32),
+                TypeErrorKind::InvalidOperand {
+                    op: "+=".to_string(),
+                    ty: Ty::Bool,
+                },
+                mismatch(Ty::Bool, U32),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let source = "func f(s : str) := { let a := s + \"!\"; let b := s == a; \
+                      let c := s.len(); let d := s[c - 1]; };";
+
+        assert!(check(source).is_ok());
+        assert_eq!(
+            identifiers(source),
+            [
+                ("s".to_string(), Ty::Str),
+                ("s".to_string(), Ty::Str),
+                ("a".to_string(), Ty::Str),
+                ("s".to_string(), Ty::Str),
+                ("s".to_string(), Ty::Str),
+                ("c".to_string(), U32),
+            ]
+        );
+
+        assert_eq!(
+            errors("func f(mut s : str) := { s[0] = 1; s.len(1); s < s; s.chars(); };"),
+            [
+                TypeErrorKind::InvalidOperand {
+                    op: "[]=".to_string(),
+                    ty: Ty::Str,
+                },
+                TypeErrorKind::ArgumentCount {
+                    expected: 0,
+                    found: 1,
+                },
+                TypeErrorKind::InvalidOperand {
+                    op: "<".to_string(),
+                    ty: Ty::Str,
+                },
+                TypeErrorKind::NoMethod {
+                    ty: Ty::Str,
+                    method: "chars".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_calls() {
+        let source = "func add(a : u32, b : u32) : u32 = { a + b }; \
+                      func f() := { add(1); add(1, \"2\"); x := 3; x(); };";
+
+        assert_eq!(
+            errors(source),
+            [
+                TypeErrorKind::ArgumentCount {
+                    expected: 2,
+                    found: 1,
+                },
+                mismatch(U32, Ty::Str),
+                TypeErrorKind::NotCallable { ty: U32 },
+            ]
+        );
+
+        // Functions without a return type return what their body does, and
+        // can be used before they are declared
+        assert_eq!(
+            identifiers("func f() := { x : str = g(); x }; func g() := { \"g\" };")[0],
+            (
+                "g".to_string(),
+                Ty::Function {
+                    params: Vec::new(),
+                    ret: Box::new(Ty::Str),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_structs_and_enums() {
+        let source = "struct Point { x : u32, y : u32 } enum Shape { Dot(Point) } \
+                      func f(p : Point) := { \
+                      q := Point { x: \"1\", z: 2 }; p.z; Shape::Dot(1); Shape::Line(p); Point; };";
+
+        assert_eq!(
+            errors(source),
+            [
+                mismatch(U32, Ty::Str),
+                TypeErrorKind::NoField {
+                    ty: Ty::Struct("Point".to_string(), Vec::new()),
+                    field: "z".to_string(),
+                },
+                TypeErrorKind::MissingFields {
+                    name: "Point".to_string(),
+                    fields: vec!["y".to_string()],
+                },
+                TypeErrorKind::NoField {
+                    ty: Ty::Struct("Point".to_string(), Vec::new()),
+                    field: "z".to_string(),
+                },
+                mismatch(Ty::Struct("Point".to_string(), Vec::new()), U32),
+                TypeErrorKind::UnknownVariant {
+                    enum_name: "Shape".to_string(),
+                    variant: "Line".to_string(),
+                },
+                TypeErrorKind::NotAValue {
+                    name: "Point".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_patterns() {
+        let source = "enum Shape { Dot(u8), Pair(u32, str) } \
+                      func f(s : Shape, o : u8?) := { \
+                      if let Shape::Pair(a, b) = s { b; a; } \
+                      while let Some(c) = o { c; } };";
+
+        assert_eq!(
+            identifiers(source)[1..],
+            [
+                ("b".to_string(), Ty::Str),
+                ("a".to_string(), U32),
+                ("o".to_string(), Ty::Optional(Box::new(U8))),
+                ("c".to_string(), U8),
+            ]
+        );
+
+        assert_eq!(
+            errors("func f(a : u32) := { if let (b, c) = a { } x := if let _ = a { 1 } else { \"\" }; };"),
+            [
+                TypeErrorKind::PatternMismatch {
+                    pattern: "(b, c)".to_string(),
+                    ty: U32,
+                },
+                mismatch(U32, Ty::Str),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        let source = "enum Shape { Dot, Line(u32) } \
+                      func f(s : Shape, o : u8?) : u32 = { \
+                      a := match o { Some(c) => c, None => 0 }; \
+                      match s { Dot => { return 1; } Shape::Line(n) => n } };";
+
+        assert_eq!(
+            identifiers(source),
+            [
+                ("o".to_string(), Ty::Optional(Box::new(U8))),
+                ("c".to_string(), U8),
+                ("s".to_string(), Ty::Enum("Shape".to_string())),
+                ("n".to_string(), U32),
+            ]
+        );
+
+        // Arms without a value don't decide the type
+        assert_eq!(
+            errors("func f(b : bool) := { x := match b { true => 1, false => \"\" }; };"),
+            [mismatch(U32, Ty::Str)]
+        );
+        assert_eq!(
+            errors("func f(b : bool) := { match b { 1 => 1, _ => 2 } };"),
+            [TypeErrorKind::PatternMismatch {
+                pattern: "1".to_string(),
+                ty: Ty::Bool,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_match_exhaustiveness() {
+        let source = "enum Shape { Dot, Line(u32), Pair(bool, bool) } \
+                      func f(s : Shape) := { match s { Shape::Pair(true, _) => {} } };";
+        let errors = check(source).unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            TypeErrorKind::NonExhaustive {
+                patterns: vec![
+                    "Shape::Dot".to_string(),
+                    "Shape::Line(_)".to_string(),
+                    "Shape::Pair(false, _)".to_string(),
+                ]
+            }
+        );
+        assert_eq!(errors[0].code(), "E0316");
+        assert_eq!(
+            errors[0].span(),
+            Span::new(source.find("match").unwrap(), source.len() - 3)
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Missing patterns in the match: Shape::Dot, Shape::Line(_), Shape::Pair(false, _)."
+        );
+
+        let missing = |source: &str| match &self::errors(source)[0] {
+            TypeErrorKind::NonExhaustive { patterns } => patterns.clone(),
+            error => panic!("Expected a missing pattern, found {:?}", error),
+        };
+
+        assert_eq!(
+            missing("func f(o : bool?) := { match o { Some(true) => {} } };"),
+            ["None", "Some(false)"]
+        );
+        assert_eq!(
+            missing("func f(t : (bool, u8)) := { match t { (true, _) => {} (_, 1) => {} } };"),
+            ["(false, _)"]
+        );
+        // Integers and strings can't all be listed
+        assert_eq!(missing("func f(a : u8) := { match a { 1 => {} } };"), ["_"]);
+
+        let source = "enum E { A, B } func f(e : E, b : bool, n : u8) := { \
+                      match e { A => {} _ => {} }; \
+                      match b { true => {} false => {} }; \
+                      match n { 1 => {} x => {} }; \
+                      match (b, b) { (true, _) => {} (_, true) => {} (false, false) => {} }; };";
+
+        assert!(check(source).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_unreachable_arms() {
+        let source = "func f(o : bool?, n : u8) := { \
+                      match o { _ => {} None => {} } \
+                      match o { None => {} Some(true) => {} Some(false) => {} Some(_) => {} } \
+                      match n { 1 => {} 2 => {} 1 => {} _ => {} } };";
+        let warnings = check(source).unwrap().warnings;
+
+        let arms: Vec<_> = warnings
+            .iter()
+            .map(|warning| &source[warning.span().start..warning.span().end])
+            .collect();
+
+        assert_eq!(arms, ["None => {}", "Some(_) => {}", "1 => {}"]);
+        assert_eq!(warnings[0].kind, TypeErrorKind::UnreachableArm);
+        assert_eq!(warnings[0].code(), "W0301");
+    }
+
+    #[test]
+    fn test_unknown_types() {
+        let errors = check("struct A { b : B } let c : Vec<u8> = 1;").unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            TypeErrorKind::UnknownType {
+                name: "B".to_string()
+            }
+        );
+        assert_eq!(errors[1].to_string(), "Can't find the type 'Vec'.");
+        assert_eq!(errors.len(), 2);
+
+        // Imported types, and values of them, could be anything
+        assert!(check("import math::{Matrix}; func f(m : Matrix) : u8 = { m.rows };").is_ok());
+    }
+
+    #[test]
+    fn test_generic_functions() {
+        let source = "func max<T>(a : T, b : T) : T = { if let true = a > b { a } else { b } }; \
+                      func f(x : u8) := { let a := max(x, 1); let b := max(2, 3); \
+                      let c : i64 = max(4, 5); let g : func(u8, u8) -> u8 = max; a; b; c; g; };";
+        let types = check(source).unwrap();
+
+        // The body is checked once for each set of types it is used with
+        let instances: Vec<_> = types
+            .instances
+            .iter()
+            .map(|instance| (instance.args.clone(), instance.ret.clone()))
+            .collect();
+
+        assert_eq!(
+            instances,
+            [
+                (vec![U8], U8),
+                (vec![U32], U32),
+                (vec![Ty::Int(IntTy::I64)], Ty::Int(IntTy::I64)),
+            ]
+        );
+        assert_eq!(types.instantiated.len(), 4);
+
+        let uses = identifiers(source);
+
+        assert!(uses.contains(&("a".to_string(), U8)));
+        assert!(uses.contains(&("b".to_string(), U32)));
+        assert!(uses.contains(&(
+            "max".to_string(),
+            Ty::Function {
+                params: vec![U8, U8],
+                ret: Box::new(U8),
+            }
+        )));
+    }
+
+    #[test]
+    fn test_generic_structs() {
+        let source = "struct Pair<A, B> { first : A, second : B } \
+                      func swap<A, B>(p : Pair<A, B>) : Pair<B, A> = { \
+                      Pair { first: p.second, second: p.first } }; \
+                      func f() : str = { let p := swap(Pair { first: 1, second: \"a\" }); \
+                      let q : Pair<u8, bool> = Pair { first: 2, second: true }; p.first };";
+        let types = check(source).unwrap();
+
+        let pair = |a: Ty, b: Ty| Ty::Struct("Pair".to_string(), vec![a, b]);
+
+        assert_eq!(types.instances[0].args, [U32, Ty::Str]);
+        assert_eq!(types.instances[0].ret, pair(Ty::Str, U32));
+        assert_eq!(pair(U8, Ty::Bool).to_string(), "Pair<u8, bool>");
+        assert!(pair(Ty::Unknown, U8).matches(&pair(U32, U8)));
+        assert!(!pair(U8, U8).matches(&pair(U32, U8)));
+
+        assert_eq!(
+            errors(
+                "struct Pair<A, B> { first : A, second : B } \
+                    let p : Pair<u8, str> = Pair { first: \"a\", second: \"b\" }; \
+                    let q : Pair<u8> = p; let r : Pair = p;"
+            ),
+            [
+                TypeErrorKind::TypeArgumentCount {
+                    name: "Pair".to_string(),
+                    expected: 2,
+                    found: 1,
+                },
+                TypeErrorKind::TypeArgumentCount {
+                    name: "Pair".to_string(),
+                    expected: 2,
+                    found: 0,
+                },
+                mismatch(U8, Ty::Str),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generic_errors() {
+        // Generic bodies are only checked with the types they are used with,
+        // and each mistake is only reported once
+        let errors = check(
+            "func max<T>(a : T, b : T) : T = { if let true = a > b { a } else { b } }; \
+             func unused<T>(a : T) := { a.field; }; \
+             func f() := { max(\"a\", \"b\"); max(\"c\", \"d\"); max(1, true); };",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors.iter().map(|error| &error.kind).collect::<Vec<_>>(),
+            [
+                &TypeErrorKind::InvalidOperand {
+                    op: ">".to_string(),
+                    ty: Ty::Str,
+                },
+                &mismatch(Ty::Bool, U32),
+                &TypeErrorKind::InvalidOperand {
+                    op: ">".to_string(),
+                    ty: Ty::Bool,
+                },
+            ]
+        );
+
+        let errors = check(
+            "func make<T>() : u32 = { 1 }; func grow<T>(a : T) : u32 = { grow((a, a)) }; \
+             func f() := { make(); grow(1); };",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "Can't infer the type of 'T' for 'make', so it needs to be written."
+        );
+        assert_eq!(
+            errors[1].kind,
+            TypeErrorKind::InstantiationLimit {
+                name: "grow".to_string()
+            }
+        );
+        assert_eq!(errors[1].code(), "E0320");
+    }
+
+    #[test]
+    fn test_traits() {
+        let source = "trait Shape { func area(self) : u32; func scaled(self, by : u32) : Self; } \
+                      struct Square { side : u32 } struct Circle { radius : u32 } \
+                      impl Shape for Square { func area(self) : u32 = { self.side * self.side }; \
+                      func scaled(self, by : u32) : Square = { Square { side: self.side * by } }; } \
+                      impl Shape for Circle { func area(self) := { 3 * self.radius }; \
+                      func scaled(self, by : u32) : Self = { Circle { radius: self.radius * by } }; } \
+                      func total<T : Shape>(a : T) : u32 = { a.scaled(2).area() }; \
+                      func f() := { total(Square { side: 1 }); total(Circle { radius: 1 }); };";
+        let types = check(source).unwrap();
+
+        let method = |item, method| MethodId { item, method };
+        let square = Ty::Struct("Square".to_string(), Vec::new());
+
+        // A method written with := returns what the trait says it does
+        assert_eq!(types.methods[&method(4, 0)], U32);
+        assert_eq!(types.methods[&method(3, 1)], square);
+
+        // Each instance of a generic function calls the methods of its types
+        let calls = |instance: usize| {
+            let mut calls: Vec<_> = types.instances[instance].calls.values().copied().collect();
+            calls.sort_by_key(|call| (call.item, call.method));
+            calls
+        };
+
+        assert_eq!(types.instances[0].args, [square]);
+        assert_eq!(calls(0), [method(3, 0), method(3, 1)]);
+        assert_eq!(calls(1), [method(4, 0), method(4, 1)]);
+    }
+
+    #[test]
+    fn test_trait_errors() {
+        let errors = check(
+            "trait Shape { func area(self) : u32; func name(self) : str; } \
+             trait Named { func name(self) : str; } struct Square { side : u32 } \
+             impl Shape for Square { func area(self) : bool = { true }; func extra(self) := {}; } \
+             impl Shape for Square { func area(self) : u32 = { 1 }; func name(self) : str = { \"a\" }; } \
+             impl Named for Square { func name(self) : str = { \"b\" }; } \
+             impl Missing for u32 {} \
+             func total<T : Shape>(a : T) : u32 = { a.area() }; \
+             func f(s : Square) := { total(3); s.name(); s.nothing(); let x := Shape; };",
+        )
+        .unwrap_err();
+
+        let square = Ty::Struct("Square".to_string(), Vec::new());
+        let area = |ret: Ty| Ty::Function {
+            params: vec![square.clone()],
+            ret: Box::new(ret),
+        };
+
+        assert_eq!(
+            errors.iter().map(|error| &error.kind).collect::<Vec<_>>(),
+            [
+                &TypeErrorKind::MissingMethods {
+                    trait_name: "Shape".to_string(),
+                    ty: square.clone(),
+                    methods: vec!["name".to_string()],
+                },
+                &TypeErrorKind::MethodSignature {
+                    trait_name: "Shape".to_string(),
+                    method: "area".to_string(),
+                    expected: area(U32),
+                    found: area(Ty::Bool),
+                },
+                &TypeErrorKind::NotInTrait {
+                    trait_name: "Shape".to_string(),
+                    method: "extra".to_string(),
+                },
+                &TypeErrorKind::DuplicateImpl {
+                    trait_name: "Shape".to_string(),
+                    ty: square.clone(),
+                },
+                &TypeErrorKind::UnknownTrait {
+                    name: "Missing".to_string(),
+                },
+                &TypeErrorKind::UnsatisfiedBound {
+                    ty: U32,
+                    trait_name: "Shape".to_string(),
+                },
+                &TypeErrorKind::AmbiguousMethod {
+                    ty: square.clone(),
+                    method: "name".to_string(),
+                    traits: vec!["Shape".to_string(), "Named".to_string()],
+                },
+                &TypeErrorKind::NoMethod {
+                    ty: square,
+                    method: "nothing".to_string(),
+                },
+                &TypeErrorKind::NotAValue {
+                    name: "Shape".to_string(),
+                },
+            ]
+        );
+        assert_eq!(errors[0].code(), "E0323");
+        assert_eq!(errors[5].to_string(), "u32 doesn't implement 'Shape'.");
+    }
+
+    #[test]
+    fn test_operator_overloading() {
+        let source = "trait Num { func add(self, other : Self) : Self; func neg(self) : Self; \
+                      func lt(self, other : Self) : bool; func index(self, i : u8) : u32; } \
+                      struct V { x : u32 } impl Num for V { \
+                      func add(self, other : V) : V = { V { x: self.x + other.x } }; \
+                      func neg(self) : V = { self }; func lt(self, other : V) : bool = { true }; \
+                      func index(self, i : u8) : u32 = { self.x }; } \
+                      func f(v : V) := { let mut a := v + v; a += -v; let b := a < v; \
+                      let c := a[1]; let d := a == v; a; b; c; d; };";
+        let types = check(source).unwrap();
+
+        let v = Ty::Struct("V".to_string(), Vec::new());
+
+        // Each operator calls its method, but `==` compares the fields of
+        // values without an `eq`
+        let mut calls: Vec<_> = types.calls.values().map(|call| call.method).collect();
+        calls.sort();
+
+        assert_eq!(calls, [0, 0, 1, 2, 3]);
+
+        let uses = identifiers(source);
+
+        assert!(uses.contains(&("a".to_string(), v)));
+        assert!(uses.contains(&("b".to_string(), Ty::Bool)));
+        assert!(uses.contains(&("c".to_string(), U32)));
+        assert!(uses.contains(&("d".to_string(), Ty::Bool)));
+
+        let errors = check(
+            "trait Eq { func eq(self, other : Self) : u32; func neg(self, by : u32) : Self; \
+             func index(self, i : u32) : u32; } \
+             struct V { x : u32 } impl Eq for V { func eq(self, other : V) : u32 = { 1 }; \
+             func neg(self, by : u32) : V = { self }; func index(self, i : u32) : u32 = { i }; } \
+             func f(v : V) := { v == v; -v; v - v; let mut w := v; w[0] = 1; w += v; !v; };",
+        )
+        .unwrap_err();
+
+        let invalid = |op: &str| TypeErrorKind::InvalidOperand {
+            op: op.to_string(),
+            ty: Ty::Struct("V".to_string(), Vec::new()),
+        };
+
+        assert_eq!(
+            errors.iter().map(|error| &error.kind).collect::<Vec<_>>(),
+            [
+                &mismatch(Ty::Bool, U32),
+                &TypeErrorKind::OperatorMethod {
+                    op: "-".to_string(),
+                    method: "neg".to_string(),
+                    expected: 1,
+                    found: 2,
+                },
+                &invalid("-"),
+                &invalid("[]="),
+                &invalid("+="),
+                &invalid("!"),
+            ]
+        );
+        assert_eq!(errors[1].code(), "E0329");
+        assert_eq!(
+            errors[1].to_string(),
+            "'neg' has to take 1 parameters to overload '-', found 2."
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let ty = Ty::Function {
+            params: vec![Ty::Tuple(vec![U8]), Ty::unit()],
+            ret: Box::new(Ty::Optional(Box::new(Ty::Array {
+                element: Box::new(Ty::Str),
+                length: Some(4),
+            }))),
+        };
+
+        assert_eq!(ty.to_string(), "func((u8,), ()) -> [str; 4]?");
+        assert!(Ty::Unknown.matches(&ty));
+        assert!(!ty.matches(&Ty::Str));
+    }
+
+    /// The problems with the entry point of a program that type checks.
+    fn entry_point(source: &str) -> Vec<TypeErrorKind> {
+        let parsed = parse(source);
+        let resolution = Resolver::resolve(&parsed).unwrap();
+        let types = TypeChecker::check(&parsed, &resolution).unwrap();
+
+        typeck::check_entry_point(&parsed, &types)
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|error| error.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_entry_point() {
+        let invalid = |problem: &str| TypeErrorKind::InvalidMain {
+            problem: problem.to_string(),
+        };
+
+        assert!(entry_point("func main() := {};").is_empty());
+        assert!(entry_point("func main() : i32 = { 0 as i32 };").is_empty());
+        assert!(entry_point("func main() : u8 = { 1 as u8 };").is_empty());
+        assert!(entry_point("func main() := { while true {} };").is_empty());
+
+        assert_eq!(entry_point("func f() := {};"), vec![TypeErrorKind::NoMain]);
+        assert_eq!(
+            entry_point("const main : u32 = 1;"),
+            vec![invalid("has to be a function")]
+        );
+        assert_eq!(
+            entry_point("func main(a : u32, b : u32) : str = { \"a\" };"),
+            vec![
+                invalid("can't take parameters, found 2"),
+                invalid("has to return nothing or an integer, found str"),
+            ]
+        );
+        assert_eq!(
+            entry_point("func main<T>() := {};"),
+            vec![invalid("can't have type parameters")]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use penguin::lexer::Lexer;
+use penguin::parser::ast::{
+    Arena, Block, ClosureParam, ExprId, ExprKind, Item, Let, Param, Stmt, Type,
+};
+use penguin::parser::visit::{self, VisitMut, Visitor};
+use penguin::parser::{ParseResult, Parser};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> ParseResult {
+        let tokens = Lexer::lex_from_string(source).lex().unwrap();
+
+        Parser::parse(&tokens).unwrap()
+    }
+
+    /// Counts every node it visits, by kind.
+    #[derive(Default)]
+    struct Counter {
+        items: usize,
+        stmts: usize,
+        exprs: usize,
+        types: usize,
+        blocks: usize,
+        lets: usize,
+        params: usize,
+    }
+
+    impl Visitor for Counter {
+        fn visit_item(&mut self, arena: &Arena, item: &Item) {
+            self.items += 1;
+            visit::walk_item(self, arena, item);
+        }
+
+        fn visit_stmt(&mut self, arena: &Arena, stmt: &Stmt) {
+            self.stmts += 1;
+            visit::walk_stmt(self, arena, stmt);
+        }
+
+        fn visit_expr(&mut self, arena: &Arena, id: ExprId) {
+            self.exprs += 1;
+            visit::walk_expr(self, arena, id);
+        }
+
+        fn visit_type(&mut self, ty: &Type) {
+            self.types += 1;
+            visit::walk_type(self, ty);
+        }
+
+        fn visit_block(&mut self, arena: &Arena, block: &Block) {
+            self.blocks += 1;
+            visit::walk_block(self, arena, block);
+        }
+
+        fn visit_let(&mut self, arena: &Arena, decl: &Let) {
+            self.lets += 1;
+            visit::walk_let(self, arena, decl);
+        }
+
+        fn visit_param(&mut self, param: &Param) {
+            self.params += 1;
+            visit::walk_param(self, param);
+        }
+
+        fn visit_closure_param(&mut self, param: &ClosureParam) {
+            self.params += 1;
+            visit::walk_closure_param(self, param);
+        }
+    }
+
+    #[test]
+    fn test_visitor_sees_every_node() {
+        let program = parse(
+            "
+            import math;
+            const MAX : [u32; 2]? = 1;
+            struct Point { x : i32, y : (i32, func(u8) -> u8) }
+            enum Shape { Empty, Circle(Point, List<u32>) }
+            func main(a : u32) := {
+                let b := (1, -a);
+                while b.first < MAX { b = f(b)[0]; break; }
+                Shape::Circle(Point { x: 1, y: 2 }, list.map(func(x : u8, y) => { x }))
+            };
+            ",
+        );
+
+        let mut counter = Counter::default();
+
+        counter.visit_program(&program);
+
+        // Every expression in the arena is visited once
+        assert_eq!(counter.exprs, program.arena.len());
+
+        assert_eq!(counter.items, 5);
+        assert_eq!(counter.lets, 2);
+        assert_eq!(counter.stmts, 2 + 2);
+        assert_eq!(counter.blocks, 3);
+        assert_eq!(counter.params, 3);
+
+        // [u32; 2]? and its parts, the field types, the payload types and
+        // the parameter types
+        assert_eq!(counter.types, 3 + 6 + 3 + 1 + 1);
+
+        // Every expression, including the literals, identifiers and the
+        // closure's body
+        assert_eq!(counter.exprs, 1 + 4 + 4 + 7 + 9);
+    }
+
+    /// Doubles every number in the program.
+    struct Doubler;
+
+    impl VisitMut for Doubler {
+        fn visit_expr_mut(&mut self, arena: &mut Arena, id: ExprId) {
+            if let ExprKind::Number(number) = &mut arena[id].kind {
+                *number *= 2;
+            }
+
+            visit::walk_expr_mut(self, arena, id);
+        }
+    }
+
+    #[test]
+    fn test_visit_mut_rewrites_the_tree() {
+        let mut program = parse("let a := 1 + f(2, { 3 }); func g() := { return 4; };");
+
+        Doubler.visit_program_mut(&mut program);
+
+        assert_eq!(
+            program,
+            parse("let a := 2 + f(4, { 6 }); func g() := { return 8; };")
+        );
+    }
+
+    /// Collects the names of the variables a program uses, in order.
+    #[derive(Default)]
+    struct Names(Vec<String>);
+
+    impl Visitor for Names {
+        fn visit_expr(&mut self, arena: &Arena, id: ExprId) {
+            if let ExprKind::Identifier(name) = &arena[id].kind {
+                self.0.push(name.clone());
+            }
+
+            visit::walk_expr(self, arena, id);
+        }
+    }
+
+    #[test]
+    fn test_visit_order() {
+        let program = parse("func f() := { a = b + c(d, e.f); g };");
+        let mut names = Names::default();
+
+        names.visit_program(&program);
+
+        assert_eq!(names.0, ["a", "b", "c", "d", "e", "g"]);
+    }
+}